@@ -0,0 +1,76 @@
+//! Run an agent eval suite and print its scores.
+//!
+//! ```sh
+//! cargo run -p insight-core --features test-util --example agent_eval -- \
+//!     crates/insight-core/evals/lease.yaml
+//! cargo run -p insight-core --features test-util --example agent_eval -- \
+//!     crates/insight-core/evals/lease.yaml openai gpt-4o
+//! ```
+//!
+//! Without a provider the suite's `mock_responses` are replayed. Remote
+//! providers read their key from `<FAMILY>_API_KEY`, e.g. `OPENAI_API_KEY`.
+//! The full report is printed as JSON after the summary.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use insight_core::agent::eval::{run_suite, EvalSuite};
+use insight_core::{
+    AnthropicChatProvider, AppState, ChatProvider, Config, GroqChatProvider, MistralChatProvider,
+    OpenAIChatProvider, OpenRouterChatProvider,
+};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(suite_path) = args.first() else {
+        bail!("Usage: agent_eval <suite.yaml> [openai|anthropic|openrouter|mistral|groq <model>]");
+    };
+    let suite = EvalSuite::load(&PathBuf::from(suite_path))?;
+
+    let provider: Option<Box<dyn ChatProvider>> = match (args.get(1), args.get(2)) {
+        (None, _) => None,
+        (Some(family), Some(model)) if family == "openai" => {
+            let key = std::env::var("OPENAI_API_KEY").context("OPENAI_API_KEY is not set")?;
+            Some(Box::new(OpenAIChatProvider::new(&key, model)))
+        }
+        (Some(family), Some(model)) if family == "anthropic" => {
+            let key = std::env::var("ANTHROPIC_API_KEY").context("ANTHROPIC_API_KEY is not set")?;
+            Some(Box::new(AnthropicChatProvider::new(&key, model)))
+        }
+        (Some(family), Some(model)) if family == "openrouter" => {
+            let key =
+                std::env::var("OPENROUTER_API_KEY").context("OPENROUTER_API_KEY is not set")?;
+            Some(Box::new(OpenRouterChatProvider::new(&key, model, None)))
+        }
+        (Some(family), Some(model)) if family == "mistral" => {
+            let key = std::env::var("MISTRAL_API_KEY").context("MISTRAL_API_KEY is not set")?;
+            Some(Box::new(MistralChatProvider::new(&key, model, None)))
+        }
+        (Some(family), Some(model)) if family == "groq" => {
+            let key = std::env::var("GROQ_API_KEY").context("GROQ_API_KEY is not set")?;
+            Some(Box::new(GroqChatProvider::new(&key, model, None)))
+        }
+        _ => bail!(
+            "Provider must be `openai`, `anthropic`, `openrouter`, `mistral` or `groq` followed by a model"
+        ),
+    };
+
+    // A throwaway data directory, so the fixture never mixes with real
+    // collections.
+    let temp_dir = tempfile::tempdir()?;
+    let config = Config {
+        data_dir: temp_dir.path().to_path_buf(),
+        iroh_dir: temp_dir.path().join("iroh"),
+        search_dir: temp_dir.path().join("search"),
+        settings_file: temp_dir.path().join("settings.json"),
+        conversations_dir: temp_dir.path().join("conversations"),
+    };
+    config.ensure_dirs()?;
+    let state = AppState::new(config).await?;
+
+    let report = run_suite(&state, &suite, provider.as_deref()).await?;
+    println!("{}", report.summary());
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
@@ -0,0 +1,350 @@
+//! Passage-level diff for the `compare_documents` tool.
+//!
+//! Both documents are cut into passages: paragraphs, with long paragraphs
+//! split into windows of [`WINDOW_WORDS`] words. The passages are aligned
+//! by a longest common subsequence over their normalized text, so reflowed
+//! lines and moved page breaks don't show up as changes. Between two
+//! aligned passages, a removed and an added passage that share most of
+//! their words are reported as one changed passage, with the words that
+//! went and came; the rest are plain removals and additions.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+/// Longest passage, in words, before a paragraph is split into windows.
+const WINDOW_WORDS: usize = 120;
+
+/// Longest passage text kept in a change, in characters.
+const MAX_PASSAGE_CHARS: usize = 600;
+
+/// Share of words two passages must have in common to be paired as one
+/// changed passage.
+const MIN_SIMILARITY: f32 = 0.5;
+
+/// Words listed per side of a changed passage.
+const MAX_CHANGED_WORDS: usize = 20;
+
+/// Most cells of the alignment table. Larger differences fall back to
+/// greedy in-order matching, which can report a moved block as removed
+/// and added but never misses a change.
+const MAX_ALIGN_CELLS: usize = 4_000_000;
+
+/// A paragraph or window of a document, anchored to the page it starts on.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Passage {
+    pub page: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One difference. `first` is the passage in the first document, `second`
+/// in the second; an addition has only `second`, a removal only `first`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first: Option<Passage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub second: Option<Passage>,
+    /// For a changed passage, words only the first version has.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub words_removed: Vec<String>,
+    /// For a changed passage, words only the second version has.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub words_added: Vec<String>,
+}
+
+/// The differences between two documents, in document order.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub unchanged: usize,
+    pub changes: Vec<Change>,
+}
+
+/// Cut `pages` (page 1 first) into passages. Blank pages add nothing.
+pub fn passages(pages: &[&str]) -> Vec<Passage> {
+    let mut passages = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        for paragraph in page.split("\n\n") {
+            let words: Vec<&str> = paragraph.split_whitespace().collect();
+            for window in words.chunks(WINDOW_WORDS) {
+                passages.push(Passage {
+                    page: i + 1,
+                    text: window.join(" "),
+                });
+            }
+        }
+    }
+    passages
+}
+
+/// Compare the passages of two documents.
+pub fn compare(first: &[Passage], second: &[Passage]) -> Comparison {
+    let first_keys: Vec<String> = first.iter().map(|p| normalize(&p.text)).collect();
+    let second_keys: Vec<String> = second.iter().map(|p| normalize(&p.text)).collect();
+    let matches = align(&first_keys, &second_keys);
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    // A sentinel match past both ends flushes the trailing gap.
+    for (mi, mj) in matches
+        .iter()
+        .copied()
+        .chain(std::iter::once((first.len(), second.len())))
+    {
+        diff_gap(&first[i..mi], &second[j..mj], &mut changes);
+        i = mi + 1;
+        j = mj + 1;
+    }
+
+    Comparison {
+        unchanged: matches.len(),
+        changes,
+    }
+}
+
+/// Lowercased words, so spacing and case differences don't count.
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Index pairs of passages that are the same in both documents, in order.
+fn align(first: &[String], second: &[String]) -> Vec<(usize, usize)> {
+    let prefix = first.iter().zip(second).take_while(|(a, b)| a == b).count();
+    let suffix = first[prefix..]
+        .iter()
+        .rev()
+        .zip(second[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let first_mid = &first[prefix..first.len() - suffix];
+    let second_mid = &second[prefix..second.len() - suffix];
+
+    let mut matches: Vec<(usize, usize)> = (0..prefix).map(|k| (k, k)).collect();
+    let middle = if (first_mid.len() + 1) * (second_mid.len() + 1) <= MAX_ALIGN_CELLS {
+        lcs(first_mid, second_mid)
+    } else {
+        greedy(first_mid, second_mid)
+    };
+    matches.extend(middle.into_iter().map(|(a, b)| (a + prefix, b + prefix)));
+    matches.extend((0..suffix).map(|k| (first.len() - suffix + k, second.len() - suffix + k)));
+    matches
+}
+
+/// Longest common subsequence by dynamic programming.
+fn lcs(first: &[String], second: &[String]) -> Vec<(usize, usize)> {
+    let width = second.len() + 1;
+    // lengths[a * width + b]: LCS of first[a..] and second[b..].
+    let mut lengths = vec![0u32; (first.len() + 1) * width];
+    for a in (0..first.len()).rev() {
+        for b in (0..second.len()).rev() {
+            lengths[a * width + b] = if first[a] == second[b] {
+                lengths[(a + 1) * width + b + 1] + 1
+            } else {
+                lengths[(a + 1) * width + b].max(lengths[a * width + b + 1])
+            };
+        }
+    }
+
+    let mut matches = Vec::new();
+    let (mut a, mut b) = (0, 0);
+    while a < first.len() && b < second.len() {
+        if first[a] == second[b] {
+            matches.push((a, b));
+            a += 1;
+            b += 1;
+        } else if lengths[(a + 1) * width + b] >= lengths[a * width + b + 1] {
+            a += 1;
+        } else {
+            b += 1;
+        }
+    }
+    matches
+}
+
+/// Match each passage of `first` to the next identical passage of
+/// `second`, keeping order.
+fn greedy(first: &[String], second: &[String]) -> Vec<(usize, usize)> {
+    let mut positions: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (b, key) in second.iter().enumerate().rev() {
+        positions.entry(key).or_default().push(b);
+    }
+
+    let mut matches = Vec::new();
+    let mut next = 0;
+    for (a, key) in first.iter().enumerate() {
+        let Some(found) = positions.get_mut(key.as_str()) else {
+            continue;
+        };
+        // Positions are stored last-first; drop those already passed.
+        while found.last().is_some_and(|&b| b < next) {
+            found.pop();
+        }
+        if let Some(b) = found.pop() {
+            matches.push((a, b));
+            next = b + 1;
+        }
+    }
+    matches
+}
+
+/// Report a run of removed and added passages between two aligned ones,
+/// pairing similar passages as changes.
+fn diff_gap(removed: &[Passage], added: &[Passage], changes: &mut Vec<Change>) {
+    let mut paired = vec![false; added.len()];
+    for old in removed {
+        let best = added
+            .iter()
+            .enumerate()
+            .filter(|(k, _)| !paired[*k])
+            .map(|(k, new)| (k, similarity(&old.text, &new.text)))
+            .filter(|&(_, score)| score >= MIN_SIMILARITY)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((k, _)) => {
+                paired[k] = true;
+                let new = &added[k];
+                changes.push(Change {
+                    kind: ChangeKind::Changed,
+                    words_removed: word_difference(&old.text, &new.text),
+                    words_added: word_difference(&new.text, &old.text),
+                    first: Some(clipped(old)),
+                    second: Some(clipped(new)),
+                });
+            }
+            None => changes.push(Change {
+                kind: ChangeKind::Removed,
+                first: Some(clipped(old)),
+                second: None,
+                words_removed: Vec::new(),
+                words_added: Vec::new(),
+            }),
+        }
+    }
+    for (new, _) in added.iter().zip(&paired).filter(|(_, &p)| !p) {
+        changes.push(Change {
+            kind: ChangeKind::Added,
+            first: None,
+            second: Some(clipped(new)),
+            words_removed: Vec::new(),
+            words_added: Vec::new(),
+        });
+    }
+}
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split_whitespace().map(|w| {
+        w.trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase()
+    })
+}
+
+/// Shared distinct words over all distinct words (Jaccard).
+fn similarity(a: &str, b: &str) -> f32 {
+    let a: HashSet<String> = words(a).collect();
+    let b: HashSet<String> = words(b).collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f32 / union as f32
+}
+
+/// Words of `a` that `b` lacks, in order of appearance, without repeats.
+fn word_difference(a: &str, b: &str) -> Vec<String> {
+    let present: HashSet<String> = words(b).collect();
+    let mut seen = HashSet::new();
+    words(a)
+        .filter(|w| !w.is_empty() && !present.contains(w) && seen.insert(w.clone()))
+        .take(MAX_CHANGED_WORDS)
+        .collect()
+}
+
+fn clipped(passage: &Passage) -> Passage {
+    let text = match passage.text.char_indices().nth(MAX_PASSAGE_CHARS) {
+        Some((cut, _)) => format!("{}…", &passage.text[..cut]),
+        None => passage.text.clone(),
+    };
+    Passage {
+        page: passage.page,
+        text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(comparison: &Comparison) -> Vec<ChangeKind> {
+        comparison.changes.iter().map(|c| c.kind).collect()
+    }
+
+    #[test]
+    fn test_passages_split_paragraphs_and_windows() {
+        let long = vec!["word"; WINDOW_WORDS + 5].join(" ");
+        let page_two = format!("Intro line\nwrapped.\n\n{}", long);
+        let passages = passages(&["First page.", "", &page_two]);
+        assert_eq!(passages.len(), 4);
+        assert_eq!(passages[0].page, 1);
+        assert_eq!(passages[1].text, "Intro line wrapped.");
+        assert_eq!(passages[1].page, 3);
+        assert_eq!(passages[3].text.split(' ').count(), 5);
+    }
+
+    #[test]
+    fn test_compare_reports_added_removed_changed() {
+        let first = passages(&[
+            "1. Parties. Acme Ltd and Beta LLC.\n\n2. Term. This agreement runs for two years.",
+            "3. Fee. Beta pays Acme $4,000 per month.\n\n4. Audit. Acme may audit Beta yearly.",
+        ]);
+        let second = passages(&[
+            "1. Parties. Acme Ltd and Beta LLC.\n\n2. Term. This agreement runs for two years.\n\n\
+             3. Fee. Beta pays Acme $5,500 per month.",
+            "5. Exclusivity. Beta may not work with competitors of Acme.",
+        ]);
+
+        let comparison = compare(&first, &second);
+        assert_eq!(comparison.unchanged, 2);
+        assert_eq!(
+            kinds(&comparison),
+            vec![ChangeKind::Changed, ChangeKind::Removed, ChangeKind::Added]
+        );
+
+        let fee = &comparison.changes[0];
+        assert_eq!(fee.first.as_ref().unwrap().page, 2);
+        assert_eq!(fee.second.as_ref().unwrap().page, 1);
+        assert_eq!(fee.words_removed, vec!["4,000"]);
+        assert_eq!(fee.words_added, vec!["5,500"]);
+        assert_eq!(comparison.changes[2].second.as_ref().unwrap().page, 2);
+    }
+
+    #[test]
+    fn test_reflowed_text_is_unchanged() {
+        let first = passages(&[
+            "The tenant pays rent\non the first day.",
+            "Notices in writing.",
+        ]);
+        let second = passages(&["The Tenant pays rent on the\nfirst day.\n\nNotices in writing."]);
+        let comparison = compare(&first, &second);
+        assert_eq!(comparison.unchanged, 2);
+        assert!(comparison.changes.is_empty());
+    }
+
+    #[test]
+    fn test_greedy_alignment_keeps_order() {
+        let keys = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let matches = greedy(&keys(&["a", "b", "c", "d"]), &keys(&["b", "x", "a", "d"]));
+        assert_eq!(matches, vec![(0, 2), (3, 3)]);
+    }
+}
@@ -0,0 +1,378 @@
+//! Keeping the transcript inside the model's context window.
+//!
+//! The stored conversation always keeps every message; only the copy sent
+//! to the model is trimmed. When the transcript outgrows its budget, old
+//! tool results are shortened first, since they are large and can be
+//! fetched again. If that isn't enough, the oldest turns are folded into a
+//! rolling summary kept on the conversation, so an investigation can go on
+//! indefinitely.
+
+use anyhow::Result;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use super::summarize::{summarize_pages, CHARS_PER_TOKEN};
+use super::{ContentBlock, Conversation, Message, MessageRole};
+use crate::prompts::PromptLibrary;
+use crate::provider::ChatProvider;
+
+/// Share of the context window, in percent, the transcript may fill. The
+/// rest is left for tool definitions and the reply.
+const TRANSCRIPT_SHARE_PERCENT: usize = 70;
+
+/// Most recent user turns whose tool results are kept whole and that are
+/// never folded into the summary.
+const KEEP_RECENT_TURNS: usize = 2;
+
+/// Tokens counted for a page image. Providers charge by pixel count; a
+/// page rendered for viewing comes to about this much.
+const PAGE_IMAGE_TOKENS: usize = 1600;
+
+/// Tool results shorter than this aren't worth shortening.
+const MIN_ELIDED_CHARS: usize = 400;
+
+/// Longest tool result passed to the summarizer, in bytes.
+const MAX_SUMMARIZED_RESULT: usize = 2000;
+
+/// Rough token count of `messages`, erring high.
+pub fn estimate_tokens(messages: &[Message]) -> usize {
+    let chars: usize = messages
+        .iter()
+        .flat_map(|m| &m.content)
+        .map(|block| match block {
+            ContentBlock::Text { text } => text.len(),
+            ContentBlock::Thinking { thinking, .. } => thinking.len(),
+            ContentBlock::ToolUse {
+                name, arguments, ..
+            } => name.len() + arguments.to_string().len(),
+            ContentBlock::ToolResult { content, .. } => content.len(),
+            ContentBlock::PageImage { .. } => PAGE_IMAGE_TOKENS * CHARS_PER_TOKEN,
+            ContentBlock::Citation { .. } | ContentBlock::SourceFile { .. } => 0,
+        })
+        .sum();
+    chars.div_ceil(CHARS_PER_TOKEN)
+}
+
+/// The messages to send for the next completion, trimmed to fit
+/// `provider`'s context window. May fold old turns into the conversation's
+/// rolling summary, which costs model calls; if that fails the request is
+/// sent with shortened tool results only.
+pub async fn request_messages(
+    provider: &dyn ChatProvider,
+    prompts: &PromptLibrary,
+    conversation: &mut Conversation,
+    cancel_token: &CancellationToken,
+) -> Vec<Message> {
+    let budget = provider.context_window() * TRANSCRIPT_SHARE_PERCENT / 100;
+
+    let messages = view(conversation);
+    if estimate_tokens(&messages) <= budget {
+        return messages;
+    }
+
+    let keep_from = conversation
+        .user_turns()
+        .saturating_sub(KEEP_RECENT_TURNS)
+        .max(conversation.compacted_turns);
+    let messages = elide_tool_results(conversation, messages, keep_from);
+    if estimate_tokens(&messages) <= budget || keep_from <= conversation.compacted_turns {
+        return messages;
+    }
+
+    match fold_turns(provider, prompts, conversation, keep_from, cancel_token).await {
+        Ok(()) => {
+            let messages = view(conversation);
+            elide_tool_results(conversation, messages, keep_from)
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to summarize earlier turns");
+            messages
+        }
+    }
+}
+
+/// Leading system messages, the rolling summary, then every turn not yet
+/// folded into it.
+fn view(conversation: &Conversation) -> Vec<Message> {
+    let system = conversation
+        .messages
+        .iter()
+        .take_while(|m| m.role == MessageRole::System);
+    let mut messages: Vec<Message> = system.cloned().collect();
+
+    let start = match conversation.rolling_summary.as_ref() {
+        Some(summary) => {
+            messages.push(Message {
+                role: MessageRole::Context,
+                content: vec![ContentBlock::Text {
+                    text: format!("Summary of the conversation so far:\n{}", summary),
+                }],
+            });
+            conversation
+                .user_message_index(conversation.compacted_turns)
+                .unwrap_or(conversation.messages.len())
+        }
+        None => messages.len(),
+    };
+    messages.extend(conversation.messages[start..].iter().cloned());
+    messages
+}
+
+/// Shorten long tool results and drop page images in the turns before user
+/// turn `keep_from`.
+/// `messages` is a [`view`] of `conversation`.
+fn elide_tool_results(
+    conversation: &Conversation,
+    mut messages: Vec<Message>,
+    keep_from: usize,
+) -> Vec<Message> {
+    let Some(cut) = conversation.user_message_index(keep_from) else {
+        return messages;
+    };
+    // The view drops the folded prefix, so map the cut into it.
+    let kept_after_cut = conversation.messages.len() - cut;
+    let cut = messages.len().saturating_sub(kept_after_cut);
+
+    for message in &mut messages[..cut] {
+        message
+            .content
+            .retain(|block| !matches!(block, ContentBlock::PageImage { .. }));
+        for block in &mut message.content {
+            if let ContentBlock::ToolResult { content, .. } = block {
+                if content.len() >= MIN_ELIDED_CHARS {
+                    *content = format!(
+                        "[Earlier result of {} characters omitted to save context. \
+                         Run the tool again if you need it.]",
+                        content.len()
+                    );
+                }
+            }
+        }
+    }
+    messages
+}
+
+/// Fold user turns from `compacted_turns` up to `keep_from` into the
+/// rolling summary.
+async fn fold_turns(
+    provider: &dyn ChatProvider,
+    prompts: &PromptLibrary,
+    conversation: &mut Conversation,
+    keep_from: usize,
+    cancel_token: &CancellationToken,
+) -> Result<()> {
+    let start = conversation
+        .user_message_index(conversation.compacted_turns)
+        .unwrap_or(conversation.messages.len());
+    let end = conversation
+        .user_message_index(keep_from)
+        .unwrap_or(conversation.messages.len());
+
+    let mut pages: Vec<String> = conversation.rolling_summary.iter().cloned().collect();
+    pages.extend(conversation.messages[start..end].iter().map(render_message));
+    let pages: Vec<&str> = pages.iter().map(String::as_str).collect();
+
+    // Not cached: the same span of a conversation is folded only once.
+    let summary = summarize_pages(
+        provider,
+        prompts,
+        None,
+        "the conversation so far",
+        &pages,
+        Some("what the user asked, what was found and in which documents, and open questions"),
+        cancel_token,
+    )
+    .await?;
+
+    info!(
+        conversation_id = %conversation.id,
+        folded_turns = keep_from - conversation.compacted_turns,
+        calls = summary.calls,
+        "Folded earlier turns into the rolling summary"
+    );
+    conversation.rolling_summary = Some(summary.text);
+    conversation.compacted_turns = keep_from;
+    Ok(())
+}
+
+/// A message as plain text for the summarizer.
+fn render_message(message: &Message) -> String {
+    let role = match message.role {
+        MessageRole::System => "System",
+        MessageRole::Context => "Session",
+        MessageRole::User => "User",
+        MessageRole::Assistant => "Assistant",
+    };
+    let mut out = String::new();
+    for block in &message.content {
+        match block {
+            ContentBlock::Text { text } => out.push_str(&format!("{}: {}\n", role, text)),
+            ContentBlock::ToolUse {
+                name, arguments, ..
+            } => out.push_str(&format!("Assistant called {} with {}\n", name, arguments)),
+            ContentBlock::ToolResult { content, .. } => {
+                let mut cut = content.len().min(MAX_SUMMARIZED_RESULT);
+                while !content.is_char_boundary(cut) {
+                    cut -= 1;
+                }
+                out.push_str(&format!("Tool result: {}\n", &content[..cut]));
+            }
+            ContentBlock::Thinking { .. }
+            | ContentBlock::Citation { .. }
+            | ContentBlock::SourceFile { .. }
+            | ContentBlock::PageImage { .. } => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{CompletionResult, ProviderEvent, SamplingParams, ToolDefinition};
+    use std::sync::Mutex;
+    use tokio::sync::mpsc;
+
+    /// A model with a tiny context window that answers every request with
+    /// the same summary.
+    struct TinyProvider {
+        calls: Mutex<usize>,
+    }
+
+    impl crate::provider::Provider for TinyProvider {
+        fn provider_name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn model_id(&self) -> &str {
+            "mock-model"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ChatProvider for TinyProvider {
+        async fn stream_completion(
+            &self,
+            _messages: &[Message],
+            _tools: &[ToolDefinition],
+            _sampling: &SamplingParams,
+            _event_tx: mpsc::Sender<ProviderEvent>,
+            _cancel_token: CancellationToken,
+        ) -> Result<CompletionResult> {
+            *self.calls.lock().unwrap() += 1;
+            Ok(CompletionResult {
+                text: "The user asked about the audit.".to_string(),
+                ..CompletionResult::default()
+            })
+        }
+
+        fn context_window(&self) -> usize {
+            1000
+        }
+    }
+
+    /// Turns that each carry a tool result of `result_chars` characters.
+    fn conversation(turns: usize, result_chars: usize) -> Conversation {
+        let mut conv = Conversation::with_system_prompt("c".to_string(), "Be brief.".to_string());
+        for turn in 0..turns {
+            conv.add_user_message(format!("question {}", turn));
+            conv.add_assistant_message(vec![
+                ContentBlock::ToolUse {
+                    id: format!("call_{}", turn),
+                    name: "search".to_string(),
+                    arguments: serde_json::json!({"query": "audit"}),
+                },
+                ContentBlock::ToolResult {
+                    tool_use_id: format!("call_{}", turn),
+                    content: "x".repeat(result_chars),
+                    is_error: false,
+                },
+            ]);
+            conv.add_assistant_message(vec![ContentBlock::Text {
+                text: format!("answer {}", turn),
+            }]);
+        }
+        conv
+    }
+
+    fn tool_results(messages: &[Message]) -> Vec<usize> {
+        messages
+            .iter()
+            .flat_map(|m| &m.content)
+            .filter_map(|b| match b {
+                ContentBlock::ToolResult { content, .. } => Some(content.len()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn provider() -> TinyProvider {
+        TinyProvider {
+            calls: Mutex::new(0),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_small_transcript_is_sent_whole() {
+        let provider = provider();
+        let mut conv = conversation(3, 50);
+        let messages = request_messages(
+            &provider,
+            &PromptLibrary::builtin(),
+            &mut conv,
+            &CancellationToken::new(),
+        )
+        .await;
+
+        assert_eq!(messages.len(), conv.messages.len());
+        assert_eq!(*provider.calls.lock().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_old_tool_results_are_shortened_first() {
+        // Budget is 700 tokens, about 2100 characters.
+        let provider = provider();
+        let mut conv = conversation(3, 900);
+        let messages = request_messages(
+            &provider,
+            &PromptLibrary::builtin(),
+            &mut conv,
+            &CancellationToken::new(),
+        )
+        .await;
+
+        let results = tool_results(&messages);
+        assert!(results[0] < 900);
+        assert_eq!(&results[1..], &[900, 900]);
+        assert!(conv.rolling_summary.is_none());
+        assert_eq!(*provider.calls.lock().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_old_turns_fold_into_rolling_summary() {
+        let provider = provider();
+        let mut conv = conversation(40, 100);
+        let stored = conv.messages.len();
+        let messages = request_messages(
+            &provider,
+            &PromptLibrary::builtin(),
+            &mut conv,
+            &CancellationToken::new(),
+        )
+        .await;
+
+        assert_eq!(conv.compacted_turns, 38);
+        assert_eq!(
+            conv.rolling_summary.as_deref(),
+            Some("The user asked about the audit.")
+        );
+        // System, summary, then the two recent turns of three messages each.
+        assert_eq!(messages.len(), 8);
+        assert!(messages[1]
+            .text()
+            .contains("The user asked about the audit."));
+        assert_eq!(messages[2].text(), "question 38");
+        // The stored transcript keeps everything.
+        assert_eq!(conv.messages.len(), stored);
+    }
+}
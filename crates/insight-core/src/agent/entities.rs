@@ -0,0 +1,510 @@
+//! Named entity extraction for the `extract_entities` tool.
+//!
+//! Like [`super::timeline`], this is a rule-based scanner, not a model.
+//! It finds candidates a reporter then checks: capitalized name runs,
+//! classified by honorifics ("Dr. Jane Roe"), organization words ("Acme
+//! Holdings Ltd", "Ministry of Finance") and location cues ("in Lagos"),
+//! plus monetary amounts ("$4.2 million", "1,500 EUR"). Single capitalized
+//! words without a cue are skipped; they are mostly sentence starts.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::timeline::Citation;
+use crate::text::tokenize;
+
+/// Citations kept per entity. The count still covers every mention.
+const MAX_CITATIONS: usize = 20;
+
+/// Longest name run, in words, treated as one entity.
+const MAX_NAME_WORDS: usize = 6;
+
+const HONORIFICS: &[&str] = &[
+    "mr",
+    "mrs",
+    "ms",
+    "miss",
+    "dr",
+    "prof",
+    "professor",
+    "sir",
+    "dame",
+    "judge",
+    "justice",
+    "senator",
+    "sen",
+    "rep",
+    "president",
+    "minister",
+    "mayor",
+    "governor",
+    "gen",
+    "general",
+    "col",
+    "capt",
+    "lt",
+    "sgt",
+    "rev",
+];
+
+const ORG_WORDS: &[&str] = &[
+    "inc",
+    "ltd",
+    "llc",
+    "llp",
+    "corp",
+    "corporation",
+    "company",
+    "co",
+    "gmbh",
+    "ag",
+    "sa",
+    "nv",
+    "bv",
+    "plc",
+    "group",
+    "holdings",
+    "bank",
+    "foundation",
+    "trust",
+    "association",
+    "institute",
+    "university",
+    "college",
+    "agency",
+    "ministry",
+    "department",
+    "council",
+    "commission",
+    "committee",
+    "court",
+    "authority",
+    "police",
+    "party",
+    "union",
+    "partners",
+    "fund",
+    "bureau",
+    "office",
+];
+
+/// Lowercase words right before a name that mark it as a place.
+const LOCATION_CUES: &[&str] = &["in", "near", "outside", "across", "throughout"];
+
+/// Capitalized words that start sentences or name dates, never entities.
+const STOPWORDS: &[&str] = &[
+    "the",
+    "a",
+    "an",
+    "this",
+    "that",
+    "these",
+    "those",
+    "in",
+    "on",
+    "at",
+    "for",
+    "from",
+    "to",
+    "by",
+    "with",
+    "and",
+    "but",
+    "or",
+    "if",
+    "when",
+    "after",
+    "before",
+    "during",
+    "however",
+    "he",
+    "she",
+    "it",
+    "they",
+    "we",
+    "i",
+    "his",
+    "her",
+    "their",
+    "our",
+    "its",
+    "there",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// Lowercase words allowed inside a name run ("Bank of America").
+const CONNECTORS: &[&str] = &["of", "and", "for", "de", "van", "von", "der", "la", "du"];
+
+const CURRENCY_SYMBOLS: &[char] = &['$', '€', '£', '¥'];
+
+const CURRENCY_CODES: &[&str] = &[
+    "usd", "eur", "gbp", "chf", "jpy", "cad", "aud", "dollars", "euros", "pounds",
+];
+
+const SCALES: &[&str] = &["thousand", "million", "billion", "trillion", "k", "m", "bn"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Person,
+    Organization,
+    Location,
+    Amount,
+}
+
+/// An entity found in text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityMention {
+    pub kind: EntityKind,
+    pub name: String,
+    /// Byte range of the mention in the scanned text.
+    pub span: Range<usize>,
+}
+
+/// An entity with how often and where it was mentioned.
+#[derive(Debug, Clone, Serialize)]
+pub struct Entity {
+    pub kind: EntityKind,
+    pub name: String,
+    pub count: usize,
+    pub citations: Vec<Citation>,
+}
+
+/// Entities gathered across documents, merged case-insensitively by kind
+/// and name.
+#[derive(Default)]
+pub struct EntityIndex {
+    entities: Vec<Entity>,
+    by_key: HashMap<(EntityKind, String), usize>,
+}
+
+impl EntityIndex {
+    /// Record every entity in `text`, citing `citation` for each.
+    pub fn add_text(&mut self, text: &str, citation: &Citation) {
+        for mention in find_entities(text) {
+            let key = (mention.kind, mention.name.to_lowercase());
+            let i = *self.by_key.entry(key).or_insert_with(|| {
+                self.entities.push(Entity {
+                    kind: mention.kind,
+                    name: mention.name,
+                    count: 0,
+                    citations: Vec::new(),
+                });
+                self.entities.len() - 1
+            });
+            let entity = &mut self.entities[i];
+            entity.count += 1;
+            if entity.citations.len() < MAX_CITATIONS && !entity.citations.contains(citation) {
+                entity.citations.push(citation.clone());
+            }
+        }
+    }
+
+    /// Entities grouped by kind, most mentioned first.
+    pub fn into_entities(mut self) -> Vec<Entity> {
+        self.entities
+            .sort_by(|a, b| a.kind.cmp(&b.kind).then(b.count.cmp(&a.count)));
+        self.entities
+    }
+}
+
+/// Find the entities in `text`, in order of appearance.
+pub fn find_entities(text: &str) -> Vec<EntityMention> {
+    let mut mentions = find_amounts(text);
+    mentions.extend(find_names(text));
+    mentions.sort_by_key(|m| m.span.start);
+    mentions
+}
+
+/// Currency amounts: a symbol before the number or a code after it, with
+/// an optional scale word ("$4.2 million", "1,500 EUR").
+fn find_amounts(text: &str) -> Vec<EntityMention> {
+    let mut mentions = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let c = rest.chars().next().unwrap();
+
+        if CURRENCY_SYMBOLS.contains(&c) {
+            let after = i + c.len_utf8();
+            let start = after + (text[after..].len() - text[after..].trim_start_matches(' ').len());
+            if let Some(number_end) = number_at(text, start).filter(|_| start - after <= 1) {
+                let end = scale_after(text, number_end).unwrap_or(number_end);
+                mentions.push(EntityMention {
+                    kind: EntityKind::Amount,
+                    name: collapse(&text[i..end]),
+                    span: i..end,
+                });
+                i = end;
+                continue;
+            }
+        }
+
+        let boundary = text[..i].chars().next_back().is_none_or(|p| {
+            !p.is_alphanumeric() && !matches!(p, '.' | ',') && !CURRENCY_SYMBOLS.contains(&p)
+        });
+        if c.is_ascii_digit() && boundary {
+            if let Some(number_end) = number_at(text, i) {
+                let scaled = scale_after(text, number_end).unwrap_or(number_end);
+                if let Some(end) = word_after(text, scaled, CURRENCY_CODES) {
+                    mentions.push(EntityMention {
+                        kind: EntityKind::Amount,
+                        name: collapse(&text[i..end]),
+                        span: i..end,
+                    });
+                    i = end;
+                    continue;
+                }
+                i = number_end;
+                continue;
+            }
+        }
+
+        i += c.len_utf8();
+    }
+    mentions
+}
+
+/// End of a number like `1,200.50` starting at `start`.
+fn number_at(text: &str, start: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    if !bytes.get(start).is_some_and(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let mut end = start;
+    while end < bytes.len()
+        && (bytes[end].is_ascii_digit()
+            || (matches!(bytes[end], b',' | b'.')
+                && bytes.get(end + 1).is_some_and(|b| b.is_ascii_digit())))
+    {
+        end += 1;
+    }
+    Some(end)
+}
+
+/// End of a scale word after `pos` ("4.2 million", "4.2m", "4.2bn").
+fn scale_after(text: &str, pos: usize) -> Option<usize> {
+    word_after(text, pos, SCALES).or_else(|| {
+        let word_end = pos
+            + text[pos..]
+                .find(|c: char| !c.is_alphanumeric())
+                .unwrap_or(text.len() - pos);
+        SCALES
+            .contains(&text[pos..word_end].to_lowercase().as_str())
+            .then_some(word_end)
+            .filter(|&end| end > pos)
+    })
+}
+
+/// End of the word after one space at `pos`, if it is one of `words`.
+fn word_after(text: &str, pos: usize, words: &[&str]) -> Option<usize> {
+    let start = pos + 1;
+    if !text[pos..].starts_with(' ') || start >= text.len() {
+        return None;
+    }
+    let end = start
+        + text[start..]
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(text.len() - start);
+    (end > start && words.contains(&text[start..end].to_lowercase().as_str())).then_some(end)
+}
+
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Capitalized word runs, classified by their words and context.
+fn find_names(text: &str) -> Vec<EntityMention> {
+    let tokens = tokenize(text);
+    let is_cap = |k: usize| {
+        let word = &text[tokens[k].clone()];
+        word.chars().next().is_some_and(char::is_uppercase)
+            && !STOPWORDS.contains(&word.to_lowercase().as_str())
+    };
+    let gap = |a: usize, b: usize| &text[tokens[a].end..tokens[b].start];
+    let is_connector = |k: usize| CONNECTORS.contains(&&text[tokens[k].clone()]);
+    let is_org_word =
+        |k: usize| ORG_WORDS.contains(&text[tokens[k].clone()].to_lowercase().as_str());
+
+    let mut mentions = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if !is_cap(i) {
+            i += 1;
+            continue;
+        }
+
+        // Extend the run over capitalized words, allowing connectors,
+        // ampersands and abbreviations ("J. Smith", "Dr. Roe").
+        let mut last = i;
+        let mut k = i + 1;
+        while k < tokens.len() && k - i < MAX_NAME_WORDS {
+            let g = gap(k - 1, k);
+            let prev = text[tokens[k - 1].clone()].to_lowercase();
+            let abbreviation =
+                g == ". " && (prev.len() == 1 || HONORIFICS.contains(&prev.as_str()));
+            if !(g == " " || g == "-" || g == " & " || abbreviation) {
+                break;
+            }
+            if is_cap(k) {
+                last = k;
+                k += 1;
+            } else if is_connector(k) && k + 1 < tokens.len() && is_cap(k + 1) {
+                k += 1;
+            } else {
+                break;
+            }
+        }
+
+        // "John Smith of Acme Ltd" is a person and an organization, while
+        // "Bank of England" is one name.
+        if let Some(c) = (i..=last).find(|&k| is_connector(k)) {
+            let prefix_is_org = (i..c).any(is_org_word);
+            if c - i >= 2 && !prefix_is_org {
+                last = c - 1;
+            }
+        }
+
+        if let Some(mention) = classify(text, &tokens, i, last) {
+            mentions.push(mention);
+        }
+        i = last + 1;
+    }
+    mentions
+}
+
+fn classify(
+    text: &str,
+    tokens: &[Range<usize>],
+    first: usize,
+    last: usize,
+) -> Option<EntityMention> {
+    let words: Vec<String> = tokens[first..=last]
+        .iter()
+        .map(|r| text[r.clone()].to_lowercase())
+        .collect();
+    let span = tokens[first].start..tokens[last].end;
+    let mention = |kind, span: Range<usize>| {
+        Some(EntityMention {
+            kind,
+            name: collapse(&text[span.clone()]),
+            span,
+        })
+    };
+
+    if HONORIFICS.contains(&words[0].as_str()) {
+        if first == last {
+            return None;
+        }
+        // Skip the honorific and its period.
+        let start = tokens[first + 1].start;
+        return mention(EntityKind::Person, start..span.end);
+    }
+    if words.iter().any(|w| ORG_WORDS.contains(&w.as_str())) && first != last {
+        return mention(EntityKind::Organization, span);
+    }
+
+    let cue = first
+        .checked_sub(1)
+        .filter(|&prev| &text[tokens[prev].end..tokens[first].start] == " ")
+        .map(|prev| text[tokens[prev].clone()].to_lowercase());
+    if cue.is_some_and(|w| LOCATION_CUES.contains(&w.as_str())) {
+        return mention(EntityKind::Location, span);
+    }
+
+    // Unlabelled runs of two or more plain words are most likely names.
+    let has_connector = tokens[first..=last]
+        .iter()
+        .any(|r| CONNECTORS.contains(&&text[r.clone()]));
+    if first != last && !has_connector {
+        return mention(EntityKind::Person, span);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str) -> Vec<(EntityKind, String)> {
+        find_entities(text)
+            .into_iter()
+            .map(|m| (m.kind, m.name))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_names() {
+        use EntityKind::*;
+        assert_eq!(
+            found("Dr. Jane Roe met John Smith of Acme Holdings Ltd in New York."),
+            vec![
+                (Person, "Jane Roe".to_string()),
+                (Person, "John Smith".to_string()),
+                (Organization, "Acme Holdings Ltd".to_string()),
+                (Location, "New York".to_string()),
+            ]
+        );
+        assert_eq!(
+            found("The Ministry of Finance wrote to the Bank of England."),
+            vec![
+                (Organization, "Ministry of Finance".to_string()),
+                (Organization, "Bank of England".to_string()),
+            ]
+        );
+        // Sentence starts, dates and lone words are not entities.
+        assert!(found("Payment followed. On Monday 5 March nothing happened.").is_empty());
+    }
+
+    #[test]
+    fn test_find_amounts() {
+        let amounts: Vec<String> = find_entities(
+            "They paid $4.2 million, then € 300 and 1,500 EUR; invoice 2023 and 40 items.",
+        )
+        .into_iter()
+        .filter(|m| m.kind == EntityKind::Amount)
+        .map(|m| m.name)
+        .collect();
+        assert_eq!(amounts, vec!["$4.2 million", "€ 300", "1,500 EUR"]);
+    }
+
+    #[test]
+    fn test_index_merges_and_counts() {
+        let cite = |page| Citation {
+            document_id: "doc".to_string(),
+            document_name: "doc.pdf".to_string(),
+            page,
+        };
+        let mut index = EntityIndex::default();
+        index.add_text("Mr. John Smith signed. Later, John Smith left.", &cite(1));
+        index.add_text("JOHN SMITH returned with $500.", &cite(2));
+
+        let entities = index.into_entities();
+        assert_eq!(entities.len(), 2);
+        assert_eq!(entities[0].kind, EntityKind::Person);
+        assert_eq!(entities[0].name, "John Smith");
+        assert_eq!(entities[0].count, 3);
+        assert_eq!(entities[0].citations, vec![cite(1), cite(2)]);
+        assert_eq!(entities[1].kind, EntityKind::Amount);
+    }
+}
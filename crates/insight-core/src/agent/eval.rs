@@ -0,0 +1,497 @@
+//! Scored replays of the agent against a fixture collection.
+//!
+//! A suite is a YAML file describing a small collection, page by page, and
+//! questions to ask about it. Each case names the pages a good answer
+//! cites and, optionally, phrases the answer must contain. [`run_suite`]
+//! loads the fixture into the given state, runs the full agent loop once
+//! per case and scores the citations it produced, so a prompt or tool
+//! change can be compared against the last run by the numbers.
+//!
+//! Cases may script the model's turns in `mock_responses`. Run without a
+//! provider, the suite replays them through a [`MockProvider`], which
+//! exercises the tools offline and deterministically; a real provider
+//! ignores the script.
+//!
+//! ```yaml
+//! name: Lease
+//! documents:
+//!   - id: lease
+//!     name: Lease.pdf
+//!     pages:
+//!       - The tenant pays a monthly rent of $1,200.
+//!       - Either party may end the lease with 60 days notice.
+//! cases:
+//!   - question: How much is the rent?
+//!     expected_citations:
+//!       - { document: lease, page: 1 }
+//!     answer_contains: ["1,200"]
+//!     mock_responses:
+//!       - tool_calls:
+//!           - { name: search, arguments: { query: rent } }
+//!       - text: The rent is $1,200 a month.
+//! ```
+//!
+//! Fixture pages are indexed for keyword search only. Tools that change
+//! data are declined, so a suite never writes outside its fixture.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::provider::{ChatProvider, CompletedToolCall, CompletionResult, MockProvider};
+use crate::search::ChunkToIndex;
+use crate::storage::DocumentMetadata;
+use crate::{AppState, CollectionInfo};
+
+use super::{
+    run_agent_loop, AgentContext, AgentEvent, ContentBlock, Conversation, MessageRole, ToolApproval,
+};
+
+/// Questions about one fixture collection.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalSuite {
+    pub name: String,
+    pub documents: Vec<FixtureDocument>,
+    pub cases: Vec<EvalCase>,
+}
+
+impl EvalSuite {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read eval suite {}", path.display()))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(yaml: &str) -> Result<Self> {
+        serde_norway::from_str(yaml).context("Failed to parse eval suite")
+    }
+}
+
+/// A document of the fixture, given as the text of each page.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixtureDocument {
+    /// Document ID, referred to by `expected_citations`.
+    pub id: String,
+    pub name: String,
+    pub pages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalCase {
+    pub question: String,
+    #[serde(default)]
+    pub expected_citations: Vec<ExpectedCitation>,
+    /// Phrases the final answer must contain, ignoring case.
+    #[serde(default)]
+    pub answer_contains: Vec<String>,
+    /// Model turns replayed when the suite runs without a provider.
+    #[serde(default)]
+    pub mock_responses: Vec<MockResponse>,
+}
+
+/// A page the answer should cite. Without a page, any page of the
+/// document counts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpectedCitation {
+    pub document: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+}
+
+impl ExpectedCitation {
+    fn matches(&self, document_id: &str, page: Option<usize>) -> bool {
+        self.document == document_id && self.page.is_none_or(|p| page == Some(p))
+    }
+}
+
+/// One scripted model turn.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MockResponse {
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub tool_calls: Vec<MockToolCall>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MockToolCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+}
+
+/// How one case went.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseReport {
+    pub question: String,
+    pub answer: String,
+    /// Pages cited during the turn, as `document_id` and page.
+    pub cited: Vec<(String, Option<usize>)>,
+    pub missing_citations: Vec<ExpectedCitation>,
+    /// Share of expected citations the answer cited.
+    pub citation_recall: f64,
+    /// Share of citations that were expected.
+    pub citation_precision: f64,
+    pub missing_phrases: Vec<String>,
+    /// Errors the agent reported, e.g. hitting a limit.
+    pub errors: Vec<String>,
+    pub passed: bool,
+}
+
+/// Scores of a whole suite.
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalReport {
+    pub suite: String,
+    pub provider: String,
+    pub model: String,
+    pub passed: usize,
+    pub total: usize,
+    /// Mean over cases.
+    pub citation_recall: f64,
+    /// Mean over cases.
+    pub citation_precision: f64,
+    pub cases: Vec<CaseReport>,
+}
+
+impl EvalReport {
+    /// One line per case and a total, for the terminal.
+    pub fn summary(&self) -> String {
+        let mut out = format!("{} ({} / {})\n", self.suite, self.provider, self.model);
+        for case in &self.cases {
+            out.push_str(&format!(
+                "  [{}] recall {:.2} precision {:.2}  {}\n",
+                if case.passed { "pass" } else { "FAIL" },
+                case.citation_recall,
+                case.citation_precision,
+                case.question
+            ));
+        }
+        out.push_str(&format!(
+            "{}/{} passed, recall {:.2}, precision {:.2}",
+            self.passed, self.total, self.citation_recall, self.citation_precision
+        ));
+        out
+    }
+}
+
+/// Store the fixture documents in a new collection and index one chunk
+/// per page.
+pub async fn load_fixture(state: &AppState, suite: &EvalSuite) -> Result<CollectionInfo> {
+    let storage = state.storage.read().await;
+    let (namespace_id, _) = storage.create_collection(&suite.name).await?;
+    let collection_id = namespace_id.to_string();
+
+    let mut chunks = Vec::new();
+    let mut total_pages = 0;
+    for doc in &suite.documents {
+        let mut text = String::new();
+        let mut page_boundaries = Vec::with_capacity(doc.pages.len());
+        for page in &doc.pages {
+            text.push_str(page);
+            text.push_str("\n\n");
+            page_boundaries.push(text.len());
+        }
+        let metadata = DocumentMetadata {
+            id: doc.id.clone(),
+            name: doc.name.clone(),
+            file_type: "text/plain".to_string(),
+            page_count: doc.pages.len(),
+            tags: Vec::new(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            page_boundaries,
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: None,
+            near_duplicates: Vec::new(),
+        };
+        storage
+            .add_document(namespace_id, metadata, text.as_bytes(), text.as_bytes())
+            .await
+            .with_context(|| format!("Failed to store fixture document {}", doc.id))?;
+
+        for (i, page) in doc.pages.iter().enumerate() {
+            chunks.push(ChunkToIndex {
+                id: format!("{}_chunk_{}", doc.id, i),
+                parent_id: doc.id.clone(),
+                parent_name: doc.name.clone(),
+                chunk_index: i,
+                content: page.clone(),
+                collection_id: collection_id.clone(),
+                page_count: doc.pages.len(),
+                start_page: i + 1,
+                end_page: i + 1,
+                vector: None,
+                embedder: None,
+            });
+        }
+        total_pages += doc.pages.len();
+    }
+    state.index_worker.index_chunks(chunks).await?;
+
+    Ok(CollectionInfo {
+        id: collection_id,
+        name: suite.name.clone(),
+        document_count: suite.documents.len(),
+        total_pages,
+        created_at: None,
+    })
+}
+
+/// Load the suite's fixture into `state` and run every case. Without a
+/// `provider`, each case replays its `mock_responses`.
+pub async fn run_suite(
+    state: &AppState,
+    suite: &EvalSuite,
+    provider: Option<&dyn ChatProvider>,
+) -> Result<EvalReport> {
+    let collection = load_fixture(state, suite).await?;
+    let ctx = AgentContext::new(state.clone(), Some(vec![collection.clone()]));
+
+    let mut cases = Vec::with_capacity(suite.cases.len());
+    for (i, case) in suite.cases.iter().enumerate() {
+        let mock;
+        let provider = match provider {
+            Some(provider) => provider,
+            None => {
+                mock = MockProvider::new(mock_script(case));
+                &mock
+            }
+        };
+        let mut conversation = Conversation::new(format!("eval_{}", i));
+        conversation.set_collections(vec![collection.clone()]);
+        cases.push(run_case(provider, &mut conversation, case, &ctx).await?);
+    }
+
+    let total = cases.len();
+    let mean = |score: fn(&CaseReport) -> f64| {
+        if total == 0 {
+            0.0
+        } else {
+            cases.iter().map(score).sum::<f64>() / total as f64
+        }
+    };
+    let (provider_name, model) = match provider {
+        Some(provider) => (provider.provider_name(), provider.model_id().to_string()),
+        None => ("mock", "mock-model".to_string()),
+    };
+    Ok(EvalReport {
+        suite: suite.name.clone(),
+        provider: provider_name.to_string(),
+        model,
+        passed: cases.iter().filter(|c| c.passed).count(),
+        total,
+        citation_recall: mean(|c| c.citation_recall),
+        citation_precision: mean(|c| c.citation_precision),
+        cases,
+    })
+}
+
+async fn run_case(
+    provider: &dyn ChatProvider,
+    conversation: &mut Conversation,
+    case: &EvalCase,
+    ctx: &AgentContext,
+) -> Result<CaseReport> {
+    let (event_tx, mut event_rx) = mpsc::channel(100);
+    let state = ctx.state.clone();
+    // Drain events as the loop runs, declining any tool that would change
+    // data.
+    let events = tokio::spawn(async move {
+        let mut errors = Vec::new();
+        while let Some(event) = event_rx.recv().await {
+            match event {
+                AgentEvent::ToolApprovalRequired { tool_use_id, .. } => {
+                    if let Some(answer_tx) = state
+                        .pending_confirmations
+                        .write()
+                        .await
+                        .remove(&tool_use_id)
+                    {
+                        let _ = answer_tx.send(ToolApproval::Deny);
+                    }
+                }
+                AgentEvent::Error { message } => errors.push(message),
+                _ => {}
+            }
+        }
+        errors
+    });
+
+    let turn_start = conversation.messages.len();
+    run_agent_loop(
+        provider,
+        conversation,
+        case.question.clone(),
+        ctx,
+        event_tx,
+        CancellationToken::new(),
+    )
+    .await?;
+    let errors = events.await.unwrap_or_default();
+
+    let turn = &conversation.messages[turn_start..];
+    let mut cited = BTreeSet::new();
+    for block in turn.iter().flat_map(|m| &m.content) {
+        if let ContentBlock::Citation {
+            document_id, page, ..
+        } = block
+        {
+            cited.insert((document_id.clone(), *page));
+        }
+    }
+    let answer = turn
+        .iter()
+        .rev()
+        .find(|m| m.role == MessageRole::Assistant)
+        .map(|m| m.text())
+        .unwrap_or_default();
+
+    Ok(score_case(
+        case,
+        answer,
+        cited.into_iter().collect(),
+        errors,
+    ))
+}
+
+fn score_case(
+    case: &EvalCase,
+    answer: String,
+    cited: Vec<(String, Option<usize>)>,
+    errors: Vec<String>,
+) -> CaseReport {
+    let missing_citations: Vec<ExpectedCitation> = case
+        .expected_citations
+        .iter()
+        .filter(|e| !cited.iter().any(|(doc, page)| e.matches(doc, *page)))
+        .cloned()
+        .collect();
+    let citation_recall = if case.expected_citations.is_empty() {
+        1.0
+    } else {
+        1.0 - missing_citations.len() as f64 / case.expected_citations.len() as f64
+    };
+    let citation_precision = if cited.is_empty() {
+        if case.expected_citations.is_empty() {
+            1.0
+        } else {
+            0.0
+        }
+    } else {
+        let relevant = cited
+            .iter()
+            .filter(|(doc, page)| {
+                case.expected_citations
+                    .iter()
+                    .any(|e| e.matches(doc, *page))
+            })
+            .count();
+        relevant as f64 / cited.len() as f64
+    };
+
+    let answer_lower = answer.to_lowercase();
+    let missing_phrases: Vec<String> = case
+        .answer_contains
+        .iter()
+        .filter(|phrase| !answer_lower.contains(&phrase.to_lowercase()))
+        .cloned()
+        .collect();
+
+    CaseReport {
+        question: case.question.clone(),
+        passed: missing_citations.is_empty() && missing_phrases.is_empty() && errors.is_empty(),
+        answer,
+        cited,
+        missing_citations,
+        citation_recall,
+        citation_precision,
+        missing_phrases,
+        errors,
+    }
+}
+
+/// A case's script as provider completions.
+fn mock_script(case: &EvalCase) -> Vec<CompletionResult> {
+    case.mock_responses
+        .iter()
+        .enumerate()
+        .map(|(turn, response)| CompletionResult {
+            text: response.text.clone(),
+            tool_calls: response
+                .tool_calls
+                .iter()
+                .enumerate()
+                .map(|(i, call)| CompletedToolCall {
+                    id: format!("call_{}_{}", turn, i),
+                    name: call.name.clone(),
+                    arguments: call.arguments.clone(),
+                })
+                .collect(),
+            ..CompletionResult::default()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = r#"
+name: Lease
+documents:
+  - id: lease
+    name: Lease.pdf
+    pages:
+      - The tenant pays a monthly rent of $1,200.
+      - Either party may end the lease with 60 days notice.
+cases:
+  - question: How much is the rent?
+    expected_citations:
+      - { document: lease, page: 1 }
+    answer_contains: ["1,200"]
+    mock_responses:
+      - tool_calls:
+          - { name: search, arguments: { query: rent } }
+      - text: The rent is $1,200 a month.
+  - question: How much notice is needed?
+    expected_citations:
+      - { document: lease, page: 2 }
+    mock_responses:
+      - text: I could not find that.
+"#;
+
+    #[tokio::test]
+    async fn test_run_suite_with_mock_provider() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = crate::Config {
+            data_dir: temp_dir.path().to_path_buf(),
+            iroh_dir: temp_dir.path().join("iroh"),
+            search_dir: temp_dir.path().join("search"),
+            settings_file: temp_dir.path().join("settings.json"),
+            conversations_dir: temp_dir.path().join("conversations"),
+        };
+        std::fs::create_dir_all(&config.iroh_dir).unwrap();
+        std::fs::create_dir_all(&config.search_dir).unwrap();
+        let state = AppState::new(config).await.unwrap();
+
+        let suite = EvalSuite::parse(SUITE).unwrap();
+        let report = run_suite(&state, &suite, None).await.unwrap();
+
+        assert_eq!(report.total, 2);
+        assert_eq!(report.passed, 1);
+        let rent = &report.cases[0];
+        assert!(rent.passed, "{:?}", rent);
+        assert_eq!(rent.cited, vec![("lease".to_string(), Some(1))]);
+        assert_eq!(rent.citation_precision, 1.0);
+
+        let notice = &report.cases[1];
+        assert!(!notice.passed);
+        assert_eq!(notice.citation_recall, 0.0);
+        assert_eq!(notice.missing_citations[0].page, Some(2));
+        assert_eq!(report.citation_recall, 0.5);
+    }
+}
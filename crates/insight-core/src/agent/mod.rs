@@ -1,12 +1,31 @@
+pub mod compare;
+pub mod context;
+pub mod entities;
+#[cfg(any(test, feature = "test-util"))]
+pub mod eval;
+pub mod paging;
+pub mod summarize;
+pub mod timeline;
 pub mod tools;
+pub mod verify;
+
+use std::time::Duration;
 
 use anyhow::Result;
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
 
-use crate::provider::{get_tool_definitions, ChatProvider, ProviderEvent};
+use crate::config::{PromptPreset, Settings};
+use crate::prompts::PromptLibrary;
+use crate::provider::pricing::estimate_cost;
+use crate::provider::{
+    get_tool_definitions, retry, ChatProvider, ProviderEvent, SamplingParams, StopReason,
+    TokenUsage,
+};
 pub use tools::{execute_tool, ToolCall, ToolResult};
 
 // Re-export CollectionInfo from crate root for convenience
@@ -18,9 +37,25 @@ pub struct AgentContext {
     pub state: crate::AppState,
     /// Collections to filter searches to (None = search all)
     pub collections: Option<Vec<CollectionInfo>>,
+    /// Documents the user pinned to the conversation. Their passages lead
+    /// search results, and their opening pages go into every request.
+    pub pinned_document_ids: Vec<String>,
+    /// Fires when the user stops the turn. Tools that call the model pass
+    /// it on so their requests stop too.
+    pub cancel: CancellationToken,
 }
 
 impl AgentContext {
+    /// A context searching `collections` (None = all), with nothing pinned.
+    pub fn new(state: crate::AppState, collections: Option<Vec<CollectionInfo>>) -> Self {
+        Self {
+            state,
+            collections,
+            pinned_document_ids: Vec::new(),
+            cancel: CancellationToken::new(),
+        }
+    }
+
     /// Get collection IDs for search filtering
     pub fn collection_ids(&self) -> Option<Vec<String>> {
         self.collections
@@ -45,12 +80,29 @@ Your search and list tools are filtered to a user-selected set of collections 
 
 Be concise. Answer in 2-4 sentences unless the user asks for more detail. Cite document names so findings are verifiable.
 
+When a question needs several searches or reads, first call `plan` with the steps, then call it again to mark steps done as you go.
+
 When answering questions:
 1. Search to find relevant documents
 2. Read documents to extract specific details
 3. Cite sources (document name)
 4. Note any gaps or contradictions worth pursuing"#;
 
+/// The base prompt with a preset's instructions appended. The preset adds
+/// to the base prompt rather than replacing it, so the session conventions
+/// and tool guidance stay in place whichever preset is picked.
+pub fn system_prompt_with_preset(preset: Option<&PromptPreset>) -> String {
+    match preset {
+        Some(preset) if !preset.instructions.trim().is_empty() => format!(
+            "{}\n\nAdditional instructions ({}):\n{}",
+            BASE_SYSTEM_PROMPT,
+            preset.name,
+            preset.instructions.trim()
+        ),
+        _ => BASE_SYSTEM_PROMPT.to_string(),
+    }
+}
+
 /// Render a single collection line like "Climate Reports (10 documents, 250 pages)"
 /// — shared between breadcrumb formatting and any UI mirror.
 fn format_collection_entry(c: &CollectionInfo) -> String {
@@ -128,6 +180,17 @@ pub enum ContentBlock {
     Text {
         text: String,
     },
+    /// The model's reasoning before its answer, shown collapsed. Only
+    /// sent back to providers that signed it.
+    Thinking {
+        thinking: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+        /// Encrypted reasoning the provider withheld. Never shown, but sent
+        /// back with the rest so the model keeps its train of thought.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        redacted: Vec<String>,
+    },
     ToolUse {
         id: String,
         name: String,
@@ -138,6 +201,38 @@ pub enum ContentBlock {
         content: String,
         is_error: bool,
     },
+    /// A passage a tool result drew on, so the UI can link a claim back to
+    /// its page. Filled by the agent loop, never sent to the model.
+    Citation {
+        collection_id: String,
+        document_id: String,
+        document_name: String,
+        /// 1-indexed; `None` when the passage's page isn't known.
+        page: Option<usize>,
+        quote: String,
+    },
+    /// An original file exported by the `get_source_file` tool for the user
+    /// to open. Filled by the agent loop, never sent to the model.
+    SourceFile {
+        collection_id: String,
+        document_id: String,
+        document_name: String,
+        file_type: String,
+        path: String,
+    },
+    /// A page rendered by the `view_page_image` tool, for models that read
+    /// images. Filled by the agent loop.
+    PageImage {
+        collection_id: String,
+        document_id: String,
+        document_name: String,
+        /// 1-indexed.
+        page: usize,
+        /// MIME type of `data`, e.g. `image/png`.
+        media_type: String,
+        /// The image, base64-encoded.
+        data: String,
+    },
 }
 
 /// A message in the conversation
@@ -161,6 +256,32 @@ impl Message {
     }
 }
 
+/// Tokens a conversation has used across all of its model calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConversationUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated from list prices at the time of each call. Local models
+    /// and unpriced remote models add nothing.
+    pub estimated_cost_usd: f64,
+}
+
+impl ConversationUsage {
+    /// Add one completion's usage on `provider_name`/`model_id`.
+    pub fn record(&mut self, provider_name: &str, model_id: &str, usage: &TokenUsage) {
+        self.input_tokens += usage.input_tokens;
+        self.output_tokens += usage.output_tokens;
+        self.estimated_cost_usd += estimate_cost(provider_name, model_id, usage).unwrap_or(0.0);
+    }
+
+    /// Add another conversation's totals.
+    pub fn add(&mut self, other: &ConversationUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.estimated_cost_usd += other.estimated_cost_usd;
+    }
+}
+
 /// A conversation with message history
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conversation {
@@ -174,6 +295,33 @@ pub struct Conversation {
     /// message to the transcript so the model can see the change.
     #[serde(default)]
     pub collections: Vec<CollectionInfo>,
+    /// Prompt preset the conversation was started with, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset_id: Option<String>,
+    /// Conversation this one was branched from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// Number of user turns shared with the parent at the branch point.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_turns: Option<usize>,
+    /// Tokens used by this conversation's own model calls. A branch starts
+    /// from zero.
+    #[serde(default)]
+    pub usage: ConversationUsage,
+    /// Summary standing in for the first `compacted_turns` user turns when
+    /// the transcript no longer fits the model's context window. See
+    /// [`context::request_messages`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rolling_summary: Option<String>,
+    #[serde(default)]
+    pub compacted_turns: usize,
+    /// Documents pinned for discussion ("these three exhibits"). See
+    /// [`AgentContext::pinned_document_ids`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pinned_document_ids: Vec<String>,
+    /// Sampling for this conversation's turns, over the provider config's.
+    #[serde(default, skip_serializing_if = "SamplingParams::is_unset")]
+    pub sampling: SamplingParams,
 }
 
 impl Conversation {
@@ -181,6 +329,15 @@ impl Conversation {
         Self::with_system_prompt(id, BASE_SYSTEM_PROMPT.to_string())
     }
 
+    /// Create a new conversation whose system prompt layers `preset` on top
+    /// of the base prompt.
+    pub fn with_preset(id: String, preset: &PromptPreset) -> Self {
+        let mut conversation =
+            Self::with_system_prompt(id, system_prompt_with_preset(Some(preset)));
+        conversation.preset_id = Some(preset.id.clone());
+        conversation
+    }
+
     /// Create a new conversation with a custom system prompt
     pub fn with_system_prompt(id: String, system_prompt: String) -> Self {
         let now = chrono::Utc::now().to_rfc3339();
@@ -196,6 +353,14 @@ impl Conversation {
             created_at: now.clone(),
             updated_at: now,
             collections: Vec::new(),
+            preset_id: None,
+            parent_id: None,
+            branch_turns: None,
+            usage: ConversationUsage::default(),
+            rolling_summary: None,
+            compacted_turns: 0,
+            pinned_document_ids: Vec::new(),
+            sampling: SamplingParams::default(),
         }
     }
 
@@ -244,6 +409,92 @@ impl Conversation {
         self.messages.iter().any(|m| m.role == MessageRole::User)
     }
 
+    /// Number of user messages, i.e. completed or in-flight turns.
+    pub fn user_turns(&self) -> usize {
+        self.messages
+            .iter()
+            .filter(|m| m.role == MessageRole::User)
+            .count()
+    }
+
+    /// Index in `messages` of the `turn`-th user message (0-based).
+    fn user_message_index(&self, turn: usize) -> Option<usize> {
+        self.messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.role == MessageRole::User)
+            .nth(turn)
+            .map(|(i, _)| i)
+    }
+
+    /// Remove the `turn`-th user message and everything after it, returning
+    /// the removed message's text so it can be sent again. Cutting the first
+    /// turn also drops its scope prelude, which the next
+    /// [`Conversation::add_user_message`] recreates.
+    pub fn truncate_at_turn(&mut self, turn: usize) -> Option<String> {
+        let index = self.user_message_index(turn)?;
+        let text = self.messages[index].text();
+        self.messages.truncate(index);
+        if turn < self.compacted_turns {
+            // The summary describes turns that are gone now.
+            self.rolling_summary = None;
+            self.compacted_turns = 0;
+        }
+        if !self.has_user_message() {
+            while self
+                .messages
+                .last()
+                .is_some_and(|m| m.role == MessageRole::Context)
+            {
+                self.messages.pop();
+            }
+        }
+        self.touch();
+        Some(text)
+    }
+
+    /// Remove the last user message and the replies to it, for regenerating
+    /// the last response. Returns the user's text.
+    pub fn pop_last_turn(&mut self) -> Option<String> {
+        let turns = self.user_turns();
+        self.truncate_at_turn(turns.checked_sub(1)?)
+    }
+
+    /// A new conversation that shares this one's transcript through the end
+    /// of `turn` (0-based): the user message and every reply to it. The
+    /// branch keeps the current collection scope and records where it came
+    /// from.
+    pub fn branch(&self, id: String, turn: usize) -> Option<Conversation> {
+        self.user_message_index(turn)?;
+        let end = self
+            .user_message_index(turn + 1)
+            .unwrap_or(self.messages.len());
+        // The summary only carries over when the branch keeps every turn
+        // it covers.
+        let (rolling_summary, compacted_turns) = if turn + 1 >= self.compacted_turns {
+            (self.rolling_summary.clone(), self.compacted_turns)
+        } else {
+            (None, 0)
+        };
+        let now = chrono::Utc::now().to_rfc3339();
+        Some(Conversation {
+            id,
+            title: format!("{} (branch)", self.title),
+            messages: self.messages[..end].to_vec(),
+            created_at: now.clone(),
+            updated_at: now,
+            collections: self.collections.clone(),
+            preset_id: self.preset_id.clone(),
+            parent_id: Some(self.id.clone()),
+            branch_turns: Some(turn + 1),
+            usage: ConversationUsage::default(),
+            rolling_summary,
+            compacted_turns,
+            pinned_document_ids: self.pinned_document_ids.clone(),
+            sampling: self.sampling,
+        })
+    }
+
     /// Generate title from first user message (truncated to 50 chars)
     pub fn generate_title(&mut self) {
         if let Some(first_user_msg) = self.messages.iter().find(|m| m.role == MessageRole::User) {
@@ -303,6 +554,7 @@ impl Conversation {
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ContentDelta {
     Text { text: String },
+    Thinking { thinking: String },
 }
 
 /// Events emitted during agent execution
@@ -315,18 +567,78 @@ pub enum AgentEvent {
     ContentBlockDelta { delta: ContentDelta },
     /// Current block streaming is complete
     ContentBlockStop,
+    /// The model started writing a tool call. Its arguments follow as
+    /// [`AgentEvent::ToolCallDelta`]s; the finished call arrives later as a
+    /// `ToolUse` block with the same `id`.
+    ToolCallStart { id: String, name: String },
+    /// More of a tool call's JSON arguments, as generated.
+    ToolCallDelta { id: String, arguments_delta: String },
+    /// A tool call that changes data is waiting for the user. Answer it
+    /// through `AppState::pending_confirmations` under `tool_use_id`.
+    ToolApprovalRequired {
+        tool_use_id: String,
+        tool_name: String,
+        prompt: String,
+    },
+    /// The model laid out or updated its plan for the turn (the `plan`
+    /// tool). Each event carries the whole plan.
+    Plan { steps: Vec<PlanStep> },
+    /// How the cited claims of the final answer held up against their
+    /// sources. Sent before `Done` when answer verification is on.
+    Verification { claims: Vec<verify::ClaimCheck> },
+    /// The model request failed for a passing reason (rate limit,
+    /// overloaded service) and is sent again after `delay_secs`.
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+        delay_secs: f64,
+        message: String,
+    },
     /// Agent turn is complete
     Done,
     /// An error occurred
     Error { message: String },
 }
 
-/// Maximum number of tool call iterations
-const MAX_ITERATIONS: usize = 100;
+/// One step of the agent's plan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanStep {
+    pub description: String,
+    #[serde(default)]
+    pub status: PlanStatus,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanStatus {
+    #[default]
+    Pending,
+    InProgress,
+    Done,
+    Skipped,
+}
+
+/// The user's answer to [`AgentEvent::ToolApprovalRequired`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolApproval {
+    Deny,
+    Allow,
+    /// Allow, and stop asking for this tool (persisted in `Settings`).
+    AlwaysAllow,
+}
+
+/// Tool calls from one model response that run at the same time.
+const MAX_PARALLEL_TOOLS: usize = 4;
+
+/// Times an answer cut off by the output limit is continued in one turn.
+const MAX_CONTINUATIONS: usize = 3;
 
 /// Run the agent loop with structured tool calling
 ///
 /// Uses the ChatProvider trait for LLM inference, allowing local or remote models.
+/// The turn is bounded by the [`crate::config::AgentLimits`] in `Settings`;
+/// hitting a limit ends it with an [`AgentEvent::Error`].
 pub async fn run_agent_loop(
     provider: &dyn ChatProvider,
     conversation: &mut Conversation,
@@ -343,20 +655,47 @@ pub async fn run_agent_loop(
         "Starting agent loop"
     );
     conversation.add_user_message(user_message);
+    let turn_start = conversation.messages.len();
 
     // Get tool definitions
-    let tools = get_tool_definitions();
+    let mut tools = get_tool_definitions();
+    if !provider.supports_images() {
+        tools.retain(|tool| tool.name != "view_page_image");
+    }
     debug!(tool_count = tools.len(), "Loaded tools");
 
-    for iteration in 0..MAX_ITERATIONS {
+    let settings = Settings::load(&ctx.state.config.settings_file);
+    let limits = settings.agent_limits;
+    let tool_timeout = Duration::from_secs(limits.tool_timeout_secs);
+    let turn_timeout = Duration::from_secs(limits.turn_timeout_secs);
+    // Pushed back by the time spent waiting on the user's approval.
+    let mut deadline = Instant::now() + turn_timeout;
+    let pinned = pinned_documents_context(ctx, provider.context_window()).await;
+    let prompts = PromptLibrary::load(&ctx.state.config.prompts_dir());
+    let global_sampling = ctx
+        .state
+        .models
+        .chat_config()
+        .await
+        .map(|config| config.sampling())
+        .unwrap_or_default();
+    let sampling = conversation.sampling.or(global_sampling);
+    let mut continuations = 0;
+    // The last response hit the output limit and is being continued.
+    let mut continuing = false;
+
+    for iteration in 0..limits.max_iterations {
         if cancel_token.is_cancelled() {
             info!(conversation_id = %conversation.id, "Agent loop cancelled");
             return Ok(());
         }
+        if Instant::now() >= deadline {
+            return turn_timed_out(conversation, limits.turn_timeout_secs, &event_tx).await;
+        }
 
         debug!(
             iteration = iteration + 1,
-            max_iterations = MAX_ITERATIONS,
+            max_iterations = limits.max_iterations,
             message_count = conversation.messages.len(),
             "Starting iteration"
         );
@@ -364,8 +703,23 @@ pub async fn run_agent_loop(
         // Create channel for provider events
         let (provider_tx, mut provider_rx) = mpsc::channel::<ProviderEvent>(100);
 
-        // Clone what we need for the provider call
-        let messages = conversation.messages.clone();
+        // Trim what we send to the model's context window
+        let mut messages =
+            context::request_messages(provider, &prompts, conversation, &cancel_token).await;
+        inject_memories(ctx, &mut messages);
+        if let Some(pinned) = &pinned {
+            append_to_system(&mut messages, pinned.clone());
+        }
+        // Only sent with the continuation request, not kept in the
+        // conversation.
+        if std::mem::take(&mut continuing) {
+            if let Ok(text) = prompts.render("continue-answer", &[]) {
+                messages.push(Message {
+                    role: MessageRole::User,
+                    content: vec![ContentBlock::Text { text }],
+                });
+            }
+        }
         let tools_clone = tools.clone();
         let cancel_clone = cancel_token.clone();
 
@@ -373,16 +727,48 @@ pub async fn run_agent_loop(
         let provider_handle = {
             // We need to handle the provider lifetime carefully
             // Since we can't move provider into the spawn, we'll run it inline
-            provider.stream_completion(&messages, &tools_clone, provider_tx, cancel_clone)
+            retry::stream_with_retry(
+                provider,
+                &messages,
+                &tools_clone,
+                &sampling,
+                provider_tx,
+                cancel_clone,
+            )
         };
 
         // Forward provider events to agent events while streaming
         let event_tx_clone = event_tx.clone();
         let forward_handle = tokio::spawn(async move {
             let mut text_started = false;
+            let mut thinking_started = false;
             while let Some(event) = provider_rx.recv().await {
                 match event {
+                    ProviderEvent::ThinkingDelta(thinking) => {
+                        if !thinking_started {
+                            let _ = event_tx_clone
+                                .send(AgentEvent::ContentBlockStart {
+                                    block: ContentBlock::Thinking {
+                                        thinking: String::new(),
+                                        signature: None,
+                                        redacted: Vec::new(),
+                                    },
+                                })
+                                .await;
+                            thinking_started = true;
+                        }
+                        let _ = event_tx_clone
+                            .send(AgentEvent::ContentBlockDelta {
+                                delta: ContentDelta::Thinking { thinking },
+                            })
+                            .await;
+                    }
                     ProviderEvent::TextDelta(text) => {
+                        // Reasoning comes first; the answer closes it.
+                        if thinking_started {
+                            let _ = event_tx_clone.send(AgentEvent::ContentBlockStop).await;
+                            thinking_started = false;
+                        }
                         if !text_started {
                             let _ = event_tx_clone
                                 .send(AgentEvent::ContentBlockStart {
@@ -399,17 +785,32 @@ pub async fn run_agent_loop(
                             })
                             .await;
                     }
-                    ProviderEvent::ToolCallStart { .. } => {
-                        // Tool calls are emitted after completion
+                    // Forwarded so the UI can show a call (e.g. a search
+                    // query) as it is typed. The complete call is emitted
+                    // as a ToolUse block once the response is finished.
+                    ProviderEvent::ToolCallStart { id, name } => {
+                        let _ = event_tx_clone
+                            .send(AgentEvent::ToolCallStart { id, name })
+                            .await;
                     }
-                    ProviderEvent::ToolCallDelta { .. } => {
-                        // Tool call deltas are accumulated by the provider
+                    ProviderEvent::ToolCallDelta {
+                        id,
+                        arguments_delta,
+                    } => {
+                        let _ = event_tx_clone
+                            .send(AgentEvent::ToolCallDelta {
+                                id,
+                                arguments_delta,
+                            })
+                            .await;
                     }
                     ProviderEvent::ToolCallComplete { .. } => {
                         // Will be processed after completion
                     }
+                    // Usage and stop reason come with the result.
+                    ProviderEvent::Finished { .. } => {}
                     ProviderEvent::Done => {
-                        if text_started {
+                        if text_started || thinking_started {
                             let _ = event_tx_clone.send(AgentEvent::ContentBlockStop).await;
                         }
                     }
@@ -418,14 +819,39 @@ pub async fn run_agent_loop(
                             .send(AgentEvent::Error { message: msg })
                             .await;
                     }
+                    ProviderEvent::Retrying {
+                        attempt,
+                        max_attempts,
+                        delay,
+                        reason,
+                    } => {
+                        let _ = event_tx_clone
+                            .send(AgentEvent::Retrying {
+                                attempt,
+                                max_attempts,
+                                delay_secs: delay.as_secs_f64(),
+                                message: reason,
+                            })
+                            .await;
+                    }
                 }
             }
             text_started
         });
 
-        // Wait for provider to complete
-        let result = provider_handle.await?;
+        // Wait for provider to complete. Dropping the request on timeout
+        // closes the event channel, which ends the forwarder.
+        let result = match tokio::time::timeout_at(deadline, provider_handle).await {
+            Ok(result) => result?,
+            Err(_) => {
+                let _ = forward_handle.await;
+                return turn_timed_out(conversation, limits.turn_timeout_secs, &event_tx).await;
+            }
+        };
         let _ = forward_handle.await;
+        conversation
+            .usage
+            .record(provider.provider_name(), provider.model_id(), &result.usage);
 
         debug!(
             text_len = result.text.len(),
@@ -435,6 +861,13 @@ pub async fn run_agent_loop(
 
         // Build content blocks from result
         let mut content_blocks = Vec::new();
+        if !result.thinking.is_empty() || !result.redacted_thinking.is_empty() {
+            content_blocks.push(ContentBlock::Thinking {
+                thinking: result.thinking,
+                signature: result.thinking_signature,
+                redacted: result.redacted_thinking,
+            });
+        }
         if !result.text.is_empty() {
             content_blocks.push(ContentBlock::Text { text: result.text });
         }
@@ -446,7 +879,9 @@ pub async fn run_agent_loop(
                 "Model requested tool calls"
             );
 
-            // Process each tool call
+            // Announce every call and settle approvals one at a time, so
+            // the user only ever sees one approval prompt.
+            let mut calls = Vec::with_capacity(result.tool_calls.len());
             for tc in &result.tool_calls {
                 info!(
                     tool_name = %tc.name,
@@ -469,14 +904,51 @@ pub async fn run_agent_loop(
                 let _ = event_tx.send(AgentEvent::ContentBlockStop).await;
                 content_blocks.push(tool_use_block);
 
-                // Execute tool
                 let tool_call = ToolCall {
                     id: tc.id.clone(),
                     name: tc.name.clone(),
                     arguments: tc.arguments.clone(),
                 };
-                let tool_result = execute_tool(&tool_call, ctx).await;
+                if let Some(steps) = tools::plan_steps(&tool_call) {
+                    let _ = event_tx.send(AgentEvent::Plan { steps }).await;
+                }
+                // Tools that change data wait for the user's approval unless
+                // they were always allowed.
+                let approved = match tools::confirmation_prompt(&tool_call) {
+                    Some(_) if is_always_allowed(ctx, &tool_call.name) => {
+                        debug!(tool_name = %tool_call.name, "Tool always allowed");
+                        true
+                    }
+                    Some(prompt) => {
+                        let asked = Instant::now();
+                        let approved =
+                            confirm_tool_call(ctx, &tool_call, prompt, &event_tx, &cancel_token)
+                                .await;
+                        deadline += asked.elapsed();
+                        approved
+                    }
+                    None => true,
+                };
+                calls.push((tool_call, approved));
+            }
+
+            // Run the calls concurrently; each result is emitted as soon as
+            // its tool finishes.
+            let cancel = &cancel_token;
+            let runs = calls.into_iter().map(|(tool_call, approved)| async move {
+                let tool_result = if approved {
+                    run_tool(&tool_call, ctx, tool_timeout, deadline, cancel).await
+                } else {
+                    ToolResult::error(
+                        &tool_call.id,
+                        "The user declined this action. Do not retry it unless they ask.",
+                    )
+                };
+                (tool_call, tool_result)
+            });
+            let mut finished = futures::stream::iter(runs).buffer_unordered(MAX_PARALLEL_TOOLS);
 
+            while let Some((tool_call, tool_result)) = finished.next().await {
                 if tool_result.is_error {
                     warn!(
                         tool_name = %tool_call.name,
@@ -492,8 +964,11 @@ pub async fn run_agent_loop(
                 }
 
                 // Emit ToolResult block
+                let citations = tool_result.citations;
+                let source_file = tool_result.source_file;
+                let page_image = tool_result.page_image;
                 let tool_result_block = ContentBlock::ToolResult {
-                    tool_use_id: tc.id.clone(),
+                    tool_use_id: tool_call.id.clone(),
                     content: tool_result.content,
                     is_error: tool_result.is_error,
                 };
@@ -504,6 +979,39 @@ pub async fn run_agent_loop(
                     .await;
                 let _ = event_tx.send(AgentEvent::ContentBlockStop).await;
                 content_blocks.push(tool_result_block);
+
+                // Record where the result came from, once per page.
+                for passage in citations {
+                    let cited = content_blocks.iter().any(|b| {
+                        matches!(b, ContentBlock::Citation { document_id, page, .. }
+                            if *document_id == passage.document_id && *page == passage.page)
+                    });
+                    if cited {
+                        continue;
+                    }
+                    let block = passage.into_block();
+                    let _ = event_tx
+                        .send(AgentEvent::ContentBlockStart {
+                            block: block.clone(),
+                        })
+                        .await;
+                    let _ = event_tx.send(AgentEvent::ContentBlockStop).await;
+                    content_blocks.push(block);
+                }
+
+                let attachments = source_file
+                    .map(|file| file.into_block())
+                    .into_iter()
+                    .chain(page_image.map(|image| image.into_block()));
+                for block in attachments {
+                    let _ = event_tx
+                        .send(AgentEvent::ContentBlockStart {
+                            block: block.clone(),
+                        })
+                        .await;
+                    let _ = event_tx.send(AgentEvent::ContentBlockStop).await;
+                    content_blocks.push(block);
+                }
             }
 
             // Store assistant message with all content blocks
@@ -514,6 +1022,23 @@ pub async fn run_agent_loop(
             continue;
         }
 
+        // Cut off by the output limit: keep what was written and ask for
+        // the rest.
+        if result.stop_reason == StopReason::Length
+            && !content_blocks.is_empty()
+            && continuations < MAX_CONTINUATIONS
+        {
+            continuations += 1;
+            info!(
+                conversation_id = %conversation.id,
+                continuations,
+                "Response hit the output limit, continuing"
+            );
+            conversation.add_assistant_message(content_blocks);
+            continuing = true;
+            continue;
+        }
+
         // No tool calls - store and we're done
         info!(
             conversation_id = %conversation.id,
@@ -529,6 +1054,9 @@ pub async fn run_agent_loop(
         }
 
         conversation.add_assistant_message(content_blocks);
+        if settings.verify_answers {
+            verify_turn(conversation, turn_start, ctx, &event_tx).await;
+        }
         let _ = event_tx.send(AgentEvent::Done).await;
         return Ok(());
     }
@@ -536,22 +1064,277 @@ pub async fn run_agent_loop(
     // Max iterations reached
     warn!(
         conversation_id = %conversation.id,
-        max_iterations = MAX_ITERATIONS,
+        max_iterations = limits.max_iterations,
         "Agent loop reached maximum iterations"
     );
     let _ = event_tx
         .send(AgentEvent::Error {
-            message: "Maximum iterations reached".to_string(),
+            message: format!(
+                "Stopped after {} steps without a final answer. Ask a narrower question, \
+                 or raise the step limit in Settings.",
+                limits.max_iterations
+            ),
         })
         .await;
 
     Ok(())
 }
 
+/// Execute an approved tool call. Long-running tools (summarization) stop
+/// with the loop, and none may outlast its timeout or the turn's deadline.
+async fn run_tool(
+    tool_call: &ToolCall,
+    ctx: &AgentContext,
+    tool_timeout: Duration,
+    turn_deadline: Instant,
+    cancel_token: &CancellationToken,
+) -> ToolResult {
+    let started = Instant::now();
+    let tool_deadline = (started + tool_timeout).min(turn_deadline);
+    let ctx = AgentContext {
+        cancel: cancel_token.clone(),
+        ..ctx.clone()
+    };
+    tokio::select! {
+        result = tokio::time::timeout_at(tool_deadline, execute_tool(tool_call, &ctx)) => {
+            result.unwrap_or_else(|_| {
+                warn!(tool_name = %tool_call.name, "Tool timed out");
+                ToolResult::error(
+                    &tool_call.id,
+                    format!(
+                        "Timed out after {} seconds. Try a narrower request.",
+                        (tool_deadline - started).as_secs()
+                    ),
+                )
+            })
+        }
+        _ = cancel_token.cancelled() => ToolResult::error(&tool_call.id, "Cancelled by the user."),
+    }
+}
+
+/// Check the turn's final answer against the passages cited during the
+/// turn and report the result. Turns without citations aren't checked.
+async fn verify_turn(
+    conversation: &Conversation,
+    turn_start: usize,
+    ctx: &AgentContext,
+    event_tx: &mpsc::Sender<AgentEvent>,
+) {
+    let turn = &conversation.messages[turn_start..];
+    let citations: Vec<ContentBlock> = turn
+        .iter()
+        .flat_map(|m| &m.content)
+        .filter(|b| matches!(b, ContentBlock::Citation { .. }))
+        .cloned()
+        .collect();
+    let Some(answer) = turn.last().map(Message::text) else {
+        return;
+    };
+    if citations.is_empty() || answer.trim().is_empty() {
+        return;
+    }
+
+    let claims = verify::verify_answer(&ctx.state, &answer, &citations).await;
+    info!(
+        checked = claims.len(),
+        unsupported = claims.iter().filter(|c| !c.supported).count(),
+        "Verified answer"
+    );
+    let _ = event_tx.send(AgentEvent::Verification { claims }).await;
+}
+
+/// End a turn that ran past its time limit.
+async fn turn_timed_out(
+    conversation: &Conversation,
+    turn_timeout_secs: u64,
+    event_tx: &mpsc::Sender<AgentEvent>,
+) -> Result<()> {
+    warn!(
+        conversation_id = %conversation.id,
+        turn_timeout_secs,
+        "Agent loop reached its time limit"
+    );
+    let _ = event_tx
+        .send(AgentEvent::Error {
+            message: format!(
+                "Stopped after {} seconds without a final answer. Ask a narrower question, \
+                 or raise the time limit in Settings.",
+                turn_timeout_secs
+            ),
+        })
+        .await;
+    Ok(())
+}
+
+/// Share of the context window, in percent, the excerpts of pinned
+/// documents may use together.
+const PINNED_SHARE_PERCENT: usize = 25;
+
+/// Opening pages of the pinned documents, for the system message. Each
+/// document gets an equal share of the budget; the model can read further
+/// with `read_pages`. None when nothing is pinned or none could be read.
+async fn pinned_documents_context(ctx: &AgentContext, context_window: usize) -> Option<String> {
+    if ctx.pinned_document_ids.is_empty() {
+        return None;
+    }
+    let budget = context_window * summarize::CHARS_PER_TOKEN * PINNED_SHARE_PERCENT
+        / 100
+        / ctx.pinned_document_ids.len();
+
+    let mut sections = Vec::new();
+    for doc_id in &ctx.pinned_document_ids {
+        let (metadata, text) = match tools::load_document_text(ctx, doc_id).await {
+            Ok(Some((_, metadata, text))) => (metadata, text),
+            Ok(None) => {
+                warn!(document_id = %doc_id, "Pinned document not found");
+                continue;
+            }
+            Err(e) => {
+                warn!(document_id = %doc_id, error = %e, "Failed to load pinned document");
+                continue;
+            }
+        };
+        let spans = tools::page_spans(&text, &metadata.page_boundaries);
+        let page_count = spans.len();
+        let mut excerpt = String::new();
+        let mut shown = 0;
+        for (i, span) in spans.into_iter().enumerate() {
+            let page = text[span].trim();
+            if page.is_empty() {
+                continue;
+            }
+            if !excerpt.is_empty() && excerpt.len() + page.len() > budget {
+                break;
+            }
+            let mut end = page.len().min(budget);
+            while !page.is_char_boundary(end) {
+                end -= 1;
+            }
+            excerpt.push_str(&format!("[Page {}]\n{}\n\n", i + 1, &page[..end]));
+            shown = i + 1;
+        }
+        let more = if shown < page_count {
+            format!("(Excerpt ends at page {} of {}.)", shown, page_count)
+        } else {
+            String::new()
+        };
+        sections.push(format!(
+            "## {} (ID {}, {} pages)\n{}{}",
+            metadata.name, doc_id, page_count, excerpt, more
+        ));
+    }
+    if sections.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "\n\nPinned documents — the user wants these discussed. Prefer them when \
+         answering and read further with `read_pages`.\n\n{}",
+        sections.join("\n\n")
+    ))
+}
+
+/// Append `text` to the system message of an outgoing request.
+fn append_to_system(messages: &mut [Message], text: String) {
+    if let Some(system) = messages.iter_mut().find(|m| m.role == MessageRole::System) {
+        system.content.push(ContentBlock::Text { text });
+    }
+}
+
+/// Most remembered facts added to a request, newest kept.
+const MAX_INJECTED_MEMORIES: usize = 50;
+
+/// Append what was remembered about the active collections (see the
+/// `remember` tool) to the system message of an outgoing request. Read
+/// fresh each time, so a fact saved mid-conversation applies right away.
+fn inject_memories(ctx: &AgentContext, messages: &mut [Message]) {
+    let Some(collections) = ctx.collections.as_deref() else {
+        return;
+    };
+    let memory_dir = ctx.state.config.memory_dir();
+    let mut lines = Vec::new();
+    for collection in collections {
+        match crate::memory::load_memories(&memory_dir, &collection.id) {
+            Ok(entries) => lines.extend(
+                entries
+                    .into_iter()
+                    .map(|e| format!("- ({}) {}", collection.name, e.text)),
+            ),
+            Err(e) => warn!(collection_id = %collection.id, error = %e, "Failed to load memories"),
+        }
+    }
+    if lines.is_empty() {
+        return;
+    }
+    let skip = lines.len().saturating_sub(MAX_INJECTED_MEMORIES);
+
+    append_to_system(
+        messages,
+        format!(
+            "\n\nRemembered from earlier conversations:\n{}",
+            lines[skip..].join("\n")
+        ),
+    );
+}
+
+/// Ask the user to approve a tool call and wait for the answer. Cancelling
+/// the generation counts as a denial.
+async fn confirm_tool_call(
+    ctx: &AgentContext,
+    tool_call: &ToolCall,
+    prompt: String,
+    event_tx: &mpsc::Sender<AgentEvent>,
+    cancel_token: &CancellationToken,
+) -> bool {
+    let (answer_tx, answer_rx) = oneshot::channel();
+    ctx.state
+        .pending_confirmations
+        .write()
+        .await
+        .insert(tool_call.id.clone(), answer_tx);
+
+    let _ = event_tx
+        .send(AgentEvent::ToolApprovalRequired {
+            tool_use_id: tool_call.id.clone(),
+            tool_name: tool_call.name.clone(),
+            prompt,
+        })
+        .await;
+
+    let approval = tokio::select! {
+        answer = answer_rx => answer.unwrap_or(ToolApproval::Deny),
+        _ = cancel_token.cancelled() => ToolApproval::Deny,
+    };
+    ctx.state
+        .pending_confirmations
+        .write()
+        .await
+        .remove(&tool_call.id);
+
+    info!(tool_name = %tool_call.name, ?approval, "Tool call confirmation answered");
+    if approval == ToolApproval::AlwaysAllow {
+        let mut settings = Settings::load(&ctx.state.config.settings_file);
+        if !settings.always_allowed_tools.contains(&tool_call.name) {
+            settings.always_allowed_tools.push(tool_call.name.clone());
+            if let Err(e) = settings.save(&ctx.state.config.settings_file) {
+                warn!(error = %e, "Failed to save always-allowed tool");
+            }
+        }
+    }
+    approval != ToolApproval::Deny
+}
+
+/// Whether the user chose to always allow `tool_name`.
+fn is_always_allowed(ctx: &AgentContext, tool_name: &str) -> bool {
+    Settings::load(&ctx.state.config.settings_file)
+        .always_allowed_tools
+        .iter()
+        .any(|t| t == tool_name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::provider::{CompletedToolCall, CompletionResult, ToolDefinition};
+    use crate::provider::{CompletedToolCall, CompletionResult, MockProvider};
 
     // ==================== CollectionInfo Tests ====================
 
@@ -922,6 +1705,86 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_conversation_with_preset_layers_on_base_prompt() {
+        let preset = PromptPreset {
+            id: "foia".to_string(),
+            name: "FOIA analyst".to_string(),
+            instructions: "Flag redactions and exemption codes.".to_string(),
+        };
+        let conv = Conversation::with_preset("conv_1".to_string(), &preset);
+
+        assert_eq!(conv.preset_id.as_deref(), Some("foia"));
+        let text = conv.messages[0].text();
+        assert!(text.starts_with(BASE_SYSTEM_PROMPT));
+        assert!(text.ends_with(
+            "Additional instructions (FOIA analyst):\nFlag redactions and exemption codes."
+        ));
+
+        // A blank preset leaves the base prompt alone.
+        let blank = PromptPreset {
+            instructions: "  ".to_string(),
+            ..preset
+        };
+        assert_eq!(system_prompt_with_preset(Some(&blank)), BASE_SYSTEM_PROMPT);
+    }
+
+    fn three_turn_conversation() -> Conversation {
+        let mut conv = Conversation::new("conv_1".to_string());
+        conv.set_collections(vec![CollectionInfo {
+            id: "col_1".to_string(),
+            name: "Leaks".to_string(),
+            document_count: 0,
+            total_pages: 0,
+            created_at: None,
+        }]);
+        for turn in 0..3 {
+            conv.add_user_message(format!("question {}", turn));
+            conv.add_assistant_message(vec![ContentBlock::Text {
+                text: format!("answer {}", turn),
+            }]);
+        }
+        conv
+    }
+
+    #[test]
+    fn test_conversation_pop_last_turn() {
+        let mut conv = three_turn_conversation();
+        assert_eq!(conv.pop_last_turn().as_deref(), Some("question 2"));
+        assert_eq!(conv.user_turns(), 2);
+        assert_eq!(conv.messages.last().unwrap().text(), "answer 1");
+
+        // Cutting the first turn drops its scope prelude too, so re-sending
+        // doesn't duplicate it.
+        assert_eq!(conv.truncate_at_turn(0).as_deref(), Some("question 0"));
+        assert_eq!(conv.messages.len(), 1);
+        conv.add_user_message("question 0".to_string());
+        let context_count = conv
+            .messages
+            .iter()
+            .filter(|m| m.role == MessageRole::Context)
+            .count();
+        assert_eq!(context_count, 1);
+
+        assert!(Conversation::new("empty".to_string())
+            .pop_last_turn()
+            .is_none());
+    }
+
+    #[test]
+    fn test_conversation_branch_shares_prefix() {
+        let conv = three_turn_conversation();
+        let branch = conv.branch("conv_2".to_string(), 1).unwrap();
+
+        assert_eq!(branch.id, "conv_2");
+        assert_eq!(branch.parent_id.as_deref(), Some("conv_1"));
+        assert_eq!(branch.branch_turns, Some(2));
+        assert_eq!(branch.user_turns(), 2);
+        assert_eq!(branch.messages.last().unwrap().text(), "answer 1");
+        assert_eq!(branch.collections.len(), 1);
+        assert!(conv.branch("conv_3".to_string(), 3).is_none());
+    }
+
     #[test]
     fn test_conversation_set_collections_before_first_message_is_silent() {
         let mut conv = Conversation::new("conv_1".to_string());
@@ -1309,64 +2172,21 @@ mod tests {
         assert!(json.contains("Something went wrong"));
     }
 
-    // ==================== run_agent_loop Tests ====================
-
-    /// Mock provider for testing the agent loop
-    struct MockProvider {
-        responses: std::sync::Mutex<Vec<CompletionResult>>,
-    }
-
-    impl MockProvider {
-        fn new(responses: Vec<CompletionResult>) -> Self {
-            Self {
-                responses: std::sync::Mutex::new(responses),
-            }
-        }
-    }
-
-    impl crate::provider::Provider for MockProvider {
-        fn provider_name(&self) -> &'static str {
-            "mock"
-        }
-
-        fn model_id(&self) -> &str {
-            "mock-model"
-        }
-    }
-
-    #[async_trait::async_trait]
-    impl ChatProvider for MockProvider {
-        async fn stream_completion(
-            &self,
-            _messages: &[Message],
-            _tools: &[ToolDefinition],
-            event_tx: mpsc::Sender<ProviderEvent>,
-            _cancel_token: CancellationToken,
-        ) -> Result<CompletionResult> {
-            // Get result while holding lock, then release before await
-            let result = {
-                let mut responses = self.responses.lock().unwrap();
-                if responses.is_empty() {
-                    CompletionResult::default()
-                } else {
-                    responses.remove(0)
-                }
-            };
-
-            // Stream text if present
-            if !result.text.is_empty() {
-                let _ = event_tx
-                    .send(ProviderEvent::TextDelta(result.text.clone()))
-                    .await;
-            }
-            let _ = event_tx.send(ProviderEvent::Done).await;
+    #[test]
+    fn test_agent_event_tool_call_delta_serialization() {
+        let event = AgentEvent::ToolCallDelta {
+            id: "call_1".to_string(),
+            arguments_delta: "{\"query\": \"ac".to_string(),
+        };
+        let json: serde_json::Value = serde_json::to_value(&event).unwrap();
 
-            Ok(result)
-        }
+        assert_eq!(json["type"], "tool_call_delta");
+        assert_eq!(json["data"]["id"], "call_1");
+        assert_eq!(json["data"]["arguments_delta"], "{\"query\": \"ac");
     }
 
-    #[tokio::test]
-    async fn test_run_agent_loop_simple_response() {
+    /// App state in a temporary directory, kept until the directory drops.
+    async fn test_state() -> (tempfile::TempDir, crate::AppState) {
         let temp_dir = tempfile::tempdir().unwrap();
         let config = crate::Config {
             data_dir: temp_dir.path().to_path_buf(),
@@ -1377,19 +2197,25 @@ mod tests {
         };
         std::fs::create_dir_all(&config.iroh_dir).unwrap();
         std::fs::create_dir_all(&config.search_dir).unwrap();
-        let (state, _progress_rx) = crate::AppState::new(config).await.unwrap();
+        let state = crate::AppState::new(config).await.unwrap();
+        (temp_dir, state)
+    }
 
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+    // ==================== run_agent_loop Tests ====================
+
+    #[tokio::test]
+    async fn test_run_agent_loop_simple_response() {
+        let (_temp_dir, state) = test_state().await;
+
+        let ctx = AgentContext::new(state, None);
 
         let provider = MockProvider::new(vec![CompletionResult {
             text: "Hello! I can help with that.".to_string(),
-            tool_calls: vec![],
+            ..CompletionResult::default()
         }]);
 
         let mut conversation = Conversation::new("test_conv".to_string());
+        conversation.sampling.temperature = Some(0.1);
         let (event_tx, mut event_rx) = mpsc::channel(100);
         let cancel_token = CancellationToken::new();
 
@@ -1407,6 +2233,9 @@ mod tests {
         // Check conversation was updated
         assert_eq!(conversation.messages.len(), 3); // system + user + assistant
 
+        // The conversation's sampling reaches the provider.
+        assert_eq!(provider.sampling()[0].temperature, Some(0.1));
+
         // Check events were emitted
         let mut events = Vec::new();
         while let Ok(event) = event_rx.try_recv() {
@@ -1418,23 +2247,133 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_run_agent_loop_cancellation() {
-        let temp_dir = tempfile::tempdir().unwrap();
-        let config = crate::Config {
-            data_dir: temp_dir.path().to_path_buf(),
-            iroh_dir: temp_dir.path().join("iroh"),
-            search_dir: temp_dir.path().join("search"),
-            settings_file: temp_dir.path().join("settings.json"),
-            conversations_dir: temp_dir.path().join("conversations"),
-        };
-        std::fs::create_dir_all(&config.iroh_dir).unwrap();
-        std::fs::create_dir_all(&config.search_dir).unwrap();
-        let (state, _progress_rx) = crate::AppState::new(config).await.unwrap();
+    async fn test_run_agent_loop_keeps_thinking() {
+        let (_temp_dir, state) = test_state().await;
+
+        let ctx = AgentContext::new(state, None);
+        let provider = MockProvider::new(vec![CompletionResult {
+            text: "The fee was $1,200.".to_string(),
+            thinking: "The invoice on page 4 gives the fee.".to_string(),
+            ..CompletionResult::default()
+        }]);
+
+        let mut conversation = Conversation::new("test_conv".to_string());
+        let (event_tx, mut event_rx) = mpsc::channel(100);
+        run_agent_loop(
+            &provider,
+            &mut conversation,
+            "What was the fee?".to_string(),
+            &ctx,
+            event_tx,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        // Reasoning is kept ahead of the answer.
+        let answer = &conversation.messages.last().unwrap().content;
+        assert!(matches!(
+            &answer[0],
+            ContentBlock::Thinking { thinking, .. } if thinking.contains("page 4")
+        ));
+        assert!(matches!(&answer[1], ContentBlock::Text { .. }));
+
+        // The thinking block is closed before the text block opens.
+        let mut events = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            events.push(event);
+        }
+        let starts: Vec<&ContentBlock> = events
+            .iter()
+            .filter_map(|e| match e {
+                AgentEvent::ContentBlockStart { block } => Some(block),
+                _ => None,
+            })
+            .collect();
+        assert!(matches!(starts[0], ContentBlock::Thinking { .. }));
+        assert!(matches!(starts[1], ContentBlock::Text { .. }));
+        let stops = events
+            .iter()
+            .filter(|e| matches!(e, AgentEvent::ContentBlockStop))
+            .count();
+        assert_eq!(stops, 2);
+    }
 
-        let ctx = AgentContext {
+    #[tokio::test]
+    async fn test_run_agent_loop_continues_truncated_answer() {
+        let (_temp_dir, state) = test_state().await;
+
+        let ctx = AgentContext::new(state, None);
+        let provider = MockProvider::new(vec![
+            CompletionResult {
+                text: "The lease runs until".to_string(),
+                stop_reason: StopReason::Length,
+                ..CompletionResult::default()
+            },
+            CompletionResult {
+                text: " March 2027.".to_string(),
+                ..CompletionResult::default()
+            },
+        ]);
+
+        let mut conversation = Conversation::new("test_conv".to_string());
+        let (event_tx, _event_rx) = mpsc::channel(100);
+        run_agent_loop(
+            &provider,
+            &mut conversation,
+            "When does the lease end?".to_string(),
+            &ctx,
+            event_tx,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        // Both parts are kept; the request to continue is not.
+        let answer: Vec<String> = conversation.messages[2..]
+            .iter()
+            .map(|m| {
+                assert_eq!(m.role, MessageRole::Assistant);
+                m.text()
+            })
+            .collect();
+        assert_eq!(answer, ["The lease runs until", " March 2027."]);
+        assert_eq!(provider.sampling().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_inject_memories() {
+        let (_temp_dir, state) = test_state().await;
+        crate::memory::add_memory(
+            &state.config.memory_dir(),
+            "col1",
+            "The audit is in Q3.pdf.",
+        )
+        .unwrap();
+
+        let ctx = AgentContext::new(
             state,
-            collections: None,
-        };
+            Some(vec![CollectionInfo {
+                id: "col1".to_string(),
+                name: "Leaks".to_string(),
+                document_count: 0,
+                total_pages: 0,
+                created_at: None,
+            }]),
+        );
+        let mut messages = Conversation::new("c".to_string()).messages;
+        inject_memories(&ctx, &mut messages);
+
+        assert!(messages[0].text().ends_with(
+            "Remembered from earlier conversations:\n- (Leaks) The audit is in Q3.pdf."
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run_agent_loop_cancellation() {
+        let (_temp_dir, state) = test_state().await;
+
+        let ctx = AgentContext::new(state, None);
 
         let provider = MockProvider::new(vec![]);
         let mut conversation = Conversation::new("test_conv".to_string());
@@ -1463,36 +2402,31 @@ mod tests {
 
     #[tokio::test]
     async fn test_run_agent_loop_with_tool_call() {
-        let temp_dir = tempfile::tempdir().unwrap();
-        let config = crate::Config {
-            data_dir: temp_dir.path().to_path_buf(),
-            iroh_dir: temp_dir.path().join("iroh"),
-            search_dir: temp_dir.path().join("search"),
-            settings_file: temp_dir.path().join("settings.json"),
-            conversations_dir: temp_dir.path().join("conversations"),
-        };
-        std::fs::create_dir_all(&config.iroh_dir).unwrap();
-        std::fs::create_dir_all(&config.search_dir).unwrap();
-        let (state, _progress_rx) = crate::AppState::new(config).await.unwrap();
+        let (_temp_dir, state) = test_state().await;
 
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let ctx = AgentContext::new(state, None);
 
         // First response: tool call, second response: final text
         let provider = MockProvider::new(vec![
             CompletionResult {
-                text: String::new(),
+                usage: TokenUsage {
+                    input_tokens: 100,
+                    output_tokens: 20,
+                },
                 tool_calls: vec![CompletedToolCall {
                     id: "call_1".to_string(),
                     name: "search".to_string(),
                     arguments: serde_json::json!({"query": "test"}),
                 }],
+                ..CompletionResult::default()
             },
             CompletionResult {
                 text: "Based on my search, I found no results.".to_string(),
-                tool_calls: vec![],
+                usage: TokenUsage {
+                    input_tokens: 150,
+                    output_tokens: 30,
+                },
+                ..CompletionResult::default()
             },
         ]);
 
@@ -1529,11 +2463,371 @@ mod tests {
         assert!(has_tool_use, "Should have a ToolUse block");
         assert!(has_tool_result, "Should have a ToolResult block");
 
+        // Usage adds up across iterations; the mock model has no price.
+        assert_eq!(conversation.usage.input_tokens, 250);
+        assert_eq!(conversation.usage.output_tokens, 50);
+        assert_eq!(conversation.usage.estimated_cost_usd, 0.0);
+
         // Collect events
         let mut events = Vec::new();
         while let Ok(event) = event_rx.try_recv() {
             events.push(event);
         }
         assert!(events.iter().any(|e| matches!(e, AgentEvent::Done)));
+
+        // The call streams in before the finished ToolUse block.
+        let start = events.iter().position(|e| {
+            matches!(e, AgentEvent::ToolCallStart { id, name } if id == "call_1" && name == "search")
+        });
+        let delta = events.iter().position(|e| {
+            matches!(e, AgentEvent::ToolCallDelta { arguments_delta, .. } if arguments_delta.contains("test"))
+        });
+        let tool_use = events.iter().position(|e| {
+            matches!(
+                e,
+                AgentEvent::ContentBlockStart {
+                    block: ContentBlock::ToolUse { .. }
+                }
+            )
+        });
+        let (start, delta, tool_use) = (start.unwrap(), delta.unwrap(), tool_use.unwrap());
+        assert!(start < delta && delta < tool_use);
+    }
+
+    #[tokio::test]
+    async fn test_run_agent_loop_runs_tool_calls_together() {
+        let (_temp_dir, state) = test_state().await;
+
+        let ctx = AgentContext::new(state, None);
+
+        let search = |id: &str, query: &str| CompletedToolCall {
+            id: id.to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({ "query": query }),
+        };
+        let provider = MockProvider::new(vec![
+            CompletionResult {
+                tool_calls: vec![
+                    search("call_1", "invoices"),
+                    search("call_2", "contracts"),
+                    search("call_3", "emails"),
+                ],
+                ..CompletionResult::default()
+            },
+            CompletionResult {
+                text: "Nothing found.".to_string(),
+                ..CompletionResult::default()
+            },
+        ]);
+
+        let mut conversation = Conversation::new("test_conv".to_string());
+        let (event_tx, _event_rx) = mpsc::channel(100);
+
+        run_agent_loop(
+            &provider,
+            &mut conversation,
+            "Search everything".to_string(),
+            &ctx,
+            event_tx,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        // Every call is announced before any result, and each gets one.
+        let blocks = &conversation.messages[2].content;
+        assert!(blocks[..3]
+            .iter()
+            .all(|b| matches!(b, ContentBlock::ToolUse { .. })));
+        let mut answered: Vec<&str> = blocks
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::ToolResult { tool_use_id, .. } => Some(tool_use_id.as_str()),
+                _ => None,
+            })
+            .collect();
+        answered.sort();
+        assert_eq!(answered, vec!["call_1", "call_2", "call_3"]);
+    }
+
+    #[tokio::test]
+    async fn test_run_agent_loop_stops_at_iteration_limit() {
+        let (_temp_dir, state) = test_state().await;
+        let settings = Settings {
+            agent_limits: crate::config::AgentLimits {
+                max_iterations: 2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        settings.save(&state.config.settings_file).unwrap();
+
+        let ctx = AgentContext::new(state, None);
+
+        // A model that never stops planning.
+        let planning = || CompletionResult {
+            tool_calls: vec![CompletedToolCall {
+                id: "call_plan".to_string(),
+                name: "plan".to_string(),
+                arguments: serde_json::json!({"steps": [{"description": "Search"}]}),
+            }],
+            ..CompletionResult::default()
+        };
+        let provider = MockProvider::new(vec![planning(), planning(), planning()]);
+
+        let mut conversation = Conversation::new("test_conv".to_string());
+        let (event_tx, mut event_rx) = mpsc::channel(100);
+
+        run_agent_loop(
+            &provider,
+            &mut conversation,
+            "Keep going".to_string(),
+            &ctx,
+            event_tx,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            events.push(event);
+        }
+        assert!(!events.iter().any(|e| matches!(e, AgentEvent::Done)));
+        assert!(events.iter().any(
+            |e| matches!(e, AgentEvent::Error { message } if message.starts_with("Stopped after 2 steps"))
+        ));
+        assert_eq!(
+            events
+                .iter()
+                .filter(|e| matches!(e, AgentEvent::Plan { .. }))
+                .count(),
+            2
+        );
+        // One model call was left unused.
+        assert_eq!(provider.responses.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_agent_loop_records_citations() {
+        let (_temp_dir, state) = test_state().await;
+
+        let chunks = ["Budget overrun flagged in March.", "Budget approved."]
+            .iter()
+            .enumerate()
+            .map(|(i, content)| crate::search::ChunkToIndex {
+                id: format!("doc1_chunk_{}", i),
+                parent_id: "doc1".to_string(),
+                parent_name: "Audit.pdf".to_string(),
+                chunk_index: i,
+                content: content.to_string(),
+                collection_id: "col".to_string(),
+                page_count: 9,
+                start_page: 4,
+                end_page: 4,
+                vector: None,
+                embedder: None,
+            })
+            .collect();
+        crate::search::index_chunks_batch(
+            &state.search,
+            &milli::update::IndexerConfig::default(),
+            chunks,
+        )
+        .unwrap();
+
+        let ctx = AgentContext::new(state, None);
+        let provider = MockProvider::new(vec![
+            CompletionResult {
+                tool_calls: vec![CompletedToolCall {
+                    id: "call_1".to_string(),
+                    name: "search".to_string(),
+                    arguments: serde_json::json!({"query": "budget"}),
+                }],
+                ..CompletionResult::default()
+            },
+            CompletionResult {
+                text: "The audit flags an overrun.".to_string(),
+                ..CompletionResult::default()
+            },
+        ]);
+
+        let mut conversation = Conversation::new("test_conv".to_string());
+        let (event_tx, _event_rx) = mpsc::channel(100);
+        run_agent_loop(
+            &provider,
+            &mut conversation,
+            "What does the audit say about the budget?".to_string(),
+            &ctx,
+            event_tx,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        // Both hits are on page 4, so one citation.
+        let citations: Vec<_> = conversation
+            .messages
+            .iter()
+            .flat_map(|m| &m.content)
+            .filter_map(|b| match b {
+                ContentBlock::Citation {
+                    document_id, page, ..
+                } => Some((document_id.as_str(), *page)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(citations, vec![("doc1", Some(4))]);
+    }
+
+    #[tokio::test]
+    async fn test_run_agent_loop_declined_confirmation() {
+        let (_temp_dir, state) = test_state().await;
+
+        let ctx = AgentContext::new(state.clone(), None);
+
+        let provider = MockProvider::new(vec![
+            CompletionResult {
+                tool_calls: vec![CompletedToolCall {
+                    id: "call_tag".to_string(),
+                    name: "tag_document".to_string(),
+                    arguments: serde_json::json!({
+                        "document_ids": ["doc_1"],
+                        "add_tags": ["acme"]
+                    }),
+                }],
+                ..CompletionResult::default()
+            },
+            CompletionResult {
+                text: "Okay, I left the tags alone.".to_string(),
+                ..CompletionResult::default()
+            },
+        ]);
+
+        let mut conversation = Conversation::new("test_conv".to_string());
+        let (event_tx, mut event_rx) = mpsc::channel(100);
+
+        // Play the user: deny the first confirmation request.
+        let answerer = tokio::spawn(async move {
+            while let Some(event) = event_rx.recv().await {
+                if let AgentEvent::ToolApprovalRequired {
+                    tool_use_id,
+                    prompt,
+                    ..
+                } = event
+                {
+                    assert!(prompt.contains("acme"));
+                    let answer_tx = state
+                        .pending_confirmations
+                        .write()
+                        .await
+                        .remove(&tool_use_id)
+                        .unwrap();
+                    answer_tx.send(ToolApproval::Deny).unwrap();
+                    return true;
+                }
+            }
+            false
+        });
+
+        run_agent_loop(
+            &provider,
+            &mut conversation,
+            "Tag the Acme documents".to_string(),
+            &ctx,
+            event_tx,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert!(answerer.await.unwrap(), "confirmation was requested");
+        let declined = conversation.messages.iter().any(|m| {
+            m.content.iter().any(|b| {
+                matches!(
+                    b,
+                    ContentBlock::ToolResult { content, is_error: true, .. }
+                        if content.contains("declined")
+                )
+            })
+        });
+        assert!(declined, "declined call should not run");
+    }
+
+    #[tokio::test]
+    async fn test_run_agent_loop_always_allow_persists() {
+        let (_temp_dir, state) = test_state().await;
+
+        let ctx = AgentContext::new(state.clone(), None);
+
+        let tag_call = |id: &str| CompletionResult {
+            tool_calls: vec![CompletedToolCall {
+                id: id.to_string(),
+                name: "tag_document".to_string(),
+                arguments: serde_json::json!({
+                    "document_ids": ["doc_1"],
+                    "add_tags": ["acme"]
+                }),
+            }],
+            ..CompletionResult::default()
+        };
+        let done = || CompletionResult {
+            text: "Done.".to_string(),
+            ..CompletionResult::default()
+        };
+        let provider =
+            MockProvider::new(vec![tag_call("call_1"), done(), tag_call("call_2"), done()]);
+        let mut conversation = Conversation::new("test_conv".to_string());
+
+        // First turn: the user answers "always allow".
+        let (event_tx, mut event_rx) = mpsc::channel(100);
+        let answer_state = state.clone();
+        let answerer = tokio::spawn(async move {
+            let mut asked = 0;
+            while let Some(event) = event_rx.recv().await {
+                if let AgentEvent::ToolApprovalRequired { tool_use_id, .. } = event {
+                    asked += 1;
+                    let answer_tx = answer_state
+                        .pending_confirmations
+                        .write()
+                        .await
+                        .remove(&tool_use_id)
+                        .unwrap();
+                    answer_tx.send(ToolApproval::AlwaysAllow).unwrap();
+                }
+            }
+            asked
+        });
+        run_agent_loop(
+            &provider,
+            &mut conversation,
+            "Tag the Acme documents".to_string(),
+            &ctx,
+            event_tx,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(answerer.await.unwrap(), 1);
+        assert_eq!(
+            Settings::load(&state.config.settings_file).always_allowed_tools,
+            vec!["tag_document".to_string()]
+        );
+
+        // Second turn: the tool runs without asking.
+        let (event_tx, mut event_rx) = mpsc::channel(100);
+        run_agent_loop(
+            &provider,
+            &mut conversation,
+            "Tag them again".to_string(),
+            &ctx,
+            event_tx,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        while let Ok(event) = event_rx.try_recv() {
+            assert!(!matches!(event, AgentEvent::ToolApprovalRequired { .. }));
+        }
     }
 }
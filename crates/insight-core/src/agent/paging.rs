@@ -0,0 +1,202 @@
+//! Paging of long tool results.
+//!
+//! A tool with more results than fit in one reply (a broad search, a big
+//! collection's document list) returns the first page and a continuation
+//! token. The `fetch_more` tool trades the token for the next page, so the
+//! agent can work through a large result set deliberately instead of
+//! seeing it cut off. The remaining pages are kept in memory; the oldest
+//! result sets are dropped once [`MAX_RESULT_SETS`] are held, and a token
+//! that is gone asks the agent to run the tool again.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use super::tools::{SourcePassage, ToolResult};
+
+/// Result sets with pages left that are kept for `fetch_more`.
+const MAX_RESULT_SETS: usize = 32;
+
+/// One result, with the passages it draws on.
+#[derive(Debug, Clone)]
+pub struct PageEntry {
+    pub text: String,
+    pub citations: Vec<SourcePassage>,
+}
+
+impl PageEntry {
+    pub fn text(text: String) -> Self {
+        Self {
+            text,
+            citations: Vec::new(),
+        }
+    }
+}
+
+/// A result set being paged through.
+#[derive(Debug)]
+struct PagedResults {
+    token: String,
+    /// Opens every page, e.g. "Found 40 relevant passages:".
+    header: String,
+    entries: Vec<PageEntry>,
+    /// Between entries on a page.
+    separator: &'static str,
+    page_size: usize,
+    /// Index of the first entry not yet returned.
+    next: usize,
+}
+
+impl PagedResults {
+    /// The next page as a tool result, advancing past it.
+    fn next_page(&mut self, tool_call_id: &str) -> ToolResult {
+        let start = self.next;
+        let end = (start + self.page_size).min(self.entries.len());
+        self.next = end;
+
+        let page = &self.entries[start..end];
+        let mut content = format!(
+            "{}\n\n{}",
+            self.header,
+            page.iter()
+                .map(|e| e.text.as_str())
+                .collect::<Vec<_>>()
+                .join(self.separator)
+        );
+        let total = self.entries.len();
+        if end < total {
+            content.push_str(&format!(
+                "\n\nShowing {}-{} of {}. Call fetch_more with token \"{}\" for the next {}.",
+                start + 1,
+                end,
+                total,
+                self.token,
+                self.page_size.min(total - end)
+            ));
+        } else if start > 0 {
+            content.push_str(&format!(
+                "\n\nShowing {}-{} of {}; these are the last results.",
+                start + 1,
+                end,
+                total
+            ));
+        }
+
+        ToolResult {
+            citations: page.iter().flat_map(|e| e.citations.clone()).collect(),
+            ..ToolResult::ok(tool_call_id, content)
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.next >= self.entries.len()
+    }
+}
+
+/// Result sets with pages left, shared by all conversations.
+#[derive(Debug, Default)]
+pub struct ResultPages {
+    sets: Mutex<VecDeque<PagedResults>>,
+}
+
+impl ResultPages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The first `page_size` entries under `header`. When there are more,
+    /// the rest are kept and the result ends with a token for them.
+    pub fn first_page(
+        &self,
+        tool_call_id: &str,
+        header: String,
+        entries: Vec<PageEntry>,
+        separator: &'static str,
+        page_size: usize,
+    ) -> ToolResult {
+        let mut results = PagedResults {
+            token: format!("more_{}", &uuid::Uuid::new_v4().simple().to_string()[..8]),
+            header,
+            entries,
+            separator,
+            page_size: page_size.max(1),
+            next: 0,
+        };
+        let result = results.next_page(tool_call_id);
+        if !results.is_done() {
+            let mut sets = self.sets.lock().unwrap_or_else(|e| e.into_inner());
+            if sets.len() == MAX_RESULT_SETS {
+                sets.pop_front();
+            }
+            sets.push_back(results);
+        }
+        result
+    }
+
+    /// The next page for `token`. The set is forgotten after its last page.
+    pub fn fetch_more(&self, tool_call_id: &str, token: &str) -> ToolResult {
+        let mut sets = self.sets.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(i) = sets.iter().position(|s| s.token == token) else {
+            return ToolResult::error(
+                tool_call_id,
+                format!(
+                    "No more results for token \"{}\": it was used up or has expired. \
+                     Run the original tool again.",
+                    token
+                ),
+            );
+        };
+        let result = sets[i].next_page(tool_call_id);
+        if sets[i].is_done() {
+            sets.remove(i);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(n: usize) -> Vec<PageEntry> {
+        (1..=n)
+            .map(|i| PageEntry::text(format!("result {}", i)))
+            .collect()
+    }
+
+    fn token(content: &str) -> &str {
+        let start = content.find("token \"").unwrap() + 7;
+        let len = content[start..].find('"').unwrap();
+        &content[start..start + len]
+    }
+
+    #[test]
+    fn test_pages_through_results() {
+        let pages = ResultPages::new();
+        let first = pages.first_page("call_1", "Found 5:".to_string(), entries(5), "\n", 2);
+        assert!(first
+            .content
+            .starts_with("Found 5:\n\nresult 1\nresult 2\n\n"));
+        assert!(first.content.contains("Showing 1-2 of 5"));
+
+        let token = token(&first.content).to_string();
+        let second = pages.fetch_more("call_2", &token);
+        assert!(second.content.contains("result 3\nresult 4"));
+        assert_eq!(token, self::token(&second.content));
+
+        let last = pages.fetch_more("call_3", &token);
+        assert!(last.content.contains("result 5"));
+        assert!(last.content.ends_with("these are the last results."));
+
+        // Used up.
+        let gone = pages.fetch_more("call_4", &token);
+        assert!(gone.is_error);
+    }
+
+    #[test]
+    fn test_single_page_keeps_nothing() {
+        let pages = ResultPages::new();
+        let result = pages.first_page("call_1", "Found 2:".to_string(), entries(2), "\n", 5);
+        assert_eq!(result.content, "Found 2:\n\nresult 1\nresult 2");
+        assert!(pages.sets.lock().unwrap().is_empty());
+    }
+}
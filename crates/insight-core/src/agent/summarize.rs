@@ -0,0 +1,441 @@
+//! Map-reduce summarization for the `summarize_document` tool.
+//!
+//! A document too long for one request is split into page-aligned
+//! sections sized to the chat provider's context window. Each section is
+//! summarized on its own (map), then the section summaries are merged into
+//! one (reduce), in several rounds if they still don't fit. This is what
+//! lets a small local model summarize a 400-page report.
+
+use anyhow::{bail, Result};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use super::{ContentBlock, Message, MessageRole};
+use crate::prompts::PromptLibrary;
+use crate::provider::{retry, ChatProvider, CompletionCache, ProviderEvent, SamplingParams};
+
+/// Conservative characters-per-token estimate. Overestimating tokens only
+/// costs an extra section; underestimating overflows the context.
+pub(crate) const CHARS_PER_TOKEN: usize = 3;
+
+/// Share of the context window, in percent, one request's input may use.
+/// The rest is left for the instructions and the reply.
+const INPUT_SHARE_PERCENT: usize = 50;
+
+/// A page-aligned piece of the document.
+#[derive(Debug, Clone, PartialEq)]
+struct Section {
+    first_page: usize,
+    last_page: usize,
+    text: String,
+}
+
+impl Section {
+    fn label(&self) -> String {
+        if self.first_page == self.last_page {
+            format!("page {}", self.first_page)
+        } else {
+            format!("pages {}-{}", self.first_page, self.last_page)
+        }
+    }
+}
+
+/// What a summary covers and how many model calls it took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub text: String,
+    pub sections: usize,
+    pub calls: usize,
+}
+
+/// Summarize `pages` (page 1 first) of the document called `title`,
+/// optionally concentrating on `focus`, with the `summarize-*` templates
+/// of `prompts`. Requests already answered in `cache` aren't sent again.
+pub async fn summarize_pages(
+    provider: &dyn ChatProvider,
+    prompts: &PromptLibrary,
+    cache: Option<&CompletionCache>,
+    title: &str,
+    pages: &[&str],
+    focus: Option<&str>,
+    cancel_token: &CancellationToken,
+) -> Result<Summary> {
+    let budget = input_budget(provider.context_window());
+    let sections = split_sections(pages, budget);
+    if sections.is_empty() {
+        bail!("The document has no text to summarize");
+    }
+    let focus_note = focus
+        .map(|f| format!(" Concentrate on: {}.", f))
+        .unwrap_or_default();
+
+    debug!(
+        sections = sections.len(),
+        budget_chars = budget,
+        "Summarizing document"
+    );
+
+    let mut calls = 0;
+    let mut partials = Vec::with_capacity(sections.len());
+    for section in &sections {
+        let prompt = prompts.render(
+            "summarize-section",
+            &[
+                ("section", &section.label()),
+                ("title", title),
+                ("focus", &focus_note),
+                ("text", &section.text),
+            ],
+        )?;
+        let summary = complete(provider, cache, prompt, cancel_token).await?;
+        calls += 1;
+        if sections.len() == 1 {
+            return Ok(Summary {
+                text: summary,
+                sections: 1,
+                calls,
+            });
+        }
+        partials.push(format!("[{}]\n{}", section.label(), summary));
+    }
+
+    // Merge in rounds until everything fits in one request. Each group
+    // takes at least two summaries, so every round shrinks the list.
+    while partials.len() > 1 && joined_len(&partials) > budget {
+        let mut merged = Vec::new();
+        for group in group_to_budget(partials, budget) {
+            let prompt = prompts.render(
+                "summarize-merge",
+                &[
+                    ("title", title),
+                    ("focus", &focus_note),
+                    ("summaries", &group.join("\n\n")),
+                ],
+            )?;
+            merged.push(complete(provider, cache, prompt, cancel_token).await?);
+            calls += 1;
+        }
+        partials = merged;
+    }
+
+    let prompt = prompts.render(
+        "summarize",
+        &[
+            ("title", title),
+            ("focus", &focus_note),
+            ("summaries", &partials.join("\n\n")),
+        ],
+    )?;
+    let text = complete(provider, cache, prompt, cancel_token).await?;
+    calls += 1;
+
+    Ok(Summary {
+        text,
+        sections: sections.len(),
+        calls,
+    })
+}
+
+/// Characters of document text one request can carry.
+pub(crate) fn input_budget(context_window: usize) -> usize {
+    context_window * CHARS_PER_TOKEN * INPUT_SHARE_PERCENT / 100
+}
+
+/// Group pages into sections of at most `budget` characters. A page longer
+/// than the budget is split on its own, at whitespace where possible.
+/// Blank pages are skipped.
+fn split_sections(pages: &[&str], budget: usize) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        let page_number = i + 1;
+        let page = page.trim();
+        if page.is_empty() {
+            continue;
+        }
+
+        if let Some(last) = sections.last_mut() {
+            if last.text.len() + 2 + page.len() <= budget {
+                last.text.push_str("\n\n");
+                last.text.push_str(page);
+                last.last_page = page_number;
+                continue;
+            }
+        }
+
+        for piece in split_text(page, budget) {
+            sections.push(Section {
+                first_page: page_number,
+                last_page: page_number,
+                text: piece.to_string(),
+            });
+        }
+    }
+    sections
+}
+
+/// Split `text` into pieces of at most `budget` bytes, preferring to cut
+/// after whitespace.
+fn split_text(text: &str, budget: usize) -> Vec<&str> {
+    let budget = budget.max(1);
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while rest.len() > budget {
+        let mut cut = budget;
+        while !rest.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        if let Some(space) = rest[..cut].rfind(char::is_whitespace) {
+            if space > 0 {
+                cut = space;
+            }
+        }
+        if cut == 0 {
+            // A single character wider than the budget.
+            cut = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        pieces.push(rest[..cut].trim_end());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+fn joined_len(parts: &[String]) -> usize {
+    parts.iter().map(|p| p.len() + 2).sum()
+}
+
+/// Consecutive groups of at least two summaries, each within `budget`
+/// where possible.
+fn group_to_budget(parts: Vec<String>, budget: usize) -> Vec<Vec<String>> {
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    for part in parts {
+        match groups.last_mut() {
+            Some(group) if group.len() < 2 || joined_len(group) + part.len() + 2 <= budget => {
+                group.push(part)
+            }
+            _ => groups.push(vec![part]),
+        }
+    }
+    // Fold a trailing single into its neighbour so it isn't summarized alone.
+    if groups.len() > 1 && groups.last().is_some_and(|g| g.len() == 1) {
+        let last = groups.pop().unwrap();
+        groups.last_mut().unwrap().extend(last);
+    }
+    groups
+}
+
+/// Run one tool-free completion and return its text, from `cache` if
+/// the same request was answered before.
+pub(crate) async fn complete(
+    provider: &dyn ChatProvider,
+    cache: Option<&CompletionCache>,
+    prompt: String,
+    cancel_token: &CancellationToken,
+) -> Result<String> {
+    if cancel_token.is_cancelled() {
+        bail!("Summarization cancelled");
+    }
+    let messages = vec![Message {
+        role: MessageRole::User,
+        content: vec![ContentBlock::Text { text: prompt }],
+    }];
+    let sampling = SamplingParams::default();
+    if let Some(text) = cache.and_then(|c| c.get(provider, &messages, &sampling)) {
+        return Ok(text);
+    }
+
+    // The result carries the full text; the stream only needs draining.
+    let (event_tx, mut event_rx) = mpsc::channel::<ProviderEvent>(100);
+    let drain = tokio::spawn(async move { while event_rx.recv().await.is_some() {} });
+    let result = retry::stream_with_retry(
+        provider,
+        &messages,
+        &[],
+        &sampling,
+        event_tx,
+        cancel_token.clone(),
+    )
+    .await;
+    let _ = drain.await;
+
+    let text = result?.text.trim().to_string();
+    if text.is_empty() {
+        bail!("The model returned an empty summary");
+    }
+    if let Some(cache) = cache {
+        cache.insert(provider, &messages, &sampling, text.clone());
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{CompletionResult, ToolDefinition};
+    use std::sync::Mutex;
+
+    /// Replies with a fixed-size summary and records every prompt.
+    struct RecordingProvider {
+        context_window: usize,
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl crate::provider::Provider for RecordingProvider {
+        fn provider_name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn model_id(&self) -> &str {
+            "mock-model"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ChatProvider for RecordingProvider {
+        async fn stream_completion(
+            &self,
+            messages: &[Message],
+            _tools: &[ToolDefinition],
+            _sampling: &SamplingParams,
+            _event_tx: mpsc::Sender<ProviderEvent>,
+            _cancel_token: CancellationToken,
+        ) -> Result<CompletionResult> {
+            let mut prompts = self.prompts.lock().unwrap();
+            prompts.push(messages[0].text());
+            Ok(CompletionResult {
+                text: format!("summary {} {}", prompts.len(), "x".repeat(60)),
+                ..CompletionResult::default()
+            })
+        }
+
+        fn context_window(&self) -> usize {
+            self.context_window
+        }
+    }
+
+    fn provider(context_window: usize) -> RecordingProvider {
+        RecordingProvider {
+            context_window,
+            prompts: Mutex::new(Vec::new()),
+        }
+    }
+
+    #[test]
+    fn test_split_sections_groups_pages() {
+        let pages = ["aaaa", "", "bbbb", "cccccccccccccccccccc dddd"];
+        let sections = split_sections(&pages, 12);
+
+        assert_eq!(sections.len(), 4);
+        assert_eq!((sections[0].first_page, sections[0].last_page), (1, 3));
+        assert_eq!(sections[0].text, "aaaa\n\nbbbb");
+        // The long page is split on its own, at whitespace where it can be.
+        assert_eq!(sections[1].text, "cccccccccccc");
+        assert_eq!(sections[2].text, "cccccccc");
+        assert_eq!(sections[3].text, "dddd");
+        assert_eq!(sections[3].label(), "page 4");
+    }
+
+    #[test]
+    fn test_split_text_respects_char_boundaries() {
+        let pieces = split_text("ééééé", 3);
+        assert!(pieces.iter().all(|p| p.len() <= 3));
+        assert_eq!(pieces.concat(), "ééééé");
+    }
+
+    #[tokio::test]
+    async fn test_short_document_takes_one_call() {
+        let provider = provider(8192);
+        let summary = summarize_pages(
+            &provider,
+            &PromptLibrary::builtin(),
+            None,
+            "Memo",
+            &["Short text."],
+            None,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary.sections, 1);
+        assert_eq!(summary.calls, 1);
+        let prompts = provider.prompts.lock().unwrap();
+        assert!(prompts[0].contains("page 1 of \"Memo\""));
+    }
+
+    #[tokio::test]
+    async fn test_long_document_maps_and_reduces() {
+        // 100 tokens * 3 chars * 50% = 150 chars per request.
+        let provider = provider(100);
+        let page = "word ".repeat(25);
+        let pages: Vec<&str> = vec![page.as_str(); 6];
+        let summary = summarize_pages(
+            &provider,
+            &PromptLibrary::builtin(),
+            None,
+            "Report",
+            &pages,
+            Some("payments"),
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary.sections, 6);
+        let prompts = provider.prompts.lock().unwrap();
+        // Six section calls, at least one merge round, then the final call.
+        assert!(summary.calls > 7, "{}", summary.calls);
+        assert_eq!(prompts.len(), summary.calls);
+        assert!(prompts[0].contains("Concentrate on: payments."));
+        assert!(prompts[6].starts_with("Combine these partial summaries"));
+        assert!(prompts
+            .last()
+            .unwrap()
+            .contains("summary of the whole document"));
+    }
+
+    #[tokio::test]
+    async fn test_cached_summary_skips_the_model() {
+        let provider = provider(8192);
+        let cache = CompletionCache::default();
+        for _ in 0..2 {
+            summarize_pages(
+                &provider,
+                &PromptLibrary::builtin(),
+                Some(&cache),
+                "Memo",
+                &["Short text."],
+                None,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(provider.prompts.lock().unwrap().len(), 1);
+        assert_eq!(cache.stats().hits, 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_before_start() {
+        let provider = provider(8192);
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result = summarize_pages(
+            &provider,
+            &PromptLibrary::builtin(),
+            None,
+            "Memo",
+            &["Text."],
+            None,
+            &cancel,
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert!(provider.prompts.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,359 @@
+//! Date extraction for the `extract_timeline` tool.
+//!
+//! Dates are found with a small rule-based scanner rather than the model:
+//! it is deterministic, runs over thousands of pages in milliseconds, and
+//! every event it reports points at text that really contains the date.
+//! Recognized forms are ISO (`2023-04-05`) and written-out dates in either
+//! order (`5 April 2023`, `April 5, 2023`, `5th of Apr. 2023`) or with
+//! month precision (`April 2023`). Purely numeric dates like `04/05/2023`
+//! are skipped because the day/month order is ambiguous.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::text::tokenize;
+
+/// Years outside this range are more likely amounts or IDs than dates.
+const YEARS: Range<u32> = 1500..2200;
+
+/// Longest excerpt kept per event, in bytes.
+const MAX_EXCERPT: usize = 240;
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// How much of a date was stated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatePrecision {
+    Day,
+    Month,
+}
+
+/// A date found in text, normalized to `YYYY-MM-DD` or `YYYY-MM`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateMention {
+    pub date: String,
+    pub precision: DatePrecision,
+    /// Byte range of the mention in the scanned text.
+    pub span: Range<usize>,
+}
+
+/// Where an event was found.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Citation {
+    pub document_id: String,
+    pub document_name: String,
+    pub page: usize,
+}
+
+/// One dated event: the sentence mentioning the date and every place it
+/// appears.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimelineEvent {
+    pub date: String,
+    pub precision: DatePrecision,
+    pub description: String,
+    pub citations: Vec<Citation>,
+}
+
+/// Events gathered across documents. The same sentence about the same date
+/// (a quoted press release, a forwarded email) becomes one event with
+/// several citations.
+#[derive(Default)]
+pub struct Timeline {
+    events: Vec<TimelineEvent>,
+    by_key: HashMap<(String, String), usize>,
+}
+
+impl Timeline {
+    /// Record every date in `text`, citing `citation` for each.
+    pub fn add_text(&mut self, text: &str, citation: &Citation) {
+        for mention in find_dates(text) {
+            let description = excerpt(text, &mention.span);
+            let key = (mention.date.clone(), description.to_lowercase());
+            match self.by_key.get(&key) {
+                Some(&i) => {
+                    let citations = &mut self.events[i].citations;
+                    if !citations.contains(citation) {
+                        citations.push(citation.clone());
+                    }
+                }
+                None => {
+                    self.by_key.insert(key, self.events.len());
+                    self.events.push(TimelineEvent {
+                        date: mention.date,
+                        precision: mention.precision,
+                        description,
+                        citations: vec![citation.clone()],
+                    });
+                }
+            }
+        }
+    }
+
+    /// Events in chronological order. A month-precision event sorts before
+    /// the days of that month.
+    pub fn into_events(mut self) -> Vec<TimelineEvent> {
+        self.events.sort_by(|a, b| a.date.cmp(&b.date));
+        self.events
+    }
+}
+
+/// Find the dates in `text`, in order of appearance.
+pub fn find_dates(text: &str) -> Vec<DateMention> {
+    let tokens = tokenize(text);
+    let mut mentions = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some((mention, used)) = date_at(text, &tokens, i) {
+            mentions.push(mention);
+            i += used;
+        } else {
+            i += 1;
+        }
+    }
+    mentions
+}
+
+/// Try each date form starting at token `i`. Returns the mention and how
+/// many tokens it used.
+fn date_at(text: &str, tokens: &[Range<usize>], i: usize) -> Option<(DateMention, usize)> {
+    let token = |k: usize| tokens.get(k).map(|r| &text[r.clone()]);
+    // Tokens `a` and `b` are adjacent, separated only by `allowed` chars.
+    let joined = |a: usize, b: usize, allowed: &str| {
+        tokens.get(b).is_some_and(|next| {
+            let gap = &text[tokens[a].end..next.start];
+            gap.len() <= 3 && gap.chars().all(|c| allowed.contains(c))
+        })
+    };
+    let mention = |date: String, precision, last: usize| {
+        Some((
+            DateMention {
+                date,
+                precision,
+                span: tokens[i].start..tokens[last].end,
+            },
+            last + 1 - i,
+        ))
+    };
+
+    let first = token(i)?;
+
+    // ISO: 2023-04-05
+    if let Some(year) = parse_year(first) {
+        if joined(i, i + 1, "-") && joined(i + 1, i + 2, "-") {
+            let month = parse_number(token(i + 1)?, 2);
+            let day = parse_number(token(i + 2)?, 2);
+            if let (Some(month), Some(day)) = (month, day) {
+                if valid_day(year, month, day) {
+                    return mention(iso(year, month, Some(day)), DatePrecision::Day, i + 2);
+                }
+            }
+        }
+        return None;
+    }
+
+    // April 5, 2023 / April 2023
+    if let Some(month) = parse_month(first) {
+        if joined(i, i + 1, " ,.") {
+            if let Some(year) = token(i + 1).and_then(parse_year) {
+                return mention(iso(year, month, None), DatePrecision::Month, i + 1);
+            }
+            if let Some(day) = token(i + 1).and_then(parse_day) {
+                if joined(i + 1, i + 2, " ,") {
+                    if let Some(year) = token(i + 2).and_then(parse_year) {
+                        if valid_day(year, month, day) {
+                            return mention(iso(year, month, Some(day)), DatePrecision::Day, i + 2);
+                        }
+                    }
+                }
+            }
+        }
+        return None;
+    }
+
+    // 5 April 2023 / 5th of April 2023
+    let day = parse_day(first)?;
+    let mut k = i + 1;
+    if !joined(i, k, " ") {
+        return None;
+    }
+    if token(k).is_some_and(|t| t.eq_ignore_ascii_case("of")) {
+        if !joined(k, k + 1, " ") {
+            return None;
+        }
+        k += 1;
+    }
+    let month = token(k).and_then(parse_month)?;
+    if !joined(k, k + 1, " ,.") {
+        return None;
+    }
+    let year = token(k + 1).and_then(parse_year)?;
+    if !valid_day(year, month, day) {
+        return None;
+    }
+    mention(iso(year, month, Some(day)), DatePrecision::Day, k + 1)
+}
+
+fn iso(year: u32, month: u32, day: Option<u32>) -> String {
+    match day {
+        Some(day) => format!("{:04}-{:02}-{:02}", year, month, day),
+        None => format!("{:04}-{:02}", year, month),
+    }
+}
+
+/// A run of at most `max_len` ASCII digits.
+fn parse_number(token: &str, max_len: usize) -> Option<u32> {
+    if token.is_empty() || token.len() > max_len || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn parse_year(token: &str) -> Option<u32> {
+    if token.len() != 4 {
+        return None;
+    }
+    parse_number(token, 4).filter(|y| YEARS.contains(y))
+}
+
+/// `5`, `05` or `5th`.
+fn parse_day(token: &str) -> Option<u32> {
+    let digits = token.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = token[digits.len()..].to_ascii_lowercase();
+    if !["", "st", "nd", "rd", "th"].contains(&suffix.as_str()) {
+        return None;
+    }
+    parse_number(digits, 2).filter(|d| (1..=31).contains(d))
+}
+
+/// Full month names and three-letter abbreviations (plus `Sept`).
+fn parse_month(token: &str) -> Option<u32> {
+    let lower = token.to_lowercase();
+    if lower.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|m| *m == lower || (lower.len() == 3 && m.starts_with(&lower)))
+        .or_else(|| (lower == "sept").then_some(8))
+        .map(|i| i as u32 + 1)
+}
+
+fn valid_day(year: u32, month: u32, day: u32) -> bool {
+    let leap = (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+/// The sentence around `span`, whitespace-collapsed and capped at
+/// [`MAX_EXCERPT`] bytes.
+fn excerpt(text: &str, span: &Range<usize>) -> String {
+    let is_break = |c: char| matches!(c, '.' | '!' | '?' | '\n');
+    let start = text[..span.start]
+        .rfind(is_break)
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let end = text[span.end..]
+        .find(is_break)
+        .map(|i| span.end + i + 1)
+        .unwrap_or(text.len());
+
+    let sentence = text[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if sentence.len() <= MAX_EXCERPT {
+        return sentence;
+    }
+    let mut cut = MAX_EXCERPT;
+    while !sentence.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    format!("{}…", &sentence[..cut])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dates(text: &str) -> Vec<String> {
+        find_dates(text).into_iter().map(|m| m.date).collect()
+    }
+
+    #[test]
+    fn test_find_dates_forms() {
+        assert_eq!(
+            dates("Signed 2021-03-14, amended on 5 April 2022 and again April 7, 2022."),
+            vec!["2021-03-14", "2022-04-05", "2022-04-07"]
+        );
+        assert_eq!(
+            dates("Filed the 1st of Sept. 2019; hearings began in March 2020."),
+            vec!["2019-09-01", "2020-03"]
+        );
+        assert_eq!(dates("Apr 30 2018"), vec!["2018-04-30"]);
+    }
+
+    #[test]
+    fn test_find_dates_rejects_noise() {
+        // Invalid days, numeric dates, amounts, and bare words. An
+        // impossible day still leaves a valid month.
+        assert_eq!(dates("Due 2023-02-30 or 31 June 2023."), vec!["2023-06"]);
+        assert!(dates("Paid 04/05/2023, invoice 2023-118, 4 May items.").is_empty());
+        assert!(dates("We may meet in March.").is_empty());
+        assert_eq!(dates("29 February 2024"), vec!["2024-02-29"]);
+        assert_eq!(dates("29 February 2023"), vec!["2023-02"]);
+    }
+
+    #[test]
+    fn test_mention_span_and_excerpt() {
+        let text = "Background. The board met on 12 May 2020 to approve the deal. Later.";
+        let mention = &find_dates(text)[0];
+        assert_eq!(&text[mention.span.clone()], "12 May 2020");
+        assert_eq!(
+            excerpt(text, &mention.span),
+            "The board met on 12 May 2020 to approve the deal."
+        );
+    }
+
+    #[test]
+    fn test_timeline_merges_and_sorts() {
+        let cite = |doc: &str, page| Citation {
+            document_id: doc.to_string(),
+            document_name: format!("{}.pdf", doc),
+            page,
+        };
+        let mut timeline = Timeline::default();
+        timeline.add_text("The merger closed on 1 June 2021.", &cite("a", 1));
+        timeline.add_text("Talks started in March 2021.", &cite("a", 2));
+        timeline.add_text("The merger closed on 1 June 2021.", &cite("b", 4));
+
+        let events = timeline.into_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].date, "2021-03");
+        assert_eq!(events[0].precision, DatePrecision::Month);
+        assert_eq!(events[1].date, "2021-06-01");
+        assert_eq!(events[1].citations, vec![cite("a", 1), cite("b", 4)]);
+    }
+}
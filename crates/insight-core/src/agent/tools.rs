@@ -1,8 +1,64 @@
+use std::ops::Range;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use iroh_docs::NamespaceId;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info, warn};
 
-use super::AgentContext;
+use super::compare::{self, Change, ChangeKind, Passage};
+use super::entities::{EntityIndex, EntityKind};
+use super::paging::PageEntry;
+use super::summarize::{self, summarize_pages};
+use super::timeline::{Citation, Timeline};
+use super::{AgentContext, ContentBlock, PlanStatus, PlanStep};
+use crate::projection::ProjectionSpec;
+use crate::prompts::PromptLibrary;
 use crate::search;
+use crate::storage::{DocumentMetadata, Storage, MARKDOWN_FILE_TYPE};
+use crate::CollectionInfo;
+
+/// Longest quote kept in a citation, in characters.
+const MAX_QUOTE_CHARS: usize = 200;
+
+/// Passages per page of `search` results.
+const SEARCH_LIMIT: usize = 15;
+
+/// Passages one `search` collects; the agent pages through them with
+/// `fetch_more`.
+const SEARCH_MAX_HITS: usize = 60;
+
+/// Documents per page of `list_documents` results.
+const LIST_PAGE_SIZE: usize = 25;
+
+/// Longest report file name, in characters, before the `.md` extension.
+const MAX_REPORT_NAME_CHARS: usize = 100;
+
+/// Resolution pages are rendered at for `view_page_image`: enough to read
+/// small print in tables without costing more than a page of text would.
+const VIEW_DPI: f32 = 110.0;
+
+/// Most pages `read_pages` returns in one call.
+const MAX_PAGES_PER_READ: usize = 10;
+
+/// Most documents one `extract_timeline` or `extract_entities` call scans.
+const MAX_SCAN_DOCUMENTS: usize = 20;
+
+/// Most events one `extract_timeline` call returns.
+const MAX_TIMELINE_EVENTS: usize = 200;
+
+/// Passages `extract_entities` scans for a query.
+const ENTITY_SEARCH_LIMIT: usize = 50;
+
+/// Most entities one `extract_entities` call returns.
+const MAX_ENTITIES: usize = 150;
+
+/// Most changes one `compare_documents` call returns.
+const MAX_COMPARE_CHANGES: usize = 100;
+
+/// Pages with fewer non-whitespace characters than this count as blank
+/// when judging extraction quality.
+const MIN_PAGE_CHARS: usize = 20;
 
 /// A tool call from the LLM
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +74,128 @@ pub struct ToolResult {
     pub tool_call_id: String,
     pub content: String,
     pub is_error: bool,
+    /// Passages the content was drawn from, for the UI to link to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<SourcePassage>,
+    /// Original file exported for the user to open (`get_source_file`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_file: Option<SourceFile>,
+    /// Rendered page for the model to look at (`view_page_image`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_image: Option<PageImage>,
+}
+
+impl ToolResult {
+    /// A successful result with nothing attached.
+    pub fn ok(tool_call_id: &str, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: tool_call_id.to_string(),
+            content: content.into(),
+            is_error: false,
+            citations: Vec::new(),
+            source_file: None,
+            page_image: None,
+        }
+    }
+
+    /// A failed result, telling the model what went wrong.
+    pub fn error(tool_call_id: &str, message: impl Into<String>) -> Self {
+        Self {
+            is_error: true,
+            ..Self::ok(tool_call_id, message)
+        }
+    }
+}
+
+/// A document passage a tool returned, anchored to its page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourcePassage {
+    pub collection_id: String,
+    pub document_id: String,
+    pub document_name: String,
+    pub page: Option<usize>,
+    /// The start of the passage, trimmed to [`MAX_QUOTE_CHARS`].
+    pub quote: String,
+}
+
+impl SourcePassage {
+    fn new(
+        collection_id: &str,
+        document_id: &str,
+        document_name: &str,
+        page: Option<usize>,
+        text: &str,
+    ) -> Self {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let quote = match text.char_indices().nth(MAX_QUOTE_CHARS) {
+            Some((cut, _)) => format!("{}…", &text[..cut]),
+            None => text,
+        };
+        Self {
+            collection_id: collection_id.to_string(),
+            document_id: document_id.to_string(),
+            document_name: document_name.to_string(),
+            page,
+            quote,
+        }
+    }
+
+    pub fn into_block(self) -> ContentBlock {
+        ContentBlock::Citation {
+            collection_id: self.collection_id,
+            document_id: self.document_id,
+            document_name: self.document_name,
+            page: self.page,
+            quote: self.quote,
+        }
+    }
+}
+
+/// A document's original file, exported to disk so the user can open it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceFile {
+    pub collection_id: String,
+    pub document_id: String,
+    pub document_name: String,
+    pub file_type: String,
+    pub path: String,
+}
+
+impl SourceFile {
+    pub fn into_block(self) -> ContentBlock {
+        ContentBlock::SourceFile {
+            collection_id: self.collection_id,
+            document_id: self.document_id,
+            document_name: self.document_name,
+            file_type: self.file_type,
+            path: self.path,
+        }
+    }
+}
+
+/// A document page rendered as an image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageImage {
+    pub collection_id: String,
+    pub document_id: String,
+    pub document_name: String,
+    pub page: usize,
+    pub media_type: String,
+    /// Base64-encoded.
+    pub data: String,
+}
+
+impl PageImage {
+    pub fn into_block(self) -> ContentBlock {
+        ContentBlock::PageImage {
+            collection_id: self.collection_id,
+            document_id: self.document_id,
+            document_name: self.document_name,
+            page: self.page,
+            media_type: self.media_type,
+            data: self.data,
+        }
+    }
 }
 
 /// Execute a tool call and return the result
@@ -25,16 +203,108 @@ pub async fn execute_tool(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResul
     match tool_call.name.as_str() {
         "search" => execute_search(tool_call, ctx).await,
         "read_chunk" => execute_read_chunk(tool_call, ctx).await,
+        "read_pages" => execute_read_pages(tool_call, ctx).await,
+        "tag_document" => execute_tag_document(tool_call, ctx).await,
+        "remember" => execute_remember(tool_call, ctx).await,
+        "save_search" => execute_save_search(tool_call, ctx).await,
+        "write_report" => execute_write_report(tool_call, ctx).await,
+        "get_source_file" => execute_get_source_file(tool_call, ctx).await,
+        "view_page_image" => execute_view_page_image(tool_call, ctx).await,
+        "plan" => execute_plan(tool_call),
+        "get_document_info" => execute_get_document_info(tool_call, ctx).await,
+        "extract_timeline" => execute_extract_timeline(tool_call, ctx).await,
+        "extract_entities" => execute_extract_entities(tool_call, ctx).await,
+        "summarize_document" => execute_summarize_document(tool_call, ctx).await,
+        "compare_documents" => execute_compare_documents(tool_call, ctx).await,
         "list_documents" => execute_list_documents(tool_call, ctx).await,
         "get_collection_terms" => execute_get_collection_terms(tool_call, ctx).await,
-        _ => ToolResult {
-            tool_call_id: tool_call.id.clone(),
-            content: format!("Unknown tool: {}", tool_call.name),
-            is_error: true,
-        },
+        "fetch_more" => execute_fetch_more(tool_call, ctx),
+        _ => ToolResult::error(&tool_call.id, format!("Unknown tool: {}", tool_call.name)),
+    }
+}
+
+/// For tools that change stored data, the question to put to the user
+/// before running the call. `None` means the call runs without asking.
+pub fn confirmation_prompt(tool_call: &ToolCall) -> Option<String> {
+    match tool_call.name.as_str() {
+        "tag_document" => {
+            let args = TagArgs::parse(&tool_call.arguments);
+            let mut changes = Vec::new();
+            if !args.add.is_empty() {
+                changes.push(format!("add {}", quoted_list(&args.add)));
+            }
+            if !args.remove.is_empty() {
+                changes.push(format!("remove {}", quoted_list(&args.remove)));
+            }
+            Some(format!(
+                "Tag {} document{}: {}?",
+                args.document_ids.len(),
+                if args.document_ids.len() == 1 {
+                    ""
+                } else {
+                    "s"
+                },
+                changes.join(", ")
+            ))
+        }
+        "remember" => {
+            let fact = tool_call.arguments["fact"].as_str().unwrap_or_default();
+            Some(format!("Remember for future chats: \"{}\"?", fact.trim()))
+        }
+        "write_report" => {
+            let title = tool_call.arguments["title"].as_str().unwrap_or_default();
+            Some(format!(
+                "Save the report \"{}\" as a document in the collection?",
+                title.trim()
+            ))
+        }
+        "save_search" => {
+            let query = tool_call.arguments["query"].as_str().unwrap_or_default();
+            let notify = tool_call.arguments["notify"].as_bool().unwrap_or(false);
+            Some(format!(
+                "Save the search \"{}\"{}?",
+                query.trim(),
+                if notify {
+                    " and alert on new matches"
+                } else {
+                    ""
+                }
+            ))
+        }
+        _ => None,
     }
 }
 
+/// The steps of a `plan` call, or `None` for any other tool or a plan
+/// with no steps.
+pub fn plan_steps(tool_call: &ToolCall) -> Option<Vec<PlanStep>> {
+    if tool_call.name != "plan" {
+        return None;
+    }
+    let steps: Vec<PlanStep> = serde_json::from_value(tool_call.arguments["steps"].clone()).ok()?;
+    let steps: Vec<PlanStep> = steps
+        .into_iter()
+        .filter(|s| !s.description.trim().is_empty())
+        .collect();
+    (!steps.is_empty()).then_some(steps)
+}
+
+/// Acknowledge a plan. The plan itself reaches the UI as
+/// [`super::AgentEvent::Plan`]; the model only needs to know it was taken.
+fn execute_plan(tool_call: &ToolCall) -> ToolResult {
+    let Some(steps) = plan_steps(tool_call) else {
+        return ToolResult::error(&tool_call.id, "Provide steps, each with a description.");
+    };
+    let done = steps
+        .iter()
+        .filter(|s| matches!(s.status, PlanStatus::Done | PlanStatus::Skipped))
+        .count();
+    ToolResult::ok(
+        &tool_call.id,
+        format!("Plan updated: {} of {} steps finished.", done, steps.len()),
+    )
+}
+
 /// Hybrid search combining keyword (BM25) and semantic matching.
 /// Falls back to keyword-only if no embedder is configured.
 async fn execute_search(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
@@ -42,9 +312,52 @@ async fn execute_search(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult
 
     info!(query = %query, "Executing hybrid search");
 
+    match search_hits(ctx, query, SEARCH_MAX_HITS).await {
+        Ok(hits) => {
+            let entries = search_entries(&ctx.state.search, &hits, ctx);
+            if entries.is_empty() {
+                return ToolResult::ok(&tool_call.id, "No matching passages found.");
+            }
+            ctx.state.result_pages.first_page(
+                &tool_call.id,
+                format!("Found {} relevant passages:", entries.len()),
+                entries,
+                "\n\n",
+                SEARCH_LIMIT,
+            )
+        }
+        Err(e) => ToolResult::error(&tool_call.id, format!("Search error: {}", e)),
+    }
+}
+
+/// Run a hybrid search over the context's collections and return the best
+/// `limit` hits.
+async fn search_hits(
+    ctx: &AgentContext,
+    query: &str,
+    limit: usize,
+) -> anyhow::Result<Vec<search::SearchHit>> {
+    // Pinned documents are searched on their own first, so their passages
+    // lead; the rest of the scope fills the remaining places.
+    let mut passes = Vec::new();
+    if !ctx.pinned_document_ids.is_empty() {
+        passes.push(Some(ctx.pinned_document_ids.as_slice()));
+    }
+    passes.push(None);
+    search_hits_in(ctx, query, limit, &passes).await
+}
+
+/// [`search_hits`] in passes, each over some documents (None = all in
+/// scope) and filling the places the ones before left.
+async fn search_hits_in(
+    ctx: &AgentContext,
+    query: &str,
+    limit: usize,
+    passes: &[Option<&[String]>],
+) -> anyhow::Result<Vec<search::SearchHit>> {
     // Try to get query embedding for semantic component
     let (query_vector, semantic_ratio) = match ctx.state.models.acquire_embedding().await {
-        Ok(Some(embedder)) => match embedder.embed(query).await {
+        Ok(Some(embedder)) => match embedder.embed_query(query).await {
             Ok(vec) => {
                 debug!(dimensions = vec.len(), "Query embedded for hybrid search");
                 (Some(vec), 0.4)
@@ -65,57 +378,179 @@ async fn execute_search(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult
     };
 
     let index = &*ctx.state.search;
-    let collection_ids = ctx.collection_ids();
-
-    let search_params = search::SearchParams {
-        query,
-        limit: 15,
-        query_vector,
-        semantic_ratio,
-        min_score: if semantic_ratio > 0.0 {
-            Some(0.15)
-        } else {
-            None
-        },
-        collection_ids: collection_ids.as_deref(),
-        ..Default::default()
+    let spaces = match query_vector {
+        Some(vector) => vector_spaces(ctx, vector).await,
+        None => vec![VectorSpace {
+            embedder: None,
+            query_vector: None,
+            collection_ids: ctx.collection_ids(),
+        }],
     };
 
-    match search::search_index(index, search_params) {
-        Ok(results) => {
-            info!(
-                query = %query,
-                hits = results.hits.len(),
-                hybrid = semantic_ratio > 0.0,
-                "Search completed"
-            );
-            let formatted = format_search_results(index, &results.hits, ctx);
-            ToolResult {
-                tool_call_id: tool_call.id.clone(),
-                content: formatted,
-                is_error: false,
+    let mut hits: Vec<search::SearchHit> = Vec::new();
+    for &document_ids in passes {
+        let mut pass_hits = Vec::new();
+        for space in &spaces {
+            let ratio = if space.query_vector.is_some() {
+                semantic_ratio
+            } else {
+                0.0
+            };
+            let search_params = search::SearchParams {
+                query,
+                limit,
+                query_vector: space.query_vector.clone(),
+                semantic_ratio: ratio,
+                min_score: if ratio > 0.0 { Some(0.15) } else { None },
+                collection_ids: space.collection_ids.as_deref(),
+                document_ids,
+                embedder: space.embedder.as_deref(),
+                ..Default::default()
+            };
+
+            match search::search_index(index, search_params) {
+                Ok(results) => pass_hits.extend(results.hits),
+                Err(e) => {
+                    warn!(query = %query, error = %e, "Search failed");
+                    return Err(e);
+                }
+            }
+        }
+
+        // Collections in different vector spaces are searched separately;
+        // merge by score so the best passages win regardless of space.
+        if spaces.len() > 1 {
+            pass_hits.sort_by(|a, b| {
+                search::compute_hit_score(&b.scores)
+                    .total_cmp(&search::compute_hit_score(&a.scores))
+            });
+        }
+        for hit in pass_hits {
+            if hits.len() == limit {
+                break;
+            }
+            if !hits.iter().any(|h| h.doc_id == hit.doc_id) {
+                hits.push(hit);
             }
         }
+    }
+
+    info!(
+        query = %query,
+        hits = hits.len(),
+        hybrid = semantic_ratio > 0.0,
+        spaces = spaces.len(),
+        "Search completed"
+    );
+    Ok(hits)
+}
+
+/// Run a hybrid search and return where each hit came from.
+pub(crate) async fn search_passages(
+    ctx: &AgentContext,
+    query: &str,
+    limit: usize,
+) -> anyhow::Result<Vec<SourcePassage>> {
+    let hits = search_hits(ctx, query, limit).await?;
+    Ok(search_citations(&ctx.state.search, &hits))
+}
+
+/// [`search_passages`] within one document.
+pub(crate) async fn document_passages(
+    ctx: &AgentContext,
+    document_id: &str,
+    query: &str,
+    limit: usize,
+) -> anyhow::Result<Vec<SourcePassage>> {
+    let document_ids = [document_id.to_string()];
+    let hits = search_hits_in(ctx, query, limit, &[Some(&document_ids)]).await?;
+    Ok(search_citations(&ctx.state.search, &hits))
+}
+
+/// Part of the search scope whose vectors share one index embedder.
+struct VectorSpace {
+    /// None = the default (native) embedder.
+    embedder: Option<String>,
+    query_vector: Option<Vec<f32>>,
+    collection_ids: Option<Vec<String>>,
+}
+
+/// Split the search scope by vector space.
+///
+/// Collections with a projection (see `crate::projection`) store reduced
+/// vectors under their own embedder, so the query is projected the same
+/// way and searched there. With no projected collections in scope this is
+/// a single space covering the original scope.
+async fn vector_spaces(ctx: &AgentContext, query_vector: Vec<f32>) -> Vec<VectorSpace> {
+    let scope = ctx.collection_ids();
+    let storage = ctx.state.storage.read().await;
+    let collections = match storage.list_collections().await {
+        Ok(c) => c,
         Err(e) => {
-            warn!(query = %query, error = %e, "Search failed");
-            ToolResult {
-                tool_call_id: tool_call.id.clone(),
-                content: format!("Search error: {}", e),
-                is_error: true,
+            warn!(error = %e, "Failed to list collections; searching native vectors only");
+            Vec::new()
+        }
+    };
+
+    let mut native = Vec::new();
+    let mut projected: Vec<(ProjectionSpec, Vec<(NamespaceId, String)>)> = Vec::new();
+    for (namespace_id, metadata) in collections {
+        let id = namespace_id.to_string();
+        if scope.as_ref().is_some_and(|ids| !ids.contains(&id)) {
+            continue;
+        }
+        match metadata.projection {
+            Some(spec) => match projected.iter_mut().find(|(s, _)| *s == spec) {
+                Some((_, members)) => members.push((namespace_id, id)),
+                None => projected.push((spec, vec![(namespace_id, id)])),
+            },
+            None => native.push(id),
+        }
+    }
+
+    if projected.is_empty() {
+        return vec![VectorSpace {
+            embedder: None,
+            query_vector: Some(query_vector),
+            collection_ids: scope,
+        }];
+    }
+
+    let mut spaces = Vec::new();
+    if !native.is_empty() {
+        spaces.push(VectorSpace {
+            embedder: None,
+            query_vector: Some(query_vector.clone()),
+            collection_ids: Some(native),
+        });
+    }
+    for (spec, members) in projected {
+        let projected_query = match storage.get_collection_projection(members[0].0).await {
+            Ok(Some(projection)) => projection.apply(&query_vector).ok(),
+            Ok(None) => None,
+            Err(e) => {
+                warn!(error = %e, "Failed to load collection projection");
+                None
             }
+        };
+        if projected_query.is_none() {
+            debug!(embedder = %spec.embedder_name(), "Projected space searched by keyword only");
         }
+        spaces.push(VectorSpace {
+            embedder: Some(spec.embedder_name()),
+            query_vector: projected_query,
+            collection_ids: Some(members.into_iter().map(|(_, id)| id).collect()),
+        });
     }
+    spaces
 }
 
-fn format_search_results(
+/// Search hits formatted for the model, each with the passage it cites.
+fn search_entries(
     index: &milli::Index,
     hits: &[search::SearchHit],
     ctx: &AgentContext,
-) -> String {
-    if hits.is_empty() {
-        return "No matching passages found.".to_string();
-    }
-
+) -> Vec<PageEntry> {
     // Build a lookup map from collection_id -> collection_name
     let collection_names: std::collections::HashMap<String, String> = ctx
         .collections
@@ -127,10 +562,13 @@ fn format_search_results(
         })
         .unwrap_or_default();
 
-    let mut results = Vec::new();
+    let mut entries = Vec::new();
     let rtxn = match index.read_txn() {
         Ok(t) => t,
-        Err(e) => return format!("Error reading index: {}", e),
+        Err(e) => {
+            warn!(error = %e, "Failed to read index for search results");
+            return entries;
+        }
     };
 
     for hit in hits {
@@ -186,17 +624,54 @@ fn format_search_results(
             format!("{} pages", page_count)
         };
 
-        results.push(format!(
-            "- Document: {} ({}) [score: {:.2}]\n  Collection: {}\n  ID: {} | Chunk: {}\n  Passage: {}",
-            parent_name, page_ref, score, collection_name, parent_id, chunk_index, passage
-        ));
+        let citation = SourcePassage::new(
+            &collection_id,
+            &parent_id,
+            &parent_name,
+            (start_page > 0).then_some(start_page as usize),
+            &content,
+        );
+        entries.push(PageEntry {
+            text: format!(
+                "- Document: {} ({}) [score: {:.2}]\n  Collection: {}\n  ID: {} | Chunk: {}\n  Passage: {}",
+                parent_name, page_ref, score, collection_name, parent_id, chunk_index, passage
+            ),
+            citations: vec![citation],
+        });
     }
+    entries
+}
 
-    format!(
-        "Found {} relevant passages:\n\n{}",
-        hits.len(),
-        results.join("\n\n")
-    )
+/// Where each search hit came from.
+fn search_citations(index: &milli::Index, hits: &[search::SearchHit]) -> Vec<SourcePassage> {
+    let rtxn = match index.read_txn() {
+        Ok(t) => t,
+        Err(e) => {
+            warn!(error = %e, "Failed to read index for citations");
+            return Vec::new();
+        }
+    };
+    hits.iter()
+        .filter_map(|hit| match search::get_document(index, &rtxn, hit.doc_id) {
+            Ok(Some(doc)) => Some(doc),
+            _ => None,
+        })
+        .map(|doc| {
+            let get_str = |key: &str| doc.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+            let page = doc
+                .get("start_page")
+                .and_then(|v| v.as_u64())
+                .filter(|&p| p > 0)
+                .map(|p| p as usize);
+            SourcePassage::new(
+                get_str("collection_id"),
+                get_str("parent_id"),
+                get_str("parent_name"),
+                page,
+                get_str("content"),
+            )
+        })
+        .collect()
 }
 
 async fn execute_read_chunk(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
@@ -219,708 +694,2709 @@ async fn execute_read_chunk(tool_call: &ToolCall, ctx: &AgentContext) -> ToolRes
                 "Chunk read successfully"
             );
 
-            ToolResult {
-                tool_call_id: tool_call.id.clone(),
-                content,
-                is_error: false,
-            }
+            ToolResult::ok(&tool_call.id, content)
         }
         Ok(None) => {
             warn!(document_id = %doc_id, chunk_index = chunk_index, "Chunk not found");
-            ToolResult {
-                tool_call_id: tool_call.id.clone(),
-                content: format!(
+            ToolResult::error(
+                &tool_call.id,
+                format!(
                     "Chunk {} not found for document {}. Try a different chunk index.",
                     chunk_index, doc_id
                 ),
-                is_error: true,
-            }
+            )
         }
         Err(e) => {
             warn!(document_id = %doc_id, chunk_index = chunk_index, error = %e, "Error reading chunk");
-            ToolResult {
-                tool_call_id: tool_call.id.clone(),
-                content: format!("Error reading chunk: {}", e),
-                is_error: true,
-            }
+            ToolResult::error(&tool_call.id, format!("Error reading chunk: {}", e))
         }
     }
 }
 
-async fn execute_list_documents(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
-    info!("Listing documents");
-
-    // Get collection IDs to list from
-    let collection_ids = ctx.collection_ids();
-
-    if collection_ids.is_none()
-        || collection_ids
-            .as_ref()
-            .map(|c| c.is_empty())
-            .unwrap_or(true)
-    {
-        return ToolResult {
-            tool_call_id: tool_call.id.clone(),
-            content: "No collections selected. Please select collections to list documents from."
-                .to_string(),
-            is_error: true,
-        };
-    }
-
-    let collection_ids = collection_ids.unwrap();
-
-    // Build a lookup map from collection_id -> collection_name
-    let collection_names: std::collections::HashMap<String, String> = ctx
-        .collections
-        .as_ref()
-        .map(|cols| {
-            cols.iter()
-                .map(|c| (c.id.clone(), c.name.clone()))
-                .collect()
-        })
-        .unwrap_or_default();
-
-    let storage = ctx.state.storage.read().await;
-    let mut all_documents = Vec::new();
+/// Read a page range from a document's stored text, marking each page.
+async fn execute_read_pages(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let doc_id = tool_call.arguments["document_id"].as_str().unwrap_or("");
+    let start_page = tool_call.arguments["start_page"].as_u64().unwrap_or(0) as usize;
+    let end_page = tool_call.arguments["end_page"]
+        .as_u64()
+        .map(|p| p as usize)
+        .unwrap_or(start_page);
 
-    for collection_id in &collection_ids {
-        let namespace_id: iroh_docs::NamespaceId = match collection_id.parse() {
-            Ok(id) => id,
-            Err(_) => {
-                warn!(collection_id = %collection_id, "Invalid collection ID, skipping");
-                continue;
-            }
-        };
+    info!(document_id = %doc_id, start_page, end_page, "Reading pages");
 
-        let collection_name = collection_names
-            .get(collection_id)
-            .cloned()
-            .unwrap_or_else(|| collection_id.chars().take(8).collect::<String>() + "...");
+    let error = |content: String| ToolResult::error(&tool_call.id, content);
 
-        match storage.list_documents(namespace_id).await {
-            Ok(documents) => {
-                for doc in documents {
-                    all_documents.push((collection_name.clone(), doc));
-                }
-            }
-            Err(e) => {
-                warn!(collection_id = %collection_id, error = %e, "Failed to list documents");
-            }
+    let (namespace_id, metadata, text) = match load_document_text(ctx, doc_id).await {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            warn!(document_id = %doc_id, "Document not found");
+            return error(format!(
+                "Document {} not found in the active collections.",
+                doc_id
+            ));
         }
-    }
-
-    drop(storage);
+        Err(e) => {
+            warn!(document_id = %doc_id, error = %e, "Error reading document");
+            return error(format!("Error reading document: {}", e));
+        }
+    };
 
-    if all_documents.is_empty() {
-        return ToolResult {
-            tool_call_id: tool_call.id.clone(),
-            content: "No documents found in the selected collections.".to_string(),
-            is_error: false,
-        };
+    let pages = page_spans(&text, &metadata.page_boundaries);
+    let page_count = pages.len();
+    if start_page == 0 || start_page > page_count {
+        return error(format!(
+            "Page {} is out of range. {} has {} page{} (numbered from 1).",
+            start_page,
+            metadata.name,
+            page_count,
+            if page_count == 1 { "" } else { "s" }
+        ));
+    }
+    if end_page < start_page {
+        return error(format!(
+            "end_page ({}) is before start_page ({}).",
+            end_page, start_page
+        ));
     }
 
-    // Sort by collection name, then document name
-    all_documents.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.name.cmp(&b.1.name)));
-
-    let total_docs = all_documents.len();
-    let total_pages: usize = all_documents.iter().map(|(_, d)| d.page_count).sum();
-
-    // Limit output to avoid overwhelming the model
-    const MAX_DOCS_TO_SHOW: usize = 25;
-    let showing_all = total_docs <= MAX_DOCS_TO_SHOW;
-    let docs_to_show: Vec<_> = all_documents.iter().take(MAX_DOCS_TO_SHOW).collect();
-
-    // Format results
-    let mut results = Vec::new();
-    let mut current_collection = String::new();
+    let last_page = end_page
+        .min(page_count)
+        .min(start_page + MAX_PAGES_PER_READ - 1);
 
-    for (collection_name, doc) in docs_to_show {
-        // Add collection header when it changes
-        if *collection_name != current_collection {
-            if !current_collection.is_empty() {
-                results.push(String::new()); // Blank line between collections
-            }
-            results.push(format!("## {}", collection_name));
-            current_collection = collection_name.clone();
+    let mut output = format!(
+        "{} [{}], pages {}-{} of {}:",
+        metadata.name, metadata.id, start_page, last_page, page_count
+    );
+    let mut citations = Vec::new();
+    for page in start_page..=last_page {
+        let content = text[pages[page - 1].clone()].trim();
+        if !content.is_empty() {
+            citations.push(SourcePassage::new(
+                &namespace_id.to_string(),
+                &metadata.id,
+                &metadata.name,
+                Some(page),
+                content,
+            ));
         }
-
-        // Format page info
-        let page_info = if doc.page_count == 1 {
-            "1p".to_string()
+        if metadata.scanned_pages.contains(&page) {
+            output.push_str(&format!("\n\n[Page {}, scanned]\n", page));
         } else {
-            format!("{}p", doc.page_count)
-        };
-
-        // Full ID needed for read_chunk tool
-        results.push(format!("- {} ({}) [{}]", doc.name, page_info, doc.id));
+            output.push_str(&format!("\n\n[Page {}]\n", page));
+        }
+        output.push_str(if content.is_empty() {
+            "(no text on this page)"
+        } else {
+            content
+        });
+    }
+    if last_page < end_page.min(page_count) {
+        output.push_str(&format!(
+            "\n\n... stopped after {} pages. Call again with start_page {} to continue.",
+            MAX_PAGES_PER_READ,
+            last_page + 1
+        ));
     }
-
-    let summary = if showing_all {
-        format!(
-            "Found {} document{} ({} total pages):\n\n{}",
-            total_docs,
-            if total_docs == 1 { "" } else { "s" },
-            total_pages,
-            results.join("\n")
-        )
-    } else {
-        format!(
-            "Found {} documents ({} total pages). Showing first {}:\n\n{}\n\n... and {} more documents. Use search to find specific documents.",
-            total_docs,
-            total_pages,
-            MAX_DOCS_TO_SHOW,
-            results.join("\n"),
-            total_docs - MAX_DOCS_TO_SHOW
-        )
-    };
 
     info!(
-        document_count = total_docs,
-        total_pages = total_pages,
-        "Listed documents"
+        document_id = %doc_id,
+        pages = last_page + 1 - start_page,
+        content_len = output.len(),
+        "Pages read successfully"
     );
 
     ToolResult {
-        tool_call_id: tool_call.id.clone(),
-        content: summary,
-        is_error: false,
+        citations,
+        ..ToolResult::ok(&tool_call.id, output)
     }
 }
 
-/// Get common terms in the collection(s) to understand what topics are covered
-async fn execute_get_collection_terms(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
-    // Parse limit parameter (default 50, max 200)
-    let limit = tool_call.arguments["limit"].as_u64().unwrap_or(50).min(200) as usize;
-
-    info!(limit = limit, "Getting collection terms");
+/// Describe a document's metadata: provenance, size, tags, and how well
+/// its text was extracted.
+async fn execute_get_document_info(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let doc_id = tool_call.arguments["document_id"].as_str().unwrap_or("");
 
-    let collection_ids = ctx.collection_ids();
-    let index = &*ctx.state.search;
+    info!(document_id = %doc_id, "Getting document info");
 
-    match search::get_collection_terms(index, collection_ids.as_deref(), limit) {
-        Ok(terms) => {
-            if terms.is_empty() {
-                return ToolResult {
-                    tool_call_id: tool_call.id.clone(),
-                    content: "No terms found in the collection(s). The collection may be empty."
-                        .to_string(),
-                    is_error: false,
-                };
+    let storage = ctx.state.storage.read().await;
+    let found = match scope_namespaces(ctx, &storage).await {
+        Ok(namespaces) => find_document(&storage, &namespaces, doc_id).await,
+        Err(e) => Err(e),
+    };
+    let (namespace_id, metadata) = match found {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            warn!(document_id = %doc_id, "Document not found");
+            return ToolResult::error(
+                &tool_call.id,
+                format!("Document {} not found in the active collections.", doc_id),
+            );
+        }
+        Err(e) => {
+            warn!(document_id = %doc_id, error = %e, "Error reading document");
+            return ToolResult::error(&tool_call.id, format!("Error reading document: {}", e));
+        }
+    };
+
+    let collection_name = match ctx
+        .collections
+        .as_ref()
+        .and_then(|cols| cols.iter().find(|c| c.id == namespace_id.to_string()))
+    {
+        Some(collection) => Some(collection.name.clone()),
+        None => storage
+            .get_collection_metadata(namespace_id)
+            .await
+            .ok()
+            .flatten()
+            .map(|m| m.name),
+    };
+    let text = storage
+        .get_document_text(namespace_id, doc_id)
+        .await
+        .ok()
+        .flatten()
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    let ocr_pending = matches!(
+        storage.get_ocr_task(namespace_id, doc_id).await,
+        Ok(Some(_))
+    );
+    // Links can outlive the document they point at; skip deleted ones.
+    let mut near_duplicates = Vec::new();
+    for dup in &metadata.near_duplicates {
+        if let Ok(Some(other)) = storage.get_document(namespace_id, &dup.doc_id).await {
+            near_duplicates.push(format!(
+                "{} ({}, {:.0}% similar)",
+                other.name,
+                other.id,
+                dup.similarity * 100.0
+            ));
+        }
+    }
+    drop(storage);
+
+    let extraction = match (&text, ocr_pending) {
+        (_, true) => "OCR still in progress; text may be incomplete".to_string(),
+        (None, false) => "no text extracted yet".to_string(),
+        (Some(text), false) => describe_extraction(text, &metadata.page_boundaries),
+    };
+    let language = text.as_deref().map(describe_language).unwrap_or("unknown");
+    let tags = if metadata.tags.is_empty() {
+        "none".to_string()
+    } else {
+        metadata.tags.join(", ")
+    };
+
+    let mut lines = vec![
+        format!("Name: {}", metadata.name),
+        format!("ID: {}", metadata.id),
+    ];
+    if let Some(collection_name) = collection_name {
+        lines.push(format!("Collection: {}", collection_name));
+    }
+    lines.extend([
+        format!("Type: {}", metadata.file_type),
+        format!("Pages: {}", metadata.page_count),
+        format!("Imported: {}", metadata.created_at),
+        format!("Tags: {}", tags),
+        format!("Language: {}", language),
+        format!("Extraction: {}", extraction),
+    ]);
+    if !near_duplicates.is_empty() {
+        lines.push(format!("Near-duplicates: {}", near_duplicates.join("; ")));
+    }
+
+    ToolResult::ok(&tool_call.id, lines.join("\n"))
+}
+
+/// Rate extraction by how many pages yielded text. Scanned pages that
+/// were never OCR'd and garbled encodings both show up here.
+fn describe_extraction(text: &str, page_boundaries: &[usize]) -> String {
+    let pages = page_spans(text, page_boundaries);
+    let with_text = pages
+        .iter()
+        .filter(|range| {
+            text[(*range).clone()]
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .count()
+                >= MIN_PAGE_CHARS
+        })
+        .count();
+    let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+    let garbled = text
+        .chars()
+        .filter(|&c| c == char::REPLACEMENT_CHARACTER)
+        .count();
+
+    let quality = if with_text == pages.len() && garbled * 100 <= chars {
+        "good"
+    } else if with_text * 2 >= pages.len() {
+        "partial"
+    } else {
+        "poor"
+    };
+    let mut description = format!(
+        "{} ({} of {} pages have text, ~{} characters per page",
+        quality,
+        with_text,
+        pages.len(),
+        chars / pages.len()
+    );
+    if garbled > 0 {
+        description.push_str(&format!(", {} unreadable characters", garbled));
+    }
+    description.push(')');
+    description
+}
+
+/// Best-effort language label for the agent.
+pub(crate) fn describe_language(text: &str) -> &'static str {
+    if text.split_whitespace().nth(19).is_none() {
+        "unknown (too little text)"
+    } else if crate::models::looks_english(text) {
+        "English (estimated)"
+    } else {
+        "not English (estimated)"
+    }
+}
+
+/// Collect dated events from documents into a chronological timeline,
+/// returned as JSON so the frontend can render it.
+async fn execute_extract_timeline(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let document_ids = string_list(&tool_call.arguments["document_ids"]);
+
+    info!(documents = document_ids.len(), "Extracting timeline");
+
+    if document_ids.is_empty() {
+        return ToolResult::error(
+            &tool_call.id,
+            "Provide document_ids to build a timeline from.",
+        );
+    }
+    if document_ids.len() > MAX_SCAN_DOCUMENTS {
+        return ToolResult::error(
+            &tool_call.id,
+            format!(
+                "Too many documents ({}). Build the timeline from at most {} at a time.",
+                document_ids.len(),
+                MAX_SCAN_DOCUMENTS
+            ),
+        );
+    }
+
+    let mut timeline = Timeline::default();
+    let missing = scan_documents(ctx, &document_ids, |text, citation| {
+        timeline.add_text(text, citation)
+    })
+    .await;
+
+    let mut events = timeline.into_events();
+    let total_events = events.len();
+    if total_events > MAX_TIMELINE_EVENTS {
+        // Keep the best-corroborated events, then restore date order.
+        events.sort_by(|a, b| b.citations.len().cmp(&a.citations.len()));
+        events.truncate(MAX_TIMELINE_EVENTS);
+        events.sort_by(|a, b| a.date.cmp(&b.date));
+    }
+
+    info!(events = events.len(), total_events, "Extracted timeline");
+
+    let content = serde_json::json!({
+        "events": events,
+        "total_events": total_events,
+        "missing_documents": missing,
+    });
+
+    ToolResult::ok(&tool_call.id, content.to_string())
+}
+
+/// Find people, organizations, places and amounts in documents or in the
+/// passages a search returns, with mention counts and source pages.
+async fn execute_extract_entities(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let document_ids = string_list(&tool_call.arguments["document_ids"]);
+    let query = tool_call.arguments["query"]
+        .as_str()
+        .map(str::trim)
+        .filter(|q| !q.is_empty());
+    let kinds: Vec<EntityKind> = tool_call.arguments["types"]
+        .as_array()
+        .map(|types| {
+            types
+                .iter()
+                .filter_map(|t| serde_json::from_value(t.clone()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    info!(documents = document_ids.len(), query = ?query, "Extracting entities");
+
+    let error = |content: String| ToolResult::error(&tool_call.id, content);
+    if document_ids.is_empty() && query.is_none() {
+        return error("Provide document_ids or a query to extract entities from.".to_string());
+    }
+    if document_ids.len() > MAX_SCAN_DOCUMENTS {
+        return error(format!(
+            "Too many documents ({}). Extract entities from at most {} at a time.",
+            document_ids.len(),
+            MAX_SCAN_DOCUMENTS
+        ));
+    }
+
+    let mut index = EntityIndex::default();
+    let missing = scan_documents(ctx, &document_ids, |text, citation| {
+        index.add_text(text, citation)
+    })
+    .await;
+    let mut passages = 0;
+    if let Some(query) = query {
+        let hits = match search_hits(ctx, query, ENTITY_SEARCH_LIMIT).await {
+            Ok(hits) => hits,
+            Err(e) => return error(format!("Search error: {}", e)),
+        };
+        passages = hits.len();
+        if let Err(e) = scan_hits(&ctx.state.search, &hits, |text, citation| {
+            index.add_text(text, citation)
+        }) {
+            return error(format!("Error reading index: {}", e));
+        }
+    }
+
+    let mut entities = index.into_entities();
+    if !kinds.is_empty() {
+        entities.retain(|e| kinds.contains(&e.kind));
+    }
+    let total_entities = entities.len();
+    if total_entities > MAX_ENTITIES {
+        // Keep the most mentioned, still grouped by kind.
+        entities.sort_by(|a, b| b.count.cmp(&a.count));
+        entities.truncate(MAX_ENTITIES);
+        entities.sort_by_key(|e| e.kind);
+    }
+
+    info!(
+        entities = entities.len(),
+        total_entities, "Extracted entities"
+    );
+
+    let content = serde_json::json!({
+        "entities": entities,
+        "total_entities": total_entities,
+        "passages_scanned": passages,
+        "missing_documents": missing,
+    });
+
+    ToolResult::ok(&tool_call.id, content.to_string())
+}
+
+/// Summarize a whole document with the chat model, section by section, so
+/// documents far longer than its context window still fit.
+async fn execute_summarize_document(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let doc_id = tool_call.arguments["document_id"].as_str().unwrap_or("");
+    let focus = tool_call.arguments["focus"]
+        .as_str()
+        .map(str::trim)
+        .filter(|f| !f.is_empty());
+
+    info!(document_id = %doc_id, focus = ?focus, "Summarizing document");
+
+    let error = |content: String| ToolResult::error(&tool_call.id, content);
+
+    let (_, metadata, text) = match load_document_text(ctx, doc_id).await {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            warn!(document_id = %doc_id, "Document not found");
+            return error(format!(
+                "Document {} not found in the active collections.",
+                doc_id
+            ));
+        }
+        Err(e) => {
+            warn!(document_id = %doc_id, error = %e, "Error reading document");
+            return error(format!("Error reading document: {}", e));
+        }
+    };
+
+    let lease = match ctx.state.models.acquire_chat().await {
+        Ok(Some(lease)) => lease,
+        Ok(None) => return error("No chat model is configured.".to_string()),
+        Err(e) => return error(format!("Failed to load chat model: {}", e)),
+    };
+
+    let pages: Vec<&str> = page_spans(&text, &metadata.page_boundaries)
+        .into_iter()
+        .map(|range| &text[range])
+        .collect();
+    let prompts = PromptLibrary::load(&ctx.state.config.prompts_dir());
+    let summary = match summarize_pages(
+        lease.provider(),
+        &prompts,
+        Some(&ctx.state.completion_cache),
+        &metadata.name,
+        &pages,
+        focus,
+        &ctx.cancel,
+    )
+    .await
+    {
+        Ok(summary) => summary,
+        Err(e) => {
+            warn!(document_id = %doc_id, error = %e, "Summarization failed");
+            return error(format!("Failed to summarize {}: {}", metadata.name, e));
+        }
+    };
+
+    info!(
+        document_id = %doc_id,
+        sections = summary.sections,
+        calls = summary.calls,
+        "Summarized document"
+    );
+
+    ToolResult::ok(
+        &tool_call.id,
+        format!(
+            "Summary of {} [{}], {} page{} in {} section{}:\n\n{}",
+            metadata.name,
+            metadata.id,
+            pages.len(),
+            if pages.len() == 1 { "" } else { "s" },
+            summary.sections,
+            if summary.sections == 1 { "" } else { "s" },
+            summary.text
+        ),
+    )
+}
+
+/// Diff two documents passage by passage, e.g. two drafts of a contract,
+/// optionally with the chat model's description of what changed.
+async fn execute_compare_documents(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let first_id = tool_call.arguments["first_document_id"]
+        .as_str()
+        .unwrap_or("");
+    let second_id = tool_call.arguments["second_document_id"]
+        .as_str()
+        .unwrap_or("");
+    let describe = tool_call.arguments["describe"].as_bool().unwrap_or(false);
+
+    info!(first = %first_id, second = %second_id, describe, "Comparing documents");
+
+    let error = |content: String| ToolResult::error(&tool_call.id, content);
+
+    let mut documents = Vec::with_capacity(2);
+    for doc_id in [first_id, second_id] {
+        match load_document_text(ctx, doc_id).await {
+            Ok(Some(found)) => documents.push(found),
+            Ok(None) => {
+                warn!(document_id = %doc_id, "Document not found");
+                return error(format!(
+                    "Document {} not found in the active collections.",
+                    doc_id
+                ));
+            }
+            Err(e) => {
+                warn!(document_id = %doc_id, error = %e, "Error reading document");
+                return error(format!("Error reading document: {}", e));
             }
+        }
+    }
+    let (second_ns, second_meta, second_text) = documents.pop().unwrap();
+    let (first_ns, first_meta, first_text) = documents.pop().unwrap();
+
+    let document_passages = |metadata: &DocumentMetadata, text: &str| {
+        let pages: Vec<&str> = page_spans(text, &metadata.page_boundaries)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+        (pages.len(), compare::passages(&pages))
+    };
+    let (first_pages, first_passages) = document_passages(&first_meta, &first_text);
+    let (second_pages, second_passages) = document_passages(&second_meta, &second_text);
+    let comparison = compare::compare(&first_passages, &second_passages);
+    let total_changes = comparison.changes.len();
+    let mut changes = comparison.changes;
+    changes.truncate(MAX_COMPARE_CHANGES);
 
-            // Format terms as a readable list
-            let mut output = format!("Top {} terms by document frequency:\n\n", terms.len());
+    info!(
+        unchanged = comparison.unchanged,
+        total_changes, "Compared documents"
+    );
 
-            for term in &terms {
-                output.push_str(&format!("- {} ({} docs)\n", term.term, term.doc_count));
+    let mut content = serde_json::json!({
+        "first": {
+            "id": first_meta.id,
+            "name": first_meta.name,
+            "pages": first_pages,
+        },
+        "second": {
+            "id": second_meta.id,
+            "name": second_meta.name,
+            "pages": second_pages,
+        },
+        "unchanged_passages": comparison.unchanged,
+        "total_changes": total_changes,
+        "changes": changes,
+    });
+
+    if describe && !changes.is_empty() {
+        match describe_changes(ctx, &first_meta.name, &second_meta.name, &changes).await {
+            Ok(description) => content["description"] = description.into(),
+            Err(e) => {
+                warn!(error = %e, "Failed to describe changes");
+                content["description_error"] = e.to_string().into();
             }
+        }
+    }
 
-            info!(term_count = terms.len(), "Retrieved collection terms");
+    let cite = |namespace_id: NamespaceId, metadata: &DocumentMetadata, passage: &Passage| {
+        SourcePassage::new(
+            &namespace_id.to_string(),
+            &metadata.id,
+            &metadata.name,
+            Some(passage.page),
+            &passage.text,
+        )
+    };
+    let citations = changes
+        .iter()
+        .flat_map(|change| {
+            let first = change
+                .first
+                .as_ref()
+                .map(|p| cite(first_ns, &first_meta, p));
+            let second = change
+                .second
+                .as_ref()
+                .map(|p| cite(second_ns, &second_meta, p));
+            first.into_iter().chain(second)
+        })
+        .collect();
+
+    ToolResult {
+        citations,
+        ..ToolResult::ok(&tool_call.id, content.to_string())
+    }
+}
+
+/// Have the chat model describe `changes` with the `compare` template.
+/// Changes that don't fit the model's context are left out.
+async fn describe_changes(
+    ctx: &AgentContext,
+    first_name: &str,
+    second_name: &str,
+    changes: &[Change],
+) -> anyhow::Result<String> {
+    let Some(lease) = ctx.state.models.acquire_chat().await? else {
+        anyhow::bail!("No chat model is configured.");
+    };
+    let budget = summarize::input_budget(lease.provider().context_window());
+
+    let mut listing = String::new();
+    for change in changes {
+        let entry = match change.kind {
+            ChangeKind::Added => {
+                let new = change.second.as_ref().unwrap();
+                format!("Added (second, page {}):\n+ {}", new.page, new.text)
+            }
+            ChangeKind::Removed => {
+                let old = change.first.as_ref().unwrap();
+                format!("Removed (first, page {}):\n- {}", old.page, old.text)
+            }
+            ChangeKind::Changed => {
+                let (old, new) = (
+                    change.first.as_ref().unwrap(),
+                    change.second.as_ref().unwrap(),
+                );
+                format!(
+                    "Changed (first page {}, second page {}):\n- {}\n+ {}",
+                    old.page, new.page, old.text, new.text
+                )
+            }
+        };
+        if !listing.is_empty() && listing.len() + entry.len() + 2 > budget {
+            break;
+        }
+        if !listing.is_empty() {
+            listing.push_str("\n\n");
+        }
+        listing.push_str(&entry);
+    }
+
+    let prompts = PromptLibrary::load(&ctx.state.config.prompts_dir());
+    let prompt = prompts.render(
+        "compare",
+        &[
+            ("first", first_name),
+            ("second", second_name),
+            ("changes", &listing),
+        ],
+    )?;
+    summarize::complete(
+        lease.provider(),
+        Some(&ctx.state.completion_cache),
+        prompt,
+        &ctx.cancel,
+    )
+    .await
+}
 
-            ToolResult {
-                tool_call_id: tool_call.id.clone(),
-                content: output,
-                is_error: false,
+/// The strings in a JSON array argument; anything else is empty.
+fn string_list(value: &serde_json::Value) -> Vec<&str> {
+    value
+        .as_array()
+        .map(|items| items.iter().filter_map(|item| item.as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// Feed each page of each document to `scan`. Returns the IDs that
+/// couldn't be found or read.
+async fn scan_documents(
+    ctx: &AgentContext,
+    document_ids: &[&str],
+    mut scan: impl FnMut(&str, &Citation),
+) -> Vec<String> {
+    let mut missing = Vec::new();
+    for doc_id in document_ids {
+        match load_document_text(ctx, doc_id).await {
+            Ok(Some((_, metadata, text))) => {
+                for (i, range) in page_spans(&text, &metadata.page_boundaries)
+                    .into_iter()
+                    .enumerate()
+                {
+                    let citation = Citation {
+                        document_id: metadata.id.clone(),
+                        document_name: metadata.name.clone(),
+                        page: i + 1,
+                    };
+                    scan(&text[range], &citation);
+                }
             }
+            Ok(None) => missing.push(doc_id.to_string()),
+            Err(e) => {
+                warn!(document_id = %doc_id, error = %e, "Error reading document");
+                missing.push(doc_id.to_string());
+            }
+        }
+    }
+    missing
+}
+
+/// Feed each search hit's passage to `scan`, citing the page it starts on.
+fn scan_hits(
+    index: &milli::Index,
+    hits: &[search::SearchHit],
+    mut scan: impl FnMut(&str, &Citation),
+) -> anyhow::Result<()> {
+    let rtxn = index.read_txn()?;
+    for hit in hits {
+        let doc = match search::get_document(index, &rtxn, hit.doc_id) {
+            Ok(Some(d)) => d,
+            _ => continue,
+        };
+        let get_str = |key: &str| -> String {
+            doc.get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let citation = Citation {
+            document_id: get_str("parent_id"),
+            document_name: get_str("parent_name"),
+            page: doc
+                .get("start_page")
+                .and_then(|v| v.as_u64())
+                .unwrap_or_default()
+                .max(1) as usize,
+        };
+        scan(&get_str("content"), &citation);
+    }
+    Ok(())
+}
+
+/// Find a document in the active collections (every collection when none
+/// are selected) and load its extracted text.
+pub(crate) async fn load_document_text(
+    ctx: &AgentContext,
+    doc_id: &str,
+) -> anyhow::Result<Option<(NamespaceId, DocumentMetadata, String)>> {
+    let storage = ctx.state.storage.read().await;
+    let namespaces = scope_namespaces(ctx, &storage).await?;
+    let Some((namespace_id, metadata)) = find_document(&storage, &namespaces, doc_id).await? else {
+        return Ok(None);
+    };
+    let text = storage
+        .get_document_text(namespace_id, doc_id)
+        .await?
+        .unwrap_or_default();
+    Ok(Some((
+        namespace_id,
+        metadata,
+        String::from_utf8_lossy(&text).into_owned(),
+    )))
+}
+
+/// Collections a tool may touch: the active ones, or every collection
+/// when none are selected.
+async fn scope_namespaces(
+    ctx: &AgentContext,
+    storage: &Storage,
+) -> anyhow::Result<Vec<NamespaceId>> {
+    Ok(match ctx.collection_ids() {
+        Some(ids) => ids.iter().filter_map(|id| id.parse().ok()).collect(),
+        None => storage
+            .list_collections()
+            .await?
+            .into_iter()
+            .map(|(namespace_id, _)| namespace_id)
+            .collect(),
+    })
+}
+
+/// The first of `namespaces` holding `doc_id`, with the document's metadata.
+async fn find_document(
+    storage: &Storage,
+    namespaces: &[NamespaceId],
+    doc_id: &str,
+) -> anyhow::Result<Option<(NamespaceId, DocumentMetadata)>> {
+    for &namespace_id in namespaces {
+        if let Some(metadata) = storage.get_document(namespace_id, doc_id).await? {
+            return Ok(Some((namespace_id, metadata)));
+        }
+    }
+    Ok(None)
+}
+
+/// Arguments of a `tag_document` call. Missing or malformed lists are
+/// treated as empty.
+struct TagArgs {
+    document_ids: Vec<String>,
+    add: Vec<String>,
+    remove: Vec<String>,
+}
+
+impl TagArgs {
+    fn parse(arguments: &serde_json::Value) -> Self {
+        let strings = |key: &str| -> Vec<String> {
+            arguments[key]
+                .as_array()
+                .map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|v| v.as_str())
+                        .map(|v| v.trim().to_string())
+                        .filter(|v| !v.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Self {
+            document_ids: strings("document_ids"),
+            add: strings("add_tags"),
+            remove: strings("remove_tags"),
         }
+    }
+}
+
+fn quoted_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("\"{}\"", item))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Add and remove tags on documents. Only reached once the user has
+/// approved the call (see [`confirmation_prompt`]).
+async fn execute_tag_document(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let args = TagArgs::parse(&tool_call.arguments);
+
+    info!(
+        documents = args.document_ids.len(),
+        add = ?args.add,
+        remove = ?args.remove,
+        "Tagging documents"
+    );
+
+    if args.document_ids.is_empty() || (args.add.is_empty() && args.remove.is_empty()) {
+        return ToolResult::error(
+            &tool_call.id,
+            "Provide document_ids and at least one tag in add_tags or remove_tags.".to_string(),
+        );
+    }
+
+    let storage = ctx.state.storage.read().await;
+    let namespaces = match scope_namespaces(ctx, &storage).await {
+        Ok(namespaces) => namespaces,
         Err(e) => {
-            warn!(error = %e, "Failed to get collection terms");
-            ToolResult {
-                tool_call_id: tool_call.id.clone(),
-                content: format!("Error getting collection terms: {}", e),
-                is_error: true,
+            warn!(error = %e, "Failed to list collections");
+            return ToolResult::error(&tool_call.id, format!("Error tagging documents: {}", e));
+        }
+    };
+
+    let mut updated = Vec::new();
+    let mut failed = Vec::new();
+    for doc_id in &args.document_ids {
+        let result = match find_document(&storage, &namespaces, doc_id).await {
+            Ok(Some((namespace_id, metadata))) => storage
+                .update_document_tags(namespace_id, doc_id, &args.add, &args.remove)
+                .await
+                .map(|tags| tags.map(|tags| (metadata.name, tags))),
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(Some((name, tags))) => updated.push((name, tags)),
+            Ok(None) => failed.push(format!("{} (not found)", doc_id)),
+            Err(e) => {
+                warn!(document_id = %doc_id, error = %e, "Failed to update tags");
+                failed.push(format!("{} ({})", doc_id, e));
             }
         }
     }
+    drop(storage);
+
+    let mut output = format!(
+        "Updated tags on {} document{}.",
+        updated.len(),
+        if updated.len() == 1 { "" } else { "s" }
+    );
+    for (name, tags) in &updated {
+        let tags = if tags.is_empty() {
+            "no tags".to_string()
+        } else {
+            tags.join(", ")
+        };
+        output.push_str(&format!("\n- {}: {}", name, tags));
+    }
+    if !failed.is_empty() {
+        output.push_str(&format!("\n\nCould not tag: {}", failed.join(", ")));
+    }
+
+    info!(
+        updated = updated.len(),
+        failed = failed.len(),
+        "Tagged documents"
+    );
+
+    ToolResult {
+        is_error: updated.is_empty(),
+        ..ToolResult::ok(&tool_call.id, output)
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::search::{self, ChunkToIndex};
-    use crate::AppState;
-    use crate::CollectionInfo;
-    use milli::update::IndexerConfig;
+/// The active collection a tool saves into: the one named by the
+/// `collection_id` argument, or the only active one when none is named.
+/// The error explains to the model what to pass instead.
+fn target_collection<'a>(
+    ctx: &'a AgentContext,
+    arguments: &serde_json::Value,
+    what: &str,
+) -> Result<&'a CollectionInfo, String> {
+    let active = ctx.collections.as_deref().unwrap_or_default();
+    let collection = match arguments["collection_id"].as_str() {
+        Some(id) => active.iter().find(|c| c.id == id),
+        None if active.len() == 1 => active.first(),
+        None => {
+            return Err(format!(
+                "Several collections are active. Pass the collection_id to save the {} to.",
+                what
+            ))
+        }
+    };
+    collection.ok_or_else(|| format!("The {} can only be saved to an active collection.", what))
+}
 
-    /// Create a minimal AppState for testing (no storage operations)
-    async fn create_test_state() -> AppState {
-        let temp_dir = tempfile::tempdir().unwrap();
-        let config = crate::Config {
-            data_dir: temp_dir.path().to_path_buf(),
-            iroh_dir: temp_dir.path().join("iroh"),
-            search_dir: temp_dir.path().join("search"),
-            settings_file: temp_dir.path().join("settings.json"),
-            conversations_dir: temp_dir.path().join("conversations"),
+/// Save a fact to the active collection's memory. Only reached once the
+/// user has approved it (see [`confirmation_prompt`]).
+async fn execute_remember(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let error = |content: String| ToolResult::error(&tool_call.id, content);
+
+    let fact = tool_call.arguments["fact"].as_str().unwrap_or_default();
+    if fact.trim().is_empty() {
+        return error("Provide the fact to remember.".to_string());
+    }
+
+    let collection = match target_collection(ctx, &tool_call.arguments, "fact") {
+        Ok(collection) => collection,
+        Err(message) => return error(message),
+    };
+
+    let memory_dir = ctx.state.config.memory_dir();
+    match crate::memory::add_memory(&memory_dir, &collection.id, fact) {
+        Ok(entry) => {
+            info!(collection_id = %collection.id, memory_id = %entry.id, "Remembered fact");
+            ToolResult::ok(
+                &tool_call.id,
+                format!("Remembered for {}: {}", collection.name, entry.text),
+            )
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to save memory");
+            error(format!("Error saving the fact: {}", e))
+        }
+    }
+}
+
+/// Save a search to an active collection, optionally with alerts on new
+/// matches. Only reached once the user has approved it (see
+/// [`confirmation_prompt`]).
+async fn execute_save_search(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let error = |content: String| ToolResult::error(&tool_call.id, content);
+
+    let query = tool_call.arguments["query"].as_str().unwrap_or_default();
+    if query.trim().is_empty() {
+        return error("Provide the query to save.".to_string());
+    }
+    let name = tool_call.arguments["name"].as_str().unwrap_or_default();
+    let notify = tool_call.arguments["notify"].as_bool().unwrap_or(false);
+
+    let collection = match target_collection(ctx, &tool_call.arguments, "search") {
+        Ok(collection) => collection,
+        Err(message) => return error(message),
+    };
+
+    // What matches now isn't news; alerts start from here.
+    let scoped = AgentContext::new(ctx.state.clone(), Some(vec![collection.clone()]));
+    let seen = match search_passages(&scoped, query, SEARCH_LIMIT).await {
+        Ok(passages) => {
+            let mut ids: Vec<String> = passages.into_iter().map(|p| p.document_id).collect();
+            ids.sort();
+            ids.dedup();
+            ids
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to run search before saving it");
+            Vec::new()
+        }
+    };
+
+    let dir = ctx.state.config.saved_searches_dir();
+    match crate::saved_searches::save_search(&dir, &collection.id, name, query, notify, seen) {
+        Ok(search) => {
+            info!(collection_id = %collection.id, search_id = %search.id, notify, "Saved search");
+            ToolResult::ok(
+                &tool_call.id,
+                format!(
+                    "Saved search \"{}\" to {}{}.",
+                    search.name,
+                    collection.name,
+                    if search.notify {
+                        "; new matching documents will be reported"
+                    } else {
+                        ""
+                    }
+                ),
+            )
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to save search");
+            error(format!("Error saving the search: {}", e))
+        }
+    }
+}
+
+/// File name for a report: the title without characters file systems
+/// reject, with a `.md` extension.
+fn report_file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .filter(|c| {
+            !c.is_control() && !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+        })
+        .take(MAX_REPORT_NAME_CHARS)
+        .collect();
+    let name = name.trim().trim_end_matches(".md").trim();
+    if name.is_empty() {
+        "Report.md".to_string()
+    } else {
+        format!("{}.md", name)
+    }
+}
+
+/// Save a Markdown report into an active collection, where it is embedded,
+/// indexed and synced like an imported document. Only reached once the
+/// user has approved it (see [`confirmation_prompt`]).
+async fn execute_write_report(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let error = |content: String| ToolResult::error(&tool_call.id, content);
+
+    let title = tool_call.arguments["title"].as_str().unwrap_or_default();
+    let content = tool_call.arguments["content"].as_str().unwrap_or_default();
+    if title.trim().is_empty() || content.trim().is_empty() {
+        return error("Provide the report's title and content.".to_string());
+    }
+
+    let collection = match target_collection(ctx, &tool_call.arguments, "report") {
+        Ok(collection) => collection,
+        Err(message) => return error(message),
+    };
+    let Ok(namespace_id) = collection.id.parse::<NamespaceId>() else {
+        return error(format!("Invalid collection ID: {}", collection.id));
+    };
+
+    // The title heads the document so it is part of what gets searched.
+    let title = title.trim();
+    let markdown = if content.trim_start().starts_with('#') {
+        content.trim().to_string()
+    } else {
+        format!("# {}\n\n{}", title, content.trim())
+    };
+    let file_name = report_file_name(title);
+
+    let result = {
+        let storage = ctx.state.storage.read().await;
+        storage
+            .store_markdown_source(namespace_id, &file_name, &markdown)
+            .await
+    };
+    match result {
+        Ok(doc_id) => {
+            info!(collection_id = %collection.id, document_id = %doc_id, "Saved report");
+            ToolResult::ok(
+                &tool_call.id,
+                format!(
+                    "Saved the report to {} as {} [{}]. It becomes searchable once indexed.",
+                    collection.name, file_name, doc_id
+                ),
+            )
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to save report");
+            error(format!("Error saving the report: {}", e))
+        }
+    }
+}
+
+/// File name for an exported original: the document's name without any
+/// directory part, with the extension its type implies.
+fn export_file_name(name: &str, file_type: &str) -> String {
+    let name = std::path::Path::new(name)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(str::trim)
+        .filter(|n| !n.is_empty() && *n != "..")
+        .unwrap_or("document");
+    let extension = match file_type {
+        "application/pdf" | "pdf" => "pdf",
+        MARKDOWN_FILE_TYPE => "md",
+        _ => return name.to_string(),
+    };
+    let has_extension = name
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case(extension));
+    if has_extension {
+        name.to_string()
+    } else {
+        format!("{}.{}", name, extension)
+    }
+}
+
+async fn execute_get_source_file(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let error = |content: String| ToolResult::error(&tool_call.id, content);
+
+    let doc_id = tool_call.arguments["document_id"].as_str().unwrap_or("");
+    info!(document_id = %doc_id, "Exporting source file");
+
+    let storage = ctx.state.storage.read().await;
+    let found = match scope_namespaces(ctx, &storage).await {
+        Ok(namespaces) => find_document(&storage, &namespaces, doc_id).await,
+        Err(e) => Err(e),
+    };
+    let (namespace_id, metadata) = match found {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            return error(format!(
+                "Document {} not found in the active collections.",
+                doc_id
+            ))
+        }
+        Err(e) => return error(format!("Error reading document: {}", e)),
+    };
+    let source = match storage.get_document_source(namespace_id, doc_id).await {
+        Ok(Some(source)) => source,
+        Ok(None) => {
+            return error(format!(
+                "The original file of {} isn't available on this device yet.",
+                metadata.name
+            ))
+        }
+        Err(e) => return error(format!("Error reading the original file: {}", e)),
+    };
+    drop(storage);
+
+    // One directory per document keeps the original file name for the
+    // viewer without collisions between documents.
+    let dir = ctx.state.config.exports_dir().join(&metadata.id);
+    let path = dir.join(export_file_name(&metadata.name, &metadata.file_type));
+    let written = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, &source));
+    if let Err(e) = written {
+        warn!(document_id = %doc_id, error = %e, "Failed to export source file");
+        return error(format!("Error exporting the original file: {}", e));
+    }
+    let path = path.to_string_lossy().into_owned();
+
+    let content = format!(
+        "Exported the original of {} [{}] to {}. The user can open it from the link shown \
+         with this result; tell them which page to look at, if it matters.",
+        metadata.name, metadata.id, path
+    );
+    ToolResult {
+        source_file: Some(SourceFile {
+            collection_id: namespace_id.to_string(),
+            document_id: metadata.id,
+            document_name: metadata.name,
+            file_type: metadata.file_type,
+            path,
+        }),
+        ..ToolResult::ok(&tool_call.id, content)
+    }
+}
+
+async fn execute_view_page_image(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let error = |content: String| ToolResult::error(&tool_call.id, content);
+
+    let doc_id = tool_call.arguments["document_id"].as_str().unwrap_or("");
+    let page = tool_call.arguments["page"].as_u64().unwrap_or(0) as usize;
+    info!(document_id = %doc_id, page, "Rendering page image");
+
+    let storage = ctx.state.storage.read().await;
+    let found = match scope_namespaces(ctx, &storage).await {
+        Ok(namespaces) => find_document(&storage, &namespaces, doc_id).await,
+        Err(e) => Err(e),
+    };
+    let (namespace_id, metadata) = match found {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            return error(format!(
+                "Document {} not found in the active collections.",
+                doc_id
+            ))
+        }
+        Err(e) => return error(format!("Error reading document: {}", e)),
+    };
+    if !metadata.file_type.contains("pdf") {
+        return error(format!(
+            "{} isn't a PDF; only PDF pages can be viewed as images.",
+            metadata.name
+        ));
+    }
+    if page == 0 || page > metadata.page_count {
+        return error(format!(
+            "{} has {} pages; page {} doesn't exist.",
+            metadata.name, metadata.page_count, page
+        ));
+    }
+    let source = match storage.get_document_source(namespace_id, doc_id).await {
+        Ok(Some(source)) => source,
+        Ok(None) => {
+            return error(format!(
+                "The original file of {} isn't available on this device yet.",
+                metadata.name
+            ))
+        }
+        Err(e) => return error(format!("Error reading the original file: {}", e)),
+    };
+    drop(storage);
+
+    // mupdf is CPU-bound.
+    let rendered = tokio::task::spawn_blocking(move || {
+        crate::pdf::rasterize_page(&source, page - 1, VIEW_DPI)
+    })
+    .await;
+    let png = match rendered {
+        Ok(Ok(png)) => png,
+        Ok(Err(e)) => return error(format!("Error rendering page {}: {}", page, e)),
+        Err(e) => return error(format!("Error rendering page {}: {}", page, e)),
+    };
+
+    let scanned = if metadata.scanned_pages.contains(&page) {
+        " Its text was read by OCR, so prefer what you see in the image where they differ."
+    } else {
+        ""
+    };
+    let content = format!(
+        "Page {} of {} [{}] is attached as an image.{}",
+        page, metadata.name, metadata.id, scanned
+    );
+    ToolResult {
+        page_image: Some(PageImage {
+            collection_id: namespace_id.to_string(),
+            document_id: metadata.id,
+            document_name: metadata.name,
+            page,
+            media_type: "image/png".to_string(),
+            data: BASE64.encode(png),
+        }),
+        ..ToolResult::ok(&tool_call.id, content)
+    }
+}
+
+/// Byte range of every page in `text`, one per boundary, so index `i` is
+/// page `i + 1`. Blank pages get empty ranges rather than being dropped.
+/// Stale boundaries are clamped to the text, and anything after the last
+/// boundary belongs to the last page (as in `char_offset_to_page`).
+pub fn page_spans(text: &str, page_boundaries: &[usize]) -> Vec<Range<usize>> {
+    if page_boundaries.is_empty() {
+        return vec![0..text.len()];
+    }
+
+    let mut spans = Vec::with_capacity(page_boundaries.len());
+    let mut start = 0;
+    for &boundary in page_boundaries {
+        let mut end = boundary.clamp(start, text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        spans.push(start..end);
+        start = end;
+    }
+    if let Some(last) = spans.last_mut() {
+        last.end = text.len();
+    }
+    spans
+}
+
+async fn execute_list_documents(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    info!("Listing documents");
+
+    // Get collection IDs to list from
+    let collection_ids = ctx.collection_ids();
+
+    if collection_ids.is_none()
+        || collection_ids
+            .as_ref()
+            .map(|c| c.is_empty())
+            .unwrap_or(true)
+    {
+        return ToolResult::error(
+            &tool_call.id,
+            "No collections selected. Please select collections to list documents from."
+                .to_string(),
+        );
+    }
+
+    let collection_ids = collection_ids.unwrap();
+
+    // Build a lookup map from collection_id -> collection_name
+    let collection_names: std::collections::HashMap<String, String> = ctx
+        .collections
+        .as_ref()
+        .map(|cols| {
+            cols.iter()
+                .map(|c| (c.id.clone(), c.name.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let storage = ctx.state.storage.read().await;
+    let mut all_documents = Vec::new();
+
+    for collection_id in &collection_ids {
+        let namespace_id: iroh_docs::NamespaceId = match collection_id.parse() {
+            Ok(id) => id,
+            Err(_) => {
+                warn!(collection_id = %collection_id, "Invalid collection ID, skipping");
+                continue;
+            }
+        };
+
+        let collection_name = collection_names
+            .get(collection_id)
+            .cloned()
+            .unwrap_or_else(|| collection_id.chars().take(8).collect::<String>() + "...");
+
+        match storage.list_documents(namespace_id).await {
+            Ok(documents) => {
+                for doc in documents {
+                    all_documents.push((collection_name.clone(), doc));
+                }
+            }
+            Err(e) => {
+                warn!(collection_id = %collection_id, error = %e, "Failed to list documents");
+            }
+        }
+    }
+
+    drop(storage);
+
+    if all_documents.is_empty() {
+        return ToolResult::ok(
+            &tool_call.id,
+            "No documents found in the selected collections.",
+        );
+    }
+
+    // Sort by collection name, then document name
+    all_documents.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.name.cmp(&b.1.name)));
+
+    let total_docs = all_documents.len();
+    let total_pages: usize = all_documents.iter().map(|(_, d)| d.page_count).sum();
+
+    let mut entries = Vec::with_capacity(total_docs);
+    let mut current_collection = None;
+    for (collection_name, doc) in &all_documents {
+        // Format page info
+        let page_info = if doc.page_count == 1 {
+            "1p".to_string()
+        } else {
+            format!("{}p", doc.page_count)
+        };
+        // Full ID needed for read_chunk tool
+        let line = format!("- {} ({}) [{}]", doc.name, page_info, doc.id);
+
+        // Add collection header when it changes
+        if current_collection != Some(collection_name) {
+            current_collection = Some(collection_name);
+            entries.push(PageEntry::text(format!("## {}\n{}", collection_name, line)));
+        } else {
+            entries.push(PageEntry::text(line));
+        }
+    }
+
+    info!(
+        document_count = total_docs,
+        total_pages = total_pages,
+        "Listed documents"
+    );
+
+    ctx.state.result_pages.first_page(
+        &tool_call.id,
+        format!(
+            "Found {} document{} ({} total pages):",
+            total_docs,
+            if total_docs == 1 { "" } else { "s" },
+            total_pages
+        ),
+        entries,
+        "\n",
+        LIST_PAGE_SIZE,
+    )
+}
+
+/// Next page of a result set another tool left a continuation token for.
+fn execute_fetch_more(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    let token = tool_call.arguments["token"].as_str().unwrap_or("").trim();
+    info!(token = %token, "Fetching more results");
+    ctx.state.result_pages.fetch_more(&tool_call.id, token)
+}
+
+/// Get common terms in the collection(s) to understand what topics are covered
+async fn execute_get_collection_terms(tool_call: &ToolCall, ctx: &AgentContext) -> ToolResult {
+    // Parse limit parameter (default 50, max 200)
+    let limit = tool_call.arguments["limit"].as_u64().unwrap_or(50).min(200) as usize;
+
+    info!(limit = limit, "Getting collection terms");
+
+    let collection_ids = ctx.collection_ids();
+    let index = &*ctx.state.search;
+
+    match search::get_collection_terms(index, collection_ids.as_deref(), limit) {
+        Ok(terms) => {
+            if terms.is_empty() {
+                return ToolResult::ok(
+                    &tool_call.id,
+                    "No terms found in the collection(s). The collection may be empty.".to_string(),
+                );
+            }
+
+            // Format terms as a readable list
+            let mut output = format!("Top {} terms by document frequency:\n\n", terms.len());
+
+            for term in &terms {
+                output.push_str(&format!("- {} ({} docs)\n", term.term, term.doc_count));
+            }
+
+            info!(term_count = terms.len(), "Retrieved collection terms");
+
+            ToolResult::ok(&tool_call.id, output)
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to get collection terms");
+            ToolResult::error(
+                &tool_call.id,
+                format!("Error getting collection terms: {}", e),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{self, ChunkToIndex};
+    use crate::AppState;
+    use crate::CollectionInfo;
+    use milli::update::IndexerConfig;
+
+    /// Create a minimal AppState for testing (no storage operations)
+    async fn create_test_state() -> AppState {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = crate::Config {
+            data_dir: temp_dir.path().to_path_buf(),
+            iroh_dir: temp_dir.path().join("iroh"),
+            search_dir: temp_dir.path().join("search"),
+            settings_file: temp_dir.path().join("settings.json"),
+            conversations_dir: temp_dir.path().join("conversations"),
+        };
+
+        // Create directories
+        std::fs::create_dir_all(&config.iroh_dir).unwrap();
+        std::fs::create_dir_all(&config.search_dir).unwrap();
+
+        let state = AppState::new(config).await.unwrap();
+        state
+    }
+
+    fn test_indexer_config() -> IndexerConfig {
+        IndexerConfig::default()
+    }
+
+    /// Helper to create a chunk for indexing
+    fn make_chunk(
+        parent_id: &str,
+        parent_name: &str,
+        content: &str,
+        collection_id: &str,
+        chunk_index: usize,
+        page_count: usize,
+        start_page: usize,
+        end_page: usize,
+    ) -> ChunkToIndex {
+        ChunkToIndex {
+            id: format!("{}_chunk_{}", parent_id, chunk_index),
+            parent_id: parent_id.to_string(),
+            parent_name: parent_name.to_string(),
+            chunk_index,
+            content: content.to_string(),
+            collection_id: collection_id.to_string(),
+            page_count,
+            start_page,
+            end_page,
+            vector: None,
+            embedder: None,
+        }
+    }
+
+    fn entry_texts(entries: &[PageEntry]) -> String {
+        entries
+            .iter()
+            .map(|e| e.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    // ==================== ToolCall / ToolResult Tests ====================
+
+    #[test]
+    fn test_tool_call_serialization() {
+        let tool_call = ToolCall {
+            id: "call_123".to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({"query": "climate report"}),
+        };
+
+        let json = serde_json::to_string(&tool_call).unwrap();
+        let parsed: ToolCall = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.id, "call_123");
+        assert_eq!(parsed.name, "search");
+        assert_eq!(parsed.arguments["query"], "climate report");
+    }
+
+    #[test]
+    fn test_tool_result_serialization() {
+        let result = ToolResult::ok("call_123", "Found 5 documents");
+
+        let json = serde_json::to_string(&result).unwrap();
+        let parsed: ToolResult = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.tool_call_id, "call_123");
+        assert_eq!(parsed.content, "Found 5 documents");
+        assert!(!parsed.is_error);
+    }
+
+    #[test]
+    fn test_source_passage_quote_is_trimmed() {
+        let text = format!("  Opening   words {}", "é".repeat(MAX_QUOTE_CHARS));
+        let passage = SourcePassage::new("col", "doc", "doc.pdf", Some(2), &text);
+
+        assert!(passage.quote.starts_with("Opening words é"));
+        assert!(passage.quote.ends_with('…'));
+        assert_eq!(passage.quote.chars().count(), MAX_QUOTE_CHARS + 1);
+    }
+
+    #[test]
+    fn test_tool_result_error_serialization() {
+        let result = ToolResult::error("call_456", "Search error: index not found");
+
+        let json = serde_json::to_string(&result).unwrap();
+        let parsed: ToolResult = serde_json::from_str(&json).unwrap();
+
+        assert!(parsed.is_error);
+        assert!(parsed.content.contains("error"));
+    }
+
+    // ==================== execute_tool Tests ====================
+
+    #[tokio::test]
+    async fn test_execute_tool_unknown_tool() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_unknown".to_string(),
+            name: "nonexistent_tool".to_string(),
+            arguments: serde_json::json!({}),
+        };
+
+        let result = execute_tool(&tool_call, &ctx).await;
+
+        assert!(result.is_error);
+        assert_eq!(result.tool_call_id, "call_unknown");
+        assert!(result.content.contains("Unknown tool"));
+        assert!(result.content.contains("nonexistent_tool"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_tool_dispatches_to_search() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_search".to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({"query": "test query"}),
+        };
+
+        let result = execute_tool(&tool_call, &ctx).await;
+
+        // Should not error (though may find no results)
+        assert!(!result.is_error);
+        assert_eq!(result.tool_call_id, "call_search");
+    }
+
+    #[tokio::test]
+    async fn test_execute_tool_dispatches_to_read_chunk() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_read".to_string(),
+            name: "read_chunk".to_string(),
+            arguments: serde_json::json!({"document_id": "doc123", "chunk_index": 0}),
+        };
+
+        let result = execute_tool(&tool_call, &ctx).await;
+
+        // Should error because chunk doesn't exist
+        assert!(result.is_error);
+        assert_eq!(result.tool_call_id, "call_read");
+        assert!(result.content.contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_tool_dispatches_to_list_documents() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_list".to_string(),
+            name: "list_documents".to_string(),
+            arguments: serde_json::json!({}),
+        };
+
+        let result = execute_tool(&tool_call, &ctx).await;
+
+        // Should error because no collections selected
+        assert!(result.is_error);
+        assert_eq!(result.tool_call_id, "call_list");
+        assert!(result.content.contains("No collections selected"));
+    }
+
+    // ==================== execute_search Tests ====================
+
+    #[tokio::test]
+    async fn test_search_empty_query() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_1".to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({}), // Missing query
+        };
+
+        let result = execute_search(&tool_call, &ctx).await;
+
+        // Empty query should still work (returns no results)
+        assert!(!result.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_indexed_documents() {
+        let state = create_test_state().await;
+
+        // Index some test documents
+        {
+            let index = &*state.search;
+            let config = test_indexer_config();
+
+            let chunks = vec![
+                make_chunk(
+                    "doc1",
+                    "Climate_Report.pdf",
+                    "Global temperatures are rising due to greenhouse gases.",
+                    "research",
+                    0,
+                    10,
+                    1,
+                    1,
+                ),
+                make_chunk(
+                    "doc2",
+                    "Financial_Summary.pdf",
+                    "Q4 revenue exceeded expectations with strong growth.",
+                    "finance",
+                    0,
+                    5,
+                    1,
+                    1,
+                ),
+            ];
+            search::index_chunks_batch(index, &config, chunks).unwrap();
+        }
+
+        let ctx = AgentContext::new(
+            state,
+            Some(vec![CollectionInfo {
+                id: "research".to_string(),
+                name: "Research Papers".to_string(),
+                document_count: 1,
+                total_pages: 10,
+                created_at: None,
+            }]),
+        );
+
+        let tool_call = ToolCall {
+            id: "call_search".to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({"query": "climate temperatures"}),
+        };
+
+        let result = execute_search(&tool_call, &ctx).await;
+
+        assert!(!result.is_error);
+        assert!(result.content.contains("Climate_Report.pdf"));
+        assert!(result.content.contains("relevant passages"));
+    }
+
+    #[tokio::test]
+    async fn test_search_no_results() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_1".to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({"query": "xyznonexistent123"}),
+        };
+
+        let result = execute_search(&tool_call, &ctx).await;
+
+        assert!(!result.is_error);
+        assert!(result.content.contains("No matching passages"));
+    }
+
+    #[tokio::test]
+    async fn test_search_pages_with_fetch_more() {
+        let state = create_test_state().await;
+        let chunks = (0..20)
+            .map(|i| {
+                make_chunk(
+                    &format!("doc{}", i),
+                    &format!("Invoice_{}.pdf", i),
+                    "Invoice for consulting services.",
+                    "finance",
+                    0,
+                    1,
+                    1,
+                    1,
+                )
+            })
+            .collect();
+        search::index_chunks_batch(&state.search, &test_indexer_config(), chunks).unwrap();
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_search".to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({"query": "invoice"}),
+        };
+        let first = execute_tool(&tool_call, &ctx).await;
+        assert!(first.content.starts_with("Found 20 relevant passages:"));
+        assert_eq!(first.citations.len(), SEARCH_LIMIT);
+        assert!(first.content.contains("Showing 1-15 of 20"));
+
+        let start = first.content.find("token \"").unwrap() + 7;
+        let token = &first.content[start..start + first.content[start..].find('"').unwrap()];
+        let more = ToolCall {
+            id: "call_more".to_string(),
+            name: "fetch_more".to_string(),
+            arguments: serde_json::json!({ "token": token }),
+        };
+        let second = execute_tool(&more, &ctx).await;
+        assert!(!second.is_error, "{}", second.content);
+        assert_eq!(second.citations.len(), 5);
+        assert!(second.content.contains("Showing 16-20 of 20"));
+        assert!(execute_tool(&more, &ctx).await.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_search_filters_by_collection() {
+        let state = create_test_state().await;
+
+        // Index documents in different collections
+        {
+            let index = &*state.search;
+            let config = test_indexer_config();
+
+            let chunks = vec![
+                make_chunk(
+                    "doc1",
+                    "Research_Paper.pdf",
+                    "Scientific research on climate change.",
+                    "research_col",
+                    0,
+                    10,
+                    1,
+                    1,
+                ),
+                make_chunk(
+                    "doc2",
+                    "Finance_Report.pdf",
+                    "Financial analysis for climate initiatives.",
+                    "finance_col",
+                    0,
+                    5,
+                    1,
+                    1,
+                ),
+            ];
+            search::index_chunks_batch(&index, &config, chunks).unwrap();
+        }
+
+        // Only search in research collection
+        let ctx = AgentContext::new(
+            state,
+            Some(vec![CollectionInfo {
+                id: "research_col".to_string(),
+                name: "Research".to_string(),
+                document_count: 1,
+                total_pages: 10,
+                created_at: None,
+            }]),
+        );
+
+        let tool_call = ToolCall {
+            id: "call_1".to_string(),
+            name: "search".to_string(),
+            arguments: serde_json::json!({"query": "climate"}),
+        };
+
+        let result = execute_search(&tool_call, &ctx).await;
+
+        assert!(!result.is_error);
+        // Should find research paper but not finance report
+        assert!(result.content.contains("Research_Paper.pdf"));
+        assert!(!result.content.contains("Finance_Report.pdf"));
+    }
+
+    #[tokio::test]
+    async fn test_search_puts_pinned_documents_first() {
+        let state = create_test_state().await;
+        {
+            let config = test_indexer_config();
+            let chunks = vec![
+                make_chunk(
+                    "doc1",
+                    "Climate_Report.pdf",
+                    "Climate climate climate: the climate report.",
+                    "col",
+                    0,
+                    1,
+                    1,
+                    1,
+                ),
+                make_chunk(
+                    "doc2",
+                    "Exhibit_B.pdf",
+                    "A passing mention of climate.",
+                    "col",
+                    0,
+                    1,
+                    1,
+                    1,
+                ),
+            ];
+            search::index_chunks_batch(&state.search, &config, chunks).unwrap();
+        }
+
+        let ctx = AgentContext {
+            pinned_document_ids: vec!["doc2".to_string()],
+            ..AgentContext::new(state, None)
+        };
+        let passages = search_passages(&ctx, "climate", 10).await.unwrap();
+        let order: Vec<&str> = passages.iter().map(|p| p.document_id.as_str()).collect();
+        assert_eq!(order, vec!["doc2", "doc1"]);
+    }
+
+    // ==================== execute_read_chunk Tests ====================
+
+    #[tokio::test]
+    async fn test_read_chunk_not_found() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_read".to_string(),
+            name: "read_chunk".to_string(),
+            arguments: serde_json::json!({
+                "document_id": "nonexistent_doc",
+                "chunk_index": 0
+            }),
+        };
+
+        let result = execute_read_chunk(&tool_call, &ctx).await;
+
+        assert!(result.is_error);
+        assert!(result.content.contains("not found"));
+        assert!(result.content.contains("nonexistent_doc"));
+    }
+
+    #[tokio::test]
+    async fn test_read_chunk_success() {
+        let state = create_test_state().await;
+
+        // Index a document with content
+        {
+            let index = &*state.search;
+            let config = test_indexer_config();
+
+            let chunks = vec![make_chunk(
+                "test_doc",
+                "Test_Document.pdf",
+                "This is the content of chunk 0 with important information.",
+                "collection1",
+                0,
+                5,
+                1,
+                1,
+            )];
+            search::index_chunks_batch(index, &config, chunks).unwrap();
+        }
+
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_read".to_string(),
+            name: "read_chunk".to_string(),
+            arguments: serde_json::json!({
+                "document_id": "test_doc",
+                "chunk_index": 0
+            }),
         };
 
-        // Create directories
-        std::fs::create_dir_all(&config.iroh_dir).unwrap();
-        std::fs::create_dir_all(&config.search_dir).unwrap();
+        let result = execute_read_chunk(&tool_call, &ctx).await;
 
-        let (state, _progress_rx) = AppState::new(config).await.unwrap();
-        state
+        assert!(!result.is_error);
+        assert!(result.content.contains("important information"));
     }
 
-    fn test_indexer_config() -> IndexerConfig {
-        IndexerConfig::default()
-    }
+    #[tokio::test]
+    async fn test_read_chunk_wrong_index() {
+        let state = create_test_state().await;
 
-    /// Helper to create a chunk for indexing
-    fn make_chunk(
-        parent_id: &str,
-        parent_name: &str,
-        content: &str,
-        collection_id: &str,
-        chunk_index: usize,
-        page_count: usize,
-        start_page: usize,
-        end_page: usize,
-    ) -> ChunkToIndex {
-        ChunkToIndex {
-            id: format!("{}_chunk_{}", parent_id, chunk_index),
-            parent_id: parent_id.to_string(),
-            parent_name: parent_name.to_string(),
-            chunk_index,
-            content: content.to_string(),
-            collection_id: collection_id.to_string(),
-            page_count,
-            start_page,
-            end_page,
-            vector: None,
+        // Index only chunk 0
+        {
+            let index = &*state.search;
+            let config = test_indexer_config();
+
+            let chunks = vec![make_chunk(
+                "test_doc",
+                "Test.pdf",
+                "Content of chunk 0",
+                "col1",
+                0,
+                1,
+                1,
+                1,
+            )];
+            search::index_chunks_batch(index, &config, chunks).unwrap();
         }
-    }
 
-    // ==================== ToolCall / ToolResult Tests ====================
+        let ctx = AgentContext::new(state, None);
 
-    #[test]
-    fn test_tool_call_serialization() {
+        // Try to read chunk 5 which doesn't exist
         let tool_call = ToolCall {
-            id: "call_123".to_string(),
-            name: "search".to_string(),
-            arguments: serde_json::json!({"query": "climate report"}),
+            id: "call_read".to_string(),
+            name: "read_chunk".to_string(),
+            arguments: serde_json::json!({
+                "document_id": "test_doc",
+                "chunk_index": 5
+            }),
         };
 
-        let json = serde_json::to_string(&tool_call).unwrap();
-        let parsed: ToolCall = serde_json::from_str(&json).unwrap();
+        let result = execute_read_chunk(&tool_call, &ctx).await;
 
-        assert_eq!(parsed.id, "call_123");
-        assert_eq!(parsed.name, "search");
-        assert_eq!(parsed.arguments["query"], "climate report");
+        assert!(result.is_error);
+        assert!(result.content.contains("not found"));
+        // Error message format: "Chunk 5 not found for document test_doc"
+        assert!(result.content.contains("Chunk 5"));
     }
 
-    #[test]
-    fn test_tool_result_serialization() {
-        let result = ToolResult {
-            tool_call_id: "call_123".to_string(),
-            content: "Found 5 documents".to_string(),
-            is_error: false,
+    #[tokio::test]
+    async fn test_read_chunk_missing_arguments() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        // Missing document_id
+        let tool_call = ToolCall {
+            id: "call_1".to_string(),
+            name: "read_chunk".to_string(),
+            arguments: serde_json::json!({"chunk_index": 0}),
         };
 
-        let json = serde_json::to_string(&result).unwrap();
-        let parsed: ToolResult = serde_json::from_str(&json).unwrap();
+        let result = execute_read_chunk(&tool_call, &ctx).await;
 
-        assert_eq!(parsed.tool_call_id, "call_123");
-        assert_eq!(parsed.content, "Found 5 documents");
-        assert!(!parsed.is_error);
+        // Should handle gracefully (empty doc_id)
+        assert!(result.is_error);
+        assert!(result.content.contains("not found"));
     }
 
-    #[test]
-    fn test_tool_result_error_serialization() {
-        let result = ToolResult {
-            tool_call_id: "call_456".to_string(),
-            content: "Search error: index not found".to_string(),
-            is_error: true,
+    // ==================== execute_read_pages Tests ====================
+
+    /// Store a three-page document in a new collection. Page 2 is blank.
+    async fn add_paged_document(state: &AppState) -> CollectionInfo {
+        let storage = state.storage.read().await;
+        let (namespace_id, _) = storage.create_collection("Paged").await.unwrap();
+        let text = "Page one text.\n\nPage three text.";
+        let metadata = DocumentMetadata {
+            id: "paged_doc".to_string(),
+            name: "Paged.pdf".to_string(),
+            file_type: "application/pdf".to_string(),
+            page_count: 3,
+            tags: vec![],
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            page_boundaries: vec![16, 16, text.len()],
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: None,
+            near_duplicates: Vec::new(),
         };
+        storage
+            .add_document(namespace_id, metadata, text.as_bytes(), b"%PDF")
+            .await
+            .unwrap();
+        CollectionInfo {
+            id: namespace_id.to_string(),
+            name: "Paged".to_string(),
+            document_count: 1,
+            total_pages: 3,
+            created_at: None,
+        }
+    }
 
-        let json = serde_json::to_string(&result).unwrap();
-        let parsed: ToolResult = serde_json::from_str(&json).unwrap();
-
-        assert!(parsed.is_error);
-        assert!(parsed.content.contains("error"));
+    fn read_pages_call(arguments: serde_json::Value) -> ToolCall {
+        ToolCall {
+            id: "call_pages".to_string(),
+            name: "read_pages".to_string(),
+            arguments,
+        }
     }
 
-    // ==================== execute_tool Tests ====================
+    #[test]
+    fn test_page_spans() {
+        let text = "abcdefXY";
+        let spans = page_spans(text, &[3, 3, 6]);
+        let pages: Vec<&str> = spans.iter().map(|r| &text[r.clone()]).collect();
+        // Blank page kept; trailing text joins the last page.
+        assert_eq!(pages, vec!["abc", "", "defXY"]);
+
+        assert_eq!(page_spans(text, &[]), vec![0..8]);
+        assert_eq!(page_spans("héllo", &[2, 100]), vec![0..1, 1..6]);
+    }
 
     #[tokio::test]
-    async fn test_execute_tool_unknown_tool() {
+    async fn test_read_pages_returns_marked_range() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let collection = add_paged_document(&state).await;
+        let ctx = AgentContext::new(state, Some(vec![collection]));
+
+        let result = execute_tool(
+            &read_pages_call(serde_json::json!({
+                "document_id": "paged_doc",
+                "start_page": 2,
+                "end_page": 3
+            })),
+            &ctx,
+        )
+        .await;
+
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("pages 2-3 of 3"));
+        assert!(result.content.contains("[Page 2]\n(no text on this page)"));
+        assert!(result.content.contains("[Page 3]\nPage three text."));
+        assert!(!result.content.contains("Page one"));
+        // Blank pages aren't cited.
+        assert_eq!(
+            result.citations,
+            vec![SourcePassage::new(
+                &ctx.collection_ids().unwrap()[0],
+                "paged_doc",
+                "Paged.pdf",
+                Some(3),
+                "Page three text."
+            )]
+        );
+    }
 
-        let tool_call = ToolCall {
-            id: "call_unknown".to_string(),
-            name: "nonexistent_tool".to_string(),
-            arguments: serde_json::json!({}),
-        };
+    #[tokio::test]
+    async fn test_read_pages_out_of_range() {
+        let state = create_test_state().await;
+        let collection = add_paged_document(&state).await;
+        let ctx = AgentContext::new(state, Some(vec![collection]));
 
-        let result = execute_tool(&tool_call, &ctx).await;
+        let result = execute_read_pages(
+            &read_pages_call(serde_json::json!({"document_id": "paged_doc", "start_page": 4})),
+            &ctx,
+        )
+        .await;
 
         assert!(result.is_error);
-        assert_eq!(result.tool_call_id, "call_unknown");
-        assert!(result.content.contains("Unknown tool"));
-        assert!(result.content.contains("nonexistent_tool"));
+        assert!(result.content.contains("has 3 pages"));
     }
 
     #[tokio::test]
-    async fn test_execute_tool_dispatches_to_search() {
+    async fn test_read_pages_not_found() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None,
+        let ctx = AgentContext::new(state, None);
+
+        let result = execute_read_pages(
+            &read_pages_call(serde_json::json!({"document_id": "missing", "start_page": 1})),
+            &ctx,
+        )
+        .await;
+
+        assert!(result.is_error);
+        assert!(result.content.contains("not found"));
+    }
+
+    // ==================== execute_tag_document Tests ====================
+
+    #[test]
+    fn test_confirmation_prompt() {
+        let tag_call = ToolCall {
+            id: "call_tag".to_string(),
+            name: "tag_document".to_string(),
+            arguments: serde_json::json!({
+                "document_ids": ["a", "b"],
+                "add_tags": ["acme"],
+                "remove_tags": ["draft"]
+            }),
         };
+        assert_eq!(
+            confirmation_prompt(&tag_call).unwrap(),
+            "Tag 2 documents: add \"acme\", remove \"draft\"?"
+        );
 
-        let tool_call = ToolCall {
+        let search_call = ToolCall {
             id: "call_search".to_string(),
             name: "search".to_string(),
-            arguments: serde_json::json!({"query": "test query"}),
+            arguments: serde_json::json!({"query": "acme"}),
         };
-
-        let result = execute_tool(&tool_call, &ctx).await;
-
-        // Should not error (though may find no results)
-        assert!(!result.is_error);
-        assert_eq!(result.tool_call_id, "call_search");
+        assert!(confirmation_prompt(&search_call).is_none());
     }
 
     #[tokio::test]
-    async fn test_execute_tool_dispatches_to_read_chunk() {
+    async fn test_tag_document() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let collection = add_paged_document(&state).await;
+        let namespace_id: NamespaceId = collection.id.parse().unwrap();
+        let ctx = AgentContext::new(state.clone(), Some(vec![collection]));
 
         let tool_call = ToolCall {
-            id: "call_read".to_string(),
-            name: "read_chunk".to_string(),
-            arguments: serde_json::json!({"document_id": "doc123", "chunk_index": 0}),
+            id: "call_tag".to_string(),
+            name: "tag_document".to_string(),
+            arguments: serde_json::json!({
+                "document_ids": ["paged_doc", "missing_doc"],
+                "add_tags": ["acme"]
+            }),
         };
-
         let result = execute_tool(&tool_call, &ctx).await;
 
-        // Should error because chunk doesn't exist
-        assert!(result.is_error);
-        assert_eq!(result.tool_call_id, "call_read");
-        assert!(result.content.contains("not found"));
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("Updated tags on 1 document."));
+        assert!(result.content.contains("- Paged.pdf: acme"));
+        assert!(result.content.contains("missing_doc (not found)"));
+
+        let metadata = state
+            .storage
+            .read()
+            .await
+            .get_document(namespace_id, "paged_doc")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.tags, vec!["acme".to_string()]);
     }
 
     #[tokio::test]
-    async fn test_execute_tool_dispatches_to_list_documents() {
+    async fn test_save_search() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None, // No collections selected
+        let collection = CollectionInfo {
+            id: "abc123".to_string(),
+            name: "Leaks".to_string(),
+            document_count: 0,
+            total_pages: 0,
+            created_at: None,
         };
+        let ctx = AgentContext::new(state.clone(), Some(vec![collection]));
 
         let tool_call = ToolCall {
-            id: "call_list".to_string(),
-            name: "list_documents".to_string(),
-            arguments: serde_json::json!({}),
+            id: "call_save".to_string(),
+            name: "save_search".to_string(),
+            arguments: serde_json::json!({
+                "name": "Acme payments",
+                "query": "payments to Acme",
+                "notify": true
+            }),
         };
-
+        assert_eq!(
+            confirmation_prompt(&tool_call).as_deref(),
+            Some("Save the search \"payments to Acme\" and alert on new matches?")
+        );
         let result = execute_tool(&tool_call, &ctx).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("Leaks"));
 
-        // Should error because no collections selected
-        assert!(result.is_error);
-        assert_eq!(result.tool_call_id, "call_list");
-        assert!(result.content.contains("No collections selected"));
+        let saved = crate::saved_searches::load_saved_searches(
+            &state.config.saved_searches_dir(),
+            "abc123",
+        )
+        .unwrap();
+        assert_eq!(saved.len(), 1);
+        assert!(saved[0].notify);
+
+        let outside = ToolCall {
+            arguments: serde_json::json!({"query": "x", "collection_id": "other1"}),
+            ..tool_call
+        };
+        assert!(execute_tool(&outside, &ctx).await.is_error);
     }
 
-    // ==================== execute_search Tests ====================
+    #[test]
+    fn test_report_file_name() {
+        assert_eq!(
+            report_file_name("Acme: payments 2021/22"),
+            "Acme payments 202122.md"
+        );
+        assert_eq!(report_file_name("Findings.md"), "Findings.md");
+        assert_eq!(report_file_name(" ?? "), "Report.md");
+    }
 
     #[tokio::test]
-    async fn test_search_empty_query() {
+    async fn test_write_report() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let collection = add_paged_document(&state).await;
+        let ctx = AgentContext::new(state.clone(), Some(vec![collection.clone()]));
 
         let tool_call = ToolCall {
-            id: "call_1".to_string(),
-            name: "search".to_string(),
-            arguments: serde_json::json!({}), // Missing query
+            id: "call_report".to_string(),
+            name: "write_report".to_string(),
+            arguments: serde_json::json!({
+                "title": "Acme payments",
+                "content": "Acme was paid twice (Paged.pdf, p. 1)."
+            }),
         };
-
-        let result = execute_search(&tool_call, &ctx).await;
-
-        // Empty query should still work (returns no results)
-        assert!(!result.is_error);
+        assert!(confirmation_prompt(&tool_call).is_some());
+        let result = execute_tool(&tool_call, &ctx).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("Acme payments.md"));
+
+        let namespace_id: NamespaceId = collection.id.parse().unwrap();
+        let storage = state.storage.read().await;
+        let documents = storage.list_documents(namespace_id).await.unwrap();
+        let report = documents
+            .iter()
+            .find(|d| d.name == "Acme payments.md")
+            .unwrap();
+        assert_eq!(report.file_type, crate::storage::MARKDOWN_FILE_TYPE);
+        let source = storage
+            .get_document_source(namespace_id, &report.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(String::from_utf8(source)
+            .unwrap()
+            .starts_with("# Acme payments\n\n"));
     }
 
-    #[tokio::test]
-    async fn test_search_with_indexed_documents() {
-        let state = create_test_state().await;
-
-        // Index some test documents
-        {
-            let index = &*state.search;
-            let config = test_indexer_config();
-
-            let chunks = vec![
-                make_chunk(
-                    "doc1",
-                    "Climate_Report.pdf",
-                    "Global temperatures are rising due to greenhouse gases.",
-                    "research",
-                    0,
-                    10,
-                    1,
-                    1,
-                ),
-                make_chunk(
-                    "doc2",
-                    "Financial_Summary.pdf",
-                    "Q4 revenue exceeded expectations with strong growth.",
-                    "finance",
-                    0,
-                    5,
-                    1,
-                    1,
-                ),
-            ];
-            search::index_chunks_batch(index, &config, chunks).unwrap();
-        }
-
-        let ctx = AgentContext {
-            state,
-            collections: Some(vec![CollectionInfo {
-                id: "research".to_string(),
-                name: "Research Papers".to_string(),
-                document_count: 1,
-                total_pages: 10,
-                created_at: None,
-            }]),
-        };
+    #[test]
+    fn test_export_file_name() {
+        assert_eq!(
+            export_file_name("Exhibit A", "application/pdf"),
+            "Exhibit A.pdf"
+        );
+        assert_eq!(export_file_name("scan.PDF", "application/pdf"), "scan.PDF");
+        assert_eq!(
+            export_file_name("../../notes", MARKDOWN_FILE_TYPE),
+            "notes.md"
+        );
+        assert_eq!(export_file_name("..", "application/pdf"), "document.pdf");
+    }
+
+    #[tokio::test]
+    async fn test_get_source_file() {
+        let state = create_test_state().await;
+        let collection = add_paged_document(&state).await;
+        let ctx = AgentContext::new(state.clone(), Some(vec![collection.clone()]));
 
         let tool_call = ToolCall {
-            id: "call_search".to_string(),
-            name: "search".to_string(),
-            arguments: serde_json::json!({"query": "climate temperatures"}),
+            id: "call_source".to_string(),
+            name: "get_source_file".to_string(),
+            arguments: serde_json::json!({"document_id": "paged_doc"}),
         };
+        let result = execute_tool(&tool_call, &ctx).await;
+        assert!(!result.is_error, "{}", result.content);
+        let file = result.source_file.unwrap();
+        assert_eq!(file.collection_id, collection.id);
+        assert!(file.path.ends_with("Paged.pdf"));
+        assert_eq!(std::fs::read(&file.path).unwrap(), b"%PDF");
+
+        let missing = ToolCall {
+            arguments: serde_json::json!({"document_id": "nope"}),
+            ..tool_call
+        };
+        assert!(execute_tool(&missing, &ctx).await.is_error);
+    }
 
-        let result = execute_search(&tool_call, &ctx).await;
+    #[tokio::test]
+    async fn test_plan() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_plan".to_string(),
+            name: "plan".to_string(),
+            arguments: serde_json::json!({"steps": [
+                {"description": "Search for the contract", "status": "done"},
+                {"description": "Read the payment terms", "status": "in_progress"},
+                {"description": "Compare with invoices"},
+                {"description": "  "}
+            ]}),
+        };
+        let steps = plan_steps(&tool_call).unwrap();
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[1].status, PlanStatus::InProgress);
+        assert_eq!(steps[2].status, PlanStatus::Pending);
 
+        let result = execute_tool(&tool_call, &ctx).await;
         assert!(!result.is_error);
-        assert!(result.content.contains("Climate_Report.pdf"));
-        assert!(result.content.contains("relevant passages"));
+        assert_eq!(result.content, "Plan updated: 1 of 3 steps finished.");
+
+        let empty = ToolCall {
+            arguments: serde_json::json!({"steps": []}),
+            ..tool_call
+        };
+        assert!(execute_tool(&empty, &ctx).await.is_error);
     }
 
     #[tokio::test]
-    async fn test_search_no_results() {
+    async fn test_remember() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None,
+        let collection = CollectionInfo {
+            id: "col1".to_string(),
+            name: "Leaks".to_string(),
+            document_count: 0,
+            total_pages: 0,
+            created_at: None,
         };
+        let ctx = AgentContext::new(state.clone(), Some(vec![collection]));
 
         let tool_call = ToolCall {
-            id: "call_1".to_string(),
-            name: "search".to_string(),
-            arguments: serde_json::json!({"query": "xyznonexistent123"}),
+            id: "call_remember".to_string(),
+            name: "remember".to_string(),
+            arguments: serde_json::json!({"fact": "Acme's CFO signed every invoice."}),
         };
+        assert_eq!(
+            confirmation_prompt(&tool_call).unwrap(),
+            "Remember for future chats: \"Acme's CFO signed every invoice.\"?"
+        );
+        let result = execute_tool(&tool_call, &ctx).await;
+        assert!(!result.is_error, "{}", result.content);
+        assert_eq!(
+            result.content,
+            "Remembered for Leaks: Acme's CFO signed every invoice."
+        );
 
-        let result = execute_search(&tool_call, &ctx).await;
+        let memories = crate::memory::load_memories(&state.config.memory_dir(), "col1").unwrap();
+        assert_eq!(memories.len(), 1);
 
-        assert!(!result.is_error);
-        assert!(result.content.contains("No matching passages"));
+        // Collections outside the conversation's scope are refused.
+        let other = ToolCall {
+            arguments: serde_json::json!({"fact": "x", "collection_id": "col2"}),
+            ..tool_call
+        };
+        assert!(execute_tool(&other, &ctx).await.is_error);
     }
 
     #[tokio::test]
-    async fn test_search_filters_by_collection() {
+    async fn test_tag_document_requires_tags() {
         let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
 
-        // Index documents in different collections
-        {
-            let index = &*state.search;
-            let config = test_indexer_config();
+        let tool_call = ToolCall {
+            id: "call_tag".to_string(),
+            name: "tag_document".to_string(),
+            arguments: serde_json::json!({"document_ids": ["doc"]}),
+        };
+        let result = execute_tag_document(&tool_call, &ctx).await;
 
-            let chunks = vec![
-                make_chunk(
-                    "doc1",
-                    "Research_Paper.pdf",
-                    "Scientific research on climate change.",
-                    "research_col",
-                    0,
-                    10,
-                    1,
-                    1,
-                ),
-                make_chunk(
-                    "doc2",
-                    "Finance_Report.pdf",
-                    "Financial analysis for climate initiatives.",
-                    "finance_col",
-                    0,
-                    5,
-                    1,
-                    1,
-                ),
-            ];
-            search::index_chunks_batch(&index, &config, chunks).unwrap();
-        }
+        assert!(result.is_error);
+        assert!(result.content.contains("at least one tag"));
+    }
 
-        // Only search in research collection
-        let ctx = AgentContext {
-            state,
-            collections: Some(vec![CollectionInfo {
-                id: "research_col".to_string(),
-                name: "Research".to_string(),
+    // ==================== execute_get_document_info Tests ====================
+
+    #[test]
+    fn test_describe_extraction() {
+        let page = "Quarterly revenue grew across every region we operate in.";
+        let text = format!("{}{}", page, page);
+        let boundaries = [page.len(), text.len()];
+        assert!(describe_extraction(&text, &boundaries).starts_with("good (2 of 2 pages"));
+
+        // One blank page out of three.
+        let text = format!("{}{}", page, page);
+        let boundaries = [page.len(), page.len(), text.len()];
+        assert!(describe_extraction(&text, &boundaries).starts_with("partial (2 of 3 pages"));
+
+        assert!(describe_extraction("", &[]).starts_with("poor (0 of 1 pages"));
+    }
+
+    #[tokio::test]
+    async fn test_get_document_info() {
+        let state = create_test_state().await;
+        let collection = add_paged_document(&state).await;
+        let ctx = AgentContext::new(state, Some(vec![collection]));
+
+        let tool_call = ToolCall {
+            id: "call_info".to_string(),
+            name: "get_document_info".to_string(),
+            arguments: serde_json::json!({"document_id": "paged_doc"}),
+        };
+        let result = execute_tool(&tool_call, &ctx).await;
+
+        assert!(!result.is_error, "{}", result.content);
+        assert!(result.content.contains("Name: Paged.pdf"));
+        assert!(result.content.contains("Collection: Paged"));
+        assert!(result.content.contains("Pages: 3"));
+        assert!(result.content.contains("Imported: 2024-01-01T00:00:00Z"));
+        assert!(result.content.contains("Tags: none"));
+        assert!(result.content.contains("Language: unknown"));
+    }
+
+    #[tokio::test]
+    async fn test_get_document_info_not_found() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_info".to_string(),
+            name: "get_document_info".to_string(),
+            arguments: serde_json::json!({"document_id": "missing"}),
+        };
+        let result = execute_get_document_info(&tool_call, &ctx).await;
+
+        assert!(result.is_error);
+        assert!(result.content.contains("not found"));
+    }
+
+    // ==================== execute_extract_timeline Tests ====================
+
+    #[tokio::test]
+    async fn test_extract_timeline() {
+        let state = create_test_state().await;
+        let collection = {
+            let storage = state.storage.read().await;
+            let (namespace_id, _) = storage.create_collection("Dated").await.unwrap();
+            let text = "The contract was signed on 3 March 2021.\nPayment followed in May 2021.";
+            let metadata = DocumentMetadata {
+                id: "dated_doc".to_string(),
+                name: "Dated.pdf".to_string(),
+                file_type: "application/pdf".to_string(),
+                page_count: 2,
+                tags: vec![],
+                created_at: "2024-01-01T00:00:00Z".to_string(),
+                page_boundaries: vec![41, text.len()],
+                scanned_pages: Vec::new(),
+                source_url: None,
+                simhash: None,
+                near_duplicates: Vec::new(),
+            };
+            storage
+                .add_document(namespace_id, metadata, text.as_bytes(), b"%PDF")
+                .await
+                .unwrap();
+            CollectionInfo {
+                id: namespace_id.to_string(),
+                name: "Dated".to_string(),
                 document_count: 1,
-                total_pages: 10,
+                total_pages: 2,
                 created_at: None,
-            }]),
+            }
         };
+        let ctx = AgentContext::new(state, Some(vec![collection]));
 
         let tool_call = ToolCall {
-            id: "call_1".to_string(),
-            name: "search".to_string(),
-            arguments: serde_json::json!({"query": "climate"}),
+            id: "call_timeline".to_string(),
+            name: "extract_timeline".to_string(),
+            arguments: serde_json::json!({"document_ids": ["dated_doc", "missing_doc"]}),
         };
+        let result = execute_tool(&tool_call, &ctx).await;
+        assert!(!result.is_error, "{}", result.content);
+
+        let json: serde_json::Value = serde_json::from_str(&result.content).unwrap();
+        let events = json["events"].as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["date"], "2021-03-03");
+        assert_eq!(events[0]["citations"][0]["page"], 1);
+        assert_eq!(events[1]["date"], "2021-05");
+        assert_eq!(events[1]["precision"], "month");
+        assert_eq!(events[1]["citations"][0]["page"], 2);
+        assert_eq!(json["missing_documents"][0], "missing_doc");
+    }
 
-        let result = execute_search(&tool_call, &ctx).await;
+    #[tokio::test]
+    async fn test_extract_timeline_requires_documents() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
 
-        assert!(!result.is_error);
-        // Should find research paper but not finance report
-        assert!(result.content.contains("Research_Paper.pdf"));
-        assert!(!result.content.contains("Finance_Report.pdf"));
+        let tool_call = ToolCall {
+            id: "call_timeline".to_string(),
+            name: "extract_timeline".to_string(),
+            arguments: serde_json::json!({}),
+        };
+        let result = execute_extract_timeline(&tool_call, &ctx).await;
+
+        assert!(result.is_error);
+        assert!(result.content.contains("document_ids"));
     }
 
-    // ==================== execute_read_chunk Tests ====================
+    // ==================== execute_compare_documents Tests ====================
 
     #[tokio::test]
-    async fn test_read_chunk_not_found() {
+    async fn test_compare_documents() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None,
+        let namespace_id = {
+            let storage = state.storage.read().await;
+            let (namespace_id, _) = storage.create_collection("Contracts").await.unwrap();
+            for (id, text) in [
+                (
+                    "draft",
+                    "Term. Two years.\n\nFee. Beta pays Acme $4,000 per month.",
+                ),
+                (
+                    "final",
+                    "Term. Two years.\n\nFee. Beta pays Acme $5,500 per month.\n\n\
+                     Exclusivity. Beta may not work with competitors.",
+                ),
+            ] {
+                let metadata = DocumentMetadata {
+                    id: id.to_string(),
+                    name: format!("{}.pdf", id),
+                    file_type: "application/pdf".to_string(),
+                    page_count: 1,
+                    tags: vec![],
+                    created_at: "2024-01-01T00:00:00Z".to_string(),
+                    page_boundaries: vec![text.len()],
+                    scanned_pages: Vec::new(),
+                    source_url: None,
+                    simhash: None,
+                    near_duplicates: Vec::new(),
+                };
+                storage
+                    .add_document(namespace_id, metadata, text.as_bytes(), b"%PDF")
+                    .await
+                    .unwrap();
+            }
+            namespace_id
         };
+        let ctx = AgentContext::new(state, None);
 
         let tool_call = ToolCall {
-            id: "call_read".to_string(),
-            name: "read_chunk".to_string(),
+            id: "call_compare".to_string(),
+            name: "compare_documents".to_string(),
             arguments: serde_json::json!({
-                "document_id": "nonexistent_doc",
-                "chunk_index": 0
+                "first_document_id": "draft",
+                "second_document_id": "final"
             }),
         };
-
-        let result = execute_read_chunk(&tool_call, &ctx).await;
-
-        assert!(result.is_error);
-        assert!(result.content.contains("not found"));
-        assert!(result.content.contains("nonexistent_doc"));
+        let result = execute_tool(&tool_call, &ctx).await;
+        assert!(!result.is_error, "{}", result.content);
+
+        let content: serde_json::Value = serde_json::from_str(&result.content).unwrap();
+        assert_eq!(content["unchanged_passages"], 1);
+        assert_eq!(content["total_changes"], 2);
+        assert_eq!(content["changes"][0]["kind"], "changed");
+        assert_eq!(content["changes"][0]["words_added"][0], "5,500");
+        assert_eq!(content["changes"][1]["kind"], "added");
+        assert_eq!(content["changes"][1]["second"]["page"], 1);
+        assert!(content.get("description").is_none());
+        // Both sides of the change and the addition are cited.
+        assert_eq!(result.citations.len(), 3);
+        assert_eq!(result.citations[0].collection_id, namespace_id.to_string());
+        assert_eq!(result.citations[0].document_id, "draft");
     }
 
+    // ==================== execute_extract_entities Tests ====================
+
     #[tokio::test]
-    async fn test_read_chunk_success() {
+    async fn test_extract_entities_from_documents() {
         let state = create_test_state().await;
-
-        // Index a document with content
+        let collection = add_paged_document(&state).await;
         {
-            let index = &*state.search;
-            let config = test_indexer_config();
-
-            let chunks = vec![make_chunk(
-                "test_doc",
-                "Test_Document.pdf",
-                "This is the content of chunk 0 with important information.",
-                "collection1",
-                0,
-                5,
-                1,
-                1,
-            )];
-            search::index_chunks_batch(index, &config, chunks).unwrap();
+            let storage = state.storage.read().await;
+            let namespace_id = collection.id.parse().unwrap();
+            let text = "Dr. Jane Roe of Acme Holdings Ltd paid $4.2 million.\nJane Roe flew in from Lagos.";
+            let metadata = DocumentMetadata {
+                id: "memo_doc".to_string(),
+                name: "Memo.pdf".to_string(),
+                file_type: "application/pdf".to_string(),
+                page_count: 2,
+                tags: vec![],
+                created_at: "2024-01-01T00:00:00Z".to_string(),
+                page_boundaries: vec![53, text.len()],
+                scanned_pages: Vec::new(),
+                source_url: None,
+                simhash: None,
+                near_duplicates: Vec::new(),
+            };
+            storage
+                .add_document(namespace_id, metadata, text.as_bytes(), b"%PDF")
+                .await
+                .unwrap();
         }
-
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let ctx = AgentContext::new(state, Some(vec![collection]));
 
         let tool_call = ToolCall {
-            id: "call_read".to_string(),
-            name: "read_chunk".to_string(),
+            id: "call_entities".to_string(),
+            name: "extract_entities".to_string(),
             arguments: serde_json::json!({
-                "document_id": "test_doc",
-                "chunk_index": 0
+                "document_ids": ["memo_doc"],
+                "types": ["person", "organization", "amount"]
             }),
         };
-
-        let result = execute_read_chunk(&tool_call, &ctx).await;
-
-        assert!(!result.is_error);
-        assert!(result.content.contains("important information"));
+        let result = execute_tool(&tool_call, &ctx).await;
+        assert!(!result.is_error, "{}", result.content);
+
+        let json: serde_json::Value = serde_json::from_str(&result.content).unwrap();
+        let entities = json["entities"].as_array().unwrap();
+        assert_eq!(entities.len(), 3);
+        assert_eq!(entities[0]["kind"], "person");
+        assert_eq!(entities[0]["name"], "Jane Roe");
+        assert_eq!(entities[0]["count"], 2);
+        assert_eq!(entities[0]["citations"].as_array().unwrap().len(), 2);
+        assert_eq!(entities[1]["name"], "Acme Holdings Ltd");
+        assert_eq!(entities[2]["kind"], "amount");
+        assert_eq!(entities[2]["citations"][0]["page"], 1);
     }
 
     #[tokio::test]
-    async fn test_read_chunk_wrong_index() {
+    async fn test_extract_entities_from_search() {
         let state = create_test_state().await;
-
-        // Index only chunk 0
         {
-            let index = &*state.search;
-            let config = test_indexer_config();
-
             let chunks = vec![make_chunk(
-                "test_doc",
-                "Test.pdf",
-                "Content of chunk 0",
-                "col1",
+                "doc1",
+                "Ledger.pdf",
+                "The ledger shows Acme Holdings Ltd paid 1,500 EUR to John Smith.",
+                "research",
                 0,
-                1,
-                1,
-                1,
+                8,
+                3,
+                3,
             )];
-            search::index_chunks_batch(index, &config, chunks).unwrap();
+            search::index_chunks_batch(&state.search, &test_indexer_config(), chunks).unwrap();
         }
+        let ctx = AgentContext::new(state, None);
 
-        let ctx = AgentContext {
-            state,
-            collections: None,
+        let tool_call = ToolCall {
+            id: "call_entities".to_string(),
+            name: "extract_entities".to_string(),
+            arguments: serde_json::json!({"query": "ledger"}),
         };
+        let result = execute_extract_entities(&tool_call, &ctx).await;
+        assert!(!result.is_error, "{}", result.content);
+
+        let json: serde_json::Value = serde_json::from_str(&result.content).unwrap();
+        assert_eq!(json["passages_scanned"], 1);
+        let names: Vec<&str> = json["entities"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["John Smith", "Acme Holdings Ltd", "1,500 EUR"]);
+        assert_eq!(json["entities"][0]["citations"][0]["page"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_extract_entities_requires_source() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
 
-        // Try to read chunk 5 which doesn't exist
         let tool_call = ToolCall {
-            id: "call_read".to_string(),
-            name: "read_chunk".to_string(),
-            arguments: serde_json::json!({
-                "document_id": "test_doc",
-                "chunk_index": 5
-            }),
+            id: "call_entities".to_string(),
+            name: "extract_entities".to_string(),
+            arguments: serde_json::json!({"query": "  "}),
         };
-
-        let result = execute_read_chunk(&tool_call, &ctx).await;
+        let result = execute_extract_entities(&tool_call, &ctx).await;
 
         assert!(result.is_error);
-        assert!(result.content.contains("not found"));
-        // Error message format: "Chunk 5 not found for document test_doc"
-        assert!(result.content.contains("Chunk 5"));
+        assert!(result.content.contains("document_ids or a query"));
     }
 
+    // ==================== execute_summarize_document Tests ====================
+
     #[tokio::test]
-    async fn test_read_chunk_missing_arguments() {
+    async fn test_summarize_document_needs_chat_model() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let collection = add_paged_document(&state).await;
+        let ctx = AgentContext::new(state, Some(vec![collection]));
 
-        // Missing document_id
         let tool_call = ToolCall {
-            id: "call_1".to_string(),
-            name: "read_chunk".to_string(),
-            arguments: serde_json::json!({"chunk_index": 0}),
+            id: "call_summary".to_string(),
+            name: "summarize_document".to_string(),
+            arguments: serde_json::json!({"document_id": "paged_doc"}),
         };
+        let result = execute_tool(&tool_call, &ctx).await;
 
-        let result = execute_read_chunk(&tool_call, &ctx).await;
+        assert!(result.is_error);
+        assert!(result.content.contains("No chat model"));
+    }
+
+    #[tokio::test]
+    async fn test_summarize_document_not_found() {
+        let state = create_test_state().await;
+        let ctx = AgentContext::new(state, None);
+
+        let tool_call = ToolCall {
+            id: "call_summary".to_string(),
+            name: "summarize_document".to_string(),
+            arguments: serde_json::json!({"document_id": "missing_doc"}),
+        };
+        let result = execute_summarize_document(&tool_call, &ctx).await;
 
-        // Should handle gracefully (empty doc_id)
         assert!(result.is_error);
-        assert!(result.content.contains("not found"));
+        assert!(result.content.contains("missing_doc not found"));
     }
 
     // ==================== execute_list_documents Tests ====================
@@ -928,10 +3404,7 @@ mod tests {
     #[tokio::test]
     async fn test_list_documents_no_collections() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let ctx = AgentContext::new(state, None);
 
         let tool_call = ToolCall {
             id: "call_list".to_string(),
@@ -948,10 +3421,7 @@ mod tests {
     #[tokio::test]
     async fn test_list_documents_empty_collections() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: Some(vec![]), // Empty list
-        };
+        let ctx = AgentContext::new(state, Some(vec![]));
 
         let tool_call = ToolCall {
             id: "call_list".to_string(),
@@ -965,10 +3435,10 @@ mod tests {
         assert!(result.content.contains("No collections selected"));
     }
 
-    // ==================== format_search_results Tests ====================
+    // ==================== search_entries Tests ====================
 
     #[tokio::test]
-    async fn test_format_search_results_empty() {
+    async fn test_search_entries_empty() {
         let temp_dir = tempfile::tempdir().unwrap();
         let index = search::open_index(temp_dir.path()).unwrap();
 
@@ -980,21 +3450,16 @@ mod tests {
             conversations_dir: temp_dir.path().join("conversations"),
         };
         std::fs::create_dir_all(&config.iroh_dir).unwrap();
-        let (state, _progress_rx) = AppState::new(config).await.unwrap();
+        let state = AppState::new(config).await.unwrap();
 
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let ctx = AgentContext::new(state, None);
 
         let hits: Vec<search::SearchHit> = vec![];
-        let result = format_search_results(&index, &hits, &ctx);
-
-        assert_eq!(result, "No matching passages found.");
+        assert!(search_entries(&index, &hits, &ctx).is_empty());
     }
 
     #[tokio::test]
-    async fn test_format_search_results_with_collection_names() {
+    async fn test_search_entries_with_collection_names() {
         let temp_dir = tempfile::tempdir().unwrap();
         let index = search::open_index(temp_dir.path()).unwrap();
         let config = test_indexer_config();
@@ -1032,28 +3497,27 @@ mod tests {
         };
         std::fs::create_dir_all(&cfg.iroh_dir).unwrap();
         std::fs::create_dir_all(&cfg.search_dir).unwrap();
-        let (state, _progress_rx) = AppState::new(cfg).await.unwrap();
+        let state = AppState::new(cfg).await.unwrap();
 
-        let ctx = AgentContext {
+        let ctx = AgentContext::new(
             state,
-            collections: Some(vec![CollectionInfo {
+            Some(vec![CollectionInfo {
                 id: "col_123".to_string(),
                 name: "Research Collection".to_string(),
                 document_count: 1,
                 total_pages: 10,
                 created_at: None,
             }]),
-        };
+        );
 
-        let formatted = format_search_results(&index, &results.hits, &ctx);
+        let formatted = entry_texts(&search_entries(&index, &results.hits, &ctx));
 
         assert!(formatted.contains("Report.pdf"));
         assert!(formatted.contains("Research Collection"));
-        assert!(formatted.contains("relevant passages"));
     }
 
     #[tokio::test]
-    async fn test_format_search_results_truncates_long_content() {
+    async fn test_search_entries_truncates_long_content() {
         let temp_dir = tempfile::tempdir().unwrap();
         let index = search::open_index(temp_dir.path()).unwrap();
         let config = test_indexer_config();
@@ -1093,14 +3557,11 @@ mod tests {
         };
         std::fs::create_dir_all(&cfg.iroh_dir).unwrap();
         std::fs::create_dir_all(&cfg.search_dir).unwrap();
-        let (state, _progress_rx) = AppState::new(cfg).await.unwrap();
+        let state = AppState::new(cfg).await.unwrap();
 
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let ctx = AgentContext::new(state, None);
 
-        let formatted = format_search_results(&index, &results.hits, &ctx);
+        let formatted = entry_texts(&search_entries(&index, &results.hits, &ctx));
 
         // Should be truncated with "..."
         assert!(
@@ -1113,7 +3574,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_format_search_results_page_references() {
+    async fn test_search_entries_page_references() {
         let temp_dir = tempfile::tempdir().unwrap();
         let index = search::open_index(temp_dir.path()).unwrap();
         let config = test_indexer_config();
@@ -1146,14 +3607,11 @@ mod tests {
         };
         std::fs::create_dir_all(&cfg.iroh_dir).unwrap();
         std::fs::create_dir_all(&cfg.search_dir).unwrap();
-        let (state, _progress_rx) = AppState::new(cfg).await.unwrap();
+        let state = AppState::new(cfg).await.unwrap();
 
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let ctx = AgentContext::new(state, None);
 
-        let formatted = format_search_results(&index, &results.hits, &ctx);
+        let formatted = entry_texts(&search_entries(&index, &results.hits, &ctx));
 
         // Should contain page references
         assert!(formatted.contains("p. 5") || formatted.contains("pp. 3-7"));
@@ -1164,10 +3622,7 @@ mod tests {
     #[tokio::test]
     async fn test_get_collection_terms_empty_index() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let ctx = AgentContext::new(state, None);
 
         let tool_call = ToolCall {
             id: "call_terms".to_string(),
@@ -1215,10 +3670,7 @@ mod tests {
             search::index_chunks_batch(index, &config, chunks).unwrap();
         }
 
-        let ctx = AgentContext {
-            state,
-            collections: None, // All collections
-        };
+        let ctx = AgentContext::new(state, None);
 
         let tool_call = ToolCall {
             id: "call_terms".to_string(),
@@ -1257,10 +3709,7 @@ mod tests {
             search::index_chunks_batch(index, &config, vec![chunk]).unwrap();
         }
 
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let ctx = AgentContext::new(state, None);
 
         let tool_call = ToolCall {
             id: "call_terms".to_string(),
@@ -1287,10 +3736,7 @@ mod tests {
     #[tokio::test]
     async fn test_get_collection_terms_max_limit_enforced() {
         let state = create_test_state().await;
-        let ctx = AgentContext {
-            state,
-            collections: None,
-        };
+        let ctx = AgentContext::new(state, None);
 
         // Request more than max allowed (200)
         let tool_call = ToolCall {
@@ -0,0 +1,266 @@
+//! Checking an answer against the passages it cites.
+//!
+//! After the final answer of a turn, every sentence that names a cited
+//! document is compared with the text of the pages cited for it. A sentence
+//! is supported when most of its words appear on one of those pages and
+//! every figure in it does; anything else is flagged, so a reporter knows
+//! which claims to check by hand before publishing.
+
+use std::collections::HashSet;
+
+use iroh_docs::NamespaceId;
+use serde::Serialize;
+use tracing::warn;
+
+use super::tools::page_spans;
+use super::ContentBlock;
+use crate::text::{is_term, words};
+use crate::AppState;
+
+/// Share of a claim's words that must appear on a cited page.
+const SUPPORT_THRESHOLD: f32 = 0.6;
+
+/// How one claim of an answer held up against its source.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClaimCheck {
+    pub claim: String,
+    pub collection_id: String,
+    pub document_id: String,
+    pub document_name: String,
+    /// The cited page that matched best; `None` when pages aren't known.
+    pub page: Option<usize>,
+    pub supported: bool,
+    /// Share of the claim's words found on that page, 0.0 to 1.0.
+    pub overlap: f32,
+}
+
+/// A cited page with its text.
+struct Source {
+    collection_id: String,
+    document_id: String,
+    document_name: String,
+    page: Option<usize>,
+    text: String,
+}
+
+/// Check the sentences of `answer` that name a document in `citations`
+/// (the turn's [`ContentBlock::Citation`]s) against the cited pages.
+pub async fn verify_answer(
+    state: &AppState,
+    answer: &str,
+    citations: &[ContentBlock],
+) -> Vec<ClaimCheck> {
+    let sources = load_sources(state, citations).await;
+    check_claims(answer, &sources)
+}
+
+async fn load_sources(state: &AppState, citations: &[ContentBlock]) -> Vec<Source> {
+    let storage = state.storage.read().await;
+    let mut sources = Vec::new();
+    for block in citations {
+        let ContentBlock::Citation {
+            collection_id,
+            document_id,
+            document_name,
+            page,
+            ..
+        } = block
+        else {
+            continue;
+        };
+        let Ok(namespace_id) = collection_id.parse::<NamespaceId>() else {
+            continue;
+        };
+        let loaded = async {
+            let metadata = storage.get_document(namespace_id, document_id).await?;
+            let text = storage.get_document_text(namespace_id, document_id).await?;
+            anyhow::Ok(metadata.zip(text))
+        };
+        let (metadata, text) = match loaded.await {
+            Ok(Some(found)) => found,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(document_id = %document_id, error = %e, "Could not load cited document");
+                continue;
+            }
+        };
+        let text = String::from_utf8_lossy(&text).into_owned();
+        let text = match page {
+            Some(page) => page_spans(&text, &metadata.page_boundaries)
+                .get(page.saturating_sub(1))
+                .map(|range| text[range.clone()].to_string())
+                .unwrap_or_default(),
+            None => text,
+        };
+        sources.push(Source {
+            collection_id: collection_id.clone(),
+            document_id: document_id.clone(),
+            document_name: document_name.clone(),
+            page: *page,
+            text,
+        });
+    }
+    sources
+}
+
+fn check_claims(answer: &str, sources: &[Source]) -> Vec<ClaimCheck> {
+    let mut checks = Vec::new();
+    for claim in split_claims(answer) {
+        let lower = claim.to_lowercase();
+        let mut named: Vec<&Source> = sources
+            .iter()
+            .filter(|s| names_document(&lower, &s.document_name))
+            .collect();
+        // One check per document the claim names, against its best page.
+        named.sort_by(|a, b| a.document_id.cmp(&b.document_id));
+        for group in named.chunk_by(|a, b| a.document_id == b.document_id) {
+            let (source, overlap, figures_found) = group
+                .iter()
+                .map(|s| {
+                    let (overlap, figures) = compare(&claim, &s.document_name, &s.text);
+                    (*s, overlap, figures)
+                })
+                .max_by(|a, b| (a.2, a.1).partial_cmp(&(b.2, b.1)).unwrap())
+                .expect("chunk_by never yields empty groups");
+            checks.push(ClaimCheck {
+                claim: claim.clone(),
+                collection_id: source.collection_id.clone(),
+                document_id: source.document_id.clone(),
+                document_name: source.document_name.clone(),
+                page: source.page,
+                supported: figures_found && overlap >= SUPPORT_THRESHOLD,
+                overlap,
+            });
+        }
+    }
+    checks
+}
+
+/// Sentences of `text`, trimmed. Markdown list markers and headings are
+/// dropped, and a line break ends a sentence.
+fn split_claims(text: &str) -> Vec<String> {
+    let mut claims = Vec::new();
+    for line in text.lines() {
+        let line = line
+            .trim()
+            .trim_start_matches(['#', '-', '*', '>'])
+            .trim_start();
+        let mut start = 0;
+        let chars: Vec<(usize, char)> = line.char_indices().collect();
+        for (i, &(pos, c)) in chars.iter().enumerate() {
+            let next_is_space = chars.get(i + 1).is_none_or(|&(_, n)| n.is_whitespace());
+            // "3.5" and "U.S." don't end a sentence.
+            if matches!(c, '.' | '!' | '?') && next_is_space {
+                let prev_is_initial = pos >= 2
+                    && line[..pos]
+                        .chars()
+                        .rev()
+                        .nth(1)
+                        .is_some_and(|p| p == '.' || p.is_whitespace());
+                if !prev_is_initial {
+                    claims.push(line[start..pos + 1].trim().to_string());
+                    start = pos + 1;
+                }
+            }
+        }
+        claims.push(line[start..].trim().to_string());
+    }
+    claims.retain(|c| c.chars().any(char::is_alphabetic));
+    claims
+}
+
+/// Whether a lowercased claim mentions `name`, with or without its file
+/// extension.
+fn names_document(claim: &str, name: &str) -> bool {
+    let name = name.to_lowercase();
+    let stem = name
+        .rsplit_once('.')
+        .map_or(name.as_str(), |(stem, _)| stem);
+    !stem.is_empty() && (claim.contains(&name) || claim.contains(stem))
+}
+
+/// Share of the claim's words found in `source`, and whether every figure
+/// in the claim is there. Words of the document's name don't count.
+fn compare(claim: &str, document_name: &str, source: &str) -> (f32, bool) {
+    let name_words: HashSet<String> = words(document_name).into_iter().collect();
+    let source_words: HashSet<String> = words(source).into_iter().collect();
+
+    let claim_words = words(claim);
+    let terms: HashSet<&String> = claim_words
+        .iter()
+        .filter(|w| is_term(w))
+        .filter(|w| !name_words.contains(*w))
+        .collect();
+    let overlap = if terms.is_empty() {
+        0.0
+    } else {
+        terms.iter().filter(|w| source_words.contains(**w)).count() as f32 / terms.len() as f32
+    };
+
+    let figures_found = claim_words
+        .iter()
+        .filter(|w| w.chars().any(|c| c.is_ascii_digit()))
+        .filter(|w| !name_words.contains(*w))
+        .all(|w| source_words.contains(w));
+    (overlap, figures_found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(page: usize, text: &str) -> Source {
+        Source {
+            collection_id: "abc123".to_string(),
+            document_id: "doc1".to_string(),
+            document_name: "Acme Contract.pdf".to_string(),
+            page: Some(page),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_split_claims() {
+        let text = "Acme paid $1,200.50 in 2019. The U.S. office closed!\n- Was it audited?";
+        assert_eq!(
+            split_claims(text),
+            vec![
+                "Acme paid $1,200.50 in 2019.",
+                "The U.S. office closed!",
+                "Was it audited?"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_claims_flags_unsupported() {
+        let sources = [
+            source(2, "Parties: Acme Corp and Borealis Ltd."),
+            source(
+                4,
+                "Acme Corp paid Borealis a fee of $1,200 on 3 March 2019.",
+            ),
+        ];
+        let answer = "The Acme Contract shows Acme paid Borealis a fee of $1,200 in 2019. \
+                      Per the Acme Contract, the fee was $5,000. \
+                      Nothing else is known.";
+        let checks = check_claims(answer, &sources);
+
+        // The third sentence names no document, so it isn't checked.
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].supported);
+        assert_eq!(checks[0].page, Some(4));
+        // Same words, but the figure isn't in the source.
+        assert!(!checks[1].supported);
+    }
+
+    #[test]
+    fn test_names_document() {
+        assert!(names_document(
+            "see acme contract, p. 4",
+            "Acme Contract.pdf"
+        ));
+        assert!(names_document("in acme contract.pdf", "Acme Contract.pdf"));
+        assert!(!names_document("see the lease", "Acme Contract.pdf"));
+    }
+}
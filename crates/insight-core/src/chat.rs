@@ -0,0 +1,188 @@
+//! Messages between the members of a collection.
+//!
+//! Messages go out on a gossip topic per collection, derived from the
+//! namespace ID like [`crate::presence`]'s, so members online see them
+//! right away. The sender also stores each message in the collection under
+//! `_chat/`, where it syncs with the rest of the collection, so members who
+//! were offline find it in the history. Members with a read-only share
+//! can't write there: their messages reach those online only.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use futures::StreamExt;
+use iroh::EndpointId;
+use iroh_docs::NamespaceId;
+use iroh_gossip::api::{Event, GossipReceiver, GossipSender};
+use iroh_gossip::net::Gossip;
+use iroh_gossip::proto::TopicId;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// Longest message, in characters.
+pub const MAX_MESSAGE_CHARS: usize = 4000;
+
+/// Most recent messages returned as a collection's history.
+pub const HISTORY_LIMIT: usize = 200;
+
+/// Mixed into the namespace ID to get a collection's chat topic.
+const TOPIC_PREFIX: &[u8] = b"insight/chat/";
+
+/// Received messages held for subscribers that fall behind.
+const RECEIVED_CAPACITY: usize = 256;
+
+/// A message in a collection's chat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub id: String,
+    pub collection_id: String,
+    /// The sender's node ID.
+    pub node_id: String,
+    /// The sender's display name.
+    pub name: Option<String>,
+    pub text: String,
+    /// When it was sent, RFC 3339.
+    pub sent_at: String,
+}
+
+/// A collection's chat topic.
+struct Topic {
+    sender: GossipSender,
+    task: JoinHandle<()>,
+}
+
+/// Chat in every collection joined. Clones share state.
+#[derive(Clone)]
+pub struct Chat {
+    gossip: Gossip,
+    node_id: EndpointId,
+    topics: Arc<Mutex<HashMap<NamespaceId, Topic>>>,
+    received: broadcast::Sender<ChatMessage>,
+}
+
+impl Chat {
+    pub fn new(gossip: Gossip, node_id: EndpointId) -> Self {
+        let (received, _) = broadcast::channel(RECEIVED_CAPACITY);
+        Self {
+            gossip,
+            node_id,
+            topics: Arc::new(Mutex::new(HashMap::new())),
+            received,
+        }
+    }
+
+    /// Start receiving a collection's messages, meeting the other members
+    /// through `peers`. Nothing changes if already joined.
+    pub async fn join(&self, namespace_id: NamespaceId, peers: Vec<EndpointId>) -> Result<()> {
+        if self.topics.lock().unwrap().contains_key(&namespace_id) {
+            return Ok(());
+        }
+
+        let (sender, receiver) = self
+            .gossip
+            .subscribe(topic_id(namespace_id), peers)
+            .await?
+            .split();
+        let task = tokio::spawn(run_topic(namespace_id, receiver, self.received.clone()));
+
+        // A concurrent join may have won; keep its topic.
+        let mut topics = self.topics.lock().unwrap();
+        if topics.contains_key(&namespace_id) {
+            task.abort();
+        } else {
+            topics.insert(namespace_id, Topic { sender, task });
+        }
+        Ok(())
+    }
+
+    /// Stop sending and receiving a collection's messages.
+    pub fn leave(&self, namespace_id: &NamespaceId) {
+        if let Some(topic) = self.topics.lock().unwrap().remove(namespace_id) {
+            topic.task.abort();
+        }
+    }
+
+    /// Messages from other members as they arrive.
+    pub fn subscribe(&self) -> broadcast::Receiver<ChatMessage> {
+        self.received.subscribe()
+    }
+
+    /// Send `text` to the members of a collection online, as `name`.
+    /// Fails for empty or overlong messages and collections not joined.
+    pub async fn send(
+        &self,
+        namespace_id: NamespaceId,
+        name: Option<String>,
+        text: &str,
+    ) -> Result<ChatMessage> {
+        let text = text.trim();
+        if text.is_empty() {
+            bail!("Message is empty");
+        }
+        if text.chars().count() > MAX_MESSAGE_CHARS {
+            bail!("Message is longer than {} characters", MAX_MESSAGE_CHARS);
+        }
+        let sender = self
+            .topics
+            .lock()
+            .unwrap()
+            .get(&namespace_id)
+            .map(|topic| topic.sender.clone())
+            .context("Collection chat not joined")?;
+
+        let message = ChatMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            collection_id: namespace_id.to_string(),
+            node_id: self.node_id.to_string(),
+            name,
+            text: text.to_string(),
+            sent_at: Utc::now().to_rfc3339(),
+        };
+        sender
+            .broadcast(serde_json::to_vec(&message)?.into())
+            .await?;
+        Ok(message)
+    }
+}
+
+/// The chat topic of a collection.
+fn topic_id(namespace_id: NamespaceId) -> TopicId {
+    let hash = blake3::hash(&[TOPIC_PREFIX, namespace_id.as_bytes()].concat());
+    TopicId::from_bytes(*hash.as_bytes())
+}
+
+/// Pass on the messages arriving on a collection's topic.
+async fn run_topic(
+    namespace_id: NamespaceId,
+    mut receiver: GossipReceiver,
+    received: broadcast::Sender<ChatMessage>,
+) {
+    while let Some(event) = receiver.next().await {
+        match event {
+            Ok(Event::Received(message)) => {
+                match serde_json::from_slice::<ChatMessage>(&message.content) {
+                    Ok(mut message) => {
+                        // The topic says which collection it's for.
+                        message.collection_id = namespace_id.to_string();
+                        let _ = received.send(message);
+                    }
+                    Err(e) => {
+                        tracing::debug!(
+                            namespace = %namespace_id,
+                            error = %e,
+                            "Unreadable chat message"
+                        );
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(namespace = %namespace_id, error = %e, "Chat topic error");
+            }
+        }
+    }
+    tracing::debug!(namespace = %namespace_id, "Left chat topic");
+}
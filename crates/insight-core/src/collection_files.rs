@@ -0,0 +1,79 @@
+//! Per-collection JSON lists kept next to the settings.
+//!
+//! Memories and saved searches both keep one file per collection holding a
+//! list of entries. This module owns reading, writing and deleting those
+//! files; callers only decide what goes in the list.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Path of a collection's file in `dir`. Collection IDs come from the model
+/// via tool arguments, so anything that isn't a plain ID is refused.
+fn list_path(dir: &Path, collection_id: &str) -> Result<PathBuf> {
+    if collection_id.is_empty() || !collection_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        bail!("Invalid collection ID: {}", collection_id);
+    }
+    Ok(dir.join(format!("{}.json", collection_id)))
+}
+
+/// A collection's entries, oldest first. Empty when none were saved.
+/// `what` names the entries in error messages.
+pub(crate) fn load_list<T: DeserializeOwned>(
+    dir: &Path,
+    collection_id: &str,
+    what: &str,
+) -> Result<Vec<T>> {
+    let path = list_path(dir, collection_id)?;
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", what)),
+    };
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", what))
+}
+
+/// Replace a collection's entries, creating `dir` if needed.
+pub(crate) fn store_list<T: Serialize>(
+    dir: &Path,
+    collection_id: &str,
+    entries: &[T],
+    what: &str,
+) -> Result<()> {
+    let path = list_path(dir, collection_id)?;
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {} directory", what))?;
+    let content = serde_json::to_string_pretty(entries)
+        .with_context(|| format!("Failed to serialize {}", what))?;
+    std::fs::write(&path, content).with_context(|| format!("Failed to write {}", what))?;
+    Ok(())
+}
+
+/// Drop the entries matching `matches`. Returns whether any did.
+pub(crate) fn remove_from_list<T: Serialize + DeserializeOwned>(
+    dir: &Path,
+    collection_id: &str,
+    what: &str,
+    matches: impl Fn(&T) -> bool,
+) -> Result<bool> {
+    let mut entries: Vec<T> = load_list(dir, collection_id, what)?;
+    let before = entries.len();
+    entries.retain(|entry| !matches(entry));
+    if entries.len() == before {
+        return Ok(false);
+    }
+    store_list(dir, collection_id, &entries, what)?;
+    Ok(true)
+}
+
+/// Delete a collection's file, e.g. when the collection is deleted.
+/// Missing files are treated as success.
+pub(crate) fn delete_list(dir: &Path, collection_id: &str, what: &str) -> Result<()> {
+    let path = list_path(dir, collection_id)?;
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Failed to delete {}", what)),
+    }
+}
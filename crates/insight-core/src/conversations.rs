@@ -3,7 +3,7 @@ use std::path::Path;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
-use crate::agent::Conversation;
+use crate::agent::{Conversation, ConversationUsage};
 
 /// Summary of a conversation for listing
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +11,11 @@ pub struct ConversationSummary {
     pub id: String,
     pub title: String,
     pub updated_at: String,
+    /// Conversation this one was branched from, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub usage: ConversationUsage,
 }
 
 /// List all conversation summaries from disk, sorted by most recent first
@@ -32,6 +37,8 @@ pub fn list_conversations(conversations_dir: &Path) -> Result<Vec<ConversationSu
                         id: conv.id,
                         title: conv.title,
                         updated_at: conv.updated_at,
+                        parent_id: conv.parent_id,
+                        usage: conv.usage,
                     });
                 }
                 Err(e) => {
@@ -47,6 +54,16 @@ pub fn list_conversations(conversations_dir: &Path) -> Result<Vec<ConversationSu
     Ok(summaries)
 }
 
+/// Token usage summed over every stored conversation. Deleted
+/// conversations no longer count.
+pub fn total_usage(conversations_dir: &Path) -> Result<ConversationUsage> {
+    let mut total = ConversationUsage::default();
+    for summary in list_conversations(conversations_dir)? {
+        total.add(&summary.usage);
+    }
+    Ok(total)
+}
+
 /// Load a full conversation from disk
 pub fn load_conversation(path: &Path) -> Result<Conversation> {
     let content = std::fs::read_to_string(path).context("Failed to read conversation file")?;
@@ -0,0 +1,229 @@
+//! One stream of everything the core reports as it works.
+//!
+//! Subsystems keep their own channels — the pipeline's progress tracker,
+//! the model manager's status broadcast, the maintenance scheduler, an
+//! agent run's event sender — and [`AppState`](crate::AppState) forwards
+//! each into its [`EventBus`] as a [`CoreEvent`]. A consumer, the GUI or a
+//! headless one, makes one [`EventBus::subscribe`] call and sees it all.
+//!
+//! The bus is a broadcast channel: a subscriber more than
+//! [`EVENT_CAPACITY`] events behind skips ahead. Progress and status events
+//! are snapshots, so the next one makes up for any missed; agent text
+//! deltas are not, so a subscriber showing a conversation must keep up.
+
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::agent::AgentEvent;
+use crate::chat::ChatMessage;
+use crate::jobs::MaintenanceRun;
+use crate::pipeline::{
+    CollectionTransfer, EmbeddingProgress, FileProgress, PipelineProgress, SyncedDocument,
+    ThroughputStats,
+};
+use crate::storage::DocumentOffer;
+use crate::{ModelDownloadProgress, ModelStatus};
+
+/// Events held for subscribers that fall behind.
+pub const EVENT_CAPACITY: usize = 4096;
+
+/// Buffer of the channels handed out by [`EventBus::channel`].
+const CHANNEL_CAPACITY: usize = 100;
+
+/// Something the core reports.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "payload", rename_all = "snake_case")]
+pub enum CoreEvent {
+    /// A collection's stage counts changed.
+    PipelineProgress(PipelineProgress),
+    /// A document's chunks were embedded.
+    EmbeddingProgress(EmbeddingProgress),
+    /// A file moved through a stage.
+    FileProgress(FileProgress),
+    /// Pipeline throughput, every few seconds while work is queued.
+    Throughput(ThroughputStats),
+    /// A document from a peer got further towards being searchable.
+    SyncedDocument(SyncedDocument),
+    /// What a collection exchanged with peers, every few seconds while
+    /// it syncs.
+    TransferStats(CollectionTransfer),
+    /// A maintenance job ran.
+    Maintenance(MaintenanceRun),
+    /// A member of a collection sent a chat message.
+    ChatMessage(ChatMessage),
+    /// Another node sent this one a document.
+    DocumentOffered(DocumentOffer),
+    /// A model started downloading or loading, became ready, was unloaded
+    /// or failed.
+    ModelStatus(ModelStatus),
+    /// More of a model was downloaded.
+    ModelDownload(ModelDownloadProgress),
+    /// An agent run in a conversation streamed something.
+    Agent {
+        conversation_id: String,
+        event: AgentEvent,
+    },
+}
+
+macro_rules! impl_from {
+    ($($variant:ident($ty:ty)),* $(,)?) => {
+        $(impl From<$ty> for CoreEvent {
+            fn from(value: $ty) -> Self {
+                CoreEvent::$variant(value)
+            }
+        })*
+    };
+}
+
+impl_from!(
+    PipelineProgress(PipelineProgress),
+    EmbeddingProgress(EmbeddingProgress),
+    FileProgress(FileProgress),
+    Throughput(ThroughputStats),
+    SyncedDocument(SyncedDocument),
+    TransferStats(CollectionTransfer),
+    Maintenance(MaintenanceRun),
+    ChatMessage(ChatMessage),
+    DocumentOffered(DocumentOffer),
+    ModelStatus(ModelStatus),
+    ModelDownload(ModelDownloadProgress),
+);
+
+/// Where core events are published and subscribed to.
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<CoreEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { tx }
+    }
+
+    /// Publish `event` to current subscribers. Dropped if there are none.
+    pub fn publish(&self, event: impl Into<CoreEvent>) {
+        let _ = self.tx.send(event.into());
+    }
+
+    /// Receive every event published from now on.
+    pub fn subscribe(&self) -> EventSubscription {
+        EventSubscription {
+            rx: self.tx.subscribe(),
+        }
+    }
+
+    /// A sender for APIs that report through an mpsc channel, such as
+    /// model downloads; what's sent is published as `into` returns it.
+    /// Must be called from within the Tokio runtime.
+    pub fn channel<T: Send + 'static>(
+        &self,
+        into: impl Fn(T) -> CoreEvent + Send + 'static,
+    ) -> mpsc::Sender<T> {
+        let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+        self.forward_mpsc(rx, into);
+        tx
+    }
+
+    /// Publish what arrives on `rx` until it closes.
+    pub fn forward_mpsc<T: Send + 'static>(
+        &self,
+        mut rx: mpsc::Receiver<T>,
+        into: impl Fn(T) -> CoreEvent + Send + 'static,
+    ) {
+        let bus = self.clone();
+        tokio::spawn(async move {
+            while let Some(value) = rx.recv().await {
+                bus.publish(into(value));
+            }
+        });
+    }
+
+    /// Publish what arrives on `rx` until it closes. Values missed while
+    /// lagging are skipped.
+    pub fn forward<T: Clone + Send + 'static>(
+        &self,
+        mut rx: broadcast::Receiver<T>,
+        into: impl Fn(T) -> CoreEvent + Send + 'static,
+    ) {
+        let bus = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(value) => bus.publish(into(value)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A subscriber's view of the bus.
+pub struct EventSubscription {
+    rx: broadcast::Receiver<CoreEvent>,
+}
+
+impl EventSubscription {
+    /// The next event, or `None` once the bus is gone. Skips ahead if
+    /// this subscriber fell behind.
+    pub async fn recv(&mut self) -> Option<CoreEvent> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::debug!(missed, "Event subscriber fell behind");
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_forwards_channels_to_subscribers() {
+        let bus = EventBus::new();
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+
+        let (runs_tx, runs_rx) = broadcast::channel(4);
+        bus.forward(runs_rx, CoreEvent::Throughput);
+        let status_tx = bus.channel(CoreEvent::ModelStatus);
+
+        runs_tx.send(ThroughputStats::default()).unwrap();
+        let Some(CoreEvent::Throughput(_)) = first.recv().await else {
+            panic!("expected throughput");
+        };
+        status_tx
+            .send(ModelStatus::Ready {
+                model_type: crate::ModelType::Embedding,
+                model_id: "m".to_string(),
+            })
+            .await
+            .unwrap();
+        let Some(CoreEvent::ModelStatus(ModelStatus::Ready { model_id, .. })) = first.recv().await
+        else {
+            panic!("expected model status");
+        };
+        assert_eq!(model_id, "m");
+
+        // Both subscribers see everything, in order.
+        assert!(matches!(
+            second.recv().await,
+            Some(CoreEvent::Throughput(_))
+        ));
+        let event = serde_json::to_value(second.recv().await.unwrap()).unwrap();
+        assert_eq!(event["kind"], "model_status");
+        assert_eq!(event["payload"]["status"], "ready");
+    }
+}
@@ -0,0 +1,495 @@
+//! Following RSS and Atom feeds.
+//!
+//! Each [`Feed`] in settings names a feed URL and the collection its
+//! articles go to. The maintenance scheduler calls [`check_feeds`] on an
+//! interval: articles not seen before are fetched from their links and
+//! stored as Markdown, with the page converted from HTML to text, or
+//! stored as-is when the link is a PDF or text file. When the page can't
+//! be fetched the feed's own copy of the article is used. The articles
+//! seen in each feed are kept in a small state file, so each is imported
+//! once, even across restarts.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use iroh_docs::NamespaceId;
+use quick_xml::events::Event;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Settings;
+use crate::net::{self, file_stem, Download};
+use crate::storage::MARKDOWN_FILE_TYPE;
+use crate::AppState;
+
+/// Tag on every document imported from a feed.
+pub const FEED_TAG: &str = "feed";
+
+/// Most articles imported from one feed per check; the rest wait for the
+/// next one.
+const MAX_PER_CHECK: usize = 20;
+
+/// Article IDs remembered per feed. Far more than a feed lists at once,
+/// so an article is never forgotten while it's still in the feed.
+const MAX_REMEMBERED: usize = 1000;
+
+/// Width text is wrapped at when converting HTML; wide enough to leave
+/// paragraphs on one line.
+const TEXT_WIDTH: usize = 10_000;
+
+/// A feed to follow, as kept in settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Feed {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    /// Collection articles are imported into.
+    pub collection_id: String,
+}
+
+impl Feed {
+    /// Check the feed is filled in enough to fetch.
+    pub fn validate(&self) -> Result<()> {
+        match reqwest::Url::parse(&self.url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+            _ => bail!("Not an http(s) URL: {}", self.url),
+        }
+        if self.collection_id.parse::<NamespaceId>().is_err() {
+            bail!("Choose a collection");
+        }
+        Ok(())
+    }
+}
+
+/// One article in a feed.
+#[derive(Debug, Default, PartialEq)]
+struct Item {
+    /// RSS `guid` or Atom `id`.
+    id: Option<String>,
+    title: String,
+    link: Option<String>,
+    published: Option<String>,
+    /// The feed's copy of the article, as HTML: full content if the feed
+    /// has it, otherwise the summary.
+    content: Option<String>,
+    /// Whether `content` is the full content rather than a summary.
+    full_content: bool,
+}
+
+impl Item {
+    /// What identifies the article across checks.
+    fn key(&self) -> &str {
+        self.id
+            .as_deref()
+            .or(self.link.as_deref())
+            .unwrap_or(&self.title)
+    }
+}
+
+/// Import new articles from every configured feed. A feed that fails is
+/// logged and skipped. Returns a one-line summary.
+pub async fn check_feeds(state: &AppState) -> Result<String> {
+    let feeds = Settings::load(&state.config.settings_file).feeds;
+    if feeds.is_empty() {
+        return Ok("No feeds to check".to_string());
+    }
+
+    let state_file = state.config.feed_state_file();
+    let mut seen = read_seen(&state_file)?;
+    let (mut imported, mut failed) = (0, 0);
+    for feed in &feeds {
+        match check_feed(state, feed, &state_file, &mut seen).await {
+            Ok(count) => imported += count,
+            Err(e) => {
+                tracing::warn!(feed = %feed.url, error = %e, "Feed check failed");
+                failed += 1;
+            }
+        }
+    }
+
+    let mut summary = format!(
+        "Imported {} articles from {} feeds",
+        imported,
+        feeds.len() - failed
+    );
+    if failed > 0 {
+        summary.push_str(&format!(", {} failed", failed));
+    }
+    Ok(summary)
+}
+
+/// Import the articles of `feed` not in `seen`. Returns how many were
+/// imported.
+async fn check_feed(
+    state: &AppState,
+    feed: &Feed,
+    state_file: &Path,
+    seen: &mut BTreeMap<String, Vec<String>>,
+) -> Result<usize> {
+    let namespace_id: NamespaceId = feed
+        .collection_id
+        .parse()
+        .context("The feed's collection no longer exists")?;
+    let client = net::http_client()?;
+    let response = client
+        .get(&feed.url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch {}", feed.url))?
+        .error_for_status()?;
+    let body = net::read_body(response, &feed.url).await?;
+    let items = parse_feed(&String::from_utf8_lossy(&body.bytes))?;
+
+    let remembered = seen.entry(feed.id.clone()).or_default();
+    let new: Vec<Item> = items
+        .into_iter()
+        .filter(|item| item.link.is_some() || item.id.is_some())
+        .filter(|item| !remembered.iter().any(|key| key == item.key()))
+        .take(MAX_PER_CHECK)
+        .collect();
+
+    let mut imported = 0;
+    for item in new {
+        let source = item
+            .link
+            .clone()
+            .or_else(|| item.id.clone())
+            .unwrap_or_default();
+        let document = match article(&client, feed, &item).await {
+            Ok(document) => document,
+            Err(e) => {
+                // Left unseen, so it's tried again next time.
+                tracing::warn!(url = %source, error = %e, "Skipping feed article");
+                continue;
+            }
+        };
+        state
+            .storage
+            .read()
+            .await
+            .store_downloaded_source(
+                namespace_id,
+                &source,
+                document.file_name,
+                &document.bytes,
+                document.file_type,
+                &[FEED_TAG.to_string()],
+            )
+            .await?;
+        imported += 1;
+
+        let remembered = seen.entry(feed.id.clone()).or_default();
+        remembered.push(item.key().to_string());
+        let excess = remembered.len().saturating_sub(MAX_REMEMBERED);
+        remembered.drain(..excess);
+        // Saved per article so a failure part-way doesn't import the
+        // earlier ones twice.
+        write_seen(state_file, seen)?;
+    }
+    Ok(imported)
+}
+
+/// The document an article becomes: the linked page as Markdown, or the
+/// linked file when it's a PDF or text. Falls back to the feed's copy when
+/// the link can't be fetched or has no text.
+async fn article(client: &reqwest::Client, feed: &Feed, item: &Item) -> Result<Download> {
+    let fetched = match item.link.as_deref() {
+        // Feeds with the full text don't need the page, with its menus
+        // and footers.
+        Some(link) if !item.full_content => fetch_page(client, link).await,
+        _ => Ok(None),
+    };
+    let text = match fetched {
+        Ok(Some(Page::Document(download))) => return Ok(download),
+        Ok(Some(Page::Text(text))) => Some(text),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::debug!(error = %e, "Falling back to the feed's copy of an article");
+            None
+        }
+    };
+    let text = text
+        .or_else(|| {
+            item.content
+                .as_deref()
+                .map(|html| html_to_text(html.as_bytes()))
+        })
+        .filter(|text| !text.trim().is_empty());
+    let Some(text) = text else {
+        bail!("No text for \"{}\"", item.title);
+    };
+    Ok(Download {
+        file_name: format!("{}.md", file_stem(&item.title)),
+        file_type: MARKDOWN_FILE_TYPE,
+        bytes: article_markdown(feed, item, &text).into_bytes(),
+    })
+}
+
+/// A fetched article link.
+enum Page {
+    /// An HTML page, converted to text.
+    Text(String),
+    /// A PDF or text file.
+    Document(Download),
+}
+
+async fn fetch_page(client: &reqwest::Client, link: &str) -> Result<Option<Page>> {
+    let response = client
+        .get(link)
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch {}", link))?
+        .error_for_status()?;
+    let body = net::read_body(response, link).await?;
+    let is_html = body
+        .content_type
+        .as_deref()
+        .is_some_and(|ct| ct == "text/html" || ct == "application/xhtml+xml");
+    if is_html {
+        let text = html_to_text(main_content(&body.bytes));
+        return Ok((!text.trim().is_empty()).then_some(Page::Text(text)));
+    }
+    Ok(Some(Page::Document(body.into_document(link)?)))
+}
+
+/// The part of a page between its first `<article>` (or `<main>`) and the
+/// last closing tag, or all of it. Cuts most navigation and footers
+/// without parsing the page twice.
+fn main_content(html: &[u8]) -> &[u8] {
+    let lower = html.to_ascii_lowercase();
+    for tag in ["article", "main"] {
+        let open = format!("<{}", tag);
+        let close = format!("</{}>", tag);
+        let start = lower.windows(open.len()).position(|w| w == open.as_bytes());
+        let end = lower
+            .windows(close.len())
+            .rposition(|w| w == close.as_bytes());
+        if let (Some(start), Some(end)) = (start, end) {
+            if start < end {
+                return &html[start..end + close.len()];
+            }
+        }
+    }
+    html
+}
+
+fn html_to_text(html: &[u8]) -> String {
+    html2text::config::plain()
+        .link_footnotes(false)
+        .string_from_read(html, TEXT_WIDTH)
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+fn article_markdown(feed: &Feed, item: &Item, text: &str) -> String {
+    let mut markdown = format!("# {}\n\n", item.title);
+    if let Some(ref link) = item.link {
+        markdown.push_str(&format!("- Source: {}\n", link));
+    }
+    if let Some(ref published) = item.published {
+        markdown.push_str(&format!("- Published: {}\n", published));
+    }
+    markdown.push_str(&format!("- Feed: {}\n\n", feed.name));
+    markdown.push_str(text);
+    markdown.push('\n');
+    markdown
+}
+
+/// The articles of an RSS (0.9x, 1.0, 2.0) or Atom feed, in feed order.
+fn parse_feed(xml: &str) -> Result<Vec<Item>> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    // Local names of the open elements.
+    let mut path: Vec<String> = Vec::new();
+    let mut items = Vec::new();
+    let mut item: Option<Item> = None;
+    let mut text = String::new();
+    loop {
+        match reader.read_event().context("Unreadable feed")? {
+            Event::Start(e) => {
+                let name = local_name(e.name());
+                if matches!(name.as_str(), "item" | "entry") {
+                    item = Some(Item::default());
+                } else if name == "link" {
+                    if let Some(ref mut item) = item {
+                        atom_link(item, &e);
+                    }
+                }
+                path.push(name);
+                text.clear();
+            }
+            Event::Empty(e) if local_name(e.name()) == "link" => {
+                if let Some(ref mut item) = item {
+                    atom_link(item, &e);
+                }
+            }
+            Event::Text(e) => {
+                let unescaped = e
+                    .unescape()
+                    .map(|t| t.into_owned())
+                    // HTML entities, e.g. &nbsp;, aren't XML ones.
+                    .unwrap_or_else(|_| String::from_utf8_lossy(&e).into_owned());
+                text.push_str(&unescaped);
+            }
+            Event::CData(e) => text.push_str(&String::from_utf8_lossy(&e)),
+            Event::End(_) => {
+                let name = path.pop().unwrap_or_default();
+                let in_item = path
+                    .last()
+                    .is_some_and(|parent| matches!(parent.as_str(), "item" | "entry"));
+                if matches!(name.as_str(), "item" | "entry") {
+                    items.extend(item.take());
+                } else if let (true, Some(item)) = (in_item, item.as_mut()) {
+                    let value = std::mem::take(&mut text).trim().to_string();
+                    if !value.is_empty() {
+                        set_field(item, &name, value);
+                    }
+                }
+                text.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(items)
+}
+
+fn set_field(item: &mut Item, name: &str, value: String) {
+    match name {
+        "title" => item.title = value,
+        // RSS links are text; Atom's are attributes, see `atom_link`.
+        "link" if item.link.is_none() => item.link = Some(value),
+        "guid" | "id" => item.id = Some(value),
+        "published" | "pubDate" | "date" => item.published = Some(value),
+        "updated" if item.published.is_none() => item.published = Some(value),
+        "encoded" | "content" => {
+            item.content = Some(value);
+            item.full_content = true;
+        }
+        "description" | "summary" if !item.full_content => item.content = Some(value),
+        _ => {}
+    }
+}
+
+/// Take an Atom `<link href="…">` to the article itself.
+fn atom_link(item: &mut Item, e: &quick_xml::events::BytesStart<'_>) {
+    let mut href = None;
+    let mut alternate = true;
+    for attr in e.attributes().flatten() {
+        let value = attr.unescape_value().map(|v| v.into_owned()).ok();
+        match attr.key.local_name().as_ref() {
+            b"href" => href = value,
+            b"rel" => alternate = value.as_deref() == Some("alternate"),
+            _ => {}
+        }
+    }
+    if let (Some(href), true, None) = (href, alternate, &item.link) {
+        item.link = Some(href);
+    }
+}
+
+fn local_name(name: quick_xml::name::QName<'_>) -> String {
+    String::from_utf8_lossy(name.local_name().as_ref()).into_owned()
+}
+
+fn read_seen(file: &Path) -> Result<BTreeMap<String, Vec<String>>> {
+    match std::fs::read_to_string(file) {
+        Ok(contents) => serde_json::from_str(&contents).context("Feed state file is corrupt"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e).context("Failed to read feed state"),
+    }
+}
+
+fn write_seen(file: &Path, seen: &BTreeMap<String, Vec<String>>) -> Result<()> {
+    std::fs::write(file, serde_json::to_string(seen)?).context("Failed to write feed state")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss() {
+        let xml = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+  <channel>
+    <title>City Desk</title>
+    <link>https://news.example.org/</link>
+    <item>
+      <title>Council approves port deal</title>
+      <link>https://news.example.org/port</link>
+      <guid isPermaLink="false">port-2024</guid>
+      <pubDate>Fri, 01 Mar 2024 10:00:00 GMT</pubDate>
+      <description>Short &amp; sweet&nbsp;summary</description>
+      <content:encoded><![CDATA[<p>The <b>full</b> story.</p>]]></content:encoded>
+    </item>
+    <item>
+      <title>Budget hearing</title>
+      <link>https://news.example.org/budget</link>
+      <description><![CDATA[<p>Summary only.</p>]]></description>
+    </item>
+  </channel>
+</rss>"#;
+        let items = parse_feed(xml).unwrap();
+        assert_eq!(
+            items,
+            vec![
+                Item {
+                    id: Some("port-2024".to_string()),
+                    title: "Council approves port deal".to_string(),
+                    link: Some("https://news.example.org/port".to_string()),
+                    published: Some("Fri, 01 Mar 2024 10:00:00 GMT".to_string()),
+                    content: Some("<p>The <b>full</b> story.</p>".to_string()),
+                    full_content: true,
+                },
+                Item {
+                    id: None,
+                    title: "Budget hearing".to_string(),
+                    link: Some("https://news.example.org/budget".to_string()),
+                    published: None,
+                    content: Some("<p>Summary only.</p>".to_string()),
+                    full_content: false,
+                },
+            ]
+        );
+        assert_eq!(items[1].key(), "https://news.example.org/budget");
+    }
+
+    #[test]
+    fn test_parse_atom() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Filings</title>
+  <link href="https://filings.example.org/"/>
+  <entry>
+    <title>Annual report</title>
+    <link rel="enclosure" href="https://filings.example.org/report.pdf"/>
+    <link rel="alternate" href="https://filings.example.org/annual"/>
+    <id>urn:uuid:1225c695</id>
+    <updated>2024-03-01T10:00:00Z</updated>
+    <summary>Filed today.</summary>
+  </entry>
+</feed>"#;
+        let items = parse_feed(xml).unwrap();
+        assert_eq!(
+            items,
+            vec![Item {
+                id: Some("urn:uuid:1225c695".to_string()),
+                title: "Annual report".to_string(),
+                link: Some("https://filings.example.org/annual".to_string()),
+                published: Some("2024-03-01T10:00:00Z".to_string()),
+                content: Some("Filed today.".to_string()),
+                full_content: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_main_content() {
+        let page = b"<nav>Home</nav><ARTICLE><p>Story</p></ARTICLE><footer>(c)</footer>";
+        assert_eq!(main_content(page), b"<ARTICLE><p>Story</p></ARTICLE>");
+        assert_eq!(main_content(b"<p>Bare</p>"), b"<p>Bare</p>");
+        assert_eq!(html_to_text(main_content(page)), "Story");
+    }
+}
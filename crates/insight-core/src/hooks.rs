@@ -0,0 +1,289 @@
+//! Hooks that tell other systems about documents coming and going.
+//!
+//! A [`Hook`] in settings names the events it wants and what to do on
+//! each: POST a JSON [`HookPayload`] to a URL, or run a local command with
+//! the payload on stdin. Events come from the collection watchers (see
+//! [`crate::pipeline`]), so they fire however a document was added or
+//! removed: an import, the agent, or a peer syncing the collection.
+//!
+//! Deliveries run in the background and are not retried; a failure is
+//! logged. [`Hooks::test`] delivers a test payload and reports the result,
+//! for checking a hook when it's set up.
+
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use iroh_docs::NamespaceId;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+use crate::storage::{DocumentMetadata, Storage};
+
+/// How long a delivery may take before it's abandoned.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What happened to a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEvent {
+    /// Stored in a collection on this device.
+    Imported,
+    /// Removed from a collection, here or by a peer.
+    Deleted,
+    /// Arrived from a peer syncing the collection.
+    Synced,
+}
+
+/// What a hook does when its events fire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HookAction {
+    /// POST the payload as JSON.
+    Http { url: String },
+    /// Run `program` with `args`, the payload as JSON on stdin and the
+    /// event, collection and document in `INSIGHT_EVENT`,
+    /// `INSIGHT_COLLECTION_ID` and `INSIGHT_DOCUMENT_ID`.
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+    },
+}
+
+/// A hook, as kept in settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hook {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    pub events: Vec<HookEvent>,
+    pub action: HookAction,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Hook {
+    /// Check the hook is filled in enough to run.
+    pub fn validate(&self) -> Result<()> {
+        if self.events.is_empty() {
+            bail!("Choose at least one event");
+        }
+        match &self.action {
+            HookAction::Http { url } => match reqwest::Url::parse(url) {
+                Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+                _ => bail!("Not an http(s) URL: {}", url),
+            },
+            HookAction::Command { program, .. } => {
+                if program.trim().is_empty() {
+                    bail!("Enter a command to run");
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// What a hook receives.
+#[derive(Debug, Clone, Serialize)]
+pub struct HookPayload {
+    pub event: HookEvent,
+    pub collection_id: String,
+    pub document_id: String,
+    /// The document's metadata when the event fired. `None` for deleted
+    /// documents, and for synced ones whose metadata hasn't arrived yet.
+    /// Page counts are filled in once text is extracted, so they are 0 on
+    /// import.
+    pub document: Option<DocumentMetadata>,
+    pub occurred_at: String,
+    /// Set on payloads sent by [`Hooks::test`].
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub test: bool,
+}
+
+/// The configured hooks, shared by the collection watchers.
+#[derive(Clone)]
+pub struct Hooks {
+    hooks: Arc<std::sync::RwLock<Vec<Hook>>>,
+    storage: Arc<RwLock<Storage>>,
+}
+
+impl Hooks {
+    pub fn new(storage: Arc<RwLock<Storage>>) -> Self {
+        Self {
+            hooks: Arc::default(),
+            storage,
+        }
+    }
+
+    /// Replace the configured hooks.
+    pub fn set(&self, hooks: Vec<Hook>) {
+        *self.hooks.write().unwrap_or_else(|e| e.into_inner()) = hooks;
+    }
+
+    /// Run the enabled hooks for `event` in the background.
+    pub fn notify(&self, event: HookEvent, namespace_id: NamespaceId, document_id: &str) {
+        let hooks: Vec<Hook> = self
+            .hooks
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|hook| hook.enabled && hook.events.contains(&event))
+            .cloned()
+            .collect();
+        if hooks.is_empty() {
+            return;
+        }
+
+        let storage = self.storage.clone();
+        let document_id = document_id.to_string();
+        tokio::spawn(async move {
+            let document = match event {
+                HookEvent::Deleted => None,
+                HookEvent::Imported | HookEvent::Synced => storage
+                    .read()
+                    .await
+                    .get_document(namespace_id, &document_id)
+                    .await
+                    .unwrap_or_else(|e| {
+                        tracing::debug!(doc_id = %document_id, error = %e, "No metadata for hook");
+                        None
+                    }),
+            };
+            let payload = HookPayload {
+                event,
+                collection_id: namespace_id.to_string(),
+                document_id,
+                document,
+                occurred_at: chrono::Utc::now().to_rfc3339(),
+                test: false,
+            };
+            let deliveries = hooks.iter().map(|hook| async {
+                if let Err(e) = deliver(&hook.action, &payload).await {
+                    tracing::warn!(hook = %hook.name, event = ?event, error = %e, "Hook failed");
+                }
+            });
+            futures::future::join_all(deliveries).await;
+        });
+    }
+
+    /// Deliver a test payload to `hook` and wait for the result.
+    pub async fn test(hook: &Hook) -> Result<()> {
+        let payload = HookPayload {
+            event: hook.events.first().copied().unwrap_or(HookEvent::Imported),
+            collection_id: String::new(),
+            document_id: String::new(),
+            document: None,
+            occurred_at: chrono::Utc::now().to_rfc3339(),
+            test: true,
+        };
+        deliver(&hook.action, &payload).await
+    }
+}
+
+async fn deliver(action: &HookAction, payload: &HookPayload) -> Result<()> {
+    let delivery = async {
+        match action {
+            HookAction::Http { url } => post(url, payload).await,
+            HookAction::Command { program, args } => run(program, args, payload).await,
+        }
+    };
+    tokio::time::timeout(DELIVERY_TIMEOUT, delivery)
+        .await
+        .context("Timed out")?
+}
+
+async fn post(url: &str, payload: &HookPayload) -> Result<()> {
+    crate::net::http_client()?
+        .post(url)
+        .json(payload)
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {}", url))?
+        .error_for_status()?;
+    Ok(())
+}
+
+async fn run(program: &str, args: &[String], payload: &HookPayload) -> Result<()> {
+    let event = serde_json::to_value(payload.event)?;
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .env("INSIGHT_EVENT", event.as_str().unwrap_or_default())
+        .env("INSIGHT_COLLECTION_ID", &payload.collection_id)
+        .env("INSIGHT_DOCUMENT_ID", &payload.document_id)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        // Stopped if the delivery times out.
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores its input may exit before reading it.
+        let _ = stdin.write_all(&serde_json::to_vec(payload)?).await;
+    }
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            stderr.trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_hook_receives_payload() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("payload.json");
+        let hook = Hook {
+            id: "h".to_string(),
+            name: "Log".to_string(),
+            enabled: true,
+            events: vec![HookEvent::Deleted],
+            action: HookAction::Command {
+                program: "sh".to_string(),
+                args: vec![
+                    "-c".to_string(),
+                    format!(
+                        "echo \"$INSIGHT_EVENT\" > '{0}.event'; cat > '{0}'",
+                        out.display()
+                    ),
+                ],
+            },
+        };
+        Hooks::test(&hook).await.unwrap();
+
+        let payload: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&out).unwrap()).unwrap();
+        assert_eq!(payload["event"], "deleted");
+        assert_eq!(payload["test"], true);
+        assert!(payload["document"].is_null());
+        let event = std::fs::read_to_string(dir.path().join("payload.json.event")).unwrap();
+        assert_eq!(event.trim(), "deleted");
+
+        let failing = Hook {
+            action: HookAction::Command {
+                program: "sh".to_string(),
+                args: vec!["-c".to_string(), "echo nope >&2; exit 3".to_string()],
+            },
+            ..hook
+        };
+        let error = Hooks::test(&failing).await.unwrap_err().to_string();
+        assert!(error.contains("nope"), "{}", error);
+    }
+}
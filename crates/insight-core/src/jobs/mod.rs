@@ -0,0 +1,495 @@
+//! Long-running work over a whole collection.
+//!
+//! `batch_ask` puts one question to every document in a collection — "what
+//! is the termination clause?" across a folder of contracts — and returns a
+//! table of answers, each citing the pages it came from. Every document is
+//! answered on its own from the pages a hybrid search of it ranks best, so
+//! one long document can't crowd the others out of the context window.
+//!
+//! `reembed_collection` regenerates a collection's embeddings with a given
+//! model, e.g. after changing the chunking or to prepare a model before
+//! switching to it.
+//!
+//! [`Scheduler`] runs the periodic maintenance jobs: blob GC, search index
+//! verification, saved-search alerts and the re-embedding backlog.
+//!
+//! Embedding itself isn't queued here. It is the last stage of
+//! [`crate::pipeline`], fed by the same watcher and progress tracker as
+//! extraction, so its queue, workers and restart resume live there; the
+//! re-embedding job only hands documents missing embeddings back to it.
+
+mod scheduler;
+
+pub use scheduler::{MaintenanceRun, MaintenanceTask, Scheduler};
+
+use anyhow::{bail, Context, Result};
+use iroh_docs::NamespaceId;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::agent::summarize::CHARS_PER_TOKEN;
+use crate::agent::tools::{document_passages, page_spans};
+use crate::agent::{AgentContext, ContentBlock, Message, MessageRole};
+use crate::config::Settings;
+use crate::prompts::PromptLibrary;
+use crate::provider::{self, ChatProvider, Provider, StructuredSchema};
+use crate::{models, AppState, CollectionInfo};
+
+/// Share of the context window, in percent, the excerpts of one document
+/// may use.
+const EXCERPT_SHARE_PERCENT: usize = 50;
+
+/// Most pages sent per document.
+const MAX_PAGES_PER_DOCUMENT: usize = 6;
+
+/// Passages searched for in each document to rank its pages.
+const SEARCH_HITS_PER_DOCUMENT: usize = 20;
+
+/// One row of a batch answer table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchAnswer {
+    pub document_id: String,
+    pub document_name: String,
+    /// Whether the document answers the question at all.
+    pub found: bool,
+    pub answer: String,
+    /// Pages the answer is drawn from.
+    pub pages: Vec<usize>,
+    /// Why the document couldn't be answered, e.g. no extracted text.
+    pub error: Option<String>,
+}
+
+/// How far a batch has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BatchProgress {
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Ask `question` of every document in the collection, reporting progress
+/// after each one. A document that fails gets an error row instead of
+/// stopping the batch; cancelling does stop it.
+pub async fn batch_ask(
+    state: &AppState,
+    namespace_id: NamespaceId,
+    question: &str,
+    mut on_progress: impl FnMut(BatchProgress),
+    cancel_token: &CancellationToken,
+) -> Result<Vec<BatchAnswer>> {
+    let question = question.trim();
+    if question.is_empty() {
+        bail!("Nothing to ask");
+    }
+
+    let (collection, documents) = {
+        let storage = state.storage.read().await;
+        let collection = storage
+            .get_collection_metadata(namespace_id)
+            .await?
+            .context("Collection not found")?;
+        let documents = storage
+            .list_documents(namespace_id)
+            .await
+            .context("Failed to list documents")?;
+        (collection, documents)
+    };
+    let ctx = AgentContext::new(
+        state.clone(),
+        Some(vec![CollectionInfo {
+            id: namespace_id.to_string(),
+            name: collection.name,
+            document_count: documents.len(),
+            total_pages: documents.iter().map(|d| d.page_count).sum(),
+            created_at: Some(collection.created_at),
+        }]),
+    );
+    // Rank every document's pages before the chat model is loaded, so
+    // the embedder and chat model needn't take turns per document.
+    let mut rankings = Vec::with_capacity(documents.len());
+    for metadata in &documents {
+        if cancel_token.is_cancelled() {
+            bail!("Batch question cancelled");
+        }
+        let ranked: Vec<usize> =
+            match document_passages(&ctx, &metadata.id, question, SEARCH_HITS_PER_DOCUMENT).await {
+                Ok(passages) => passages.into_iter().filter_map(|p| p.page).collect(),
+                Err(e) => {
+                    warn!(document_id = %metadata.id, error = %e, "Search failed");
+                    Vec::new()
+                }
+            };
+        rankings.push(ranked);
+    }
+
+    let lease = state
+        .models
+        .acquire_chat()
+        .await?
+        .context("No chat model is configured")?;
+    let provider = lease.provider();
+    let prompts = PromptLibrary::load(&state.config.prompts_dir());
+
+    info!(documents = documents.len(), question = %question, "Starting batch question");
+
+    let total = documents.len();
+    let mut answers = Vec::with_capacity(total);
+    for (i, (metadata, ranked)) in documents.into_iter().zip(rankings).enumerate() {
+        if cancel_token.is_cancelled() {
+            bail!("Batch question cancelled");
+        }
+
+        let text = state
+            .storage
+            .read()
+            .await
+            .get_document_text(namespace_id, &metadata.id)
+            .await;
+        let text = match text {
+            Ok(Some(text)) => String::from_utf8_lossy(&text).into_owned(),
+            Ok(None) => String::new(),
+            Err(e) => {
+                warn!(document_id = %metadata.id, error = %e, "Error reading document");
+                String::new()
+            }
+        };
+        let pages: Vec<&str> = page_spans(&text, &metadata.page_boundaries)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+
+        let mut row = BatchAnswer {
+            document_id: metadata.id.clone(),
+            document_name: metadata.name.clone(),
+            found: false,
+            answer: String::new(),
+            pages: Vec::new(),
+            error: None,
+        };
+        match answer_document(
+            provider,
+            &prompts,
+            &metadata.name,
+            &pages,
+            &ranked,
+            question,
+            cancel_token,
+        )
+        .await
+        {
+            Ok((found, answer, cited)) => {
+                row.found = found;
+                row.answer = answer;
+                row.pages = cited;
+            }
+            Err(e) => {
+                if cancel_token.is_cancelled() {
+                    bail!("Batch question cancelled");
+                }
+                warn!(document_id = %metadata.id, error = %e, "Batch answer failed");
+                row.error = Some(e.to_string());
+            }
+        }
+        answers.push(row);
+        on_progress(BatchProgress {
+            completed: i + 1,
+            total,
+        });
+    }
+
+    info!(
+        answered = answers.iter().filter(|a| a.found).count(),
+        total, "Finished batch question"
+    );
+    Ok(answers)
+}
+
+/// What re-embedding a collection did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ReembedSummary {
+    pub embedded: usize,
+    pub failed: usize,
+}
+
+/// Embed every document of the collection that has text again with
+/// `model_id`, replacing its stored vectors and reporting progress after
+/// each one. A document that fails is counted and skipped; cancelling stops
+/// the job, keeping what was already embedded.
+///
+/// The active model's vectors are indexed as they are stored. Any other
+/// downloaded model can be used too: its vectors are kept for when the
+/// user switches to it, and it is loaded for this job alone.
+pub async fn reembed_collection(
+    state: &AppState,
+    namespace_id: NamespaceId,
+    model_id: &str,
+    mut on_progress: impl FnMut(BatchProgress),
+    cancel_token: &CancellationToken,
+) -> Result<ReembedSummary> {
+    let model = models::get_embedding_model(model_id)
+        .with_context(|| format!("Unknown embedding model: {}", model_id))?;
+    let active = state.models.embedding_model_id().await.as_deref() == Some(model_id);
+    // The active model is borrowed from the model manager per document;
+    // another is loaded here and unloaded when the job ends.
+    let standalone = if active {
+        None
+    } else {
+        if !state.model_downloader.is_downloaded(&model) {
+            bail!("Download {} before re-embedding with it", model.name);
+        }
+        let device = Settings::load(&state.config.settings_file)
+            .devices
+            .embedding;
+        Some(provider::local::embedding_provider(
+            model_id,
+            &model,
+            &state.model_downloader,
+            &device,
+        )?)
+    };
+
+    let doc_ids = state
+        .storage
+        .read()
+        .await
+        .list_documents_with_text(namespace_id)
+        .await
+        .context("Failed to list documents")?;
+    info!(
+        namespace = %namespace_id,
+        model = %model_id,
+        documents = doc_ids.len(),
+        "Re-embedding collection"
+    );
+
+    let total = doc_ids.len();
+    let mut summary = ReembedSummary {
+        embedded: 0,
+        failed: 0,
+    };
+    for (i, doc_id) in doc_ids.iter().enumerate() {
+        if cancel_token.is_cancelled() {
+            break;
+        }
+        let result = match &standalone {
+            Some(embedder) => {
+                embed_for_job(
+                    state,
+                    namespace_id,
+                    doc_id,
+                    &**embedder,
+                    model_id,
+                    cancel_token,
+                )
+                .await
+            }
+            None => match state.models.acquire_embedding().await {
+                Ok(Some(lease)) => {
+                    let embedder = lease.provider();
+                    embed_for_job(
+                        state,
+                        namespace_id,
+                        doc_id,
+                        embedder,
+                        model_id,
+                        cancel_token,
+                    )
+                    .await
+                }
+                Ok(None) => Err(anyhow::anyhow!("Embedder not configured")),
+                Err(e) => Err(e),
+            },
+        };
+        match result {
+            Ok(()) => summary.embedded += 1,
+            Err(_) if cancel_token.is_cancelled() => break,
+            Err(e) => {
+                warn!(document_id = %doc_id, error = %e, "Re-embed failed");
+                summary.failed += 1;
+            }
+        }
+        on_progress(BatchProgress {
+            completed: i + 1,
+            total,
+        });
+    }
+
+    if let Some(embedder) = standalone {
+        if let Err(e) = embedder.unload().await {
+            warn!(error = %e, "Failed to unload the re-embed model");
+        }
+    }
+    if cancel_token.is_cancelled() {
+        bail!("Re-embedding cancelled");
+    }
+    info!(
+        embedded = summary.embedded,
+        failed = summary.failed,
+        "Finished re-embedding collection"
+    );
+    Ok(summary)
+}
+
+/// Embed one document for [`reembed_collection`], stopping between
+/// batches when cancelled.
+async fn embed_for_job(
+    state: &AppState,
+    namespace_id: NamespaceId,
+    doc_id: &str,
+    embedder: &dyn provider::EmbeddingProvider,
+    model_id: &str,
+    cancel_token: &CancellationToken,
+) -> Result<()> {
+    let embed = state
+        .pipeline
+        .embed_document_with(namespace_id, doc_id, embedder, model_id);
+    tokio::select! {
+        result = embed => result,
+        _ = cancel_token.cancelled() => bail!("Re-embedding cancelled"),
+    }
+}
+
+/// Answer `question` from one document's most relevant pages, `ranked`
+/// best first by search. Returns whether it was answered, the answer, and
+/// the pages cited.
+async fn answer_document(
+    provider: &dyn ChatProvider,
+    prompts: &PromptLibrary,
+    name: &str,
+    pages: &[&str],
+    ranked: &[usize],
+    question: &str,
+    cancel_token: &CancellationToken,
+) -> Result<(bool, String, Vec<usize>)> {
+    let budget = provider.context_window() * CHARS_PER_TOKEN * EXCERPT_SHARE_PERCENT / 100;
+    let selected = relevant_pages(pages, ranked, budget);
+    if selected.is_empty() {
+        bail!("The document has no extracted text");
+    }
+
+    let excerpts: Vec<String> = selected
+        .iter()
+        .map(|&page| format!("[Page {}]\n{}", page, pages[page - 1].trim()))
+        .collect();
+    let prompt = prompts.render(
+        "batch-answer",
+        &[
+            ("title", name),
+            ("question", question),
+            ("excerpts", &excerpts.join("\n\n")),
+        ],
+    )?;
+    let messages = [Message {
+        role: MessageRole::User,
+        content: vec![ContentBlock::Text { text: prompt }],
+    }];
+
+    let value = provider
+        .complete_structured(&messages, &answer_schema(), cancel_token.clone())
+        .await?;
+    Ok(parse_answer(&value, &selected))
+}
+
+fn answer_schema() -> StructuredSchema {
+    StructuredSchema {
+        name: "answer".to_string(),
+        description: "Record the answer found in the document".to_string(),
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "found": { "type": "boolean" },
+                "answer": { "type": "string" },
+                "pages": { "type": "array", "items": { "type": "integer" } }
+            },
+            "required": ["found", "answer", "pages"],
+            "additionalProperties": false
+        }),
+    }
+}
+
+/// Read a validated answer, keeping only pages that were actually sent.
+fn parse_answer(value: &serde_json::Value, offered: &[usize]) -> (bool, String, Vec<usize>) {
+    let found = value["found"].as_bool().unwrap_or(false);
+    let answer = value["answer"]
+        .as_str()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    let mut pages: Vec<usize> = value["pages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.as_u64())
+        .map(|p| p as usize)
+        .filter(|p| offered.contains(p))
+        .collect();
+    pages.sort_unstable();
+    pages.dedup();
+    (found && !answer.is_empty(), answer, pages)
+}
+
+/// Page numbers (1-based, in page order) worth sending: the pages search
+/// ranked best for the question, then the rest from the start, as many as
+/// fit in `budget` characters.
+fn relevant_pages(pages: &[&str], ranked: &[usize], budget: usize) -> Vec<usize> {
+    let mut selected = Vec::new();
+    let mut used = 0;
+    for page in ranked.iter().copied().chain(1..=pages.len()) {
+        if page == 0 || page > pages.len() || selected.contains(&page) {
+            continue;
+        }
+        let len = pages[page - 1].trim().len();
+        if len == 0 || (!selected.is_empty() && used + len > budget) {
+            continue;
+        }
+        selected.push(page);
+        used += len;
+        if selected.len() == MAX_PAGES_PER_DOCUMENT {
+            break;
+        }
+    }
+    selected.sort_unstable();
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relevant_pages_prefers_matching_pages() {
+        let pages = [
+            "Parties and definitions.",
+            "",
+            "Either party may terminate this agreement with 30 days notice.",
+            "Payment terms.",
+            "Termination for cause: the agreement may terminate immediately.",
+        ];
+        // Blank pages are never sent.
+        assert_eq!(relevant_pages(&pages, &[5, 3], 10_000), vec![1, 3, 4, 5]);
+
+        // A tight budget keeps the best match only.
+        assert_eq!(relevant_pages(&pages, &[5, 3], 70), vec![5]);
+        assert_eq!(relevant_pages(&pages, &[3, 5], 70), vec![3]);
+
+        // Without search hits, the opening pages; pages that don't exist
+        // are ignored.
+        assert_eq!(relevant_pages(&pages, &[9], 40), vec![1, 4]);
+    }
+
+    #[test]
+    fn test_parse_answer_keeps_offered_pages() {
+        let value = serde_json::json!({
+            "found": true,
+            "answer": " 30 days' notice. ",
+            "pages": [5, 3, 3, 9]
+        });
+        assert_eq!(
+            parse_answer(&value, &[3, 5]),
+            (true, "30 days' notice.".to_string(), vec![3, 5])
+        );
+
+        let empty = serde_json::json!({ "found": true, "answer": "", "pages": [] });
+        assert!(!parse_answer(&empty, &[1]).0);
+    }
+}
@@ -0,0 +1,394 @@
+//! Periodic maintenance, run in the background on configurable intervals.
+//!
+//! The scheduler only needs an [`AppState`], so the desktop app and a
+//! headless process run it the same way: create the state, call
+//! [`Scheduler::start`], and optionally [`Scheduler::subscribe`] to hear
+//! what each run did.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::MaintenanceConfig;
+use crate::saved_searches::{self, SearchAlert};
+use crate::{feeds, mail, search, AppState, CollectionInfo};
+
+/// How long a job waiting for the pipeline to go idle waits before
+/// checking again.
+const BUSY_RETRY: Duration = Duration::from_secs(5 * 60);
+
+/// A periodic maintenance job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceTask {
+    /// Untag blobs nothing refers to so the blob store frees them.
+    BlobGc,
+    /// Reconcile the search index with stored documents.
+    VerifyIndex,
+    /// Re-run saved searches that notify on new matches.
+    SearchAlerts,
+    /// Queue documents with text but no embeddings for the active model.
+    Reembed,
+    /// Import new messages from the tips mailbox.
+    CheckMail,
+    /// Import new articles from followed feeds.
+    CheckFeeds,
+}
+
+impl MaintenanceTask {
+    pub const ALL: [MaintenanceTask; 6] = [
+        MaintenanceTask::BlobGc,
+        MaintenanceTask::VerifyIndex,
+        MaintenanceTask::SearchAlerts,
+        MaintenanceTask::Reembed,
+        MaintenanceTask::CheckMail,
+        MaintenanceTask::CheckFeeds,
+    ];
+
+    /// How often the task runs, or `None` if it's turned off.
+    pub fn interval(self, config: &MaintenanceConfig) -> Option<Duration> {
+        let minutes = match self {
+            MaintenanceTask::BlobGc => config.blob_gc_minutes,
+            MaintenanceTask::VerifyIndex => config.index_verify_minutes,
+            MaintenanceTask::SearchAlerts => config.search_alert_minutes,
+            MaintenanceTask::Reembed => config.reembed_minutes,
+            MaintenanceTask::CheckMail => config.mail_minutes,
+            MaintenanceTask::CheckFeeds => config.feed_minutes,
+        };
+        (minutes > 0).then(|| Duration::from_secs(minutes * 60))
+    }
+
+    /// Whether the task waits for imports to finish. Their results would
+    /// be stale mid-import, and blob GC locks storage while it runs.
+    fn needs_idle_pipeline(self) -> bool {
+        !matches!(
+            self,
+            MaintenanceTask::SearchAlerts
+                | MaintenanceTask::CheckMail
+                | MaintenanceTask::CheckFeeds
+        )
+    }
+}
+
+/// What one maintenance run did.
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceRun {
+    pub task: MaintenanceTask,
+    pub finished_at: String,
+    /// One line for logs and the UI, e.g. "Re-queued 3 documents".
+    pub summary: String,
+    pub error: Option<String>,
+    /// New saved-search matches, from `SearchAlerts` runs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<SearchAlert>,
+}
+
+/// Runs [`MaintenanceTask`]s on their configured intervals.
+pub struct Scheduler {
+    config: watch::Sender<MaintenanceConfig>,
+    triggers: mpsc::UnboundedSender<MaintenanceTask>,
+    /// Taken by the first `start`.
+    trigger_rx: std::sync::Mutex<Option<mpsc::UnboundedReceiver<MaintenanceTask>>>,
+    runs: broadcast::Sender<MaintenanceRun>,
+    cancel: CancellationToken,
+}
+
+impl Scheduler {
+    /// Create a scheduler. Nothing runs until [`Self::start`].
+    pub fn new(config: MaintenanceConfig) -> Self {
+        let (triggers, trigger_rx) = mpsc::unbounded_channel();
+        Self {
+            config: watch::Sender::new(config),
+            triggers,
+            trigger_rx: std::sync::Mutex::new(Some(trigger_rx)),
+            runs: broadcast::channel(16).0,
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Start running tasks. Each first runs one interval from now. Later
+    /// calls do nothing. Must be called from within the Tokio runtime.
+    pub fn start(&self, state: AppState) {
+        let Some(trigger_rx) = self.trigger_rx.lock().unwrap().take() else {
+            return;
+        };
+        tokio::spawn(run(
+            state,
+            self.config.subscribe(),
+            trigger_rx,
+            self.runs.clone(),
+            self.cancel.clone(),
+        ));
+    }
+
+    pub fn config(&self) -> MaintenanceConfig {
+        self.config.borrow().clone()
+    }
+
+    /// Change intervals. Takes effect immediately, counting from each
+    /// task's last run.
+    pub fn set_config(&self, config: MaintenanceConfig) {
+        self.config.send_replace(config);
+    }
+
+    /// Run a task now, whatever its interval, once the current run (if
+    /// any) finishes. Doesn't wait for the pipeline to go idle.
+    pub fn run_now(&self, task: MaintenanceTask) {
+        let _ = self.triggers.send(task);
+    }
+
+    /// Receive every finished run.
+    pub fn subscribe(&self) -> broadcast::Receiver<MaintenanceRun> {
+        self.runs.subscribe()
+    }
+
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+}
+
+/// When each enabled task is next due. Deferred tasks are due when their
+/// deferral ends, the rest one interval after they last ran.
+fn next_due(
+    config: &MaintenanceConfig,
+    last_run: &HashMap<MaintenanceTask, Instant>,
+    deferred: &HashMap<MaintenanceTask, Instant>,
+) -> Vec<(MaintenanceTask, Instant)> {
+    MaintenanceTask::ALL
+        .into_iter()
+        .filter_map(|task| {
+            let interval = task.interval(config)?;
+            let due = deferred
+                .get(&task)
+                .copied()
+                .unwrap_or_else(|| last_run[&task] + interval);
+            Some((task, due))
+        })
+        .collect()
+}
+
+async fn run(
+    state: AppState,
+    mut config: watch::Receiver<MaintenanceConfig>,
+    mut triggers: mpsc::UnboundedReceiver<MaintenanceTask>,
+    runs: broadcast::Sender<MaintenanceRun>,
+    cancel: CancellationToken,
+) {
+    let started = Instant::now();
+    let mut last_run: HashMap<MaintenanceTask, Instant> = MaintenanceTask::ALL
+        .into_iter()
+        .map(|task| (task, started))
+        .collect();
+    let mut deferred: HashMap<MaintenanceTask, Instant> = HashMap::new();
+    info!("Maintenance scheduler started");
+
+    loop {
+        let due = next_due(&config.borrow(), &last_run, &deferred);
+        let wake = due.iter().map(|(_, at)| *at).min();
+
+        let ready: Vec<MaintenanceTask> = tokio::select! {
+            _ = cancel.cancelled() => break,
+            changed = config.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                continue;
+            }
+            Some(task) = triggers.recv() => vec![task],
+            _ = async {
+                match wake {
+                    Some(at) => tokio::time::sleep_until(at).await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                let now = Instant::now();
+                let busy = !state.pipeline.get_all_progress().await.is_empty();
+                let mut ready = Vec::new();
+                for (task, _) in due.into_iter().filter(|(_, at)| *at <= now) {
+                    if busy && task.needs_idle_pipeline() {
+                        deferred.insert(task, now + BUSY_RETRY);
+                    } else {
+                        ready.push(task);
+                    }
+                }
+                ready
+            }
+        };
+
+        for task in ready {
+            let result = run_task(&state, task).await;
+            last_run.insert(task, Instant::now());
+            deferred.remove(&task);
+            let (summary, error, alerts) = match result {
+                Ok((summary, alerts)) => {
+                    info!(task = ?task, "{}", summary);
+                    (summary, None, alerts)
+                }
+                Err(e) => {
+                    warn!(task = ?task, error = %e, "Maintenance task failed");
+                    ("Failed".to_string(), Some(e.to_string()), Vec::new())
+                }
+            };
+            let _ = runs.send(MaintenanceRun {
+                task,
+                finished_at: chrono::Utc::now().to_rfc3339(),
+                summary,
+                error,
+                alerts,
+            });
+        }
+    }
+
+    info!("Maintenance scheduler stopped");
+}
+
+async fn run_task(state: &AppState, task: MaintenanceTask) -> Result<(String, Vec<SearchAlert>)> {
+    match task {
+        MaintenanceTask::BlobGc => {
+            // Exclusive: see `Storage::untag_unreferenced_blobs`.
+            let count = state
+                .storage
+                .write()
+                .await
+                .untag_unreferenced_blobs()
+                .await?;
+            Ok((format!("Released {} unreferenced blobs", count), Vec::new()))
+        }
+        MaintenanceTask::VerifyIndex => Ok((verify_index(state).await?, Vec::new())),
+        MaintenanceTask::SearchAlerts => {
+            let alerts = check_all_alerts(state).await?;
+            let matches: usize = alerts.iter().map(|a| a.matches.len()).sum();
+            Ok((format!("{} new saved-search matches", matches), alerts))
+        }
+        MaintenanceTask::Reembed => {
+            let count = state.pipeline.requeue_pending_embeddings().await;
+            Ok((
+                format!("Re-queued {} documents for embedding", count),
+                Vec::new(),
+            ))
+        }
+        MaintenanceTask::CheckMail => Ok((mail::check_mailbox(state).await?, Vec::new())),
+        MaintenanceTask::CheckFeeds => Ok((feeds::check_feeds(state).await?, Vec::new())),
+    }
+}
+
+/// Drop index chunks of documents that no longer exist, and re-index
+/// documents whose embeddings for the active model are stored but
+/// missing from the index.
+async fn verify_index(state: &AppState) -> Result<String> {
+    let index = state.search.clone();
+    let indexed = tokio::task::spawn_blocking(move || search::indexed_documents(&index))
+        .await
+        .context("Index scan panicked")??;
+    let model_id = state.models.embedding_model_id().await;
+
+    let mut stored = HashSet::new();
+    let mut missing = Vec::new();
+    {
+        let storage = state.storage.read().await;
+        for (namespace_id, _) in storage.list_collections().await? {
+            for metadata in storage.list_documents(namespace_id).await? {
+                stored.insert(metadata.id);
+            }
+            if let Some(model_id) = &model_id {
+                let unindexed: Vec<String> = storage
+                    .find_embedded_documents(namespace_id, model_id)
+                    .await?
+                    .into_iter()
+                    .filter(|doc_id| !indexed.contains_key(doc_id))
+                    .collect();
+                if !unindexed.is_empty() {
+                    missing.push((namespace_id, unindexed));
+                }
+            }
+        }
+    }
+
+    let mut removed = 0;
+    for doc_id in indexed.into_keys().filter(|id| !stored.contains(id)) {
+        state.index_worker.delete_document_chunks(doc_id).await?;
+        removed += 1;
+    }
+    let mut reindexed = 0;
+    for (namespace_id, doc_ids) in missing {
+        reindexed += state
+            .pipeline
+            .reindex_documents(namespace_id, doc_ids)
+            .await;
+    }
+
+    Ok(format!(
+        "Removed {} deleted documents from the index, re-indexed {}",
+        removed, reindexed
+    ))
+}
+
+/// Check every collection's alerting searches. A collection that fails
+/// is logged and skipped.
+async fn check_all_alerts(state: &AppState) -> Result<Vec<SearchAlert>> {
+    let collections = state.storage.read().await.list_collections().await?;
+    let mut alerts = Vec::new();
+    for (namespace_id, metadata) in collections {
+        let collection = CollectionInfo {
+            id: namespace_id.to_string(),
+            name: metadata.name,
+            document_count: 0,
+            total_pages: 0,
+            created_at: None,
+        };
+        match saved_searches::check_alerts(state, collection).await {
+            Ok(found) => alerts.extend(found),
+            Err(e) => warn!(collection = %namespace_id, error = %e, "Saved-search alerts failed"),
+        }
+    }
+    Ok(alerts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_due() {
+        let config = MaintenanceConfig {
+            blob_gc_minutes: 0,
+            index_verify_minutes: 60,
+            search_alert_minutes: 10,
+            reembed_minutes: 30,
+            mail_minutes: 15,
+            feed_minutes: 60,
+        };
+        let start = Instant::now();
+        let last_run: HashMap<_, _> = MaintenanceTask::ALL
+            .into_iter()
+            .map(|task| (task, start))
+            .collect();
+        let mut deferred = HashMap::new();
+        deferred.insert(MaintenanceTask::Reembed, start + Duration::from_secs(5));
+
+        let due: HashMap<_, _> = next_due(&config, &last_run, &deferred)
+            .into_iter()
+            .collect();
+        // Disabled tasks never come due
+        assert!(!due.contains_key(&MaintenanceTask::BlobGc));
+        assert_eq!(
+            due[&MaintenanceTask::VerifyIndex],
+            start + Duration::from_secs(3600)
+        );
+        assert_eq!(
+            due[&MaintenanceTask::SearchAlerts],
+            start + Duration::from_secs(600)
+        );
+        // A deferral replaces the interval
+        assert_eq!(
+            due[&MaintenanceTask::Reembed],
+            start + Duration::from_secs(5)
+        );
+    }
+}
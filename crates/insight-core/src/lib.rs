@@ -7,21 +7,44 @@
 //! - Inference providers (local + remote) via [`provider`] + [`manager::ModelManager`]
 //! - Agent/conversation handling
 //! - Event-driven pipeline for document import
+//! - One stream of progress, status and agent events ([`events`])
 
 pub mod agent;
+pub mod chat;
+mod collection_files;
 pub mod config;
 pub mod conversations;
+pub mod events;
+pub mod feeds;
+pub mod hooks;
+pub mod jobs;
+pub mod mail;
 pub mod manager;
+pub mod memory;
 pub mod models;
+pub mod net;
 pub mod pdf;
+pub mod personal;
 pub mod pipeline;
+pub mod presence;
+pub mod projection;
+pub mod prompts;
 pub mod provider;
+pub mod rate_limit;
+pub mod redact;
+pub mod remote;
+pub mod saved_searches;
 pub mod search;
+pub mod secrets;
+pub mod server;
+pub mod spend;
 pub mod storage;
+pub mod text;
 
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{oneshot, RwLock};
 use tokio_util::sync::CancellationToken;
 
 use milli::update::IndexerConfig;
@@ -96,23 +119,46 @@ pub struct ModelDownloadProgress {
     pub progress: models::DownloadProgress,
 }
 
-pub use agent::{AgentContext, AgentEvent, Conversation};
-pub use config::{Config, LifecycleConfig, Settings};
-pub use manager::{ChatLease, EmbeddingLease, ModelManager, OcrLease};
-pub use pipeline::{Pipeline, PipelineProgress, StageProgress};
+pub use agent::{AgentContext, AgentEvent, Conversation, ToolApproval};
+pub use chat::{Chat, ChatMessage};
+pub use config::{
+    AgentLimits, ComputeBackend, Config, DeviceConfig, DeviceSettings, KvCacheType,
+    LifecycleConfig, LocalRuntimeConfig, MaintenanceConfig, PeerAccess, PipelineConfig,
+    PromptPreset, ProxyConfig, ServerConfig, Settings, SyncPolicy, WatchFolder,
+};
+pub use events::{CoreEvent, EventBus, EventSubscription};
+pub use manager::{
+    ChatLease, EmbeddingHealth, EmbeddingLease, EmbeddingReadiness, ModelManager, OcrLease,
+};
+pub use personal::{PersonalSync, PersonalSyncStatus};
+pub use pipeline::{
+    CollectionTransfer, DirectoryFilter, DuplicateStatus, EmbeddingCacheStats, EmbeddingProgress,
+    FilePreview, FileProgress, FolderWatcher, ImportPriority, ImportQueue, ImportReport,
+    ImportReports, ImportSummary, PeerSyncHealth, Pipeline, PipelineProgress, QueuedImport,
+    StageProgress, SyncStatus, SyncedDocument, ThroughputStats,
+};
+pub use presence::{PeerPresence, Presence};
 pub use provider::{
     get_provider_families, get_tool_definitions, AnthropicChatProvider, ChatProvider,
-    CompletedToolCall, CompletionResult, EmbeddingProvider, LocalChatProvider,
-    LocalEmbeddingProvider, LocalOcrProvider, OcrProvider, OpenAIChatProvider, ProviderConfig,
-    ProviderEvent, ProviderFamily, RemoteModelInfo, ToolDefinition,
+    ChunkingConfig, CompletedToolCall, CompletionCache, CompletionCacheStats, CompletionResult,
+    EmbeddingProvider, GroqChatProvider, LocalChatProvider, LocalEmbeddingProvider,
+    LocalOcrProvider, MistralChatProvider, OcrProvider, OpenAIChatProvider,
+    OpenAICompatibleChatProvider, OpenRouterChatProvider, ProviderCheck, ProviderConfig,
+    ProviderEvent, ProviderFamily, RemoteModelInfo, SamplingParams, StopReason, ToolDefinition,
 };
 pub use search::{spawn_index_worker, IndexWorkerHandle};
-pub use storage::{EmbeddingChunk, EmbeddingData, Storage};
+pub use secrets::SecretStore;
+pub use storage::{
+    CollectionMember, DocumentOffer, EmbeddingChunk, EmbeddingData, NearDuplicate,
+    NetworkDiagnostics, Storage, VectorEncoding,
+};
 
 /// Application state shared across Tauri commands
 #[derive(Clone)]
 pub struct AppState {
     pub config: Config,
+    /// API keys, in the keychain or the fallback secrets file.
+    pub secrets: SecretStore,
     /// Downloads and caches HuggingFace model files on disk.
     pub model_downloader: Arc<models::ModelDownloader>,
     /// Central manager for in-memory inference providers (chat, embed, OCR).
@@ -130,24 +176,64 @@ pub struct AppState {
     pub active_generations: Arc<RwLock<HashMap<String, CancellationToken>>>,
     /// Cancellation tokens for active predictions (tab completion)
     pub active_predictions: Arc<RwLock<HashMap<String, CancellationToken>>>,
+    /// Cancellation tokens for batch questions, by collection ID
+    pub active_batches: Arc<RwLock<HashMap<String, CancellationToken>>>,
+    /// Cancellation tokens for re-embed jobs, by collection ID
+    pub active_reembeds: Arc<RwLock<HashMap<String, CancellationToken>>>,
+    /// Tool calls waiting for the user to approve or deny, keyed by tool call ID
+    pub pending_confirmations: Arc<RwLock<HashMap<String, oneshot::Sender<ToolApproval>>>>,
+    /// Unread pages of long tool results, for `fetch_more`
+    pub result_pages: Arc<agent::paging::ResultPages>,
     /// Event-driven document processing pipeline
     pub pipeline: Arc<Pipeline>,
+    /// Replies to repeated helper calls (summaries, suggestions)
+    pub completion_cache: Arc<CompletionCache>,
+    /// Watch folders being imported from, replaced when they change
+    pub folder_watcher: Arc<std::sync::Mutex<Option<FolderWatcher>>>,
+    /// Periodic maintenance jobs, started with the rest of the app
+    pub maintenance: Arc<jobs::Scheduler>,
+    /// Progress, status and agent events from every subsystem
+    pub events: EventBus,
+    /// Who is online in each collection, and this node's announcements
+    pub presence: Presence,
+    /// Messages between the members of each collection
+    pub chat: Chat,
+    /// Settings, saved searches and conversations shared with this user's
+    /// other devices
+    pub personal: PersonalSync,
 }
 
 impl AppState {
     /// Create AppState with initialized storage and search.
     /// Called from setup() where Tauri's async runtime is available.
     ///
-    /// Returns the state and a receiver for pipeline progress events
-    /// that should be forwarded to the frontend.
-    pub async fn new(
-        config: Config,
-    ) -> anyhow::Result<(Self, tokio::sync::mpsc::Receiver<PipelineProgress>)> {
+    /// Progress and status from the pipeline, models and maintenance jobs
+    /// are forwarded to [`Self::events`] from here on.
+    pub async fn new(config: Config) -> anyhow::Result<Self> {
+        let settings = Settings::load(&config.settings_file);
+        rate_limit::set_overrides(settings.rate_limits);
+        // Settings saved through `set_proxy` are valid; a hand-edited one
+        // may not be, and then nothing remote connects until it's fixed.
+        if let Err(e) = net::load_proxy(settings.proxy) {
+            tracing::error!(error = %e, "Proxy settings can't be used");
+        }
         let model_downloader = Arc::new(models::ModelDownloader::new().await?);
-        let models = Arc::new(ModelManager::new());
+        let models = Arc::new(ModelManager::new().with_metering(spend::Metering {
+            ledger_file: config.spend_file(),
+            settings_file: config.settings_file.clone(),
+        }));
 
         // Fast async init - just opens files
-        let storage = Storage::open(&config.iroh_dir).await?;
+        let sharing = Settings::load(&config.settings_file);
+        let storage = Storage::open(&config.iroh_dir, &sharing.peer_access).await?;
+        let presence = Presence::new(
+            storage.gossip().clone(),
+            storage.node_id(),
+            sharing.display_name,
+        );
+        let chat = Chat::new(storage.gossip().clone(), storage.node_id());
+        let offers = storage.subscribe_offers();
+        let uploads = storage.subscribe_uploads();
 
         // Sync init - create index and indexer config
         let index = search::open_index(&config.search_dir)?;
@@ -159,25 +245,71 @@ impl AppState {
         // Spawn index worker - handles all milli write operations in a dedicated thread
         let index_worker = spawn_index_worker(search.clone(), indexer_config);
 
+        let secrets = SecretStore::new(config.secrets_file());
+        if let Err(e) = secrets::migrate_settings(&config.settings_file, &secrets) {
+            tracing::warn!("Failed to move API keys out of settings: {}", e);
+        }
+
         // Create event-driven pipeline for document processing
-        let (pipeline, progress_rx) =
-            Pipeline::new(storage.clone(), models.clone(), index_worker.clone());
-
-        Ok((
-            Self {
-                config,
-                model_downloader,
-                models,
-                storage,
-                search,
-                index_worker,
-                conversations: Arc::new(RwLock::new(HashMap::new())),
-                active_generations: Arc::new(RwLock::new(HashMap::new())),
-                active_predictions: Arc::new(RwLock::new(HashMap::new())),
-                pipeline: Arc::new(pipeline),
-            },
-            progress_rx,
-        ))
+        let settings = Settings::load(&config.settings_file);
+        let (pipeline, progress_rx) = Pipeline::new(
+            storage.clone(),
+            models.clone(),
+            index_worker.clone(),
+            &settings.pipeline,
+            settings.chunking,
+            ImportQueue::open(config.import_queue_file())?,
+            ImportReports::new(config.import_reports_dir()),
+        );
+        pipeline.count_uploads(uploads);
+        let maintenance = Arc::new(jobs::Scheduler::new(settings.maintenance.clone()));
+
+        let events = EventBus::new();
+        events.forward_mpsc(progress_rx, CoreEvent::PipelineProgress);
+        events.forward(
+            pipeline.subscribe_embedding_progress(),
+            CoreEvent::EmbeddingProgress,
+        );
+        events.forward(pipeline.subscribe_file_progress(), CoreEvent::FileProgress);
+        events.forward(pipeline.subscribe_throughput(), CoreEvent::Throughput);
+        events.forward(
+            pipeline.subscribe_synced_documents(),
+            CoreEvent::SyncedDocument,
+        );
+        events.forward(
+            pipeline.subscribe_transfer_stats(),
+            CoreEvent::TransferStats,
+        );
+        events.forward(maintenance.subscribe(), CoreEvent::Maintenance);
+        events.forward(chat.subscribe(), CoreEvent::ChatMessage);
+        events.forward(offers, CoreEvent::DocumentOffered);
+        events.forward(models.subscribe_status(), CoreEvent::ModelStatus);
+        let personal = PersonalSync::new(storage.clone(), config.clone());
+
+        Ok(Self {
+            config,
+            secrets,
+            model_downloader,
+            models,
+            storage,
+            search,
+            index_worker,
+            conversations: Arc::new(RwLock::new(HashMap::new())),
+            active_generations: Arc::new(RwLock::new(HashMap::new())),
+            active_predictions: Arc::new(RwLock::new(HashMap::new())),
+            active_batches: Arc::new(RwLock::new(HashMap::new())),
+            active_reembeds: Arc::new(RwLock::new(HashMap::new())),
+            pending_confirmations: Arc::new(RwLock::new(HashMap::new())),
+            result_pages: Arc::new(agent::paging::ResultPages::new()),
+            pipeline: Arc::new(pipeline),
+            completion_cache: Arc::new(CompletionCache::default()),
+            folder_watcher: Arc::new(std::sync::Mutex::new(None)),
+            maintenance,
+            events,
+            presence,
+            chat,
+            personal,
+        })
     }
 
     /// Restore provider configurations from settings. Called once at startup.
@@ -186,11 +318,9 @@ impl AppState {
     /// inference request. Auto-downloads the default embedding model if
     /// settings don't name one. The only blocking work done here is the
     /// potential download; everything else is cheap.
-    pub async fn restore_configs_from_settings(
-        &self,
-        status_tx: tokio::sync::mpsc::Sender<ModelStatus>,
-        progress_tx: tokio::sync::mpsc::Sender<ModelDownloadProgress>,
-    ) {
+    pub async fn restore_configs_from_settings(&self) {
+        let status_tx = self.events.channel(CoreEvent::ModelStatus);
+        let progress_tx = self.events.channel(CoreEvent::ModelDownload);
         let mut settings = Settings::load(&self.config.settings_file);
 
         // Prime the manager with the current lifecycle settings so provider
@@ -199,20 +329,70 @@ impl AppState {
             .set_lifecycle_config(settings.lifecycle.clone())
             .await;
 
-        // Start watching existing collections for indexing events.
+        // Start watching existing collections for indexing events, and
+        // run hooks for what arrives. Collections download what their
+        // sync policy allows.
+        self.pipeline.set_hooks(settings.hooks.clone());
+        self.pipeline
+            .set_sync_policies(
+                settings
+                    .sync_policies
+                    .iter()
+                    .filter_map(|(id, policy)| Some((id.parse().ok()?, *policy)))
+                    .collect(),
+            )
+            .await;
         self.watch_existing_collections().await;
+        self.register_author(&self.collection_ids().await).await;
+
+        // Collections paused before the restart stay detached from peers.
+        {
+            let storage = self.storage.read().await;
+            for namespace_id in settings.paused_sync.iter().filter_map(|id| id.parse().ok()) {
+                if let Err(e) = storage.set_sync_enabled(namespace_id, false).await {
+                    tracing::warn!(namespace = %namespace_id, error = %e, "Failed to pause sync");
+                }
+            }
+        }
+        self.join_gossip(&self.collection_ids().await).await;
+        self.personal.resume();
 
         // Drain any orphan OCR tasks (interrupted process, or imports
         // that landed before an OCR model was configured). Idempotent —
         // tasks with a matching text entry are skipped.
         self.pipeline.requeue_pending_ocr().await;
 
+        // Finish imports the app closed in the middle of. Runs in the
+        // background: a large queue shouldn't hold up provider setup.
+        // Queued remote objects need their sources first.
+        self.connect_remote_sources(&settings.remote_sources).await;
+        let pipeline = self.pipeline.clone();
+        tokio::spawn(async move { pipeline.resume_imports().await });
+
+        self.watch_folders(settings.watch_folders.clone());
+        self.maintenance.start(self.clone());
+
         // Install chat provider (no load) if configured.
-        if let Some(ref provider_config) = settings.provider {
-            self.install_chat_provider_from_config(provider_config, &status_tx)
+        if let Some(mut provider_config) = settings.provider.clone() {
+            let provider_type = provider_config.provider_type();
+            if let Some(api_key) = provider_config.api_key_mut() {
+                *api_key = settings
+                    .api_key(&self.secrets, provider_type)
+                    .unwrap_or_default();
+            }
+            self.install_chat_provider_from_config(&provider_config, &settings, &status_tx)
                 .await;
         }
 
+        if let Some(ref model_id) = settings.prediction_model_id {
+            self.install_prediction_provider(
+                model_id,
+                &settings.devices.chat,
+                &settings.local_runtime,
+            )
+            .await;
+        }
+
         // Auto-configure default embedding model if not set.
         let model_id = match settings.embedding_model_id.clone() {
             Some(id) => id,
@@ -256,7 +436,6 @@ impl AppState {
                 return;
             }
         }
-
         // Configure milli's embedder entry up front so vector search paths
         // don't fail while the actual embedding model is still unloaded.
         if let Err(e) = self
@@ -267,12 +446,17 @@ impl AppState {
             tracing::warn!("Failed to configure embedder in index: {}", e);
         }
 
-        let provider = LocalEmbeddingProvider::new(&model_id, &model.hf_repo_id, model.dimensions);
-        if let Err(e) = self
-            .models
-            .set_embedding(Arc::new(provider), model_id.clone())
-            .await
-        {
+        let provider = provider::local::embedding_provider(
+            &model_id,
+            &model,
+            &self.model_downloader,
+            &settings.devices.embedding,
+        );
+        let installed = match provider {
+            Ok(provider) => self.models.set_embedding(provider, model_id.clone()).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = installed {
             tracing::error!("Failed to install embedding provider: {}", e);
             let _ = status_tx
                 .send(ModelStatus::Failed {
@@ -291,6 +475,89 @@ impl AppState {
                 model_id: model_id.clone(),
             })
             .await;
+
+        // Resume embedding for documents whose embed job was lost when the
+        // app last closed. Needs the provider installed to know the model.
+        self.pipeline.requeue_pending_embeddings().await;
+    }
+
+    /// Watch `folders` for new PDFs to import, replacing any folders
+    /// watched before. Must be called from within the Tokio runtime.
+    pub fn watch_folders(&self, folders: Vec<WatchFolder>) {
+        let mut current = self.folder_watcher.lock().unwrap();
+        // Stop the old watcher first so a folder isn't imported twice.
+        *current = None;
+        if folders.is_empty() {
+            return;
+        }
+        match FolderWatcher::spawn(folders, self.pipeline.clone(), self.storage.clone()) {
+            Ok(watcher) => *current = Some(watcher),
+            Err(e) => tracing::warn!("Failed to watch folders: {}", e),
+        }
+    }
+
+    /// Fetch queued remote imports from `sources`, replacing the sources
+    /// used before.
+    pub async fn connect_remote_sources(&self, sources: &[remote::RemoteSource]) {
+        self.pipeline
+            .set_remotes(remote::Remote::connect_all(sources, &self.secrets))
+            .await;
+    }
+
+    /// Install the local prediction model without loading weights. Returns
+    /// false if the model is unknown or not downloaded.
+    pub async fn install_prediction_provider(
+        &self,
+        model_id: &str,
+        device: &DeviceConfig,
+        runtime: &LocalRuntimeConfig,
+    ) -> bool {
+        let Some(model) = models::get_language_model(model_id) else {
+            tracing::warn!("Unknown prediction model: {}", model_id);
+            return false;
+        };
+        let Some(path) = self.model_downloader.get_path(&model) else {
+            tracing::info!("Prediction model '{}' not downloaded, skipping", model_id);
+            return false;
+        };
+        let provider = LocalChatProvider::new(&path, &model)
+            .with_device(device.clone())
+            .with_runtime(runtime.clone());
+        self.models.set_prediction(Arc::new(provider)).await;
+        tracing::info!("Prediction model '{}' installed (lazy)", model_id);
+        true
+    }
+
+    /// A local chat provider for `model`, downloaded to `path`, placed and
+    /// sized per `settings`. Decodes speculatively when the model has a
+    /// draft model paired with it that is downloaded too.
+    pub fn local_chat_provider(
+        &self,
+        model: &models::LanguageModelInfo,
+        path: &Path,
+        settings: &Settings,
+    ) -> LocalChatProvider {
+        let provider = LocalChatProvider::new(path, model)
+            .with_device(settings.devices.chat.clone())
+            .with_runtime(settings.local_runtime.clone());
+        let Some(draft_id) = settings.draft_models.get(&model.id) else {
+            return provider;
+        };
+        let draft = models::get_language_model(draft_id)
+            .and_then(|draft| Some((self.model_downloader.get_path(&draft)?, draft)));
+        match draft {
+            Some((draft_path, draft)) => {
+                tracing::info!("Drafting for '{}' with '{}'", model.id, draft.id);
+                provider.with_draft(draft_path, &draft)
+            }
+            None => {
+                tracing::warn!(
+                    "Draft model '{}' unavailable, decoding without it",
+                    draft_id
+                );
+                provider
+            }
+        }
     }
 
     /// Install a chat provider from saved configuration without loading
@@ -298,10 +565,11 @@ impl AppState {
     async fn install_chat_provider_from_config(
         &self,
         config: &ProviderConfig,
+        settings: &Settings,
         status_tx: &tokio::sync::mpsc::Sender<ModelStatus>,
     ) {
         match config {
-            ProviderConfig::Local { model_id } => {
+            ProviderConfig::Local { model_id, .. } => {
                 let Some(model) = models::get_language_model(model_id) else {
                     tracing::warn!("Unknown local model: {}", model_id);
                     return;
@@ -314,7 +582,7 @@ impl AppState {
                     return;
                 };
 
-                let provider = LocalChatProvider::new(&path, &model);
+                let provider = self.local_chat_provider(&model, &path, settings);
                 if let Err(e) = self
                     .models
                     .set_chat(Arc::new(provider), config.clone())
@@ -338,7 +606,7 @@ impl AppState {
                     })
                     .await;
             }
-            ProviderConfig::OpenAI { api_key, model } => {
+            ProviderConfig::OpenAI { api_key, model, .. } => {
                 let provider = OpenAIChatProvider::new(api_key, model);
                 if let Err(e) = self
                     .models
@@ -350,7 +618,7 @@ impl AppState {
                 }
                 tracing::info!("Loaded OpenAI provider: {}", model);
             }
-            ProviderConfig::Anthropic { api_key, model } => {
+            ProviderConfig::Anthropic { api_key, model, .. } => {
                 let provider = AnthropicChatProvider::new(api_key, model);
                 if let Err(e) = self
                     .models
@@ -362,6 +630,80 @@ impl AppState {
                 }
                 tracing::info!("Loaded Anthropic provider: {}", model);
             }
+            ProviderConfig::OpenRouter {
+                api_key,
+                model,
+                context_window,
+                ..
+            } => {
+                let provider = OpenRouterChatProvider::new(api_key, model, *context_window);
+                if let Err(e) = self
+                    .models
+                    .set_chat(Arc::new(provider), config.clone())
+                    .await
+                {
+                    tracing::error!("Failed to install OpenRouter provider: {}", e);
+                    return;
+                }
+                tracing::info!("Loaded OpenRouter provider: {}", model);
+            }
+            ProviderConfig::Mistral {
+                api_key,
+                model,
+                context_window,
+                ..
+            } => {
+                let provider = MistralChatProvider::new(api_key, model, *context_window);
+                if let Err(e) = self
+                    .models
+                    .set_chat(Arc::new(provider), config.clone())
+                    .await
+                {
+                    tracing::error!("Failed to install Mistral provider: {}", e);
+                    return;
+                }
+                tracing::info!("Loaded Mistral provider: {}", model);
+            }
+            ProviderConfig::Groq {
+                api_key,
+                model,
+                context_window,
+                ..
+            } => {
+                let provider = GroqChatProvider::new(api_key, model, *context_window);
+                if let Err(e) = self
+                    .models
+                    .set_chat(Arc::new(provider), config.clone())
+                    .await
+                {
+                    tracing::error!("Failed to install Groq provider: {}", e);
+                    return;
+                }
+                tracing::info!("Loaded Groq provider: {}", model);
+            }
+            ProviderConfig::OpenAICompatible {
+                base_url,
+                api_key,
+                model,
+                context_window,
+                ..
+            } => {
+                let provider =
+                    OpenAICompatibleChatProvider::new(base_url, api_key, model, *context_window);
+                if let Err(e) = self
+                    .models
+                    .set_chat(Arc::new(provider), config.clone())
+                    .await
+                {
+                    tracing::error!("Failed to install OpenAI-compatible provider: {}", e);
+                    return;
+                }
+                tracing::info!(
+                    "Loaded OpenAI-compatible provider: {} at {}",
+                    model,
+                    base_url
+                );
+            }
         }
     }
 
@@ -391,13 +733,91 @@ impl AppState {
         );
     }
 
-    /// Start watching a namespace for pipeline events.
+    /// IDs of every collection.
+    pub async fn collection_ids(&self) -> Vec<iroh_docs::NamespaceId> {
+        match self.storage.read().await.list_collections().await {
+            Ok(collections) => collections.into_iter().map(|(id, _)| id).collect(),
+            Err(e) => {
+                tracing::warn!("Failed to list collections: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Record this node, with the display name from settings, in each
+    /// collection's author registry so members can tell who's who.
+    pub async fn register_author(&self, namespace_ids: &[iroh_docs::NamespaceId]) {
+        let name = Settings::load(&self.config.settings_file).display_name;
+        let storage = self.storage.read().await;
+        for namespace_id in namespace_ids {
+            if let Err(e) = storage
+                .register_author(*namespace_id, name.as_deref())
+                .await
+            {
+                // Expected for collections shared with us read-only.
+                tracing::debug!(namespace = %namespace_id, error = %e, "Not registered as author");
+            }
+        }
+    }
+
+    /// Join the presence and chat topics of each collection not paused,
+    /// through the peers it has been synced with.
+    pub async fn join_gossip(&self, namespace_ids: &[iroh_docs::NamespaceId]) {
+        let paused = Settings::load(&self.config.settings_file).paused_sync;
+        let storage = self.storage.read().await;
+        for namespace_id in namespace_ids {
+            if paused.contains(&namespace_id.to_string()) {
+                continue;
+            }
+            let peers = storage.sync_peers(*namespace_id).await.unwrap_or_default();
+            if let Err(e) = self.presence.join(*namespace_id, peers.clone()).await {
+                tracing::warn!(namespace = %namespace_id, error = %e, "Failed to join presence");
+            }
+            if let Err(e) = self.chat.join(*namespace_id, peers).await {
+                tracing::warn!(namespace = %namespace_id, error = %e, "Failed to join chat");
+            }
+        }
+    }
+
+    /// Leave a collection's presence and chat topics.
+    pub fn leave_gossip(&self, namespace_id: &iroh_docs::NamespaceId) {
+        self.presence.leave(namespace_id);
+        self.chat.leave(namespace_id);
+    }
+
+    /// Send `text` to the members of a collection online, under the
+    /// display name from settings, and store it in the collection's
+    /// history. A read-only collection's history can't be written, so
+    /// the message only reaches those online.
+    pub async fn send_chat_message(
+        &self,
+        namespace_id: iroh_docs::NamespaceId,
+        text: &str,
+    ) -> anyhow::Result<ChatMessage> {
+        let name = Settings::load(&self.config.settings_file).display_name;
+        let message = self.chat.send(namespace_id, name, text).await?;
+        if let Err(e) = self
+            .storage
+            .read()
+            .await
+            .store_chat_message(namespace_id, &message)
+            .await
+        {
+            tracing::debug!(namespace = %namespace_id, error = %e, "Chat message not stored");
+        }
+        Ok(message)
+    }
+
+    /// Start watching a namespace for pipeline events, and join its
+    /// presence and chat topics.
     pub async fn watch_namespace(&self, namespace_id: iroh_docs::NamespaceId) {
         self.pipeline.watch(namespace_id).await;
+        self.join_gossip(&[namespace_id]).await;
     }
 
     /// Stop watching a namespace.
     pub async fn unwatch_namespace(&self, namespace_id: &iroh_docs::NamespaceId) {
         self.pipeline.unwatch(namespace_id).await;
+        self.leave_gossip(namespace_id);
     }
 }
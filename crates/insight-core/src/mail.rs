@@ -0,0 +1,457 @@
+//! Pulling tips from a mailbox over IMAP.
+//!
+//! An optional [`MailboxConfig`] in settings names an IMAP folder, a filter
+//! on sender and subject, and the collection messages go to. The
+//! maintenance scheduler calls [`check_mailbox`] on an interval: messages
+//! that arrived since the last check and match the filter are fetched
+//! (without marking them read), each stored as a Markdown document with
+//! its headers and text, and each PDF or text attachment stored as a
+//! document of its own. The last UID stored is kept in a small state file
+//! so a message is only imported once, even across restarts.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use futures::TryStreamExt;
+use iroh_docs::NamespaceId;
+use mail_parser::{MessageParser, MimeHeaders};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Settings;
+use crate::net::file_stem;
+use crate::secrets::{SecretRef, SecretStore};
+use crate::storage::MARKDOWN_FILE_TYPE;
+use crate::AppState;
+
+/// Tag on every document imported from the mailbox.
+pub const EMAIL_TAG: &str = "email";
+
+/// Most messages imported per check; the rest wait for the next one.
+const MAX_PER_CHECK: usize = 50;
+
+/// The tips mailbox, as kept in settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MailboxConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    pub host: String,
+    /// IMAP over TLS; 993 on nearly every server.
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub user: String,
+    /// Where the password is kept.
+    #[serde(default)]
+    pub password: Option<SecretRef>,
+    #[serde(default = "default_folder")]
+    pub folder: String,
+    /// Only messages whose sender contains this.
+    #[serde(default)]
+    pub from_filter: Option<String>,
+    /// Only messages whose subject contains this.
+    #[serde(default)]
+    pub subject_filter: Option<String>,
+    /// Collection messages are imported into.
+    pub collection_id: String,
+}
+
+fn default_port() -> u16 {
+    993
+}
+
+fn default_folder() -> String {
+    "INBOX".to_string()
+}
+
+impl MailboxConfig {
+    /// The stored password, if any.
+    pub fn password(&self, secrets: &SecretStore) -> Option<String> {
+        let secret = self.password.as_ref()?;
+        secrets.get(secret).unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Failed to read mailbox password");
+            None
+        })
+    }
+
+    /// Store `password`, replacing any earlier one. An empty password
+    /// removes it.
+    pub fn set_password(&mut self, secrets: &SecretStore, password: &str) -> Result<()> {
+        if password.is_empty() {
+            if let Some(secret) = self.password.take() {
+                secrets.delete(&secret)?;
+            }
+            return Ok(());
+        }
+        self.password = Some(secrets.set("mailbox_password", password)?);
+        Ok(())
+    }
+
+    /// Key of this folder in the state file.
+    fn state_key(&self) -> String {
+        format!("{}@{}/{}", self.user, self.host, self.folder)
+    }
+
+    /// IMAP SEARCH criteria for matching messages after `last_uid`.
+    fn search_query(&self, last_uid: u32) -> String {
+        let mut query = format!("UID {}:*", last_uid + 1);
+        let mut utf8 = false;
+        for (key, value) in [
+            ("FROM", &self.from_filter),
+            ("SUBJECT", &self.subject_filter),
+        ] {
+            let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) else {
+                continue;
+            };
+            utf8 |= !value.is_ascii();
+            query.push_str(&format!(" {} {}", key, quote(value)));
+        }
+        if utf8 {
+            query.insert_str(0, "CHARSET UTF-8 ");
+        }
+        query
+    }
+}
+
+/// How far a folder has been imported. A new UIDVALIDITY means the server
+/// renumbered the folder, so it is read again from the start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+struct FolderState {
+    uid_validity: u32,
+    last_uid: u32,
+}
+
+/// One message, ready to store.
+#[derive(Debug, PartialEq)]
+struct Email {
+    subject: String,
+    markdown: String,
+    attachments: Vec<Attachment>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Attachment {
+    name: String,
+    file_type: &'static str,
+    bytes: Vec<u8>,
+}
+
+/// Import new matching messages from the configured mailbox. Returns a
+/// one-line summary.
+pub async fn check_mailbox(state: &AppState) -> Result<String> {
+    let settings = Settings::load(&state.config.settings_file);
+    let Some(config) = settings.mailbox.filter(|m| m.enabled) else {
+        return Ok("No mailbox to check".to_string());
+    };
+    let namespace_id: NamespaceId = config
+        .collection_id
+        .parse()
+        .context("The mailbox's collection no longer exists")?;
+    let Some(password) = config.password(&state.secrets) else {
+        bail!("No password stored for {}", config.state_key());
+    };
+
+    let state_file = state.config.mail_state_file();
+    let mut states = read_states(&state_file)?;
+    let key = config.state_key();
+    let saved = states.get(&key).copied().unwrap_or_default();
+
+    let (uid_validity, messages) = fetch_new(&config, &password, saved).await?;
+    let mut folder = FolderState {
+        uid_validity,
+        last_uid: if uid_validity == saved.uid_validity {
+            saved.last_uid
+        } else {
+            0
+        },
+    };
+
+    let (mut imported, mut attachments) = (0, 0);
+    for (uid, raw) in messages {
+        let source = format!("imap://{}/{};UID={}", config.host, config.folder, uid);
+        match parse_email(&raw) {
+            Some(email) => {
+                attachments += store_email(state, namespace_id, &source, email).await?;
+                imported += 1;
+            }
+            None => tracing::warn!(uid, "Skipping unreadable message"),
+        }
+        // Saved per message so a failure part-way doesn't import the
+        // earlier ones twice.
+        folder.last_uid = uid;
+        states.insert(key.clone(), folder);
+        write_states(&state_file, &states)?;
+    }
+
+    Ok(format!(
+        "Imported {} messages and {} attachments",
+        imported, attachments
+    ))
+}
+
+/// Store a message and its attachments. Returns the number of
+/// attachments stored.
+async fn store_email(
+    state: &AppState,
+    namespace_id: NamespaceId,
+    source: &str,
+    email: Email,
+) -> Result<usize> {
+    let storage = state.storage.read().await;
+    let tags = vec![EMAIL_TAG.to_string()];
+    storage
+        .store_downloaded_source(
+            namespace_id,
+            source,
+            format!("{}.md", file_stem(&email.subject)),
+            email.markdown.as_bytes(),
+            MARKDOWN_FILE_TYPE,
+            &tags,
+        )
+        .await?;
+    let count = email.attachments.len();
+    for attachment in email.attachments {
+        storage
+            .store_downloaded_source(
+                namespace_id,
+                source,
+                attachment.name,
+                &attachment.bytes,
+                attachment.file_type,
+                &tags,
+            )
+            .await?;
+    }
+    Ok(count)
+}
+
+/// Log in and fetch the raw messages matching `config` after `saved`, up
+/// to [`MAX_PER_CHECK`], oldest first. Returns the folder's UIDVALIDITY
+/// with them. Messages are fetched with `BODY.PEEK`, so they stay unread.
+async fn fetch_new(
+    config: &MailboxConfig,
+    password: &str,
+    saved: FolderState,
+) -> Result<(u32, Vec<(u32, Vec<u8>)>)> {
+    let tcp = tokio::net::TcpStream::connect((config.host.as_str(), config.port))
+        .await
+        .with_context(|| format!("Failed to connect to {}", config.host))?;
+    let tls = tokio_native_tls::TlsConnector::from(
+        tokio_native_tls::native_tls::TlsConnector::new().context("TLS unavailable")?,
+    );
+    let stream = tls
+        .connect(&config.host, tcp)
+        .await
+        .with_context(|| format!("TLS handshake with {} failed", config.host))?;
+
+    let client = async_imap::Client::new(stream);
+    let mut session = client
+        .login(&config.user, password)
+        .await
+        .map_err(|(e, _)| e)
+        .context("IMAP login failed")?;
+
+    let mailbox = session
+        .select(&config.folder)
+        .await
+        .with_context(|| format!("No folder {}", config.folder))?;
+    let uid_validity = mailbox.uid_validity.unwrap_or_default();
+    let last_uid = if uid_validity == saved.uid_validity {
+        saved.last_uid
+    } else {
+        0
+    };
+
+    // `n:*` always matches the newest message, even if it's below n.
+    let mut uids: Vec<u32> = session
+        .uid_search(config.search_query(last_uid))
+        .await?
+        .into_iter()
+        .filter(|uid| *uid > last_uid)
+        .collect();
+    uids.sort_unstable();
+    uids.truncate(MAX_PER_CHECK);
+
+    let mut messages = Vec::with_capacity(uids.len());
+    if !uids.is_empty() {
+        let set = uids
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let fetches: Vec<_> = session
+            .uid_fetch(set, "(UID BODY.PEEK[])")
+            .await?
+            .try_collect()
+            .await?;
+        for fetch in &fetches {
+            if let (Some(uid), Some(body)) = (fetch.uid, fetch.body()) {
+                messages.push((uid, body.to_vec()));
+            }
+        }
+        messages.sort_by_key(|(uid, _)| *uid);
+    }
+
+    if let Err(e) = session.logout().await {
+        tracing::debug!(error = %e, "IMAP logout failed");
+    }
+    Ok((uid_validity, messages))
+}
+
+/// The document a raw message becomes: its headers and text as Markdown,
+/// plus the attachments that can be imported.
+fn parse_email(raw: &[u8]) -> Option<Email> {
+    let message = MessageParser::default().parse(raw)?;
+    let subject = message
+        .subject()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or("(no subject)")
+        .to_string();
+    let from =
+        message
+            .from()
+            .and_then(|a| a.first())
+            .map(|addr| match (addr.name(), addr.address()) {
+                (Some(name), Some(address)) => format!("{} <{}>", name, address),
+                (Some(name), None) => name.to_string(),
+                (None, Some(address)) => address.to_string(),
+                (None, None) => String::new(),
+            });
+
+    let mut attachments = Vec::new();
+    let mut skipped = Vec::new();
+    for part in message.attachments() {
+        let name = part.attachment_name().unwrap_or("attachment").to_string();
+        let content_type = part
+            .content_type()
+            .map(|ct| format!("{}/{}", ct.ctype(), ct.subtype().unwrap_or_default()));
+        match crate::net::sniff(part.contents(), content_type.as_deref(), &name) {
+            Some(file_type) => attachments.push(Attachment {
+                name,
+                file_type,
+                bytes: part.contents().to_vec(),
+            }),
+            None => skipped.push(name),
+        }
+    }
+
+    let mut markdown = format!("# {}\n\n", subject);
+    if let Some(from) = from.filter(|f| !f.is_empty()) {
+        markdown.push_str(&format!("- From: {}\n", from));
+    }
+    if let Some(date) = message.date() {
+        markdown.push_str(&format!("- Date: {}\n", date.to_rfc3339()));
+    }
+    if !attachments.is_empty() {
+        let names: Vec<&str> = attachments.iter().map(|a| a.name.as_str()).collect();
+        markdown.push_str(&format!("- Attachments: {}\n", names.join(", ")));
+    }
+    if !skipped.is_empty() {
+        markdown.push_str(&format!("- Not imported: {}\n", skipped.join(", ")));
+    }
+    if let Some(body) = message.body_text(0) {
+        markdown.push('\n');
+        markdown.push_str(body.trim());
+        markdown.push('\n');
+    }
+
+    Some(Email {
+        subject,
+        markdown,
+        attachments,
+    })
+}
+
+/// `value` as an IMAP quoted string.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn read_states(file: &Path) -> Result<BTreeMap<String, FolderState>> {
+    match std::fs::read_to_string(file) {
+        Ok(contents) => serde_json::from_str(&contents).context("Mail state file is corrupt"),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e).context("Failed to read mail state"),
+    }
+}
+
+fn write_states(file: &Path, states: &BTreeMap<String, FolderState>) -> Result<()> {
+    std::fs::write(file, serde_json::to_string_pretty(states)?)
+        .context("Failed to write mail state")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> MailboxConfig {
+        MailboxConfig {
+            enabled: true,
+            host: "imap.example.org".to_string(),
+            port: 993,
+            user: "tips".to_string(),
+            password: None,
+            folder: "INBOX".to_string(),
+            from_filter: None,
+            subject_filter: Some("leak \"urgent\"".to_string()),
+            collection_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_search_query() {
+        let mut config = config();
+        assert_eq!(
+            config.search_query(41),
+            r#"UID 42:* SUBJECT "leak \"urgent\"""#
+        );
+        config.from_filter = Some("café.org".to_string());
+        assert_eq!(
+            config.search_query(0),
+            r#"CHARSET UTF-8 UID 1:* FROM "café.org" SUBJECT "leak \"urgent\"""#
+        );
+    }
+
+    #[test]
+    fn test_parse_email() {
+        let raw = b"From: Jane Source <jane@example.org>\r\n\
+Subject: Contract/offer details\r\n\
+Date: Fri, 1 Mar 2024 10:00:00 +0000\r\n\
+MIME-Version: 1.0\r\n\
+Content-Type: multipart/mixed; boundary=\"b\"\r\n\
+\r\n\
+--b\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+See the attached memo.\r\n\
+--b\r\n\
+Content-Type: application/pdf; name=\"memo.pdf\"\r\n\
+Content-Disposition: attachment; filename=\"memo.pdf\"\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+JVBERi0xLjQK\r\n\
+--b\r\n\
+Content-Type: image/png; name=\"logo.png\"\r\n\
+Content-Disposition: attachment; filename=\"logo.png\"\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+iVBORw0KGgo=\r\n\
+--b--\r\n";
+        let email = parse_email(raw).unwrap();
+        assert_eq!(email.subject, "Contract/offer details");
+        assert_eq!(file_stem(&email.subject), "Contract_offer details");
+        assert_eq!(
+            email.markdown,
+            "# Contract/offer details\n\n\
+             - From: Jane Source <jane@example.org>\n\
+             - Date: 2024-03-01T10:00:00Z\n\
+             - Attachments: memo.pdf\n\
+             - Not imported: logo.png\n\
+             \n\
+             See the attached memo.\n"
+        );
+        assert_eq!(email.attachments.len(), 1);
+        assert_eq!(email.attachments[0].file_type, "application/pdf");
+        assert_eq!(email.attachments[0].bytes, b"%PDF-1.4\n");
+    }
+}
@@ -21,18 +21,38 @@
 //!   loaded provider to unloaded). The provider is the single authority
 //!   on whether a transition happened, which keeps replace/clear, evict,
 //!   and reap announcements consistent and prevents spurious events.
+//! - A replaced chat model that is still answering can't be unloaded at
+//!   once. It is kept as "retiring" and unloaded as soon as its last
+//!   request ends; a new local chat model waits for that before loading,
+//!   so the two never hold VRAM together.
+//! - An optional prediction slot holds a small local model for
+//!   next-message predictions, kept loaded alongside the chat model.
+//!   Without one, predictions use the chat model.
+//!
+//! # Background priority
+//!
+//! Chat answers and suggestions come first. While the research page is
+//! focused or an [`InteractiveGuard`] is held, background workers wait at
+//! their next document boundary ([`FocusGuard`]); embedding also yields
+//! between batches ([`ModelManager::yield_to_interactive`]), so a long
+//! document doesn't hold the GPU for the length of an answer. In low power
+//! mode background batches are further spaced out.
 
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use serde::Serialize;
 use tokio::sync::{broadcast, watch, RwLock};
 
 use crate::config::LifecycleConfig;
 use crate::provider::{
     ChatProvider, EmbeddingProvider, MemoryKind, OcrProvider, Provider, ProviderConfig,
+    SamplingParams,
 };
+use crate::rate_limit::RateLimitedChatProvider;
+use crate::spend::{MeteredChatProvider, Metering};
 use crate::{ModelStatus, ModelType};
 
 const STATUS_CHANNEL_CAPACITY: usize = 64;
@@ -43,15 +63,60 @@ const IDLE_TTL: Duration = Duration::from_secs(5 * 60);
 /// How often the reaper checks for idle models.
 const REAP_INTERVAL: Duration = Duration::from_secs(30);
 
+/// How often to retry unloading a replaced model that is still in use.
+const RETIRE_POLL: Duration = Duration::from_millis(200);
+
+/// Longest a new chat model waits for a replaced one to finish its
+/// requests before loading anyway.
+const RETIRE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Longest a background worker rests between batches in low power mode.
+const MAX_LOW_POWER_REST: Duration = Duration::from_secs(30);
+
+/// Whether the embedding slot can produce vectors right now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingReadiness {
+    /// No embedding model configured.
+    Unconfigured,
+    /// Configured; weights load on first use.
+    Idle,
+    Loading,
+    /// Loaded and passed its health check.
+    Ready,
+    /// Failed to load or failed its health check. Retried on next use.
+    Broken,
+}
+
+/// Embedding readiness plus the most recent load or health-check error.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmbeddingHealth {
+    pub readiness: EmbeddingReadiness,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct HealthState {
+    loading: bool,
+    broken: bool,
+    last_error: Option<String>,
+}
+
 /// Central manager for chat / embedding / OCR providers.
 pub struct ModelManager {
     chat: RwLock<Option<Arc<dyn ChatProvider>>>,
     chat_config: RwLock<Option<ProviderConfig>>,
     chat_last_activity: AtomicU64,
+    /// Replaced chat models still serving a request, unloaded when done.
+    chat_retiring: Mutex<Vec<Arc<dyn ChatProvider>>>,
+
+    prediction: RwLock<Option<Arc<dyn ChatProvider>>>,
+    prediction_last_activity: AtomicU64,
 
     embedding: RwLock<Option<Arc<dyn EmbeddingProvider>>>,
     embedding_model_id: RwLock<Option<String>>,
     embedding_last_activity: AtomicU64,
+    embedding_health: Mutex<HealthState>,
 
     ocr: RwLock<Option<Arc<dyn OcrProvider>>>,
     ocr_model_id: RwLock<Option<String>>,
@@ -60,28 +125,49 @@ pub struct ModelManager {
     lifecycle: RwLock<LifecycleConfig>,
     status_tx: broadcast::Sender<ModelStatus>,
     focus_tx: watch::Sender<bool>,
+    /// Interactive requests in flight; see [`InteractiveGuard`].
+    interactive_tx: Arc<watch::Sender<usize>>,
+    low_power_tx: watch::Sender<bool>,
+    /// Spend tracking for remote chat providers (None = untracked).
+    metering: Option<Metering>,
 }
 
 impl ModelManager {
     pub fn new() -> Self {
         let (status_tx, _) = broadcast::channel(STATUS_CHANNEL_CAPACITY);
         let (focus_tx, _) = watch::channel(false);
+        let (interactive_tx, _) = watch::channel(0);
+        let (low_power_tx, _) = watch::channel(false);
         Self {
             chat: RwLock::new(None),
             chat_config: RwLock::new(None),
             chat_last_activity: AtomicU64::new(0),
+            chat_retiring: Mutex::new(Vec::new()),
+            prediction: RwLock::new(None),
+            prediction_last_activity: AtomicU64::new(0),
             embedding: RwLock::new(None),
             embedding_model_id: RwLock::new(None),
             embedding_last_activity: AtomicU64::new(0),
+            embedding_health: Mutex::new(HealthState::default()),
             ocr: RwLock::new(None),
             ocr_model_id: RwLock::new(None),
             ocr_last_activity: AtomicU64::new(0),
             lifecycle: RwLock::new(LifecycleConfig::default()),
             status_tx,
             focus_tx,
+            interactive_tx: Arc::new(interactive_tx),
+            low_power_tx,
+            metering: None,
         }
     }
 
+    /// Check the monthly budget before, and record the cost of, every
+    /// completion on a remote chat provider installed from now on.
+    pub fn with_metering(mut self, metering: Metering) -> Self {
+        self.metering = Some(metering);
+        self
+    }
+
     /// Spawn the idle reaper. Call once from app startup.
     ///
     /// Ticks every [`REAP_INTERVAL`]; any resident local model idle longer
@@ -131,7 +217,43 @@ impl ModelManager {
     pub fn focus_guard(&self) -> FocusGuard {
         FocusGuard {
             rx: self.focus_tx.subscribe(),
+            interactive: self.interactive_tx.subscribe(),
+        }
+    }
+
+    /// Mark an interactive request (a chat answer, a suggestion) as
+    /// running until the guard is dropped. Background work yields to it.
+    pub fn interactive(&self) -> InteractiveGuard {
+        self.interactive_tx.send_modify(|count| *count += 1);
+        InteractiveGuard {
+            tx: self.interactive_tx.clone(),
+        }
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        *self.interactive_tx.borrow() > 0
+    }
+
+    pub fn is_low_power(&self) -> bool {
+        *self.low_power_tx.borrow()
+    }
+
+    /// Called by background workers between batches, after `worked` spent
+    /// on the last one. Waits while interactive requests run, then in low
+    /// power mode rests as long as the batch took. Returns whether it
+    /// waited for an interactive request, in which case the worker's model
+    /// may have been evicted meanwhile.
+    pub async fn yield_to_interactive(&self, worked: Duration) -> bool {
+        let mut interactive = self.interactive_tx.subscribe();
+        let waited = *interactive.borrow_and_update() > 0;
+        if waited {
+            tracing::debug!("Background work yielding to interactive request");
+            let _ = interactive.wait_for(|count| *count == 0).await;
+        }
+        if self.is_low_power() {
+            tokio::time::sleep(worked.min(MAX_LOW_POWER_REST)).await;
         }
+        waited
     }
 
     // ------------------------------------------------------------------
@@ -142,6 +264,7 @@ impl ModelManager {
     /// currently installed providers.
     pub async fn set_lifecycle_config(&self, cfg: LifecycleConfig) {
         *self.lifecycle.write().await = cfg.clone();
+        self.low_power_tx.send_replace(cfg.low_power);
         if let Some(p) = self.chat.read().await.as_ref() {
             p.set_coexist(cfg.chat_coexist);
         }
@@ -166,12 +289,22 @@ impl ModelManager {
         provider: Arc<dyn ChatProvider>,
         config: ProviderConfig,
     ) -> Result<()> {
+        let provider: Arc<dyn ChatProvider> = if provider.memory_kind() == MemoryKind::Remote {
+            Arc::new(RateLimitedChatProvider::new(provider))
+        } else {
+            provider
+        };
+        let provider: Arc<dyn ChatProvider> = match &self.metering {
+            Some(metering) if provider.memory_kind() == MemoryKind::Remote => {
+                Arc::new(MeteredChatProvider::new(provider, metering.clone()))
+            }
+            _ => provider,
+        };
         // Apply current coexist setting so the provider's view matches
         // settings without a separate call.
         provider.set_coexist(self.lifecycle.read().await.chat_coexist);
         if let Some(old) = self.chat.write().await.replace(provider) {
-            self.announce_unload(old.as_ref() as &dyn Provider, ModelType::Language)
-                .await;
+            self.retire_chat(old).await;
         }
         *self.chat_config.write().await = Some(config);
         Ok(())
@@ -179,8 +312,7 @@ impl ModelManager {
 
     pub async fn clear_chat(&self) {
         if let Some(old) = self.chat.write().await.take() {
-            self.announce_unload(old.as_ref() as &dyn Provider, ModelType::Language)
-                .await;
+            self.retire_chat(old).await;
         }
         *self.chat_config.write().await = None;
     }
@@ -193,19 +325,192 @@ impl ModelManager {
         self.touch(ModelType::Language);
         self.evict_conflicting(ModelType::Language, provider.as_ref() as &dyn Provider)
             .await;
+        if provider.memory_kind() == MemoryKind::Local {
+            self.finish_retiring().await;
+        }
         self.ensure_loaded(provider.as_ref() as &dyn Provider, ModelType::Language)
             .await?;
         Ok(Some(Lease { provider }))
     }
 
+    /// Unload a replaced chat model now, or once its last request ends.
+    async fn retire_chat(&self, old: Arc<dyn ChatProvider>) {
+        if self
+            .announce_unload(old.as_ref() as &dyn Provider, ModelType::Language)
+            .await
+            || old.memory_kind() != MemoryKind::Local
+            || !old.is_loaded().await
+        {
+            return;
+        }
+        tracing::info!(model = %old.model_id(), "Replaced chat model is busy, unloading when done");
+        self.chat_retiring.lock().unwrap().push(old);
+    }
+
+    /// Unload replaced chat models whose requests have ended.
+    async fn reap_retiring(&self) {
+        let retiring = std::mem::take(&mut *self.chat_retiring.lock().unwrap());
+        for old in retiring {
+            let unloaded = self
+                .announce_unload(old.as_ref() as &dyn Provider, ModelType::Language)
+                .await;
+            if !unloaded && old.is_loaded().await {
+                self.chat_retiring.lock().unwrap().push(old);
+            }
+        }
+    }
+
+    /// Wait for replaced chat models to unload, so a new local model
+    /// doesn't load while they still hold memory.
+    async fn finish_retiring(&self) {
+        let retiring = std::mem::take(&mut *self.chat_retiring.lock().unwrap());
+        if retiring.is_empty() {
+            return;
+        }
+        let deadline = tokio::time::Instant::now() + RETIRE_TIMEOUT;
+        for old in retiring {
+            loop {
+                if !old.is_loaded().await
+                    || self
+                        .announce_unload(old.as_ref() as &dyn Provider, ModelType::Language)
+                        .await
+                {
+                    break;
+                }
+                if tokio::time::Instant::now() >= deadline {
+                    tracing::warn!(
+                        model = %old.model_id(),
+                        "Replaced chat model still busy, loading the new one anyway"
+                    );
+                    break;
+                }
+                tokio::time::sleep(RETIRE_POLL).await;
+            }
+        }
+    }
+
     pub async fn chat_config(&self) -> Option<ProviderConfig> {
         self.chat_config.read().await.clone()
     }
 
+    /// Change the default sampling of the installed chat provider. Returns
+    /// the updated config to persist, or `None` when no provider is set.
+    pub async fn set_chat_sampling(&self, sampling: SamplingParams) -> Option<ProviderConfig> {
+        let mut config = self.chat_config.write().await;
+        let config = config.as_mut()?;
+        config.set_sampling(sampling);
+        Some(config.clone())
+    }
+
     pub async fn chat_ready(&self) -> bool {
         self.chat.read().await.is_some()
     }
 
+    // ------------------------------------------------------------------
+    // Prediction
+    // ------------------------------------------------------------------
+
+    /// Use `provider`, a small local model, for next-message predictions.
+    /// It stays loaded alongside the chat model until idle.
+    pub async fn set_prediction(&self, provider: Arc<dyn ChatProvider>) {
+        provider.set_coexist(true);
+        if let Some(old) = self.prediction.write().await.replace(provider) {
+            if let Err(e) = old.unload().await {
+                tracing::warn!(model = %old.model_id(), error = %e, "unload failed");
+            }
+        }
+    }
+
+    pub async fn clear_prediction(&self) {
+        if let Some(old) = self.prediction.write().await.take() {
+            if let Err(e) = old.unload().await {
+                tracing::warn!(model = %old.model_id(), error = %e, "unload failed");
+            }
+        }
+    }
+
+    /// A lease on the prediction model, or on the chat model when no
+    /// prediction model is set. Loading the prediction model emits no
+    /// status: it happens in the background and the UI tracks the chat
+    /// model.
+    pub async fn acquire_prediction(&self) -> Result<Option<ChatLease>> {
+        let Some(provider) = self.prediction.read().await.as_ref().cloned() else {
+            return self.acquire_chat().await;
+        };
+        self.prediction_last_activity
+            .store(unix_now(), Ordering::Relaxed);
+        provider.ensure_loaded().await?;
+        Ok(Some(Lease { provider }))
+    }
+
+    pub async fn prediction_model_id(&self) -> Option<String> {
+        self.prediction
+            .read()
+            .await
+            .as_ref()
+            .map(|p| p.model_id().to_string())
+    }
+
+    // ------------------------------------------------------------------
+    // Explicit unload / reload
+    // ------------------------------------------------------------------
+
+    /// Unload `model_type`'s weights now, keeping it configured; the next
+    /// request loads them again. Returns whether anything was unloaded,
+    /// which it isn't while a request is using the model.
+    pub async fn unload(&self, model_type: ModelType) -> bool {
+        let Some(provider) = self.resident(model_type).await else {
+            return false;
+        };
+        let unloaded = self.announce_unload(provider.as_ref(), model_type).await;
+        if unloaded {
+            self.reset_activity(model_type);
+        }
+        unloaded
+    }
+
+    /// Unload `model_type` and load it again, e.g. to recover from a
+    /// failed load or after freeing memory elsewhere.
+    pub async fn reload(&self, model_type: ModelType) -> Result<()> {
+        let Some(provider) = self.resident(model_type).await else {
+            anyhow::bail!("No {:?} model is configured", model_type);
+        };
+        self.unload(model_type).await;
+        anyhow::ensure!(
+            !provider.is_loaded().await,
+            "The model is in use; try again when it's done"
+        );
+        match model_type {
+            ModelType::Language => self.acquire_chat().await.map(|_| ()),
+            ModelType::Embedding => self.acquire_embedding().await.map(|_| ()),
+            ModelType::Ocr => self.acquire_ocr().await.map(|_| ()),
+        }
+    }
+
+    /// The provider installed for `model_type`.
+    async fn resident(&self, model_type: ModelType) -> Option<Arc<dyn Provider>> {
+        match model_type {
+            ModelType::Language => self
+                .chat
+                .read()
+                .await
+                .clone()
+                .map(|p| p as Arc<dyn Provider>),
+            ModelType::Embedding => self
+                .embedding
+                .read()
+                .await
+                .clone()
+                .map(|p| p as Arc<dyn Provider>),
+            ModelType::Ocr => self
+                .ocr
+                .read()
+                .await
+                .clone()
+                .map(|p| p as Arc<dyn Provider>),
+        }
+    }
+
     // ------------------------------------------------------------------
     // Embedding
     // ------------------------------------------------------------------
@@ -221,6 +526,7 @@ impl ModelManager {
                 .await;
         }
         *self.embedding_model_id.write().await = Some(model_id);
+        *self.embedding_health.lock().unwrap() = HealthState::default();
         Ok(())
     }
 
@@ -230,8 +536,15 @@ impl ModelManager {
                 .await;
         }
         *self.embedding_model_id.write().await = None;
+        *self.embedding_health.lock().unwrap() = HealthState::default();
     }
 
+    /// Lease the embedder, loading it on first use.
+    ///
+    /// A fresh load is followed by [`EmbeddingProvider::health_check`], so
+    /// a model that loads but can't produce vectors fails here instead of
+    /// letting documents index without them. A broken embedder is checked
+    /// again on every acquire until it recovers.
     pub async fn acquire_embedding(&self) -> Result<Option<EmbeddingLease>> {
         let provider = match self.embedding.read().await.as_ref().cloned() {
             Some(p) => p,
@@ -240,9 +553,82 @@ impl ModelManager {
         self.touch(ModelType::Embedding);
         self.evict_conflicting(ModelType::Embedding, provider.as_ref() as &dyn Provider)
             .await;
-        self.ensure_loaded(provider.as_ref() as &dyn Provider, ModelType::Embedding)
-            .await?;
-        Ok(Some(Lease { provider }))
+
+        let was_loaded = provider.is_loaded().await;
+        let needs_check = {
+            let mut health = self.embedding_health.lock().unwrap();
+            if !was_loaded {
+                health.loading = true;
+            }
+            !was_loaded || health.broken
+        };
+
+        let result = async {
+            self.ensure_loaded(provider.as_ref() as &dyn Provider, ModelType::Embedding)
+                .await?;
+            if needs_check {
+                provider
+                    .health_check()
+                    .await
+                    .context("Embedding model failed its health check")?;
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                let mut health = self.embedding_health.lock().unwrap();
+                health.loading = false;
+                health.broken = false;
+                Ok(Some(Lease { provider }))
+            }
+            Err(e) => {
+                let error = format!("{:#}", e);
+                {
+                    let mut health = self.embedding_health.lock().unwrap();
+                    health.loading = false;
+                    health.broken = true;
+                    health.last_error = Some(error.clone());
+                }
+                tracing::warn!(model = %provider.model_id(), error = %error, "Embedder unavailable");
+                if provider.is_loaded().await {
+                    // Loaded but unhealthy: ensure_loaded already announced
+                    // Ready, so correct it.
+                    self.emit(ModelStatus::Failed {
+                        model_type: ModelType::Embedding,
+                        model_id: provider.model_id().to_string(),
+                        error,
+                    });
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Readiness of the embedding slot and its last error, for the UI.
+    pub async fn embedding_health(&self) -> EmbeddingHealth {
+        let provider = self.embedding.read().await.as_ref().cloned();
+        let loaded = match &provider {
+            Some(p) => p.is_loaded().await,
+            None => false,
+        };
+        let health = self.embedding_health.lock().unwrap();
+        let readiness = if provider.is_none() {
+            EmbeddingReadiness::Unconfigured
+        } else if health.loading {
+            EmbeddingReadiness::Loading
+        } else if health.broken {
+            EmbeddingReadiness::Broken
+        } else if loaded {
+            EmbeddingReadiness::Ready
+        } else {
+            EmbeddingReadiness::Idle
+        };
+        EmbeddingHealth {
+            readiness,
+            last_error: health.last_error.clone(),
+        }
     }
 
     /// Refresh the embedding activity timestamp. Call from long-running
@@ -389,6 +775,8 @@ impl ModelManager {
         let cutoff = unix_now().saturating_sub(IDLE_TTL.as_secs());
         let focus_held = self.is_research_focused();
 
+        self.reap_retiring().await;
+
         // Skip chat while the user is looking at research.
         if !focus_held {
             if let Some(provider) = self.chat.read().await.as_ref().cloned() {
@@ -400,6 +788,14 @@ impl ModelManager {
             }
         }
 
+        if let Some(provider) = self.prediction.read().await.as_ref().cloned() {
+            let last = self.prediction_last_activity.load(Ordering::Relaxed);
+            if last != 0 && last <= cutoff && provider.unload().await.unwrap_or(false) {
+                tracing::info!(model = %provider.model_id(), "Idle reaper: unloaded prediction model");
+                self.prediction_last_activity.store(0, Ordering::Relaxed);
+            }
+        }
+
         if let Some(provider) = self.embedding.read().await.as_ref().cloned() {
             let last = self.embedding_last_activity.load(Ordering::Relaxed);
             if last != 0 && last <= cutoff {
@@ -468,6 +864,7 @@ impl ModelManager {
     pub async fn shutdown(&self) {
         tracing::info!("ModelManager: shutting down all providers");
         self.clear_chat().await;
+        self.clear_prediction().await;
         self.clear_embedding().await;
         self.clear_ocr().await;
         // Give background threads (e.g. mistralrs engine) a moment to
@@ -499,24 +896,47 @@ impl Default for ModelManager {
 /// [`FocusGuard::wait_until_released`] between jobs so chat has priority.
 pub struct FocusGuard {
     rx: watch::Receiver<bool>,
+    interactive: watch::Receiver<usize>,
 }
 
 impl FocusGuard {
-    /// Return immediately if focus isn't held; otherwise wait until it is
-    /// released. In-progress work is never preempted — workers call this at
-    /// natural boundaries (between documents).
+    /// Return immediately if neither focus nor an interactive request is
+    /// held; otherwise wait until both are released. In-progress work is
+    /// never preempted — workers call this at natural boundaries (between
+    /// documents).
     pub async fn wait_until_released(&mut self) {
-        if !*self.rx.borrow() {
+        if !self.held() {
             return;
         }
         tracing::debug!("Worker yielding — research focus is held");
-        while *self.rx.borrow() {
-            if self.rx.changed().await.is_err() {
+        while self.held() {
+            let changed = tokio::select! {
+                changed = self.rx.changed() => changed,
+                changed = self.interactive.changed() => changed,
+            };
+            if changed.is_err() {
                 return;
             }
         }
         tracing::debug!("Research focus released — worker resuming");
     }
+
+    fn held(&self) -> bool {
+        *self.rx.borrow() || *self.interactive.borrow() > 0
+    }
+}
+
+/// Held for the length of an interactive request; see
+/// [`ModelManager::interactive`].
+pub struct InteractiveGuard {
+    tx: Arc<watch::Sender<usize>>,
+}
+
+impl Drop for InteractiveGuard {
+    fn drop(&mut self) {
+        self.tx
+            .send_modify(|count| *count = count.saturating_sub(1));
+    }
 }
 
 /// Short-lived handle that keeps a provider alive for the duration of one
@@ -561,7 +981,9 @@ fn unix_now() -> u64 {
 mod tests {
     use super::*;
     use crate::agent::Message;
-    use crate::provider::{ChatProvider, CompletionResult, ProviderEvent, ToolDefinition};
+    use crate::provider::{
+        ChatProvider, CompletionResult, ProviderEvent, SamplingParams, ToolDefinition,
+    };
     use async_trait::async_trait;
     use std::sync::atomic::{AtomicBool, AtomicUsize};
     use tokio::sync::mpsc;
@@ -574,6 +996,8 @@ mod tests {
         kind: MemoryKind,
         coexist: AtomicBool,
         loaded: AtomicBool,
+        /// Serving a request, so `unload` is skipped.
+        busy: AtomicBool,
         ensure_calls: AtomicUsize,
         unload_calls: AtomicUsize,
     }
@@ -585,6 +1009,7 @@ mod tests {
                 kind,
                 coexist: AtomicBool::new(coexist),
                 loaded: AtomicBool::new(kind == MemoryKind::Remote),
+                busy: AtomicBool::new(false),
                 ensure_calls: AtomicUsize::new(0),
                 unload_calls: AtomicUsize::new(0),
             })
@@ -625,6 +1050,9 @@ mod tests {
         }
         async fn unload(&self) -> Result<bool> {
             self.unload_calls.fetch_add(1, Ordering::Relaxed);
+            if self.busy.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
             Ok(self.loaded.swap(false, Ordering::Relaxed))
         }
     }
@@ -635,6 +1063,7 @@ mod tests {
             &self,
             _messages: &[Message],
             _tools: &[ToolDefinition],
+            _sampling: &SamplingParams,
             event_tx: mpsc::Sender<ProviderEvent>,
             _cancel: CancellationToken,
         ) -> Result<CompletionResult> {
@@ -647,6 +1076,7 @@ mod tests {
         ProviderConfig::OpenAI {
             api_key: "test".into(),
             model: "gpt".into(),
+            sampling: Default::default(),
         }
     }
 
@@ -667,6 +1097,81 @@ mod tests {
         assert_eq!(second.unload_calls(), 0);
     }
 
+    #[tokio::test]
+    async fn busy_replaced_chat_unloads_before_new_one_loads() {
+        let manager = ModelManager::new();
+        let first = TestChatProvider::new("first", MemoryKind::Local, false);
+        manager
+            .set_chat(first.clone(), remote_config())
+            .await
+            .unwrap();
+        let _ = manager.acquire_chat().await.unwrap().unwrap();
+        first.busy.store(true, Ordering::Relaxed);
+
+        let second = TestChatProvider::new("second", MemoryKind::Local, false);
+        manager
+            .set_chat(second.clone(), remote_config())
+            .await
+            .unwrap();
+        assert!(first.is_loaded().await);
+
+        // The request ends; the next load frees the old weights first.
+        first.busy.store(false, Ordering::Relaxed);
+        let _ = manager.acquire_chat().await.unwrap().unwrap();
+        assert!(!first.is_loaded().await);
+        assert!(second.is_loaded().await);
+        assert!(manager.chat_retiring.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn predictions_use_own_model_or_fall_back_to_chat() {
+        let manager = ModelManager::new();
+        let chat = TestChatProvider::new("chat", MemoryKind::Local, false);
+        manager
+            .set_chat(chat.clone(), remote_config())
+            .await
+            .unwrap();
+
+        let lease = manager.acquire_prediction().await.unwrap().unwrap();
+        assert_eq!(lease.model_id(), "chat");
+
+        let small = TestChatProvider::new("small", MemoryKind::Local, false);
+        manager.set_prediction(small.clone()).await;
+        let lease = manager.acquire_prediction().await.unwrap().unwrap();
+        assert_eq!(lease.model_id(), "small");
+        assert!(small.coexist());
+        // Loading the chat model leaves the prediction model warm.
+        let _ = manager.acquire_chat().await.unwrap().unwrap();
+        assert!(small.is_loaded().await);
+
+        manager.clear_prediction().await;
+        assert!(!small.is_loaded().await);
+        assert_eq!(manager.prediction_model_id().await, None);
+    }
+
+    #[tokio::test]
+    async fn unload_keeps_model_configured_and_reload_loads_it() {
+        let manager = ModelManager::new();
+        let chat = TestChatProvider::new("chat", MemoryKind::Local, false);
+        manager
+            .set_chat(chat.clone(), remote_config())
+            .await
+            .unwrap();
+        let _ = manager.acquire_chat().await.unwrap().unwrap();
+
+        assert!(manager.unload(ModelType::Language).await);
+        assert!(!chat.is_loaded().await);
+        assert!(manager.chat_ready().await);
+
+        manager.reload(ModelType::Language).await.unwrap();
+        assert!(chat.is_loaded().await);
+        assert_eq!(chat.ensure_calls(), 2);
+
+        chat.busy.store(true, Ordering::Relaxed);
+        assert!(manager.reload(ModelType::Language).await.is_err());
+        assert!(manager.reload(ModelType::Ocr).await.is_err());
+    }
+
     #[tokio::test]
     async fn acquire_chat_loads_exactly_once() {
         let manager = ModelManager::new();
@@ -711,6 +1216,7 @@ mod tests {
                 chat_coexist: true,
                 embedding_coexist: false,
                 ocr_coexist: false,
+                low_power: false,
             })
             .await;
         let chat = TestChatProvider::new("chat", MemoryKind::Local, true);
@@ -758,6 +1264,7 @@ mod tests {
                 chat_coexist: true,
                 embedding_coexist: false,
                 ocr_coexist: false,
+                low_power: false,
             })
             .await;
         assert!(chat.coexist());
@@ -975,6 +1482,171 @@ mod tests {
             .expect("guard should release after focus flips to false");
     }
 
+    #[tokio::test]
+    async fn background_yields_to_interactive_requests() {
+        let manager = Arc::new(ModelManager::new());
+        let mut guard = manager.focus_guard();
+        assert!(!manager.yield_to_interactive(Duration::ZERO).await);
+
+        let first = manager.interactive();
+        let second = manager.interactive();
+        assert!(manager.is_interactive());
+
+        let m = manager.clone();
+        let batch = tokio::spawn(async move { m.yield_to_interactive(Duration::ZERO).await });
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!batch.is_finished(), "should wait for every request");
+
+        drop(second);
+        let waited = tokio::time::timeout(Duration::from_millis(500), batch)
+            .await
+            .expect("batch should resume once requests finish")
+            .unwrap();
+        assert!(waited);
+        tokio::time::timeout(Duration::from_millis(50), guard.wait_until_released())
+            .await
+            .expect("guard should not block once requests finish");
+    }
+
+    #[tokio::test]
+    async fn low_power_rests_between_batches() {
+        let manager = ModelManager::new();
+        let started = std::time::Instant::now();
+        manager.yield_to_interactive(Duration::from_secs(5)).await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        manager
+            .set_lifecycle_config(LifecycleConfig {
+                low_power: true,
+                ..Default::default()
+            })
+            .await;
+        let started = std::time::Instant::now();
+        manager
+            .yield_to_interactive(Duration::from_millis(50))
+            .await;
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+
+    // ---- Embedding health tests ----
+
+    struct TestEmbeddingProvider {
+        loaded: AtomicBool,
+        healthy: AtomicBool,
+    }
+
+    impl TestEmbeddingProvider {
+        fn new(healthy: bool) -> Arc<Self> {
+            Arc::new(Self {
+                loaded: AtomicBool::new(false),
+                healthy: AtomicBool::new(healthy),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Provider for TestEmbeddingProvider {
+        fn provider_name(&self) -> &'static str {
+            "test-embedding"
+        }
+        fn model_id(&self) -> &str {
+            "embed"
+        }
+        fn memory_kind(&self) -> MemoryKind {
+            MemoryKind::Local
+        }
+        fn coexist(&self) -> bool {
+            true
+        }
+        fn set_coexist(&self, _v: bool) {}
+        async fn is_loaded(&self) -> bool {
+            self.loaded.load(Ordering::Relaxed)
+        }
+        async fn ensure_loaded(&self) -> Result<()> {
+            self.loaded.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        async fn unload(&self) -> Result<bool> {
+            Ok(self.loaded.swap(false, Ordering::Relaxed))
+        }
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for TestEmbeddingProvider {
+        fn dimensions(&self) -> usize {
+            2
+        }
+        async fn chunk_text(
+            &self,
+            content: &str,
+            _config: &crate::provider::ChunkingConfig,
+        ) -> Result<Vec<String>> {
+            Ok(vec![content.to_string()])
+        }
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>> {
+            if self.healthy.load(Ordering::Relaxed) {
+                Ok(vec![0.6, 0.8])
+            } else {
+                Ok(vec![0.0, 0.0])
+            }
+        }
+        async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+            let mut out = Vec::new();
+            for text in texts {
+                out.push(self.embed(text).await?);
+            }
+            Ok(out)
+        }
+    }
+
+    #[tokio::test]
+    async fn embedding_health_tracks_readiness() {
+        let manager = ModelManager::new();
+        assert_eq!(
+            manager.embedding_health().await.readiness,
+            EmbeddingReadiness::Unconfigured
+        );
+
+        let p = TestEmbeddingProvider::new(true);
+        manager
+            .set_embedding(p.clone(), "embed".into())
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.embedding_health().await.readiness,
+            EmbeddingReadiness::Idle
+        );
+
+        assert!(manager.acquire_embedding().await.unwrap().is_some());
+        let health = manager.embedding_health().await;
+        assert_eq!(health.readiness, EmbeddingReadiness::Ready);
+        assert!(health.last_error.is_none());
+    }
+
+    #[tokio::test]
+    async fn unhealthy_embedder_is_broken_until_it_recovers() {
+        let manager = ModelManager::new();
+        let p = TestEmbeddingProvider::new(false);
+        manager
+            .set_embedding(p.clone(), "embed".into())
+            .await
+            .unwrap();
+
+        assert!(manager.acquire_embedding().await.is_err());
+        let health = manager.embedding_health().await;
+        assert_eq!(health.readiness, EmbeddingReadiness::Broken);
+        assert!(health.last_error.unwrap().contains("all-zero"));
+
+        // Already loaded, but still re-checked while broken.
+        p.healthy.store(true, Ordering::Relaxed);
+        assert!(manager.acquire_embedding().await.unwrap().is_some());
+        assert_eq!(
+            manager.embedding_health().await.readiness,
+            EmbeddingReadiness::Ready
+        );
+    }
+
     // ---- OCR slot tests ----
 
     use crate::provider::OcrProvider;
@@ -1087,6 +1759,7 @@ mod tests {
                 chat_coexist: false,
                 embedding_coexist: false,
                 ocr_coexist: true,
+                low_power: false,
             })
             .await;
         let p = TestOcrProvider::new("p", MemoryKind::Local, false);
@@ -0,0 +1,101 @@
+//! Facts the agent keeps about a collection across conversations.
+//!
+//! Each collection has its own JSON file of short notes — findings the user
+//! confirmed, which documents matter, names to watch for — so a new chat
+//! doesn't have to rediscover them. The agent adds notes with the
+//! `remember` tool and sees them in its system prompt.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::collection_files;
+
+/// One remembered fact.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub id: String,
+    pub text: String,
+    pub created_at: String,
+}
+
+/// Names memory files in error messages.
+const WHAT: &str = "memories";
+
+/// A collection's memories, oldest first. Empty when none were saved.
+pub fn load_memories(memory_dir: &Path, collection_id: &str) -> Result<Vec<MemoryEntry>> {
+    collection_files::load_list(memory_dir, collection_id, WHAT)
+}
+
+/// Remember `text` for a collection. Returns the stored entry; remembering
+/// the same text twice (ignoring case and spacing) returns the first one.
+pub fn add_memory(memory_dir: &Path, collection_id: &str, text: &str) -> Result<MemoryEntry> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        bail!("Nothing to remember");
+    }
+
+    let mut entries = load_memories(memory_dir, collection_id)?;
+    if let Some(existing) = entries.iter().find(|e| e.text.eq_ignore_ascii_case(&text)) {
+        return Ok(existing.clone());
+    }
+
+    let entry = MemoryEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        text,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    entries.push(entry.clone());
+    collection_files::store_list(memory_dir, collection_id, &entries, WHAT)?;
+    Ok(entry)
+}
+
+/// Forget one memory. Returns whether it existed.
+pub fn delete_memory(memory_dir: &Path, collection_id: &str, memory_id: &str) -> Result<bool> {
+    collection_files::remove_from_list(memory_dir, collection_id, WHAT, |e: &MemoryEntry| {
+        e.id == memory_id
+    })
+}
+
+/// Forget everything remembered about a collection, e.g. when it is
+/// deleted. Missing files are treated as success.
+pub fn delete_memories(memory_dir: &Path, collection_id: &str) -> Result<()> {
+    collection_files::delete_list(memory_dir, collection_id, WHAT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_load_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let memory_dir = dir.path().join("memory");
+        assert!(load_memories(&memory_dir, "abc123").unwrap().is_empty());
+
+        let first = add_memory(&memory_dir, "abc123", "  Acme  paid the invoice.").unwrap();
+        assert_eq!(first.text, "Acme paid the invoice.");
+        let again = add_memory(&memory_dir, "abc123", "acme paid the invoice.").unwrap();
+        assert_eq!(again.id, first.id);
+        add_memory(&memory_dir, "abc123", "The CFO resigned in May.").unwrap();
+
+        let entries = load_memories(&memory_dir, "abc123").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(load_memories(&memory_dir, "other1").unwrap().is_empty());
+
+        assert!(delete_memory(&memory_dir, "abc123", &first.id).unwrap());
+        assert!(!delete_memory(&memory_dir, "abc123", &first.id).unwrap());
+        assert_eq!(load_memories(&memory_dir, "abc123").unwrap().len(), 1);
+
+        delete_memories(&memory_dir, "abc123").unwrap();
+        assert!(load_memories(&memory_dir, "abc123").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_path_like_collection_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(add_memory(dir.path(), "../settings", "x").is_err());
+        assert!(load_memories(dir.path(), "").is_err());
+    }
+}
@@ -9,6 +9,8 @@ use hf_hub::Cache;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use tokio::sync::mpsc;
 
+use crate::provider::embedding::{EmbeddingPrompts, QWEN3_QUERY_PROMPT};
+
 // ============================================================================
 // ModelSpec Trait
 // ============================================================================
@@ -61,6 +63,18 @@ pub struct LanguageModelInfo {
     pub gguf_file: String,
     /// Repo ID for tokenizer (e.g., "Qwen/Qwen3-8B")
     pub tokenizer_repo_id: String,
+    /// Longest context the model was trained on, in tokens
+    pub context_length: usize,
+    /// Models in one family share a vocabulary (e.g., "qwen3")
+    pub family: String,
+}
+
+impl LanguageModelInfo {
+    /// Whether this model can draft tokens for `target` in speculative
+    /// decoding: it must share the vocabulary and be smaller to be faster.
+    pub fn can_draft_for(&self, target: &LanguageModelInfo) -> bool {
+        self.family == target.family && self.size_gb < target.size_gb
+    }
 }
 
 impl ModelSpec for LanguageModelInfo {
@@ -122,6 +136,8 @@ pub fn available_language_models() -> Vec<LanguageModelInfo> {
             gguf_repo_id: "Qwen/Qwen3-8B-GGUF".to_string(),
             gguf_file: "Qwen3-8B-Q4_K_M.gguf".to_string(),
             tokenizer_repo_id: "Qwen/Qwen3-8B".to_string(),
+            context_length: 32_768,
+            family: "qwen3".to_string(),
         },
         // Smaller option for constrained systems
         LanguageModelInfo {
@@ -132,6 +148,8 @@ pub fn available_language_models() -> Vec<LanguageModelInfo> {
             gguf_repo_id: "Qwen/Qwen3-4B-GGUF".to_string(),
             gguf_file: "Qwen3-4B-Q4_K_M.gguf".to_string(),
             tokenizer_repo_id: "Qwen/Qwen3-4B".to_string(),
+            context_length: 32_768,
+            family: "qwen3".to_string(),
         },
         // Higher quality option
         LanguageModelInfo {
@@ -143,6 +161,22 @@ pub fn available_language_models() -> Vec<LanguageModelInfo> {
             gguf_repo_id: "Qwen/Qwen3-8B-GGUF".to_string(),
             gguf_file: "Qwen3-8B-Q8_0.gguf".to_string(),
             tokenizer_repo_id: "Qwen/Qwen3-8B".to_string(),
+            context_length: 32_768,
+            family: "qwen3".to_string(),
+        },
+        // Draft model for speculative decoding with the larger Qwen3 models
+        LanguageModelInfo {
+            id: "qwen3-0.6b-q8".to_string(),
+            name: "Qwen3 0.6B (Q8_0)".to_string(),
+            description: "Tiny model for suggestions and for drafting answers with a larger \
+                          Qwen3. Too small for investigations. ~0.6GB download."
+                .to_string(),
+            size_gb: 0.6,
+            gguf_repo_id: "Qwen/Qwen3-0.6B-GGUF".to_string(),
+            gguf_file: "Qwen3-0.6B-Q8_0.gguf".to_string(),
+            tokenizer_repo_id: "Qwen/Qwen3-0.6B".to_string(),
+            context_length: 32_768,
+            family: "qwen3".to_string(),
         },
     ]
 }
@@ -151,6 +185,61 @@ pub fn available_language_models() -> Vec<LanguageModelInfo> {
 // Embedding Models
 // ============================================================================
 
+/// Languages an embedding model retrieves well in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LanguageCoverage {
+    /// Not known (e.g. a custom model from an unrecognized family).
+    #[default]
+    Unknown,
+    English,
+    Multilingual,
+}
+
+impl LanguageCoverage {
+    /// Best guess from a HuggingFace repo id, for custom models.
+    pub fn for_repo(hf_repo_id: &str) -> Self {
+        let repo = hf_repo_id.to_ascii_lowercase();
+        if repo.contains("multilingual") || repo.contains("bge-m3") || repo.contains("qwen3") {
+            Self::Multilingual
+        } else if repo.ends_with("-en") || repo.contains("-en-") {
+            Self::English
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+/// Runtime that embeds with a model. A model never switches runtimes,
+/// because the two pool token states differently and their vectors aren't
+/// comparable.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum EmbeddingBackend {
+    /// mistralrs, from the repo's safetensors weights.
+    #[default]
+    Mistralrs,
+    /// ONNX Runtime, from the repo's `onnx/model.onnx` export. Used for the
+    /// XLM-RoBERTa based models mistralrs can't load. Needs the `onnx`
+    /// feature.
+    Onnx {
+        pooling: OnnxPooling,
+        /// The export keeps its weights in `onnx/model.onnx_data`.
+        external_data: bool,
+    },
+}
+
+/// How the ONNX backend turns token states into one vector, matching how
+/// the model was trained. Unused when the export has a pooled output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnnxPooling {
+    /// Average over the attention mask (E5).
+    Mean,
+    /// First token (BGE).
+    Cls,
+}
+
 /// Information about an embedding model for semantic search
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingModelInfo {
@@ -166,6 +255,28 @@ pub struct EmbeddingModelInfo {
     pub hf_repo_id: String,
     /// Vector dimensions produced by this model
     pub dimensions: usize,
+    /// Query/document prompts the model was trained with.
+    #[serde(default)]
+    pub prompts: EmbeddingPrompts,
+    /// Languages the model handles, for recommending one.
+    #[serde(default)]
+    pub languages: LanguageCoverage,
+    /// Runtime that embeds with this model.
+    #[serde(default)]
+    pub backend: EmbeddingBackend,
+}
+
+impl EmbeddingModelInfo {
+    /// Why this build can't run the model, or `None` if it can.
+    pub fn unavailable_reason(&self) -> Option<&'static str> {
+        match self.backend {
+            EmbeddingBackend::Onnx { .. } if !cfg!(feature = "onnx") => Some(
+                "Runs on ONNX Runtime, which this build doesn't include. \
+                 Build with the onnx feature to use it.",
+            ),
+            _ => None,
+        }
+    }
 }
 
 impl ModelSpec for EmbeddingModelInfo {
@@ -186,12 +297,23 @@ impl ModelSpec for EmbeddingModelInfo {
     }
 
     fn required_files(&self) -> Vec<(String, String)> {
-        vec![
-            (self.hf_repo_id.clone(), "config.json".to_string()),
-            (self.hf_repo_id.clone(), "model.safetensors".to_string()),
-            (self.hf_repo_id.clone(), "tokenizer.json".to_string()),
-            (self.hf_repo_id.clone(), "tokenizer_config.json".to_string()),
-        ]
+        let weights: &[&str] = match self.backend {
+            EmbeddingBackend::Mistralrs => &["model.safetensors"],
+            EmbeddingBackend::Onnx {
+                external_data: false,
+                ..
+            } => &[ONNX_EXPORT_FILE],
+            EmbeddingBackend::Onnx {
+                external_data: true,
+                ..
+            } => &[ONNX_EXPORT_FILE, ONNX_EXTERNAL_DATA_FILE],
+        };
+        ["config.json"]
+            .iter()
+            .chain(weights)
+            .chain(&["tokenizer.json", "tokenizer_config.json"])
+            .map(|file| (self.hf_repo_id.clone(), file.to_string()))
+            .collect()
     }
 
     fn primary_repo(&self) -> &str {
@@ -203,20 +325,95 @@ impl ModelSpec for EmbeddingModelInfo {
     }
 }
 
+/// Path of a sentence-transformers ONNX export within an embedding repo.
+pub const ONNX_EXPORT_FILE: &str = "onnx/model.onnx";
+
+/// External weights for ONNX exports too large for a single protobuf.
+const ONNX_EXTERNAL_DATA_FILE: &str = "onnx/model.onnx_data";
+
 /// Get the default embedding model
 pub fn default_embedding_model() -> EmbeddingModelInfo {
     available_embedding_models().into_iter().next().unwrap()
 }
 
-/// Get an embedding model by ID
+/// Get an embedding model by ID. Custom model IDs (see
+/// [`custom_embedding_model`]) resolve without a registry entry.
 pub fn get_embedding_model(id: &str) -> Option<EmbeddingModelInfo> {
     available_embedding_models()
         .into_iter()
         .find(|m| m.id == id)
+        .or_else(|| parse_custom_embedding_id(id))
+}
+
+/// Prefix of custom embedding model IDs: `custom:{org}:{name}:{dimensions}`.
+///
+/// The repo and dimensions are encoded in the ID itself so it survives
+/// restarts without extra settings, and so peers that pick the same repo
+/// get the same ID and can share embeddings. `/` is swapped for `:` because
+/// model IDs become iroh key segments (`files/{id}/embeddings/{model_id}`).
+pub const CUSTOM_EMBEDDING_PREFIX: &str = "custom:";
+
+/// Largest vector size accepted for a custom model.
+const MAX_CUSTOM_DIMENSIONS: usize = 8192;
+
+/// Describe a user-supplied HuggingFace embedding model.
+///
+/// Checks the repo id shape and dimensions only; whether the repo has the
+/// expected files is checked by [`ModelDownloader::verify_remote_files`],
+/// and whether it really produces `dimensions`-sized vectors is checked
+/// when the model loads. The repo must be loadable by mistralrs and ship a
+/// single `model.safetensors`.
+pub fn custom_embedding_model(repo_id: &str, dimensions: usize) -> Result<EmbeddingModelInfo> {
+    let repo_id = repo_id.trim();
+    let (org, name) = repo_id
+        .split_once('/')
+        .ok_or_else(|| anyhow::anyhow!("Repo id must look like 'org/name': {}", repo_id))?;
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    if !valid_part(org) || !valid_part(name) {
+        anyhow::bail!("Invalid HuggingFace repo id: {}", repo_id);
+    }
+    if dimensions == 0 || dimensions > MAX_CUSTOM_DIMENSIONS {
+        anyhow::bail!(
+            "Dimensions must be between 1 and {}, got {}",
+            MAX_CUSTOM_DIMENSIONS,
+            dimensions
+        );
+    }
+
+    Ok(EmbeddingModelInfo {
+        id: format!("{}{}:{}:{}", CUSTOM_EMBEDDING_PREFIX, org, name, dimensions),
+        name: format!("{} (custom)", repo_id),
+        description: format!("Custom model from HuggingFace. {} dimensions.", dimensions),
+        size_gb: 0.0,
+        hf_repo_id: repo_id.to_string(),
+        dimensions,
+        prompts: EmbeddingPrompts::for_repo(repo_id),
+        languages: LanguageCoverage::for_repo(repo_id),
+        backend: EmbeddingBackend::Mistralrs,
+    })
 }
 
-/// Available embedding models registry
-/// Note: Only models supported by mistralrs are listed
+/// Rebuild a custom model from its ID, or `None` if `id` isn't one.
+fn parse_custom_embedding_id(id: &str) -> Option<EmbeddingModelInfo> {
+    let rest = id.strip_prefix(CUSTOM_EMBEDDING_PREFIX)?;
+    let mut parts = rest.split(':');
+    let (org, name, dimensions) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some() {
+        return None;
+    }
+    let model =
+        custom_embedding_model(&format!("{}/{}", org, name), dimensions.parse().ok()?).ok()?;
+    // Reject non-canonical spellings (e.g. "0768") so one repo has one ID.
+    (model.id == id).then_some(model)
+}
+
+/// Available embedding models registry. ONNX models are listed in every
+/// build; see [`EmbeddingModelInfo::unavailable_reason`].
 pub fn available_embedding_models() -> Vec<EmbeddingModelInfo> {
     vec![
         // Default: Qwen3 Embedding - Apache 2.0, not gated
@@ -227,10 +424,108 @@ pub fn available_embedding_models() -> Vec<EmbeddingModelInfo> {
             size_gb: 1.2,
             hf_repo_id: "Qwen/Qwen3-Embedding-0.6B".to_string(),
             dimensions: 1024,
+            prompts: EmbeddingPrompts::new(QWEN3_QUERY_PROMPT, ""),
+            languages: LanguageCoverage::Multilingual,
+            backend: EmbeddingBackend::Mistralrs,
+        },
+        // XLM-RoBERTa based models below: mistralrs can't run them, so they
+        // use their repos' ONNX exports.
+        // MIT licensed, ~100 languages
+        EmbeddingModelInfo {
+            id: "multilingual-e5-base".to_string(),
+            name: "Multilingual E5 Base".to_string(),
+            description: "Fast multilingual model covering ~100 languages.".to_string(),
+            size_gb: 1.1,
+            hf_repo_id: "intfloat/multilingual-e5-base".to_string(),
+            dimensions: 768,
+            prompts: EmbeddingPrompts::new("query: ", "passage: "),
+            languages: LanguageCoverage::Multilingual,
+            backend: EmbeddingBackend::Onnx {
+                pooling: OnnxPooling::Mean,
+                external_data: false,
+            },
+        },
+        EmbeddingModelInfo {
+            id: "multilingual-e5-large".to_string(),
+            name: "Multilingual E5 Large".to_string(),
+            description: "Higher quality multilingual model covering ~100 languages.".to_string(),
+            size_gb: 2.2,
+            hf_repo_id: "intfloat/multilingual-e5-large".to_string(),
+            dimensions: 1024,
+            prompts: EmbeddingPrompts::new("query: ", "passage: "),
+            languages: LanguageCoverage::Multilingual,
+            backend: EmbeddingBackend::Onnx {
+                pooling: OnnxPooling::Mean,
+                external_data: true,
+            },
+        },
+        // MIT licensed, 100+ languages, trained without prompts
+        EmbeddingModelInfo {
+            id: "bge-m3".to_string(),
+            name: "BGE-M3".to_string(),
+            description: "Multilingual model covering 100+ languages, strong on long passages."
+                .to_string(),
+            size_gb: 2.3,
+            hf_repo_id: "BAAI/bge-m3".to_string(),
+            dimensions: 1024,
+            prompts: EmbeddingPrompts::default(),
+            languages: LanguageCoverage::Multilingual,
+            backend: EmbeddingBackend::Onnx {
+                pooling: OnnxPooling::Cls,
+                external_data: true,
+            },
         },
     ]
 }
 
+/// Common English function words. Their share of a text's words is a
+/// cheap, dependency-free signal for whether it is English.
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "the", "and", "of", "to", "in", "is", "that", "for", "it", "with", "as", "was", "on", "be",
+    "by", "this", "are", "or", "not", "from", "at", "which", "have", "has", "were", "an",
+];
+
+/// Below this stopword share a text is treated as non-English. English
+/// prose sits around 0.3–0.45; other Latin-script languages well under 0.1.
+const ENGLISH_STOPWORD_SHARE: f32 = 0.15;
+
+/// Heuristic language check. Texts too short to judge count as English.
+pub fn looks_english(text: &str) -> bool {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .take(2000)
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.len() < 20 {
+        return true;
+    }
+    let stopwords = words
+        .iter()
+        .filter(|w| ENGLISH_STOPWORDS.contains(&w.as_str()))
+        .count();
+    stopwords as f32 / words.len() as f32 >= ENGLISH_STOPWORD_SHARE
+}
+
+/// Suggest a multilingual model when most `samples` aren't English and
+/// `current` isn't known to handle other languages. `None` means keep the
+/// current model.
+pub fn recommend_embedding_model(
+    current: Option<&EmbeddingModelInfo>,
+    samples: &[&str],
+) -> Option<EmbeddingModelInfo> {
+    if current.is_some_and(|m| m.languages == LanguageCoverage::Multilingual) {
+        return None;
+    }
+    let non_english = samples.iter().filter(|s| !looks_english(s)).count();
+    if non_english * 2 <= samples.len() {
+        return None;
+    }
+    available_embedding_models()
+        .into_iter()
+        .find(|m| m.languages == LanguageCoverage::Multilingual && m.unavailable_reason().is_none())
+}
+
 // ============================================================================
 // OCR Models
 // ============================================================================
@@ -530,6 +825,10 @@ impl ModelDownloader {
         status_tx: mpsc::Sender<crate::ModelStatus>,
         progress_tx: mpsc::Sender<crate::ModelDownloadProgress>,
     ) -> Result<PathBuf> {
+        // The proxy was exported for the HuggingFace client at startup,
+        // unless it couldn't be used.
+        crate::net::check_proxy()?;
+
         // Emit downloading status
         let _ = status_tx
             .send(crate::ModelStatus::Downloading {
@@ -601,6 +900,44 @@ impl ModelDownloader {
         Ok(model_dir)
     }
 
+    /// Check that a model's repos exist and list every required file,
+    /// without downloading anything. Used to reject bad custom models up
+    /// front instead of failing midway through a download.
+    pub async fn verify_remote_files<M: ModelSpec>(&self, model: &M) -> Result<()> {
+        let mut by_repo: Vec<(String, Vec<String>)> = Vec::new();
+        for (repo_id, filename) in model.required_files() {
+            match by_repo.iter_mut().find(|(r, _)| *r == repo_id) {
+                Some((_, files)) => files.push(filename),
+                None => by_repo.push((repo_id, vec![filename])),
+            }
+        }
+
+        for (repo_id, files) in by_repo {
+            let info = self
+                .api
+                .model(repo_id.clone())
+                .info()
+                .await
+                .with_context(|| format!("HuggingFace repo not found: {}", repo_id))?;
+            let missing: Vec<&str> = files
+                .iter()
+                .filter(|f| !info.siblings.iter().any(|s| &s.rfilename == *f))
+                .map(|f| f.as_str())
+                .collect();
+            if !missing.is_empty() {
+                anyhow::bail!("{} is missing {}", repo_id, missing.join(", "));
+            }
+        }
+        Ok(())
+    }
+
+    /// Cached ONNX export and tokenizer for an embedding model, if both
+    /// have been downloaded.
+    pub fn onnx_export_paths(&self, model: &EmbeddingModelInfo) -> Option<(PathBuf, PathBuf)> {
+        let repo = self.cache.model(model.hf_repo_id.clone());
+        Some((repo.get(ONNX_EXPORT_FILE)?, repo.get("tokenizer.json")?))
+    }
+
     /// Get the HuggingFace cache directory path
     pub fn cache_path(&self) -> PathBuf {
         self.cache.path().clone()
@@ -628,6 +965,15 @@ mod tests {
         assert!(missing.is_none());
     }
 
+    #[test]
+    fn test_draft_models() {
+        let draft = get_language_model("qwen3-0.6b-q8").unwrap();
+        let target = default_language_model();
+        assert!(draft.can_draft_for(&target));
+        assert!(!target.can_draft_for(&draft));
+        assert!(!target.can_draft_for(&target));
+    }
+
     #[test]
     fn test_default_language_model() {
         let model = default_language_model();
@@ -653,6 +999,23 @@ mod tests {
         assert_eq!(models[0].dimensions, 1024);
     }
 
+    #[test]
+    fn test_onnx_embedding_models() {
+        // Listed in every build, unavailable without the onnx feature.
+        let bge = get_embedding_model("bge-m3").unwrap();
+        assert_eq!(bge.dimensions, 1024);
+        assert_eq!(bge.unavailable_reason().is_none(), cfg!(feature = "onnx"));
+
+        // ONNX models download the export instead of the safetensors.
+        let files: Vec<String> = bge.required_files().into_iter().map(|(_, f)| f).collect();
+        assert!(files.contains(&ONNX_EXPORT_FILE.to_string()));
+        assert!(files.contains(&ONNX_EXTERNAL_DATA_FILE.to_string()));
+        assert!(!files.contains(&"model.safetensors".to_string()));
+
+        let e5 = get_embedding_model("multilingual-e5-base").unwrap();
+        assert_eq!(e5.required_files().len(), 4);
+    }
+
     #[test]
     fn test_get_embedding_model() {
         let model = get_embedding_model("qwen3-embedding");
@@ -670,6 +1033,32 @@ mod tests {
         assert_eq!(model.hf_repo_id, "Qwen/Qwen3-Embedding-0.6B");
     }
 
+    #[test]
+    fn test_custom_embedding_model_roundtrip() {
+        let model = custom_embedding_model("BAAI/bge-base-en-v1.5", 768).unwrap();
+        assert_eq!(model.id, "custom:BAAI:bge-base-en-v1.5:768");
+        assert_eq!(model.hf_repo_id, "BAAI/bge-base-en-v1.5");
+
+        let parsed = get_embedding_model(&model.id).unwrap();
+        assert_eq!(parsed.hf_repo_id, "BAAI/bge-base-en-v1.5");
+        assert_eq!(parsed.dimensions, 768);
+        assert_eq!(parsed.prompts, model.prompts);
+        assert!(parsed.prompts.query.starts_with("Represent"));
+    }
+
+    #[test]
+    fn test_custom_embedding_model_validation() {
+        assert!(custom_embedding_model("no-slash", 768).is_err());
+        assert!(custom_embedding_model("org/name/extra", 768).is_err());
+        assert!(custom_embedding_model("org/na me", 768).is_err());
+        assert!(custom_embedding_model("org/name", 0).is_err());
+        assert!(custom_embedding_model("org/name", 100_000).is_err());
+
+        assert!(get_embedding_model("custom:org:name").is_none());
+        assert!(get_embedding_model("custom:org:name:0768").is_none());
+        assert!(get_embedding_model("custom:org:name:768:x").is_none());
+    }
+
     #[test]
     fn test_embedding_model_spec() {
         let model = default_embedding_model();
@@ -680,6 +1069,39 @@ mod tests {
         assert_eq!(model.primary_file(), "config.json");
     }
 
+    #[test]
+    fn test_looks_english() {
+        let english = "The court held that the contract was void because the supplier \
+                       had not delivered the goods by the date stated in the agreement, \
+                       and the buyer was entitled to a refund of the deposit.";
+        let german = "Das Gericht entschied, dass der Vertrag nichtig war, weil der \
+                      Lieferant die Waren nicht bis zu dem im Vertrag genannten Datum \
+                      geliefert hatte und der Käufer Anspruch auf Rückzahlung hatte.";
+        assert!(looks_english(english));
+        assert!(!looks_english(german));
+        assert!(looks_english("Too short to judge"));
+    }
+
+    #[test]
+    fn test_recommend_embedding_model() {
+        let english_only = custom_embedding_model("BAAI/bge-base-en-v1.5", 768).unwrap();
+        assert_eq!(english_only.languages, LanguageCoverage::English);
+        let french = "Le tribunal a jugé que le contrat était nul parce que le fournisseur \
+                      n'avait pas livré les marchandises à la date prévue dans l'accord, \
+                      et l'acheteur avait droit au remboursement de son acompte.";
+
+        let suggested = recommend_embedding_model(Some(&english_only), &[french, french]).unwrap();
+        assert_eq!(suggested.languages, LanguageCoverage::Multilingual);
+
+        // Mostly English, or already multilingual: keep the current model.
+        let english = "The buyer was entitled to a refund of the deposit because the goods \
+                       had not been delivered by the date that was stated in the contract.";
+        assert!(
+            recommend_embedding_model(Some(&english_only), &[english, english, french]).is_none()
+        );
+        assert!(recommend_embedding_model(Some(&default_embedding_model()), &[french]).is_none());
+    }
+
     #[test]
     fn test_available_ocr_models() {
         let models = available_ocr_models();
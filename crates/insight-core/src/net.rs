@@ -0,0 +1,331 @@
+//! Outgoing HTTP connections and the proxy they go through.
+//!
+//! Remote providers take a client from [`http_client`] for each request,
+//! which uses the proxy set with [`set_proxy`], so a change applies to the
+//! next request. The HuggingFace downloader builds its own client and only
+//! reads the standard proxy environment variables, so [`export_proxy_env`]
+//! sets those at startup, before any other thread runs; downloads pick up a
+//! change on the next start.
+//!
+//! A configured proxy that can't be used is never bypassed: after
+//! [`load_proxy`] finds one, [`http_client`] and [`check_proxy`] fail until
+//! a usable proxy is set, rather than connecting directly.
+//!
+//! [`download`] fetches documents imported from a URL, through the same
+//! proxy; [`crate::remote`] and [`crate::feeds`] read what they fetch the
+//! same way.
+
+use std::sync::RwLock;
+
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+
+use crate::config::ProxyConfig;
+use crate::storage::MARKDOWN_FILE_TYPE;
+
+/// Largest document [`download`] accepts.
+pub const MAX_DOWNLOAD_BYTES: usize = 256 * 1024 * 1024;
+
+/// How outgoing requests connect.
+enum Route {
+    /// reqwest's default: the proxy environment variables, if any.
+    Default,
+    /// Through the proxy from settings.
+    Proxy(reqwest::Client),
+    /// The proxy from settings can't be used, so nothing connects.
+    Unusable(String),
+}
+
+static ROUTE: RwLock<Route> = RwLock::new(Route::Default);
+
+/// Route clients handed out from now on through `proxy`. Fails, keeping
+/// the previous route, if the proxy can't be used.
+pub fn set_proxy(proxy: Option<ProxyConfig>) -> Result<()> {
+    let route = match proxy {
+        Some(ref proxy) => Route::Proxy(build_client(proxy)?),
+        None => Route::Default,
+    };
+    *ROUTE.write().unwrap_or_else(|e| e.into_inner()) = route;
+    Ok(())
+}
+
+/// Route through the proxy saved in settings, as at startup. If it can't
+/// be used, requests fail until [`set_proxy`] is given one that can.
+pub fn load_proxy(proxy: Option<ProxyConfig>) -> Result<()> {
+    set_proxy(proxy).inspect_err(|err| {
+        *ROUTE.write().unwrap_or_else(|e| e.into_inner()) = Route::Unusable(format!("{:#}", err));
+    })
+}
+
+/// Fails if a proxy is configured but can't be used.
+pub fn check_proxy() -> Result<()> {
+    http_client().map(|_| ())
+}
+
+/// An HTTP client going through the configured proxy. Fails if that proxy
+/// can't be used.
+pub fn http_client() -> Result<reqwest::Client> {
+    match &*ROUTE.read().unwrap_or_else(|e| e.into_inner()) {
+        Route::Default => Ok(reqwest::Client::default()),
+        Route::Proxy(client) => Ok(client.clone()),
+        Route::Unusable(error) => bail!("The proxy in settings can't be used: {}", error),
+    }
+}
+
+fn build_client(proxy: &ProxyConfig) -> Result<reqwest::Client> {
+    proxy.validate()?;
+    let mut route = reqwest::Proxy::all(&proxy.url)?;
+    if let Some(ref hosts) = proxy.no_proxy {
+        route = route.no_proxy(reqwest::NoProxy::from_string(hosts));
+    }
+    Ok(reqwest::Client::builder().proxy(route).build()?)
+}
+
+/// A document fetched by [`download`].
+#[derive(Debug)]
+pub struct Download {
+    /// Name from the last segment of the URL, with an extension matching
+    /// `file_type`.
+    pub file_name: String,
+    /// `application/pdf` or `text/markdown`, sniffed from the content.
+    pub file_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+/// Download a PDF or Markdown/text document from an http(s) URL. Fails if
+/// it is larger than [`MAX_DOWNLOAD_BYTES`] or isn't a type that can be
+/// imported, such as an HTML page.
+pub async fn download(url: &str) -> Result<Download> {
+    let parsed = reqwest::Url::parse(url).context("Invalid URL")?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        bail!("Only http and https URLs can be imported: {}", url);
+    }
+
+    let response = http_client()?
+        .get(parsed)
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch {}", url))?
+        .error_for_status()?;
+    read_document(response, url).await
+}
+
+/// Read the document in a successful `response` as [`download`] does.
+/// `url` names it in errors.
+pub(crate) async fn read_document(response: reqwest::Response, url: &str) -> Result<Download> {
+    read_body(response, url).await?.into_document(url)
+}
+
+/// A response body of any type, read by [`read_body`].
+pub(crate) struct Body {
+    /// Without parameters, lowercased.
+    pub content_type: Option<String>,
+    /// After redirects.
+    pub url: reqwest::Url,
+    pub bytes: Vec<u8>,
+}
+
+impl Body {
+    /// The body as a document, if it is a type that can be imported.
+    pub fn into_document(self, url: &str) -> Result<Download> {
+        let Some(file_type) = sniff(&self.bytes, self.content_type.as_deref(), self.url.path())
+        else {
+            bail!(
+                "{} is not a PDF or text document ({})",
+                url,
+                self.content_type.as_deref().unwrap_or("unknown type")
+            );
+        };
+        Ok(Download {
+            file_name: file_name(&self.url, file_type),
+            file_type,
+            bytes: self.bytes,
+        })
+    }
+}
+
+/// Read a successful `response`, failing if it is larger than
+/// [`MAX_DOWNLOAD_BYTES`]. `url` names it in errors.
+pub(crate) async fn read_body(response: reqwest::Response, url: &str) -> Result<Body> {
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_DOWNLOAD_BYTES as u64)
+    {
+        bail!("{} is larger than {} MB", url, MAX_DOWNLOAD_BYTES >> 20);
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| {
+            v.split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+        });
+    // After redirects, so the name comes from the file actually served.
+    let final_url = response.url().clone();
+
+    // Content-Length can be missing or wrong, so the limit is also
+    // enforced while reading.
+    let mut bytes = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.with_context(|| format!("Failed to download {}", url))?;
+        if bytes.len() + chunk.len() > MAX_DOWNLOAD_BYTES {
+            bail!("{} is larger than {} MB", url, MAX_DOWNLOAD_BYTES >> 20);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Body {
+        content_type,
+        url: final_url,
+        bytes,
+    })
+}
+
+/// Type of downloaded `bytes`. The content decides for PDFs, since servers
+/// often label them `application/octet-stream`; text is only taken when
+/// the server or the URL says it is text, which keeps HTML pages out.
+pub(crate) fn sniff(bytes: &[u8], content_type: Option<&str>, path: &str) -> Option<&'static str> {
+    // The header may follow a little junk, as in some generated PDFs.
+    let head = &bytes[..bytes.len().min(1024)];
+    if head.windows(5).any(|w| w == b"%PDF-") {
+        return Some("application/pdf");
+    }
+    let path = path.to_ascii_lowercase();
+    let is_text = matches!(
+        content_type,
+        Some("text/markdown" | "text/x-markdown" | "text/plain")
+    ) || [".md", ".markdown", ".txt"]
+        .iter()
+        .any(|ext| path.ends_with(ext));
+    (is_text && std::str::from_utf8(bytes).is_ok()).then_some(MARKDOWN_FILE_TYPE)
+}
+
+/// File name for a download: the URL's last path segment, or its host,
+/// given an extension for `file_type` if it has none.
+fn file_name(url: &reqwest::Url, file_type: &str) -> String {
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.rfind(|s| !s.is_empty()))
+        .map(percent_decode)
+        .or_else(|| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "download".to_string());
+    let ext = if file_type == MARKDOWN_FILE_TYPE {
+        "md"
+    } else {
+        "pdf"
+    };
+    if std::path::Path::new(&name).extension().is_some() {
+        name
+    } else {
+        format!("{}.{}", name, ext)
+    }
+}
+
+/// A file name from a title, without characters file systems reject.
+pub(crate) fn file_stem(title: &str) -> String {
+    let stem: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(100)
+        .collect();
+    stem.trim().to_string()
+}
+
+pub(crate) fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Set the proxy environment variables to `proxy`, for libraries that
+/// build their own clients. Only call from `main`, before any runtime or
+/// other thread starts: changing the environment races with threads that
+/// read it.
+pub fn export_proxy_env(proxy: &ProxyConfig) -> Result<()> {
+    proxy.validate()?;
+    for name in ["HTTP_PROXY", "HTTPS_PROXY", "ALL_PROXY"] {
+        std::env::set_var(name, &proxy.url);
+    }
+    if let Some(ref hosts) = proxy.no_proxy {
+        std::env::set_var("NO_PROXY", hosts);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_client_with_proxy() {
+        let proxy = ProxyConfig {
+            url: "socks5h://127.0.0.1:1080".to_string(),
+            no_proxy: Some("localhost,.internal".to_string()),
+        };
+        assert!(build_client(&proxy).is_ok());
+
+        let invalid = ProxyConfig {
+            url: "not a url".to_string(),
+            no_proxy: None,
+        };
+        assert!(build_client(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_unusable_proxy_is_never_bypassed() {
+        let invalid = ProxyConfig {
+            url: "not a url".to_string(),
+            no_proxy: None,
+        };
+        assert!(load_proxy(Some(invalid)).is_err());
+        assert!(http_client().is_err());
+        assert!(check_proxy().is_err());
+
+        set_proxy(None).unwrap();
+        assert!(http_client().is_ok());
+    }
+
+    #[test]
+    fn test_sniff_and_name_downloads() {
+        let pdf = b"%PDF-1.7\n...";
+        assert_eq!(
+            sniff(pdf, Some("application/octet-stream"), "/get"),
+            Some("application/pdf")
+        );
+        assert_eq!(
+            sniff(b"# Notes", Some("text/plain"), "/notes"),
+            Some(MARKDOWN_FILE_TYPE)
+        );
+        assert_eq!(sniff(b"<html></html>", Some("text/html"), "/page"), None);
+
+        let url = reqwest::Url::parse("https://example.com/papers/Annual%20Report").unwrap();
+        assert_eq!(file_name(&url, "application/pdf"), "Annual Report.pdf");
+        let url = reqwest::Url::parse("https://example.com/notes/").unwrap();
+        assert_eq!(file_name(&url, MARKDOWN_FILE_TYPE), "notes.md");
+    }
+}
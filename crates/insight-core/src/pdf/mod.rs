@@ -1,6 +1,8 @@
 mod extractor;
+mod writer;
 
 pub use extractor::{
     char_offset_to_page, extract_text, extract_text_from_bytes, rasterize_page, ExtractedDocument,
     OcrTask, PageDecision, PageExtraction, DIGITAL_TEXT_THRESHOLD, RASTER_DPI,
 };
+pub use writer::text_pdf;
@@ -0,0 +1,180 @@
+//! Plain-text PDF output.
+//!
+//! Writes text as Helvetica on US Letter pages, wrapping long lines and
+//! starting a new PDF page for every source page. Characters outside the
+//! standard font's Latin-1 range are written as `?`.
+
+use anyhow::{Context, Result};
+use lopdf::{dictionary, Document, Object, Stream};
+
+const PAGE_WIDTH: i64 = 612;
+const PAGE_HEIGHT: i64 = 792;
+const MARGIN: i64 = 54;
+const FONT_SIZE: i64 = 10;
+const LEADING: i64 = 13;
+
+/// Characters per line. Helvetica averages about half an em per
+/// character, so this fills the text width without overflowing it.
+const LINE_CHARS: usize = 96;
+
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT - 2 * MARGIN) / LEADING) as usize;
+
+/// A PDF of `pages`, one or more PDF pages each, titled `title`.
+pub fn text_pdf(title: &str, pages: &[String]) -> Result<Vec<u8>> {
+    let mut doc = Document::with_version("1.4");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+
+    let mut kids: Vec<Object> = Vec::new();
+    for page in pages {
+        let lines = wrap(page);
+        // A blank source page still gets a PDF page, so numbering holds.
+        let chunks: Vec<&[String]> = if lines.is_empty() {
+            vec![&[]]
+        } else {
+            lines.chunks(LINES_PER_PAGE).collect()
+        };
+        for chunk in chunks {
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content(chunk)));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+                "Resources" => resources_id,
+                "Contents" => content_id,
+            });
+            kids.push(page_id.into());
+        }
+    }
+
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    let info_id = doc.add_object(dictionary! {
+        "Title" => Object::string_literal(encode(title)),
+        "Producer" => Object::string_literal("Insight"),
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.trailer.set("Info", info_id);
+
+    let mut buffer = Vec::new();
+    doc.save_to(&mut buffer).context("Failed to write PDF")?;
+    Ok(buffer)
+}
+
+/// Lines of `text` at most [`LINE_CHARS`] long, broken at spaces where
+/// possible. Trailing blank lines are dropped.
+fn wrap(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let mut current = String::new();
+        for word in line.split(' ') {
+            let fits = current.chars().count() + 1 + word.chars().count() <= LINE_CHARS;
+            if !current.is_empty() && !fits {
+                lines.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+            while current.chars().count() > LINE_CHARS {
+                let cut = current
+                    .char_indices()
+                    .nth(LINE_CHARS)
+                    .map_or(current.len(), |(i, _)| i);
+                let rest = current.split_off(cut);
+                lines.push(std::mem::replace(&mut current, rest));
+            }
+        }
+        lines.push(current);
+    }
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// The content stream showing `lines` from the top margin down.
+fn content(lines: &[String]) -> Vec<u8> {
+    let mut out = format!(
+        "BT /F1 {} Tf {} TL {} {} Td\n",
+        FONT_SIZE,
+        LEADING,
+        MARGIN,
+        PAGE_HEIGHT - MARGIN - FONT_SIZE
+    )
+    .into_bytes();
+    for line in lines {
+        out.push(b'(');
+        for byte in encode(line) {
+            if matches!(byte, b'(' | b')' | b'\\') {
+                out.push(b'\\');
+            }
+            out.push(byte);
+        }
+        out.extend_from_slice(b") Tj T*\n");
+    }
+    out.extend_from_slice(b"ET");
+    out
+}
+
+/// `text` in the font's encoding: Latin-1 as is, tabs as spaces, anything
+/// else as `?`.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '\t' => b' ',
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_breaks_long_lines() {
+        let long = vec!["word"; 40].join(" ");
+        let lines = wrap(&format!("Short line\n{}\n\n", long));
+        assert_eq!(lines[0], "Short line");
+        assert!(lines[1..].iter().all(|l| l.chars().count() <= LINE_CHARS));
+        assert_eq!(lines[1..].join(" "), long);
+
+        let unbroken = "x".repeat(LINE_CHARS + 5);
+        assert_eq!(wrap(&unbroken).len(), 2);
+    }
+
+    #[test]
+    fn test_text_pdf_round_trips() {
+        let pages = vec![
+            "Paid by [NAME 1] (see note).".to_string(),
+            String::new(),
+            "Café".to_string(),
+        ];
+        let bytes = text_pdf("Memo (redacted)", &pages).unwrap();
+        let doc = Document::load_mem(&bytes).unwrap();
+        assert_eq!(doc.get_pages().len(), 3);
+        let text = doc.extract_text(&[1]).unwrap();
+        assert!(text.contains("Paid by [NAME 1] (see note)."));
+    }
+}
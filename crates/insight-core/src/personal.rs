@@ -0,0 +1,655 @@
+//! Settings, saved searches and conversations shared between a user's own
+//! devices.
+//!
+//! They go in a personal space: an iroh doc like a collection's, shared
+//! only through write tickets the user takes to their other devices. Each
+//! file is an entry named after it — `settings.json`,
+//! `saved_searches/{collection_id}.json`, `conversations/{id}.json` — and
+//! [`PersonalSync`] reconciles the files with the entries every
+//! [`SYNC_INTERVAL`] and as entries arrive. Remembering what each one was
+//! when last in sync, it can tell which side changed; if both did, the
+//! later write wins.
+//!
+//! Only the preferences in [`SHARED_SETTINGS`] go: secrets, models,
+//! paths, hardware and sharing stay on the machine they were set on, as
+//! does any setting not listed. Settings read once at startup take effect
+//! on the next one.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use iroh_blobs::Hash;
+use iroh_docs::NamespaceId;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::{Config, Settings};
+use crate::storage::{LiveEvent, Storage};
+
+/// How often local files are checked for changes to send.
+pub const SYNC_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How long changes from other devices are gathered before applying them.
+const SETTLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Settings shared with the user's other devices. The rest are kept to
+/// this one, so a new setting only syncs once it's added here.
+pub const SHARED_SETTINGS: &[&str] = &[
+    "prompt_presets",
+    "always_allowed_tools",
+    "agent_limits",
+    "verify_answers",
+    "cross_collection_duplicates",
+    "chunking",
+    "monthly_budget_usd",
+];
+
+const SETTINGS_KEY: &str = "settings.json";
+const SAVED_SEARCHES_PREFIX: &str = "saved_searches/";
+const CONVERSATIONS_PREFIX: &str = "conversations/";
+
+/// Whether this device syncs with the user's others.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersonalSyncStatus {
+    /// The personal space, if syncing.
+    pub namespace_id: Option<String>,
+    /// When files and entries were last reconciled, RFC 3339.
+    pub last_synced: Option<String>,
+}
+
+/// What each key was when last in sync, for the personal space it was
+/// synced with.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    namespace_id: String,
+    /// Content hash by key.
+    synced: BTreeMap<String, String>,
+}
+
+impl SyncState {
+    /// The state for `namespace_id`; empty if none was saved or it was
+    /// for another space.
+    fn load(path: &Path, namespace_id: NamespaceId) -> Self {
+        let namespace_id = namespace_id.to_string();
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<SyncState>(&content).ok())
+            .filter(|state| state.namespace_id == namespace_id)
+            .unwrap_or(SyncState {
+                namespace_id,
+                synced: BTreeMap::new(),
+            })
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content).context("Failed to write personal sync state")
+    }
+
+    fn base(&self, key: &str) -> Option<Hash> {
+        self.synced.get(key).and_then(|hash| hash.parse().ok())
+    }
+
+    fn record(&mut self, key: &str, hash: Option<Hash>) {
+        match hash {
+            Some(hash) => self.synced.insert(key.to_string(), hash.to_string()),
+            None => self.synced.remove(key),
+        };
+    }
+}
+
+/// What to do with a key whose file and entry may differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    InSync,
+    /// Write the file to the entry.
+    Send,
+    /// Write the entry to the file.
+    Receive,
+}
+
+/// One side of a key: its content hash (`None` if absent or deleted) and
+/// when it was last written, in microseconds since the Unix epoch.
+#[derive(Debug, Clone, Copy)]
+struct Side {
+    hash: Option<Hash>,
+    modified: u64,
+}
+
+/// Compare a key's file and entry with what they were when last in sync.
+fn reconcile(local: Side, remote: Side, base: Option<Hash>) -> Action {
+    if local.hash == remote.hash {
+        Action::InSync
+    } else if local.hash == base {
+        Action::Receive
+    } else if remote.hash == base {
+        Action::Send
+    } else if local.modified > remote.modified {
+        Action::Send
+    } else {
+        Action::Receive
+    }
+}
+
+/// The file a key is stored in, for keys naming one of the files synced.
+/// Keys come from other devices, so anything else is refused.
+fn local_path(config: &Config, key: &str) -> Option<PathBuf> {
+    let file_name = |name: &str| {
+        let stem = name.strip_suffix(".json")?;
+        let valid = !stem.is_empty()
+            && stem
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| name.to_string())
+    };
+    if key == SETTINGS_KEY {
+        Some(config.settings_file.clone())
+    } else if let Some(name) = key.strip_prefix(SAVED_SEARCHES_PREFIX) {
+        Some(config.saved_searches_dir().join(file_name(name)?))
+    } else if let Some(name) = key.strip_prefix(CONVERSATIONS_PREFIX) {
+        Some(config.conversations_dir.join(file_name(name)?))
+    } else {
+        None
+    }
+}
+
+/// Settings as shared with other devices: only [`SHARED_SETTINGS`], with
+/// keys sorted so unchanged settings always hash the same.
+fn shared_settings(settings: &Settings) -> Result<Vec<u8>> {
+    let mut value = serde_json::to_value(settings)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.retain(|field, _| SHARED_SETTINGS.contains(&field.as_str()));
+    }
+    Ok(serde_json::to_vec_pretty(&value)?)
+}
+
+/// `local` with the [`SHARED_SETTINGS`] another device shared.
+fn merge_settings(local: &Settings, shared: &[u8]) -> Result<Settings> {
+    let shared: serde_json::Value =
+        serde_json::from_slice(shared).context("Failed to parse shared settings")?;
+    let mut merged = serde_json::to_value(local)?;
+    if let (Some(merged), Some(shared)) = (merged.as_object_mut(), shared.as_object()) {
+        for (field, value) in shared {
+            if SHARED_SETTINGS.contains(&field.as_str()) {
+                merged.insert(field.clone(), value.clone());
+            }
+        }
+    }
+    serde_json::from_value(merged).context("Failed to apply shared settings")
+}
+
+fn micros(time: SystemTime) -> u64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+/// A file synced: what would be written to its entry, and when it changed.
+struct LocalFile {
+    bytes: Vec<u8>,
+    modified: u64,
+}
+
+/// The files synced, by key.
+fn local_files(config: &Config) -> Result<BTreeMap<String, LocalFile>> {
+    let mut files = BTreeMap::new();
+    if let Ok(metadata) = std::fs::metadata(&config.settings_file) {
+        let settings = Settings::load(&config.settings_file);
+        files.insert(
+            SETTINGS_KEY.to_string(),
+            LocalFile {
+                bytes: shared_settings(&settings)?,
+                modified: metadata.modified().map(micros).unwrap_or_default(),
+            },
+        );
+    }
+    for (prefix, dir) in [
+        (SAVED_SEARCHES_PREFIX, config.saved_searches_dir()),
+        (CONVERSATIONS_PREFIX, config.conversations_dir.clone()),
+    ] {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", dir)),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let key = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            if local_path(config, &key).is_none() {
+                continue;
+            }
+            files.insert(
+                key,
+                LocalFile {
+                    bytes: std::fs::read(entry.path())?,
+                    modified: entry.metadata()?.modified().map(micros).unwrap_or_default(),
+                },
+            );
+        }
+    }
+    Ok(files)
+}
+
+/// Write what another device shared under `key` to its file, or remove the
+/// file if they deleted it.
+fn write_local(config: &Config, key: &str, bytes: Option<&[u8]>) -> Result<()> {
+    let path = local_path(config, key).context("Not a synced file")?;
+    if key == SETTINGS_KEY {
+        // Settings are never deleted, only left alone.
+        if let Some(bytes) = bytes {
+            let settings = merge_settings(&Settings::load(&config.settings_file), bytes)?;
+            settings.save(&config.settings_file)?;
+        }
+        return Ok(());
+    }
+    match bytes {
+        Some(bytes) => {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&path, bytes)?;
+        }
+        None => match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        },
+    }
+    Ok(())
+}
+
+/// Reconcile the files synced with the personal space's entries. Returns
+/// how many keys changed on either side.
+async fn sync_once(
+    storage: &RwLock<Storage>,
+    config: &Config,
+    namespace_id: NamespaceId,
+) -> Result<usize> {
+    let state_file = config.personal_sync_file();
+    let mut state = SyncState::load(&state_file, namespace_id);
+    let local = local_files(config)?;
+    let storage = storage.read().await;
+    let remote: BTreeMap<String, _> = storage
+        .latest_entries(namespace_id)
+        .await?
+        .into_iter()
+        .filter(|entry| local_path(config, &entry.key).is_some())
+        .map(|entry| (entry.key.clone(), entry))
+        .collect();
+
+    let keys: BTreeSet<&String> = local.keys().chain(remote.keys()).collect();
+    let mut changed = 0;
+    for key in keys {
+        let file = local.get(key);
+        let entry = remote.get(key);
+        let local_side = Side {
+            hash: file.map(|f| Hash::new(&f.bytes)),
+            modified: file.map(|f| f.modified).unwrap_or_default(),
+        };
+        let remote_side = Side {
+            hash: entry.filter(|e| e.len > 0).map(|e| e.hash),
+            modified: entry.map(|e| e.timestamp).unwrap_or_default(),
+        };
+
+        let agreed = match reconcile(local_side, remote_side, state.base(key)) {
+            Action::InSync => local_side.hash,
+            Action::Send => {
+                match file {
+                    Some(file) => storage.put_entry(namespace_id, key, &file.bytes).await?,
+                    None => storage.delete_entry(namespace_id, key).await?,
+                }
+                changed += 1;
+                local_side.hash
+            }
+            Action::Receive => {
+                let bytes = match remote_side.hash {
+                    Some(hash) => match storage.get_blob(&hash).await? {
+                        Some(bytes) => Some(bytes),
+                        // Not downloaded yet; it's picked up once it is.
+                        None => continue,
+                    },
+                    None => None,
+                };
+                if let Err(e) = write_local(config, key, bytes.as_deref()) {
+                    tracing::warn!(key = %key, error = %e, "Failed to apply synced file");
+                    continue;
+                }
+                changed += 1;
+                remote_side.hash
+            }
+        };
+        state.record(key, agreed);
+    }
+    state.save(&state_file)?;
+    Ok(changed)
+}
+
+/// Syncing with the personal space, while one is set. Clones share state.
+#[derive(Clone)]
+pub struct PersonalSync {
+    storage: Arc<RwLock<Storage>>,
+    config: Config,
+    running: Arc<Mutex<Option<CancellationToken>>>,
+    last_synced: Arc<Mutex<Option<String>>>,
+}
+
+impl PersonalSync {
+    pub fn new(storage: Arc<RwLock<Storage>>, config: Config) -> Self {
+        Self {
+            storage,
+            config,
+            running: Arc::new(Mutex::new(None)),
+            last_synced: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The personal space in settings, if any.
+    fn namespace_id(&self) -> Option<NamespaceId> {
+        Settings::load(&self.config.settings_file)
+            .personal_sync
+            .and_then(|id| id.parse().ok())
+    }
+
+    /// Start syncing with the personal space in settings, if there is one.
+    pub fn resume(&self) {
+        if let Some(namespace_id) = self.namespace_id() {
+            self.start(namespace_id);
+        }
+    }
+
+    /// Start syncing this device's files with a personal space, creating
+    /// one if there is none yet. Returns the ticket other devices join
+    /// with; it grants write access, so it is for this user only.
+    pub async fn enable(&self) -> Result<String> {
+        let namespace_id = match self.namespace_id() {
+            Some(namespace_id) => namespace_id,
+            None => {
+                let namespace_id = self.storage.read().await.create_personal_space().await?;
+                self.set_namespace_id(Some(namespace_id))?;
+                self.start(namespace_id);
+                namespace_id
+            }
+        };
+        self.storage
+            .read()
+            .await
+            .share_collection(namespace_id, true)
+            .await
+    }
+
+    /// Sync with the personal space another device's ticket shares,
+    /// instead of any this device had.
+    pub async fn join(&self, ticket: &str) -> Result<()> {
+        let namespace_id = self
+            .storage
+            .read()
+            .await
+            .join_personal_space(ticket)
+            .await?;
+        if let Some(previous) = self.namespace_id().filter(|id| *id != namespace_id) {
+            self.stop();
+            self.storage
+                .read()
+                .await
+                .delete_collection(previous)
+                .await?;
+        }
+        self.set_namespace_id(Some(namespace_id))?;
+        self.start(namespace_id);
+        Ok(())
+    }
+
+    /// Stop syncing with other devices and remove this device's copy of
+    /// the personal space. Files stay as they are.
+    pub async fn disable(&self) -> Result<()> {
+        self.stop();
+        if let Some(namespace_id) = self.namespace_id() {
+            self.storage
+                .read()
+                .await
+                .delete_collection(namespace_id)
+                .await?;
+        }
+        self.set_namespace_id(None)?;
+        match std::fs::remove_file(self.config.personal_sync_file()) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> PersonalSyncStatus {
+        PersonalSyncStatus {
+            namespace_id: self.namespace_id().map(|id| id.to_string()),
+            last_synced: self.last_synced.lock().unwrap().clone(),
+        }
+    }
+
+    fn set_namespace_id(&self, namespace_id: Option<NamespaceId>) -> Result<()> {
+        let mut settings = Settings::load(&self.config.settings_file);
+        settings.personal_sync = namespace_id.map(|id| id.to_string());
+        settings.save(&self.config.settings_file)?;
+        Ok(())
+    }
+
+    fn start(&self, namespace_id: NamespaceId) {
+        let cancel = CancellationToken::new();
+        if let Some(previous) = self.running.lock().unwrap().replace(cancel.clone()) {
+            previous.cancel();
+        }
+        tokio::spawn(run(
+            self.storage.clone(),
+            self.config.clone(),
+            namespace_id,
+            self.last_synced.clone(),
+            cancel,
+        ));
+    }
+
+    fn stop(&self) {
+        if let Some(cancel) = self.running.lock().unwrap().take() {
+            cancel.cancel();
+        }
+    }
+}
+
+/// Reconcile when local files may have changed and when other devices
+/// wrote entries, until cancelled.
+async fn run(
+    storage: Arc<RwLock<Storage>>,
+    config: Config,
+    namespace_id: NamespaceId,
+    last_synced: Arc<Mutex<Option<String>>>,
+    cancel: CancellationToken,
+) {
+    let subscribed = {
+        let storage = storage.read().await;
+        // Reconnect with the devices synced with before.
+        match storage.set_sync_enabled(namespace_id, true).await {
+            Ok(()) => storage.subscribe(namespace_id).await,
+            Err(e) => Err(e),
+        }
+    };
+    let events = match subscribed {
+        Ok(events) => events,
+        Err(e) => {
+            tracing::warn!(namespace = %namespace_id, error = %e, "Failed to start personal sync");
+            return;
+        }
+    };
+    tokio::pin!(events);
+    tracing::info!(namespace = %namespace_id, "Personal sync started");
+
+    let mut rescan = tokio::time::interval(SYNC_INTERVAL);
+    let mut settle = tokio::time::interval(SETTLE_INTERVAL);
+    let mut dirty = false;
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = rescan.tick() => dirty = true,
+            event = events.next() => match event {
+                Some(Ok(LiveEvent::InsertRemote { .. } | LiveEvent::ContentReady { .. })) => {
+                    dirty = true;
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    tracing::warn!(
+                        namespace = %namespace_id,
+                        error = %e,
+                        "Personal sync event error"
+                    );
+                }
+                None => break,
+            },
+            _ = settle.tick() => {
+                if !std::mem::take(&mut dirty) {
+                    continue;
+                }
+                match sync_once(&storage, &config, namespace_id).await {
+                    Ok(changed) => {
+                        if changed > 0 {
+                            tracing::info!(changed, "Synced with other devices");
+                        }
+                        *last_synced.lock().unwrap() = Some(chrono::Utc::now().to_rfc3339());
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            namespace = %namespace_id,
+                            error = %e,
+                            "Personal sync failed"
+                        );
+                    }
+                }
+            }
+        }
+    }
+    tracing::info!(namespace = %namespace_id, "Personal sync stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn side(content: Option<&[u8]>, modified: u64) -> Side {
+        Side {
+            hash: content.map(Hash::new),
+            modified,
+        }
+    }
+
+    #[test]
+    fn test_reconcile_sends_and_receives_what_changed() {
+        let (old, new) = (Some(Hash::new(b"old")), Some(b"new".as_slice()));
+        let old_content = Some(b"old".as_slice());
+
+        // Unchanged on both sides
+        assert_eq!(
+            reconcile(side(old_content, 1), side(old_content, 2), old),
+            Action::InSync
+        );
+        // Edited or deleted here
+        assert_eq!(
+            reconcile(side(new, 1), side(old_content, 2), old),
+            Action::Send
+        );
+        assert_eq!(
+            reconcile(side(None, 0), side(old_content, 2), old),
+            Action::Send
+        );
+        // Edited or deleted on another device
+        assert_eq!(
+            reconcile(side(old_content, 9), side(new, 2), old),
+            Action::Receive
+        );
+        assert_eq!(
+            reconcile(side(old_content, 9), side(None, 2), old),
+            Action::Receive
+        );
+        // New on either side
+        assert_eq!(reconcile(side(new, 1), side(None, 0), None), Action::Send);
+        assert_eq!(
+            reconcile(side(None, 0), side(new, 1), None),
+            Action::Receive
+        );
+        // Edited on both: the later write wins
+        let other = Some(b"other".as_slice());
+        assert_eq!(reconcile(side(new, 5), side(other, 3), old), Action::Send);
+        assert_eq!(
+            reconcile(side(new, 3), side(other, 5), old),
+            Action::Receive
+        );
+    }
+
+    #[test]
+    fn test_only_synced_files_have_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            data_dir: dir.path().to_path_buf(),
+            iroh_dir: dir.path().join("iroh"),
+            search_dir: dir.path().join("search"),
+            conversations_dir: dir.path().join("conversations"),
+            settings_file: dir.path().join("settings.json"),
+        };
+
+        assert_eq!(
+            local_path(&config, "settings.json"),
+            Some(config.settings_file.clone())
+        );
+        assert_eq!(
+            local_path(&config, "conversations/abc-123.json"),
+            Some(config.conversations_dir.join("abc-123.json"))
+        );
+        assert!(local_path(&config, "saved_searches/abc.json").is_some());
+        assert!(local_path(&config, "conversations/../secrets.json").is_none());
+        assert!(local_path(&config, "conversations/abc.txt").is_none());
+        assert!(local_path(&config, "secrets.json").is_none());
+    }
+
+    #[test]
+    fn test_only_shared_settings_sync() {
+        let local = Settings {
+            display_name: Some("Laptop".to_string()),
+            monthly_budget_usd: Some(5.0),
+            embedding_model_id: Some("laptop-model".to_string()),
+            personal_sync: Some("space".to_string()),
+            ..Default::default()
+        };
+        let mut other = Settings {
+            display_name: Some("Desktop".to_string()),
+            embedding_model_id: Some("desktop-model".to_string()),
+            paused_sync: vec!["collection".to_string()],
+            personal_sync: Some("space".to_string()),
+            ..Default::default()
+        };
+        other.api_keys.insert(
+            "openai".to_string(),
+            crate::secrets::SecretRef {
+                name: "openai".to_string(),
+                backend: crate::secrets::SecretBackend::Keychain,
+            },
+        );
+
+        let shared = shared_settings(&other).unwrap();
+        let text = String::from_utf8(shared.clone()).unwrap();
+        assert!(!text.contains("api_keys"));
+        assert!(!text.contains("display_name"));
+        assert!(!text.contains("embedding_model_id"));
+        assert!(!text.contains("paused_sync"));
+        assert!(!text.contains("personal_sync"));
+        assert_eq!(shared_settings(&other).unwrap(), shared);
+
+        let merged = merge_settings(&local, &shared).unwrap();
+        assert_eq!(merged.display_name.as_deref(), Some("Laptop"));
+        assert_eq!(merged.monthly_budget_usd, None);
+        assert_eq!(merged.embedding_model_id.as_deref(), Some("laptop-model"));
+        assert!(merged.paused_sync.is_empty());
+        assert_eq!(merged.personal_sync.as_deref(), Some("space"));
+        assert!(merged.api_keys.is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+//! Chunking strategies applied before embedding.
+//!
+//! [`ChunkStrategy::Tokens`] is the embedder's own tokenizer-aware split.
+//! [`ChunkStrategy::Semantic`] first groups sentences by topic, using the
+//! same embedder to compare neighbouring sentences, then token-splits any
+//! group that is still over budget. [`ChunkStrategy::Page`] token-splits
+//! each page separately using the document's `page_boundaries`.
+
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::manager::ModelManager;
+use crate::provider::{ChunkStrategy, ChunkingConfig, EmbeddingProvider};
+
+/// Sentences embedded per call during the semantic grouping pass.
+const SENTENCE_BATCH: usize = 64;
+
+/// Neighbouring sentences whose distance falls in the top
+/// `100 - BREAKPOINT_PERCENTILE` percent of a document start a new group.
+/// Percentile rather than absolute threshold so it adapts per model and
+/// per document.
+const BREAKPOINT_PERCENTILE: f32 = 90.0;
+
+/// Split `text` into chunks according to `config.strategy`.
+///
+/// Chunks are always substrings of `text` so callers can locate them by
+/// offset. `page_boundaries` are the byte offsets where each page ends
+/// (see `DocumentMetadata`). `max_chunks_per_doc` is left to the caller.
+pub async fn chunk_document(
+    embedder: &dyn EmbeddingProvider,
+    text: &str,
+    page_boundaries: &[usize],
+    config: &ChunkingConfig,
+    models: &ModelManager,
+) -> anyhow::Result<Vec<String>> {
+    match config.strategy {
+        ChunkStrategy::Tokens => embedder.chunk_text(text, config).await,
+        ChunkStrategy::Semantic => semantic_chunks(embedder, text, config, models).await,
+        ChunkStrategy::Page => page_chunks(embedder, text, page_boundaries, config).await,
+    }
+}
+
+/// Token-split each page independently. Documents without page
+/// boundaries (non-PDF text) fall back to a whole-document split.
+async fn page_chunks(
+    embedder: &dyn EmbeddingProvider,
+    text: &str,
+    page_boundaries: &[usize],
+    config: &ChunkingConfig,
+) -> anyhow::Result<Vec<String>> {
+    if page_boundaries.is_empty() {
+        return embedder.chunk_text(text, config).await;
+    }
+
+    let mut chunks = Vec::new();
+    for range in page_ranges(text, page_boundaries) {
+        chunks.extend(embedder.chunk_text(&text[range], config).await?);
+    }
+    Ok(chunks)
+}
+
+/// Byte ranges of each page. Boundaries past the end of `text` or off a
+/// char boundary (stale metadata) are clamped rather than trusted; any
+/// text after the last boundary is treated as a final page.
+fn page_ranges(text: &str, page_boundaries: &[usize]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::with_capacity(page_boundaries.len() + 1);
+    let mut start = 0;
+    for &boundary in page_boundaries {
+        let mut end = boundary.min(text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end > start {
+            ranges.push(start..end);
+            start = end;
+        }
+    }
+    if start < text.len() {
+        ranges.push(start..text.len());
+    }
+    ranges
+}
+
+/// Group sentences at topic shifts, then token-split oversized groups.
+/// Overlap only applies inside a group that needed splitting — topic
+/// boundaries are clean cuts.
+async fn semantic_chunks(
+    embedder: &dyn EmbeddingProvider,
+    text: &str,
+    config: &ChunkingConfig,
+    models: &ModelManager,
+) -> anyhow::Result<Vec<String>> {
+    let spans = sentence_spans(text);
+    if spans.len() < 2 {
+        return embedder.chunk_text(text, config).await;
+    }
+
+    let sentences: Vec<&str> = spans.iter().map(|r| text[r.clone()].trim()).collect();
+    let mut vectors = Vec::with_capacity(sentences.len());
+    for batch in sentences.chunks(SENTENCE_BATCH) {
+        vectors.extend(embedder.embed_documents(batch).await?);
+        models.touch_embedding();
+    }
+
+    let breaks = semantic_breakpoints(&vectors, BREAKPOINT_PERCENTILE);
+
+    let mut chunks = Vec::new();
+    let mut group_start = 0;
+    for group_end in breaks.into_iter().chain(std::iter::once(spans.len())) {
+        let byte_range = spans[group_start].start..spans[group_end - 1].end;
+        chunks.extend(embedder.chunk_text(&text[byte_range], config).await?);
+        group_start = group_end;
+    }
+    Ok(chunks)
+}
+
+/// Byte ranges of the sentences in `text`. Whitespace-only segments
+/// (paragraph gaps) are folded into the preceding sentence so every span
+/// has content worth embedding.
+fn sentence_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans: Vec<Range<usize>> = Vec::new();
+    for (start, sentence) in text.split_sentence_bound_indices() {
+        let end = start + sentence.len();
+        match spans.last_mut() {
+            Some(last) if sentence.trim().is_empty() => last.end = end,
+            _ if sentence.trim().is_empty() => {}
+            _ => spans.push(start..end),
+        }
+    }
+    spans
+}
+
+/// Indices of sentences that start a new group: those whose cosine
+/// distance from the previous sentence exceeds the given percentile of
+/// all neighbour distances in the document.
+fn semantic_breakpoints(vectors: &[Vec<f32>], percentile: f32) -> Vec<usize> {
+    if vectors.len() < 2 {
+        return Vec::new();
+    }
+
+    let distances: Vec<f32> = vectors
+        .windows(2)
+        .map(|pair| 1.0 - cosine_similarity(&pair[0], &pair[1]))
+        .collect();
+
+    let mut sorted = distances.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let rank = ((percentile / 100.0) * (sorted.len() - 1) as f32) as usize;
+    let threshold = sorted[rank.min(sorted.len() - 1)];
+
+    distances
+        .iter()
+        .enumerate()
+        .filter(|(_, &d)| d > threshold)
+        .map(|(i, _)| i + 1)
+        .collect()
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentence_spans_fold_whitespace() {
+        let text = "First sentence. Second one.\n\nThird here.";
+        let spans = sentence_spans(text);
+        let sentences: Vec<&str> = spans.iter().map(|r| &text[r.clone()]).collect();
+        assert_eq!(
+            sentences,
+            vec!["First sentence. ", "Second one.\n\n", "Third here."]
+        );
+        // Spans tile the text with no gaps.
+        assert_eq!(spans.last().unwrap().end, text.len());
+    }
+
+    #[test]
+    fn test_semantic_breakpoints_split_at_topic_shift() {
+        // Two topics: sentences 0-2 point one way, 3-5 another.
+        let a = vec![1.0, 0.0];
+        let a2 = vec![0.95, 0.05];
+        let b = vec![0.0, 1.0];
+        let b2 = vec![0.05, 0.95];
+        let vectors = vec![a.clone(), a2.clone(), a, b.clone(), b2, b];
+        assert_eq!(semantic_breakpoints(&vectors, 90.0), vec![3]);
+    }
+
+    #[test]
+    fn test_semantic_breakpoints_uniform_has_no_breaks() {
+        let v = vec![0.6, 0.8];
+        let vectors = vec![v.clone(), v.clone(), v.clone(), v];
+        assert!(semantic_breakpoints(&vectors, 90.0).is_empty());
+        assert!(semantic_breakpoints(&[vec![1.0]], 90.0).is_empty());
+    }
+
+    #[test]
+    fn test_page_ranges() {
+        let text = "page one\npage two\n\npage four\n";
+        // Page three is blank: its boundary equals page two's.
+        let boundaries = vec![9, 18, 18, 29];
+        let pages: Vec<&str> = page_ranges(text, &boundaries)
+            .into_iter()
+            .map(|r| &text[r])
+            .collect();
+        assert_eq!(pages, vec!["page one\n", "page two\n", "\npage four\n"]);
+    }
+
+    #[test]
+    fn test_page_ranges_clamps_stale_boundaries() {
+        let text = "héllo world";
+        // 2 is inside the two-byte 'é'; 100 is past the end.
+        let pages: Vec<&str> = page_ranges(text, &[2, 100])
+            .into_iter()
+            .map(|r| &text[r])
+            .collect();
+        assert_eq!(pages, vec!["h", "éllo world"]);
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+}
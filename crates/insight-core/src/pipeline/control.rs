@@ -0,0 +1,459 @@
+//! Pausing and cancelling imports.
+//!
+//! Every file an import queues is tracked here from the moment it is queued
+//! until it is indexed or fails, first by path and, once its source is
+//! stored, by document ID as well. Each gets a cancellation token, a child
+//! of its collection's token, so a whole collection or a single file can be
+//! stopped. Workers ask [`ImportControl::admit`] before starting a job:
+//! jobs for paused files are parked here until resumed, jobs for cancelled
+//! files are dropped, and running jobs race the token and stop when it
+//! fires.
+//!
+//! Documents not queued by an import this run (synced from peers,
+//! re-embedded, or resumed at startup) fall back to the collection: they
+//! follow its pause, and a cancel stops the one in flight.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use super::order::ImportPriority;
+use super::types::{EmbedJob, ExtractJob, IndexJob, OcrJob, Stage};
+
+/// A job held back while its file or collection is paused.
+#[derive(Debug)]
+pub enum ParkedJob {
+    Extract(ExtractJob),
+    Ocr(OcrJob),
+    Embed(EmbedJob),
+    Index(IndexJob),
+}
+
+impl ParkedJob {
+    pub fn doc_id(&self) -> &str {
+        match self {
+            Self::Extract(job) => &job.doc_id,
+            Self::Ocr(job) => &job.doc_id,
+            Self::Embed(job) => &job.doc_id,
+            Self::Index(job) => &job.doc_id,
+        }
+    }
+
+    pub fn collection_id(&self) -> String {
+        match self {
+            Self::Extract(job) => job.namespace_id.to_string(),
+            Self::Ocr(job) => job.namespace_id.to_string(),
+            Self::Embed(job) => job.namespace_id.to_string(),
+            Self::Index(job) => job.namespace_id.to_string(),
+        }
+    }
+
+    pub fn stage(&self) -> Stage {
+        match self {
+            Self::Extract(_) => Stage::Extract,
+            Self::Ocr(_) => Stage::Ocr,
+            Self::Embed(_) => Stage::Embed,
+            Self::Index(_) => Stage::Index,
+        }
+    }
+}
+
+/// What a worker should do with a job.
+pub enum Admission {
+    /// Run it, stopping if the token fires.
+    Run(CancellationToken),
+    /// Hand it to [`ImportControl::park`] and move on.
+    Park,
+    /// Drop it; the file was cancelled.
+    Drop,
+}
+
+/// A file queued by an import this run.
+struct TrackedFile {
+    doc_id: Option<String>,
+    cancel: CancellationToken,
+}
+
+#[derive(Default)]
+struct ControlState {
+    collections: HashMap<String, CancellationToken>,
+    paused_collections: HashSet<String>,
+    paused_files: HashSet<(String, PathBuf)>,
+    /// Files raised or lowered from normal priority, until stored.
+    priorities: HashMap<(String, PathBuf), ImportPriority>,
+    files: HashMap<(String, PathBuf), TrackedFile>,
+    /// Stored documents of tracked files, to their file.
+    docs: HashMap<String, (String, PathBuf)>,
+    /// Documents whose file was cancelled. Jobs already queued for them
+    /// are dropped when they come up.
+    cancelled_docs: HashSet<String>,
+    parked: Vec<ParkedJob>,
+    /// Stages whose output a linked document brought along from another
+    /// collection, skipped when their job comes up.
+    linked: HashMap<String, HashSet<Stage>>,
+}
+
+impl ControlState {
+    fn collection_token(&mut self, collection_id: &str) -> CancellationToken {
+        self.collections
+            .entry(collection_id.to_string())
+            .or_default()
+            .clone()
+    }
+
+    fn is_paused(&self, collection_id: &str, path: Option<&PathBuf>) -> bool {
+        self.paused_collections.contains(collection_id)
+            || path.is_some_and(|p| {
+                self.paused_files
+                    .contains(&(collection_id.to_string(), p.clone()))
+            })
+    }
+}
+
+/// Pause and cancel state for imports; see the module docs.
+#[derive(Default)]
+pub struct ImportControl {
+    state: Mutex<ControlState>,
+    resumed: Notify,
+}
+
+impl ImportControl {
+    /// Start tracking `paths` queued for `collection_id`. Returns one token
+    /// per path.
+    pub fn track(&self, collection_id: &str, paths: &[PathBuf]) -> Vec<CancellationToken> {
+        let mut state = self.state.lock().unwrap();
+        let parent = state.collection_token(collection_id);
+        paths
+            .iter()
+            .map(|path| {
+                let cancel = parent.child_token();
+                state.files.insert(
+                    (collection_id.to_string(), path.clone()),
+                    TrackedFile {
+                        doc_id: None,
+                        cancel: cancel.clone(),
+                    },
+                );
+                cancel
+            })
+            .collect()
+    }
+
+    /// Record the document `path` was stored as.
+    pub fn stored(&self, collection_id: &str, path: &Path, doc_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        let key = (collection_id.to_string(), path.to_path_buf());
+        state.priorities.remove(&key);
+        if let Some(file) = state.files.get_mut(&key) {
+            file.doc_id = Some(doc_id.to_string());
+            state.docs.insert(doc_id.to_string(), key);
+        }
+    }
+
+    /// Stop tracking a file that was never stored.
+    pub fn forget_file(&self, collection_id: &str, path: &Path) {
+        let mut state = self.state.lock().unwrap();
+        let key = (collection_id.to_string(), path.to_path_buf());
+        state.priorities.remove(&key);
+        state.files.remove(&key);
+    }
+
+    /// Set the priority `path` is stored with, if it hasn't been yet.
+    pub fn set_priority(&self, collection_id: &str, path: &Path, priority: ImportPriority) {
+        let mut state = self.state.lock().unwrap();
+        let key = (collection_id.to_string(), path.to_path_buf());
+        if priority.is_normal() {
+            state.priorities.remove(&key);
+        } else {
+            state.priorities.insert(key, priority);
+        }
+    }
+
+    pub fn priority(&self, collection_id: &str, path: &Path) -> ImportPriority {
+        self.state
+            .lock()
+            .unwrap()
+            .priorities
+            .get(&(collection_id.to_string(), path.to_path_buf()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Stop tracking a document that was indexed or failed for good.
+    pub fn finished(&self, doc_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(key) = state.docs.remove(doc_id) {
+            state.files.remove(&key);
+        }
+    }
+
+    /// Skip `stages` for `doc_id` once, because their output was linked
+    /// from another collection. Call before the entries are written.
+    pub fn link(&self, doc_id: &str, stages: impl IntoIterator<Item = Stage>) {
+        let stages: HashSet<Stage> = stages.into_iter().collect();
+        if !stages.is_empty() {
+            let mut state = self.state.lock().unwrap();
+            state.linked.insert(doc_id.to_string(), stages);
+        }
+    }
+
+    /// Whether the `stage` job for `doc_id` should be skipped as linked.
+    pub fn skip_linked(&self, doc_id: &str, stage: Stage) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(stages) = state.linked.get_mut(doc_id) else {
+            return false;
+        };
+        let skip = stages.remove(&stage);
+        if stages.is_empty() {
+            state.linked.remove(doc_id);
+        }
+        skip
+    }
+
+    /// Whether a worker may run a job for `doc_id` now.
+    pub fn admit(&self, collection_id: &str, doc_id: &str) -> Admission {
+        let mut state = self.state.lock().unwrap();
+        if state.cancelled_docs.contains(doc_id) {
+            return Admission::Drop;
+        }
+        let key = state.docs.get(doc_id).cloned();
+        if state.is_paused(collection_id, key.as_ref().map(|(_, path)| path)) {
+            return Admission::Park;
+        }
+        let cancel = key
+            .and_then(|key| state.files.get(&key).map(|file| file.cancel.clone()))
+            .unwrap_or_else(|| state.collection_token(collection_id));
+        Admission::Run(cancel)
+    }
+
+    /// Hold a job until its file or collection is resumed.
+    pub fn park(&self, job: ParkedJob) {
+        self.state.lock().unwrap().parked.push(job);
+    }
+
+    /// Wait until `path` may be stored. Returns `false` if it was cancelled
+    /// instead.
+    pub async fn wait_to_store(
+        &self,
+        collection_id: &str,
+        path: &Path,
+        cancel: &CancellationToken,
+    ) -> bool {
+        let path = path.to_path_buf();
+        loop {
+            // Registered before checking, so a resume in between still wakes us.
+            let resumed = self.resumed.notified();
+            if cancel.is_cancelled() {
+                return false;
+            }
+            if !self
+                .state
+                .lock()
+                .unwrap()
+                .is_paused(collection_id, Some(&path))
+            {
+                return true;
+            }
+            tokio::select! {
+                _ = resumed => {}
+                _ = cancel.cancelled() => return false,
+            }
+        }
+    }
+
+    pub fn pause_collection(&self, collection_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.paused_collections.insert(collection_id.to_string());
+    }
+
+    pub fn pause_file(&self, collection_id: &str, path: &Path) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .paused_files
+            .insert((collection_id.to_string(), path.to_path_buf()));
+    }
+
+    /// Whether `path` is paused on its own, apart from its collection.
+    pub fn is_file_paused(&self, collection_id: &str, path: &Path) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .paused_files
+            .contains(&(collection_id.to_string(), path.to_path_buf()))
+    }
+
+    /// Whether `collection_id` is paused as a whole.
+    pub fn is_collection_paused(&self, collection_id: &str) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .paused_collections
+            .contains(collection_id)
+    }
+
+    /// Resume a collection and its paused files. Returns the jobs parked
+    /// for it, to be queued again.
+    pub fn resume_collection(&self, collection_id: &str) -> Vec<ParkedJob> {
+        let mut state = self.state.lock().unwrap();
+        state.paused_collections.remove(collection_id);
+        state.paused_files.retain(|(c, _)| c != collection_id);
+        let jobs = take_parked(&mut state, |job| job.collection_id() == collection_id);
+        drop(state);
+        self.resumed.notify_waiters();
+        jobs
+    }
+
+    /// Resume one file. Returns the jobs parked for it, to be queued again,
+    /// unless its collection is still paused.
+    pub fn resume_file(&self, collection_id: &str, path: &Path) -> Vec<ParkedJob> {
+        let mut state = self.state.lock().unwrap();
+        let key = (collection_id.to_string(), path.to_path_buf());
+        state.paused_files.remove(&key);
+        let jobs = if state.paused_collections.contains(collection_id) {
+            Vec::new()
+        } else {
+            let doc_id = state.files.get(&key).and_then(|f| f.doc_id.clone());
+            take_parked(&mut state, |job| Some(job.doc_id()) == doc_id.as_deref())
+        };
+        drop(state);
+        self.resumed.notify_waiters();
+        jobs
+    }
+
+    /// Cancel everything queued for a collection. Returns the stored
+    /// documents to remove and the parked jobs that were dropped.
+    pub fn cancel_collection(&self, collection_id: &str) -> (Vec<String>, Vec<ParkedJob>) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(token) = state.collections.remove(collection_id) {
+            token.cancel();
+        }
+        state.paused_collections.remove(collection_id);
+        state.paused_files.retain(|(c, _)| c != collection_id);
+        state.priorities.retain(|(c, _), _| c != collection_id);
+
+        let keys: Vec<(String, PathBuf)> = state
+            .files
+            .keys()
+            .filter(|(c, _)| c == collection_id)
+            .cloned()
+            .collect();
+        let doc_ids = cancel_files(&mut state, &keys);
+        let dropped = take_parked(&mut state, |job| job.collection_id() == collection_id);
+        drop(state);
+        self.resumed.notify_waiters();
+        (doc_ids, dropped)
+    }
+
+    /// Cancel one file. Returns its stored document, if any, and the parked
+    /// jobs that were dropped.
+    pub fn cancel_file(
+        &self,
+        collection_id: &str,
+        path: &Path,
+    ) -> (Option<String>, Vec<ParkedJob>) {
+        let mut state = self.state.lock().unwrap();
+        let key = (collection_id.to_string(), path.to_path_buf());
+        state.paused_files.remove(&key);
+        state.priorities.remove(&key);
+        let doc_id = cancel_files(&mut state, &[key]).pop();
+        let dropped = take_parked(&mut state, |job| Some(job.doc_id()) == doc_id.as_deref());
+        drop(state);
+        self.resumed.notify_waiters();
+        (doc_id, dropped)
+    }
+}
+
+/// Cancel and untrack the files at `keys`, returning their stored documents.
+fn cancel_files(state: &mut ControlState, keys: &[(String, PathBuf)]) -> Vec<String> {
+    let mut doc_ids = Vec::new();
+    for key in keys {
+        let Some(file) = state.files.remove(key) else {
+            continue;
+        };
+        file.cancel.cancel();
+        if let Some(doc_id) = file.doc_id {
+            state.docs.remove(&doc_id);
+            state.cancelled_docs.insert(doc_id.clone());
+            doc_ids.push(doc_id);
+        }
+    }
+    doc_ids
+}
+
+fn take_parked(state: &mut ControlState, matches: impl Fn(&ParkedJob) -> bool) -> Vec<ParkedJob> {
+    let (taken, kept) = std::mem::take(&mut state.parked)
+        .into_iter()
+        .partition(|job| matches(job));
+    state.parked = kept;
+    taken
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iroh_docs::NamespaceId;
+
+    fn extract(namespace_id: NamespaceId, doc_id: &str) -> ParkedJob {
+        ParkedJob::Extract(ExtractJob {
+            namespace_id,
+            doc_id: doc_id.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_paused_file_parks_until_resumed() {
+        let control = ImportControl::default();
+        let namespace_id = NamespaceId::from([1u8; 32]);
+        let col = namespace_id.to_string();
+        let paths = vec![PathBuf::from("a.pdf"), PathBuf::from("b.pdf")];
+        control.track(&col, &paths);
+        control.stored(&col, &paths[0], "doc-a");
+        control.stored(&col, &paths[1], "doc-b");
+
+        control.pause_file(&col, &paths[0]);
+        assert!(matches!(control.admit(&col, "doc-a"), Admission::Park));
+        assert!(matches!(control.admit(&col, "doc-b"), Admission::Run(_)));
+        control.park(extract(namespace_id, "doc-a"));
+
+        let resumed = control.resume_file(&col, &paths[0]);
+        assert_eq!(resumed.len(), 1);
+        assert!(matches!(control.admit(&col, "doc-a"), Admission::Run(_)));
+    }
+
+    #[test]
+    fn test_cancel_collection_stops_its_files() {
+        let control = ImportControl::default();
+        let namespace_id = NamespaceId::from([2u8; 32]);
+        let col = namespace_id.to_string();
+        let paths = vec![PathBuf::from("a.pdf"), PathBuf::from("b.pdf")];
+        let tokens = control.track(&col, &paths);
+        control.stored(&col, &paths[0], "doc-a");
+        control.pause_collection(&col);
+        control.park(extract(namespace_id, "doc-a"));
+
+        let (doc_ids, dropped) = control.cancel_collection(&col);
+        assert_eq!(doc_ids, vec!["doc-a".to_string()]);
+        assert_eq!(dropped.len(), 1);
+        assert!(tokens.iter().all(|t| t.is_cancelled()));
+        assert!(matches!(control.admit(&col, "doc-a"), Admission::Drop));
+
+        // A later import into the same collection starts fresh.
+        let tokens = control.track(&col, &paths[1..]);
+        assert!(!tokens[0].is_cancelled());
+        assert!(!control.is_collection_paused(&col));
+    }
+
+    #[test]
+    fn test_linked_stages_skip_once() {
+        let control = ImportControl::default();
+        control.link("doc-a", [Stage::Extract, Stage::Embed]);
+        assert!(control.skip_linked("doc-a", Stage::Extract));
+        assert!(!control.skip_linked("doc-a", Stage::Extract));
+        assert!(!control.skip_linked("doc-a", Stage::Index));
+        assert!(control.skip_linked("doc-a", Stage::Embed));
+        assert!(!control.skip_linked("doc-b", Stage::Embed));
+    }
+}
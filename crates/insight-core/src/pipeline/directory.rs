@@ -0,0 +1,156 @@
+//! Importing a whole directory: every PDF under it that passes the
+//! include/exclude globs and isn't in the collection yet, tagged with the
+//! folder it sits in relative to the directory.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use iroh_blobs::Hash;
+use iroh_docs::NamespaceId;
+
+use super::folders::{collect_files, is_importable};
+use crate::storage::Storage;
+
+/// Which files under a directory to import. A glob matches a file if it
+/// matches either its path relative to the directory or its name, so
+/// `*.pdf` matches at any depth and `drafts/**` only under `drafts`.
+#[derive(Debug, Clone)]
+pub struct DirectoryFilter {
+    /// `None` includes every PDF.
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl DirectoryFilter {
+    /// Build a filter from glob patterns. Fails on an invalid pattern.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(build_set(include)?)
+        };
+        Ok(Self {
+            include,
+            exclude: build_set(exclude)?,
+        })
+    }
+
+    /// Whether the file at `relative` (to the directory) is imported.
+    pub fn matches(&self, relative: &Path) -> bool {
+        let name = relative.file_name().map(Path::new).unwrap_or(relative);
+        let hit = |set: &GlobSet| set.is_match(relative) || set.is_match(name);
+        self.include.as_ref().is_none_or(hit) && !hit(&self.exclude)
+    }
+}
+
+fn build_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut set = GlobSetBuilder::new();
+    for pattern in patterns {
+        set.add(glob(pattern)?);
+    }
+    Ok(set.build()?)
+}
+
+fn glob(pattern: &str) -> Result<Glob> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .case_insensitive(true)
+        .build()
+        .with_context(|| format!("Invalid pattern: {}", pattern))
+}
+
+/// Files under `root` to import into `namespace_id`, grouped by tag: the
+/// folder they are in relative to `root`, with `/` separators. Files
+/// directly in `root` get no tag. Files whose content is already in the
+/// collection are left out.
+///
+/// Returns the groups and the number of files left out as duplicates.
+pub(super) async fn scan(
+    root: &Path,
+    filter: &DirectoryFilter,
+    storage: &Storage,
+    namespace_id: NamespaceId,
+) -> Result<(Vec<(Vec<String>, Vec<PathBuf>)>, usize)> {
+    let dir = root.to_path_buf();
+    let files = tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        collect_files(&dir, &mut files);
+        files
+    })
+    .await
+    .context("Directory scan failed")?;
+
+    let mut groups: BTreeMap<Vec<String>, Vec<PathBuf>> = BTreeMap::new();
+    let mut duplicates = 0;
+    for path in files {
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        if !is_importable(&path) || !filter.matches(relative) {
+            continue;
+        }
+        if already_imported(storage, namespace_id, &path).await {
+            duplicates += 1;
+            continue;
+        }
+        let tags = relative_tag(relative).into_iter().collect();
+        groups.entry(tags).or_default().push(path);
+    }
+    Ok((groups.into_iter().collect(), duplicates))
+}
+
+/// Whether the content of the file at `path` is already a source in the
+/// collection. Unreadable files count as new, so their import reports why.
+pub(super) async fn already_imported(
+    storage: &Storage,
+    namespace_id: NamespaceId,
+    path: &Path,
+) -> bool {
+    let Ok(bytes) = tokio::fs::read(path).await else {
+        return false;
+    };
+    storage
+        .has_source_hash(namespace_id, &Hash::new(&bytes))
+        .await
+        .unwrap_or(false)
+}
+
+/// Tag for a file at `relative`: its folder, `None` at the top level.
+fn relative_tag(relative: &Path) -> Option<String> {
+    let parent = relative.parent()?;
+    let parts: Vec<String> = parent
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_matches_path_or_name() {
+        let filter = DirectoryFilter::new(
+            &["*.pdf".to_string()],
+            &["drafts/**".to_string(), "*-old.pdf".to_string()],
+        )
+        .unwrap();
+        assert!(filter.matches(Path::new("report.PDF")));
+        assert!(filter.matches(Path::new("contracts/2024/lease.pdf")));
+        assert!(!filter.matches(Path::new("drafts/lease.pdf")));
+        assert!(!filter.matches(Path::new("contracts/lease-old.pdf")));
+        assert!(DirectoryFilter::new(&["[".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_relative_tag() {
+        assert_eq!(relative_tag(Path::new("lease.pdf")), None);
+        assert_eq!(
+            relative_tag(Path::new("contracts/2024/lease.pdf")).as_deref(),
+            Some("contracts/2024")
+        );
+    }
+}
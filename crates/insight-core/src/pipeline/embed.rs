@@ -5,36 +5,98 @@
 //! per-model plumbing (tokenizer, weights, GPU/CPU dispatch) lives in
 //! `provider::local::embedding`.
 
+use std::time::Instant;
+
 use iroh_docs::NamespaceId;
+use tokio::sync::Semaphore;
 
 use crate::manager::ModelManager;
-use crate::provider::EmbeddingProvider;
-use crate::storage::{DocumentMetadata, EmbeddingChunk, EmbeddingData, Storage};
+use crate::projection::Projection;
+use crate::provider::{ChunkingConfig, EmbeddingProvider};
+use crate::storage::{DocumentMetadata, EmbeddingChunk, EmbeddingData, Storage, VectorEncoding};
+
+use super::chunking::chunk_document;
+use super::embed_cache::EmbeddingCache;
+use super::progress::ProgressTracker;
+
+/// Chunks sent to the embedder per call. Keeps GPU memory bounded and
+/// sets the cadence of `embedding-progress` events on long documents.
+const EMBED_BATCH_CHUNKS: usize = 32;
+
+/// Resolve the chunking config for a collection: its own override if set,
+/// otherwise the global default.
+pub async fn resolve_chunking(
+    storage: &Storage,
+    namespace_id: NamespaceId,
+    default: &ChunkingConfig,
+) -> ChunkingConfig {
+    match storage.get_collection_metadata(namespace_id).await {
+        Ok(Some(metadata)) => metadata.chunking.unwrap_or_else(|| default.clone()),
+        Ok(None) => default.clone(),
+        Err(e) => {
+            tracing::warn!(
+                namespace = %namespace_id,
+                error = %e,
+                "Failed to read collection chunking; using default"
+            );
+            default.clone()
+        }
+    }
+}
+
+/// Shared state embed workers hand to each document.
+pub struct EmbedContext<'a> {
+    pub models: &'a ModelManager,
+    pub progress: &'a ProgressTracker,
+    pub cache: &'a EmbeddingCache,
+    /// Bounds embedder calls in flight across all workers, so extra
+    /// workers overlap I/O and chunking instead of queueing on the model.
+    pub inference: &'a Semaphore,
+    /// Encoding the generated vectors are stored with.
+    pub vector_encoding: VectorEncoding,
+}
+
+/// Fetch a document's extracted text.
+pub async fn load_document_text(
+    storage: &Storage,
+    namespace_id: NamespaceId,
+    doc_id: &str,
+) -> anyhow::Result<String> {
+    let text_bytes = storage
+        .get_document_text(namespace_id, doc_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Text not found for document {}", doc_id))?;
 
-/// Maximum chunks to send to GPU in one call (to avoid OOM).
-const MAX_CHUNKS_PER_GPU_CALL: usize = 256;
+    String::from_utf8(text_bytes)
+        .map_err(|e| anyhow::anyhow!("Invalid UTF-8 in document {}: {}", doc_id, e))
+}
 
 /// Generate embeddings for a document.
 ///
-/// Fetches text from storage, chunks it, and embeds it. Returns the data
-/// without storing it — callers write to storage when ready.
+/// Chunks `text` per `chunking` and embeds it. Returns the data without
+/// storing it — callers write to storage when ready. Chunks already in
+/// the cache skip the model. Chunk progress is reported after every batch.
 pub async fn generate_embeddings_data(
-    storage: &Storage,
     embedder: &dyn EmbeddingProvider,
     model_id: &str,
     namespace_id: NamespaceId,
     metadata: &DocumentMetadata,
-    models: &ModelManager,
+    text: &str,
+    chunking: &ChunkingConfig,
+    ctx: &EmbedContext<'_>,
 ) -> anyhow::Result<EmbeddingData> {
-    let text_bytes = storage
-        .get_document_text(namespace_id, &metadata.id)
-        .await?
-        .ok_or_else(|| anyhow::anyhow!("Text not found for document {}", metadata.id))?;
-
-    let text = String::from_utf8(text_bytes)
-        .map_err(|e| anyhow::anyhow!("Invalid UTF-8 in document {}: {}", metadata.id, e))?;
+    // The splitter ranks line breaks above sentence ends, so PDF hard
+    // wraps would otherwise become preferred cut points mid-sentence.
+    let text = reflow_line_breaks(text);
 
-    let chunks = embedder.chunk_text(&text).await?;
+    let mut chunks = chunk_document(
+        embedder,
+        &text,
+        &metadata.page_boundaries,
+        chunking,
+        ctx.models,
+    )
+    .await?;
     if chunks.is_empty() {
         tracing::warn!(doc_id = %metadata.id, "Document has no text to embed");
         return Ok(EmbeddingData {
@@ -42,9 +104,24 @@ pub async fn generate_embeddings_data(
             dimensions: embedder.dimensions(),
             chunks: vec![],
             created_at: chrono::Utc::now().to_rfc3339(),
+            chunking: Some(chunking.clone()),
+            projection: None,
+            vector_encoding: ctx.vector_encoding,
         });
     }
 
+    if let Some(max) = chunking.max_chunks_per_doc {
+        if chunks.len() > max {
+            tracing::warn!(
+                doc_id = %metadata.id,
+                chunk_count = chunks.len(),
+                max_chunks = max,
+                "Document exceeds max chunks; truncating"
+            );
+            chunks.truncate(max);
+        }
+    }
+
     let chunk_offsets = find_chunk_offsets(&text, &chunks);
 
     tracing::debug!(
@@ -54,7 +131,16 @@ pub async fn generate_embeddings_data(
     );
 
     let chunk_refs: Vec<&str> = chunks.iter().map(|s| s.as_str()).collect();
-    let vectors = embed_with_chunking(embedder, &chunk_refs, models).await?;
+    let collection_id = namespace_id.to_string();
+    let vectors = embed_in_batches(
+        embedder,
+        model_id,
+        &chunk_refs,
+        ctx,
+        &collection_id,
+        &metadata.id,
+    )
+    .await?;
 
     let embedding_chunks: Vec<EmbeddingChunk> = chunks
         .iter()
@@ -89,9 +175,69 @@ pub async fn generate_embeddings_data(
         dimensions: embedder.dimensions(),
         chunks: embedding_chunks,
         created_at: chrono::Utc::now().to_rfc3339(),
+        chunking: Some(chunking.clone()),
+        projection: None,
+        vector_encoding: ctx.vector_encoding,
     })
 }
 
+/// Reduce `data`'s vectors with the collection's projection.
+///
+/// Skipped (native vectors kept) when the projection was built for a
+/// different model or vector size — e.g. after switching models, until
+/// the collection's projection is reset for the new model.
+pub fn apply_projection(data: &mut EmbeddingData, projection: &Projection) -> anyhow::Result<()> {
+    let spec = &projection.spec;
+    if spec.model_id != data.model_id || spec.source_dims != data.dimensions {
+        tracing::warn!(
+            projection_model = %spec.model_id,
+            model = %data.model_id,
+            "Collection projection doesn't match the active model; storing native vectors"
+        );
+        return Ok(());
+    }
+
+    for chunk in &mut data.chunks {
+        chunk.vector = projection.apply(&chunk.vector)?;
+    }
+    data.dimensions = spec.target_dims;
+    data.projection = Some(spec.clone());
+    Ok(())
+}
+
+/// Join hard-wrapped lines so only real paragraph breaks remain.
+///
+/// A single line break between two non-blank lines becomes a space; runs
+/// containing a blank line are kept as paragraph breaks. Each replaced
+/// byte is swapped one-for-one (`\n` and `\r` become spaces), so byte
+/// offsets — and with them `page_boundaries` — still line up with the
+/// stored text.
+fn reflow_line_breaks(text: &str) -> String {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut out = String::with_capacity(text.len());
+
+    for (i, line) in lines.iter().enumerate() {
+        let joins_next =
+            !line.trim().is_empty() && lines.get(i + 1).is_some_and(|next| !next.trim().is_empty());
+
+        match line.strip_suffix('\n') {
+            Some(body) if joins_next => match body.strip_suffix('\r') {
+                Some(body) => {
+                    out.push_str(body);
+                    out.push_str("  ");
+                }
+                None => {
+                    out.push_str(body);
+                    out.push(' ');
+                }
+            },
+            _ => out.push_str(line),
+        }
+    }
+
+    out
+}
+
 /// Find the starting byte offset of each chunk in the original text.
 fn find_chunk_offsets(text: &str, chunks: &[String]) -> Vec<usize> {
     let mut offsets = Vec::with_capacity(chunks.len());
@@ -113,29 +259,78 @@ fn find_chunk_offsets(text: &str, chunks: &[String]) -> Vec<usize> {
     offsets
 }
 
-/// Embed chunks, splitting into multiple GPU calls if needed to avoid OOM.
-/// Touches embedding activity between batches so a long document doesn't
-/// race the idle reaper.
-async fn embed_with_chunking(
+/// Embed chunks in fixed-size batches, reporting progress after each.
+/// Cached chunks are filled in without calling the model; only misses are
+/// sent, and their vectors are added to the cache. Touches embedding
+/// activity between batches so a long document doesn't race the idle
+/// reaper, and yields to interactive requests there (see
+/// [`ModelManager::yield_to_interactive`]).
+async fn embed_in_batches(
     emb: &dyn EmbeddingProvider,
+    model_id: &str,
     chunks: &[&str],
-    models: &ModelManager,
+    ctx: &EmbedContext<'_>,
+    collection_id: &str,
+    doc_id: &str,
 ) -> anyhow::Result<Vec<Vec<f32>>> {
-    if chunks.len() <= MAX_CHUNKS_PER_GPU_CALL {
-        return emb.embed_batch(chunks).await;
-    }
+    let EmbedContext {
+        models,
+        progress,
+        cache,
+        inference,
+        ..
+    } = ctx;
+    let total = chunks.len();
+    progress
+        .report_embedding(collection_id, doc_id, 0, total)
+        .await;
 
-    tracing::debug!(
-        total_chunks = chunks.len(),
-        max_per_call = MAX_CHUNKS_PER_GPU_CALL,
-        "Splitting embedding into multiple GPU calls"
-    );
+    let mut all_vectors = Vec::with_capacity(total);
+    let mut cache_hits = 0;
+    for chunk_batch in chunks.chunks(EMBED_BATCH_CHUNKS) {
+        let mut batch: Vec<Option<Vec<f32>>> = chunk_batch
+            .iter()
+            .map(|chunk| cache.get(model_id, chunk))
+            .collect();
+        let misses: Vec<&str> = chunk_batch
+            .iter()
+            .zip(&batch)
+            .filter(|(_, cached)| cached.is_none())
+            .map(|(chunk, _)| *chunk)
+            .collect();
+        cache_hits += chunk_batch.len() - misses.len();
+
+        if !misses.is_empty() {
+            let started = Instant::now();
+            let mut fresh = {
+                let _permit = inference.acquire().await?;
+                emb.embed_documents(&misses).await?.into_iter()
+            };
+            for (chunk, slot) in chunk_batch.iter().zip(batch.iter_mut()) {
+                if slot.is_none() {
+                    let vector = fresh.next().ok_or_else(|| {
+                        anyhow::anyhow!("Embedder returned fewer vectors than chunks")
+                    })?;
+                    cache.insert(model_id, chunk, vector.clone());
+                    *slot = Some(vector);
+                }
+            }
+            models.touch_embedding();
+            if models.yield_to_interactive(started.elapsed()).await {
+                // The chat model may have evicted the embedder meanwhile.
+                models.acquire_embedding().await?;
+            }
+        }
+
+        all_vectors.extend(batch.into_iter().flatten());
+        progress.record_chunks_embedded(chunk_batch.len());
+        progress
+            .report_embedding(collection_id, doc_id, all_vectors.len(), total)
+            .await;
+    }
 
-    let mut all_vectors = Vec::with_capacity(chunks.len());
-    for chunk_batch in chunks.chunks(MAX_CHUNKS_PER_GPU_CALL) {
-        let vectors = emb.embed_batch(chunk_batch).await?;
-        all_vectors.extend(vectors);
-        models.touch_embedding();
+    if cache_hits > 0 {
+        tracing::debug!(doc_id, cache_hits, total, "Reused cached chunk embeddings");
     }
     Ok(all_vectors)
 }
@@ -151,4 +346,57 @@ mod tests {
         let offsets = find_chunk_offsets(text, &chunks);
         assert_eq!(offsets, vec![0, 13]);
     }
+
+    #[test]
+    fn test_apply_projection() {
+        use crate::projection::ProjectionSpec;
+        use crate::storage::EmbeddingChunk;
+
+        let mut data = EmbeddingData {
+            model_id: "model-a".to_string(),
+            dimensions: 8,
+            chunks: vec![EmbeddingChunk {
+                index: 0,
+                content: "text".to_string(),
+                vector: vec![1.0, 0.5, 0.0, -1.0, 2.0, 0.0, 0.3, 1.0],
+                start_page: 1,
+                end_page: 1,
+            }],
+            created_at: String::new(),
+            chunking: None,
+            projection: None,
+            vector_encoding: VectorEncoding::F32,
+        };
+
+        let other = Projection::generate(ProjectionSpec::new("model-b", 8, 4).unwrap());
+        apply_projection(&mut data, &other).unwrap();
+        assert_eq!(data.dimensions, 8);
+        assert!(data.projection.is_none());
+
+        let projection = Projection::generate(ProjectionSpec::new("model-a", 8, 4).unwrap());
+        apply_projection(&mut data, &projection).unwrap();
+        assert_eq!(data.dimensions, 4);
+        assert_eq!(data.chunks[0].vector.len(), 4);
+        assert_eq!(data.projection, Some(projection.spec));
+    }
+
+    #[test]
+    fn test_reflow_joins_wrapped_lines() {
+        let text =
+            "The court found that the\ndefendant had acted in\nbad faith.\n\nSecond paragraph.\n";
+        let reflowed = reflow_line_breaks(text);
+        assert_eq!(
+            reflowed,
+            "The court found that the defendant had acted in bad faith.\n\nSecond paragraph.\n"
+        );
+        assert_eq!(reflowed.len(), text.len());
+    }
+
+    #[test]
+    fn test_reflow_keeps_blank_line_runs_and_offsets() {
+        let text = "Heading\r\n  \r\nBody line one\r\nline two";
+        let reflowed = reflow_line_breaks(text);
+        assert_eq!(reflowed, "Heading\r\n  \r\nBody line one  line two");
+        assert_eq!(reflowed.len(), text.len());
+    }
 }
@@ -0,0 +1,163 @@
+//! Chunk embedding cache.
+//!
+//! Large imports repeat the same boilerplate (letterheads, disclaimers,
+//! signature blocks) across thousands of documents. Vectors are cached by
+//! `blake3(model_id, chunk text)` so each distinct chunk is embedded once
+//! per model. The cache is in-memory and bounded; the oldest entries are
+//! evicted first.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+/// Default number of cached vectors. At 1024 dimensions this is ~40 MB.
+pub const DEFAULT_CACHE_ENTRIES: usize = 10_000;
+
+/// Hit/miss counters for the embedding cache.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EmbeddingCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+    /// `hits / (hits + misses)`, or 0 before any lookup.
+    pub hit_rate: f64,
+}
+
+struct CacheInner {
+    vectors: HashMap<blake3::Hash, Vec<f32>>,
+    /// Insertion order, oldest first.
+    order: VecDeque<blake3::Hash>,
+}
+
+/// Bounded map from chunk text hash to embedding vector.
+pub struct EmbeddingCache {
+    inner: Mutex<CacheInner>,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl EmbeddingCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(CacheInner {
+                vectors: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+            capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn key(model_id: &str, text: &str) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(model_id.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(text.as_bytes());
+        hasher.finalize()
+    }
+
+    /// Look up a chunk's vector, counting the hit or miss.
+    pub fn get(&self, model_id: &str, text: &str) -> Option<Vec<f32>> {
+        let key = Self::key(model_id, text);
+        let found = self.inner.lock().unwrap().vectors.get(&key).cloned();
+        let counter = if found.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    /// Store a chunk's vector, evicting the oldest entries when full.
+    pub fn insert(&self, model_id: &str, text: &str, vector: Vec<f32>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = Self::key(model_id, text);
+        let mut inner = self.inner.lock().unwrap();
+        if inner.vectors.insert(key, vector).is_some() {
+            return;
+        }
+        inner.order.push_back(key);
+        while inner.order.len() > self.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.vectors.remove(&oldest);
+            }
+        }
+    }
+
+    /// Drop all cached vectors. Counters are kept.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.vectors.clear();
+        inner.order.clear();
+    }
+
+    pub fn stats(&self) -> EmbeddingCacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let lookups = hits + misses;
+        EmbeddingCacheStats {
+            hits,
+            misses,
+            entries: self.inner.lock().unwrap().vectors.len(),
+            capacity: self.capacity,
+            hit_rate: if lookups == 0 {
+                0.0
+            } else {
+                hits as f64 / lookups as f64
+            },
+        }
+    }
+}
+
+impl Default for EmbeddingCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_ENTRIES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hit_and_miss_counts() {
+        let cache = EmbeddingCache::new(4);
+        assert_eq!(cache.get("m", "disclaimer"), None);
+        cache.insert("m", "disclaimer", vec![1.0, 2.0]);
+        assert_eq!(cache.get("m", "disclaimer"), Some(vec![1.0, 2.0]));
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.entries, 1);
+        assert!((stats.hit_rate - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_keys_are_per_model() {
+        let cache = EmbeddingCache::new(4);
+        cache.insert("model-a", "text", vec![1.0]);
+        assert_eq!(cache.get("model-b", "text"), None);
+    }
+
+    #[test]
+    fn test_evicts_oldest_when_full() {
+        let cache = EmbeddingCache::new(2);
+        cache.insert("m", "one", vec![1.0]);
+        cache.insert("m", "two", vec![2.0]);
+        cache.insert("m", "three", vec![3.0]);
+
+        assert_eq!(cache.get("m", "one"), None);
+        assert_eq!(cache.get("m", "two"), Some(vec![2.0]));
+        assert_eq!(cache.get("m", "three"), Some(vec![3.0]));
+        assert_eq!(cache.stats().entries, 2);
+    }
+}
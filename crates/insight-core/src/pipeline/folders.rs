@@ -0,0 +1,281 @@
+//! Watch folders: PDFs that appear in a configured folder are imported
+//! into its collection, tagged with the name of the folder they landed in.
+//!
+//! Folders are watched recursively with `notify`. A file is imported once
+//! it has gone [`SETTLE`] without another event, so a sync client still
+//! writing it isn't read half-way. Files already in the collection (by
+//! content hash) are skipped, which also makes the scan on startup, for
+//! files added while the app was closed, cheap to repeat.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use iroh_docs::NamespaceId;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::{mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
+
+use super::directory::already_imported;
+use super::Pipeline;
+use crate::config::WatchFolder;
+use crate::storage::Storage;
+
+/// Quiet time after the last event before a file is imported.
+const SETTLE: Duration = Duration::from_secs(2);
+
+/// How often settled files are checked for.
+const TICK: Duration = Duration::from_secs(1);
+
+/// Watches folders and imports new PDFs from them. Dropping it stops the
+/// watch.
+pub struct FolderWatcher {
+    _watcher: RecommendedWatcher,
+    cancel: CancellationToken,
+}
+
+impl FolderWatcher {
+    /// Start watching `folders`. A folder that can't be watched (missing,
+    /// no permission) is skipped with a warning.
+    pub fn spawn(
+        folders: Vec<WatchFolder>,
+        pipeline: Arc<Pipeline>,
+        storage: Arc<RwLock<Storage>>,
+    ) -> Result<Self> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+                Ok(event) => {
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        for path in event.paths {
+                            let _ = tx.send(path);
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Watch folder error"),
+            })?;
+
+        let mut watched = Vec::new();
+        for folder in folders {
+            match watcher.watch(&folder.path, RecursiveMode::Recursive) {
+                Ok(()) => {
+                    tracing::info!(
+                        path = %folder.path.display(),
+                        collection = %folder.collection_id,
+                        "Watching folder"
+                    );
+                    watched.push(folder);
+                }
+                Err(e) => {
+                    tracing::warn!(path = %folder.path.display(), error = %e, "Can't watch folder")
+                }
+            }
+        }
+
+        let cancel = CancellationToken::new();
+        tokio::spawn(run(watched, rx, pipeline, storage, cancel.clone()));
+        Ok(Self {
+            _watcher: watcher,
+            cancel,
+        })
+    }
+}
+
+impl Drop for FolderWatcher {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+async fn run(
+    folders: Vec<WatchFolder>,
+    mut events: mpsc::UnboundedReceiver<PathBuf>,
+    pipeline: Arc<Pipeline>,
+    storage: Arc<RwLock<Storage>>,
+    cancel: CancellationToken,
+) {
+    // Path -> time of its last event.
+    let mut settling: HashMap<PathBuf, Instant> = HashMap::new();
+    // Paths handed to the pipeline and not yet stored.
+    let in_flight: Arc<Mutex<HashSet<PathBuf>>> = Arc::default();
+
+    // Files added while the app was closed count as settled already.
+    let roots: Vec<PathBuf> = folders.iter().map(|f| f.path.clone()).collect();
+    let existing = tokio::task::spawn_blocking(move || {
+        let mut files = Vec::new();
+        for root in &roots {
+            collect_files(root, &mut files);
+        }
+        files
+    })
+    .await
+    .unwrap_or_default();
+    let long_ago = Instant::now()
+        .checked_sub(SETTLE)
+        .unwrap_or_else(Instant::now);
+    settling.extend(existing.into_iter().map(|path| (path, long_ago)));
+
+    let mut tick = tokio::time::interval(TICK);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            Some(path) = events.recv() => {
+                settling.insert(path, Instant::now());
+            }
+            _ = tick.tick() => {
+                let now = Instant::now();
+                let settled: Vec<PathBuf> = settling
+                    .iter()
+                    .filter(|(_, at)| now.duration_since(**at) >= SETTLE)
+                    .map(|(path, _)| path.clone())
+                    .collect();
+                if settled.is_empty() {
+                    continue;
+                }
+                for path in &settled {
+                    settling.remove(path);
+                }
+                import_settled(settled, &folders, &pipeline, &storage, &in_flight).await;
+            }
+        }
+    }
+    tracing::debug!("Folder watcher stopped");
+}
+
+/// Import the settled files not already in their collection, batched by
+/// collection and tag.
+async fn import_settled(
+    paths: Vec<PathBuf>,
+    folders: &[WatchFolder],
+    pipeline: &Arc<Pipeline>,
+    storage: &Arc<RwLock<Storage>>,
+    in_flight: &Arc<Mutex<HashSet<PathBuf>>>,
+) {
+    let mut batches: HashMap<(NamespaceId, String), Vec<PathBuf>> = HashMap::new();
+    for path in paths {
+        if !is_importable(&path) || in_flight.lock().unwrap().contains(&path) {
+            continue;
+        }
+        let Some(folder) = folder_for(folders, &path) else {
+            continue;
+        };
+        let Ok(namespace_id) = folder.collection_id.parse::<NamespaceId>() else {
+            continue;
+        };
+        if already_imported(&storage.read().await, namespace_id, &path).await {
+            continue;
+        }
+        let tag = tag_for(&path);
+        batches.entry((namespace_id, tag)).or_default().push(path);
+    }
+
+    for ((namespace_id, tag), paths) in batches {
+        tracing::info!(
+            collection = %namespace_id,
+            tag = %tag,
+            count = paths.len(),
+            "Importing from watch folder"
+        );
+        in_flight.lock().unwrap().extend(paths.iter().cloned());
+        let pipeline = pipeline.clone();
+        let in_flight = in_flight.clone();
+        // Spawned so a paused collection doesn't hold up the other folders.
+        tokio::spawn(async move {
+            let tags = if tag.is_empty() {
+                Vec::new()
+            } else {
+                vec![tag]
+            };
+            pipeline
+                .import_tagged_files(namespace_id, paths.clone(), tags)
+                .await;
+            let mut in_flight = in_flight.lock().unwrap();
+            for path in &paths {
+                in_flight.remove(path);
+            }
+        });
+    }
+}
+
+/// Every file under `dir`, recursively. Unreadable entries are skipped.
+pub(super) fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(t) if t.is_dir() => collect_files(&path, files),
+            Ok(t) if t.is_file() => files.push(path),
+            _ => {}
+        }
+    }
+}
+
+/// A PDF that isn't hidden or an office lock file.
+pub(super) fn is_importable(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    !name.starts_with('.')
+        && !name.starts_with("~$")
+        && path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+        && path.is_file()
+}
+
+/// The innermost watched folder containing `path`.
+fn folder_for<'a>(folders: &'a [WatchFolder], path: &Path) -> Option<&'a WatchFolder> {
+    folders
+        .iter()
+        .filter(|f| path.starts_with(&f.path))
+        .max_by_key(|f| f.path.components().count())
+}
+
+/// Name of the folder `path` is in.
+fn tag_for(path: &Path) -> String {
+    path.parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_visible_pdfs_are_imported() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["report.PDF", ".report.pdf", "~$report.pdf", "notes.txt"] {
+            std::fs::write(dir.path().join(name), b"x").unwrap();
+        }
+        let importable: Vec<bool> = ["report.PDF", ".report.pdf", "~$report.pdf", "notes.txt"]
+            .iter()
+            .map(|name| is_importable(&dir.path().join(name)))
+            .collect();
+        assert_eq!(importable, vec![true, false, false, false]);
+        assert!(!is_importable(&dir.path().join("missing.pdf")));
+    }
+
+    #[test]
+    fn test_innermost_folder_and_tag() {
+        let folders = vec![
+            WatchFolder {
+                collection_id: "outer".to_string(),
+                path: PathBuf::from("/sync"),
+            },
+            WatchFolder {
+                collection_id: "inner".to_string(),
+                path: PathBuf::from("/sync/contracts"),
+            },
+        ];
+        let path = Path::new("/sync/contracts/2024/lease.pdf");
+        assert_eq!(folder_for(&folders, path).unwrap().collection_id, "inner");
+        assert_eq!(tag_for(path), "2024");
+        assert!(folder_for(&folders, Path::new("/elsewhere/a.pdf")).is_none());
+    }
+}
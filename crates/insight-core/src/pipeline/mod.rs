@@ -20,7 +20,7 @@
 //! files/*/source   files/*/text   files/*/embeddings/*
 //!     │                 │                 │
 //!     ▼                 ▼                 ▼
-//! Extract(4)        Embed(2)         Index(1)
+//! Extract(4)        Embed(n)         Index(1)
 //!     │                 │                 │
 //!     ▼                 ▼                 ▼
 //! InsertLocal       InsertLocal      Document searchable
@@ -29,35 +29,85 @@
 //!
 //! Each stage writes to iroh, which triggers the next stage via events.
 
+mod chunking;
+mod control;
+mod directory;
 mod embed;
+mod embed_cache;
+mod folders;
 mod ocr;
+mod order;
+mod preview;
 mod progress;
+mod queue;
+mod report;
+mod retry;
+mod sync_health;
+mod throughput;
+mod transfer_stats;
 mod types;
 mod watcher;
 mod workers;
 
-pub use progress::{DocProgress, PipelineProgress, ProgressTracker, StageProgress};
+pub use control::{ImportControl, ParkedJob};
+pub use directory::DirectoryFilter;
+pub use embed_cache::{EmbeddingCache, EmbeddingCacheStats};
+pub use folders::FolderWatcher;
+pub use order::ImportPriority;
+pub use preview::{DuplicateStatus, FilePreview};
+pub use progress::{
+    DocProgress, EmbeddingProgress, FileProgress, PipelineProgress, ProgressTracker, StageProgress,
+    SyncStatus, SyncedDocument,
+};
+pub use queue::{ImportQueue, QueuedImport};
+pub use report::{FileStatus, ImportReport, ImportReports, ImportSummary, ReportedFile};
+pub use retry::FailedFile;
+pub use sync_health::{PeerSyncHealth, SyncHealth};
+pub use throughput::ThroughputStats;
+pub use transfer_stats::{CollectionTransfer, TransferStats};
 pub use types::{EmbedJob, ExtractJob, IndexJob, OcrJob, ProgressUpdate, Stage};
 pub use watcher::{CollectionWatcher, JobSenders};
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use anyhow::Context;
 use iroh_docs::NamespaceId;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, RwLock, Semaphore};
 use tokio_util::sync::CancellationToken;
 
+use crate::config::{PipelineConfig, SyncPolicy};
+use crate::hooks::{Hook, Hooks};
 use crate::manager::ModelManager;
+use crate::projection::{Projection, ProjectionSpec};
+use crate::provider::{ChunkingConfig, EmbeddingProvider};
+use crate::remote::{self, Remote};
 use crate::search::IndexWorkerHandle;
-use crate::storage::Storage;
+use crate::storage::{Storage, Upload, VectorEncoding};
 
+use retry::Retries;
 use workers::{spawn_embed_workers, spawn_extract_workers, SharedReceiver};
 
-/// Number of workers per stage.
-const EXTRACT_WORKERS: usize = 4;
+/// Number of OCR workers. Store, extract and embed workers come from
+/// [`PipelineConfig`].
 const OCR_WORKERS: usize = 1;
-const EMBED_WORKERS: usize = 2;
+
+/// Upper bound on configured embed workers. Beyond this the shared
+/// embedder is the bottleneck and extra workers only hold memory.
+const MAX_EMBED_WORKERS: usize = 8;
+
+/// Upper bound on configured store and extract workers. Both are mostly
+/// disk-bound; past this they only contend for it.
+const MAX_IO_WORKERS: usize = 16;
+
+/// How often throughput snapshots are broadcast while work is queued.
+const THROUGHPUT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often transfer counts are broadcast for collections exchanging
+/// data.
+const TRANSFER_STATS_INTERVAL: Duration = Duration::from_secs(10);
 
 /// Event-driven document processing pipeline.
 ///
@@ -76,9 +126,54 @@ pub struct Pipeline {
     // Per-collection watchers
     watchers: Arc<RwLock<HashMap<NamespaceId, CollectionWatcher>>>,
 
+    // Global chunking default (collections may override)
+    chunking: Arc<RwLock<ChunkingConfig>>,
+
+    // Chunk vectors shared by embed workers
+    embedding_cache: Arc<EmbeddingCache>,
+
+    // Embedder calls in flight, shared by the workers and re-embed jobs
+    inference: Arc<Semaphore>,
+
+    // Encoding generated vectors are stored with
+    vector_encoding: VectorEncoding,
+
     // Shared progress tracker
     progress: ProgressTracker,
 
+    // How syncing each collection with each peer is going
+    sync_health: SyncHealth,
+
+    // What syncing each collection has cost in data
+    transfer_stats: TransferStats,
+
+    // What is downloaded of each collection; others download everything
+    sync_policies: RwLock<HashMap<NamespaceId, SyncPolicy>>,
+
+    // Files waiting to be stored, kept on disk across restarts
+    import_queue: Arc<ImportQueue>,
+
+    // Reports of finished imports
+    import_reports: Arc<ImportReports>,
+
+    // Pause and cancel state for imports
+    control: Arc<ImportControl>,
+
+    // Automatic retries and jobs that failed for good
+    retries: Arc<Retries>,
+
+    // Files stored concurrently per import
+    store_workers: usize,
+
+    // Remote storage queued objects are fetched from, by source ID
+    remotes: RwLock<HashMap<String, Remote>>,
+
+    // Run as documents are imported, deleted and synced
+    hooks: Hooks,
+
+    // For removing chunks of cancelled documents
+    index_worker: IndexWorkerHandle,
+
     // Master cancellation token
     cancel: CancellationToken,
 }
@@ -92,15 +187,37 @@ impl Pipeline {
         storage: Arc<RwLock<Storage>>,
         models: Arc<ModelManager>,
         index_worker: IndexWorkerHandle,
+        config: &PipelineConfig,
+        chunking: ChunkingConfig,
+        import_queue: ImportQueue,
+        import_reports: ImportReports,
     ) -> (Self, mpsc::Receiver<PipelineProgress>) {
         let (progress, progress_rx) = ProgressTracker::new();
+        let chunking = Arc::new(RwLock::new(chunking));
+        let embedding_cache = Arc::new(EmbeddingCache::default());
+        let store_workers = config.store_workers.clamp(1, MAX_IO_WORKERS);
+        let extract_workers = config.extract_workers.clamp(1, MAX_IO_WORKERS);
+        let embed_workers = config.embed_workers.clamp(1, MAX_EMBED_WORKERS);
+        let embed_concurrency = config.embed_concurrency.clamp(1, embed_workers);
         let cancel = CancellationToken::new();
+        let control = Arc::new(ImportControl::default());
+        let hooks = Hooks::new(storage.clone());
+        let inference = Arc::new(Semaphore::new(embed_concurrency));
 
         // Create unbounded channels (avoids blocking the event watcher)
         let (extract_tx, extract_rx) = mpsc::unbounded_channel();
         let (ocr_tx, ocr_rx) = mpsc::unbounded_channel();
         let (embed_tx, embed_rx) = mpsc::unbounded_channel();
         let (index_tx, index_rx) = mpsc::unbounded_channel();
+        let retries = Arc::new(Retries::new(
+            config.retry_attempts,
+            JobSenders {
+                extract: extract_tx.clone(),
+                ocr: ocr_tx.clone(),
+                embed: embed_tx.clone(),
+                index: index_tx.clone(),
+            },
+        ));
 
         // Shared receivers for multi-worker stages
         let extract_rx = SharedReceiver::new_unbounded(extract_rx);
@@ -110,9 +227,11 @@ impl Pipeline {
 
         // Spawn worker pools
         spawn_extract_workers(
-            EXTRACT_WORKERS,
+            extract_workers,
             extract_rx,
             storage.clone(),
+            control.clone(),
+            retries.clone(),
             progress.clone(),
         );
 
@@ -121,14 +240,22 @@ impl Pipeline {
             ocr_rx,
             storage.clone(),
             models.clone(),
+            control.clone(),
+            retries.clone(),
             progress.clone(),
         );
 
         spawn_embed_workers(
-            EMBED_WORKERS,
+            embed_workers,
             embed_rx,
             storage.clone(),
             models.clone(),
+            chunking.clone(),
+            embedding_cache.clone(),
+            inference.clone(),
+            config.vector_encoding,
+            control.clone(),
+            retries.clone(),
             progress.clone(),
         );
 
@@ -136,13 +263,22 @@ impl Pipeline {
             index_rx,
             storage.clone(),
             index_worker.clone(),
+            control.clone(),
+            retries.clone(),
             progress.clone(),
         );
 
+        progress.spawn_throughput_reporter(THROUGHPUT_INTERVAL, cancel.clone());
+        let transfer_stats = TransferStats::default();
+        transfer_stats.spawn_reporter(TRANSFER_STATS_INTERVAL, cancel.clone());
+
         tracing::info!(
-            extract_workers = EXTRACT_WORKERS,
+            store_workers,
+            extract_workers,
             ocr_workers = OCR_WORKERS,
-            embed_workers = EMBED_WORKERS,
+            embed_workers,
+            embed_concurrency,
+            vector_encoding = ?config.vector_encoding,
             "Pipeline started"
         );
 
@@ -155,7 +291,22 @@ impl Pipeline {
                 embed_tx,
                 index_tx,
                 watchers: Arc::new(RwLock::new(HashMap::new())),
+                chunking,
+                embedding_cache,
+                inference,
+                vector_encoding: config.vector_encoding,
                 progress,
+                sync_health: SyncHealth::default(),
+                transfer_stats,
+                sync_policies: RwLock::new(HashMap::new()),
+                import_queue: Arc::new(import_queue),
+                import_reports: Arc::new(import_reports),
+                control,
+                retries,
+                store_workers,
+                remotes: RwLock::new(HashMap::new()),
+                hooks,
+                index_worker,
                 cancel,
             },
             progress_rx,
@@ -173,19 +324,16 @@ impl Pipeline {
             return;
         }
 
-        let senders = JobSenders {
-            extract: self.extract_tx.clone(),
-            ocr: self.ocr_tx.clone(),
-            embed: self.embed_tx.clone(),
-            index: self.index_tx.clone(),
-        };
-
         let watcher = CollectionWatcher::spawn(
             namespace_id,
             self.storage.clone(),
             self.models.clone(),
-            senders,
+            self.senders(),
             self.progress.clone(),
+            self.sync_health.clone(),
+            self.transfer_stats.clone(),
+            self.hooks.clone(),
+            self.sync_policy(&namespace_id).await,
             self.cancel.child_token(),
         );
 
@@ -198,10 +346,49 @@ impl Pipeline {
         let mut watchers = self.watchers.write().await;
         if let Some(watcher) = watchers.remove(namespace_id) {
             watcher.stop();
+            self.sync_health.forget(&namespace_id.to_string());
+            self.transfer_stats.forget(&namespace_id.to_string());
             tracing::info!(namespace = %namespace_id, "Stopped watching collection");
         }
     }
 
+    /// What is downloaded of a collection.
+    pub async fn sync_policy(&self, namespace_id: &NamespaceId) -> SyncPolicy {
+        self.sync_policies
+            .read()
+            .await
+            .get(namespace_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Replace what is downloaded of each collection. Call before watching
+    /// collections; collections not listed download everything.
+    pub async fn set_sync_policies(&self, policies: HashMap<NamespaceId, SyncPolicy>) {
+        *self.sync_policies.write().await = policies;
+    }
+
+    /// Change what is downloaded of a collection. If it is being watched,
+    /// content the new policy allows is downloaded now.
+    pub async fn set_sync_policy(&self, namespace_id: NamespaceId, policy: SyncPolicy) {
+        self.sync_policies
+            .write()
+            .await
+            .insert(namespace_id, policy);
+        if let Some(watcher) = self.watchers.read().await.get(&namespace_id) {
+            watcher.set_policy(policy);
+        }
+    }
+
+    /// Download the rest of a document a narrower [`SyncPolicy`] left on
+    /// peers. It is processed like any synced document once it arrives,
+    /// reported as [`SyncedDocument`]s.
+    pub async fn fetch_document(&self, namespace_id: &NamespaceId, doc_id: &str) {
+        if let Some(watcher) = self.watchers.read().await.get(namespace_id) {
+            watcher.fetch_document(doc_id);
+        }
+    }
+
     /// Import files into a collection.
     ///
     /// Stores each file's source bytes. The iroh events drive the rest:
@@ -209,64 +396,740 @@ impl Pipeline {
     /// - InsertLocal(text) → Embed
     /// - InsertLocal(embeddings) → Index
     ///
+    /// Files are recorded in the import queue first, so any not yet stored
+    /// when the app closes are imported again by [`Self::resume_imports`].
+    ///
+    /// A path may also be an http(s) URL, which is downloaded when its turn
+    /// comes and stored with the URL in its metadata.
+    ///
+    /// Once the batch is stored, a report of it is written to
+    /// [`Self::import_reports`].
+    ///
     /// Returns (successful_count, errors).
     pub async fn import_files(
         &self,
         namespace_id: NamespaceId,
         paths: Vec<PathBuf>,
     ) -> (usize, Vec<(PathBuf, String)>) {
+        self.import_tagged_files(namespace_id, paths, Vec::new())
+            .await
+    }
+
+    /// Import files as [`Self::import_files`] does, tagging each new
+    /// document with `tags`.
+    pub async fn import_tagged_files(
+        &self,
+        namespace_id: NamespaceId,
+        paths: Vec<PathBuf>,
+        tags: Vec<String>,
+    ) -> (usize, Vec<(PathBuf, String)>) {
+        let started_at = chrono::Utc::now();
+        let files = self.queue_and_store(namespace_id, paths, &tags).await;
+        self.finish_batch(namespace_id, started_at, files)
+    }
+
+    /// Record `paths` in the import queue and store them.
+    async fn queue_and_store(
+        &self,
+        namespace_id: NamespaceId,
+        paths: Vec<PathBuf>,
+        tags: &[String],
+    ) -> Vec<(PathBuf, ReportedFile)> {
         let collection_id = namespace_id.to_string();
-        let mut success = 0;
-        let mut errors = Vec::new();
+        if let Err(e) = self.import_queue.push(&collection_id, &paths, tags) {
+            tracing::warn!(error = %e, "Failed to record queued imports");
+        }
+        // Priorities set before the files were queued.
+        for path in &paths {
+            let priority = self.control.priority(&collection_id, path);
+            if !priority.is_normal() {
+                if let Err(e) = self
+                    .import_queue
+                    .set_priority(&collection_id, path, priority)
+                {
+                    tracing::warn!(error = %e, "Failed to record import priority");
+                }
+            }
+        }
+        self.store_queued(namespace_id, paths, tags).await
+    }
+
+    /// Import every PDF under `root` that passes `filter` and isn't in the
+    /// collection yet, tagging each with its folder relative to `root`.
+    /// Files go through the import queue as [`Self::import_files`] does.
+    ///
+    /// Returns (successful_count, errors).
+    pub async fn import_directory(
+        &self,
+        namespace_id: NamespaceId,
+        root: &Path,
+        filter: &DirectoryFilter,
+    ) -> anyhow::Result<(usize, Vec<(PathBuf, String)>)> {
+        let (groups, duplicates) = {
+            let storage = self.storage.read().await;
+            directory::scan(root, filter, &storage, namespace_id).await?
+        };
+        tracing::info!(
+            root = %root.display(),
+            files = groups.iter().map(|(_, paths)| paths.len()).sum::<usize>(),
+            duplicates,
+            "Importing directory"
+        );
+
+        // Together, so the store workers aren't limited to one folder.
+        let started_at = chrono::Utc::now();
+        let results = futures::future::join_all(
+            groups
+                .iter()
+                .map(|(tags, paths)| self.queue_and_store(namespace_id, paths.clone(), tags)),
+        )
+        .await;
+        Ok(self.finish_batch(
+            namespace_id,
+            started_at,
+            results.into_iter().flatten().collect(),
+        ))
+    }
+
+    /// Add document `doc_id` of collection `from` to `namespace_id` by
+    /// linking it: source, text and embeddings are shared rather than
+    /// stored and computed again, so normally only indexing runs.
+    pub async fn link_document(
+        &self,
+        from: NamespaceId,
+        doc_id: &str,
+        namespace_id: NamespaceId,
+    ) -> anyhow::Result<crate::storage::DocumentMetadata> {
+        let new_doc_id = uuid::Uuid::new_v4().to_string();
+        let model_id = self.models.embedding_model_id().await;
+        let storage = self.storage.read().await;
+
+        let mut skip = Vec::new();
+        if storage.get_document_text(from, doc_id).await?.is_some() {
+            skip.push(Stage::Extract);
+            if let Some(model_id) = &model_id {
+                if storage
+                    .get_embeddings(from, doc_id, model_id)
+                    .await?
+                    .is_some()
+                {
+                    skip.push(Stage::Embed);
+                }
+            }
+        }
+        // Before any entry is written: the watcher queues jobs as they land.
+        self.control.link(&new_doc_id, skip);
+
+        let simhash = storage
+            .get_document(from, doc_id)
+            .await?
+            .and_then(|m| m.simhash);
+        let mut metadata = storage
+            .link_document(from, doc_id, namespace_id, &new_doc_id)
+            .await?;
+        if let Some(simhash) = simhash {
+            metadata.near_duplicates = storage
+                .record_fingerprint(namespace_id, &new_doc_id, simhash)
+                .await?;
+            metadata.simhash = Some(simhash);
+        }
+        Ok(metadata)
+    }
+
+    /// Add the document a peer sent with `ticket` to `namespace_id`,
+    /// downloading it from them. Text they extracted is kept, so only
+    /// embedding and indexing run.
+    pub async fn receive_document(
+        &self,
+        namespace_id: NamespaceId,
+        ticket: &str,
+    ) -> anyhow::Result<crate::storage::DocumentMetadata> {
+        let new_doc_id = uuid::Uuid::new_v4().to_string();
+        let storage = self.storage.read().await;
+        let (bundle, sender) = storage.fetch_bundle(ticket).await?;
+        let simhash = bundle.metadata.simhash;
+        // Before any entry is written: the watcher queues jobs as they land.
+        if bundle.text.is_some() {
+            self.control.link(&new_doc_id, [Stage::Extract]);
+        }
+
+        let mut metadata = storage
+            .add_bundle(namespace_id, bundle, sender, &new_doc_id)
+            .await?;
+        if let Some(simhash) = simhash {
+            metadata.near_duplicates = storage
+                .record_fingerprint(namespace_id, &new_doc_id, simhash)
+                .await?;
+            metadata.simhash = Some(simhash);
+        }
+        Ok(metadata)
+    }
+
+    /// Report what importing `paths` into a collection would do — page
+    /// counts, language, duplicates, embedding cost — without storing
+    /// anything. Files are previewed a few at a time, in order.
+    pub async fn preview_import(
+        &self,
+        namespace_id: NamespaceId,
+        paths: &[PathBuf],
+    ) -> anyhow::Result<Vec<FilePreview>> {
+        let default_chunking = self.chunking.read().await.clone();
+        let chunks_per_sec = self.progress.throughput().await.embed_chunks_per_sec;
+        let storage = self.storage.read().await;
+        let documents = storage.list_documents(namespace_id).await?;
+        let chunking = embed::resolve_chunking(&storage, namespace_id, &default_chunking).await;
+
+        let mut previews = Vec::with_capacity(paths.len());
+        for batch in paths.chunks(self.store_workers) {
+            previews.extend(
+                futures::future::join_all(batch.iter().map(|path| async {
+                    if queued_url(path).is_some() || remote::parse_queued(path).is_some() {
+                        return FilePreview::failed(
+                            path,
+                            "Links and remote files are fetched when imported and can't be previewed"
+                                .into(),
+                        );
+                    }
+                    preview::preview_file(
+                        &storage,
+                        namespace_id,
+                        &documents,
+                        path,
+                        &chunking,
+                        chunks_per_sec,
+                    )
+                    .await
+                }))
+                .await,
+            );
+        }
+        Ok(previews)
+    }
 
-        for path in paths {
-            // Track store stage
+    /// Import the files left in the queue by a previous run. Sources they
+    /// had already stored but not extracted are re-queued for extraction.
+    pub async fn resume_imports(&self) {
+        self.requeue_pending_extractions().await;
+
+        let mut batches: Vec<(String, Vec<String>, Vec<PathBuf>)> = Vec::new();
+        for item in self.import_queue.pending() {
+            if !item.priority.is_normal() {
+                self.control
+                    .set_priority(&item.collection_id, &item.path, item.priority);
+            }
+            match batches
+                .iter_mut()
+                .find(|(id, tags, _)| *id == item.collection_id && *tags == item.tags)
+            {
+                Some((_, _, paths)) => paths.push(item.path),
+                None => batches.push((item.collection_id, item.tags, vec![item.path])),
+            }
+        }
+
+        for (collection_id, tags, paths) in batches {
+            let Ok(namespace_id) = collection_id.parse::<NamespaceId>() else {
+                tracing::warn!(collection = %collection_id, "Dropping queued imports for unknown collection");
+                for path in &paths {
+                    let _ = self.import_queue.finish(&collection_id, path);
+                }
+                continue;
+            };
+            tracing::info!(
+                collection = %collection_id,
+                count = paths.len(),
+                "Resuming interrupted import"
+            );
+            let started_at = chrono::Utc::now();
+            let files = self.store_queued(namespace_id, paths, &tags).await;
+            let (success, errors) = self.finish_batch(namespace_id, started_at, files);
+            tracing::info!(
+                collection = %collection_id,
+                success,
+                failed = errors.len(),
+                "Resumed import finished"
+            );
+        }
+    }
+
+    /// Files recorded in the import queue and not yet stored.
+    pub fn queued_imports(&self) -> Vec<QueuedImport> {
+        self.import_queue.pending()
+    }
+
+    /// Reports of finished imports.
+    pub fn import_reports(&self) -> &ImportReports {
+        &self.import_reports
+    }
+
+    /// Pause imports into a collection. Files not yet stored wait, and
+    /// queued jobs for its documents are held until it is resumed; jobs
+    /// already running finish.
+    pub async fn pause_collection(&self, namespace_id: &NamespaceId) {
+        let collection_id = namespace_id.to_string();
+        self.control.pause_collection(&collection_id);
+        self.progress.set_paused(&collection_id, true).await;
+        tracing::info!(collection = %collection_id, "Paused imports");
+    }
+
+    /// Resume a paused collection, including files paused on their own.
+    pub async fn resume_collection(&self, namespace_id: &NamespaceId) {
+        let collection_id = namespace_id.to_string();
+        let jobs = self.control.resume_collection(&collection_id);
+        self.progress.set_paused(&collection_id, false).await;
+        tracing::info!(collection = %collection_id, jobs = jobs.len(), "Resumed imports");
+        self.requeue_parked(jobs);
+    }
+
+    /// Pause one file of an import.
+    pub fn pause_file(&self, namespace_id: &NamespaceId, path: &Path) {
+        self.control.pause_file(&namespace_id.to_string(), path);
+    }
+
+    /// Resume one paused file.
+    pub fn resume_file(&self, namespace_id: &NamespaceId, path: &Path) {
+        let jobs = self.control.resume_file(&namespace_id.to_string(), path);
+        self.requeue_parked(jobs);
+    }
+
+    /// Raise or lower a file's place in the import order. Takes effect for
+    /// files not yet stored, including ones about to be imported.
+    pub fn set_file_priority(
+        &self,
+        namespace_id: &NamespaceId,
+        path: &Path,
+        priority: ImportPriority,
+    ) {
+        let collection_id = namespace_id.to_string();
+        self.control.set_priority(&collection_id, path, priority);
+        if let Err(e) = self
+            .import_queue
+            .set_priority(&collection_id, path, priority)
+        {
+            tracing::warn!(error = %e, "Failed to record import priority");
+        }
+    }
+
+    /// Cancel everything imported into a collection this run that hasn't
+    /// been indexed yet. Files not yet stored are dropped from the queue,
+    /// running jobs stop, and documents already stored are removed again.
+    ///
+    /// Returns the number of documents removed.
+    pub async fn cancel_collection(&self, namespace_id: NamespaceId) -> usize {
+        let collection_id = namespace_id.to_string();
+        let (doc_ids, dropped) = self.control.cancel_collection(&collection_id);
+        self.progress.set_paused(&collection_id, false).await;
+        self.drop_parked(dropped).await;
+        for doc_id in &doc_ids {
+            self.remove_cancelled(namespace_id, doc_id).await;
+        }
+        tracing::info!(collection = %collection_id, removed = doc_ids.len(), "Cancelled imports");
+        doc_ids.len()
+    }
+
+    /// Cancel one file of an import, removing its document if it was
+    /// already stored. Returns whether a document was removed.
+    pub async fn cancel_file(&self, namespace_id: NamespaceId, path: &Path) -> bool {
+        let (doc_id, dropped) = self.control.cancel_file(&namespace_id.to_string(), path);
+        self.drop_parked(dropped).await;
+        match doc_id {
+            Some(doc_id) => {
+                self.remove_cancelled(namespace_id, &doc_id).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Try documents and files that failed for good again, for one
+    /// collection or all of them. Files go back through the import queue.
+    ///
+    /// Returns the number of documents and files retried.
+    pub async fn retry_failed(self: &Arc<Self>, namespace_id: Option<NamespaceId>) -> usize {
+        let collection_id = namespace_id.map(|ns| ns.to_string());
+        let (jobs, files) = self.retries.take_failed(collection_id.as_deref());
+        let count = jobs.len() + files.len();
+
+        let senders = self.senders();
+        for job in jobs {
             self.progress
-                .apply(ProgressUpdate::Queued {
-                    collection_id: collection_id.clone(),
-                    stage: Stage::Store,
+                .apply(ProgressUpdate::Retrying {
+                    collection_id: job.collection_id(),
+                    stage: job.stage(),
+                    was_failed: true,
                 })
                 .await;
+            senders.send(job);
+        }
+
+        let mut batches: Vec<(String, Vec<String>, Vec<PathBuf>)> = Vec::new();
+        for file in files {
+            match batches
+                .iter_mut()
+                .find(|(id, tags, _)| *id == file.collection_id && *tags == file.tags)
+            {
+                Some((_, _, paths)) => paths.push(file.path),
+                None => batches.push((file.collection_id, file.tags, vec![file.path])),
+            }
+        }
+        for (collection_id, tags, paths) in batches {
+            let Ok(namespace_id) = collection_id.parse::<NamespaceId>() else {
+                continue;
+            };
+            let pipeline = self.clone();
+            tokio::spawn(async move {
+                pipeline
+                    .import_tagged_files(namespace_id, paths, tags)
+                    .await;
+            });
+        }
 
+        tracing::info!(count, "Retrying failed imports");
+        count
+    }
+
+    /// Send resumed jobs back to their stage.
+    fn requeue_parked(&self, jobs: Vec<ParkedJob>) {
+        let senders = self.senders();
+        for job in jobs {
+            senders.send(job);
+        }
+    }
+
+    fn senders(&self) -> JobSenders {
+        JobSenders {
+            extract: self.extract_tx.clone(),
+            ocr: self.ocr_tx.clone(),
+            embed: self.embed_tx.clone(),
+            index: self.index_tx.clone(),
+        }
+    }
+
+    /// Count parked jobs of a cancelled import as cancelled.
+    async fn drop_parked(&self, jobs: Vec<ParkedJob>) {
+        for job in jobs {
             self.progress
-                .apply(ProgressUpdate::Started {
-                    collection_id: collection_id.clone(),
-                    stage: Stage::Store,
+                .apply(ProgressUpdate::Cancelled {
+                    collection_id: job.collection_id(),
+                    stage: job.stage(),
+                    was_active: false,
                 })
                 .await;
+        }
+    }
 
-            // Store PDF source
-            let storage = self.storage.read().await;
-            let result = storage.store_pdf_source(&path, namespace_id).await;
-            drop(storage);
+    /// Delete a cancelled document from storage and the search index.
+    async fn remove_cancelled(&self, namespace_id: NamespaceId, doc_id: &str) {
+        self.retries.forget(doc_id);
+        let result = self
+            .storage
+            .read()
+            .await
+            .delete_document(namespace_id, doc_id)
+            .await;
+        if let Err(e) = result {
+            tracing::warn!(doc_id = %doc_id, error = %e, "Failed to remove cancelled document");
+        }
+        if let Err(e) = self
+            .index_worker
+            .delete_document_chunks(doc_id.to_string())
+            .await
+        {
+            tracing::warn!(doc_id = %doc_id, error = %e, "Failed to remove chunks of cancelled document");
+        }
+    }
+
+    /// Store queued `paths`, up to `store_workers` at a time and in the
+    /// order described in [`order`], removing each from the queue once
+    /// stored or failed.
+    async fn store_queued(
+        &self,
+        namespace_id: NamespaceId,
+        paths: Vec<PathBuf>,
+        tags: &[String],
+    ) -> Vec<(PathBuf, ReportedFile)> {
+        use futures::StreamExt;
+
+        let collection_id = namespace_id.to_string();
+
+        let cancels = self.control.track(&collection_id, &paths);
+        let mut files = Vec::with_capacity(paths.len());
+        for (path, cancel) in paths.into_iter().zip(cancels) {
+            let size = match queued_url(&path) {
+                Some(_) => None,
+                None => tokio::fs::metadata(&path).await.ok().map(|m| m.len()),
+            };
+            files.push((path, cancel, size));
+        }
+        let mut order = order::ImportOrder::new(files);
+        // Picked as workers free up, so pauses and priorities set meanwhile count.
+        let next = std::iter::from_fn(|| {
+            order.next(|path| {
+                (
+                    self.control.is_file_paused(&collection_id, path),
+                    self.control.priority(&collection_id, path),
+                )
+            })
+        });
+
+        futures::stream::iter(next)
+            .map(|(path, cancel)| async move {
+                let started = Instant::now();
+                let result = self.store_file(namespace_id, &path, tags, &cancel).await;
+                let file = ReportedFile::new(
+                    path.to_string_lossy().into_owned(),
+                    result,
+                    started.elapsed(),
+                );
+                (path, file)
+            })
+            .buffer_unordered(self.store_workers)
+            .collect()
+            .await
+    }
+
+    /// Tally a finished batch and write its report in the background, once
+    /// extraction has settled.
+    ///
+    /// Returns (successful_count, errors).
+    fn finish_batch(
+        &self,
+        namespace_id: NamespaceId,
+        started_at: chrono::DateTime<chrono::Utc>,
+        files: Vec<(PathBuf, ReportedFile)>,
+    ) -> (usize, Vec<(PathBuf, String)>) {
+        let mut success = 0;
+        let mut errors = Vec::new();
+        let mut reported = Vec::with_capacity(files.len());
+        for (path, file) in files {
+            match (file.status, &file.error) {
+                (FileStatus::Stored, _) => success += 1,
+                (FileStatus::Failed, Some(e)) => errors.push((path, e.clone())),
+                _ => {}
+            }
+            reported.push(file);
+        }
+        if reported.is_empty() {
+            return (success, errors);
+        }
+
+        let mut report = ImportReport::new(namespace_id.to_string(), started_at, reported);
+        let storage = self.storage.clone();
+        let progress = self.progress.clone();
+        let reports = self.import_reports.clone();
+        tokio::spawn(async move {
+            report.complete(&storage, &progress, namespace_id).await;
+            match reports.save(&report) {
+                Ok(path) => tracing::info!(path = %path.display(), "Wrote import report"),
+                Err(e) => tracing::warn!(error = %e, "Failed to write import report"),
+            }
+        });
+        (success, errors)
+    }
+
+    /// Store one queued file once it isn't paused. Returns the new
+    /// `doc_id`, or `None` if the file was cancelled first.
+    async fn store_file(
+        &self,
+        namespace_id: NamespaceId,
+        path: &Path,
+        tags: &[String],
+        cancel: &CancellationToken,
+    ) -> Option<Result<String, String>> {
+        let collection_id = namespace_id.to_string();
+
+        if !self
+            .control
+            .wait_to_store(&collection_id, path, cancel)
+            .await
+        {
+            tracing::info!(path = %path.display(), "Import cancelled before storing");
+            self.control.forget_file(&collection_id, path);
+            self.finish_queued(&collection_id, path);
+            return None;
+        }
+
+        // Track store stage
+        self.progress
+            .apply(ProgressUpdate::Queued {
+                collection_id: collection_id.clone(),
+                stage: Stage::Store,
+            })
+            .await;
 
+        self.progress
+            .apply(ProgressUpdate::Started {
+                collection_id: collection_id.clone(),
+                stage: Stage::Store,
+            })
+            .await;
+        let file = FileProgress::file(&collection_id, path);
+        self.progress.report_file(file.clone());
+
+        let mut attempt = 0;
+        let result = loop {
+            let result = match (queued_url(path), remote::parse_queued(path)) {
+                (Some(url), _) => self.store_download(namespace_id, url, tags).await,
+                (None, Some((source_id, key))) => {
+                    self.store_remote(namespace_id, source_id, key, tags).await
+                }
+                (None, None) => {
+                    let storage = self.storage.read().await;
+                    storage.store_pdf_source(path, namespace_id, tags).await
+                }
+            };
             match result {
-                Ok(doc_id) => {
-                    tracing::info!(doc_id = %doc_id, path = %path.display(), "Stored PDF source");
-                    self.progress
-                        .apply(ProgressUpdate::Completed {
-                            collection_id: collection_id.clone(),
-                            stage: Stage::Store,
-                        })
-                        .await;
-                    success += 1;
-                    // InsertLocal(files/*/source) will trigger extract via watcher
+                Err(e) if attempt < self.retries.max_attempts() && retry::is_transient(&e) => {
+                    attempt += 1;
+                    let delay = retry::backoff(attempt);
+                    tracing::warn!(
+                        path = %path.display(),
+                        attempt,
+                        delay_secs = delay.as_secs(),
+                        error = %e,
+                        "Transient store failure, retrying"
+                    );
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = cancel.cancelled() => break Err(e),
+                    }
                 }
+                result => break result,
+            }
+        };
+
+        let result = match result {
+            Ok(doc_id) => {
+                tracing::info!(doc_id = %doc_id, path = %path.display(), "Stored PDF source");
+                self.control.stored(&collection_id, path, &doc_id);
+                self.progress.report_file(file.with_doc_id(&doc_id).done());
+                self.progress
+                    .apply(ProgressUpdate::Completed {
+                        collection_id: collection_id.clone(),
+                        stage: Stage::Store,
+                    })
+                    .await;
+                // InsertLocal(files/*/source) will trigger extract via watcher
+                Ok(doc_id)
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), error = %e, "Failed to store PDF");
+                self.control.forget_file(&collection_id, path);
+                self.retries.file_failed(FailedFile {
+                    collection_id: collection_id.clone(),
+                    path: path.to_path_buf(),
+                    tags: tags.to_vec(),
+                });
+                self.progress.report_file(file.failed(e.to_string()));
+                self.progress
+                    .apply(ProgressUpdate::Failed {
+                        collection_id: collection_id.clone(),
+                        stage: Stage::Store,
+                        error: e.to_string(),
+                    })
+                    .await;
+                Err(e.to_string())
+            }
+        };
+
+        self.finish_queued(&collection_id, path);
+        Some(result)
+    }
+
+    /// Download `url` and store it as a new source. Storage isn't locked
+    /// during the download.
+    async fn store_download(
+        &self,
+        namespace_id: NamespaceId,
+        url: &str,
+        tags: &[String],
+    ) -> anyhow::Result<String> {
+        let download = crate::net::download(url).await?;
+        tracing::info!(url = %url, bytes = download.bytes.len(), "Downloaded import");
+        let storage = self.storage.read().await;
+        storage
+            .store_downloaded_source(
+                namespace_id,
+                url,
+                download.file_name,
+                &download.bytes,
+                download.file_type,
+                tags,
+            )
+            .await
+    }
+
+    /// Fetch a queued object from remote storage and store it as a new
+    /// source. Returns the new `doc_id`.
+    async fn store_remote(
+        &self,
+        namespace_id: NamespaceId,
+        source_id: &str,
+        key: &str,
+        tags: &[String],
+    ) -> anyhow::Result<String> {
+        let remote = self
+            .remotes
+            .read()
+            .await
+            .get(source_id)
+            .cloned()
+            .with_context(|| format!("Remote source {} no longer exists", source_id))?;
+        let download = remote.fetch(key).await?;
+        tracing::info!(source = %source_id, key = %key, bytes = download.bytes.len(), "Fetched remote import");
+        let storage = self.storage.read().await;
+        storage
+            .store_downloaded_source(
+                namespace_id,
+                &remote.object_url(key),
+                download.file_name,
+                &download.bytes,
+                download.file_type,
+                tags,
+            )
+            .await
+    }
+
+    fn finish_queued(&self, collection_id: &str, path: &Path) {
+        if let Err(e) = self.import_queue.finish(collection_id, path) {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to update import queue");
+        }
+    }
+
+    /// Re-queue documents this node imported whose source was stored but
+    /// never extracted; see [`Storage::find_pending_extractions`]. The
+    /// extract queue is in-memory, so these are left behind when the app
+    /// closes mid-import.
+    async fn requeue_pending_extractions(&self) {
+        let storage = self.storage.read().await;
+        let collections = match storage.list_collections().await {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!(error = %e, "requeue_pending_extractions: list_collections failed");
+                return;
+            }
+        };
+        for (namespace_id, _) in collections {
+            let pending = match storage.find_pending_extractions(namespace_id).await {
+                Ok(v) => v,
                 Err(e) => {
-                    tracing::error!(path = %path.display(), error = %e, "Failed to store PDF");
-                    self.progress
-                        .apply(ProgressUpdate::Failed {
-                            collection_id: collection_id.clone(),
-                            stage: Stage::Store,
-                            error: e.to_string(),
-                        })
-                        .await;
-                    errors.push((path, e.to_string()));
+                    tracing::warn!(
+                        namespace = %namespace_id,
+                        error = %e,
+                        "find_pending_extractions failed"
+                    );
+                    continue;
                 }
+            };
+            for doc_id in pending {
+                self.progress
+                    .queue(&namespace_id.to_string(), Stage::Extract)
+                    .await;
+                let _ = self.extract_tx.send(ExtractJob {
+                    namespace_id,
+                    doc_id,
+                });
             }
         }
-
-        (success, errors)
     }
 
     /// Re-queue any orphan `ocr_task` entries — documents whose extract
@@ -306,6 +1169,329 @@ impl Pipeline {
         }
     }
 
+    /// Re-queue documents that have extracted text but no embeddings for
+    /// the active embedding model. Covers imports interrupted by an app
+    /// restart (the embed queue is in-memory) and documents imported under
+    /// a different model. No-op when no embedder is configured.
+    ///
+    /// Returns the number of documents queued.
+    pub async fn requeue_pending_embeddings(&self) -> usize {
+        let Some(model_id) = self.models.embedding_model_id().await else {
+            return 0;
+        };
+
+        let storage = self.storage.read().await;
+        let collections = match storage.list_collections().await {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!(error = %e, "requeue_pending_embeddings: list_collections failed");
+                return 0;
+            }
+        };
+        let mut requeued = 0;
+        for (namespace_id, _) in collections {
+            let pending = match storage
+                .find_pending_embeddings(namespace_id, &model_id)
+                .await
+            {
+                Ok(v) => v,
+                Err(e) => {
+                    tracing::warn!(
+                        namespace = %namespace_id,
+                        error = %e,
+                        "find_pending_embeddings failed"
+                    );
+                    continue;
+                }
+            };
+            for doc_id in pending {
+                self.progress
+                    .queue(&namespace_id.to_string(), Stage::Embed)
+                    .await;
+                let _ = self.embed_tx.send(EmbedJob {
+                    namespace_id,
+                    doc_id,
+                });
+                requeued += 1;
+            }
+        }
+
+        if requeued > 0 {
+            tracing::info!(count = requeued, model = %model_id, "Re-queued pending embeddings");
+        }
+        requeued
+    }
+
+    /// Index documents from their stored embeddings for the active model,
+    /// without re-embedding. For documents that dropped out of the search
+    /// index. Returns the number queued; 0 when no embedder is configured.
+    pub async fn reindex_documents(
+        &self,
+        namespace_id: NamespaceId,
+        doc_ids: Vec<String>,
+    ) -> usize {
+        let Some(model_id) = self.models.embedding_model_id().await else {
+            return 0;
+        };
+
+        let collection_id = namespace_id.to_string();
+        let count = doc_ids.len();
+        for doc_id in doc_ids {
+            self.progress.queue(&collection_id, Stage::Index).await;
+            let _ = self.index_tx.send(IndexJob {
+                namespace_id,
+                doc_id,
+                model_id: model_id.clone(),
+            });
+        }
+        count
+    }
+
+    /// Index every document from its stored embeddings for the active
+    /// model. Run after switching models, so documents embedded with the
+    /// new model ahead of time become searchable without embedding them
+    /// again. Returns the number queued; 0 when no embedder is configured.
+    pub async fn index_stored_embeddings(&self) -> usize {
+        let Some(model_id) = self.models.embedding_model_id().await else {
+            return 0;
+        };
+
+        let mut queued = 0;
+        let storage = self.storage.read().await;
+        let collections = match storage.list_collections().await {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!(error = %e, "index_stored_embeddings: list_collections failed");
+                return 0;
+            }
+        };
+        for (namespace_id, _) in collections {
+            let doc_ids = match storage.list_documents_with_text(namespace_id).await {
+                Ok(ids) => ids,
+                Err(e) => {
+                    tracing::warn!(
+                        namespace = %namespace_id,
+                        error = %e,
+                        "Failed to list documents"
+                    );
+                    continue;
+                }
+            };
+            let collection_id = namespace_id.to_string();
+            for doc_id in doc_ids {
+                if !matches!(
+                    storage
+                        .has_embeddings(namespace_id, &doc_id, &model_id)
+                        .await,
+                    Ok(true)
+                ) {
+                    continue;
+                }
+                self.progress.queue(&collection_id, Stage::Index).await;
+                let _ = self.index_tx.send(IndexJob {
+                    namespace_id,
+                    doc_id,
+                    model_id: model_id.clone(),
+                });
+                queued += 1;
+            }
+        }
+        if queued > 0 {
+            tracing::info!(count = queued, model = %model_id, "Indexing stored embeddings");
+        }
+        queued
+    }
+
+    /// Embed one document with `embedder` and store the vectors under
+    /// `files/{id}/embeddings/{model_id}`, replacing any already there.
+    /// Vectors for the active model are then indexed by the watcher;
+    /// others wait until that model is adopted. For re-embed jobs, which
+    /// may use a model other than the active one.
+    pub async fn embed_document_with(
+        &self,
+        namespace_id: NamespaceId,
+        doc_id: &str,
+        embedder: &dyn EmbeddingProvider,
+        model_id: &str,
+    ) -> anyhow::Result<()> {
+        let job = EmbedJob {
+            namespace_id,
+            doc_id: doc_id.to_string(),
+        };
+        let default_chunking = self.chunking.read().await.clone();
+        let ctx = embed::EmbedContext {
+            models: &self.models,
+            progress: &self.progress,
+            cache: &self.embedding_cache,
+            inference: &self.inference,
+            vector_encoding: self.vector_encoding,
+        };
+        workers::embed_document(
+            &job,
+            embedder,
+            model_id,
+            &self.storage,
+            &default_chunking,
+            &ctx,
+        )
+        .await
+    }
+
+    /// Queue every document with extracted text for the embed workers,
+    /// even if embeddings already exist, so they are redone with the
+    /// active model. Returns the number of documents queued.
+    async fn queue_collection_embeds(&self, namespace_id: NamespaceId) -> anyhow::Result<usize> {
+        let doc_ids = {
+            let storage = self.storage.read().await;
+            storage.list_documents_with_text(namespace_id).await?
+        };
+
+        let collection_id = namespace_id.to_string();
+        for doc_id in &doc_ids {
+            self.progress.queue(&collection_id, Stage::Embed).await;
+            let _ = self.embed_tx.send(EmbedJob {
+                namespace_id,
+                doc_id: doc_id.clone(),
+            });
+        }
+
+        tracing::info!(
+            namespace = %namespace_id,
+            count = doc_ids.len(),
+            "Queued collection for re-embedding"
+        );
+        Ok(doc_ids.len())
+    }
+
+    /// Set or clear a collection's vector projection, then re-embed it so
+    /// stored and indexed vectors move to the new space.
+    ///
+    /// The projection is built for the active embedding model, reducing
+    /// its native size to `target_dims`. Returns the number of documents
+    /// queued for re-embedding.
+    pub async fn set_collection_projection(
+        &self,
+        namespace_id: NamespaceId,
+        target_dims: Option<usize>,
+    ) -> anyhow::Result<usize> {
+        let model_id = self
+            .models
+            .embedding_model_id()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Embedder not configured"))?;
+
+        let projection = match target_dims {
+            Some(target) => {
+                let source = crate::models::get_embedding_model(&model_id)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown embedding model: {}", model_id))?
+                    .dimensions;
+                let spec = ProjectionSpec::new(&model_id, source, target)?;
+                Some(Projection::generate(spec))
+            }
+            None => None,
+        };
+
+        {
+            let storage = self.storage.read().await;
+            storage
+                .set_collection_projection(namespace_id, projection.as_ref())
+                .await?;
+        }
+
+        tracing::info!(
+            namespace = %namespace_id,
+            target_dims = ?target_dims,
+            "Collection projection updated"
+        );
+        self.queue_collection_embeds(namespace_id).await
+    }
+
+    /// Current global chunking default.
+    pub async fn chunking_config(&self) -> ChunkingConfig {
+        self.chunking.read().await.clone()
+    }
+
+    /// Replace the global chunking default. Applies to embed jobs picked
+    /// up from now on; already-embedded documents keep their chunks until
+    /// re-embedded.
+    pub async fn set_chunking_config(&self, chunking: ChunkingConfig) {
+        *self.chunking.write().await = chunking;
+    }
+
+    /// Replace the remote sources queued objects are fetched from. Call
+    /// before resuming imports and whenever the sources change.
+    pub async fn set_remotes(&self, remotes: Vec<Remote>) {
+        *self.remotes.write().await = remotes
+            .into_iter()
+            .map(|r| (r.id().to_string(), r))
+            .collect();
+    }
+
+    /// Replace the hooks run as documents come and go.
+    pub fn set_hooks(&self, hooks: Vec<Hook>) {
+        self.hooks.set(hooks);
+    }
+
+    /// Hit/miss counters for the chunk embedding cache.
+    pub fn embedding_cache_stats(&self) -> EmbeddingCacheStats {
+        self.embedding_cache.stats()
+    }
+
+    /// Drop the cached chunk vectors, e.g. when the embedding model
+    /// changes and the old model's vectors would only take up room.
+    pub fn clear_embedding_cache(&self) {
+        self.embedding_cache.clear();
+    }
+
+    /// Subscribe to chunk-level embedding progress events.
+    pub fn subscribe_embedding_progress(&self) -> broadcast::Receiver<EmbeddingProgress> {
+        self.progress.subscribe_embedding()
+    }
+
+    /// Subscribe to per-file stage progress events.
+    pub fn subscribe_file_progress(&self) -> broadcast::Receiver<FileProgress> {
+        self.progress.subscribe_files()
+    }
+
+    /// Subscribe to documents arriving from peers, step by step.
+    pub fn subscribe_synced_documents(&self) -> broadcast::Receiver<SyncedDocument> {
+        self.progress.subscribe_synced()
+    }
+
+    /// How syncing a collection with each peer is going, most recently
+    /// seen peer first.
+    pub fn sync_health(&self, namespace_id: &NamespaceId) -> Vec<PeerSyncHealth> {
+        self.sync_health.collection(&namespace_id.to_string())
+    }
+
+    /// Data a collection exchanged with peers since the app started.
+    pub fn transfer_stats(&self, namespace_id: &NamespaceId) -> CollectionTransfer {
+        self.transfer_stats.collection(&namespace_id.to_string())
+    }
+
+    /// Subscribe to transfer counts of collections as they exchange data.
+    pub fn subscribe_transfer_stats(&self) -> broadcast::Receiver<CollectionTransfer> {
+        self.transfer_stats.subscribe()
+    }
+
+    /// Count content peers download from this node toward the collections
+    /// holding it.
+    pub fn count_uploads(&self, uploads: broadcast::Receiver<Upload>) {
+        self.transfer_stats
+            .count_uploads(uploads, self.cancel.child_token());
+    }
+
+    /// Embed and index rates with an estimated time to finish.
+    pub async fn throughput(&self) -> ThroughputStats {
+        self.progress.throughput().await
+    }
+
+    /// Subscribe to throughput snapshots, sent every few seconds while
+    /// documents are being processed.
+    pub fn subscribe_throughput(&self) -> broadcast::Receiver<ThroughputStats> {
+        self.progress.subscribe_throughput()
+    }
+
     /// Get progress for a collection.
     pub async fn get_progress(&self, collection_id: &str) -> Option<PipelineProgress> {
         self.progress.get(collection_id).await
@@ -333,3 +1519,9 @@ impl Drop for Pipeline {
         self.cancel.cancel();
     }
 }
+
+/// The URL a queued path stands for, if it was imported from the web.
+fn queued_url(path: &Path) -> Option<&str> {
+    path.to_str()
+        .filter(|p| p.starts_with("http://") || p.starts_with("https://"))
+}
@@ -26,7 +26,9 @@ use crate::pdf::{rasterize_page, OcrTask, PageDecision, RASTER_DPI};
 use crate::provider::local::ocr::OCR_PAGE_TIMEOUT;
 use crate::storage::Storage;
 
-use super::progress::ProgressTracker;
+use super::control::{Admission, ImportControl, ParkedJob};
+use super::progress::{FileProgress, ProgressTracker};
+use super::retry::Retries;
 use super::types::{OcrJob, ProgressUpdate, Stage};
 use super::workers::SharedReceiver;
 
@@ -41,12 +43,16 @@ pub fn spawn_ocr_workers(
     rx: SharedReceiver<OcrJob>,
     storage: Arc<RwLock<Storage>>,
     models: Arc<ModelManager>,
+    control: Arc<ImportControl>,
+    retries: Arc<Retries>,
     progress: ProgressTracker,
 ) {
     for i in 0..count {
         let rx = rx.clone();
         let storage = storage.clone();
         let models = models.clone();
+        let control = control.clone();
+        let retries = retries.clone();
         let progress = progress.clone();
         let mut focus_guard = models.focus_guard();
 
@@ -56,15 +62,59 @@ pub fn spawn_ocr_workers(
                 focus_guard.wait_until_released().await;
                 let collection_id = job.namespace_id.to_string();
 
+                let cancel = match control.admit(&collection_id, &job.doc_id) {
+                    Admission::Run(cancel) => cancel,
+                    Admission::Park => {
+                        control.park(ParkedJob::Ocr(job));
+                        continue;
+                    }
+                    Admission::Drop => {
+                        progress
+                            .apply(ProgressUpdate::Cancelled {
+                                collection_id,
+                                stage: Stage::Ocr,
+                                was_active: false,
+                            })
+                            .await;
+                        continue;
+                    }
+                };
+
                 progress
                     .apply(ProgressUpdate::Started {
                         collection_id: collection_id.clone(),
                         stage: Stage::Ocr,
                     })
                     .await;
+                progress.report_file(FileProgress::doc(&collection_id, &job.doc_id, Stage::Ocr));
+
+                // A cancel between pages leaves the task entry for a later
+                // run; the cancelled document is removed anyway.
+                let result = tokio::select! {
+                    result = run_ocr_job(&job, &storage, &models, &progress) => result,
+                    _ = cancel.cancelled() => {
+                        tracing::info!(doc_id = %job.doc_id, "OCR cancelled");
+                        progress.report_file(
+                            FileProgress::doc(&collection_id, &job.doc_id, Stage::Ocr)
+                                .failed("Cancelled"),
+                        );
+                        progress
+                            .apply(ProgressUpdate::Cancelled {
+                                collection_id,
+                                stage: Stage::Ocr,
+                                was_active: true,
+                            })
+                            .await;
+                        continue;
+                    }
+                };
 
-                match run_ocr_job(&job, &storage, &models, &progress).await {
+                match result {
                     Ok(()) => {
+                        retries.succeeded(&job.doc_id);
+                        progress.report_file(
+                            FileProgress::doc(&collection_id, &job.doc_id, Stage::Ocr).done(),
+                        );
                         progress
                             .apply(ProgressUpdate::Completed {
                                 collection_id,
@@ -73,11 +123,26 @@ pub fn spawn_ocr_workers(
                             .await;
                     }
                     Err(e) => {
+                        if retries.retry(ParkedJob::Ocr(job.clone()), &e).is_some() {
+                            progress
+                                .apply(ProgressUpdate::Retrying {
+                                    collection_id,
+                                    stage: Stage::Ocr,
+                                    was_failed: false,
+                                })
+                                .await;
+                            continue;
+                        }
                         tracing::error!(
                             doc_id = %job.doc_id,
                             error = ?e,
                             "OCR failed; leaving ocr_task in place for retry",
                         );
+                        control.finished(&job.doc_id);
+                        progress.report_file(
+                            FileProgress::doc(&collection_id, &job.doc_id, Stage::Ocr)
+                                .failed(e.to_string()),
+                        );
                         progress
                             .apply(ProgressUpdate::Failed {
                                 collection_id,
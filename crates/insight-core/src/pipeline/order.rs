@@ -0,0 +1,123 @@
+//! The order queued files are stored in.
+//!
+//! Everything after the store stage runs in the order documents arrive, so
+//! the order files are stored in decides what finishes first. Rather than
+//! strict submission order, the next file is picked each time a store
+//! worker frees up: files the user raised first, then the smallest, so a
+//! batch shows results quickly and big scans — the slow OCR jobs — run
+//! last. Files paused on their own go after everything else so they don't
+//! hold up the rest. Ties keep submission order.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+/// User-set priority of a file waiting to be imported.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl ImportPriority {
+    pub fn is_normal(&self) -> bool {
+        *self == Self::Normal
+    }
+}
+
+struct Waiting {
+    path: PathBuf,
+    cancel: CancellationToken,
+    /// Unknown for links, which are only sized once downloaded; they go
+    /// after local files of the same priority.
+    size: Option<u64>,
+}
+
+/// Files not yet handed to a store worker.
+pub(super) struct ImportOrder {
+    /// In submission order.
+    waiting: Vec<Waiting>,
+}
+
+impl ImportOrder {
+    pub fn new(files: impl IntoIterator<Item = (PathBuf, CancellationToken, Option<u64>)>) -> Self {
+        Self {
+            waiting: files
+                .into_iter()
+                .map(|(path, cancel, size)| Waiting { path, cancel, size })
+                .collect(),
+        }
+    }
+
+    /// Take the file to store next. `state` gives a file's current pause
+    /// and priority, which may have changed since it was queued.
+    pub fn next(
+        &mut self,
+        state: impl Fn(&Path) -> (bool, ImportPriority),
+    ) -> Option<(PathBuf, CancellationToken)> {
+        let index = self
+            .waiting
+            .iter()
+            .enumerate()
+            .min_by_key(|(index, file)| {
+                let (paused, priority) = state(&file.path);
+                (
+                    paused,
+                    std::cmp::Reverse(priority),
+                    file.size.unwrap_or(u64::MAX),
+                    *index,
+                )
+            })?
+            .0;
+        let file = self.waiting.remove(index);
+        Some((file.path, file.cancel))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_order() {
+        let files = [
+            ("big.pdf", Some(50_000_000)),
+            ("link", None),
+            ("small.pdf", Some(10_000)),
+            ("medium.pdf", Some(2_000_000)),
+            ("paused.pdf", Some(1)),
+            ("also-small.pdf", Some(10_000)),
+        ];
+        let mut order = ImportOrder::new(
+            files
+                .iter()
+                .map(|(p, size)| (PathBuf::from(p), CancellationToken::new(), *size)),
+        );
+        let mut priorities = HashMap::new();
+        priorities.insert(PathBuf::from("big.pdf"), ImportPriority::High);
+        priorities.insert(PathBuf::from("small.pdf"), ImportPriority::Low);
+        let state = |path: &Path| {
+            (
+                path == Path::new("paused.pdf"),
+                priorities.get(path).copied().unwrap_or_default(),
+            )
+        };
+
+        let mut next = || order.next(state).map(|(path, _)| path);
+        assert_eq!(next().unwrap(), Path::new("big.pdf"));
+        assert_eq!(next().unwrap(), Path::new("also-small.pdf"));
+        assert_eq!(next().unwrap(), Path::new("medium.pdf"));
+        assert_eq!(next().unwrap(), Path::new("link"));
+        assert_eq!(next().unwrap(), Path::new("small.pdf"));
+        assert_eq!(next().unwrap(), Path::new("paused.pdf"));
+        assert!(next().is_none());
+    }
+}
@@ -0,0 +1,245 @@
+//! Import preview: what importing a batch of files would do, without
+//! storing anything.
+//!
+//! Each file is read and its text extracted exactly as the extract stage
+//! would, then checked against the collection for duplicates. Nothing is
+//! written to storage, the queue or the index.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use iroh_blobs::Hash;
+use iroh_docs::NamespaceId;
+use serde::Serialize;
+
+use crate::agent::summarize::CHARS_PER_TOKEN;
+use crate::pdf::PageDecision;
+use crate::provider::ChunkingConfig;
+use crate::storage::{fingerprint, DocumentMetadata, Storage};
+
+/// How a previewed file relates to documents already stored.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DuplicateStatus {
+    New,
+    /// The same file is already in the collection; import skips it.
+    Exact {
+        document_id: String,
+        document_name: String,
+    },
+    /// The same file is in other collections and could be linked instead.
+    OtherCollections {
+        collection_ids: Vec<String>,
+    },
+    /// Different file, nearly identical text.
+    Near {
+        document_id: String,
+        document_name: String,
+        similarity: f32,
+    },
+}
+
+/// What importing one file would produce.
+#[derive(Debug, Clone, Serialize)]
+pub struct FilePreview {
+    pub path: PathBuf,
+    pub name: String,
+    pub size_bytes: u64,
+    pub page_count: usize,
+    /// Pages without a usable text layer, which would go through OCR.
+    pub ocr_pages: usize,
+    /// Characters of text extracted without OCR.
+    pub characters: usize,
+    pub language: String,
+    pub duplicate: DuplicateStatus,
+    /// Chunks the embed stage would produce from the text above.
+    pub estimated_chunks: usize,
+    /// Seconds to embed those chunks at the current measured rate, if
+    /// anything has been embedded recently enough to measure one.
+    pub estimated_embed_secs: Option<f64>,
+    /// Why the file couldn't be previewed; it would likely fail to import.
+    pub error: Option<String>,
+}
+
+impl FilePreview {
+    pub(super) fn failed(path: &Path, error: String) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            name: file_name(path),
+            size_bytes: 0,
+            page_count: 0,
+            ocr_pages: 0,
+            characters: 0,
+            language: "unknown".to_string(),
+            duplicate: DuplicateStatus::New,
+            estimated_chunks: 0,
+            estimated_embed_secs: None,
+            error: Some(error),
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Roughly how many chunks `chars` characters split into.
+fn estimate_chunks(chars: usize, chunking: &ChunkingConfig) -> usize {
+    if chars == 0 {
+        return 0;
+    }
+    let tokens = chars.div_ceil(CHARS_PER_TOKEN);
+    let target = chunking.target_tokens.max(1);
+    let step = target.saturating_sub(chunking.overlap_tokens).max(1);
+    let chunks = 1 + tokens.saturating_sub(target).div_ceil(step);
+    match chunking.max_chunks_per_doc {
+        Some(max) => chunks.min(max),
+        None => chunks,
+    }
+}
+
+/// Preview one file. `documents` is the collection's current contents,
+/// listed once for the whole batch.
+pub(super) async fn preview_file(
+    storage: &Storage,
+    namespace_id: NamespaceId,
+    documents: &[DocumentMetadata],
+    path: &Path,
+    chunking: &ChunkingConfig,
+    chunks_per_sec: f64,
+) -> FilePreview {
+    match preview_inner(
+        storage,
+        namespace_id,
+        documents,
+        path,
+        chunking,
+        chunks_per_sec,
+    )
+    .await
+    {
+        Ok(preview) => preview,
+        Err(e) => FilePreview::failed(path, format!("{:#}", e)),
+    }
+}
+
+async fn preview_inner(
+    storage: &Storage,
+    namespace_id: NamespaceId,
+    documents: &[DocumentMetadata],
+    path: &Path,
+    chunking: &ChunkingConfig,
+    chunks_per_sec: f64,
+) -> Result<FilePreview> {
+    let bytes = tokio::fs::read(path).await.context("Failed to read file")?;
+    let size_bytes = bytes.len() as u64;
+    let hash = Hash::new(&bytes);
+
+    let extracted = tokio::task::spawn_blocking(move || crate::pdf::extract_text_from_bytes(bytes))
+        .await
+        .context("Extract task panicked")??;
+    let (text, _) = extracted.digital_text_concatenated();
+    let ocr_pages = extracted
+        .pages
+        .iter()
+        .filter(|p| p.decision == PageDecision::NeedsOcr)
+        .count();
+    let characters = text.chars().count();
+
+    let duplicate = duplicate_status(storage, namespace_id, documents, &hash, &text).await?;
+    let estimated_chunks = estimate_chunks(characters, chunking);
+    let estimated_embed_secs = if chunks_per_sec > 0.0 {
+        Some(estimated_chunks as f64 / chunks_per_sec)
+    } else {
+        None
+    };
+
+    Ok(FilePreview {
+        path: path.to_path_buf(),
+        name: file_name(path),
+        size_bytes,
+        page_count: extracted.page_count,
+        ocr_pages,
+        characters,
+        language: crate::agent::tools::describe_language(&text).to_string(),
+        duplicate,
+        estimated_chunks,
+        estimated_embed_secs,
+        error: None,
+    })
+}
+
+async fn duplicate_status(
+    storage: &Storage,
+    namespace_id: NamespaceId,
+    documents: &[DocumentMetadata],
+    hash: &Hash,
+    text: &str,
+) -> Result<DuplicateStatus> {
+    if let Some(doc_id) = storage.find_source(namespace_id, hash).await? {
+        let document_name = documents
+            .iter()
+            .find(|d| d.id == doc_id)
+            .map(|d| d.name.clone())
+            .unwrap_or_default();
+        return Ok(DuplicateStatus::Exact {
+            document_id: doc_id,
+            document_name,
+        });
+    }
+
+    let elsewhere = storage.find_source_elsewhere(namespace_id, hash).await?;
+    if !elsewhere.is_empty() {
+        let collection_ids = elsewhere.iter().map(|(ns, _)| ns.to_string()).collect();
+        return Ok(DuplicateStatus::OtherCollections { collection_ids });
+    }
+
+    if let Some(simhash) = fingerprint::simhash(text) {
+        let closest = documents
+            .iter()
+            .filter_map(|d| {
+                let other = d.simhash?;
+                fingerprint::is_near_duplicate(simhash, other)
+                    .then(|| (d, fingerprint::similarity(simhash, other)))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((document, similarity)) = closest {
+            return Ok(DuplicateStatus::Near {
+                document_id: document.id.clone(),
+                document_name: document.name.clone(),
+                similarity,
+            });
+        }
+    }
+
+    Ok(DuplicateStatus::New)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_chunks() {
+        let chunking = ChunkingConfig {
+            target_tokens: 100,
+            overlap_tokens: 20,
+            max_chunks_per_doc: None,
+            ..ChunkingConfig::default()
+        };
+        assert_eq!(estimate_chunks(0, &chunking), 0);
+        // Up to one chunk's worth of tokens
+        assert_eq!(estimate_chunks(300, &chunking), 1);
+        // 180 tokens: the first chunk, then one 80-token step
+        assert_eq!(estimate_chunks(540, &chunking), 2);
+        assert_eq!(estimate_chunks(543, &chunking), 3);
+
+        let capped = ChunkingConfig {
+            max_chunks_per_doc: Some(2),
+            ..chunking
+        };
+        assert_eq!(estimate_chunks(100_000, &capped), 2);
+    }
+}
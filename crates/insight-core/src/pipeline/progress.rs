@@ -1,11 +1,15 @@
 //! Progress tracking for the document processing pipeline.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
 
+use super::throughput::{Throughput, ThroughputStats};
 use super::types::{ProgressUpdate, Stage};
 
 /// Progress for a single processing stage.
@@ -15,12 +19,14 @@ pub struct StageProgress {
     pub active: usize,
     pub completed: usize,
     pub failed: usize,
+    #[serde(default)]
+    pub cancelled: usize,
 }
 
 impl StageProgress {
     /// Total documents that entered this stage.
     pub fn total(&self) -> usize {
-        self.pending + self.active + self.completed + self.failed
+        self.pending + self.active + self.completed + self.failed + self.cancelled
     }
 
     /// Check if stage has any active work.
@@ -37,6 +43,125 @@ pub struct DocProgress {
     pub total: usize,
 }
 
+/// Chunk-level progress for a document in the embed stage.
+///
+/// Broadcast separately from [`PipelineProgress`] because it fires once
+/// per embedding batch, which is far more often than stage transitions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingProgress {
+    pub collection_id: String,
+    pub doc_id: String,
+    pub chunks_done: usize,
+    pub chunks_total: usize,
+}
+
+/// Where one file is within a stage, so a large PDF that takes minutes to
+/// OCR or embed visibly moves.
+///
+/// Sent when a file enters a stage (0%), as pages or chunk batches finish,
+/// and when it leaves the stage (100%, or with `error`). Files are known by
+/// `path` until stored and by `doc_id` after; the store stage's final event
+/// carries both.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileProgress {
+    pub collection_id: String,
+    pub doc_id: Option<String>,
+    pub path: Option<PathBuf>,
+    pub stage: Stage,
+    /// Pages (OCR) or chunks (embed) done, when the stage counts them.
+    pub current: usize,
+    pub total: usize,
+    /// Percent of this stage done for this file.
+    pub percent: u8,
+    pub error: Option<String>,
+}
+
+impl FileProgress {
+    /// A stored document entering `stage`.
+    pub fn doc(collection_id: &str, doc_id: &str, stage: Stage) -> Self {
+        Self {
+            collection_id: collection_id.to_string(),
+            doc_id: Some(doc_id.to_string()),
+            path: None,
+            stage,
+            current: 0,
+            total: 0,
+            percent: 0,
+            error: None,
+        }
+    }
+
+    /// A file being stored.
+    pub fn file(collection_id: &str, path: &Path) -> Self {
+        Self {
+            collection_id: collection_id.to_string(),
+            doc_id: None,
+            path: Some(path.to_path_buf()),
+            stage: Stage::Store,
+            current: 0,
+            total: 0,
+            percent: 0,
+            error: None,
+        }
+    }
+
+    /// `current` of `total` steps done.
+    pub fn at(mut self, current: usize, total: usize) -> Self {
+        self.current = current;
+        self.total = total;
+        self.percent = if total == 0 {
+            0
+        } else {
+            (current.min(total) * 100 / total) as u8
+        };
+        self
+    }
+
+    /// The stage finished for this file.
+    pub fn done(mut self) -> Self {
+        self.current = self.total;
+        self.percent = 100;
+        self
+    }
+
+    /// The stage failed for this file.
+    pub fn failed(mut self, error: impl Into<String>) -> Self {
+        self.error = Some(error.into());
+        self
+    }
+
+    pub fn with_doc_id(mut self, doc_id: &str) -> Self {
+        self.doc_id = Some(doc_id.to_string());
+        self
+    }
+}
+
+/// How far a document written by a peer has got here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    /// Its metadata arrived, so it can be listed.
+    Received,
+    /// Its source, text or embeddings are being downloaded from the peer.
+    Downloading,
+    /// The peer's embeddings are for the model in use here and are indexed
+    /// as they are.
+    EmbeddingReused,
+    /// It was embedded here, with the model in use here.
+    EmbeddingRegenerated,
+    /// It's searchable.
+    Indexed,
+}
+
+/// A step of a document arriving from a peer, so it can appear while it
+/// syncs rather than on the next refresh.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncedDocument {
+    pub collection_id: String,
+    pub doc_id: String,
+    pub status: SyncStatus,
+}
+
 /// Progress for a collection across all pipeline stages.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PipelineProgress {
@@ -47,6 +172,10 @@ pub struct PipelineProgress {
     pub embed: StageProgress,
     pub index: StageProgress,
 
+    /// Whether imports into this collection are paused.
+    #[serde(default)]
+    pub paused: bool,
+
     /// Which document is currently being processed per stage (if any).
     /// Only set for stages with 1 active job (e.g. single-document OCR).
     pub store_doc: Option<DocProgress>,
@@ -126,15 +255,36 @@ pub struct ProgressTracker {
     collections: Arc<RwLock<HashMap<String, PipelineProgress>>>,
     /// Channel to notify listeners of progress changes
     notify_tx: mpsc::Sender<PipelineProgress>,
+    /// Chunk-level embedding progress (see [`EmbeddingProgress`])
+    embedding_tx: broadcast::Sender<EmbeddingProgress>,
+    /// Per-file stage progress (see [`FileProgress`])
+    file_tx: broadcast::Sender<FileProgress>,
+    /// Documents from peers (see [`SyncedDocument`])
+    synced_tx: broadcast::Sender<SyncedDocument>,
+    /// IDs of documents from peers that aren't indexed yet
+    syncing: Arc<std::sync::Mutex<HashSet<String>>>,
+    /// Embed and index rates (see [`ThroughputStats`])
+    throughput: Arc<Throughput>,
+    throughput_tx: broadcast::Sender<ThroughputStats>,
 }
 
 impl ProgressTracker {
     pub fn new() -> (Self, mpsc::Receiver<PipelineProgress>) {
         let (notify_tx, notify_rx) = mpsc::channel(256);
+        let (embedding_tx, _) = broadcast::channel(256);
+        let (file_tx, _) = broadcast::channel(256);
+        let (synced_tx, _) = broadcast::channel(256);
+        let (throughput_tx, _) = broadcast::channel(16);
         (
             Self {
                 collections: Arc::new(RwLock::new(HashMap::new())),
                 notify_tx,
+                embedding_tx,
+                file_tx,
+                synced_tx,
+                syncing: Arc::new(std::sync::Mutex::new(HashSet::new())),
+                throughput: Arc::new(Throughput::new()),
+                throughput_tx,
             },
             notify_rx,
         )
@@ -149,6 +299,127 @@ impl ProgressTracker {
         .await;
     }
 
+    /// Subscribe to chunk-level embedding progress.
+    pub fn subscribe_embedding(&self) -> broadcast::Receiver<EmbeddingProgress> {
+        self.embedding_tx.subscribe()
+    }
+
+    /// Subscribe to per-file stage progress.
+    pub fn subscribe_files(&self) -> broadcast::Receiver<FileProgress> {
+        self.file_tx.subscribe()
+    }
+
+    /// Broadcast where a file is within its current stage.
+    pub fn report_file(&self, progress: FileProgress) {
+        // No subscribers is fine — the frontend may not be listening yet.
+        let _ = self.file_tx.send(progress);
+    }
+
+    /// Subscribe to documents arriving from peers.
+    pub fn subscribe_synced(&self) -> broadcast::Receiver<SyncedDocument> {
+        self.synced_tx.subscribe()
+    }
+
+    /// Broadcast a step of a document written by a peer. Later steps of
+    /// the document are reported through [`Self::report_if_synced`].
+    pub fn report_synced(&self, collection_id: &str, doc_id: &str, status: SyncStatus) {
+        self.syncing.lock().unwrap().insert(doc_id.to_string());
+        self.send_synced(collection_id, doc_id, status);
+    }
+
+    /// Broadcast `status` if `doc_id` came from a peer and isn't indexed
+    /// yet. Workers call this for every document; local imports have their
+    /// own [`FileProgress`].
+    pub fn report_if_synced(&self, collection_id: &str, doc_id: &str, status: SyncStatus) {
+        let tracked = {
+            let mut syncing = self.syncing.lock().unwrap();
+            if status == SyncStatus::Indexed {
+                syncing.remove(doc_id)
+            } else {
+                syncing.contains(doc_id)
+            }
+        };
+        if tracked {
+            self.send_synced(collection_id, doc_id, status);
+        }
+    }
+
+    fn send_synced(&self, collection_id: &str, doc_id: &str, status: SyncStatus) {
+        // No subscribers is fine — the frontend may not be listening yet.
+        let _ = self.synced_tx.send(SyncedDocument {
+            collection_id: collection_id.to_string(),
+            doc_id: doc_id.to_string(),
+            status,
+        });
+    }
+
+    /// Record chunk progress for a document in the embed stage. Updates
+    /// the collection's `embed_doc` and broadcasts an [`EmbeddingProgress`].
+    pub async fn report_embedding(
+        &self,
+        collection_id: &str,
+        doc_id: &str,
+        chunks_done: usize,
+        chunks_total: usize,
+    ) {
+        self.apply(ProgressUpdate::PageProgress {
+            collection_id: collection_id.to_string(),
+            doc_id: doc_id.to_string(),
+            stage: Stage::Embed,
+            current: chunks_done,
+            total: chunks_total,
+        })
+        .await;
+
+        // No subscribers is fine — the frontend may not be listening yet.
+        let _ = self.embedding_tx.send(EmbeddingProgress {
+            collection_id: collection_id.to_string(),
+            doc_id: doc_id.to_string(),
+            chunks_done,
+            chunks_total,
+        });
+    }
+
+    /// Count chunks that finished embedding, cache hits included.
+    pub fn record_chunks_embedded(&self, count: usize) {
+        self.throughput.record_chunks_embedded(count);
+    }
+
+    /// Current embed and index throughput across all collections.
+    pub async fn throughput(&self) -> ThroughputStats {
+        let collections: Vec<PipelineProgress> =
+            self.collections.read().await.values().cloned().collect();
+        self.throughput.stats(&collections)
+    }
+
+    /// Subscribe to periodic throughput snapshots.
+    pub fn subscribe_throughput(&self) -> broadcast::Receiver<ThroughputStats> {
+        self.throughput_tx.subscribe()
+    }
+
+    /// Broadcast a throughput snapshot every `period` while documents are
+    /// in flight, plus one final snapshot when the pipeline drains.
+    pub(super) fn spawn_throughput_reporter(&self, period: Duration, cancel: CancellationToken) {
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            let mut was_active = false;
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = interval.tick() => {
+                        let stats = tracker.throughput().await;
+                        let active = stats.docs_remaining > 0;
+                        if active || was_active {
+                            let _ = tracker.throughput_tx.send(stats);
+                        }
+                        was_active = active;
+                    }
+                }
+            }
+        });
+    }
+
     /// Apply a progress update.
     pub async fn apply(&self, update: ProgressUpdate) {
         let mut collections = self.collections.write().await;
@@ -158,6 +429,8 @@ impl ProgressTracker {
             | ProgressUpdate::Started { collection_id, .. }
             | ProgressUpdate::Completed { collection_id, .. }
             | ProgressUpdate::Failed { collection_id, .. }
+            | ProgressUpdate::Cancelled { collection_id, .. }
+            | ProgressUpdate::Retrying { collection_id, .. }
             | ProgressUpdate::PageProgress { collection_id, .. } => collection_id.clone(),
         };
 
@@ -185,6 +458,11 @@ impl ProgressTracker {
                 collection_id,
                 stage,
             } => {
+                match stage {
+                    Stage::Embed => self.throughput.record_doc_embedded(),
+                    Stage::Index => self.throughput.record_doc_indexed(),
+                    _ => {}
+                }
                 if let Some(progress) = collections.get_mut(&collection_id) {
                     let stage_progress = progress.stage_mut(stage);
                     stage_progress.active = stage_progress.active.saturating_sub(1);
@@ -205,6 +483,41 @@ impl ProgressTracker {
                     *progress.doc_mut(stage) = None;
                 }
             }
+            ProgressUpdate::Cancelled {
+                collection_id,
+                stage,
+                was_active,
+            } => {
+                if let Some(progress) = collections.get_mut(&collection_id) {
+                    let stage_progress = progress.stage_mut(stage);
+                    if was_active {
+                        stage_progress.active = stage_progress.active.saturating_sub(1);
+                    } else {
+                        stage_progress.pending = stage_progress.pending.saturating_sub(1);
+                    }
+                    stage_progress.cancelled += 1;
+                    if was_active {
+                        *progress.doc_mut(stage) = None;
+                    }
+                }
+            }
+            ProgressUpdate::Retrying {
+                collection_id,
+                stage,
+                was_failed,
+            } => {
+                let progress = collections
+                    .entry(collection_id.clone())
+                    .or_insert_with(|| PipelineProgress::new(collection_id));
+                let stage_progress = progress.stage_mut(stage);
+                if was_failed {
+                    stage_progress.failed = stage_progress.failed.saturating_sub(1);
+                } else {
+                    stage_progress.active = stage_progress.active.saturating_sub(1);
+                    *progress.doc_mut(stage) = None;
+                }
+                progress.stage_mut(stage).pending += 1;
+            }
             ProgressUpdate::PageProgress {
                 collection_id,
                 doc_id,
@@ -212,6 +525,9 @@ impl ProgressTracker {
                 current,
                 total,
             } => {
+                self.report_file(
+                    FileProgress::doc(&collection_id, &doc_id, stage).at(current, total),
+                );
                 if let Some(progress) = collections.get_mut(&collection_id) {
                     *progress.doc_mut(stage) = Some(DocProgress {
                         doc_id,
@@ -244,8 +560,146 @@ impl ProgressTracker {
             .collect()
     }
 
+    /// Mark imports into a collection as paused or running.
+    pub async fn set_paused(&self, collection_id: &str, paused: bool) {
+        let mut collections = self.collections.write().await;
+        let progress = collections
+            .entry(collection_id.to_string())
+            .or_insert_with(|| PipelineProgress::new(collection_id.to_string()));
+        progress.paused = paused;
+        let _ = self.notify_tx.try_send(progress.clone());
+    }
+
     /// Remove a collection from tracking.
     pub async fn remove(&self, collection_id: &str) {
         self.collections.write().await.remove(collection_id);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn report_embedding_updates_doc_and_broadcasts() {
+        let (tracker, _rx) = ProgressTracker::new();
+        let mut embedding_rx = tracker.subscribe_embedding();
+
+        tracker.queue("col", Stage::Embed).await;
+        tracker
+            .apply(ProgressUpdate::Started {
+                collection_id: "col".to_string(),
+                stage: Stage::Embed,
+            })
+            .await;
+        tracker.report_embedding("col", "doc-1", 32, 100).await;
+
+        let event = embedding_rx.recv().await.unwrap();
+        assert_eq!(event.doc_id, "doc-1");
+        assert_eq!(event.chunks_done, 32);
+        assert_eq!(event.chunks_total, 100);
+
+        let progress = tracker.get("col").await.unwrap();
+        let doc = progress.embed_doc.expect("embed_doc set");
+        assert_eq!((doc.current, doc.total), (32, 100));
+
+        tracker
+            .apply(ProgressUpdate::Completed {
+                collection_id: "col".to_string(),
+                stage: Stage::Embed,
+            })
+            .await;
+        let progress = tracker.get("col").await.unwrap();
+        assert!(progress.embed_doc.is_none());
+        assert_eq!(progress.embed.completed, 1);
+    }
+
+    #[tokio::test]
+    async fn synced_documents_are_reported_until_indexed() {
+        let (tracker, _rx) = ProgressTracker::new();
+        let mut synced_rx = tracker.subscribe_synced();
+
+        // Local imports aren't reported.
+        tracker.report_if_synced("col", "local", SyncStatus::Indexed);
+        tracker.report_synced("col", "doc-1", SyncStatus::Received);
+        tracker.report_if_synced("col", "doc-1", SyncStatus::EmbeddingRegenerated);
+        tracker.report_if_synced("col", "doc-1", SyncStatus::Indexed);
+        // Reindexing later isn't part of the sync.
+        tracker.report_if_synced("col", "doc-1", SyncStatus::Indexed);
+
+        let mut statuses = Vec::new();
+        while let Ok(event) = synced_rx.try_recv() {
+            assert_eq!(event.doc_id, "doc-1");
+            statuses.push(event.status);
+        }
+        assert_eq!(
+            statuses,
+            [
+                SyncStatus::Received,
+                SyncStatus::EmbeddingRegenerated,
+                SyncStatus::Indexed
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn page_progress_reports_file_percent() {
+        let (tracker, _rx) = ProgressTracker::new();
+        let mut file_rx = tracker.subscribe_files();
+
+        tracker.queue("col", Stage::Ocr).await;
+        tracker
+            .apply(ProgressUpdate::PageProgress {
+                collection_id: "col".to_string(),
+                doc_id: "doc-1".to_string(),
+                stage: Stage::Ocr,
+                current: 3,
+                total: 12,
+            })
+            .await;
+
+        let event = file_rx.recv().await.unwrap();
+        assert_eq!(event.doc_id.as_deref(), Some("doc-1"));
+        assert_eq!(event.stage, Stage::Ocr);
+        assert_eq!((event.current, event.total, event.percent), (3, 12, 25));
+        assert_eq!(
+            FileProgress::doc("col", "doc-1", Stage::Ocr).done().percent,
+            100
+        );
+    }
+
+    #[tokio::test]
+    async fn completed_stages_feed_throughput() {
+        let (tracker, _rx) = ProgressTracker::new();
+        for stage in [Stage::Embed, Stage::Index] {
+            tracker.queue("col", stage).await;
+            tracker
+                .apply(ProgressUpdate::Started {
+                    collection_id: "col".to_string(),
+                    stage,
+                })
+                .await;
+        }
+        tracker.queue("col", Stage::Index).await;
+        tracker.record_chunks_embedded(40);
+
+        let stats = tracker.throughput().await;
+        assert_eq!(stats.chunks_embedded, 40);
+        assert_eq!(stats.docs_remaining, 3);
+        assert_eq!(stats.eta_secs, None);
+
+        for stage in [Stage::Embed, Stage::Index] {
+            tracker
+                .apply(ProgressUpdate::Completed {
+                    collection_id: "col".to_string(),
+                    stage,
+                })
+                .await;
+        }
+        let stats = tracker.throughput().await;
+        assert_eq!((stats.docs_embedded, stats.docs_indexed), (1, 1));
+        assert_eq!(stats.docs_remaining, 1);
+        assert!(stats.index_docs_per_sec > 0.0);
+        assert!(stats.eta_secs.is_some());
+    }
+}
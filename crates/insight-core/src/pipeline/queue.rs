@@ -0,0 +1,259 @@
+//! Files waiting to be imported, persisted across restarts.
+//!
+//! Importing stores each file's bytes one after another, and everything
+//! after that is driven by iroh events. Files not yet stored would be lost
+//! if the app closed mid-import, so they are written to a JSONL log in the
+//! data directory first: a `queued` line per file when the import starts and
+//! a `done` line once it has been stored (or has failed for good), with a
+//! `prioritized` line whenever the user changes a waiting file's priority. On
+//! startup the log is replayed, rewritten to the files still waiting, and
+//! those are imported again.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::order::ImportPriority;
+
+/// A file waiting to be stored in a collection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueuedImport {
+    pub collection_id: String,
+    pub path: PathBuf,
+    /// Tags for the new document.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "ImportPriority::is_normal")]
+    pub priority: ImportPriority,
+}
+
+/// One line of the log.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Entry {
+    Queued(QueuedImport),
+    Done(QueuedImport),
+    Prioritized {
+        collection_id: String,
+        path: PathBuf,
+        priority: ImportPriority,
+    },
+}
+
+/// Files waiting to be imported, with the log that keeps them.
+pub struct ImportQueue {
+    file: PathBuf,
+    /// In import order.
+    pending: Mutex<Vec<QueuedImport>>,
+}
+
+impl ImportQueue {
+    /// Open the log at `file`, keeping only the files still waiting.
+    pub fn open(file: PathBuf) -> Result<Self> {
+        let pending = replay(&file)?;
+        let queue = Self {
+            file,
+            pending: Mutex::new(pending),
+        };
+        queue.compact()?;
+        Ok(queue)
+    }
+
+    /// Record `paths` as waiting for `collection_id`, to be tagged `tags`.
+    pub fn push(&self, collection_id: &str, paths: &[PathBuf], tags: &[String]) -> Result<()> {
+        let items: Vec<QueuedImport> = paths
+            .iter()
+            .map(|path| QueuedImport {
+                collection_id: collection_id.to_string(),
+                path: path.clone(),
+                tags: tags.to_vec(),
+                priority: ImportPriority::Normal,
+            })
+            .collect();
+        let mut pending = self.pending.lock().unwrap();
+        self.append(items.iter().cloned().map(Entry::Queued))?;
+        pending.extend(items);
+        Ok(())
+    }
+
+    /// Record `path` as no longer waiting, stored or failed.
+    pub fn finish(&self, collection_id: &str, path: &Path) -> Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        let Some(index) = pending
+            .iter()
+            .position(|i| i.collection_id == collection_id && i.path == path)
+        else {
+            return Ok(());
+        };
+        let item = pending.remove(index);
+        self.append(std::iter::once(Entry::Done(item)))
+    }
+
+    /// Record a new priority for `path`, if it's still waiting.
+    pub fn set_priority(
+        &self,
+        collection_id: &str,
+        path: &Path,
+        priority: ImportPriority,
+    ) -> Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        let Some(item) = pending
+            .iter_mut()
+            .find(|i| i.collection_id == collection_id && i.path == path)
+        else {
+            return Ok(());
+        };
+        if item.priority == priority {
+            return Ok(());
+        }
+        item.priority = priority;
+        self.append(std::iter::once(Entry::Prioritized {
+            collection_id: collection_id.to_string(),
+            path: path.to_path_buf(),
+            priority,
+        }))
+    }
+
+    /// Files still waiting, in import order.
+    pub fn pending(&self) -> Vec<QueuedImport> {
+        self.pending.lock().unwrap().clone()
+    }
+
+    fn append(&self, entries: impl Iterator<Item = Entry>) -> Result<()> {
+        let mut lines = String::new();
+        for entry in entries {
+            lines.push_str(&serde_json::to_string(&entry)?);
+            lines.push('\n');
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file)
+            .context("Failed to open import queue")?;
+        file.write_all(lines.as_bytes())
+            .context("Failed to write import queue")
+    }
+
+    /// Rewrite the log to the files still waiting.
+    fn compact(&self) -> Result<()> {
+        let pending = self.pending.lock().unwrap();
+        let tmp = self.file.with_extension("jsonl.tmp");
+        let mut lines = String::new();
+        for item in pending.iter() {
+            lines.push_str(&serde_json::to_string(&Entry::Queued(item.clone()))?);
+            lines.push('\n');
+        }
+        std::fs::write(&tmp, lines).context("Failed to write import queue")?;
+        std::fs::rename(&tmp, &self.file).context("Failed to replace import queue")
+    }
+}
+
+/// Files queued in the log at `file` and not done. A line cut short by a
+/// crash is skipped.
+fn replay(file: &Path) -> Result<Vec<QueuedImport>> {
+    let content = match std::fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context("Failed to read import queue"),
+    };
+    let mut pending: Vec<QueuedImport> = Vec::new();
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str::<Entry>(line) {
+            Ok(Entry::Queued(item)) => pending.push(item),
+            Ok(Entry::Done(item)) => {
+                if let Some(index) = pending.iter().position(|i| *i == item) {
+                    pending.remove(index);
+                }
+            }
+            Ok(Entry::Prioritized {
+                collection_id,
+                path,
+                priority,
+            }) => {
+                if let Some(item) = pending
+                    .iter_mut()
+                    .find(|i| i.collection_id == collection_id && i.path == path)
+                {
+                    item.priority = priority;
+                }
+            }
+            Err(e) => tracing::warn!(error = %e, "Skipping unreadable import queue entry"),
+        }
+    }
+    Ok(pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("import_queue.jsonl");
+        let paths: Vec<PathBuf> = ["a.pdf", "b.pdf", "c.pdf"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+
+        let queue = ImportQueue::open(file.clone()).unwrap();
+        queue.push("col", &paths, &[]).unwrap();
+        queue.finish("col", Path::new("a.pdf")).unwrap();
+        drop(queue);
+
+        // A crash mid-write leaves a partial last line.
+        let mut log = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&file)
+            .unwrap();
+        log.write_all(b"{\"done\":{\"collec").unwrap();
+
+        let queue = ImportQueue::open(file.clone()).unwrap();
+        let pending: Vec<PathBuf> = queue.pending().into_iter().map(|i| i.path).collect();
+        assert_eq!(pending, paths[1..]);
+        // Reopening rewrote the log to what's left.
+        assert_eq!(std::fs::read_to_string(&file).unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_finish_is_per_collection() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = ImportQueue::open(dir.path().join("import_queue.jsonl")).unwrap();
+        let paths = vec![PathBuf::from("memo.pdf")];
+        queue.push("one", &paths, &[]).unwrap();
+        queue.push("two", &paths, &["memos".to_string()]).unwrap();
+
+        queue.finish("two", Path::new("memo.pdf")).unwrap();
+        let pending = queue.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].collection_id, "one");
+    }
+
+    #[test]
+    fn test_priority_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("import_queue.jsonl");
+        let paths = vec![PathBuf::from("a.pdf"), PathBuf::from("b.pdf")];
+
+        let queue = ImportQueue::open(file.clone()).unwrap();
+        queue.push("col", &paths, &[]).unwrap();
+        queue
+            .set_priority("col", Path::new("a.pdf"), ImportPriority::High)
+            .unwrap();
+        queue
+            .set_priority("col", Path::new("b.pdf"), ImportPriority::Low)
+            .unwrap();
+        // Done lines carry the new priority and still match.
+        queue.finish("col", Path::new("b.pdf")).unwrap();
+        drop(queue);
+
+        let queue = ImportQueue::open(file).unwrap();
+        let pending = queue.pending();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].path, Path::new("a.pdf"));
+        assert_eq!(pending[0].priority, ImportPriority::High);
+    }
+}
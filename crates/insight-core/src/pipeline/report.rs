@@ -0,0 +1,320 @@
+//! Reports of finished imports, for chain-of-custody records.
+//!
+//! Once a batch of files has been stored, a report is written listing each
+//! file with its outcome, the hashes and size of the source as stored, its
+//! page count and how long storing it took. Page counts come from text
+//! extraction, so the report waits for the collection's extract and OCR
+//! stages to settle first (up to [`MAX_SETTLE`]). Reports are JSON files in
+//! the data directory, one per batch, and are never removed by the app.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use iroh_docs::NamespaceId;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::progress::ProgressTracker;
+use crate::storage::Storage;
+
+/// Longest a report waits for extraction before it's written without the
+/// page counts still missing.
+const MAX_SETTLE: Duration = Duration::from_secs(30 * 60);
+
+/// How often extraction progress is checked while waiting.
+const SETTLE_POLL: Duration = Duration::from_secs(2);
+
+/// What happened to one file of an import.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Stored,
+    Failed,
+    Cancelled,
+}
+
+/// One file of an import.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportedFile {
+    /// The file's path, or the URL or remote object it was fetched from.
+    pub source: String,
+    pub status: FileStatus,
+    pub doc_id: Option<String>,
+    pub error: Option<String>,
+    /// Bytes stored.
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+    /// BLAKE3 hash, which the stored source is addressed by.
+    pub blake3: Option<String>,
+    /// `None` if extraction hadn't finished when the report was written.
+    pub page_count: Option<usize>,
+    /// Time spent storing the file, including waits while it was paused.
+    pub duration_ms: u64,
+}
+
+impl ReportedFile {
+    pub(crate) fn new(
+        source: String,
+        result: Option<Result<String, String>>,
+        duration: Duration,
+    ) -> Self {
+        let (status, doc_id, error) = match result {
+            Some(Ok(doc_id)) => (FileStatus::Stored, Some(doc_id), None),
+            Some(Err(e)) => (FileStatus::Failed, None, Some(e)),
+            None => (FileStatus::Cancelled, None, None),
+        };
+        Self {
+            source,
+            status,
+            doc_id,
+            error,
+            size: None,
+            sha256: None,
+            blake3: None,
+            page_count: None,
+            duration_ms: duration.as_millis() as u64,
+        }
+    }
+
+    /// Record the size and hashes of the stored source.
+    fn hash(&mut self, bytes: &[u8]) {
+        self.size = Some(bytes.len() as u64);
+        self.sha256 = Some(format!("{:x}", Sha256::digest(bytes)));
+        self.blake3 = Some(blake3::hash(bytes).to_hex().to_string());
+    }
+}
+
+/// When an import ran and how it went, without its files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub id: String,
+    pub collection_id: String,
+    pub started_at: String,
+    pub finished_at: String,
+    /// From the first file queued to the last one stored.
+    pub duration_ms: u64,
+    pub stored: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+/// A finished import and each of its files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    #[serde(flatten)]
+    pub summary: ImportSummary,
+    pub files: Vec<ReportedFile>,
+}
+
+impl ImportReport {
+    pub(crate) fn new(
+        collection_id: String,
+        started_at: chrono::DateTime<chrono::Utc>,
+        files: Vec<ReportedFile>,
+    ) -> Self {
+        let finished_at = chrono::Utc::now();
+        let count = |status| files.iter().filter(|f| f.status == status).count();
+        Self {
+            summary: ImportSummary {
+                id: uuid::Uuid::new_v4().to_string(),
+                collection_id,
+                started_at: started_at.to_rfc3339(),
+                finished_at: finished_at.to_rfc3339(),
+                duration_ms: (finished_at - started_at).num_milliseconds().max(0) as u64,
+                stored: count(FileStatus::Stored),
+                failed: count(FileStatus::Failed),
+                cancelled: count(FileStatus::Cancelled),
+            },
+            files,
+        }
+    }
+
+    /// Fill in hashes and page counts of the stored files, once extraction
+    /// has settled.
+    pub(crate) async fn complete(
+        &mut self,
+        storage: &tokio::sync::RwLock<Storage>,
+        progress: &ProgressTracker,
+        namespace_id: NamespaceId,
+    ) {
+        settle(progress, &self.summary.collection_id).await;
+
+        let storage = storage.read().await;
+        for file in &mut self.files {
+            let Some(doc_id) = file.doc_id.clone() else {
+                continue;
+            };
+            match storage.get_document_source(namespace_id, &doc_id).await {
+                Ok(Some(bytes)) => file.hash(&bytes),
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(doc_id = %doc_id, error = %e, "Failed to read source for import report")
+                }
+            }
+            let has_text = matches!(
+                storage.get_document_text(namespace_id, &doc_id).await,
+                Ok(Some(_))
+            );
+            if has_text {
+                if let Ok(Some(metadata)) = storage.get_document(namespace_id, &doc_id).await {
+                    file.page_count = Some(metadata.page_count);
+                }
+            }
+        }
+    }
+}
+
+/// Wait until nothing is being extracted or OCRed in the collection.
+async fn settle(progress: &ProgressTracker, collection_id: &str) {
+    let waiting = async {
+        loop {
+            let busy = progress
+                .get(collection_id)
+                .await
+                .is_some_and(|p| p.extract.is_active() || p.ocr.is_active());
+            if !busy {
+                return;
+            }
+            tokio::time::sleep(SETTLE_POLL).await;
+        }
+    };
+    if tokio::time::timeout(MAX_SETTLE, waiting).await.is_err() {
+        tracing::warn!(collection = %collection_id, "Writing import report before extraction finished");
+    }
+}
+
+/// The import reports kept on disk.
+pub struct ImportReports {
+    dir: PathBuf,
+}
+
+impl ImportReports {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Write `report`, named by its ID.
+    pub fn save(&self, report: &ImportReport) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let path = self.dir.join(format!("{}.json", report.summary.id));
+        std::fs::write(&path, serde_json::to_vec_pretty(report)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// Summaries of the reports for `collection_id`, or for every
+    /// collection, newest first.
+    pub fn list(&self, collection_id: Option<&str>) -> Result<Vec<ImportSummary>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("Failed to read import reports"),
+        };
+        let mut summaries = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match read(&path) {
+                Ok(report) => {
+                    if collection_id.is_none_or(|id| report.summary.collection_id == id) {
+                        summaries.push(report.summary);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Skipping unreadable import report")
+                }
+            }
+        }
+        summaries.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        Ok(summaries)
+    }
+
+    /// The report with `id`, if there is one.
+    pub fn get(&self, id: &str) -> Result<Option<ImportReport>> {
+        // IDs come from the frontend; only ever read files named by a UUID.
+        if uuid::Uuid::parse_str(id).is_err() {
+            return Ok(None);
+        }
+        let path = self.path(id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        read(&path).map(Some)
+    }
+
+    /// Where the report with `id` is kept.
+    pub fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+}
+
+fn read(path: &std::path::Path) -> Result<ImportReport> {
+    let bytes = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(collection_id: &str, started_at: &str) -> ImportReport {
+        let started_at = chrono::DateTime::parse_from_rfc3339(started_at)
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let mut stored = ReportedFile::new(
+            "/evidence/a.pdf".to_string(),
+            Some(Ok("doc-a".to_string())),
+            Duration::from_millis(120),
+        );
+        stored.hash(b"abc");
+        let files = vec![
+            stored,
+            ReportedFile::new(
+                "/evidence/b.pdf".to_string(),
+                Some(Err("Duplicate document: a.pdf".to_string())),
+                Duration::from_millis(5),
+            ),
+            ReportedFile::new("/evidence/c.pdf".to_string(), None, Duration::ZERO),
+        ];
+        ImportReport::new(collection_id.to_string(), started_at, files)
+    }
+
+    #[test]
+    fn test_reports_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let reports = ImportReports::new(dir.path().join("import_reports"));
+        assert!(reports.list(None).unwrap().is_empty());
+
+        let older = report("one", "2026-01-01T10:00:00Z");
+        let newer = report("one", "2026-02-01T10:00:00Z");
+        let other = report("two", "2026-03-01T10:00:00Z");
+        for r in [&older, &newer, &other] {
+            reports.save(r).unwrap();
+        }
+
+        let listed = reports.list(Some("one")).unwrap();
+        let ids: Vec<_> = listed.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, [newer.summary.id.as_str(), older.summary.id.as_str()]);
+        assert_eq!(
+            (listed[0].stored, listed[0].failed, listed[0].cancelled),
+            (1, 1, 1)
+        );
+        assert_eq!(reports.list(None).unwrap().len(), 3);
+
+        let read = reports.get(&older.summary.id).unwrap().unwrap();
+        assert_eq!(read.files.len(), 3);
+        assert_eq!(read.files[0].size, Some(3));
+        assert_eq!(
+            read.files[0].sha256.as_deref(),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(read.files[1].status, FileStatus::Failed);
+        assert_eq!(read.files[2].status, FileStatus::Cancelled);
+
+        assert!(reports.get("../settings").unwrap().is_none());
+    }
+}
@@ -0,0 +1,199 @@
+//! Retrying failed pipeline jobs.
+//!
+//! A job that fails with a transient error — the embedder still loading,
+//! the index busy, a dropped connection — goes back to its stage after an
+//! exponential backoff, up to the configured number of attempts. Anything
+//! else, such as a corrupt PDF, fails straight away. Jobs that failed for
+//! good are kept (in memory, for this run) so [`Retries::take_failed`] can
+//! hand them back once the cause is fixed.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::control::ParkedJob;
+use super::watcher::JobSenders;
+
+/// Backoff before the first retry; doubled for each one after.
+const BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// Longest backoff between attempts.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Error messages that mean "try again later" rather than "this file is
+/// bad". Matched case-insensitively against the whole error chain.
+const TRANSIENT: &[&str] = &[
+    "not configured",
+    "not loaded",
+    "still loading",
+    "busy",
+    "database is locked",
+    "timed out",
+    "timeout",
+    "connection",
+    "temporarily",
+    "try again",
+    "channel closed",
+    "dropped response",
+    "too many requests",
+];
+
+/// Whether `error` is likely to go away on its own.
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            if matches!(
+                io.kind(),
+                std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::WouldBlock
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+            ) {
+                return true;
+            }
+        }
+        let message = cause.to_string().to_ascii_lowercase();
+        TRANSIENT.iter().any(|pattern| message.contains(pattern))
+    })
+}
+
+/// Backoff before retry number `attempt`, counting from 1.
+pub fn backoff(attempt: u32) -> Duration {
+    BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_DELAY)
+}
+
+/// A file that failed to store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedFile {
+    pub collection_id: String,
+    pub path: PathBuf,
+    pub tags: Vec<String>,
+}
+
+/// Attempt counts and failed jobs; see the module docs.
+pub struct Retries {
+    max_attempts: u32,
+    senders: JobSenders,
+    /// Retries so far of each document's current stage.
+    attempts: Mutex<HashMap<String, u32>>,
+    failed_jobs: Mutex<Vec<ParkedJob>>,
+    failed_files: Mutex<Vec<FailedFile>>,
+}
+
+impl Retries {
+    pub fn new(max_attempts: u32, senders: JobSenders) -> Self {
+        Self {
+            max_attempts,
+            senders,
+            attempts: Mutex::default(),
+            failed_jobs: Mutex::default(),
+            failed_files: Mutex::default(),
+        }
+    }
+
+    /// Retries allowed after a first failure.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Handle a failed job: if `error` is transient and attempts are left,
+    /// send it back to its stage after a backoff and return the backoff.
+    /// Otherwise keep it as failed and return `None`.
+    pub fn retry(&self, job: ParkedJob, error: &anyhow::Error) -> Option<Duration> {
+        let doc_id = job.doc_id().to_string();
+        let attempt = {
+            let mut attempts = self.attempts.lock().unwrap();
+            let attempt = attempts.entry(doc_id.clone()).or_default();
+            *attempt += 1;
+            *attempt
+        };
+        if !is_transient(error) || attempt > self.max_attempts {
+            self.attempts.lock().unwrap().remove(&doc_id);
+            self.failed_jobs.lock().unwrap().push(job);
+            return None;
+        }
+
+        let delay = backoff(attempt);
+        tracing::warn!(
+            doc_id = %doc_id,
+            stage = %job.stage(),
+            attempt,
+            delay_secs = delay.as_secs(),
+            error = %error,
+            "Transient failure, retrying"
+        );
+        let senders = self.senders.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            senders.send(job);
+        });
+        Some(delay)
+    }
+
+    /// Reset the attempt count once a document's stage has succeeded.
+    pub fn succeeded(&self, doc_id: &str) {
+        self.attempts.lock().unwrap().remove(doc_id);
+    }
+
+    /// Keep a file that failed to store.
+    pub fn file_failed(&self, file: FailedFile) {
+        let mut files = self.failed_files.lock().unwrap();
+        if !files.contains(&file) {
+            files.push(file);
+        }
+    }
+
+    /// Take the jobs and files that failed for good, for one collection or
+    /// all of them.
+    pub fn take_failed(&self, collection_id: Option<&str>) -> (Vec<ParkedJob>, Vec<FailedFile>) {
+        let matches = |id: &str| collection_id.is_none_or(|c| c == id);
+        let mut jobs = self.failed_jobs.lock().unwrap();
+        let (taken_jobs, kept): (Vec<_>, Vec<_>) = jobs
+            .drain(..)
+            .partition(|job| matches(&job.collection_id()));
+        *jobs = kept;
+        let mut files = self.failed_files.lock().unwrap();
+        let (taken_files, kept): (Vec<_>, Vec<_>) = files
+            .drain(..)
+            .partition(|file| matches(&file.collection_id));
+        *files = kept;
+        (taken_jobs, taken_files)
+    }
+
+    /// Forget a document removed from its collection.
+    pub fn forget(&self, doc_id: &str) {
+        self.attempts.lock().unwrap().remove(doc_id);
+        self.failed_jobs
+            .lock()
+            .unwrap()
+            .retain(|job| job.doc_id() != doc_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(is_transient(&anyhow::anyhow!("Embedder not configured")));
+        assert!(is_transient(
+            &anyhow::anyhow!("Index worker channel closed").context("Index failed")
+        ));
+        assert!(is_transient(&anyhow::Error::new(std::io::Error::from(
+            std::io::ErrorKind::TimedOut
+        ))));
+        assert!(!is_transient(&anyhow::anyhow!("Invalid PDF: missing xref")));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        assert_eq!(backoff(1), Duration::from_secs(2));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(20), MAX_DELAY);
+    }
+}
@@ -0,0 +1,168 @@
+//! How syncing each collection with each peer is going.
+//!
+//! The collection watcher records what iroh reports about the peers a
+//! collection syncs with: neighbors coming and going, syncs finishing or
+//! failing, and entries whose content is still being downloaded. When a
+//! colleague isn't getting documents, this shows whether their node has
+//! been seen at all, when a sync last succeeded and why the last one
+//! failed. Kept in memory, so it covers this run only.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Sync state of one collection with one peer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerSyncHealth {
+    pub collection_id: String,
+    /// The peer's node ID.
+    pub peer: String,
+    /// Whether the peer is currently a neighbor in the collection's swarm.
+    pub connected: bool,
+    /// When anything was last heard from the peer, RFC 3339.
+    pub last_seen: String,
+    /// When a sync with the peer last succeeded, RFC 3339.
+    pub last_sync: Option<String>,
+    /// Entries received and sent in the last successful sync.
+    pub entries_received: usize,
+    pub entries_sent: usize,
+    /// Entries from the peer whose content hasn't been downloaded yet.
+    pub pending: usize,
+    /// Why the last failed sync failed, and when.
+    pub last_error: Option<String>,
+    pub last_error_at: Option<String>,
+}
+
+impl PeerSyncHealth {
+    fn new(collection_id: &str, peer: &str, now: String) -> Self {
+        Self {
+            collection_id: collection_id.to_string(),
+            peer: peer.to_string(),
+            connected: false,
+            last_seen: now,
+            last_sync: None,
+            entries_received: 0,
+            entries_sent: 0,
+            pending: 0,
+            last_error: None,
+            last_error_at: None,
+        }
+    }
+}
+
+/// Sync state of every watched collection, by peer. Clones share state.
+#[derive(Clone, Default)]
+pub struct SyncHealth {
+    peers: Arc<Mutex<HashMap<(String, String), PeerSyncHealth>>>,
+}
+
+impl SyncHealth {
+    /// The peers `collection_id` has synced or tried to sync with, most
+    /// recently seen first.
+    pub fn collection(&self, collection_id: &str) -> Vec<PeerSyncHealth> {
+        let peers = self.peers.lock().unwrap();
+        let mut health: Vec<PeerSyncHealth> = peers
+            .values()
+            .filter(|h| h.collection_id == collection_id)
+            .cloned()
+            .collect();
+        health.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+        health
+    }
+
+    /// A peer joined or left the collection's swarm.
+    pub(super) fn neighbor(&self, collection_id: &str, peer: &str, connected: bool) {
+        self.update(collection_id, peer, |h| h.connected = connected);
+    }
+
+    /// A sync with a peer finished, receiving and sending the given
+    /// number of entries or failing with an error.
+    pub(super) fn sync_finished(
+        &self,
+        collection_id: &str,
+        peer: &str,
+        finished: SystemTime,
+        result: Result<(usize, usize), &str>,
+    ) {
+        let finished = DateTime::<Utc>::from(finished).to_rfc3339();
+        self.update(collection_id, peer, |h| match result {
+            Ok((received, sent)) => {
+                h.last_sync = Some(finished);
+                h.entries_received = received;
+                h.entries_sent = sent;
+            }
+            Err(e) => {
+                h.last_error = Some(e.to_string());
+                h.last_error_at = Some(finished);
+            }
+        });
+    }
+
+    /// A peer sent an entry whose content is still to be downloaded.
+    pub(super) fn content_pending(&self, collection_id: &str, peer: &str) {
+        self.update(collection_id, peer, |h| h.pending += 1);
+    }
+
+    /// The content of an entry a peer sent has been downloaded.
+    pub(super) fn content_ready(&self, collection_id: &str, peer: &str) {
+        self.update(collection_id, peer, |h| {
+            h.pending = h.pending.saturating_sub(1)
+        });
+    }
+
+    /// Drop everything recorded for a collection no longer watched.
+    pub(super) fn forget(&self, collection_id: &str) {
+        self.peers
+            .lock()
+            .unwrap()
+            .retain(|(id, _), _| id != collection_id);
+    }
+
+    fn update(&self, collection_id: &str, peer: &str, f: impl FnOnce(&mut PeerSyncHealth)) {
+        let now = Utc::now().to_rfc3339();
+        let mut peers = self.peers.lock().unwrap();
+        let health = peers
+            .entry((collection_id.to_string(), peer.to_string()))
+            .or_insert_with(|| PeerSyncHealth::new(collection_id, peer, now.clone()));
+        health.last_seen = now;
+        f(health);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_syncs_and_pending_content_per_peer() {
+        let health = SyncHealth::default();
+        health.neighbor("col", "alice", true);
+        health.content_pending("col", "alice");
+        health.content_pending("col", "alice");
+        health.content_ready("col", "alice");
+        health.sync_finished("col", "alice", SystemTime::now(), Ok((12, 3)));
+        health.sync_finished("col", "bob", SystemTime::now(), Err("connection lost"));
+        health.neighbor("other", "alice", true);
+
+        let peers = health.collection("col");
+        assert_eq!(peers.len(), 2);
+        let alice = peers.iter().find(|h| h.peer == "alice").unwrap();
+        assert!(alice.connected);
+        assert_eq!(alice.pending, 1);
+        assert_eq!((alice.entries_received, alice.entries_sent), (12, 3));
+        assert!(alice.last_sync.is_some());
+        assert!(alice.last_error.is_none());
+
+        let bob = peers.iter().find(|h| h.peer == "bob").unwrap();
+        assert!(!bob.connected);
+        assert!(bob.last_sync.is_none());
+        assert_eq!(bob.last_error.as_deref(), Some("connection lost"));
+
+        health.forget("col");
+        assert!(health.collection("col").is_empty());
+        assert_eq!(health.collection("other").len(), 1);
+    }
+}
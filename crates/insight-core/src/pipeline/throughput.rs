@@ -0,0 +1,263 @@
+//! Throughput metrics for the embed and index stages.
+//!
+//! Rates are measured over a sliding window so they follow the current
+//! import rather than the lifetime average. Together with the stage
+//! counts in [`PipelineProgress`] they give an estimated time to finish,
+//! which matters for imports of tens of thousands of documents.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::progress::{PipelineProgress, StageProgress};
+
+/// How far back rates look.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Shortest span a rate is computed over, so the first batch doesn't
+/// report an absurd rate.
+const MIN_SPAN: Duration = Duration::from_secs(1);
+
+/// Snapshot of pipeline throughput.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ThroughputStats {
+    /// Chunks embedded per second, cache hits included.
+    pub embed_chunks_per_sec: f64,
+    /// Documents finishing the embed stage per second.
+    pub embed_docs_per_sec: f64,
+    /// Documents indexed per second.
+    pub index_docs_per_sec: f64,
+    /// Totals since the pipeline started.
+    pub chunks_embedded: u64,
+    pub docs_embedded: u64,
+    pub docs_indexed: u64,
+    /// Documents across all collections that haven't been indexed yet.
+    pub docs_remaining: usize,
+    /// Estimated seconds until every queued document is indexed. `None`
+    /// while there is work but no measured rate to extrapolate from.
+    pub eta_secs: Option<u64>,
+}
+
+/// Event counter with a sliding-window rate.
+struct RateMeter {
+    samples: VecDeque<(Instant, u64)>,
+    total: u64,
+}
+
+impl RateMeter {
+    fn new() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            total: 0,
+        }
+    }
+
+    fn record(&mut self, now: Instant, count: u64) {
+        self.total += count;
+        self.samples.push_back((now, count));
+        self.prune(now);
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some((at, _)) = self.samples.front() {
+            if now.duration_since(*at) > THROUGHPUT_WINDOW {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn rate(&mut self, now: Instant) -> f64 {
+        self.prune(now);
+        let Some((oldest, _)) = self.samples.front() else {
+            return 0.0;
+        };
+        let span = now.duration_since(*oldest).max(MIN_SPAN);
+        let count: u64 = self.samples.iter().map(|(_, n)| n).sum();
+        count as f64 / span.as_secs_f64()
+    }
+}
+
+struct Meters {
+    embed_chunks: RateMeter,
+    embed_docs: RateMeter,
+    index_docs: RateMeter,
+}
+
+/// Counters fed by the embed and index workers.
+pub(super) struct Throughput {
+    meters: Mutex<Meters>,
+}
+
+impl Throughput {
+    pub(super) fn new() -> Self {
+        Self {
+            meters: Mutex::new(Meters {
+                embed_chunks: RateMeter::new(),
+                embed_docs: RateMeter::new(),
+                index_docs: RateMeter::new(),
+            }),
+        }
+    }
+
+    pub(super) fn record_chunks_embedded(&self, count: usize) {
+        self.meters
+            .lock()
+            .unwrap()
+            .embed_chunks
+            .record(Instant::now(), count as u64);
+    }
+
+    pub(super) fn record_doc_embedded(&self) {
+        self.meters
+            .lock()
+            .unwrap()
+            .embed_docs
+            .record(Instant::now(), 1);
+    }
+
+    pub(super) fn record_doc_indexed(&self) {
+        self.meters
+            .lock()
+            .unwrap()
+            .index_docs
+            .record(Instant::now(), 1);
+    }
+
+    pub(super) fn stats(&self, progress: &[PipelineProgress]) -> ThroughputStats {
+        self.stats_at(Instant::now(), progress)
+    }
+
+    fn stats_at(&self, now: Instant, progress: &[PipelineProgress]) -> ThroughputStats {
+        let mut meters = self.meters.lock().unwrap();
+        let embed_chunks_per_sec = meters.embed_chunks.rate(now);
+        let embed_docs_per_sec = meters.embed_docs.rate(now);
+        let index_docs_per_sec = meters.index_docs.rate(now);
+
+        // Everything upstream of a stage still has to pass through it.
+        let to_embed: usize = progress
+            .iter()
+            .map(|p| {
+                remaining(&p.store)
+                    + remaining(&p.extract)
+                    + remaining(&p.ocr)
+                    + remaining(&p.embed)
+            })
+            .sum();
+        let to_index: usize =
+            to_embed + progress.iter().map(|p| remaining(&p.index)).sum::<usize>();
+
+        let eta_secs = eta(to_embed, embed_docs_per_sec)
+            .zip(eta(to_index, index_docs_per_sec))
+            .map(|(embed, index)| embed.max(index));
+
+        ThroughputStats {
+            embed_chunks_per_sec,
+            embed_docs_per_sec,
+            index_docs_per_sec,
+            chunks_embedded: meters.embed_chunks.total,
+            docs_embedded: meters.embed_docs.total,
+            docs_indexed: meters.index_docs.total,
+            docs_remaining: to_index,
+            eta_secs,
+        }
+    }
+}
+
+fn remaining(stage: &StageProgress) -> usize {
+    stage.pending + stage.active
+}
+
+/// Seconds to clear `docs` at `rate`. Zero work needs no rate.
+fn eta(docs: usize, rate: f64) -> Option<u64> {
+    if docs == 0 {
+        Some(0)
+    } else if rate > 0.0 {
+        Some((docs as f64 / rate).ceil() as u64)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_uses_sliding_window() {
+        let start = Instant::now();
+        let mut meter = RateMeter::new();
+        meter.record(start, 100);
+        meter.record(start + Duration::from_secs(10), 100);
+
+        let rate = meter.rate(start + Duration::from_secs(10));
+        assert!((rate - 20.0).abs() < 1e-9, "{}", rate);
+
+        // The first sample ages out; only the second remains.
+        let later = start + THROUGHPUT_WINDOW + Duration::from_secs(5);
+        let rate = meter.rate(later);
+        assert!((rate - 100.0 / 55.0).abs() < 1e-9, "{}", rate);
+        assert_eq!(meter.total, 200);
+
+        let idle = start + THROUGHPUT_WINDOW * 3;
+        assert_eq!(meter.rate(idle), 0.0);
+    }
+
+    #[test]
+    fn test_first_sample_rate_is_bounded() {
+        let now = Instant::now();
+        let mut meter = RateMeter::new();
+        meter.record(now, 32);
+        assert_eq!(meter.rate(now), 32.0);
+    }
+
+    #[test]
+    fn test_eta_uses_slowest_stage() {
+        let start = Instant::now();
+        let throughput = Throughput::new();
+        {
+            let mut meters = throughput.meters.lock().unwrap();
+            for i in 0..10 {
+                let at = start + Duration::from_secs(i);
+                meters.embed_docs.record(at, 2);
+                meters.index_docs.record(at, 10);
+            }
+            meters.embed_chunks.record(start, 500);
+        }
+
+        let progress = PipelineProgress {
+            extract: StageProgress {
+                pending: 30,
+                ..Default::default()
+            },
+            index: StageProgress {
+                pending: 20,
+                ..Default::default()
+            },
+            ..PipelineProgress::new("col".to_string())
+        };
+        let stats = throughput.stats_at(start + Duration::from_secs(10), &[progress]);
+
+        assert_eq!(stats.docs_remaining, 50);
+        assert_eq!(stats.chunks_embedded, 500);
+        // 30 docs at 2/s to embed, 50 at 10/s to index: embedding decides.
+        assert_eq!(stats.eta_secs, Some(15));
+    }
+
+    #[test]
+    fn test_eta_unknown_without_rate() {
+        let throughput = Throughput::new();
+        let progress = PipelineProgress {
+            embed: StageProgress {
+                pending: 5,
+                ..Default::default()
+            },
+            ..PipelineProgress::new("col".to_string())
+        };
+        assert_eq!(throughput.stats(&[progress]).eta_secs, None);
+        assert_eq!(throughput.stats(&[]).eta_secs, Some(0));
+    }
+}
@@ -0,0 +1,279 @@
+//! What syncing each collection has cost in data.
+//!
+//! The collection watcher counts the entries each sync exchanged and the
+//! content downloaded from peers; the blobs protocol reports content
+//! served to them, which counts toward every watched collection holding
+//! it. Content downloaded or served outside of sync, such as a document
+//! sent on its own, isn't counted. Kept in memory, so it covers this run
+//! only.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use iroh_blobs::Hash;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+use crate::storage::Upload;
+
+/// Snapshots held for subscribers that fall behind.
+const SNAPSHOT_CAPACITY: usize = 64;
+
+/// Data a collection exchanged with peers since the app started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollectionTransfer {
+    pub collection_id: String,
+    /// Content bytes downloaded from peers.
+    pub bytes_downloaded: u64,
+    /// Content bytes peers downloaded from this node.
+    pub bytes_uploaded: u64,
+    /// Entries received and sent in syncs with peers.
+    pub entries_received: u64,
+    pub entries_sent: u64,
+    /// When anything was last exchanged, RFC 3339.
+    pub last_transfer: Option<String>,
+}
+
+impl CollectionTransfer {
+    fn new(collection_id: &str) -> Self {
+        Self {
+            collection_id: collection_id.to_string(),
+            bytes_downloaded: 0,
+            bytes_uploaded: 0,
+            entries_received: 0,
+            entries_sent: 0,
+            last_transfer: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    collections: HashMap<String, CollectionTransfer>,
+    /// Watched collections holding each piece of content.
+    holders: HashMap<Hash, HashSet<String>>,
+    /// Collections changed since the last snapshot was broadcast.
+    changed: HashSet<String>,
+}
+
+impl Counters {
+    fn update(&mut self, collection_id: &str, f: impl FnOnce(&mut CollectionTransfer)) {
+        let transfer = self
+            .collections
+            .entry(collection_id.to_string())
+            .or_insert_with(|| CollectionTransfer::new(collection_id));
+        transfer.last_transfer = Some(Utc::now().to_rfc3339());
+        f(transfer);
+        self.changed.insert(collection_id.to_string());
+    }
+}
+
+/// Transfer counts of every watched collection. Clones share state.
+#[derive(Clone)]
+pub struct TransferStats {
+    counters: Arc<Mutex<Counters>>,
+    snapshots: broadcast::Sender<CollectionTransfer>,
+}
+
+impl Default for TransferStats {
+    fn default() -> Self {
+        let (snapshots, _) = broadcast::channel(SNAPSHOT_CAPACITY);
+        Self {
+            counters: Arc::new(Mutex::new(Counters::default())),
+            snapshots,
+        }
+    }
+}
+
+impl TransferStats {
+    /// What a collection exchanged; all zero if nothing yet.
+    pub fn collection(&self, collection_id: &str) -> CollectionTransfer {
+        self.counters
+            .lock()
+            .unwrap()
+            .collections
+            .get(collection_id)
+            .cloned()
+            .unwrap_or_else(|| CollectionTransfer::new(collection_id))
+    }
+
+    /// Snapshots of collections as they exchange data, a few seconds
+    /// apart.
+    pub fn subscribe(&self) -> broadcast::Receiver<CollectionTransfer> {
+        self.snapshots.subscribe()
+    }
+
+    /// A sync with a peer finished, receiving and sending the given
+    /// number of entries.
+    pub(super) fn synced(&self, collection_id: &str, received: usize, sent: usize) {
+        if received == 0 && sent == 0 {
+            return;
+        }
+        self.counters.lock().unwrap().update(collection_id, |t| {
+            t.entries_received += received as u64;
+            t.entries_sent += sent as u64;
+        });
+    }
+
+    /// `bytes` of content were downloaded from a peer.
+    pub(super) fn downloaded(&self, collection_id: &str, hash: Hash, bytes: u64) {
+        let mut counters = self.counters.lock().unwrap();
+        counters
+            .holders
+            .entry(hash)
+            .or_default()
+            .insert(collection_id.to_string());
+        counters.update(collection_id, |t| t.bytes_downloaded += bytes);
+    }
+
+    /// The collection holds content with these hashes, which peers may
+    /// download from it.
+    pub(super) fn holds(&self, collection_id: &str, hashes: impl IntoIterator<Item = Hash>) {
+        let mut counters = self.counters.lock().unwrap();
+        for hash in hashes {
+            counters
+                .holders
+                .entry(hash)
+                .or_default()
+                .insert(collection_id.to_string());
+        }
+    }
+
+    /// A peer downloaded content from this node.
+    pub(super) fn uploaded(&self, upload: Upload) {
+        let mut counters = self.counters.lock().unwrap();
+        let Some(holders) = counters.holders.get(&upload.hash).cloned() else {
+            return;
+        };
+        for collection_id in holders {
+            counters.update(&collection_id, |t| t.bytes_uploaded += upload.bytes);
+        }
+    }
+
+    /// Drop everything recorded for a collection no longer watched.
+    pub(super) fn forget(&self, collection_id: &str) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.collections.remove(collection_id);
+        counters.changed.remove(collection_id);
+        counters.holders.retain(|_, holders| {
+            holders.remove(collection_id);
+            !holders.is_empty()
+        });
+    }
+
+    /// Count uploads as the blobs protocol reports them.
+    pub(super) fn count_uploads(
+        &self,
+        mut uploads: broadcast::Receiver<Upload>,
+        cancel: CancellationToken,
+    ) {
+        let stats = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    upload = uploads.recv() => match upload {
+                        Ok(upload) => stats.uploaded(upload),
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            tracing::debug!(missed, "Missed counting uploads");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                }
+            }
+        });
+    }
+
+    /// Broadcast a snapshot of each collection that exchanged anything,
+    /// every `period`.
+    pub(super) fn spawn_reporter(&self, period: Duration, cancel: CancellationToken) {
+        let stats = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = interval.tick() => stats.broadcast_changed(),
+                }
+            }
+        });
+    }
+
+    fn broadcast_changed(&self) {
+        let snapshots: Vec<CollectionTransfer> = {
+            let mut counters = self.counters.lock().unwrap();
+            let changed = std::mem::take(&mut counters.changed);
+            changed
+                .iter()
+                .filter_map(|id| counters.collections.get(id).cloned())
+                .collect()
+        };
+        for snapshot in snapshots {
+            let _ = self.snapshots.send(snapshot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_transfers_per_collection() {
+        let stats = TransferStats::default();
+        let shared = Hash::new(b"in both");
+        let own = Hash::new(b"only in col");
+
+        stats.synced("col", 10, 2);
+        stats.synced("col", 5, 0);
+        stats.downloaded("col", own, 300);
+        stats.holds("other", [shared]);
+        stats.holds("col", [shared]);
+        stats.uploaded(Upload {
+            hash: shared,
+            bytes: 100,
+        });
+        stats.uploaded(Upload {
+            hash: own,
+            bytes: 40,
+        });
+        stats.uploaded(Upload {
+            hash: Hash::new(b"unknown"),
+            bytes: 1_000,
+        });
+
+        let col = stats.collection("col");
+        assert_eq!((col.entries_received, col.entries_sent), (15, 2));
+        assert_eq!(col.bytes_downloaded, 300);
+        assert_eq!(col.bytes_uploaded, 140);
+        assert!(col.last_transfer.is_some());
+        assert_eq!(stats.collection("other").bytes_uploaded, 100);
+
+        stats.forget("col");
+        assert_eq!(stats.collection("col"), CollectionTransfer::new("col"));
+        stats.uploaded(Upload {
+            hash: own,
+            bytes: 40,
+        });
+        assert_eq!(stats.collection("col").bytes_uploaded, 0);
+        assert_eq!(stats.collection("other").bytes_uploaded, 100);
+    }
+
+    #[test]
+    fn test_broadcasts_changed_collections_once() {
+        let stats = TransferStats::default();
+        let mut snapshots = stats.subscribe();
+
+        stats.synced("col", 1, 0);
+        stats.synced("col", 1, 0);
+        stats.broadcast_changed();
+        assert_eq!(snapshots.try_recv().unwrap().entries_received, 2);
+        assert!(snapshots.try_recv().is_err());
+
+        stats.broadcast_changed();
+        assert!(snapshots.try_recv().is_err());
+    }
+}
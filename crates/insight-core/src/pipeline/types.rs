@@ -70,6 +70,20 @@ pub enum ProgressUpdate {
     },
     /// Job queued for processing (-> pending).
     Queued { collection_id: String, stage: Stage },
+    /// Job dropped because its import was cancelled (pending or active
+    /// -> cancelled).
+    Cancelled {
+        collection_id: String,
+        stage: Stage,
+        was_active: bool,
+    },
+    /// Job sent back to its stage to try again (active or failed ->
+    /// pending).
+    Retrying {
+        collection_id: String,
+        stage: Stage,
+        was_failed: bool,
+    },
     /// Per-item progress within an active job (e.g. OCR page 5/20).
     PageProgress {
         collection_id: String,
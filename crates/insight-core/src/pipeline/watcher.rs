@@ -2,20 +2,32 @@
 //!
 //! Watches iroh-docs events and dispatches to worker pools based on key patterns.
 
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 
 use futures::StreamExt;
-use iroh_docs::NamespaceId;
-use tokio::sync::{mpsc, RwLock};
+use iroh::EndpointId;
+use iroh_blobs::api::downloader::Downloader;
+use iroh_blobs::Hash;
+use iroh_docs::{ContentStatus, NamespaceId};
+use tokio::sync::{mpsc, watch, RwLock, Semaphore};
+use tokio::time::Instant;
 use tokio_util::sync::CancellationToken;
 
+use crate::config::SyncPolicy;
+use crate::hooks::{HookEvent, Hooks};
 use crate::manager::ModelManager;
-use crate::storage::{LiveEvent, Storage};
+use crate::storage::{LiveEvent, MissingContent, Storage};
 
-use super::progress::ProgressTracker;
+use super::control::ParkedJob;
+use super::progress::{ProgressTracker, SyncStatus};
+use super::sync_health::SyncHealth;
+use super::transfer_stats::TransferStats;
 use super::types::{EmbedJob, ExtractJob, IndexJob, OcrJob, Stage};
 
 /// Grouped job dispatch channels for pipeline stages.
+#[derive(Clone)]
 pub struct JobSenders {
     pub extract: mpsc::UnboundedSender<ExtractJob>,
     pub ocr: mpsc::UnboundedSender<OcrJob>,
@@ -23,6 +35,31 @@ pub struct JobSenders {
     pub index: mpsc::UnboundedSender<IndexJob>,
 }
 
+impl JobSenders {
+    /// Send a held-back job to its stage.
+    pub fn send(&self, job: ParkedJob) {
+        match job {
+            ParkedJob::Extract(job) => {
+                let _ = self.extract.send(job);
+            }
+            ParkedJob::Ocr(job) => {
+                let _ = self.ocr.send(job);
+            }
+            ParkedJob::Embed(job) => {
+                let _ = self.embed.send(job);
+            }
+            ParkedJob::Index(job) => {
+                let _ = self.index.send(job);
+            }
+        }
+    }
+}
+
+/// Check if key is a metadata entry: files/{doc_id}/meta
+fn is_meta_key(key: &str) -> bool {
+    key.starts_with("files/") && key.ends_with("/meta")
+}
+
 /// Check if key is a source entry: files/{doc_id}/source
 fn is_source_key(key: &str) -> bool {
     key.starts_with("files/") && key.ends_with("/source")
@@ -38,6 +75,11 @@ fn is_ocr_task_key(key: &str) -> bool {
     key.starts_with("files/") && key.ends_with("/ocr_task")
 }
 
+/// Check if key is a tag entry: files/{doc_id}/tags/{tag}
+fn is_tag_key(key: &str) -> bool {
+    key.starts_with("files/") && key.contains("/tags/")
+}
+
 /// Check if key is an embedding entry: files/{doc_id}/embeddings/{model_id}
 fn is_embedding_key(key: &str) -> bool {
     key.starts_with("files/") && key.contains("/embeddings/")
@@ -60,9 +102,37 @@ fn extract_model_id(key: &str) -> Option<&str> {
     }
 }
 
+/// Downloads the watcher runs at once, for policies that leave
+/// downloading to it.
+const CONCURRENT_DOWNLOADS: usize = 4;
+
+/// How long a peer's text waits for the peer's embeddings before it is
+/// embedded here. Peers write embeddings shortly after the text, but they
+/// sync as separate entries.
+const PEER_EMBED_GRACE: Duration = Duration::from_secs(60);
+
+/// Whether `policy` has content of entry `key` downloaded as it syncs.
+fn policy_downloads(policy: SyncPolicy, key: &str) -> bool {
+    match policy {
+        SyncPolicy::Everything => true,
+        SyncPolicy::Text => {
+            !key.starts_with("files/") || is_meta_key(key) || is_tag_key(key) || is_text_key(key)
+        }
+        SyncPolicy::Metadata => !key.starts_with("files/") || is_meta_key(key) || is_tag_key(key),
+    }
+}
+
+/// Whether iroh downloads content of entry `key` under `policy`; see
+/// [`Storage::set_download_policy`].
+fn iroh_downloads(policy: SyncPolicy, key: &str) -> bool {
+    policy == SyncPolicy::Everything || !key.starts_with("files/")
+}
+
 /// Watches a collection for iroh events and dispatches to worker pools.
 pub struct CollectionWatcher {
     cancel: CancellationToken,
+    policy: watch::Sender<SyncPolicy>,
+    fetch: mpsc::UnboundedSender<String>,
 }
 
 impl CollectionWatcher {
@@ -72,17 +142,36 @@ impl CollectionWatcher {
     /// to the appropriate worker pools based on key patterns:
     /// - files/*/source (InsertLocal only) → Extract
     /// - files/*/ocr_task (InsertLocal only) → OCR
-    /// - files/*/text → Embed
+    /// - files/*/text → Embed (a peer's waits for the peer's embeddings)
     /// - files/*/embeddings/* → Index
+    ///
+    /// Source entries also run the [`Hooks`]: stored locally is an
+    /// import, by a peer a sync, and emptied a deletion.
+    ///
+    /// Entries from peers are dispatched once their content has been
+    /// downloaded, and each step of a synced document is reported as a
+    /// [`SyncedDocument`](super::SyncedDocument). How syncing with each
+    /// peer goes is recorded in `health`.
+    ///
+    /// Under [`SyncPolicy::Everything`] iroh downloads all content. Under
+    /// a narrower `policy` the watcher downloads what it allows itself,
+    /// and the rest of a document when [`Self::fetch_document`] asks.
+    #[allow(clippy::too_many_arguments)]
     pub fn spawn(
         namespace_id: NamespaceId,
         storage: Arc<RwLock<Storage>>,
         models: Arc<ModelManager>,
         senders: JobSenders,
         progress: ProgressTracker,
+        health: SyncHealth,
+        transfers: TransferStats,
+        hooks: Hooks,
+        policy: SyncPolicy,
         cancel: CancellationToken,
     ) -> Self {
         let cancel_clone = cancel.clone();
+        let (policy_tx, policy_rx) = watch::channel(policy);
+        let (fetch_tx, fetch_rx) = mpsc::unbounded_channel();
 
         tokio::spawn(async move {
             if let Err(e) = run_watcher(
@@ -91,6 +180,11 @@ impl CollectionWatcher {
                 models,
                 senders,
                 progress,
+                health,
+                transfers,
+                hooks,
+                policy_rx,
+                fetch_rx,
                 cancel_clone.clone(),
             )
             .await
@@ -105,7 +199,22 @@ impl CollectionWatcher {
             }
         });
 
-        Self { cancel }
+        Self {
+            cancel,
+            policy: policy_tx,
+            fetch: fetch_tx,
+        }
+    }
+
+    /// Change what is downloaded. Content the new policy allows that
+    /// hasn't been downloaded yet is fetched.
+    pub fn set_policy(&self, policy: SyncPolicy) {
+        self.policy.send_replace(policy);
+    }
+
+    /// Download whatever of a document hasn't been downloaded yet.
+    pub fn fetch_document(&self, doc_id: &str) {
+        let _ = self.fetch.send(doc_id.to_string());
     }
 
     /// Stop the watcher.
@@ -114,29 +223,51 @@ impl CollectionWatcher {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_watcher(
     namespace_id: NamespaceId,
     storage: Arc<RwLock<Storage>>,
     models: Arc<ModelManager>,
     senders: JobSenders,
     progress: ProgressTracker,
+    health: SyncHealth,
+    transfers: TransferStats,
+    hooks: Hooks,
+    mut policy: watch::Receiver<SyncPolicy>,
+    mut fetch: mpsc::UnboundedReceiver<String>,
     cancel: CancellationToken,
 ) -> anyhow::Result<()> {
-    // Subscribe to namespace events (need storage just for subscription)
-    let stream = {
+    let (stream, downloader) = {
         let storage_guard = storage.read().await;
-        storage_guard.subscribe(namespace_id).await?
+        (
+            storage_guard.subscribe(namespace_id).await?,
+            storage_guard.downloader(),
+        )
     };
-    // Drop storage reference - we don't need it after subscribing
-    drop(storage);
 
     tokio::pin!(stream);
 
     let collection_id = namespace_id.to_string();
+    let (fetched_tx, mut fetched_rx) = mpsc::unbounded_channel();
+    let mut downloads = Downloads::new(downloader, fetched_tx, transfers.clone());
     tracing::info!(namespace = %namespace_id, "CollectionWatcher started");
 
+    // Content already in the collection counts toward it when peers
+    // download it.
+    match storage.read().await.content_hashes(namespace_id).await {
+        Ok(hashes) => transfers.holds(&collection_id, hashes),
+        Err(e) => {
+            tracing::warn!(namespace = %namespace_id, error = %e, "Failed to list content");
+        }
+    }
+
+    // Pick up content left undownloaded by an earlier run or policy.
+    policy.mark_changed();
+
+    let mut deferred = DeferredEmbeds::default();
     loop {
-        tokio::select! {
+        let next_due = deferred.next_due();
+        let inserts = tokio::select! {
             biased;
 
             _ = cancel.cancelled() => {
@@ -144,20 +275,75 @@ async fn run_watcher(
                 break;
             }
 
+            _ = tokio::time::sleep_until(next_due.unwrap_or_else(Instant::now)),
+                if next_due.is_some() =>
+            {
+                for doc_id in deferred.take_due(Instant::now()) {
+                    tracing::debug!(doc_id = %doc_id, "No embeddings from peer, embedding here");
+                    queue_embed(namespace_id, &collection_id, doc_id, &senders, &progress).await;
+                }
+                continue;
+            }
+
+            Ok(()) = policy.changed() => {
+                let current = *policy.borrow_and_update();
+                let storage = storage.read().await;
+                if let Err(e) = storage.set_download_policy(namespace_id, current).await {
+                    tracing::warn!(
+                        namespace = %namespace_id,
+                        error = %e,
+                        "Failed to set download policy"
+                    );
+                }
+                match missing_content(&storage, namespace_id, "files/").await {
+                    Ok((missing, peers)) => {
+                        let missing = missing
+                            .into_iter()
+                            .filter(|m| policy_downloads(current, &m.key))
+                            .collect();
+                        downloads.fetch(missing, peers, &collection_id, &progress, &health);
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            namespace = %namespace_id,
+                            error = %e,
+                            "Failed to find undownloaded content"
+                        );
+                    }
+                }
+                continue;
+            }
+
+            Some(doc_id) = fetch.recv() => {
+                let storage = storage.read().await;
+                let prefix = format!("files/{}/", doc_id);
+                match missing_content(&storage, namespace_id, &prefix).await {
+                    Ok((missing, peers)) => {
+                        tracing::info!(
+                            doc_id = %doc_id,
+                            entries = missing.len(),
+                            "Fetching document"
+                        );
+                        downloads.fetch(missing, peers, &collection_id, &progress, &health);
+                    }
+                    Err(e) => {
+                        tracing::warn!(doc_id = %doc_id, error = %e, "Failed to fetch document");
+                    }
+                }
+                continue;
+            }
+
+            Some(fetched) = fetched_rx.recv() => {
+                downloads.finished(fetched, &collection_id, &health)
+            }
+
             event = stream.next() => {
                 match event {
                     Some(Ok(live_event)) => {
-                        // Read configured embedding model once per event
-                        let current_model_id = models.embedding_model_id().await;
-                        handle_event(
-                            &live_event,
-                            namespace_id,
-                            &collection_id,
-                            &current_model_id,
-                            &senders,
-                            &progress,
-                        )
-                        .await;
+                        record_peer(&live_event, &collection_id, &health);
+                        record_transfer(&live_event, &collection_id, &transfers);
+                        let current = *policy.borrow();
+                        downloads.ready(live_event, current, &collection_id, &progress, &health)
                     }
                     Some(Err(e)) => {
                         tracing::warn!(
@@ -165,6 +351,7 @@ async fn run_watcher(
                             error = %e,
                             "Event stream error"
                         );
+                        continue;
                     }
                     None => {
                         tracing::debug!(namespace = %namespace_id, "Event stream ended");
@@ -172,34 +359,447 @@ async fn run_watcher(
                     }
                 }
             }
+        };
+        if inserts.is_empty() {
+            continue;
         }
+
+        // Read configured embedding model once per event
+        let current_model_id = models.embedding_model_id().await;
+        let current_policy = *policy.borrow();
+        dispatch(
+            &inserts,
+            namespace_id,
+            &collection_id,
+            &current_model_id,
+            current_policy,
+            &storage,
+            &senders,
+            &progress,
+            &hooks,
+            &mut deferred,
+        )
+        .await;
     }
 
     tracing::info!(namespace = %namespace_id, "CollectionWatcher stopped");
     Ok(())
 }
 
-async fn handle_event(
-    event: &LiveEvent,
+/// Peer texts whose embed is held back while the peer's embeddings may
+/// still arrive; see [`PEER_EMBED_GRACE`].
+#[derive(Default)]
+struct DeferredEmbeds {
+    /// When each held-back text is embedded here, by document.
+    due: HashMap<String, Instant>,
+    /// Documents whose grace period ran out. They are indexed from the
+    /// vectors embedded here, so the peer's are not indexed again.
+    released: HashSet<String>,
+}
+
+impl DeferredEmbeds {
+    fn defer(&mut self, doc_id: String, now: Instant) {
+        self.released.remove(&doc_id);
+        self.due.insert(doc_id, now + PEER_EMBED_GRACE);
+    }
+
+    /// The peer's embeddings for `doc_id` arrived. Drops a held-back
+    /// embed; returns whether they should be indexed.
+    fn peer_embedded(&mut self, doc_id: &str) -> bool {
+        self.due.remove(doc_id);
+        !self.released.remove(doc_id)
+    }
+
+    fn next_due(&self) -> Option<Instant> {
+        self.due.values().min().copied()
+    }
+
+    /// Documents whose grace period has run out by `now`.
+    fn take_due(&mut self, now: Instant) -> Vec<String> {
+        let due: Vec<String> = self
+            .due
+            .iter()
+            .filter(|(_, at)| **at <= now)
+            .map(|(doc_id, _)| doc_id.clone())
+            .collect();
+        for doc_id in &due {
+            self.due.remove(doc_id);
+            self.released.insert(doc_id.clone());
+        }
+        due
+    }
+}
+
+/// Dispatch a batch of inserts to the worker pools.
+///
+/// A peer's text is only embedded here if the peer's embeddings for the
+/// current model won't sync: if they already have, it is skipped, and
+/// otherwise it waits for them in `deferred`.
+#[allow(clippy::too_many_arguments)]
+async fn dispatch(
+    inserts: &[Insert],
     namespace_id: NamespaceId,
     collection_id: &str,
     model_id: &Option<String>,
+    policy: SyncPolicy,
+    storage: &RwLock<Storage>,
     senders: &JobSenders,
     progress: &ProgressTracker,
+    hooks: &Hooks,
+    deferred: &mut DeferredEmbeds,
 ) {
-    let (key_bytes, is_local) = match event {
-        LiveEvent::InsertLocal { entry, .. } => (entry.key(), true),
-        LiveEvent::InsertRemote { entry, .. } => (entry.key(), false),
-        _ => return,
+    for insert in inserts {
+        let doc_id = extract_doc_id(&insert.key);
+        if is_text_key(&insert.key) && !insert.is_local {
+            if let Some(doc_id) =
+                doc_id.filter(|_| syncs_peer_embeddings(&insert.key, model_id, policy))
+            {
+                if has_peer_embeddings(storage, namespace_id, doc_id, model_id).await {
+                    tracing::debug!(doc_id = %doc_id, "Peer embedded the text, skipping embed");
+                } else {
+                    tracing::debug!(doc_id = %doc_id, "Waiting for the peer's embeddings");
+                    deferred.defer(doc_id.to_string(), Instant::now());
+                }
+                continue;
+            }
+        }
+        if is_embedding_key(&insert.key)
+            && !insert.is_local
+            && extract_model_id(&insert.key) == model_id.as_deref()
+        {
+            if let Some(doc_id) = doc_id.filter(|doc_id| !deferred.peer_embedded(doc_id)) {
+                tracing::debug!(doc_id = %doc_id, "Embedded here already, not indexing peer's");
+                continue;
+            }
+        }
+        handle_insert(
+            insert,
+            namespace_id,
+            collection_id,
+            model_id,
+            senders,
+            progress,
+            hooks,
+        )
+        .await;
+    }
+}
+
+/// Entries under `prefix` whose content is missing, with the peers it
+/// can be downloaded from.
+async fn missing_content(
+    storage: &Storage,
+    namespace_id: NamespaceId,
+    prefix: &str,
+) -> anyhow::Result<(Vec<MissingContent>, Vec<EndpointId>)> {
+    let missing = storage.missing_content(namespace_id, prefix).await?;
+    if missing.is_empty() {
+        return Ok((missing, Vec::new()));
+    }
+    Ok((missing, storage.sync_peers(namespace_id).await?))
+}
+
+/// Whether `policy` downloads peers' embeddings of the text at
+/// `text_key` with `model_id`. Those are indexed as they arrive, so
+/// embedding the text here would only repeat the work.
+fn syncs_peer_embeddings(text_key: &str, model_id: &Option<String>, policy: SyncPolicy) -> bool {
+    let (Some(doc_id), Some(model_id)) = (extract_doc_id(text_key), model_id) else {
+        return false;
     };
+    policy_downloads(policy, &format!("files/{}/embeddings/{}", doc_id, model_id))
+}
+
+/// Whether embeddings of `doc_id` with `model_id` have synced already.
+async fn has_peer_embeddings(
+    storage: &RwLock<Storage>,
+    namespace_id: NamespaceId,
+    doc_id: &str,
+    model_id: &Option<String>,
+) -> bool {
+    let Some(model_id) = model_id else {
+        return false;
+    };
+    let storage = storage.read().await;
+    match storage.has_embeddings(namespace_id, doc_id, model_id).await {
+        Ok(found) => found,
+        Err(e) => {
+            tracing::warn!(doc_id = %doc_id, error = %e, "Failed to look up peer embeddings");
+            false
+        }
+    }
+}
+
+/// Record neighbors coming and going and syncs finishing in `health`.
+fn record_peer(event: &LiveEvent, collection_id: &str, health: &SyncHealth) {
+    match event {
+        LiveEvent::NeighborUp(peer) => health.neighbor(collection_id, &peer.to_string(), true),
+        LiveEvent::NeighborDown(peer) => health.neighbor(collection_id, &peer.to_string(), false),
+        LiveEvent::SyncFinished(sync) => health.sync_finished(
+            collection_id,
+            &sync.peer.to_string(),
+            sync.finished,
+            sync.result
+                .as_ref()
+                .map(|details| (details.entries_received, details.entries_sent))
+                .map_err(String::as_str),
+        ),
+        _ => {}
+    }
+}
+
+/// Record entries synced and content the collection holds in `transfers`.
+fn record_transfer(event: &LiveEvent, collection_id: &str, transfers: &TransferStats) {
+    match event {
+        LiveEvent::InsertLocal { entry, .. } | LiveEvent::InsertRemote { entry, .. }
+            if entry.content_len() > 0 =>
+        {
+            transfers.holds(collection_id, [entry.content_hash()]);
+        }
+        LiveEvent::SyncFinished(sync) => {
+            if let Ok(details) = &sync.result {
+                transfers.synced(
+                    collection_id,
+                    details.entries_received,
+                    details.entries_sent,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// An entry written to the collection whose content is available.
+struct Insert {
+    key: String,
+    content_len: u64,
+    is_local: bool,
+    /// The peer a remote entry came from, if known.
+    from: Option<EndpointId>,
+}
 
-    let key = String::from_utf8_lossy(key_bytes);
-    let doc_id = match extract_doc_id(&key) {
+/// A download the watcher started, finished or failed.
+struct Fetched {
+    hash: Hash,
+    result: anyhow::Result<()>,
+}
+
+/// Entries from peers whose content is still being downloaded, by hash.
+/// Documents with identical text share a hash.
+struct Downloads {
+    pending: HashMap<Hash, Vec<Insert>>,
+    downloader: Downloader,
+    permits: Arc<Semaphore>,
+    fetched: mpsc::UnboundedSender<Fetched>,
+    transfers: TransferStats,
+}
+
+impl Downloads {
+    fn new(
+        downloader: Downloader,
+        fetched: mpsc::UnboundedSender<Fetched>,
+        transfers: TransferStats,
+    ) -> Self {
+        Self {
+            pending: HashMap::new(),
+            downloader,
+            permits: Arc::new(Semaphore::new(CONCURRENT_DOWNLOADS)),
+            fetched,
+            transfers,
+        }
+    }
+
+    /// The entries `event` makes available. A peer's entry is held back
+    /// until its content is downloaded: until then, reading it would
+    /// fail, and it counts as pending for its peer in `health`. iroh
+    /// downloads content under [`SyncPolicy::Everything`]; otherwise the
+    /// watcher downloads what `policy` allows and leaves the rest.
+    fn ready(
+        &mut self,
+        event: LiveEvent,
+        policy: SyncPolicy,
+        collection_id: &str,
+        progress: &ProgressTracker,
+        health: &SyncHealth,
+    ) -> Vec<Insert> {
+        match event {
+            LiveEvent::InsertLocal { entry, .. } => vec![Insert {
+                key: String::from_utf8_lossy(entry.key()).into_owned(),
+                content_len: entry.content_len(),
+                is_local: true,
+                from: None,
+            }],
+            LiveEvent::InsertRemote {
+                from,
+                entry,
+                content_status,
+            } => {
+                let insert = Insert {
+                    key: String::from_utf8_lossy(entry.key()).into_owned(),
+                    content_len: entry.content_len(),
+                    is_local: false,
+                    from: EndpointId::from_bytes(&from).ok(),
+                };
+                if entry.content_len() == 0 || matches!(content_status, ContentStatus::Complete) {
+                    return vec![insert];
+                }
+                if !policy_downloads(policy, &insert.key) {
+                    return Vec::new();
+                }
+                let hash = entry.content_hash();
+                let download = !iroh_downloads(policy, &insert.key);
+                let from = insert.from;
+                if self.hold(hash, insert, collection_id, progress, health) && download {
+                    self.download(hash, from.into_iter().collect());
+                }
+                Vec::new()
+            }
+            LiveEvent::ContentReady { hash } => {
+                let inserts = self.release(hash, collection_id, health);
+                self.count_download(hash, &inserts, collection_id);
+                inserts
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Download `missing` content from `peers`, holding its entries back
+    /// until it arrives.
+    fn fetch(
+        &mut self,
+        missing: Vec<MissingContent>,
+        peers: Vec<EndpointId>,
+        collection_id: &str,
+        progress: &ProgressTracker,
+        health: &SyncHealth,
+    ) {
+        for content in missing {
+            let insert = Insert {
+                key: content.key,
+                content_len: content.len,
+                is_local: false,
+                from: None,
+            };
+            if self.hold(content.hash, insert, collection_id, progress, health) {
+                self.download(content.hash, peers.clone());
+            }
+        }
+    }
+
+    /// The entries a download the watcher started makes available. If it
+    /// failed they are dropped; fetching the document tries again.
+    fn finished(
+        &mut self,
+        fetched: Fetched,
+        collection_id: &str,
+        health: &SyncHealth,
+    ) -> Vec<Insert> {
+        let inserts = self.release(fetched.hash, collection_id, health);
+        match fetched.result {
+            Ok(()) => {
+                self.count_download(fetched.hash, &inserts, collection_id);
+                inserts
+            }
+            Err(e) => {
+                tracing::warn!(
+                    collection = %collection_id,
+                    hash = %fetched.hash,
+                    entries = inserts.len(),
+                    error = %e,
+                    "Failed to download content"
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// Hold `insert` back until `hash` is downloaded, unless it already
+    /// is. Returns whether nothing was waiting for `hash` yet.
+    fn hold(
+        &mut self,
+        hash: Hash,
+        insert: Insert,
+        collection_id: &str,
+        progress: &ProgressTracker,
+        health: &SyncHealth,
+    ) -> bool {
+        let waiting = self.pending.entry(hash).or_default();
+        if waiting.iter().any(|held| held.key == insert.key) {
+            return false;
+        }
+        if let Some(doc_id) = extract_doc_id(&insert.key) {
+            progress.report_synced(collection_id, doc_id, SyncStatus::Downloading);
+        }
+        if let Some(peer) = insert.from {
+            health.content_pending(collection_id, &peer.to_string());
+        }
+        waiting.push(insert);
+        waiting.len() == 1
+    }
+
+    /// The entries waiting for `hash`, no longer pending for their peers.
+    fn release(&mut self, hash: Hash, collection_id: &str, health: &SyncHealth) -> Vec<Insert> {
+        let inserts = self.pending.remove(&hash).unwrap_or_default();
+        for peer in inserts.iter().filter_map(|insert| insert.from) {
+            health.content_ready(collection_id, &peer.to_string());
+        }
+        inserts
+    }
+
+    /// Count content that arrived for entries held back waiting for it.
+    /// Entries sharing it were downloaded once.
+    fn count_download(&self, hash: Hash, inserts: &[Insert], collection_id: &str) {
+        if let Some(insert) = inserts.first() {
+            self.transfers
+                .downloaded(collection_id, hash, insert.content_len);
+        }
+    }
+
+    fn download(&self, hash: Hash, providers: Vec<EndpointId>) {
+        let downloader = self.downloader.clone();
+        let permits = self.permits.clone();
+        let fetched = self.fetched.clone();
+        tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let result = downloader
+                .download(hash, providers)
+                .await
+                .map_err(anyhow::Error::from);
+            let _ = fetched.send(Fetched { hash, result });
+        });
+    }
+}
+
+async fn handle_insert(
+    insert: &Insert,
+    namespace_id: NamespaceId,
+    collection_id: &str,
+    model_id: &Option<String>,
+    senders: &JobSenders,
+    progress: &ProgressTracker,
+    hooks: &Hooks,
+) {
+    let key = insert.key.as_str();
+    let is_local = insert.is_local;
+    let doc_id = match extract_doc_id(key) {
         Some(id) => id.to_string(),
         None => return,
     };
 
-    if is_source_key(&key) && is_local {
+    if is_meta_key(key) && !is_local && insert.content_len > 0 {
+        progress.report_synced(collection_id, &doc_id, SyncStatus::Received);
+    }
+
+    if is_source_key(key) {
+        // Deleting writes an empty entry over each key
+        let hook_event = match (insert.content_len, is_local) {
+            (0, _) => HookEvent::Deleted,
+            (_, true) => HookEvent::Imported,
+            (_, false) => HookEvent::Synced,
+        };
+        hooks.notify(hook_event, namespace_id, &doc_id);
+    }
+
+    if is_source_key(key) && is_local {
         // Local source stored → queue extract
         tracing::debug!(doc_id = %doc_id, "Source stored, queuing extract");
         progress.queue(collection_id, Stage::Extract).await;
@@ -207,7 +807,7 @@ async fn handle_event(
             namespace_id,
             doc_id,
         });
-    } else if is_ocr_task_key(&key) && is_local {
+    } else if is_ocr_task_key(key) && is_local {
         // OCR task written by extract → queue OCR. Local-only — peers
         // don't OCR for documents they didn't import.
         tracing::debug!(doc_id = %doc_id, "OCR task queued");
@@ -216,22 +816,21 @@ async fn handle_event(
             namespace_id,
             doc_id,
         });
-    } else if is_text_key(&key) {
+    } else if is_text_key(key) {
         // Text ready → queue embed
         tracing::debug!(doc_id = %doc_id, is_local, "Text ready, queuing embed");
-        progress.queue(collection_id, Stage::Embed).await;
-        let _ = senders.embed.send(EmbedJob {
-            namespace_id,
-            doc_id,
-        });
-    } else if is_embedding_key(&key) {
+        queue_embed(namespace_id, collection_id, doc_id, senders, progress).await;
+    } else if is_embedding_key(key) {
         // Embeddings ready → queue index
-        let event_model_id = extract_model_id(&key).unwrap_or("unknown");
+        let event_model_id = extract_model_id(key).unwrap_or("unknown");
 
         // Only index if this is for our configured model
         if let Some(ref mid) = model_id {
             if mid == event_model_id {
                 tracing::debug!(doc_id = %doc_id, model = %event_model_id, "Embeddings ready, queuing index");
+                if !is_local {
+                    progress.report_synced(collection_id, &doc_id, SyncStatus::EmbeddingReused);
+                }
                 progress.queue(collection_id, Stage::Index).await;
                 let _ = senders.index.send(IndexJob {
                     namespace_id,
@@ -243,9 +842,127 @@ async fn handle_event(
     }
 }
 
+/// Queue `doc_id` for the embed stage.
+async fn queue_embed(
+    namespace_id: NamespaceId,
+    collection_id: &str,
+    doc_id: String,
+    senders: &JobSenders,
+    progress: &ProgressTracker,
+) {
+    progress.queue(collection_id, Stage::Embed).await;
+    let _ = senders.embed.send(EmbedJob {
+        namespace_id,
+        doc_id,
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::PeerAccess;
+
+    /// A collection and the channels a watcher dispatches to.
+    struct Harness {
+        _dir: tempfile::TempDir,
+        storage: Arc<RwLock<Storage>>,
+        namespace_id: NamespaceId,
+        senders: JobSenders,
+        progress: ProgressTracker,
+        hooks: Hooks,
+        embed_rx: mpsc::UnboundedReceiver<EmbedJob>,
+        index_rx: mpsc::UnboundedReceiver<IndexJob>,
+    }
+
+    impl Harness {
+        async fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let storage = Storage::open(dir.path(), &PeerAccess::default())
+                .await
+                .unwrap();
+            let (namespace_id, _) = storage.create_collection("Peer").await.unwrap();
+            let storage = Arc::new(RwLock::new(storage));
+            let (embed, embed_rx) = mpsc::unbounded_channel();
+            let (index, index_rx) = mpsc::unbounded_channel();
+            let senders = JobSenders {
+                extract: mpsc::unbounded_channel().0,
+                ocr: mpsc::unbounded_channel().0,
+                embed,
+                index,
+            };
+            Self {
+                hooks: Hooks::new(storage.clone()),
+                progress: ProgressTracker::new().0,
+                _dir: dir,
+                storage,
+                namespace_id,
+                senders,
+                embed_rx,
+                index_rx,
+            }
+        }
+
+        /// Dispatch an entry synced from a peer.
+        async fn receive(&self, key: &str, deferred: &mut DeferredEmbeds) {
+            let insert = Insert {
+                key: key.to_string(),
+                content_len: 4,
+                is_local: false,
+                from: None,
+            };
+            dispatch(
+                &[insert],
+                self.namespace_id,
+                &self.namespace_id.to_string(),
+                &Some("model-a".to_string()),
+                SyncPolicy::Everything,
+                &self.storage,
+                &self.senders,
+                &self.progress,
+                &self.hooks,
+                deferred,
+            )
+            .await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_peer_embeddings_after_text() {
+        let mut watcher = Harness::new().await;
+        let mut deferred = DeferredEmbeds::default();
+
+        // The peer's embeddings arrive within the grace period: nothing is
+        // embedded here and they are indexed once.
+        watcher.receive("files/doc-1/text", &mut deferred).await;
+        watcher
+            .receive("files/doc-1/embeddings/model-a", &mut deferred)
+            .await;
+        assert!(deferred
+            .take_due(Instant::now() + PEER_EMBED_GRACE)
+            .is_empty());
+        assert!(watcher.embed_rx.try_recv().is_err());
+        assert_eq!(watcher.index_rx.try_recv().unwrap().doc_id, "doc-1");
+        assert!(watcher.index_rx.try_recv().is_err());
+
+        // They arrive late: the text was embedded here, and the peer's
+        // vectors aren't indexed on top.
+        watcher.receive("files/doc-2/text", &mut deferred).await;
+        assert_eq!(
+            deferred.take_due(Instant::now() + PEER_EMBED_GRACE),
+            vec!["doc-2".to_string()]
+        );
+        watcher
+            .receive("files/doc-2/embeddings/model-a", &mut deferred)
+            .await;
+        assert!(watcher.index_rx.try_recv().is_err());
+
+        // Embeddings for another model don't cancel the wait.
+        watcher.receive("files/doc-3/text", &mut deferred).await;
+        watcher
+            .receive("files/doc-3/embeddings/model-b", &mut deferred)
+            .await;
+        assert!(deferred.next_due().is_some());
+    }
 
     #[test]
     fn test_key_patterns() {
@@ -259,11 +976,39 @@ mod tests {
         assert!(is_embedding_key("files/doc-123/embeddings/qwen3"));
         assert!(!is_embedding_key("files/doc-123/text"));
 
+        assert!(is_meta_key("files/doc-123/meta"));
+        assert!(!is_meta_key("files/doc-123/source"));
+
         assert!(is_ocr_task_key("files/doc-123/ocr_task"));
         assert!(!is_ocr_task_key("files/doc-123/text"));
         assert!(!is_ocr_task_key("files/doc-123/source"));
     }
 
+    #[test]
+    fn test_sync_policy_downloads() {
+        let keys = [
+            "_collection",
+            "files/doc-1/meta",
+            "files/doc-1/tags/6163",
+            "files/doc-1/text",
+            "files/doc-1/source",
+            "files/doc-1/embeddings/qwen3",
+        ];
+        let downloaded = |policy| {
+            keys.iter()
+                .filter(|key| policy_downloads(policy, key))
+                .count()
+        };
+        assert_eq!(downloaded(SyncPolicy::Everything), 6);
+        assert_eq!(downloaded(SyncPolicy::Text), 4);
+        assert_eq!(downloaded(SyncPolicy::Metadata), 3);
+
+        // The watcher downloads document content for narrower policies.
+        assert!(iroh_downloads(SyncPolicy::Everything, "files/doc-1/text"));
+        assert!(iroh_downloads(SyncPolicy::Metadata, "_collection"));
+        assert!(!iroh_downloads(SyncPolicy::Text, "files/doc-1/text"));
+    }
+
     #[test]
     fn test_extract_doc_id() {
         assert_eq!(extract_doc_id("files/doc-123/source"), Some("doc-123"));
@@ -1,15 +1,20 @@
 //! Worker pools for pipeline stages.
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
-use tokio::sync::{mpsc, Mutex, RwLock};
+use tokio::sync::{mpsc, Mutex, RwLock, Semaphore};
 
 use crate::manager::ModelManager;
+use crate::provider::{ChunkingConfig, EmbeddingProvider};
 use crate::search::{ChunkToIndex, IndexWorkerHandle};
-use crate::storage::Storage;
+use crate::storage::{fingerprint, Storage, VectorEncoding};
 
-use super::embed::generate_embeddings_data;
-use super::progress::ProgressTracker;
+use super::control::{Admission, ImportControl, ParkedJob};
+use super::embed::{generate_embeddings_data, load_document_text, resolve_chunking, EmbedContext};
+use super::embed_cache::EmbeddingCache;
+use super::progress::{FileProgress, ProgressTracker, SyncStatus};
+use super::retry::Retries;
 use super::types::{EmbedJob, ExtractJob, IndexJob, ProgressUpdate, Stage};
 
 /// Shared receiver for multiple workers pulling from one unbounded channel.
@@ -45,11 +50,15 @@ pub fn spawn_extract_workers(
     count: usize,
     rx: SharedReceiver<ExtractJob>,
     storage: Arc<RwLock<Storage>>,
+    control: Arc<ImportControl>,
+    retries: Arc<Retries>,
     progress: ProgressTracker,
 ) {
     for i in 0..count {
         let rx = rx.clone();
         let storage = storage.clone();
+        let control = control.clone();
+        let retries = retries.clone();
         let progress = progress.clone();
 
         tokio::spawn(async move {
@@ -58,6 +67,42 @@ pub fn spawn_extract_workers(
             while let Some(job) = rx.recv().await {
                 let collection_id = job.namespace_id.to_string();
 
+                let cancel = match control.admit(&collection_id, &job.doc_id) {
+                    Admission::Run(cancel) => cancel,
+                    Admission::Park => {
+                        control.park(ParkedJob::Extract(job));
+                        continue;
+                    }
+                    Admission::Drop => {
+                        progress
+                            .apply(ProgressUpdate::Cancelled {
+                                collection_id,
+                                stage: Stage::Extract,
+                                was_active: false,
+                            })
+                            .await;
+                        continue;
+                    }
+                };
+
+                // Output copied along with a linked document.
+                if control.skip_linked(&job.doc_id, Stage::Extract) {
+                    tracing::debug!(doc_id = %job.doc_id, "Linked document, skipping extract");
+                    for update in [
+                        ProgressUpdate::Started {
+                            collection_id: collection_id.clone(),
+                            stage: Stage::Extract,
+                        },
+                        ProgressUpdate::Completed {
+                            collection_id,
+                            stage: Stage::Extract,
+                        },
+                    ] {
+                        progress.apply(update).await;
+                    }
+                    continue;
+                }
+
                 // Mark as started
                 progress
                     .apply(ProgressUpdate::Started {
@@ -65,22 +110,48 @@ pub fn spawn_extract_workers(
                         stage: Stage::Extract,
                     })
                     .await;
+                progress.report_file(FileProgress::doc(
+                    &collection_id,
+                    &job.doc_id,
+                    Stage::Extract,
+                ));
 
                 // Do the work
-                let result = {
-                    let storage = storage.read().await;
-                    storage
-                        .extract_and_dispatch(job.namespace_id, &job.doc_id)
-                        .await
+                let result = tokio::select! {
+                    result = async {
+                        let storage = storage.read().await;
+                        storage
+                            .extract_and_dispatch(job.namespace_id, &job.doc_id)
+                            .await
+                    } => result,
+                    _ = cancel.cancelled() => {
+                        tracing::info!(doc_id = %job.doc_id, "Extract cancelled");
+                        progress.report_file(
+                            FileProgress::doc(&collection_id, &job.doc_id, Stage::Extract)
+                                .failed("Cancelled"),
+                        );
+                        progress
+                            .apply(ProgressUpdate::Cancelled {
+                                collection_id,
+                                stage: Stage::Extract,
+                                was_active: true,
+                            })
+                            .await;
+                        continue;
+                    }
                 };
 
                 match result {
                     Ok(Some(metadata)) => {
+                        retries.succeeded(&job.doc_id);
                         tracing::debug!(
                             doc_id = %job.doc_id,
                             pages = metadata.page_count,
                             "Extracted text"
                         );
+                        progress.report_file(
+                            FileProgress::doc(&collection_id, &job.doc_id, Stage::Extract).done(),
+                        );
                         progress
                             .apply(ProgressUpdate::Completed {
                                 collection_id,
@@ -94,9 +165,34 @@ pub fn spawn_extract_workers(
                             doc_id = %job.doc_id,
                             "Document deleted before extract; skipping"
                         );
+                        control.finished(&job.doc_id);
                     }
                     Err(e) => {
+                        if retries.retry(ParkedJob::Extract(job.clone()), &e).is_some() {
+                            progress
+                                .apply(ProgressUpdate::Retrying {
+                                    collection_id,
+                                    stage: Stage::Extract,
+                                    was_failed: false,
+                                })
+                                .await;
+                            continue;
+                        }
                         tracing::error!(doc_id = %job.doc_id, error = %e, "Extract failed");
+                        control.finished(&job.doc_id);
+                        // Keep it from being extracted again on every start.
+                        if let Err(err) = storage
+                            .read()
+                            .await
+                            .mark_extract_failed(job.namespace_id, &job.doc_id, &e.to_string())
+                            .await
+                        {
+                            tracing::warn!(error = %err, "Failed to record extract failure");
+                        }
+                        progress.report_file(
+                            FileProgress::doc(&collection_id, &job.doc_id, Stage::Extract)
+                                .failed(e.to_string()),
+                        );
                         progress
                             .apply(ProgressUpdate::Failed {
                                 collection_id,
@@ -117,17 +213,33 @@ pub fn spawn_extract_workers(
 ///
 /// Workers generate embeddings and store them in iroh.
 /// The InsertLocal event for files/*/embeddings/* triggers the next stage.
+/// `chunking` is the global default; collections may override it.
+/// `cache` is shared across workers so repeated chunks embed once, and
+/// `inference` caps how many of them call the embedder at a time.
+/// Vectors are stored with `vector_encoding`.
+#[allow(clippy::too_many_arguments)]
 pub fn spawn_embed_workers(
     count: usize,
     rx: SharedReceiver<EmbedJob>,
     storage: Arc<RwLock<Storage>>,
     models: Arc<ModelManager>,
+    chunking: Arc<RwLock<ChunkingConfig>>,
+    cache: Arc<EmbeddingCache>,
+    inference: Arc<Semaphore>,
+    vector_encoding: VectorEncoding,
+    control: Arc<ImportControl>,
+    retries: Arc<Retries>,
     progress: ProgressTracker,
 ) {
     for i in 0..count {
         let rx = rx.clone();
         let storage = storage.clone();
         let models = models.clone();
+        let chunking = chunking.clone();
+        let cache = cache.clone();
+        let inference = inference.clone();
+        let control = control.clone();
+        let retries = retries.clone();
         let progress = progress.clone();
 
         let mut focus_guard = models.focus_guard();
@@ -141,6 +253,42 @@ pub fn spawn_embed_workers(
 
                 let collection_id = job.namespace_id.to_string();
 
+                let cancel = match control.admit(&collection_id, &job.doc_id) {
+                    Admission::Run(cancel) => cancel,
+                    Admission::Park => {
+                        control.park(ParkedJob::Embed(job));
+                        continue;
+                    }
+                    Admission::Drop => {
+                        progress
+                            .apply(ProgressUpdate::Cancelled {
+                                collection_id,
+                                stage: Stage::Embed,
+                                was_active: false,
+                            })
+                            .await;
+                        continue;
+                    }
+                };
+
+                // Output copied along with a linked document.
+                if control.skip_linked(&job.doc_id, Stage::Embed) {
+                    tracing::debug!(doc_id = %job.doc_id, "Linked document, skipping embed");
+                    for update in [
+                        ProgressUpdate::Started {
+                            collection_id: collection_id.clone(),
+                            stage: Stage::Embed,
+                        },
+                        ProgressUpdate::Completed {
+                            collection_id,
+                            stage: Stage::Embed,
+                        },
+                    ] {
+                        progress.apply(update).await;
+                    }
+                    continue;
+                }
+
                 // Mark as started
                 progress
                     .apply(ProgressUpdate::Started {
@@ -148,6 +296,7 @@ pub fn spawn_embed_workers(
                         stage: Stage::Embed,
                     })
                     .await;
+                progress.report_file(FileProgress::doc(&collection_id, &job.doc_id, Stage::Embed));
 
                 // Acquire embedding provider + current model id
                 let lease_result = models.acquire_embedding().await;
@@ -155,35 +304,40 @@ pub fn spawn_embed_workers(
 
                 let result = match (lease_result, model_id) {
                     (Ok(Some(emb)), Some(mid)) => {
-                        // Get document metadata
-                        let storage_guard = storage.read().await;
-                        let metadata_result = storage_guard
-                            .get_document(job.namespace_id, &job.doc_id)
-                            .await;
-
-                        match metadata_result {
-                            Ok(Some(metadata)) => {
-                                let emb_result = generate_embeddings_data(
-                                    &storage_guard,
-                                    &*emb,
-                                    &mid,
-                                    job.namespace_id,
-                                    &metadata,
-                                    &models,
-                                )
-                                .await;
-
-                                match emb_result {
-                                    Ok(data) => {
-                                        storage_guard
-                                            .store_embeddings(job.namespace_id, &job.doc_id, data)
-                                            .await
-                                    }
-                                    Err(e) => Err(e),
-                                }
+                        let default_chunking = chunking.read().await.clone();
+                        let ctx = EmbedContext {
+                            models: &models,
+                            progress: &progress,
+                            cache: &cache,
+                            inference: &inference,
+                            vector_encoding,
+                        };
+                        // Dropping the embed future between batches stops
+                        // it before anything is stored.
+                        tokio::select! {
+                            result = embed_document(
+                                &job,
+                                &*emb,
+                                &mid,
+                                &storage,
+                                &default_chunking,
+                                &ctx,
+                            ) => result,
+                            _ = cancel.cancelled() => {
+                                tracing::info!(doc_id = %job.doc_id, "Embed cancelled");
+                                progress.report_file(
+                                    FileProgress::doc(&collection_id, &job.doc_id, Stage::Embed)
+                                        .failed("Cancelled"),
+                                );
+                                progress
+                                    .apply(ProgressUpdate::Cancelled {
+                                        collection_id,
+                                        stage: Stage::Embed,
+                                        was_active: true,
+                                    })
+                                    .await;
+                                continue;
                             }
-                            Ok(None) => Err(anyhow::anyhow!("Document not found: {}", job.doc_id)),
-                            Err(e) => Err(e),
                         }
                     }
                     _ => Err(anyhow::anyhow!("Embedder not configured")),
@@ -191,7 +345,16 @@ pub fn spawn_embed_workers(
 
                 match result {
                     Ok(_) => {
+                        retries.succeeded(&job.doc_id);
                         tracing::debug!(doc_id = %job.doc_id, "Generated embeddings");
+                        progress.report_file(
+                            FileProgress::doc(&collection_id, &job.doc_id, Stage::Embed).done(),
+                        );
+                        progress.report_if_synced(
+                            &collection_id,
+                            &job.doc_id,
+                            SyncStatus::EmbeddingRegenerated,
+                        );
                         progress
                             .apply(ProgressUpdate::Completed {
                                 collection_id,
@@ -201,7 +364,22 @@ pub fn spawn_embed_workers(
                         // InsertLocal(files/*/embeddings/*) will trigger index via watcher
                     }
                     Err(e) => {
+                        if retries.retry(ParkedJob::Embed(job.clone()), &e).is_some() {
+                            progress
+                                .apply(ProgressUpdate::Retrying {
+                                    collection_id,
+                                    stage: Stage::Embed,
+                                    was_failed: false,
+                                })
+                                .await;
+                            continue;
+                        }
                         tracing::error!(doc_id = %job.doc_id, error = %e, "Embed failed");
+                        control.finished(&job.doc_id);
+                        progress.report_file(
+                            FileProgress::doc(&collection_id, &job.doc_id, Stage::Embed)
+                                .failed(e.to_string()),
+                        );
                         progress
                             .apply(ProgressUpdate::Failed {
                                 collection_id,
@@ -218,6 +396,81 @@ pub fn spawn_embed_workers(
     }
 }
 
+/// Embed one document and store the result.
+///
+/// Inputs are read under the storage lock, which is released while the
+/// embedder runs: a long document would otherwise hold a read guard for
+/// minutes and stall any writer (and, behind it, every other worker).
+pub(super) async fn embed_document(
+    job: &EmbedJob,
+    emb: &dyn EmbeddingProvider,
+    model_id: &str,
+    storage: &RwLock<Storage>,
+    default_chunking: &ChunkingConfig,
+    ctx: &EmbedContext<'_>,
+) -> anyhow::Result<()> {
+    let (metadata, text, chunking, projection) = {
+        let storage = storage.read().await;
+        let metadata = storage
+            .get_document(job.namespace_id, &job.doc_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Document not found: {}", job.doc_id))?;
+        let text = load_document_text(&storage, job.namespace_id, &job.doc_id).await?;
+        let chunking = resolve_chunking(&storage, job.namespace_id, default_chunking).await;
+        let projection = storage.get_collection_projection(job.namespace_id).await?;
+        (metadata, text, chunking, projection)
+    };
+
+    if let Some(simhash) = fingerprint::simhash(&text) {
+        if metadata.simhash != Some(simhash) {
+            record_fingerprint(storage, job, simhash).await;
+        }
+    }
+
+    let mut data = generate_embeddings_data(
+        emb,
+        model_id,
+        job.namespace_id,
+        &metadata,
+        &text,
+        &chunking,
+        ctx,
+    )
+    .await?;
+    if let Some(projection) = &projection {
+        apply_projection(&mut data, projection)?;
+    }
+
+    storage
+        .read()
+        .await
+        .store_embeddings(job.namespace_id, &job.doc_id, data)
+        .await
+}
+
+/// Record a document's text fingerprint and link its near-duplicates.
+/// Best effort: a failure here shouldn't fail the embed.
+async fn record_fingerprint(storage: &RwLock<Storage>, job: &EmbedJob, simhash: u64) {
+    match storage
+        .read()
+        .await
+        .record_fingerprint(job.namespace_id, &job.doc_id, simhash)
+        .await
+    {
+        Ok(matches) if !matches.is_empty() => {
+            tracing::info!(
+                doc_id = %job.doc_id,
+                count = matches.len(),
+                "Near-duplicate documents found"
+            );
+        }
+        Ok(_) => {}
+        Err(e) => {
+            tracing::warn!(doc_id = %job.doc_id, error = %e, "Failed to record fingerprint");
+        }
+    }
+}
+
 /// Spawn index worker.
 ///
 /// Single worker that indexes embeddings into milli for search.
@@ -225,14 +478,40 @@ pub fn spawn_index_worker(
     rx: SharedReceiver<IndexJob>,
     storage: Arc<RwLock<Storage>>,
     index_worker: IndexWorkerHandle,
+    control: Arc<ImportControl>,
+    retries: Arc<Retries>,
     progress: ProgressTracker,
 ) {
     tokio::spawn(async move {
         tracing::debug!("Index worker started");
 
+        // Projected vector spaces registered with the index this run.
+        let mut configured_embedders = HashSet::new();
+
         while let Some(job) = rx.recv().await {
             let collection_id = job.namespace_id.to_string();
 
+            // Indexing is one short write to milli, so a cancel only stops
+            // jobs that haven't started; chunks of a cancelled document are
+            // removed along with it.
+            match control.admit(&collection_id, &job.doc_id) {
+                Admission::Run(_) => {}
+                Admission::Park => {
+                    control.park(ParkedJob::Index(job));
+                    continue;
+                }
+                Admission::Drop => {
+                    progress
+                        .apply(ProgressUpdate::Cancelled {
+                            collection_id,
+                            stage: Stage::Index,
+                            was_active: false,
+                        })
+                        .await;
+                    continue;
+                }
+            }
+
             // Mark as started
             progress
                 .apply(ProgressUpdate::Started {
@@ -240,6 +519,7 @@ pub fn spawn_index_worker(
                     stage: Stage::Index,
                 })
                 .await;
+            progress.report_file(FileProgress::doc(&collection_id, &job.doc_id, Stage::Index));
 
             // Get embeddings from storage
             let storage_guard = storage.read().await;
@@ -262,6 +542,26 @@ pub fn spawn_index_worker(
                         tracing::warn!(doc_id = %job.doc_id, error = %e, "Failed to delete old chunks");
                     }
 
+                    let embedder = embedding_data
+                        .projection
+                        .as_ref()
+                        .map(|spec| (spec.embedder_name(), spec.target_dims));
+                    if let Some((name, dimensions)) = &embedder {
+                        if !configured_embedders.contains(name) {
+                            match index_worker
+                                .configure_embedder(name.clone(), *dimensions)
+                                .await
+                            {
+                                Ok(()) => {
+                                    configured_embedders.insert(name.clone());
+                                }
+                                Err(e) => {
+                                    tracing::warn!(embedder = %name, error = %e, "Failed to configure projected embedder")
+                                }
+                            }
+                        }
+                    }
+
                     // Build chunks for indexing
                     let chunks: Vec<ChunkToIndex> = embedding_data
                         .chunks
@@ -279,6 +579,7 @@ pub fn spawn_index_worker(
                                 start_page: chunk.start_page,
                                 end_page: chunk.end_page,
                                 vector: Some(chunk.vector.clone()),
+                                embedder: embedder.as_ref().map(|(name, _)| name.clone()),
                             }
                         })
                         .collect();
@@ -296,7 +597,13 @@ pub fn spawn_index_worker(
 
             match result {
                 Ok(_) => {
+                    retries.succeeded(&job.doc_id);
                     tracing::info!(doc_id = %job.doc_id, "Document indexed");
+                    control.finished(&job.doc_id);
+                    progress.report_file(
+                        FileProgress::doc(&collection_id, &job.doc_id, Stage::Index).done(),
+                    );
+                    progress.report_if_synced(&collection_id, &job.doc_id, SyncStatus::Indexed);
                     progress
                         .apply(ProgressUpdate::Completed {
                             collection_id,
@@ -305,7 +612,22 @@ pub fn spawn_index_worker(
                         .await;
                 }
                 Err(e) => {
+                    if retries.retry(ParkedJob::Index(job.clone()), &e).is_some() {
+                        progress
+                            .apply(ProgressUpdate::Retrying {
+                                collection_id,
+                                stage: Stage::Index,
+                                was_failed: false,
+                            })
+                            .await;
+                        continue;
+                    }
                     tracing::error!(doc_id = %job.doc_id, error = %e, "Index failed");
+                    control.finished(&job.doc_id);
+                    progress.report_file(
+                        FileProgress::doc(&collection_id, &job.doc_id, Stage::Index)
+                            .failed(e.to_string()),
+                    );
                     progress
                         .apply(ProgressUpdate::Failed {
                             collection_id,
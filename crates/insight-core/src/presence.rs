@@ -0,0 +1,331 @@
+//! Who is online in each collection.
+//!
+//! Every node announces itself on a gossip topic per collection: its node
+//! ID, its display name and whether it is online. It does so when joining,
+//! when a new neighbor comes up, when its name changes and every
+//! [`HEARTBEAT_INTERVAL`]; leaving, it announces it went offline. A peer
+//! not heard from in [`OFFLINE_AFTER`] counts as offline too, for nodes
+//! that quit without saying so.
+//!
+//! The topic is derived from the namespace ID rather than being it, as the
+//! docs engine already uses that one to announce new entries. Peers are
+//! found through those the collection was synced with. Announcements
+//! aren't signed, so a member could pass itself off as another; presence
+//! is a hint of who is reachable, not something to trust.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use iroh::EndpointId;
+use iroh_docs::NamespaceId;
+use iroh_gossip::api::{Event, GossipReceiver, GossipSender};
+use iroh_gossip::net::Gossip;
+use iroh_gossip::proto::TopicId;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// How often a node announces it is still online.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a peer stays online without announcing itself.
+pub const OFFLINE_AFTER: Duration = Duration::from_secs(90);
+
+/// Mixed into the namespace ID to get a collection's presence topic.
+const TOPIC_PREFIX: &[u8] = b"insight/presence/";
+
+/// What a node broadcasts about itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Announcement {
+    node_id: String,
+    name: Option<String>,
+    online: bool,
+}
+
+/// A peer seen in a collection's presence topic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerPresence {
+    /// The peer's node ID.
+    pub node_id: String,
+    /// Display name from the peer's settings.
+    pub name: Option<String>,
+    /// Whether the peer is online and reachable for sync.
+    pub online: bool,
+    /// When the peer last announced itself, RFC 3339.
+    pub last_seen: String,
+}
+
+/// Last announcement from each peer, by collection.
+#[derive(Default)]
+struct Roster {
+    peers: HashMap<(NamespaceId, String), (Announcement, DateTime<Utc>)>,
+}
+
+impl Roster {
+    fn record(&mut self, namespace_id: NamespaceId, announcement: Announcement, at: DateTime<Utc>) {
+        self.peers.insert(
+            (namespace_id, announcement.node_id.clone()),
+            (announcement, at),
+        );
+    }
+
+    /// Peers of a collection as of `now`, online first, then most
+    /// recently seen.
+    fn collection(&self, namespace_id: NamespaceId, now: DateTime<Utc>) -> Vec<PeerPresence> {
+        let offline_after = chrono::Duration::from_std(OFFLINE_AFTER).unwrap_or_default();
+        let mut peers: Vec<(PeerPresence, DateTime<Utc>)> = self
+            .peers
+            .iter()
+            .filter(|((id, _), _)| *id == namespace_id)
+            .map(|(_, (announcement, at))| {
+                let presence = PeerPresence {
+                    node_id: announcement.node_id.clone(),
+                    name: announcement.name.clone(),
+                    online: announcement.online && now - *at < offline_after,
+                    last_seen: at.to_rfc3339(),
+                };
+                (presence, *at)
+            })
+            .collect();
+        peers.sort_by(|(a, a_at), (b, b_at)| b.online.cmp(&a.online).then(b_at.cmp(a_at)));
+        peers.into_iter().map(|(presence, _)| presence).collect()
+    }
+
+    fn forget(&mut self, namespace_id: NamespaceId) {
+        self.peers.retain(|(id, _), _| *id != namespace_id);
+    }
+}
+
+/// A collection's presence topic being announced in.
+struct Topic {
+    cancel: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+/// Presence in every collection joined. Clones share state.
+#[derive(Clone)]
+pub struct Presence {
+    gossip: Gossip,
+    node_id: EndpointId,
+    name: Arc<watch::Sender<Option<String>>>,
+    topics: Arc<Mutex<HashMap<NamespaceId, Topic>>>,
+    roster: Arc<Mutex<Roster>>,
+}
+
+impl Presence {
+    /// Presence of this node, known to peers as `name`.
+    pub fn new(gossip: Gossip, node_id: EndpointId, name: Option<String>) -> Self {
+        Self {
+            gossip,
+            node_id,
+            name: Arc::new(watch::Sender::new(name)),
+            topics: Arc::new(Mutex::new(HashMap::new())),
+            roster: Arc::new(Mutex::new(Roster::default())),
+        }
+    }
+
+    /// Start announcing this node in a collection, meeting the other
+    /// members through `peers`. Nothing changes if already joined.
+    pub async fn join(&self, namespace_id: NamespaceId, peers: Vec<EndpointId>) -> Result<()> {
+        if self.topics.lock().unwrap().contains_key(&namespace_id) {
+            return Ok(());
+        }
+
+        let (sender, receiver) = self
+            .gossip
+            .subscribe(topic_id(namespace_id), peers)
+            .await?
+            .split();
+        let cancel = CancellationToken::new();
+        let task = tokio::spawn(run_topic(
+            namespace_id,
+            self.node_id,
+            sender,
+            receiver,
+            self.name.subscribe(),
+            self.roster.clone(),
+            cancel.clone(),
+        ));
+
+        // A concurrent join may have won; keep its topic, and don't let
+        // this one announce going offline.
+        let mut topics = self.topics.lock().unwrap();
+        if topics.contains_key(&namespace_id) {
+            task.abort();
+        } else {
+            topics.insert(namespace_id, Topic { cancel, task });
+        }
+        Ok(())
+    }
+
+    /// Announce this node went offline in a collection and stop
+    /// following who is online there.
+    pub fn leave(&self, namespace_id: &NamespaceId) {
+        if let Some(topic) = self.topics.lock().unwrap().remove(namespace_id) {
+            topic.cancel.cancel();
+        }
+        self.roster.lock().unwrap().forget(*namespace_id);
+    }
+
+    /// Leave every collection, waiting for the announcements to go out.
+    pub async fn shutdown(&self) {
+        let topics: Vec<Topic> = self
+            .topics
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, t)| t)
+            .collect();
+        for topic in topics {
+            topic.cancel.cancel();
+            let _ = topic.task.await;
+        }
+    }
+
+    /// Change the name this node is announced under.
+    pub fn set_name(&self, name: Option<String>) {
+        self.name.send_replace(name);
+    }
+
+    /// Peers seen in a collection, online first.
+    pub fn collection(&self, namespace_id: &NamespaceId) -> Vec<PeerPresence> {
+        self.roster
+            .lock()
+            .unwrap()
+            .collection(*namespace_id, Utc::now())
+    }
+}
+
+/// The presence topic of a collection.
+fn topic_id(namespace_id: NamespaceId) -> TopicId {
+    let hash = blake3::hash(&[TOPIC_PREFIX, namespace_id.as_bytes()].concat());
+    TopicId::from_bytes(*hash.as_bytes())
+}
+
+/// Announce this node in a topic and record what peers announce, until
+/// cancelled.
+async fn run_topic(
+    namespace_id: NamespaceId,
+    node_id: EndpointId,
+    sender: GossipSender,
+    mut receiver: GossipReceiver,
+    mut name: watch::Receiver<Option<String>>,
+    roster: Arc<Mutex<Roster>>,
+    cancel: CancellationToken,
+) {
+    // Reads the name here so its borrow isn't held across the broadcast.
+    let announce = |online: bool, name: &watch::Receiver<Option<String>>| Announcement {
+        node_id: node_id.to_string(),
+        name: name.borrow().clone(),
+        online,
+    };
+
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => {
+                broadcast(&sender, namespace_id, announce(false, &name)).await;
+                break;
+            }
+            _ = heartbeat.tick() => {
+                broadcast(&sender, namespace_id, announce(true, &name)).await;
+            }
+            Ok(()) = name.changed() => {
+                broadcast(&sender, namespace_id, announce(true, &name)).await;
+            }
+            event = receiver.next() => match event {
+                Some(Ok(Event::Received(message))) => {
+                    match serde_json::from_slice::<Announcement>(&message.content) {
+                        Ok(announcement) if announcement.node_id != node_id.to_string() => {
+                            roster.lock().unwrap().record(namespace_id, announcement, Utc::now());
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            tracing::debug!(
+                                namespace = %namespace_id,
+                                error = %e,
+                                "Unreadable presence announcement"
+                            );
+                        }
+                    }
+                }
+                // Let the newcomer know who is here without waiting for
+                // the next heartbeat.
+                Some(Ok(Event::NeighborUp(_))) => {
+                    broadcast(&sender, namespace_id, announce(true, &name)).await;
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    tracing::warn!(namespace = %namespace_id, error = %e, "Presence topic error");
+                }
+                None => break,
+            },
+        }
+    }
+    tracing::debug!(namespace = %namespace_id, "Left presence topic");
+}
+
+async fn broadcast(sender: &GossipSender, namespace_id: NamespaceId, announcement: Announcement) {
+    let bytes = match serde_json::to_vec(&announcement) {
+        Ok(bytes) => bytes,
+        Err(_) => return,
+    };
+    if let Err(e) = sender.broadcast(bytes.into()).await {
+        tracing::debug!(namespace = %namespace_id, error = %e, "Failed to announce presence");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roster_marks_quiet_and_departed_peers_offline() {
+        let collection = NamespaceId::from([1u8; 32]);
+        let other = NamespaceId::from([2u8; 32]);
+        let now = Utc::now();
+        let announcement = |node_id: &str, online: bool| Announcement {
+            node_id: node_id.to_string(),
+            name: Some(node_id.to_uppercase()),
+            online,
+        };
+
+        let mut roster = Roster::default();
+        roster.record(
+            collection,
+            announcement("quiet", true),
+            now - chrono::Duration::minutes(5),
+        );
+        roster.record(collection, announcement("alice", true), now);
+        roster.record(collection, announcement("bob", true), now);
+        roster.record(collection, announcement("bob", false), now);
+        roster.record(other, announcement("carol", true), now);
+
+        let peers = roster.collection(collection, now);
+        let summary: Vec<_> = peers
+            .iter()
+            .map(|p| (p.node_id.as_str(), p.online))
+            .collect();
+        assert_eq!(summary[0], ("alice", true));
+        assert_eq!(summary.len(), 3);
+        assert!(summary.contains(&("bob", false)));
+        assert!(summary.contains(&("quiet", false)));
+        assert_eq!(peers[0].name.as_deref(), Some("ALICE"));
+
+        roster.forget(collection);
+        assert!(roster.collection(collection, now).is_empty());
+        assert_eq!(roster.collection(other, now).len(), 1);
+    }
+
+    #[test]
+    fn test_topic_differs_from_namespace() {
+        let namespace_id = NamespaceId::from([7u8; 32]);
+        assert_ne!(topic_id(namespace_id).as_bytes(), namespace_id.as_bytes());
+        assert_eq!(topic_id(namespace_id), topic_id(namespace_id));
+    }
+}
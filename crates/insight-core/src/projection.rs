@@ -0,0 +1,204 @@
+//! Random projection of embedding vectors to fewer dimensions.
+//!
+//! A collection may opt into projecting vectors from the model's native
+//! size (e.g. 1024) down to something smaller (e.g. 256) before they are
+//! stored and indexed. Random projections approximately preserve cosine
+//! similarity (Johnson–Lindenstrauss), need no training data, and shrink
+//! both the synced `embeddings` entries and the index's vector store.
+//!
+//! The matrix is a sparse Achlioptas projection: each entry is `+√3`, `0`
+//! or `−√3` with probability 1/6, 2/3, 1/6, scaled by `1/√target_dims`. It
+//! is generated from a seed derived from the model and sizes, so every
+//! collection projecting the same model to the same size shares one vector
+//! space — and one index embedder. The matrix is still persisted per
+//! collection (`_projection`) so peers project with exactly the stored
+//! matrix rather than trusting their own generator.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Identifies a projection. Stored in `CollectionMetadata`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ProjectionSpec {
+    /// Embedding model whose vectors this projects.
+    pub model_id: String,
+    pub source_dims: usize,
+    pub target_dims: usize,
+    pub seed: u64,
+}
+
+impl ProjectionSpec {
+    pub fn new(model_id: &str, source_dims: usize, target_dims: usize) -> Result<Self> {
+        if target_dims == 0 || target_dims >= source_dims {
+            anyhow::bail!(
+                "Target dimensions must be between 1 and {}, got {}",
+                source_dims.saturating_sub(1),
+                target_dims
+            );
+        }
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(model_id.as_bytes());
+        hasher.update(&(source_dims as u64).to_le_bytes());
+        hasher.update(&(target_dims as u64).to_le_bytes());
+        let digest = hasher.finalize();
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&digest.as_bytes()[..8]);
+
+        Ok(Self {
+            model_id: model_id.to_string(),
+            source_dims,
+            target_dims,
+            seed: u64::from_le_bytes(seed),
+        })
+    }
+
+    /// Name of the index embedder holding vectors in this space.
+    pub fn embedder_name(&self) -> String {
+        format!("projected-{}-{:016x}", self.target_dims, self.seed)
+    }
+}
+
+/// A projection matrix, row-major `target_dims × source_dims`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projection {
+    pub spec: ProjectionSpec,
+    matrix: Vec<f32>,
+}
+
+impl Projection {
+    /// Generate the matrix for `spec`.
+    pub fn generate(spec: ProjectionSpec) -> Self {
+        let scale = (3.0 / spec.target_dims as f32).sqrt();
+        let mut rng = SplitMix64(spec.seed);
+        let matrix = (0..spec.target_dims * spec.source_dims)
+            .map(|_| match rng.next() % 6 {
+                0 => scale,
+                1 => -scale,
+                _ => 0.0,
+            })
+            .collect();
+        Self { spec, matrix }
+    }
+
+    /// Rebuild from bytes written by [`Projection::to_bytes`].
+    pub fn from_bytes(spec: ProjectionSpec, bytes: &[u8]) -> Result<Self> {
+        let expected = spec.target_dims * spec.source_dims * 4;
+        if bytes.len() != expected {
+            anyhow::bail!(
+                "Projection matrix is {} bytes, expected {}",
+                bytes.len(),
+                expected
+            );
+        }
+        let matrix = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Ok(Self { spec, matrix })
+    }
+
+    /// Little-endian `f32`s, row-major.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.matrix.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// Project and L2-normalize a vector. Vectors of the wrong size are
+    /// rejected rather than silently truncated.
+    pub fn apply(&self, vector: &[f32]) -> Result<Vec<f32>> {
+        if vector.len() != self.spec.source_dims {
+            anyhow::bail!(
+                "Cannot project a {}-dimensional vector with a {}→{} projection",
+                vector.len(),
+                self.spec.source_dims,
+                self.spec.target_dims
+            );
+        }
+
+        let mut out: Vec<f32> = self
+            .matrix
+            .chunks_exact(self.spec.source_dims)
+            .map(|row| row.iter().zip(vector).map(|(a, b)| a * b).sum())
+            .collect();
+        let norm = out.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            out.iter_mut().for_each(|x| *x /= norm);
+        }
+        Ok(out)
+    }
+}
+
+/// Small, fixed PRNG so the matrix for a seed never changes with a
+/// dependency bump.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+        let na = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+        let nb = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+        dot / (na * nb)
+    }
+
+    #[test]
+    fn test_spec_is_deterministic() {
+        let a = ProjectionSpec::new("qwen3-embedding", 1024, 256).unwrap();
+        let b = ProjectionSpec::new("qwen3-embedding", 1024, 256).unwrap();
+        let c = ProjectionSpec::new("other-model", 1024, 256).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a.seed, c.seed);
+        assert_eq!(a.embedder_name(), b.embedder_name());
+        assert_eq!(Projection::generate(a), Projection::generate(b));
+    }
+
+    #[test]
+    fn test_spec_rejects_bad_sizes() {
+        assert!(ProjectionSpec::new("m", 768, 0).is_err());
+        assert!(ProjectionSpec::new("m", 768, 768).is_err());
+        assert!(ProjectionSpec::new("m", 768, 1024).is_err());
+    }
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let projection = Projection::generate(ProjectionSpec::new("m", 64, 16).unwrap());
+        let bytes = projection.to_bytes();
+        assert_eq!(bytes.len(), 64 * 16 * 4);
+        let restored = Projection::from_bytes(projection.spec.clone(), &bytes).unwrap();
+        assert_eq!(restored, projection);
+        assert!(Projection::from_bytes(projection.spec.clone(), &bytes[4..]).is_err());
+    }
+
+    #[test]
+    fn test_apply_roughly_preserves_similarity() {
+        let projection = Projection::generate(ProjectionSpec::new("m", 512, 128).unwrap());
+        let a: Vec<f32> = (0..512).map(|i| ((i * 7) % 13) as f32 - 6.0).collect();
+        let near: Vec<f32> = a
+            .iter()
+            .enumerate()
+            .map(|(i, v)| v + (i % 3) as f32 * 0.1)
+            .collect();
+        let far: Vec<f32> = (0..512).map(|i| ((i * 11) % 17) as f32 - 8.0).collect();
+
+        let pa = projection.apply(&a).unwrap();
+        assert_eq!(pa.len(), 128);
+        let pn = projection.apply(&near).unwrap();
+        let pf = projection.apply(&far).unwrap();
+
+        assert!((cosine(&a, &near) - cosine(&pa, &pn)).abs() < 0.1);
+        assert!(cosine(&pa, &pn) > cosine(&pa, &pf));
+        assert!(projection.apply(&a[..100]).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Named prompt templates shared by tools and commands.
+//!
+//! Every instruction Insight sends to a model outside the chat itself
+//! (summarizing, comparing, answering batch questions) is a template with
+//! `{{name}}` placeholders. A newsroom can replace any of them without
+//! rebuilding the app: a file `<template name>.txt` in the prompts
+//! directory overrides the built-in text. Overrides are read each time a
+//! library is loaded, so an edit applies to the next request.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use tracing::warn;
+
+/// Built-in templates: name, what it's for, text.
+const BUILTIN: &[(&str, &str, &str)] = &[
+    (
+        "summarize-section",
+        "Summarize one section of a long document.",
+        "Summarize {{section}} of \"{{title}}\". Keep the names, dates, amounts and other \
+         specifics, and say which page each comes from.{{focus}} Reply with the summary \
+         only.\n\n{{text}}",
+    ),
+    (
+        "summarize-merge",
+        "Combine partial summaries when they don't fit in one request.",
+        "Combine these partial summaries of \"{{title}}\" into one, keeping the page \
+         references.{{focus}} Reply with the summary only.\n\n{{summaries}}",
+    ),
+    (
+        "summarize",
+        "Write the final summary of a document from its section summaries.",
+        "These are summaries of consecutive sections of \"{{title}}\". Write one summary \
+         of the whole document: the main points first, then key names, dates and figures \
+         with their pages.{{focus}} Reply with the summary only.\n\n{{summaries}}",
+    ),
+    (
+        "compare",
+        "Describe what changed between two versions of a document.",
+        "Below are the passages that differ between \"{{first}}\" and \"{{second}}\". \
+         Describe the changes that matter: obligations, amounts, dates, parties and \
+         deadlines first, wording changes last. Give the pages for each change and reply \
+         with the description only.\n\n{{changes}}",
+    ),
+    (
+        "batch-answer",
+        "Answer a question from one document's excerpts, for batch questions.",
+        "Answer the question using only these excerpts from \"{{title}}\". If they don't \
+         answer it, set found to false. Keep the answer to one or two sentences and list \
+         the pages it comes from.\n\nQuestion: {{question}}\n\n{{excerpts}}",
+    ),
+    (
+        "continue-answer",
+        "Ask the model to go on with an answer the output limit cut off.",
+        "Your last reply was cut off by the output limit. Continue exactly where it \
+         stopped, without repeating anything or starting over.",
+    ),
+    (
+        "predict-next-message",
+        "Suggest the user's next message for tab completion.",
+        "Based on the conversation above, predict what the user is most likely to ask or \
+         say next.\n\nRules:\n- Output ONLY the predicted message, nothing else\n- Keep it \
+         concise (1-2 sentences max)\n- Make it a natural follow-up question or \
+         statement\n- If the assistant just answered a question, predict a likely \
+         follow-up\n- If unsure, output nothing",
+    ),
+];
+
+/// Where a template's text comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptSource {
+    Builtin,
+    User,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub description: String,
+    pub text: String,
+    pub source: PromptSource,
+}
+
+impl PromptTemplate {
+    /// Placeholder names in the order they first appear.
+    pub fn params(&self) -> Vec<&str> {
+        let mut params = Vec::new();
+        let mut rest = self.text.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + 2 + len].trim();
+            if !params.contains(&name) {
+                params.push(name);
+            }
+            rest = &rest[start + 2 + len + 2..];
+        }
+        params
+    }
+
+    /// Fill the placeholders. Every placeholder needs a value; values
+    /// without a placeholder are ignored, so an override may drop one.
+    pub fn render(&self, values: &[(&str, &str)]) -> Result<String> {
+        let missing: Vec<&str> = self
+            .params()
+            .into_iter()
+            .filter(|p| !values.iter().any(|(name, _)| name == p))
+            .collect();
+        if !missing.is_empty() {
+            bail!(
+                "Prompt template '{}' has no value for {}",
+                self.name,
+                missing.join(", ")
+            );
+        }
+
+        let mut out = String::with_capacity(self.text.len());
+        let mut rest = self.text.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + 2 + len].trim();
+            out.push_str(&rest[..start]);
+            if let Some((_, value)) = values.iter().find(|(n, _)| *n == name) {
+                out.push_str(value);
+            }
+            rest = &rest[start + 2 + len + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+/// The built-in templates with the user's overrides applied.
+#[derive(Debug, Clone)]
+pub struct PromptLibrary {
+    templates: BTreeMap<String, PromptTemplate>,
+}
+
+impl PromptLibrary {
+    pub fn builtin() -> Self {
+        let templates = BUILTIN
+            .iter()
+            .map(|&(name, description, text)| {
+                let template = PromptTemplate {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    text: text.to_string(),
+                    source: PromptSource::Builtin,
+                };
+                (name.to_string(), template)
+            })
+            .collect();
+        Self { templates }
+    }
+
+    /// Built-ins overridden by `<name>.txt` files in `dir`. Files that
+    /// don't name a built-in template are ignored; a missing directory
+    /// means no overrides.
+    pub fn load(dir: &Path) -> Self {
+        let mut library = Self::builtin();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return library,
+            Err(e) => {
+                warn!(error = %e, "Failed to read prompts directory");
+                return library;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.extension().is_some_and(|ext| ext == "txt") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Some(template) = library.templates.get_mut(name) else {
+                warn!(path = %path.display(), "Ignoring prompt file for unknown template");
+                continue;
+            };
+            match std::fs::read_to_string(&path) {
+                Ok(text) if !text.trim().is_empty() => {
+                    template.text = text.trim_end().to_string();
+                    template.source = PromptSource::User;
+                }
+                Ok(_) => {}
+                Err(e) => warn!(path = %path.display(), error = %e, "Failed to read prompt file"),
+            }
+        }
+        library
+    }
+
+    pub fn templates(&self) -> impl Iterator<Item = &PromptTemplate> {
+        self.templates.values()
+    }
+
+    /// Render the template called `name`.
+    pub fn render(&self, name: &str, values: &[(&str, &str)]) -> Result<String> {
+        match self.templates.get(name) {
+            Some(template) => template.render(values),
+            None => bail!("No prompt template named '{}'", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_fills_placeholders() {
+        let library = PromptLibrary::builtin();
+        let prompt = library
+            .render(
+                "summarize-merge",
+                &[
+                    ("title", "Annual Report"),
+                    ("focus", ""),
+                    ("summaries", "[page 1]\nRevenue rose."),
+                ],
+            )
+            .unwrap();
+        assert!(prompt.starts_with("Combine these partial summaries of \"Annual Report\""));
+        assert!(prompt.ends_with("[page 1]\nRevenue rose."));
+
+        let err = library
+            .render("summarize-merge", &[("title", "Annual Report")])
+            .unwrap_err();
+        assert!(err.to_string().contains("focus, summaries"));
+    }
+
+    #[test]
+    fn test_user_file_overrides_builtin() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("summarize.txt"),
+            "Summarize {{title}} for the night desk in three bullets.\n\n{{summaries}}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("unknown.txt"), "Ignored").unwrap();
+
+        let library = PromptLibrary::load(dir.path());
+        let summarize = library.templates().find(|t| t.name == "summarize").unwrap();
+        assert_eq!(summarize.source, PromptSource::User);
+        // The override dropped {{focus}}; passing it anyway is fine.
+        let prompt = library
+            .render(
+                "summarize",
+                &[("title", "Memo"), ("focus", ""), ("summaries", "S")],
+            )
+            .unwrap();
+        assert_eq!(
+            prompt,
+            "Summarize Memo for the night desk in three bullets.\n\nS"
+        );
+        assert!(library.templates().all(|t| t.name != "unknown"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Completion cache for helper calls.
+//!
+//! Section summaries of a document that hasn't changed and suggestions for
+//! a conversation that hasn't moved on are asked again with the exact same
+//! prompt. Replies are cached by `blake3(provider, model, sampling,
+//! messages)` so an identical request is answered without waiting on (or
+//! paying for) the model again. Agent turns are never cached. The cache is
+//! in-memory and bounded; the oldest entries are evicted first.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use super::{Provider, SamplingParams};
+use crate::agent::Message;
+
+/// Default number of cached replies.
+pub const DEFAULT_CACHE_ENTRIES: usize = 2_000;
+
+/// Hit/miss counters for the completion cache.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CompletionCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+struct CacheInner {
+    replies: HashMap<blake3::Hash, String>,
+    /// Insertion order, oldest first.
+    order: VecDeque<blake3::Hash>,
+}
+
+/// Bounded map from request hash to reply text.
+pub struct CompletionCache {
+    inner: Mutex<CacheInner>,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CompletionCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(CacheInner {
+                replies: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+            capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn key(
+        provider: &dyn Provider,
+        messages: &[Message],
+        sampling: &SamplingParams,
+    ) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new();
+        for part in [provider.provider_name(), provider.model_id()] {
+            hasher.update(part.as_bytes());
+            hasher.update(&[0]);
+        }
+        // Both serialize infallibly: plain data with string keys.
+        hasher.update(&serde_json::to_vec(sampling).unwrap_or_default());
+        hasher.update(&[0]);
+        hasher.update(&serde_json::to_vec(messages).unwrap_or_default());
+        hasher.finalize()
+    }
+
+    /// The cached reply to this request, counting the hit or miss.
+    pub fn get(
+        &self,
+        provider: &dyn Provider,
+        messages: &[Message],
+        sampling: &SamplingParams,
+    ) -> Option<String> {
+        let key = Self::key(provider, messages, sampling);
+        let found = self.inner.lock().unwrap().replies.get(&key).cloned();
+        let counter = if found.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    /// Store the reply to a request, evicting the oldest entries when full.
+    pub fn insert(
+        &self,
+        provider: &dyn Provider,
+        messages: &[Message],
+        sampling: &SamplingParams,
+        reply: String,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = Self::key(provider, messages, sampling);
+        let mut inner = self.inner.lock().unwrap();
+        if inner.replies.insert(key, reply).is_some() {
+            return;
+        }
+        inner.order.push_back(key);
+        while inner.order.len() > self.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.replies.remove(&oldest);
+            }
+        }
+    }
+
+    /// Drop all cached replies. Counters are kept.
+    pub fn clear(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.replies.clear();
+        inner.order.clear();
+    }
+
+    pub fn stats(&self) -> CompletionCacheStats {
+        CompletionCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.inner.lock().unwrap().replies.len(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl Default for CompletionCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_ENTRIES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{ContentBlock, MessageRole};
+
+    struct Named(&'static str);
+
+    impl Provider for Named {
+        fn provider_name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn model_id(&self) -> &str {
+            self.0
+        }
+    }
+
+    fn ask(text: &str) -> Vec<Message> {
+        vec![Message {
+            role: MessageRole::User,
+            content: vec![ContentBlock::Text {
+                text: text.to_string(),
+            }],
+        }]
+    }
+
+    #[test]
+    fn test_hit_only_for_identical_requests() {
+        let cache = CompletionCache::new(4);
+        let sampling = SamplingParams::default();
+        cache.insert(&Named("a"), &ask("summarize"), &sampling, "S".to_string());
+
+        assert_eq!(
+            cache
+                .get(&Named("a"), &ask("summarize"), &sampling)
+                .as_deref(),
+            Some("S")
+        );
+        assert_eq!(cache.get(&Named("b"), &ask("summarize"), &sampling), None);
+        assert_eq!(cache.get(&Named("a"), &ask("summarise"), &sampling), None);
+        let warmer = SamplingParams {
+            temperature: Some(1.0),
+            ..SamplingParams::default()
+        };
+        assert_eq!(cache.get(&Named("a"), &ask("summarize"), &warmer), None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 3, 1));
+    }
+
+    #[test]
+    fn test_evicts_oldest_when_full() {
+        let cache = CompletionCache::new(2);
+        let sampling = SamplingParams::default();
+        for text in ["one", "two", "three"] {
+            cache.insert(&Named("m"), &ask(text), &sampling, text.to_string());
+        }
+        assert_eq!(cache.get(&Named("m"), &ask("one"), &sampling), None);
+        assert_eq!(
+            cache.get(&Named("m"), &ask("three"), &sampling).as_deref(),
+            Some("three")
+        );
+        assert_eq!(cache.stats().entries, 2);
+    }
+}
@@ -9,9 +9,9 @@ use async_trait::async_trait;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
-use crate::agent::Message;
+use crate::agent::{ContentBlock, Message, MessageRole};
 
-use super::Provider;
+use super::{Provider, SamplingParams};
 
 /// Tool definition in a provider-agnostic shape.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -56,6 +56,274 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                 "required": ["document_id", "chunk_index"]
             }),
         },
+        ToolDefinition {
+            name: "read_pages".to_string(),
+            description: "Read a range of pages from a document. Prefer this over reading many chunks when you need a full section; search results tell you which pages to look at. Returns at most 10 pages per call, each marked with its page number.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "document_id": {
+                        "type": "string",
+                        "description": "The document ID from search or list results"
+                    },
+                    "start_page": {
+                        "type": "integer",
+                        "description": "First page to read (1-based)"
+                    },
+                    "end_page": {
+                        "type": "integer",
+                        "description": "Last page to read, inclusive (defaults to start_page)"
+                    }
+                },
+                "required": ["document_id", "start_page"]
+            }),
+        },
+        ToolDefinition {
+            name: "tag_document".to_string(),
+            description: "Add or remove tags on one or more documents. The user is asked to approve the change before it is applied, so batch all matching documents into a single call. Returns each document's tags after the change.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "document_ids": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "IDs of the documents to tag, from search or list results"
+                    },
+                    "add_tags": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Tags to add"
+                    },
+                    "remove_tags": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Tags to remove"
+                    }
+                },
+                "required": ["document_ids"]
+            }),
+        },
+        ToolDefinition {
+            name: "plan".to_string(),
+            description: "Lay out the steps you intend to take for a question that needs several searches or reads, shown to the user as a checklist. Call it again with the full list and updated statuses as steps finish.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "steps": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "description": {
+                                    "type": "string",
+                                    "description": "What the step does, e.g. 'Search for payments to Acme'"
+                                },
+                                "status": {
+                                    "type": "string",
+                                    "enum": ["pending", "in_progress", "done", "skipped"]
+                                }
+                            },
+                            "required": ["description", "status"]
+                        },
+                        "description": "Every step of the plan, in order"
+                    }
+                },
+                "required": ["steps"]
+            }),
+        },
+        ToolDefinition {
+            name: "remember".to_string(),
+            description: "Save a short fact about the active collection for future conversations, such as a confirmed finding or which documents matter for a topic. Remembered facts appear in your instructions in every later chat on the collection. The user is asked to approve each fact, so only save what the user confirmed or what was clearly established from the documents.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "fact": {
+                        "type": "string",
+                        "description": "The fact, in one or two sentences, naming the documents it comes from"
+                    },
+                    "collection_id": {
+                        "type": "string",
+                        "description": "Collection the fact belongs to. Only needed when several collections are active."
+                    }
+                },
+                "required": ["fact"]
+            }),
+        },
+        ToolDefinition {
+            name: "write_report".to_string(),
+            description: "Save a long-form write-up of findings as a Markdown document in the active collection, where it is searchable and shared alongside the evidence. Use it when the user asks for a report or memo to keep. Cite document names and pages in the text. The user is asked to approve each report.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "title": {
+                        "type": "string",
+                        "description": "Report title, also used as the file name"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "The report in Markdown"
+                    },
+                    "collection_id": {
+                        "type": "string",
+                        "description": "Collection to save the report to. Only needed when several collections are active."
+                    }
+                },
+                "required": ["title", "content"]
+            }),
+        },
+        ToolDefinition {
+            name: "save_search".to_string(),
+            description: "Save a search query to the active collection so the user can run it again later. Set notify to report documents that start matching after today, e.g. when new files are imported, to keep watching a lead. The user is asked to approve each saved search.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "The search query, as you would pass it to search"
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "Short label for the search, e.g. 'Payments to Acme'"
+                    },
+                    "notify": {
+                        "type": "boolean",
+                        "description": "Report newly matching documents (default false)"
+                    },
+                    "collection_id": {
+                        "type": "string",
+                        "description": "Collection to save the search to. Only needed when several collections are active."
+                    }
+                },
+                "required": ["query"]
+            }),
+        },
+        ToolDefinition {
+            name: "get_document_info".to_string(),
+            description: "Get a document's metadata: file type, page count, import date, tags, estimated language, how well its text was extracted, and any near-duplicate documents in the same collection. Use this to judge provenance, recency, or whether a document's text can be trusted before quoting it.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "document_id": {
+                        "type": "string",
+                        "description": "The document ID from search or list results"
+                    }
+                },
+                "required": ["document_id"]
+            }),
+        },
+        ToolDefinition {
+            name: "get_source_file".to_string(),
+            description: "Export a document's original file (e.g. the PDF) so the user can open it in their own viewer. Use this when the user wants to see the original, such as to check a cited page themselves.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "document_id": {
+                        "type": "string",
+                        "description": "The document ID from search or list results"
+                    }
+                },
+                "required": ["document_id"]
+            }),
+        },
+        ToolDefinition {
+            name: "view_page_image".to_string(),
+            description: "Look at a PDF page as an image. Use it when a page's text is garbled or jumbled, typically a scanned page read by OCR (marked as scanned in read_pages), or when a table, form, stamp or handwriting matters. One page per call.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "document_id": {
+                        "type": "string",
+                        "description": "The document ID from search or list results"
+                    },
+                    "page": {
+                        "type": "integer",
+                        "description": "Page to view (1-based)"
+                    }
+                },
+                "required": ["document_id", "page"]
+            }),
+        },
+        ToolDefinition {
+            name: "extract_timeline".to_string(),
+            description: "Build a chronological timeline from dates mentioned in documents. Returns JSON events, each with a normalized date, the sentence mentioning it, and the documents and pages it appears on. Use it to reconstruct the order of events across a set of documents (up to 20 per call).".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "document_ids": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "IDs of the documents to scan, from search or list results"
+                    }
+                },
+                "required": ["document_ids"]
+            }),
+        },
+        ToolDefinition {
+            name: "extract_entities".to_string(),
+            description: "Find people, organizations, locations and monetary amounts in documents or in the passages matching a search query. Returns JSON entities with mention counts and the documents and pages they appear on. Results are rule-based candidates: confirm important ones by reading the cited pages.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "document_ids": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "IDs of documents to scan in full (up to 20)"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "Search query; entities are extracted from the matching passages"
+                    },
+                    "types": {
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "enum": ["person", "organization", "location", "amount"]
+                        },
+                        "description": "Entity types to return (default: all)"
+                    }
+                }
+            }),
+        },
+        ToolDefinition {
+            name: "summarize_document".to_string(),
+            description: "Summarize an entire document, however long. The document is summarized section by section and the results merged, so this works for reports of hundreds of pages. Slower than reading pages: use it when the user asks for an overview of a whole document.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "document_id": {
+                        "type": "string",
+                        "description": "The document ID from search or list results"
+                    },
+                    "focus": {
+                        "type": "string",
+                        "description": "Optional topic to concentrate the summary on, e.g. 'payments to contractors'"
+                    }
+                },
+                "required": ["document_id"]
+            }),
+        },
+        ToolDefinition {
+            name: "compare_documents".to_string(),
+            description: "Compare two documents passage by passage, e.g. two drafts of a contract or two versions of a filing. Returns JSON changes in document order: passages added, removed, or changed (with the words that went and came), each with its page in either document. Reflowed text and moved page breaks are not reported.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "first_document_id": {
+                        "type": "string",
+                        "description": "The earlier or original version"
+                    },
+                    "second_document_id": {
+                        "type": "string",
+                        "description": "The later or revised version"
+                    },
+                    "describe": {
+                        "type": "boolean",
+                        "description": "Also have the model write a short description of the changes that matter (slower; default false)"
+                    }
+                },
+                "required": ["first_document_id", "second_document_id"]
+            }),
+        },
         ToolDefinition {
             name: "list_documents".to_string(),
             description: "List all documents in the current collection(s) with their metadata. Use this to get an overview of available documents before searching, or to find documents by characteristics like page count rather than content. Returns document names, IDs, and page counts.".to_string(),
@@ -79,6 +347,20 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                 "required": []
             }),
         },
+        ToolDefinition {
+            name: "fetch_more".to_string(),
+            description: "Get the next page of a long result. When search or list_documents has more results than fit in one reply, the reply ends with a token; pass it here to continue where it stopped.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "token": {
+                        "type": "string",
+                        "description": "The token from the end of the previous page"
+                    }
+                },
+                "required": ["token"]
+            }),
+        },
     ]
 }
 
@@ -86,18 +368,81 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
 #[derive(Debug, Clone)]
 pub enum ProviderEvent {
     TextDelta(String),
-    ToolCallStart { id: String, name: String },
-    ToolCallDelta { id: String, arguments_delta: String },
-    ToolCallComplete { id: String },
+    /// Reasoning the model writes before its answer.
+    ThinkingDelta(String),
+    ToolCallStart {
+        id: String,
+        name: String,
+    },
+    ToolCallDelta {
+        id: String,
+        arguments_delta: String,
+    },
+    ToolCallComplete {
+        id: String,
+    },
+    /// How the completion ended, sent just before [`ProviderEvent::Done`].
+    Finished {
+        usage: TokenUsage,
+        stop_reason: StopReason,
+    },
     Done,
     Error(String),
+    /// The attempt failed for a passing reason (see [`super::retry`]) and
+    /// attempt `attempt` of `max_attempts` starts after `delay`.
+    Retrying {
+        attempt: u32,
+        max_attempts: u32,
+        delay: std::time::Duration,
+        reason: String,
+    },
+}
+
+/// Tokens one completion consumed, as reported by the provider. Both
+/// counts are zero when the provider doesn't report usage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Why the model stopped generating.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// The model finished, or the provider didn't say why it stopped.
+    #[default]
+    Stop,
+    /// The output token limit cut the reply off.
+    Length,
+    /// The model stopped to call tools.
+    ToolUse,
+}
+
+impl StopReason {
+    /// Map a Chat Completions `finish_reason`.
+    pub fn from_finish_reason(reason: &str) -> Self {
+        match reason {
+            "length" => Self::Length,
+            "tool_calls" | "function_call" => Self::ToolUse,
+            _ => Self::Stop,
+        }
+    }
 }
 
 /// Streaming completion result.
 #[derive(Debug, Clone, Default)]
 pub struct CompletionResult {
     pub text: String,
+    /// Reasoning that preceded the text, empty when there was none.
+    pub thinking: String,
+    /// Provider signature over `thinking`, required to send it back.
+    pub thinking_signature: Option<String>,
+    /// Encrypted reasoning the provider withheld, to send back unchanged.
+    pub redacted_thinking: Vec<String>,
     pub tool_calls: Vec<CompletedToolCall>,
+    pub usage: TokenUsage,
+    pub stop_reason: StopReason,
 }
 
 /// A completed tool call the model emitted.
@@ -127,18 +472,225 @@ where
         .collect()
 }
 
+/// A JSON shape a structured completion must produce.
+#[derive(Debug, Clone)]
+pub struct StructuredSchema {
+    /// Short identifier, e.g. `timeline`. Used as the tool name when the
+    /// schema is requested through tool calling.
+    pub name: String,
+    pub description: String,
+    /// JSON Schema for the value; see [`super::schema`] for what is checked.
+    pub schema: serde_json::Value,
+}
+
+/// Attempts at a structured completion before giving up. Each retry tells
+/// the model what was wrong with the previous value.
+pub const STRUCTURED_ATTEMPTS: usize = 2;
+
+/// Context window assumed for providers that don't report one. Small
+/// enough for the local models Insight ships.
+pub const DEFAULT_CONTEXT_WINDOW: usize = 8192;
+
 /// Chat role trait. Extends [`Provider`] with a streaming completion method.
 #[async_trait]
 pub trait ChatProvider: Provider {
+    /// Context window in tokens. Tools that feed long documents to the
+    /// model size their requests from this.
+    fn context_window(&self) -> usize {
+        DEFAULT_CONTEXT_WINDOW
+    }
+
+    /// Whether the model reads images. Page images are only offered to
+    /// models that do; others ignore image blocks in the transcript.
+    fn supports_images(&self) -> bool {
+        false
+    }
+
     /// Stream a chat completion with optional tool calling.
     ///
     /// Events stream via `event_tx` as content arrives. Tool calls are
-    /// accumulated and returned in the final [`CompletionResult`].
+    /// accumulated and returned in the final [`CompletionResult`]. Unset
+    /// `sampling` fields keep the provider's defaults.
     async fn stream_completion(
         &self,
         messages: &[Message],
         tools: &[ToolDefinition],
+        sampling: &SamplingParams,
         event_tx: mpsc::Sender<ProviderEvent>,
         cancel_token: CancellationToken,
     ) -> Result<CompletionResult>;
+
+    /// Complete with a JSON value matching `schema`, validated before it is
+    /// returned.
+    ///
+    /// The default asks for the value as the arguments of a single tool
+    /// call, which every provider with tool calling supports. Providers
+    /// with constrained decoding override this. Both decode greedily
+    /// ([`SamplingParams::EXTRACTION`]).
+    async fn complete_structured(
+        &self,
+        messages: &[Message],
+        schema: &StructuredSchema,
+        cancel_token: CancellationToken,
+    ) -> Result<serde_json::Value> {
+        let tools = [ToolDefinition {
+            name: schema.name.clone(),
+            description: schema.description.clone(),
+            parameters: schema.schema.clone(),
+        }];
+        let mut messages = messages.to_vec();
+        messages.push(Message {
+            role: MessageRole::User,
+            content: vec![ContentBlock::Text {
+                text: format!(
+                    "Reply only by calling the `{}` tool with your answer.",
+                    schema.name
+                ),
+            }],
+        });
+
+        let mut problem = String::new();
+        for _ in 0..STRUCTURED_ATTEMPTS {
+            // Events aren't needed; the result carries the tool call.
+            let (event_tx, mut event_rx) = mpsc::channel::<ProviderEvent>(100);
+            let drain = tokio::spawn(async move { while event_rx.recv().await.is_some() {} });
+            let result = self
+                .stream_completion(
+                    &messages,
+                    &tools,
+                    &SamplingParams::EXTRACTION,
+                    event_tx,
+                    cancel_token.clone(),
+                )
+                .await;
+            let _ = drain.await;
+            let result = result?;
+
+            let value = result
+                .tool_calls
+                .into_iter()
+                .find(|call| call.name == schema.name)
+                .map(|call| call.arguments);
+            problem = match value {
+                Some(value) => match super::schema::validate(&value, &schema.schema) {
+                    Ok(()) => return Ok(value),
+                    Err(e) => e,
+                },
+                None => format!("the `{}` tool was not called", schema.name),
+            };
+            messages.push(Message {
+                role: MessageRole::User,
+                content: vec![ContentBlock::Text {
+                    text: format!(
+                        "That answer was rejected: {}. Call the `{}` tool again with a corrected value.",
+                        problem, schema.name
+                    ),
+                }],
+            });
+        }
+        anyhow::bail!(
+            "The model did not produce valid {}: {}",
+            schema.name,
+            problem
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Answers with scripted tool-call arguments, one per request.
+    struct ScriptedProvider {
+        replies: Mutex<Vec<serde_json::Value>>,
+        requests: Mutex<Vec<Vec<Message>>>,
+    }
+
+    impl Provider for ScriptedProvider {
+        fn provider_name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn model_id(&self) -> &str {
+            "mock-model"
+        }
+    }
+
+    #[async_trait]
+    impl ChatProvider for ScriptedProvider {
+        async fn stream_completion(
+            &self,
+            messages: &[Message],
+            tools: &[ToolDefinition],
+            _sampling: &SamplingParams,
+            _event_tx: mpsc::Sender<ProviderEvent>,
+            _cancel_token: CancellationToken,
+        ) -> Result<CompletionResult> {
+            self.requests.lock().unwrap().push(messages.to_vec());
+            let arguments = self.replies.lock().unwrap().remove(0);
+            Ok(CompletionResult {
+                tool_calls: vec![CompletedToolCall {
+                    id: "call_1".to_string(),
+                    name: tools[0].name.clone(),
+                    arguments,
+                }],
+                ..CompletionResult::default()
+            })
+        }
+    }
+
+    fn people_schema() -> StructuredSchema {
+        StructuredSchema {
+            name: "people".to_string(),
+            description: "People named in the text".to_string(),
+            schema: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "names": { "type": "array", "items": { "type": "string" } }
+                },
+                "required": ["names"]
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_structured_retries_invalid_value() {
+        let provider = ScriptedProvider {
+            replies: Mutex::new(vec![
+                serde_json::json!({ "names": "Jane Doe" }),
+                serde_json::json!({ "names": ["Jane Doe"] }),
+            ]),
+            requests: Mutex::new(Vec::new()),
+        };
+
+        let value = provider
+            .complete_structured(&[], &people_schema(), CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(value, serde_json::json!({ "names": ["Jane Doe"] }));
+        let requests = provider.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1]
+            .last()
+            .unwrap()
+            .text()
+            .contains("rejected: $.names: expected array"));
+    }
+
+    #[tokio::test]
+    async fn test_complete_structured_gives_up() {
+        let provider = ScriptedProvider {
+            replies: Mutex::new(vec![serde_json::json!({}); STRUCTURED_ATTEMPTS]),
+            requests: Mutex::new(Vec::new()),
+        };
+
+        let result = provider
+            .complete_structured(&[], &people_schema(), CancellationToken::new())
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(provider.requests.lock().unwrap().len(), STRUCTURED_ATTEMPTS);
+    }
 }
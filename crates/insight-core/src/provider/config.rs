@@ -2,17 +2,88 @@
 
 use serde::{Deserialize, Serialize};
 
-/// Provider configuration stored in settings.
+/// Provider configuration stored in settings. API keys aren't saved with
+/// it; they are kept in the [`crate::secrets`] store and filled in on load.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ProviderConfig {
     /// Local model via mistralrs
-    Local { model_id: String },
+    Local {
+        model_id: String,
+        #[serde(default, skip_serializing_if = "SamplingParams::is_unset")]
+        sampling: SamplingParams,
+    },
     /// OpenAI API
     #[serde(rename = "openai")]
-    OpenAI { api_key: String, model: String },
+    OpenAI {
+        #[serde(default, skip_serializing)]
+        api_key: String,
+        model: String,
+        #[serde(default, skip_serializing_if = "SamplingParams::is_unset")]
+        sampling: SamplingParams,
+    },
     /// Anthropic API
-    Anthropic { api_key: String, model: String },
+    Anthropic {
+        #[serde(default, skip_serializing)]
+        api_key: String,
+        model: String,
+        #[serde(default, skip_serializing_if = "SamplingParams::is_unset")]
+        sampling: SamplingParams,
+    },
+    /// OpenRouter API, routing to many hosted models
+    #[serde(rename = "openrouter")]
+    OpenRouter {
+        #[serde(default, skip_serializing)]
+        api_key: String,
+        model: String,
+        /// Context window OpenRouter reported for the model when it was
+        /// chosen.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context_window: Option<usize>,
+        #[serde(default, skip_serializing_if = "SamplingParams::is_unset")]
+        sampling: SamplingParams,
+    },
+    /// Mistral API
+    Mistral {
+        #[serde(default, skip_serializing)]
+        api_key: String,
+        model: String,
+        /// Context window Mistral reported for the model when it was
+        /// chosen.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context_window: Option<usize>,
+        #[serde(default, skip_serializing_if = "SamplingParams::is_unset")]
+        sampling: SamplingParams,
+    },
+    /// Groq API
+    Groq {
+        #[serde(default, skip_serializing)]
+        api_key: String,
+        model: String,
+        /// Context window Groq reported for the model when it was
+        /// chosen.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context_window: Option<usize>,
+        #[serde(default, skip_serializing_if = "SamplingParams::is_unset")]
+        sampling: SamplingParams,
+    },
+    /// Any server with an OpenAI-style Chat Completions API, e.g. llama.cpp,
+    /// vLLM or LM Studio
+    #[serde(rename = "openai_compatible")]
+    OpenAICompatible {
+        /// Base URL the API paths are relative to, e.g.
+        /// `http://localhost:8080/v1`.
+        base_url: String,
+        /// Empty for servers that don't check keys.
+        #[serde(default, skip_serializing)]
+        api_key: String,
+        model: String,
+        /// Context window the server reported for the model, if it did.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context_window: Option<usize>,
+        #[serde(default, skip_serializing_if = "SamplingParams::is_unset")]
+        sampling: SamplingParams,
+    },
 }
 
 impl ProviderConfig {
@@ -22,17 +93,132 @@ impl ProviderConfig {
             ProviderConfig::Local { .. } => "local",
             ProviderConfig::OpenAI { .. } => "openai",
             ProviderConfig::Anthropic { .. } => "anthropic",
+            ProviderConfig::OpenRouter { .. } => "openrouter",
+            ProviderConfig::Mistral { .. } => "mistral",
+            ProviderConfig::Groq { .. } => "groq",
+            ProviderConfig::OpenAICompatible { .. } => "openai_compatible",
         }
     }
 
     /// Model identifier within the provider family.
     pub fn model_id(&self) -> &str {
         match self {
-            ProviderConfig::Local { model_id } => model_id,
+            ProviderConfig::Local { model_id, .. } => model_id,
             ProviderConfig::OpenAI { model, .. } => model,
             ProviderConfig::Anthropic { model, .. } => model,
+            ProviderConfig::OpenRouter { model, .. } => model,
+            ProviderConfig::Mistral { model, .. } => model,
+            ProviderConfig::Groq { model, .. } => model,
+            ProviderConfig::OpenAICompatible { model, .. } => model,
         }
     }
+
+    /// The API key, for remote providers.
+    pub fn api_key_mut(&mut self) -> Option<&mut String> {
+        match self {
+            ProviderConfig::Local { .. } => None,
+            ProviderConfig::OpenAI { api_key, .. }
+            | ProviderConfig::Anthropic { api_key, .. }
+            | ProviderConfig::OpenRouter { api_key, .. }
+            | ProviderConfig::Mistral { api_key, .. }
+            | ProviderConfig::Groq { api_key, .. }
+            | ProviderConfig::OpenAICompatible { api_key, .. } => Some(api_key),
+        }
+    }
+
+    /// Default sampling for chat turns with this provider. A conversation's
+    /// own settings take precedence.
+    pub fn sampling(&self) -> SamplingParams {
+        match self {
+            ProviderConfig::Local { sampling, .. }
+            | ProviderConfig::OpenAI { sampling, .. }
+            | ProviderConfig::Anthropic { sampling, .. }
+            | ProviderConfig::OpenRouter { sampling, .. }
+            | ProviderConfig::Mistral { sampling, .. }
+            | ProviderConfig::Groq { sampling, .. }
+            | ProviderConfig::OpenAICompatible { sampling, .. } => *sampling,
+        }
+    }
+
+    /// Replace the default sampling.
+    pub fn set_sampling(&mut self, new: SamplingParams) {
+        match self {
+            ProviderConfig::Local { sampling, .. }
+            | ProviderConfig::OpenAI { sampling, .. }
+            | ProviderConfig::Anthropic { sampling, .. }
+            | ProviderConfig::OpenRouter { sampling, .. }
+            | ProviderConfig::Mistral { sampling, .. }
+            | ProviderConfig::Groq { sampling, .. }
+            | ProviderConfig::OpenAICompatible { sampling, .. } => *sampling = new,
+        }
+    }
+}
+
+/// Sampling settings for a completion. Unset fields are left to the next
+/// layer: a conversation's settings fall back to the provider config's,
+/// and those to the provider's own defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SamplingParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Longest reply, in tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Tokens the model may spend reasoning before it answers, for models
+    /// that can. Unset or zero leaves reasoning off.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking_budget: Option<u32>,
+}
+
+impl SamplingParams {
+    /// Greedy decoding, for extracting values from documents where the
+    /// most likely answer is the one wanted.
+    pub const EXTRACTION: SamplingParams = SamplingParams {
+        temperature: Some(0.0),
+        top_p: None,
+        max_tokens: None,
+        thinking_budget: None,
+    };
+
+    pub fn is_unset(&self) -> bool {
+        *self == SamplingParams::default()
+    }
+
+    /// These settings, with unset fields taken from `fallback`.
+    pub fn or(self, fallback: SamplingParams) -> SamplingParams {
+        SamplingParams {
+            temperature: self.temperature.or(fallback.temperature),
+            top_p: self.top_p.or(fallback.top_p),
+            max_tokens: self.max_tokens.or(fallback.max_tokens),
+            thinking_budget: self.thinking_budget.or(fallback.thinking_budget),
+        }
+    }
+
+    /// Whether the model should reason before answering.
+    pub fn thinking(&self) -> bool {
+        self.thinking_budget.is_some_and(|budget| budget > 0)
+    }
+
+    /// Check the values are in range: temperature 0 to 2, top_p above 0
+    /// and at most 1, and at least one token.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Some(t) = self.temperature {
+            anyhow::ensure!(
+                (0.0..=2.0).contains(&t),
+                "Temperature must be between 0 and 2"
+            );
+        }
+        if let Some(p) = self.top_p {
+            anyhow::ensure!(p > 0.0 && p <= 1.0, "top_p must be above 0 and at most 1");
+        }
+        if let Some(n) = self.max_tokens {
+            anyhow::ensure!(n > 0, "max_tokens must be at least 1");
+        }
+        Ok(())
+    }
 }
 
 /// Information about a remote model returned from an API listing.
@@ -41,6 +227,9 @@ pub struct RemoteModelInfo {
     pub id: String,
     pub name: String,
     pub description: Option<String>,
+    /// Context window in tokens, for listings that report it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<usize>,
 }
 
 /// Provider family descriptor for the UI picker.
@@ -72,5 +261,80 @@ pub fn get_provider_families() -> Vec<ProviderFamily> {
             description: "Claude 3.5 Sonnet, Claude 3 Opus, and more".to_string(),
             requires_api_key: true,
         },
+        ProviderFamily {
+            id: "openrouter".to_string(),
+            name: "OpenRouter".to_string(),
+            description: "Hundreds of hosted models behind one API key".to_string(),
+            requires_api_key: true,
+        },
+        ProviderFamily {
+            id: "mistral".to_string(),
+            name: "Mistral".to_string(),
+            description: "Mistral Large, Medium and Small, hosted in the EU".to_string(),
+            requires_api_key: true,
+        },
+        ProviderFamily {
+            id: "groq".to_string(),
+            name: "Groq".to_string(),
+            description: "Open models like Llama and Qwen with very fast responses".to_string(),
+            requires_api_key: true,
+        },
+        ProviderFamily {
+            id: "openai_compatible".to_string(),
+            name: "Custom Endpoint".to_string(),
+            description: "llama.cpp, vLLM, LM Studio or any OpenAI-compatible server".to_string(),
+            requires_api_key: false,
+        },
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sampling_or_prefers_own_values() {
+        let conversation = SamplingParams {
+            temperature: Some(0.2),
+            ..Default::default()
+        };
+        let global = SamplingParams {
+            temperature: Some(1.0),
+            max_tokens: Some(512),
+            ..Default::default()
+        };
+        assert_eq!(
+            conversation.or(global),
+            SamplingParams {
+                temperature: Some(0.2),
+                top_p: None,
+                max_tokens: Some(512),
+                thinking_budget: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_provider_config_without_sampling_deserializes() {
+        let config: ProviderConfig =
+            serde_json::from_str(r#"{"type":"openai","api_key":"k","model":"gpt-4o"}"#).unwrap();
+        assert!(config.sampling().is_unset());
+        // Unset sampling isn't written back, and neither is the key.
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(!json.contains("sampling"));
+        assert!(!json.contains("api_key"));
+    }
+
+    #[test]
+    fn test_openai_compatible_config_without_key_deserializes() {
+        let config: ProviderConfig = serde_json::from_str(
+            r#"{"type":"openai_compatible","base_url":"http://localhost:8080/v1","model":"qwen3"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.provider_type(), "openai_compatible");
+        assert!(matches!(
+            config,
+            ProviderConfig::OpenAICompatible { ref api_key, .. } if api_key.is_empty()
+        ));
+    }
+}
@@ -6,20 +6,155 @@
 
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 
 use super::Provider;
 
+/// Algorithm used to split document text into chunks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkStrategy {
+    /// Token-budgeted windows cut at paragraph and sentence boundaries.
+    #[default]
+    Tokens,
+    /// Group consecutive sentences while they stay on one topic, cutting
+    /// where embedding similarity between neighbours drops. Costs an extra
+    /// embedding pass over every sentence.
+    Semantic,
+    /// Chunk each page on its own so no chunk spans two pages. Citations
+    /// then map to exactly one page. Blank pages produce no chunks.
+    Page,
+}
+
+/// How document text is split before embedding.
+///
+/// Sizes are in model tokens. Set globally in `Settings` and optionally
+/// overridden per collection; the effective config is recorded on each
+/// `EmbeddingData` so stored chunks can be traced back to their settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkingConfig {
+    /// Splitting algorithm.
+    #[serde(default)]
+    pub strategy: ChunkStrategy,
+    /// Target (maximum) tokens per chunk. Most embedding models cap
+    /// input at 512 tokens.
+    #[serde(default = "default_target_tokens")]
+    pub target_tokens: usize,
+    /// Tokens shared between consecutive chunks.
+    #[serde(default = "default_overlap_tokens")]
+    pub overlap_tokens: usize,
+    /// Stop after this many chunks per document (None = no limit).
+    #[serde(default)]
+    pub max_chunks_per_doc: Option<usize>,
+}
+
+fn default_target_tokens() -> usize {
+    450
+}
+
+fn default_overlap_tokens() -> usize {
+    50
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ChunkStrategy::default(),
+            target_tokens: default_target_tokens(),
+            overlap_tokens: default_overlap_tokens(),
+            max_chunks_per_doc: None,
+        }
+    }
+}
+
+impl ChunkingConfig {
+    /// Reject configs the splitter can't honor.
+    pub fn validate(&self) -> Result<()> {
+        if self.target_tokens == 0 {
+            anyhow::bail!("Chunk size must be greater than zero");
+        }
+        if self.overlap_tokens >= self.target_tokens {
+            anyhow::bail!(
+                "Chunk overlap ({}) must be smaller than chunk size ({})",
+                self.overlap_tokens,
+                self.target_tokens
+            );
+        }
+        if self.max_chunks_per_doc == Some(0) {
+            anyhow::bail!("Max chunks per document must be greater than zero");
+        }
+        Ok(())
+    }
+}
+
+/// Text prepended before embedding, per side of a search.
+///
+/// Asymmetric retrieval models are trained with instructions on the query
+/// side, the document side, or both (e5's `query: ` / `passage: `, Qwen3's
+/// `Instruct: …\nQuery:`). Embedding raw text on either side quietly costs
+/// recall. Empty strings mean no prefix.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingPrompts {
+    /// Prepended to search queries.
+    #[serde(default)]
+    pub query: String,
+    /// Prepended to document chunks.
+    #[serde(default)]
+    pub document: String,
+}
+
+impl EmbeddingPrompts {
+    pub fn new(query: &str, document: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            document: document.to_string(),
+        }
+    }
+
+    /// Known prompts for a HuggingFace repo, by model family. Used for
+    /// custom models, which carry nothing but the repo id.
+    pub fn for_repo(hf_repo_id: &str) -> Self {
+        let repo = hf_repo_id.to_ascii_lowercase();
+        let name = repo.rsplit('/').next().unwrap_or(&repo);
+        if name.starts_with("qwen3-embedding") {
+            Self::new(QWEN3_QUERY_PROMPT, "")
+        } else if name.contains("e5-") && !name.contains("instruct") {
+            Self::new("query: ", "passage: ")
+        } else if name.starts_with("bge-") && name.ends_with("en-v1.5") {
+            Self::new(
+                "Represent this sentence for searching relevant passages: ",
+                "",
+            )
+        } else if name.starts_with("nomic-embed-text") {
+            Self::new("search_query: ", "search_document: ")
+        } else {
+            Self::default()
+        }
+    }
+
+    fn apply(prefix: &str, text: &str) -> String {
+        format!("{}{}", prefix, text)
+    }
+}
+
+/// Retrieval instruction recommended for Qwen3-Embedding queries.
+/// Documents are embedded without one.
+pub const QWEN3_QUERY_PROMPT: &str =
+    "Instruct: Given a question, retrieve passages from the documents that answer it\nQuery:";
+
 /// Embedding role trait. Extends [`Provider`] with chunking + vector output.
 #[async_trait]
 pub trait EmbeddingProvider: Provider {
     /// Vector dimensions this model produces.
     fn dimensions(&self) -> usize;
 
-    /// Split text into chunks suitable for this model.
+    /// Split text into token-budgeted chunks sized per `config`.
+    /// `strategy` and `max_chunks_per_doc` are applied by the caller
+    /// (see `pipeline::chunking`), not here.
     ///
     /// Async because the implementation may need to load a tokenizer to
     /// produce the split (see [`crate::provider::Provider::ensure_loaded`]).
-    async fn chunk_text(&self, content: &str) -> Result<Vec<String>>;
+    async fn chunk_text(&self, content: &str, config: &ChunkingConfig) -> Result<Vec<String>>;
 
     /// Embed a single short text (e.g. a query).
     async fn embed(&self, text: &str) -> Result<Vec<f32>>;
@@ -27,4 +162,114 @@ pub trait EmbeddingProvider: Provider {
     /// Batch embed multiple texts. More efficient than calling [`embed`]
     /// in a loop — implementations may fan out to the model in one call.
     async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>>;
+
+    /// Embed a trivial input and check the vector is usable: the declared
+    /// size, finite, and not all zeros. Run by the model manager after each
+    /// load so a broken model is caught before documents are indexed
+    /// without vectors.
+    async fn health_check(&self) -> Result<()> {
+        let vector = self.embed("health check").await?;
+        if vector.len() != self.dimensions() {
+            anyhow::bail!(
+                "Produced a {}-dimensional vector, expected {}",
+                vector.len(),
+                self.dimensions()
+            );
+        }
+        if vector.iter().any(|v| !v.is_finite()) {
+            anyhow::bail!("Produced non-finite values");
+        }
+        if vector.iter().all(|v| *v == 0.0) {
+            anyhow::bail!("Produced an all-zero vector");
+        }
+        Ok(())
+    }
+
+    /// Prompts this model expects (None = embed raw text).
+    fn prompts(&self) -> Option<&EmbeddingPrompts> {
+        None
+    }
+
+    /// Embed a search query, with the model's query prompt.
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        match self.prompts() {
+            Some(prompts) if !prompts.query.is_empty() && !query.trim().is_empty() => {
+                self.embed(&EmbeddingPrompts::apply(&prompts.query, query))
+                    .await
+            }
+            _ => self.embed(query).await,
+        }
+    }
+
+    /// Embed document text, with the model's document prompt.
+    async fn embed_documents(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        match self.prompts() {
+            Some(prompts) if !prompts.document.is_empty() => {
+                let prompted: Vec<String> = texts
+                    .iter()
+                    .map(|text| EmbeddingPrompts::apply(&prompts.document, text))
+                    .collect();
+                let refs: Vec<&str> = prompted.iter().map(|s| s.as_str()).collect();
+                self.embed_batch(&refs).await
+            }
+            _ => self.embed_batch(texts).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunking_config_defaults_when_missing() {
+        let parsed: ChunkingConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(parsed, ChunkingConfig::default());
+        assert_eq!(parsed.target_tokens, 450);
+        assert_eq!(parsed.overlap_tokens, 50);
+        assert_eq!(parsed.strategy, ChunkStrategy::Tokens);
+        assert!(parsed.validate().is_ok());
+    }
+
+    #[test]
+    fn prompts_for_known_families() {
+        let e5 = EmbeddingPrompts::for_repo("intfloat/multilingual-e5-large");
+        assert_eq!(e5.query, "query: ");
+        assert_eq!(e5.document, "passage: ");
+
+        let qwen = EmbeddingPrompts::for_repo("Qwen/Qwen3-Embedding-0.6B");
+        assert_eq!(qwen.query, QWEN3_QUERY_PROMPT);
+        assert!(qwen.document.is_empty());
+
+        let nomic = EmbeddingPrompts::for_repo("nomic-ai/nomic-embed-text-v1.5");
+        assert_eq!(nomic.document, "search_document: ");
+
+        assert_eq!(
+            EmbeddingPrompts::for_repo("sentence-transformers/all-MiniLM-L6-v2"),
+            EmbeddingPrompts::default()
+        );
+    }
+
+    #[test]
+    fn chunking_config_rejects_invalid() {
+        let overlap_too_big = ChunkingConfig {
+            target_tokens: 100,
+            overlap_tokens: 100,
+            ..Default::default()
+        };
+        assert!(overlap_too_big.validate().is_err());
+
+        let zero_size = ChunkingConfig {
+            target_tokens: 0,
+            overlap_tokens: 0,
+            ..Default::default()
+        };
+        assert!(zero_size.validate().is_err());
+
+        let zero_max = ChunkingConfig {
+            max_chunks_per_doc: Some(0),
+            ..Default::default()
+        };
+        assert!(zero_max.validate().is_err());
+    }
 }
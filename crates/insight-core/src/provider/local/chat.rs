@@ -7,20 +7,26 @@ use std::sync::Arc;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use mistralrs::{
-    CalledFunction, ChatCompletionChunkResponse, Delta, GgufModelBuilder, Model, RequestBuilder,
-    Response, TextMessageRole, Tool, ToolCallResponse, ToolCallType, ToolChoice, ToolType,
+    AutoDeviceMapParams, CalledFunction, ChatCompletionChunkResponse, Constraint, Delta,
+    DeviceMapSetting, GgufModelBuilder, MemoryGpuConfig, Model, PagedAttentionMetaBuilder,
+    PagedCacheType, RequestBuilder, Response, TextMessageRole, Tool, ToolCallResponse,
+    ToolCallType, ToolChoice, ToolType,
 };
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 use tracing::debug;
 
 use crate::agent::{render_context_message, ContentBlock, Message, MessageRole};
+use crate::config::{ComputeBackend, DeviceConfig, KvCacheType, LocalRuntimeConfig};
 use crate::models::LanguageModelInfo;
 use crate::provider::{
-    finalize_tool_calls, ChatProvider, CompletionResult, MemoryKind, Provider, ProviderEvent,
-    ToolDefinition,
+    finalize_tool_calls, schema, ChatProvider, CompletionResult, MemoryKind, Provider,
+    ProviderEvent, SamplingParams, StopReason, StructuredSchema, TokenUsage, ToolDefinition,
 };
 
+use super::device::{layer_mapping, DevicePlacement};
+use super::speculative;
+use super::think::{Segment, ThinkParser};
 use super::LocalModelState;
 
 /// Local LLM provider backed by a mistralrs GGUF model.
@@ -30,21 +36,69 @@ use super::LocalModelState;
 /// `Arc`; any in-flight inference keeps its own clone and finishes at the
 /// natural boundary.
 pub struct LocalChatProvider {
-    model_path: PathBuf,
-    gguf_file: String,
-    tokenizer_repo_id: String,
+    source: GgufSource,
+    /// Smaller model that proposes tokens for this one to check.
+    draft: Option<GgufSource>,
+    device: DeviceConfig,
+    runtime: LocalRuntimeConfig,
+    /// Longest context the model was trained on.
+    max_context: usize,
     state: LocalModelState<Model>,
 }
 
 impl LocalChatProvider {
     pub fn new(model_path: impl AsRef<Path>, model_info: &LanguageModelInfo) -> Self {
         Self {
-            model_path: model_path.as_ref().to_path_buf(),
-            gguf_file: model_info.gguf_file.clone(),
-            tokenizer_repo_id: model_info.tokenizer_repo_id.clone(),
+            source: GgufSource::new(model_path, model_info),
+            draft: None,
+            device: DeviceConfig::default(),
+            runtime: LocalRuntimeConfig::default(),
+            max_context: model_info.context_length,
             state: LocalModelState::new(model_info.id.clone()),
         }
     }
+
+    /// Place the model per `device` instead of the mistralrs default.
+    pub fn with_device(mut self, device: DeviceConfig) -> Self {
+        self.device = device;
+        self
+    }
+
+    /// Size the context and attention cache per `runtime`.
+    pub fn with_runtime(mut self, runtime: LocalRuntimeConfig) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// Decode speculatively, with `draft_info` proposing tokens. The draft
+    /// must share this model's tokenizer.
+    pub fn with_draft(
+        mut self,
+        draft_path: impl AsRef<Path>,
+        draft_info: &LanguageModelInfo,
+    ) -> Self {
+        self.draft = Some(GgufSource::new(draft_path, draft_info));
+        self
+    }
+}
+
+/// Where a GGUF model's files are.
+#[derive(Debug, Clone)]
+pub(super) struct GgufSource {
+    /// Directory holding the GGUF file.
+    pub path: PathBuf,
+    pub file: String,
+    pub tokenizer_repo_id: String,
+}
+
+impl GgufSource {
+    fn new(path: impl AsRef<Path>, model_info: &LanguageModelInfo) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            file: model_info.gguf_file.clone(),
+            tokenizer_repo_id: model_info.tokenizer_repo_id.clone(),
+        }
+    }
 }
 
 #[async_trait]
@@ -74,20 +128,46 @@ impl Provider for LocalChatProvider {
     }
 
     async fn ensure_loaded(&self) -> Result<()> {
-        let path = self.model_path.clone();
-        let gguf = self.gguf_file.clone();
-        let tok = self.tokenizer_repo_id.clone();
+        let source = self.source.clone();
+        let draft = self.draft.clone();
+        let device = self.device.clone();
+        let runtime = self.runtime.clone();
+        let context = self.runtime.context_for(self.max_context);
         let model_id = self.state.model_id().to_string();
 
         self.state
             .get_or_load(|| async move {
-                tracing::info!("Loading local chat model '{}'...", model_id);
-                let model = GgufModelBuilder::new(path.to_string_lossy().to_string(), vec![gguf])
-                    .with_tok_model_id(&tok)
-                    .with_logging()
-                    .build()
-                    .await
-                    .context("Failed to load GGUF model")?;
+                tracing::info!(
+                    "Loading local chat model '{}' with a {}-token context...",
+                    model_id,
+                    context
+                );
+                let model = match draft {
+                    Some(draft) => {
+                        if runtime.paged() {
+                            tracing::warn!(
+                                "Flash attention and KV cache quantization are off with a draft model"
+                            );
+                        }
+                        let mapping = layer_mapping(&device).unwrap_or(context_mapping(context));
+                        speculative::build(&source, &draft, &device, mapping)
+                            .await
+                            .context("Failed to load GGUF model with its draft model")?
+                    }
+                    None => {
+                        let builder = GgufModelBuilder::new(
+                            source.path.to_string_lossy().to_string(),
+                            vec![source.file.clone()],
+                        )
+                        .with_tok_model_id(&source.tokenizer_repo_id)
+                        .with_logging()
+                        .with_device_config(&device)?;
+                        with_runtime_config(builder, &device, &runtime, context)?
+                            .build()
+                            .await
+                            .context("Failed to load GGUF model")?
+                    }
+                };
                 tracing::info!("Local chat model '{}' loaded", model_id);
                 Ok(model)
             })
@@ -106,10 +186,15 @@ impl Provider for LocalChatProvider {
 
 #[async_trait]
 impl ChatProvider for LocalChatProvider {
+    fn context_window(&self) -> usize {
+        self.runtime.context_for(self.max_context)
+    }
+
     async fn stream_completion(
         &self,
         messages: &[Message],
         tools: &[ToolDefinition],
+        sampling: &SamplingParams,
         event_tx: mpsc::Sender<ProviderEvent>,
         cancel_token: CancellationToken,
     ) -> Result<CompletionResult> {
@@ -121,11 +206,15 @@ impl ChatProvider for LocalChatProvider {
             .ok_or_else(|| anyhow::anyhow!("Local chat model not loaded"))?;
 
         let mistral_tools = convert_tools(tools);
-        let request = build_request(messages, &mistral_tools);
+        let request = build_request(messages, &mistral_tools, sampling);
 
         let mut stream = model.stream_chat_request(request).await?;
 
         let mut text_content = String::new();
+        let mut thinking = String::new();
+        let mut think_parser = ThinkParser::new();
+        let mut usage = TokenUsage::default();
+        let mut stop_reason = StopReason::Stop;
         let mut tool_calls: Vec<ToolCallResponse> = Vec::new();
 
         while let Some(chunk) = stream.next().await {
@@ -134,8 +223,23 @@ impl ChatProvider for LocalChatProvider {
             }
 
             match chunk {
-                Response::Chunk(ChatCompletionChunkResponse { choices, .. }) => {
+                Response::Chunk(ChatCompletionChunkResponse {
+                    choices,
+                    usage: chunk_usage,
+                    ..
+                }) => {
+                    // The last chunk carries the token counts.
+                    if let Some(reported) = chunk_usage {
+                        usage = TokenUsage {
+                            input_tokens: reported.prompt_tokens as u64,
+                            output_tokens: reported.completion_tokens as u64,
+                        };
+                    }
+
                     if let Some(choice) = choices.first() {
+                        if let Some(reason) = &choice.finish_reason {
+                            stop_reason = StopReason::from_finish_reason(reason);
+                        }
                         let Delta {
                             content: delta_content,
                             tool_calls: delta_tool_calls,
@@ -143,9 +247,14 @@ impl ChatProvider for LocalChatProvider {
                         } = &choice.delta;
 
                         if let Some(text) = delta_content {
-                            if !text.is_empty() {
-                                let _ = event_tx.send(ProviderEvent::TextDelta(text.clone())).await;
-                                text_content.push_str(text);
+                            for segment in think_parser.push(text) {
+                                forward_segment(
+                                    segment,
+                                    &event_tx,
+                                    &mut text_content,
+                                    &mut thinking,
+                                )
+                                .await;
                             }
                         }
 
@@ -187,6 +296,10 @@ impl ChatProvider for LocalChatProvider {
             }
         }
 
+        for segment in think_parser.finish() {
+            forward_segment(segment, &event_tx, &mut text_content, &mut thinking).await;
+        }
+
         let completed_tool_calls = finalize_tool_calls(
             tool_calls
                 .into_iter()
@@ -198,14 +311,122 @@ impl ChatProvider for LocalChatProvider {
                 .send(ProviderEvent::ToolCallComplete { id: tc.id.clone() })
                 .await;
         }
+        if stop_reason == StopReason::Stop && !completed_tool_calls.is_empty() {
+            stop_reason = StopReason::ToolUse;
+        }
 
+        let _ = event_tx
+            .send(ProviderEvent::Finished { usage, stop_reason })
+            .await;
         let _ = event_tx.send(ProviderEvent::Done).await;
 
         Ok(CompletionResult {
-            text: text_content,
+            text: text_content.trim_start().to_string(),
             tool_calls: completed_tool_calls,
+            usage,
+            stop_reason,
+            thinking: thinking.trim().to_string(),
+            ..CompletionResult::default()
         })
     }
+
+    /// Constrained decoding: the sampler can only emit tokens that keep the
+    /// output valid against the schema, so small models don't need the
+    /// tool-calling round trip.
+    async fn complete_structured(
+        &self,
+        messages: &[Message],
+        structured: &StructuredSchema,
+        cancel_token: CancellationToken,
+    ) -> Result<serde_json::Value> {
+        self.ensure_loaded().await?;
+        let model: Arc<Model> = self
+            .state
+            .current()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("Local chat model not loaded"))?;
+
+        let request = build_request(messages, &[], &SamplingParams::EXTRACTION)
+            .set_constraint(Constraint::JsonSchema(structured.schema.clone()));
+        let response = tokio::select! {
+            response = model.send_chat_request(request) => response?,
+            _ = cancel_token.cancelled() => anyhow::bail!("Structured completion cancelled"),
+        };
+
+        let text = response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.content.clone())
+            .unwrap_or_default();
+        let value: serde_json::Value = serde_json::from_str(text.trim())
+            .with_context(|| format!("The model did not return JSON for {}", structured.name))?;
+        schema::validate(&value, &structured.schema).map_err(|e| {
+            anyhow::anyhow!("The model did not produce valid {}: {}", structured.name, e)
+        })?;
+        Ok(value)
+    }
+}
+
+/// Stream one parsed segment and add it to the reply or the reasoning.
+async fn forward_segment(
+    segment: Segment,
+    event_tx: &mpsc::Sender<ProviderEvent>,
+    text_content: &mut String,
+    thinking: &mut String,
+) {
+    match segment {
+        Segment::Text(text) => {
+            let _ = event_tx.send(ProviderEvent::TextDelta(text.clone())).await;
+            text_content.push_str(&text);
+        }
+        Segment::Thinking(text) => {
+            let _ = event_tx
+                .send(ProviderEvent::ThinkingDelta(text.clone()))
+                .await;
+            thinking.push_str(&text);
+        }
+    }
+}
+
+/// Reserve memory for `context` tokens and set up the attention cache.
+///
+/// Without a layer count, mistralrs spreads layers over the GPU sized for
+/// a 4k-token context, which leaves no room for longer ones.
+fn with_runtime_config(
+    builder: GgufModelBuilder,
+    device: &DeviceConfig,
+    runtime: &LocalRuntimeConfig,
+    context: usize,
+) -> Result<GgufModelBuilder> {
+    let builder = if device.gpu_layers.is_none() && device.backend != ComputeBackend::Cpu {
+        builder.with_device_mapping(context_mapping(context))
+    } else {
+        builder
+    };
+    if !runtime.paged() {
+        return Ok(builder);
+    }
+
+    let cache_type = match runtime.kv_cache {
+        KvCacheType::Auto => PagedCacheType::Auto,
+        KvCacheType::F8e4m3 => PagedCacheType::F8E4M3,
+    };
+    // mistralrs ignores the paged cache, with a warning, where the device
+    // doesn't support it.
+    builder.with_paged_attn(|| {
+        PagedAttentionMetaBuilder::default()
+            .with_gpu_memory(MemoryGpuConfig::ContextSize(context))
+            .with_cache_type(cache_type)
+            .build()
+    })
+}
+
+/// Automatic layer placement with room for `context` tokens.
+fn context_mapping(context: usize) -> DeviceMapSetting {
+    DeviceMapSetting::Auto(AutoDeviceMapParams::Text {
+        max_seq_len: context,
+        max_batch_size: 1,
+    })
 }
 
 fn convert_tools(tools: &[ToolDefinition]) -> Vec<Tool> {
@@ -230,11 +451,25 @@ fn json_to_hashmap(value: &serde_json::Value) -> HashMap<String, serde_json::Val
     }
 }
 
-fn build_request(messages: &[Message], tools: &[Tool]) -> RequestBuilder {
+fn build_request(
+    messages: &[Message],
+    tools: &[Tool],
+    sampling: &SamplingParams,
+) -> RequestBuilder {
     let mut request = RequestBuilder::new()
         .set_tools(tools.to_vec())
         .set_tool_choice(ToolChoice::Auto)
-        .enable_thinking(false);
+        // Reasoning can't be capped locally; the budget only switches it on.
+        .enable_thinking(sampling.thinking());
+    if let Some(temperature) = sampling.temperature {
+        request = request.set_sampler_temperature(temperature as f64);
+    }
+    if let Some(top_p) = sampling.top_p {
+        request = request.set_sampler_topp(top_p as f64);
+    }
+    if let Some(max_tokens) = sampling.max_tokens {
+        request = request.set_sampler_max_len(max_tokens as usize);
+    }
 
     for msg in messages {
         let text = msg.text();
@@ -0,0 +1,93 @@
+//! Device placement for mistralrs model builders.
+//!
+//! Translates a [`DeviceConfig`] into builder calls. `Auto` with no layer
+//! count leaves the builder untouched, so defaults stay mistralrs's own.
+
+use anyhow::{Context, Result};
+use mistralrs::{
+    Device, DeviceLayerMapMetadata, DeviceMapMetadata, DeviceMapSetting, EmbeddingModelBuilder,
+    GgufModelBuilder,
+};
+
+use crate::config::{ComputeBackend, DeviceConfig};
+
+/// Builders that accept device placement.
+pub(super) trait DevicePlacement: Sized {
+    fn force_cpu(self) -> Self;
+    fn device(self, device: Device) -> Self;
+    fn device_mapping(self, mapping: DeviceMapSetting) -> Self;
+
+    /// Apply `config`. Fails if the requested backend isn't compiled in
+    /// or the device index doesn't exist.
+    fn with_device_config(self, config: &DeviceConfig) -> Result<Self> {
+        if config.backend == ComputeBackend::Cpu {
+            return Ok(self.force_cpu());
+        }
+        let builder = match select_device(config)? {
+            Some(device) => self.device(device),
+            None => self,
+        };
+        Ok(match layer_mapping(config) {
+            Some(mapping) => builder.device_mapping(mapping),
+            None => builder,
+        })
+    }
+}
+
+/// The device `config` names, or `None` for mistralrs's choice. Fails if
+/// the requested backend isn't compiled in or the device index doesn't
+/// exist.
+pub(super) fn select_device(config: &DeviceConfig) -> Result<Option<Device>> {
+    Ok(match config.backend {
+        ComputeBackend::Auto => None,
+        ComputeBackend::Cpu => Some(Device::Cpu),
+        ComputeBackend::Cuda => Some(
+            Device::new_cuda(config.device_index)
+                .with_context(|| format!("CUDA device {} unavailable", config.device_index))?,
+        ),
+        ComputeBackend::Metal => Some(
+            Device::new_metal(config.device_index)
+                .with_context(|| format!("Metal device {} unavailable", config.device_index))?,
+        ),
+    })
+}
+
+/// Layer split for `config.gpu_layers`, if set.
+pub(super) fn layer_mapping(config: &DeviceConfig) -> Option<DeviceMapSetting> {
+    config.gpu_layers.map(|layers| {
+        DeviceMapSetting::Map(DeviceMapMetadata::from_num_device_layers(vec![
+            DeviceLayerMapMetadata {
+                ordinal: config.device_index,
+                layers,
+            },
+        ]))
+    })
+}
+
+impl DevicePlacement for GgufModelBuilder {
+    fn force_cpu(self) -> Self {
+        self.with_force_cpu()
+    }
+
+    fn device(self, device: Device) -> Self {
+        self.with_device(device)
+    }
+
+    fn device_mapping(self, mapping: DeviceMapSetting) -> Self {
+        self.with_device_mapping(mapping)
+    }
+}
+
+impl DevicePlacement for EmbeddingModelBuilder {
+    fn force_cpu(self) -> Self {
+        self.with_force_cpu()
+    }
+
+    fn device(self, device: Device) -> Self {
+        self.with_device(device)
+    }
+
+    fn device_mapping(self, mapping: DeviceMapSetting) -> Self {
+        self.with_device_mapping(mapping)
+    }
+}
@@ -3,7 +3,8 @@
 //! Construction is cheap: the HuggingFace repo id and dimensions are
 //! recorded. Tokenizer + weights load on [`Provider::ensure_loaded`].
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
@@ -12,37 +13,91 @@ use mistralrs::{EmbeddingModelBuilder, EmbeddingRequest, Model};
 use text_splitter::{ChunkConfig, TextSplitter};
 use tokenizers::Tokenizer;
 
-use crate::provider::{EmbeddingProvider, MemoryKind, Provider};
+use crate::config::DeviceConfig;
+use crate::provider::{ChunkingConfig, EmbeddingPrompts, EmbeddingProvider, MemoryKind, Provider};
 
+use super::device::DevicePlacement;
 use super::LocalModelState;
 
-/// Max tokens per chunk (most embedding models have 512 token limit).
-const CHUNK_MAX_TOKENS: usize = 450;
-
-/// Overlap between chunks in tokens.
-const CHUNK_OVERLAP_TOKENS: usize = 50;
-
-/// Weights + tokenizer-aware splitter. Loaded together on first use.
+/// Weights + tokenizer. Loaded together on first use; the tokenizer sizes
+/// chunks so they fit the model's input limit.
 struct LoadedState {
     model: Arc<Model>,
-    splitter: TextSplitter<Tokenizer>,
+    splitters: SplitterCache,
+}
+
+/// Token-aware splitters keyed by (target_tokens, overlap_tokens). Building
+/// one clones the tokenizer, so they are reused across calls. Shared by
+/// every local embedding backend.
+pub(super) struct SplitterCache {
+    tokenizer: Tokenizer,
+    splitters: Mutex<HashMap<(usize, usize), Arc<TextSplitter<Tokenizer>>>>,
+}
+
+impl SplitterCache {
+    pub(super) fn new(tokenizer: Tokenizer) -> Self {
+        Self {
+            tokenizer,
+            splitters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn splitter(&self, config: &ChunkingConfig) -> Result<Arc<TextSplitter<Tokenizer>>> {
+        let key = (config.target_tokens, config.overlap_tokens);
+        let mut splitters = self.splitters.lock().unwrap();
+        if let Some(splitter) = splitters.get(&key) {
+            return Ok(splitter.clone());
+        }
+        let splitter = Arc::new(TextSplitter::new(
+            ChunkConfig::new(config.target_tokens)
+                .with_sizer(self.tokenizer.clone())
+                .with_overlap(config.overlap_tokens)
+                .context("Invalid chunk config")?,
+        ));
+        splitters.insert(key, splitter.clone());
+        Ok(splitter)
+    }
+
+    /// Split `content` at the coarsest boundary that fits the budget.
+    pub(super) fn chunks(&self, content: &str, config: &ChunkingConfig) -> Result<Vec<String>> {
+        // TextSplitter cuts at the coarsest boundary that fits the token
+        // budget — paragraph, then sentence, then word — and only hard-cuts
+        // a single sentence longer than the budget.
+        let splitter = self.splitter(config)?;
+        Ok(splitter.chunks(content).map(String::from).collect())
+    }
 }
 
 pub struct LocalEmbeddingProvider {
     hf_repo_id: String,
     dimensions: usize,
+    prompts: EmbeddingPrompts,
+    device: DeviceConfig,
     state: LocalModelState<LoadedState>,
 }
 
 impl LocalEmbeddingProvider {
-    pub fn new(model_id: &str, hf_repo_id: &str, dimensions: usize) -> Self {
+    pub fn new(
+        model_id: &str,
+        hf_repo_id: &str,
+        dimensions: usize,
+        prompts: EmbeddingPrompts,
+    ) -> Self {
         Self {
             hf_repo_id: hf_repo_id.to_string(),
             dimensions,
+            prompts,
+            device: DeviceConfig::default(),
             state: LocalModelState::new(model_id),
         }
     }
 
+    /// Place the model per `device` instead of the mistralrs default.
+    pub fn with_device(mut self, device: DeviceConfig) -> Self {
+        self.device = device;
+        self
+    }
+
     async fn loaded(&self) -> Result<Arc<LoadedState>> {
         self.state
             .current()
@@ -80,6 +135,7 @@ impl Provider for LocalEmbeddingProvider {
     async fn ensure_loaded(&self) -> Result<()> {
         let hf_repo_id = self.hf_repo_id.clone();
         let dimensions = self.dimensions;
+        let device = self.device.clone();
 
         self.state
             .get_or_load(|| async move {
@@ -95,24 +151,33 @@ impl Provider for LocalEmbeddingProvider {
                 let tokenizer =
                     Tokenizer::from_file(&tokenizer_path).map_err(|e| anyhow::anyhow!("{}", e))?;
 
-                let splitter = TextSplitter::new(
-                    ChunkConfig::new(CHUNK_MAX_TOKENS)
-                        .with_sizer(tokenizer)
-                        .with_overlap(CHUNK_OVERLAP_TOKENS)
-                        .context("Invalid chunk config")?,
-                );
-
                 let model = EmbeddingModelBuilder::new(&hf_repo_id)
                     .with_logging()
+                    .with_device_config(&device)?
                     .build()
                     .await
                     .context("Failed to load embedding model")?;
 
+                // Custom models declare their dimensions by hand; a mismatch
+                // would otherwise surface much later as index errors.
+                let probe = model
+                    .generate_embedding("dimension check")
+                    .await
+                    .context("Embedding model failed its first inference")?;
+                if probe.len() != dimensions {
+                    anyhow::bail!(
+                        "{} produces {}-dimensional vectors, expected {}",
+                        hf_repo_id,
+                        probe.len(),
+                        dimensions
+                    );
+                }
+
                 tracing::info!("Embedding model loaded: {} ({}D)", hf_repo_id, dimensions);
 
                 Ok(LoadedState {
                     model: Arc::new(model),
-                    splitter,
+                    splitters: SplitterCache::new(tokenizer),
                 })
             })
             .await
@@ -134,14 +199,18 @@ impl EmbeddingProvider for LocalEmbeddingProvider {
         self.dimensions
     }
 
-    async fn chunk_text(&self, content: &str) -> Result<Vec<String>> {
+    fn prompts(&self) -> Option<&EmbeddingPrompts> {
+        Some(&self.prompts)
+    }
+
+    async fn chunk_text(&self, content: &str, config: &ChunkingConfig) -> Result<Vec<String>> {
         let content = content.trim();
         if content.is_empty() {
             return Ok(vec![]);
         }
         self.ensure_loaded().await?;
         let state = self.loaded().await?;
-        Ok(state.splitter.chunks(content).map(String::from).collect())
+        state.splitters.chunks(content, config)
     }
 
     async fn embed(&self, text: &str) -> Result<Vec<f32>> {
@@ -227,4 +296,17 @@ mod tests {
         assert!(joined.contains("First"));
         assert!(joined.contains("Fourth"));
     }
+
+    #[test]
+    fn test_text_splitter_prefers_sentence_boundaries() {
+        let text = "The contract was signed in May. Payment was due in June. \
+                    The supplier never delivered.\n\nA second paragraph follows here.";
+        let splitter = TextSplitter::new(60);
+        let chunks: Vec<&str> = splitter.chunks(text).collect();
+
+        assert!(chunks.len() > 1);
+        for chunk in &chunks {
+            assert!(chunk.ends_with('.'), "chunk cut mid-sentence: {:?}", chunk);
+        }
+    }
 }
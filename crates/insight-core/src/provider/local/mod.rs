@@ -1,4 +1,5 @@
-//! Local provider implementations backed by mistralrs.
+//! Local provider implementations backed by mistralrs (and, with the
+//! `onnx` feature, ONNX Runtime for embeddings).
 //!
 //! All local providers compose [`LocalModelState`] — a shared helper that
 //! owns the weight slot (lazy-loaded, unloadable) and the coexist flag.
@@ -7,12 +8,78 @@
 //! to the shared state.
 
 pub mod chat;
+mod device;
 pub mod embedding;
 pub mod ocr;
+#[cfg(feature = "onnx")]
+pub mod onnx_embedding;
+mod speculative;
 mod state;
+mod think;
+
+use std::sync::Arc;
+
+#[cfg(not(feature = "onnx"))]
+use anyhow::bail;
+#[cfg(feature = "onnx")]
+use anyhow::Context;
+use anyhow::Result;
+
+use crate::config::DeviceConfig;
+use crate::models::{EmbeddingBackend, EmbeddingModelInfo, ModelDownloader};
+use crate::provider::EmbeddingProvider;
 
 pub(crate) use state::LocalModelState;
 
 pub use chat::LocalChatProvider;
 pub use embedding::LocalEmbeddingProvider;
 pub use ocr::LocalOcrProvider;
+#[cfg(feature = "onnx")]
+pub use onnx_embedding::OnnxEmbeddingProvider;
+
+/// Build the local embedding provider for `model`, on the runtime its
+/// [`EmbeddingBackend`] names. ONNX models run on the CPU and need their
+/// export in the local cache; they fail here rather than falling back to
+/// mistralrs, which can't load them.
+pub fn embedding_provider(
+    model_id: &str,
+    model: &EmbeddingModelInfo,
+    downloader: &ModelDownloader,
+    device: &DeviceConfig,
+) -> Result<Arc<dyn EmbeddingProvider>> {
+    match model.backend {
+        EmbeddingBackend::Mistralrs => Ok(Arc::new(
+            LocalEmbeddingProvider::new(
+                model_id,
+                &model.hf_repo_id,
+                model.dimensions,
+                model.prompts.clone(),
+            )
+            .with_device(device.clone()),
+        )),
+        #[cfg(feature = "onnx")]
+        EmbeddingBackend::Onnx { pooling, .. } => {
+            let (onnx_path, tokenizer_path) = downloader
+                .onnx_export_paths(model)
+                .with_context(|| format!("Download {} before using it", model.name))?;
+            tracing::info!(model = %model_id, "Using ONNX Runtime embedding backend");
+            Ok(Arc::new(OnnxEmbeddingProvider::new(
+                model_id,
+                onnx_path,
+                tokenizer_path,
+                model.dimensions,
+                pooling,
+                model.prompts.clone(),
+            )))
+        }
+        #[cfg(not(feature = "onnx"))]
+        EmbeddingBackend::Onnx { .. } => {
+            let _ = downloader;
+            bail!(
+                "{}: {}",
+                model.name,
+                model.unavailable_reason().unwrap_or_default()
+            )
+        }
+    }
+}
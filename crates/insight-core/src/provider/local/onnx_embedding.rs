@@ -0,0 +1,349 @@
+//! Local embedding provider using ONNX Runtime.
+//!
+//! Runs the XLM-RoBERTa based models mistralrs can't load (see
+//! [`super::embedding_provider`]) from the repo's `onnx/model.onnx` export.
+//! Expects a sentence-transformers style export: `input_ids` +
+//! `attention_mask` (+ optional `token_type_ids`) in, either
+//! `sentence_embedding` or a token-level hidden state out. Token states are
+//! pooled the way the model was trained ([`OnnxPooling`]); vectors are
+//! L2-normalized either way.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ort::session::builder::GraphOptimizationLevel;
+use ort::session::Session;
+use ort::value::Tensor;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+use crate::models::OnnxPooling;
+use crate::provider::{ChunkingConfig, EmbeddingPrompts, EmbeddingProvider, MemoryKind, Provider};
+
+use super::embedding::SplitterCache;
+use super::LocalModelState;
+
+/// Longest input, in tokens, passed to the model. Chunks are sized well
+/// below this; it only guards against oversized queries.
+const MAX_INPUT_TOKENS: usize = 512;
+
+struct LoadedState {
+    /// `Session::run` needs `&mut`; inference is serialized per model.
+    session: Mutex<Session>,
+    /// Tokenizer with padding + truncation, used for model input.
+    encoder: Tokenizer,
+    has_token_type_ids: bool,
+    pooling: OnnxPooling,
+    splitters: SplitterCache,
+}
+
+pub struct OnnxEmbeddingProvider {
+    onnx_path: PathBuf,
+    tokenizer_path: PathBuf,
+    dimensions: usize,
+    pooling: OnnxPooling,
+    prompts: EmbeddingPrompts,
+    state: LocalModelState<LoadedState>,
+}
+
+impl OnnxEmbeddingProvider {
+    pub fn new(
+        model_id: &str,
+        onnx_path: PathBuf,
+        tokenizer_path: PathBuf,
+        dimensions: usize,
+        pooling: OnnxPooling,
+        prompts: EmbeddingPrompts,
+    ) -> Self {
+        Self {
+            onnx_path,
+            tokenizer_path,
+            dimensions,
+            pooling,
+            prompts,
+            state: LocalModelState::new(model_id),
+        }
+    }
+
+    async fn loaded(&self) -> Result<Arc<LoadedState>> {
+        self.state
+            .current()
+            .await
+            .ok_or_else(|| anyhow::anyhow!("ONNX embedding model not loaded"))
+    }
+}
+
+impl LoadedState {
+    fn load(onnx_path: &Path, tokenizer_path: &Path, pooling: OnnxPooling) -> Result<Self> {
+        let tokenizer =
+            Tokenizer::from_file(tokenizer_path).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let mut encoder = tokenizer.clone();
+        encoder.with_padding(Some(PaddingParams::default()));
+        encoder
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_INPUT_TOKENS,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)
+            // Drops the builder the error carries, which isn't Send.
+            .map_err(ort::Error::from)?
+            .commit_from_file(onnx_path)
+            .with_context(|| format!("Failed to load ONNX model {}", onnx_path.display()))?;
+        let has_token_type_ids = session
+            .inputs()
+            .iter()
+            .any(|i| i.name() == "token_type_ids");
+
+        Ok(Self {
+            session: Mutex::new(session),
+            encoder,
+            has_token_type_ids,
+            pooling,
+            splitters: SplitterCache::new(tokenizer),
+        })
+    }
+
+    /// Blocking: tokenize, run the graph, pool and normalize.
+    fn embed_blocking(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let encodings = self
+            .encoder
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow::anyhow!("Tokenization failed: {}", e))?;
+
+        let batch = encodings.len();
+        let seq_len = encodings.first().map(|e| e.len()).unwrap_or(0);
+        let mut ids = Vec::with_capacity(batch * seq_len);
+        let mut mask = Vec::with_capacity(batch * seq_len);
+        let mut type_ids = Vec::with_capacity(batch * seq_len);
+        for encoding in &encodings {
+            ids.extend(encoding.get_ids().iter().map(|&v| v as i64));
+            mask.extend(encoding.get_attention_mask().iter().map(|&v| v as i64));
+            type_ids.extend(encoding.get_type_ids().iter().map(|&v| v as i64));
+        }
+
+        let shape = [batch, seq_len];
+        let mut inputs = vec![
+            ("input_ids", Tensor::from_array((shape, ids))?.into_dyn()),
+            (
+                "attention_mask",
+                Tensor::from_array((shape, mask.clone()))?.into_dyn(),
+            ),
+        ];
+        if self.has_token_type_ids {
+            inputs.push((
+                "token_type_ids",
+                Tensor::from_array((shape, type_ids))?.into_dyn(),
+            ));
+        }
+
+        let mut session = self.session.lock().unwrap();
+        let outputs = session.run(inputs)?;
+
+        let vectors = match outputs.get("sentence_embedding") {
+            Some(pooled) => {
+                let (dims, data) = pooled.try_extract_tensor::<f32>()?;
+                let width = dims[1] as usize;
+                data.chunks(width).map(|v| v.to_vec()).collect()
+            }
+            None => {
+                let (dims, data) = outputs[0].try_extract_tensor::<f32>()?;
+                let hidden = dims[2] as usize;
+                match self.pooling {
+                    OnnxPooling::Mean => mean_pool(data, &mask, batch, seq_len, hidden),
+                    OnnxPooling::Cls => data
+                        .chunks(seq_len * hidden)
+                        .map(|states| states[..hidden].to_vec())
+                        .collect(),
+                }
+            }
+        };
+
+        Ok(vectors.into_iter().map(normalize).collect())
+    }
+}
+
+/// Average token states over positions where `mask` is 1.
+fn mean_pool(
+    states: &[f32],
+    mask: &[i64],
+    batch: usize,
+    seq_len: usize,
+    hidden: usize,
+) -> Vec<Vec<f32>> {
+    (0..batch)
+        .map(|b| {
+            let mut sum = vec![0.0f32; hidden];
+            let mut count = 0.0f32;
+            for t in 0..seq_len {
+                if mask[b * seq_len + t] == 0 {
+                    continue;
+                }
+                let offset = (b * seq_len + t) * hidden;
+                for (acc, v) in sum.iter_mut().zip(&states[offset..offset + hidden]) {
+                    *acc += v;
+                }
+                count += 1.0;
+            }
+            if count > 0.0 {
+                sum.iter_mut().for_each(|v| *v /= count);
+            }
+            sum
+        })
+        .collect()
+}
+
+fn normalize(mut v: Vec<f32>) -> Vec<f32> {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+    v
+}
+
+#[async_trait]
+impl Provider for OnnxEmbeddingProvider {
+    fn provider_name(&self) -> &'static str {
+        "local"
+    }
+
+    fn model_id(&self) -> &str {
+        self.state.model_id()
+    }
+
+    fn memory_kind(&self) -> MemoryKind {
+        MemoryKind::Local
+    }
+
+    fn coexist(&self) -> bool {
+        self.state.coexist()
+    }
+
+    fn set_coexist(&self, coexist: bool) {
+        self.state.set_coexist(coexist);
+    }
+
+    async fn is_loaded(&self) -> bool {
+        self.state.is_loaded().await
+    }
+
+    async fn ensure_loaded(&self) -> Result<()> {
+        let onnx_path = self.onnx_path.clone();
+        let tokenizer_path = self.tokenizer_path.clone();
+        let dimensions = self.dimensions;
+        let pooling = self.pooling;
+
+        self.state
+            .get_or_load(|| async move {
+                tracing::info!("Loading ONNX embedding model: {}", onnx_path.display());
+
+                let state = tokio::task::spawn_blocking(move || {
+                    let state = LoadedState::load(&onnx_path, &tokenizer_path, pooling)?;
+                    let probe = state.embed_blocking(&["dimension check".to_string()])?;
+                    let produced = probe.first().map(|v| v.len()).unwrap_or(0);
+                    if produced != dimensions {
+                        anyhow::bail!(
+                            "ONNX model produces {}-dimensional vectors, expected {}",
+                            produced,
+                            dimensions
+                        );
+                    }
+                    Ok(state)
+                })
+                .await??;
+
+                tracing::info!("ONNX embedding model loaded ({}D)", dimensions);
+                Ok(state)
+            })
+            .await
+            .map(|_| ())
+    }
+
+    async fn unload(&self) -> Result<bool> {
+        let did = self.state.unload().await;
+        if did {
+            tracing::info!("Unloaded ONNX embedding model '{}'", self.state.model_id());
+        }
+        Ok(did)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OnnxEmbeddingProvider {
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn prompts(&self) -> Option<&EmbeddingPrompts> {
+        Some(&self.prompts)
+    }
+
+    async fn chunk_text(&self, content: &str, config: &ChunkingConfig) -> Result<Vec<String>> {
+        let content = content.trim();
+        if content.is_empty() {
+            return Ok(vec![]);
+        }
+        self.ensure_loaded().await?;
+        let state = self.loaded().await?;
+        state.splitters.chunks(content, config)
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(vec![0.0; self.dimensions]);
+        }
+        let mut vectors = self.embed_batch(&[text]).await?;
+        vectors
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("ONNX model returned no embedding"))
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.ensure_loaded().await?;
+        let state = self.loaded().await?;
+        let texts: Vec<String> = texts.iter().map(|s| s.to_string()).collect();
+
+        let start = std::time::Instant::now();
+        let batch_size = texts.len();
+        let result = tokio::task::spawn_blocking(move || state.embed_blocking(&texts))
+            .await?
+            .context("Failed to generate batch embeddings");
+        tracing::debug!(
+            batch_size,
+            elapsed_ms = start.elapsed().as_millis(),
+            "ONNX batch embedding complete"
+        );
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mean_pool_ignores_padding() {
+        // One sequence of 3 tokens, hidden size 2; last token is padding.
+        let states = [1.0, 2.0, 3.0, 4.0, 100.0, 100.0];
+        let mask = [1, 1, 0];
+        let pooled = mean_pool(&states, &mask, 1, 3, 2);
+        assert_eq!(pooled, vec![vec![2.0, 3.0]]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let v = normalize(vec![3.0, 4.0]);
+        assert!((v[0] - 0.6).abs() < 1e-6);
+        assert!((v[1] - 0.8).abs() < 1e-6);
+        assert_eq!(normalize(vec![0.0, 0.0]), vec![0.0, 0.0]);
+    }
+}
@@ -0,0 +1,78 @@
+//! Speculative decoding for local chat models.
+//!
+//! A small draft model sharing the chat model's tokenizer proposes a few
+//! tokens at a time; the chat model checks them in a single forward pass
+//! and keeps the prefix it agrees with. Output is what the chat model
+//! alone would produce, only faster when the draft guesses well.
+//!
+//! `GgufModelBuilder` loads a single model, so the pair is loaded through
+//! mistralrs's lower-level loader API instead.
+
+use std::num::NonZeroUsize;
+
+use anyhow::Result;
+use mistralrs::{
+    best_device, DefaultSchedulerMethod, DeviceMapSetting, GGUFLoaderBuilder, GGUFSpecificConfig,
+    Loader, MistralRsBuilder, Model, ModelDType, SchedulerConfig, SpeculativeConfig,
+    SpeculativeLoader, TokenSource,
+};
+
+use super::chat::GgufSource;
+use super::device::select_device;
+use crate::config::DeviceConfig;
+
+/// Tokens the draft proposes per step. Small drafts drift quickly, so
+/// longer runs mostly get rejected.
+const DRAFT_TOKENS: usize = 4;
+
+/// Load `target` with `draft` proposing its tokens, placed per `device`.
+pub(super) async fn build(
+    target: &GgufSource,
+    draft: &GgufSource,
+    device: &DeviceConfig,
+    mapping: DeviceMapSetting,
+) -> Result<Model> {
+    let loader = SpeculativeLoader {
+        target: gguf_loader(target),
+        draft: gguf_loader(draft),
+        config: SpeculativeConfig {
+            gamma: DRAFT_TOKENS,
+        },
+    };
+    let device = match select_device(device)? {
+        Some(device) => device,
+        None => best_device(false)?,
+    };
+    let pipeline = loader.load_model_from_hf(
+        None,
+        TokenSource::CacheToken,
+        &ModelDType::Auto,
+        &device,
+        false,
+        mapping,
+        None,
+        None,
+    )?;
+
+    // Speculative pipelines decode one sequence at a time.
+    let scheduler = SchedulerConfig::DefaultScheduler {
+        method: DefaultSchedulerMethod::Fixed(NonZeroUsize::MIN),
+    };
+    let runner = MistralRsBuilder::new(pipeline, scheduler, false, None)
+        .build()
+        .await;
+    Ok(Model::new(runner))
+}
+
+fn gguf_loader(source: &GgufSource) -> Box<dyn Loader> {
+    GGUFLoaderBuilder::new(
+        None,
+        Some(source.tokenizer_repo_id.clone()),
+        source.path.to_string_lossy().to_string(),
+        vec![source.file.clone()],
+        GGUFSpecificConfig::default(),
+        false,
+        None,
+    )
+    .build()
+}
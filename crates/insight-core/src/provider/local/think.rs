@@ -0,0 +1,136 @@
+//! Splitting `<think>` reasoning out of a streamed reply.
+//!
+//! Reasoning models write their chain of thought inside `<think>…</think>`
+//! before the answer. A tag can arrive split across stream chunks, so the
+//! parser holds back any tail that might be the start of one until the
+//! next chunk settles it.
+
+const OPEN_TAG: &str = "<think>";
+const CLOSE_TAG: &str = "</think>";
+
+/// A run of streamed output, on one side of a tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Thinking(String),
+    Text(String),
+}
+
+/// Incremental `<think>` tag parser. Feed deltas to [`ThinkParser::push`]
+/// and call [`ThinkParser::finish`] when the stream ends.
+#[derive(Debug, Default)]
+pub struct ThinkParser {
+    pending: String,
+    in_thinking: bool,
+}
+
+impl ThinkParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse `delta`, returning the segments that are certain so far.
+    pub fn push(&mut self, delta: &str) -> Vec<Segment> {
+        self.pending.push_str(delta);
+        let mut segments = Vec::new();
+        loop {
+            let tag = if self.in_thinking {
+                CLOSE_TAG
+            } else {
+                OPEN_TAG
+            };
+            if let Some(pos) = self.pending.find(tag) {
+                let before: String = self.pending.drain(..pos).collect();
+                self.pending.drain(..tag.len());
+                self.emit(&mut segments, before);
+                self.in_thinking = !self.in_thinking;
+                continue;
+            }
+            // Tags are ASCII, so the held-back tail starts on a char boundary.
+            let held = partial_tag_len(&self.pending, tag);
+            let ready: String = self.pending.drain(..self.pending.len() - held).collect();
+            self.emit(&mut segments, ready);
+            return segments;
+        }
+    }
+
+    /// Flush whatever was held back. An unclosed `<think>` stays thinking.
+    pub fn finish(&mut self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let rest = std::mem::take(&mut self.pending);
+        self.emit(&mut segments, rest);
+        segments
+    }
+
+    fn emit(&self, segments: &mut Vec<Segment>, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        segments.push(if self.in_thinking {
+            Segment::Thinking(text)
+        } else {
+            Segment::Text(text)
+        });
+    }
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of `tag`.
+fn partial_tag_len(text: &str, tag: &str) -> usize {
+    (1..tag.len())
+        .rev()
+        .find(|&n| text.ends_with(&tag[..n]))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(chunks: &[&str]) -> Vec<Segment> {
+        let mut parser = ThinkParser::new();
+        let mut segments: Vec<Segment> = chunks.iter().flat_map(|c| parser.push(c)).collect();
+        segments.extend(parser.finish());
+        // Merge adjacent runs so the result doesn't depend on chunking.
+        segments.dedup_by(|next, prev| match (prev, next) {
+            (Segment::Thinking(a), Segment::Thinking(b)) | (Segment::Text(a), Segment::Text(b)) => {
+                a.push_str(b);
+                true
+            }
+            _ => false,
+        });
+        segments
+    }
+
+    #[test]
+    fn test_splits_thinking_from_answer() {
+        let expected = vec![
+            Segment::Thinking("Check page 4.".to_string()),
+            Segment::Text("The fee was $1,200.".to_string()),
+        ];
+        assert_eq!(
+            parse(&["<think>Check page 4.</think>The fee was $1,200."]),
+            expected
+        );
+        // Tags split across chunks.
+        assert_eq!(
+            parse(&[
+                "<th",
+                "ink>Check page",
+                " 4.</thi",
+                "nk>The fee was $1,200."
+            ]),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_text_without_tags_passes_through() {
+        assert_eq!(
+            parse(&["a < b and ", "x <t"]),
+            vec![Segment::Text("a < b and x <t".to_string())]
+        );
+        assert_eq!(
+            parse(&["<think>still going"]),
+            vec![Segment::Thinking("still going".to_string())]
+        );
+    }
+}
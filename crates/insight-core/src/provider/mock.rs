@@ -0,0 +1,106 @@
+//! Scripted chat provider for tests and offline agent evaluation.
+//!
+//! [`MockProvider`] replays a fixed list of completions in order, streaming
+//! each as the real providers would, and answers with an empty completion
+//! once the script runs out.
+
+use std::sync::Mutex;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::agent::Message;
+
+use super::{
+    ChatProvider, CompletionResult, Provider, ProviderEvent, SamplingParams, ToolDefinition,
+};
+
+pub struct MockProvider {
+    responses: Mutex<Vec<CompletionResult>>,
+    /// Sampling passed with each request.
+    sampling: Mutex<Vec<SamplingParams>>,
+}
+
+impl MockProvider {
+    pub fn new(responses: Vec<CompletionResult>) -> Self {
+        Self {
+            responses: Mutex::new(responses),
+            sampling: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Sampling of every request so far, oldest first.
+    pub fn sampling(&self) -> Vec<SamplingParams> {
+        self.sampling.lock().unwrap().clone()
+    }
+}
+
+impl Provider for MockProvider {
+    fn provider_name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn model_id(&self) -> &str {
+        "mock-model"
+    }
+}
+
+#[async_trait]
+impl ChatProvider for MockProvider {
+    async fn stream_completion(
+        &self,
+        _messages: &[Message],
+        _tools: &[ToolDefinition],
+        sampling: &SamplingParams,
+        event_tx: mpsc::Sender<ProviderEvent>,
+        _cancel_token: CancellationToken,
+    ) -> Result<CompletionResult> {
+        self.sampling.lock().unwrap().push(*sampling);
+        // Get result while holding lock, then release before await
+        let result = {
+            let mut responses = self.responses.lock().unwrap();
+            if responses.is_empty() {
+                CompletionResult::default()
+            } else {
+                responses.remove(0)
+            }
+        };
+
+        if !result.thinking.is_empty() {
+            let _ = event_tx
+                .send(ProviderEvent::ThinkingDelta(result.thinking.clone()))
+                .await;
+        }
+        // Stream text if present
+        if !result.text.is_empty() {
+            let _ = event_tx
+                .send(ProviderEvent::TextDelta(result.text.clone()))
+                .await;
+        }
+        for call in &result.tool_calls {
+            let _ = event_tx
+                .send(ProviderEvent::ToolCallStart {
+                    id: call.id.clone(),
+                    name: call.name.clone(),
+                })
+                .await;
+            let _ = event_tx
+                .send(ProviderEvent::ToolCallDelta {
+                    id: call.id.clone(),
+                    arguments_delta: call.arguments.to_string(),
+                })
+                .await;
+        }
+        let _ = event_tx
+            .send(ProviderEvent::Finished {
+                usage: result.usage,
+                stop_reason: result.stop_reason,
+            })
+            .await;
+        let _ = event_tx.send(ProviderEvent::Done).await;
+
+        Ok(result)
+    }
+}
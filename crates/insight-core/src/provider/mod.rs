@@ -6,25 +6,41 @@
 //! `unload` that reports no transition); local providers override to report
 //! VRAM kind, the user's coexist choice, and a real load/unload cycle.
 
+pub mod cache;
 pub mod chat;
 pub mod config;
 pub mod embedding;
 pub mod local;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
 pub mod ocr;
+pub mod pricing;
 pub mod remote;
+pub mod retry;
+pub mod schema;
+pub mod verify;
 
 use anyhow::Result;
 use async_trait::async_trait;
 
+pub use cache::{CompletionCache, CompletionCacheStats};
 pub use chat::{
     finalize_tool_calls, get_tool_definitions, ChatProvider, CompletedToolCall, CompletionResult,
-    ProviderEvent, ToolDefinition,
+    ProviderEvent, StopReason, StructuredSchema, TokenUsage, ToolDefinition,
 };
-pub use config::{get_provider_families, ProviderConfig, ProviderFamily, RemoteModelInfo};
-pub use embedding::EmbeddingProvider;
+pub use config::{
+    get_provider_families, ProviderConfig, ProviderFamily, RemoteModelInfo, SamplingParams,
+};
+pub use embedding::{ChunkStrategy, ChunkingConfig, EmbeddingPrompts, EmbeddingProvider};
 pub use local::{LocalChatProvider, LocalEmbeddingProvider, LocalOcrProvider};
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::MockProvider;
 pub use ocr::OcrProvider;
-pub use remote::{AnthropicChatProvider, OpenAIChatProvider};
+pub use remote::{
+    AnthropicChatProvider, GroqChatProvider, MistralChatProvider, OpenAIChatProvider,
+    OpenAICompatibleChatProvider, OpenRouterChatProvider,
+};
+pub use verify::ProviderCheck;
 
 /// Where a provider's weights live at runtime.
 ///
@@ -0,0 +1,75 @@
+//! List prices for remote chat models, used to estimate what a
+//! conversation cost. Local models are free to run and have no entry.
+//!
+//! Prices change; these are estimates for the user's budgeting, not billing.
+
+use super::TokenUsage;
+
+/// `(provider, model ID prefix, USD per million input tokens, USD per
+/// million output tokens)`. The longest matching prefix wins, so dated or
+/// point releases fall back to their family's price.
+const PRICES: &[(&str, &str, f64, f64)] = &[
+    ("anthropic", "claude-opus-4-5", 5.0, 25.0),
+    ("anthropic", "claude-opus-4", 15.0, 75.0),
+    ("anthropic", "claude-sonnet-4", 3.0, 15.0),
+    ("anthropic", "claude-3-7-sonnet", 3.0, 15.0),
+    ("anthropic", "claude-haiku-4", 1.0, 5.0),
+    ("anthropic", "claude-3-5-haiku", 0.8, 4.0),
+    ("openai", "gpt-5", 1.25, 10.0),
+    ("openai", "gpt-5-mini", 0.25, 2.0),
+    ("openai", "gpt-5-nano", 0.05, 0.4),
+    ("openai", "gpt-4.1", 2.0, 8.0),
+    ("openai", "gpt-4.1-mini", 0.4, 1.6),
+    ("openai", "gpt-4.1-nano", 0.1, 0.4),
+    ("openai", "gpt-4o", 2.5, 10.0),
+    ("openai", "gpt-4o-mini", 0.15, 0.6),
+    ("openai", "o4-mini", 1.1, 4.4),
+    ("mistral", "mistral-large", 2.0, 6.0),
+    ("mistral", "mistral-medium", 0.4, 2.0),
+    ("mistral", "mistral-small", 0.1, 0.3),
+    ("mistral", "magistral-medium", 2.0, 5.0),
+    ("mistral", "magistral-small", 0.5, 1.5),
+    ("mistral", "ministral-8b", 0.1, 0.1),
+    ("mistral", "ministral-3b", 0.04, 0.04),
+    ("mistral", "codestral", 0.3, 0.9),
+    ("groq", "llama-3.3-70b", 0.59, 0.79),
+    ("groq", "llama-3.1-8b", 0.05, 0.08),
+    ("groq", "meta-llama/llama-4-scout", 0.11, 0.34),
+    ("groq", "meta-llama/llama-4-maverick", 0.2, 0.6),
+    ("groq", "openai/gpt-oss-120b", 0.15, 0.75),
+    ("groq", "openai/gpt-oss-20b", 0.1, 0.5),
+    ("groq", "qwen/qwen3-32b", 0.29, 0.59),
+    ("groq", "moonshotai/kimi-k2", 1.0, 3.0),
+];
+
+/// Estimated cost in USD of `usage` on `model_id`, or `None` when the
+/// model has no known price (local models, unlisted remote models).
+pub fn estimate_cost(provider_name: &str, model_id: &str, usage: &TokenUsage) -> Option<f64> {
+    let (_, _, input, output) = PRICES
+        .iter()
+        .filter(|(provider, prefix, _, _)| {
+            *provider == provider_name && model_id.starts_with(prefix)
+        })
+        .max_by_key(|(_, prefix, _, _)| prefix.len())?;
+    Some((usage.input_tokens as f64 * input + usage.output_tokens as f64 * output) / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_cost_uses_longest_prefix() {
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+        };
+        assert_eq!(
+            estimate_cost("openai", "gpt-4o-mini-2024-07-18", &usage),
+            Some(0.21)
+        );
+        assert_eq!(estimate_cost("openai", "gpt-4o", &usage), Some(3.5));
+        assert_eq!(estimate_cost("local", "qwen3-8b", &usage), None);
+        assert_eq!(estimate_cost("anthropic", "gpt-4o", &usage), None);
+    }
+}
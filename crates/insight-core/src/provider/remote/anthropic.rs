@@ -10,17 +10,21 @@ use tokio_util::sync::CancellationToken;
 use tracing::debug;
 
 use crate::agent::{render_context_message, ContentBlock, Message, MessageRole};
+use crate::provider::retry::{self, ApiStatusError};
 use crate::provider::{
     finalize_tool_calls, ChatProvider, CompletionResult, Provider, ProviderEvent, RemoteModelInfo,
-    ToolDefinition,
+    SamplingParams, StopReason, TokenUsage, ToolDefinition,
 };
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
 const ANTHROPIC_MODELS_URL: &str = "https://api.anthropic.com/v1/models";
 const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// Reply length when none is configured; the API requires one.
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+/// Smallest reasoning budget the API accepts.
+const MIN_THINKING_BUDGET: u32 = 1024;
 
 pub struct AnthropicChatProvider {
-    client: reqwest::Client,
     api_key: String,
     model: String,
 }
@@ -28,7 +32,6 @@ pub struct AnthropicChatProvider {
 impl AnthropicChatProvider {
     pub fn new(api_key: &str, model: &str) -> Self {
         Self {
-            client: reqwest::Client::new(),
             api_key: api_key.to_string(),
             model: model.to_string(),
         }
@@ -36,7 +39,7 @@ impl AnthropicChatProvider {
 
     /// Fetch available models from Anthropic API.
     pub async fn fetch_models(api_key: &str) -> Result<Vec<RemoteModelInfo>> {
-        let client = reqwest::Client::new();
+        let client = crate::net::http_client()?;
 
         let mut headers = HeaderMap::new();
         headers.insert(
@@ -71,6 +74,7 @@ impl AnthropicChatProvider {
                 id: m.id,
                 name: m.display_name,
                 description: None,
+                context_window: None,
             })
             .collect();
 
@@ -95,10 +99,19 @@ impl Provider for AnthropicChatProvider {
 
 #[async_trait]
 impl ChatProvider for AnthropicChatProvider {
+    fn context_window(&self) -> usize {
+        200_000
+    }
+
+    fn supports_images(&self) -> bool {
+        true
+    }
+
     async fn stream_completion(
         &self,
         messages: &[Message],
         tools: &[ToolDefinition],
+        sampling: &SamplingParams,
         event_tx: mpsc::Sender<ProviderEvent>,
         cancel_token: CancellationToken,
     ) -> Result<CompletionResult> {
@@ -119,13 +132,40 @@ impl ChatProvider for AnthropicChatProvider {
             )
         };
 
-        let request = AnthropicRequest {
-            model: self.model.clone(),
-            max_tokens: 8192,
-            messages: anthropic_messages,
-            system,
-            tools: anthropic_tools,
-            stream: Some(true),
+        let max_tokens = sampling.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+        // Extended thinking counts against max_tokens and doesn't allow
+        // changing temperature or top_p.
+        let request = if sampling.thinking() {
+            let budget_tokens = sampling
+                .thinking_budget
+                .unwrap_or_default()
+                .max(MIN_THINKING_BUDGET);
+            AnthropicRequest {
+                model: self.model.clone(),
+                max_tokens: max_tokens + budget_tokens,
+                temperature: None,
+                top_p: None,
+                thinking: Some(AnthropicThinking {
+                    kind: "enabled",
+                    budget_tokens,
+                }),
+                messages: anthropic_messages,
+                system,
+                tools: anthropic_tools,
+                stream: Some(true),
+            }
+        } else {
+            AnthropicRequest {
+                model: self.model.clone(),
+                max_tokens,
+                temperature: sampling.temperature,
+                top_p: sampling.top_p,
+                thinking: None,
+                messages: anthropic_messages,
+                system,
+                tools: anthropic_tools,
+                stream: Some(true),
+            }
         };
 
         let mut headers = HeaderMap::new();
@@ -139,25 +179,37 @@ impl ChatProvider for AnthropicChatProvider {
             HeaderValue::from_static(ANTHROPIC_VERSION),
         );
 
-        let response = self
-            .client
+        let response = crate::net::http_client()?
             .post(ANTHROPIC_API_URL)
             .headers(headers)
             .json(&request)
             .send()
             .await?;
 
-        if !response.status().is_success() {
-            let error: AnthropicError = response.json().await?;
-            return Err(anyhow::anyhow!(
-                "Anthropic API error: {}",
-                error.error.message
-            ));
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = retry::retry_after(response.headers());
+            let body = response.text().await.unwrap_or_default();
+            let message =
+                serde_json::from_str::<AnthropicError>(&body).map_or(body, |e| e.error.message);
+            return Err(ApiStatusError {
+                service: "Anthropic",
+                status,
+                code: None,
+                message,
+                retry_after,
+            }
+            .into());
         }
 
         let mut stream = response.bytes_stream();
         let mut buffer = String::new();
         let mut text_content = String::new();
+        let mut thinking = String::new();
+        let mut thinking_signature = None;
+        let mut redacted_thinking = Vec::new();
+        let mut usage = TokenUsage::default();
+        let mut stop_reason = StopReason::Stop;
         let mut tool_calls: std::collections::HashMap<usize, (String, String, String)> =
             std::collections::HashMap::new();
 
@@ -194,7 +246,11 @@ impl ChatProvider for AnthropicChatProvider {
                                             .send(ProviderEvent::ToolCallStart { id, name })
                                             .await;
                                     }
-                                    ContentBlockStart::Text { .. } => {}
+                                    ContentBlockStart::RedactedThinking { data } => {
+                                        redacted_thinking.push(data);
+                                    }
+                                    ContentBlockStart::Text { .. }
+                                    | ContentBlockStart::Thinking { .. } => {}
                                 },
                                 StreamEvent::ContentBlockDelta { index, delta } => match delta {
                                     ContentBlockDelta::TextDelta { text } => {
@@ -203,6 +259,15 @@ impl ChatProvider for AnthropicChatProvider {
                                             .await;
                                         text_content.push_str(&text);
                                     }
+                                    ContentBlockDelta::ThinkingDelta { thinking: delta } => {
+                                        let _ = event_tx
+                                            .send(ProviderEvent::ThinkingDelta(delta.clone()))
+                                            .await;
+                                        thinking.push_str(&delta);
+                                    }
+                                    ContentBlockDelta::SignatureDelta { signature } => {
+                                        thinking_signature = Some(signature);
+                                    }
                                     ContentBlockDelta::InputJsonDelta { partial_json } => {
                                         if let Some(tc) = tool_calls.get_mut(&index) {
                                             tc.2.push_str(&partial_json);
@@ -224,6 +289,28 @@ impl ChatProvider for AnthropicChatProvider {
                                             .await;
                                     }
                                 }
+                                // Input tokens arrive with the message start,
+                                // the final output count with the last delta.
+                                StreamEvent::MessageStart { message } => {
+                                    if let Some(n) = message["usage"]["input_tokens"].as_u64() {
+                                        usage.input_tokens = n;
+                                    }
+                                }
+                                StreamEvent::MessageDelta {
+                                    delta,
+                                    usage: delta_usage,
+                                } => {
+                                    if let Some(n) =
+                                        delta_usage.and_then(|u| u["output_tokens"].as_u64())
+                                    {
+                                        usage.output_tokens = n;
+                                    }
+                                    stop_reason = match delta["stop_reason"].as_str() {
+                                        Some("max_tokens") => StopReason::Length,
+                                        Some("tool_use") => StopReason::ToolUse,
+                                        _ => StopReason::Stop,
+                                    };
+                                }
                                 StreamEvent::MessageStop => {
                                     debug!("Message complete");
                                 }
@@ -241,11 +328,19 @@ impl ChatProvider for AnthropicChatProvider {
 
         let completed_tool_calls = finalize_tool_calls(tool_calls.into_values());
 
+        let _ = event_tx
+            .send(ProviderEvent::Finished { usage, stop_reason })
+            .await;
         let _ = event_tx.send(ProviderEvent::Done).await;
 
         Ok(CompletionResult {
             text: text_content,
             tool_calls: completed_tool_calls,
+            usage,
+            stop_reason,
+            thinking,
+            thinking_signature,
+            redacted_thinking,
         })
     }
 }
@@ -316,6 +411,25 @@ fn convert_assistant_blocks(blocks: &[ContentBlock]) -> AnthropicContent {
                     parts.push(AnthropicContentPart::Text { text: text.clone() });
                 }
             }
+            // The API checks the signature; unsigned reasoning came from
+            // another provider and is dropped.
+            ContentBlock::Thinking {
+                thinking,
+                signature,
+                redacted,
+            } => {
+                if let Some(signature) = signature {
+                    parts.push(AnthropicContentPart::Thinking {
+                        thinking: thinking.clone(),
+                        signature: signature.clone(),
+                    });
+                }
+                parts.extend(
+                    redacted
+                        .iter()
+                        .map(|data| AnthropicContentPart::RedactedThinking { data: data.clone() }),
+                );
+            }
             ContentBlock::ToolUse {
                 id,
                 name,
@@ -327,7 +441,10 @@ fn convert_assistant_blocks(blocks: &[ContentBlock]) -> AnthropicContent {
                     input: arguments.clone(),
                 });
             }
-            ContentBlock::ToolResult { .. } => {}
+            ContentBlock::ToolResult { .. }
+            | ContentBlock::Citation { .. }
+            | ContentBlock::SourceFile { .. }
+            | ContentBlock::PageImage { .. } => {}
         }
     }
 
@@ -350,7 +467,11 @@ fn convert_user_blocks(blocks: &[ContentBlock]) -> AnthropicContent {
                     parts.push(AnthropicContentPart::Text { text: text.clone() });
                 }
             }
-            ContentBlock::ToolUse { .. } => {}
+            ContentBlock::ToolUse { .. }
+            | ContentBlock::Thinking { .. }
+            | ContentBlock::Citation { .. }
+            | ContentBlock::SourceFile { .. }
+            | ContentBlock::PageImage { .. } => {}
             ContentBlock::ToolResult {
                 tool_use_id,
                 content,
@@ -374,24 +495,35 @@ fn convert_user_blocks(blocks: &[ContentBlock]) -> AnthropicContent {
     AnthropicContent::Parts(parts)
 }
 
+/// The tool results of an assistant message, followed by the page images
+/// the tools returned. The API wants the results first.
 fn extract_tool_results(blocks: &[ContentBlock]) -> AnthropicContent {
-    let parts: Vec<AnthropicContentPart> = blocks
-        .iter()
-        .filter_map(|block| match block {
-            ContentBlock::ToolResult {
-                tool_use_id,
-                content,
-                is_error,
-            } => Some(AnthropicContentPart::ToolResult {
-                tool_use_id: tool_use_id.clone(),
-                content: content.clone(),
-                is_error: Some(*is_error),
-            }),
-            _ => None,
-        })
-        .collect();
-
-    AnthropicContent::Parts(parts)
+    let results = blocks.iter().filter_map(|block| match block {
+        ContentBlock::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+        } => Some(AnthropicContentPart::ToolResult {
+            tool_use_id: tool_use_id.clone(),
+            content: content.clone(),
+            is_error: Some(*is_error),
+        }),
+        _ => None,
+    });
+    let images = blocks.iter().filter_map(|block| match block {
+        ContentBlock::PageImage {
+            media_type, data, ..
+        } => Some(AnthropicContentPart::Image {
+            source: AnthropicImageSource {
+                kind: "base64",
+                media_type: media_type.clone(),
+                data: data.clone(),
+            },
+        }),
+        _ => None,
+    });
+
+    AnthropicContent::Parts(results.chain(images).collect())
 }
 
 // ============================================================================
@@ -402,6 +534,12 @@ fn extract_tool_results(blocks: &[ContentBlock]) -> AnthropicContent {
 struct AnthropicRequest {
     model: String,
     max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<AnthropicThinking>,
     messages: Vec<AnthropicMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     system: Option<String>,
@@ -411,6 +549,13 @@ struct AnthropicRequest {
     stream: Option<bool>,
 }
 
+#[derive(Debug, Serialize)]
+struct AnthropicThinking {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    budget_tokens: u32,
+}
+
 #[derive(Debug, Serialize)]
 struct AnthropicMessage {
     role: String,
@@ -430,6 +575,13 @@ enum AnthropicContentPart {
     Text {
         text: String,
     },
+    Thinking {
+        thinking: String,
+        signature: String,
+    },
+    RedactedThinking {
+        data: String,
+    },
     ToolUse {
         id: String,
         name: String,
@@ -441,6 +593,17 @@ enum AnthropicContentPart {
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
     },
+    Image {
+        source: AnthropicImageSource,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct AnthropicImageSource {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    media_type: String,
+    data: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -491,6 +654,8 @@ enum StreamEvent {
     },
     MessageDelta {
         delta: serde_json::Value,
+        #[serde(default)]
+        usage: Option<serde_json::Value>,
     },
     MessageStop,
     Ping,
@@ -505,11 +670,57 @@ enum StreamEvent {
 enum ContentBlockStart {
     Text { text: String },
     ToolUse { id: String, name: String },
+    Thinking { thinking: String },
+    RedactedThinking { data: String },
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum ContentBlockDelta {
     TextDelta { text: String },
+    ThinkingDelta { thinking: String },
+    SignatureDelta { signature: String },
     InputJsonDelta { partial_json: String },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacted_thinking_is_sent_back() {
+        let blocks = vec![
+            ContentBlock::Thinking {
+                thinking: "Check the lease.".to_string(),
+                signature: Some("sig".to_string()),
+                redacted: vec!["encrypted".to_string()],
+            },
+            ContentBlock::Text {
+                text: "The rent is $1,200.".to_string(),
+            },
+        ];
+        let AnthropicContent::Parts(parts) = convert_assistant_blocks(&blocks) else {
+            panic!("expected content parts");
+        };
+        let json = serde_json::to_value(&parts).unwrap();
+        assert_eq!(json[0]["type"], "thinking");
+        assert_eq!(
+            json[1],
+            serde_json::json!({"type": "redacted_thinking", "data": "encrypted"})
+        );
+        assert_eq!(json[2]["type"], "text");
+
+        let event: StreamEvent = serde_json::from_str(
+            r#"{"type":"content_block_start","index":0,
+                "content_block":{"type":"redacted_thinking","data":"encrypted"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            event,
+            StreamEvent::ContentBlockStart {
+                content_block: ContentBlockStart::RedactedThinking { .. },
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,772 @@
+//! Shared client for OpenAI-compatible Chat Completions APIs.
+//!
+//! Many hosted services (OpenRouter, Mistral, Groq) speak the same
+//! `/chat/completions` dialect: OpenAI's message and tool shapes, streamed
+//! as server-sent events. [`ChatCompletionsApi`] holds the HTTP side and
+//! [`ChatRequest`] the request body; a provider adds its own base URL,
+//! headers and extra request fields, and keeps its own model listing,
+//! which is where the services differ.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::agent::{render_context_message, ContentBlock, Message, MessageRole};
+use crate::provider::retry::{retry_after, ApiStatusError};
+use crate::provider::{
+    finalize_tool_calls, CompletionResult, ProviderEvent, SamplingParams, StopReason, TokenUsage,
+    ToolDefinition,
+};
+
+/// An authenticated Chat Completions endpoint. Requests go through
+/// [`crate::net::http_client`].
+pub(crate) struct ChatCompletionsApi {
+    /// Name used in error messages, e.g. "OpenRouter".
+    service: &'static str,
+    /// URL the `/chat/completions` and `/models` paths are relative to.
+    base_url: String,
+    /// Empty for servers that don't check keys.
+    api_key: String,
+    headers: HeaderMap,
+}
+
+impl ChatCompletionsApi {
+    pub fn new(service: &'static str, base_url: &str, api_key: &str) -> Self {
+        Self {
+            service,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Send `value` as the `name` header with every request.
+    pub fn with_header(mut self, name: &'static str, value: &'static str) -> Self {
+        self.headers.insert(
+            HeaderName::from_static(name),
+            HeaderValue::from_static(value),
+        );
+        self
+    }
+
+    /// A request to `path` with the key and extra headers set.
+    fn request(&self, method: reqwest::Method, path: &str) -> Result<reqwest::RequestBuilder> {
+        let builder = crate::net::http_client()?
+            .request(method, format!("{}{}", self.base_url, path))
+            .headers(self.headers.clone());
+        Ok(if self.api_key.is_empty() {
+            builder
+        } else {
+            builder.bearer_auth(&self.api_key)
+        })
+    }
+
+    /// GET `path` and decode the JSON reply.
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let response = self.request(reqwest::Method::GET, path)?.send().await?;
+        let response = self.check(response).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Stream `request`, forwarding text, reasoning and tool calls to
+    /// `event_tx` as they arrive.
+    pub async fn stream(
+        &self,
+        request: &ChatRequest,
+        event_tx: mpsc::Sender<ProviderEvent>,
+        cancel_token: CancellationToken,
+    ) -> Result<CompletionResult> {
+        let response = self
+            .request(reqwest::Method::POST, "/chat/completions")?
+            .json(request)
+            .send()
+            .await?;
+        let response = self.check(response).await?;
+
+        let mut stream = response.bytes_stream();
+        let mut buffer = String::new();
+        let mut state = StreamState::default();
+
+        'read: while let Some(chunk_result) = stream.next().await {
+            if cancel_token.is_cancelled() {
+                break;
+            }
+
+            let chunk = chunk_result?;
+            buffer.push_str(&String::from_utf8_lossy(&chunk).replace('\r', ""));
+
+            while let Some(event_end) = buffer.find("\n\n") {
+                let event_data = buffer[..event_end].to_string();
+                buffer = buffer[event_end + 2..].to_string();
+
+                for line in event_data.lines() {
+                    // Lines starting with ':' are keep-alive comments.
+                    let Some(data) = line.strip_prefix("data:") else {
+                        continue;
+                    };
+                    let data = data.trim();
+                    if data == "[DONE]" {
+                        break 'read;
+                    }
+                    let Ok(chunk) = serde_json::from_str::<ChatChunk>(data) else {
+                        debug!(
+                            service = self.service,
+                            data, "Skipping unparsed stream event"
+                        );
+                        continue;
+                    };
+                    match state.apply(chunk) {
+                        Ok(events) => {
+                            for event in events {
+                                let _ = event_tx.send(event).await;
+                            }
+                        }
+                        Err(message) => {
+                            let _ = event_tx.send(ProviderEvent::Error(message.clone())).await;
+                            bail!("{} error: {}", self.service, message);
+                        }
+                    }
+                }
+            }
+        }
+
+        let (events, result) = state.finish();
+        for event in events {
+            let _ = event_tx.send(event).await;
+        }
+        let _ = event_tx.send(ProviderEvent::Done).await;
+        Ok(result)
+    }
+
+    /// Turn an error status into an error carrying the service's message.
+    async fn check(&self, response: reqwest::Response) -> Result<reqwest::Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let retry_after = retry_after(response.headers());
+        let body = response.text().await.unwrap_or_default();
+        let (code, message) = match serde_json::from_str::<ApiError>(&body) {
+            Ok(e) => (e.error.code(), e.error.message),
+            Err(_) => (None, body),
+        };
+        Err(ApiStatusError {
+            service: self.service,
+            status,
+            code,
+            message: message.trim().to_string(),
+            retry_after,
+        }
+        .into())
+    }
+}
+
+/// A streaming Chat Completions request.
+#[derive(Debug, Serialize)]
+pub(crate) struct ChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ChatTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    /// Service-specific fields, e.g. OpenRouter's `reasoning`.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ChatRequest {
+    pub fn new(
+        model: &str,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        sampling: &SamplingParams,
+    ) -> Self {
+        Self {
+            model: model.to_string(),
+            messages: convert_messages(messages),
+            tools: tools
+                .iter()
+                .map(|t| ChatTool {
+                    kind: "function",
+                    function: ChatFunction {
+                        name: t.name.clone(),
+                        description: t.description.clone(),
+                        parameters: t.parameters.clone(),
+                    },
+                })
+                .collect(),
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            max_tokens: sampling.max_tokens,
+            stream: true,
+            stream_options: Some(StreamOptions {
+                include_usage: true,
+            }),
+            extra: serde_json::Map::new(),
+        }
+    }
+
+    /// Leave out `stream_options`, for services that always report usage
+    /// and reject fields they don't know.
+    pub fn without_stream_options(mut self) -> Self {
+        self.stream_options = None;
+        self
+    }
+}
+
+/// Convert messages to Chat Completions form. Tool results become `tool`
+/// messages, which must follow the assistant message that called them.
+fn convert_messages(messages: &[Message]) -> Vec<ChatMessage> {
+    let mut result = Vec::new();
+
+    for msg in messages {
+        match msg.role {
+            MessageRole::System => result.push(ChatMessage::text("system", msg.text())),
+            MessageRole::Context => {
+                // Not every service accepts system messages after the
+                // first, so breadcrumbs ride along as tagged user notes.
+                result.push(ChatMessage::text(
+                    "user",
+                    render_context_message(&msg.text()),
+                ));
+            }
+            MessageRole::User => {
+                for block in &msg.content {
+                    match block {
+                        ContentBlock::Text { text } if !text.is_empty() => {
+                            result.push(ChatMessage::text("user", text.clone()));
+                        }
+                        ContentBlock::ToolResult { .. } => result.extend(tool_message(block)),
+                        _ => {}
+                    }
+                }
+            }
+            MessageRole::Assistant => {
+                let text = msg.text();
+                let tool_calls: Vec<ChatToolCall> = msg
+                    .content
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::ToolUse {
+                            id,
+                            name,
+                            arguments,
+                        } => Some(ChatToolCall {
+                            id: id.clone(),
+                            kind: "function",
+                            function: ChatFunctionCall {
+                                name: name.clone(),
+                                arguments: arguments.to_string(),
+                            },
+                        }),
+                        _ => None,
+                    })
+                    .collect();
+                if !text.is_empty() || !tool_calls.is_empty() {
+                    result.push(ChatMessage {
+                        role: "assistant",
+                        content: (!text.is_empty()).then_some(text),
+                        tool_calls,
+                        tool_call_id: None,
+                    });
+                }
+                result.extend(msg.content.iter().filter_map(tool_message));
+            }
+        }
+    }
+
+    result
+}
+
+/// A `tool` message for a tool result block. The format has no error
+/// flag, so failures say so in the content.
+fn tool_message(block: &ContentBlock) -> Option<ChatMessage> {
+    let ContentBlock::ToolResult {
+        tool_use_id,
+        content,
+        is_error,
+    } = block
+    else {
+        return None;
+    };
+    Some(ChatMessage {
+        role: "tool",
+        content: Some(if *is_error {
+            format!("Error: {}", content)
+        } else {
+            content.clone()
+        }),
+        tool_calls: Vec::new(),
+        tool_call_id: Some(tool_use_id.clone()),
+    })
+}
+
+/// A tool call being streamed.
+#[derive(Debug, Default)]
+struct PendingCall {
+    id: String,
+    name: String,
+    arguments: String,
+    started: bool,
+}
+
+/// What a stream has produced so far.
+#[derive(Debug, Default)]
+struct StreamState {
+    text: String,
+    thinking: String,
+    usage: TokenUsage,
+    stop_reason: StopReason,
+    /// Keyed by the call's index in the reply, so they finish in order.
+    tool_calls: BTreeMap<usize, PendingCall>,
+}
+
+impl StreamState {
+    /// Take in one chunk and return the events it produces, or the
+    /// service's error message.
+    fn apply(&mut self, chunk: ChatChunk) -> Result<Vec<ProviderEvent>, String> {
+        if let Some(error) = chunk.error {
+            return Err(error.message);
+        }
+        if let Some(usage) = chunk.usage.or(chunk.x_groq.and_then(|x| x.usage)) {
+            self.usage = TokenUsage {
+                input_tokens: usage.prompt_tokens,
+                output_tokens: usage.completion_tokens,
+            };
+        }
+
+        let mut events = Vec::new();
+        for choice in chunk.choices {
+            if let Some(reason) = choice.finish_reason {
+                self.stop_reason = StopReason::from_finish_reason(&reason);
+            }
+            let delta = choice.delta;
+            let mut content = String::new();
+            let mut reasoning = delta.reasoning.unwrap_or_default();
+            if let Some(delta_content) = delta.content {
+                delta_content.split_into(&mut content, &mut reasoning);
+            }
+            if !reasoning.is_empty() {
+                self.thinking.push_str(&reasoning);
+                events.push(ProviderEvent::ThinkingDelta(reasoning));
+            }
+            if !content.is_empty() {
+                self.text.push_str(&content);
+                events.push(ProviderEvent::TextDelta(content));
+            }
+            // Some services send whole calls without an index; those are
+            // numbered after the calls seen so far.
+            let next_index = self.tool_calls.len();
+            for (position, call) in delta.tool_calls.into_iter().enumerate() {
+                let index = call.index.unwrap_or(next_index + position);
+                let pending = self.tool_calls.entry(index).or_default();
+                if let Some(id) = call.id {
+                    pending.id = id;
+                }
+                let (name, arguments) = match call.function {
+                    Some(function) => (function.name, function.arguments),
+                    None => (None, None),
+                };
+                if let Some(name) = name {
+                    pending.name.push_str(&name);
+                }
+                if !pending.started && !pending.id.is_empty() && !pending.name.is_empty() {
+                    pending.started = true;
+                    events.push(ProviderEvent::ToolCallStart {
+                        id: pending.id.clone(),
+                        name: pending.name.clone(),
+                    });
+                }
+                if let Some(arguments) = arguments.filter(|a| !a.is_empty()) {
+                    pending.arguments.push_str(&arguments);
+                    if pending.started {
+                        events.push(ProviderEvent::ToolCallDelta {
+                            id: pending.id.clone(),
+                            arguments_delta: arguments,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    /// Close the open tool calls and return the completion.
+    fn finish(self) -> (Vec<ProviderEvent>, CompletionResult) {
+        let mut events: Vec<ProviderEvent> = self
+            .tool_calls
+            .values()
+            .filter(|call| call.started)
+            .map(|call| ProviderEvent::ToolCallComplete {
+                id: call.id.clone(),
+            })
+            .collect();
+        let tool_calls = finalize_tool_calls(
+            self.tool_calls
+                .into_values()
+                .filter(|call| !call.name.is_empty())
+                .map(|call| (call.id, call.name, call.arguments)),
+        );
+        // Some servers say "stop" after tool calls.
+        let stop_reason = match self.stop_reason {
+            StopReason::Stop if !tool_calls.is_empty() => StopReason::ToolUse,
+            reason => reason,
+        };
+        events.push(ProviderEvent::Finished {
+            usage: self.usage,
+            stop_reason,
+        });
+        let result = CompletionResult {
+            text: self.text,
+            thinking: self.thinking,
+            tool_calls,
+            usage: self.usage,
+            stop_reason,
+            ..CompletionResult::default()
+        };
+        (events, result)
+    }
+}
+
+// ============================================================================
+// API Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatMessage {
+    role: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ChatToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    fn text(role: &'static str, text: String) -> Self {
+        Self {
+            role,
+            content: Some(text),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ChatTool {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: ChatFunction,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatFunction {
+    name: String,
+    description: String,
+    parameters: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: ChatFunctionCall,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatFunctionCall {
+    name: String,
+    arguments: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    error: ApiErrorDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrorDetail {
+    message: String,
+    /// A string for most services, a number for some.
+    #[serde(default)]
+    code: Option<serde_json::Value>,
+}
+
+impl ApiErrorDetail {
+    fn code(&self) -> Option<String> {
+        match self.code.as_ref()? {
+            serde_json::Value::String(code) => Some(code.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChunk {
+    #[serde(default)]
+    choices: Vec<ChunkChoice>,
+    #[serde(default)]
+    usage: Option<ChunkUsage>,
+    /// Groq reports usage here rather than in `usage`.
+    #[serde(default)]
+    x_groq: Option<GroqExtension>,
+    #[serde(default)]
+    error: Option<ApiErrorDetail>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GroqExtension {
+    #[serde(default)]
+    usage: Option<ChunkUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkChoice {
+    #[serde(default)]
+    delta: ChunkDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ChunkDelta {
+    #[serde(default)]
+    content: Option<DeltaContent>,
+    /// OpenRouter calls it `reasoning`, others `reasoning_content`.
+    #[serde(default, alias = "reasoning_content")]
+    reasoning: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ToolCallDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum DeltaContent {
+    Text(String),
+    /// Typed parts, as Mistral's reasoning models send them.
+    Parts(Vec<ContentPart>),
+}
+
+impl DeltaContent {
+    /// Append the answer text to `text` and any reasoning to `thinking`.
+    fn split_into(self, text: &mut String, thinking: &mut String) {
+        match self {
+            DeltaContent::Text(t) => text.push_str(&t),
+            DeltaContent::Parts(parts) => {
+                for part in parts {
+                    match part {
+                        ContentPart::Text { text: t } => text.push_str(&t),
+                        ContentPart::Thinking { thinking: inner } => {
+                            DeltaContent::Parts(inner).split_into(thinking, &mut String::new())
+                        }
+                        ContentPart::Other => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentPart {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: Vec<ContentPart>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCallDelta {
+    #[serde(default)]
+    index: Option<usize>,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: Option<FunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionDelta {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(state: &mut StreamState, data: &str) -> Vec<ProviderEvent> {
+        state.apply(serde_json::from_str(data).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_stream_accumulates_text_and_tool_calls() {
+        let mut state = StreamState::default();
+        apply(
+            &mut state,
+            r#"{"choices":[{"delta":{"role":"assistant","reasoning":"Need a search."}}]}"#,
+        );
+        apply(
+            &mut state,
+            r#"{"choices":[{"delta":{"content":"Let me look."}}]}"#,
+        );
+        let events = apply(
+            &mut state,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"search","arguments":""}}]}}]}"#,
+        );
+        assert!(matches!(
+            &events[..],
+            [ProviderEvent::ToolCallStart { id, name }] if id == "call_1" && name == "search"
+        ));
+        apply(
+            &mut state,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"query\":"}}]}}]}"#,
+        );
+        apply(
+            &mut state,
+            r#"{"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"lease\"}"}}]}}]}"#,
+        );
+        apply(
+            &mut state,
+            r#"{"choices":[],"usage":{"prompt_tokens":120,"completion_tokens":30,"total_tokens":150}}"#,
+        );
+
+        let (events, result) = state.finish();
+        assert!(matches!(
+            &events[..],
+            [ProviderEvent::ToolCallComplete { id }, ProviderEvent::Finished { .. }]
+                if id == "call_1"
+        ));
+        assert_eq!(result.text, "Let me look.");
+        assert_eq!(result.thinking, "Need a search.");
+        assert_eq!(result.tool_calls.len(), 1);
+        assert_eq!(
+            result.tool_calls[0].arguments,
+            serde_json::json!({"query": "lease"})
+        );
+        assert_eq!(
+            result.usage,
+            TokenUsage {
+                input_tokens: 120,
+                output_tokens: 30
+            }
+        );
+    }
+
+    #[test]
+    fn test_stream_splits_typed_content_parts() {
+        let mut state = StreamState::default();
+        let events = apply(
+            &mut state,
+            r#"{"choices":[{"delta":{"content":[{"type":"thinking","thinking":[{"type":"text","text":"Check the dates."}]},{"type":"text","text":"Signed in May."}]}}]}"#,
+        );
+        assert!(matches!(
+            &events[..],
+            [ProviderEvent::ThinkingDelta(t), ProviderEvent::TextDelta(a)]
+                if t == "Check the dates." && a == "Signed in May."
+        ));
+    }
+
+    #[test]
+    fn test_stream_reports_truncation() {
+        let mut state = StreamState::default();
+        apply(
+            &mut state,
+            r#"{"choices":[{"delta":{"content":"The lease runs until"},"finish_reason":"length"}]}"#,
+        );
+        let (events, result) = state.finish();
+        assert!(matches!(
+            &events[..],
+            [ProviderEvent::Finished {
+                stop_reason: StopReason::Length,
+                ..
+            }]
+        ));
+        assert_eq!(result.stop_reason, StopReason::Length);
+    }
+
+    #[test]
+    fn test_stream_error_chunk() {
+        let mut state = StreamState::default();
+        let chunk =
+            serde_json::from_str(r#"{"error":{"message":"Rate limited","code":429}}"#).unwrap();
+        assert_eq!(state.apply(chunk).unwrap_err(), "Rate limited");
+    }
+
+    #[test]
+    fn test_convert_messages_orders_tool_results() {
+        let messages = vec![
+            Message {
+                role: MessageRole::System,
+                content: vec![ContentBlock::Text {
+                    text: "Be brief.".to_string(),
+                }],
+            },
+            Message {
+                role: MessageRole::Assistant,
+                content: vec![
+                    ContentBlock::ToolUse {
+                        id: "call_1".to_string(),
+                        name: "search".to_string(),
+                        arguments: serde_json::json!({"query": "lease"}),
+                    },
+                    ContentBlock::ToolResult {
+                        tool_use_id: "call_1".to_string(),
+                        content: "No matching passages found.".to_string(),
+                        is_error: false,
+                    },
+                ],
+            },
+        ];
+        let request = ChatRequest::new("model", &messages, &[], &SamplingParams::default());
+        let json = serde_json::to_value(&request).unwrap();
+        let messages = json["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["role"], "assistant");
+        assert!(messages[1].get("content").is_none());
+        assert_eq!(
+            messages[1]["tool_calls"][0]["function"]["arguments"],
+            r#"{"query":"lease"}"#
+        );
+        assert_eq!(messages[2]["role"], "tool");
+        assert_eq!(messages[2]["tool_call_id"], "call_1");
+        assert!(json.get("tools").is_none());
+        assert_eq!(json["stream_options"]["include_usage"], true);
+    }
+}
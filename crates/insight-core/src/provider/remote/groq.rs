@@ -0,0 +1,174 @@
+//! Groq chat provider over Groq's OpenAI-compatible Chat Completions API.
+//!
+//! Groq rejects a completion whose tool call didn't parse
+//! (`tool_use_failed`) instead of returning it; that is retried once.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::chat_completions::{ChatCompletionsApi, ChatRequest};
+use crate::agent::Message;
+use crate::provider::retry::ApiStatusError;
+use crate::provider::{
+    ChatProvider, CompletionResult, Provider, ProviderEvent, RemoteModelInfo, SamplingParams,
+    ToolDefinition,
+};
+
+const GROQ_API_URL: &str = "https://api.groq.com/openai/v1";
+/// Context window assumed when the model listing didn't give one.
+const DEFAULT_CONTEXT_WINDOW: usize = 128_000;
+/// Sends of a request whose tool call Groq couldn't parse.
+const TOOL_USE_ATTEMPTS: usize = 2;
+
+/// Listed models that aren't chat models: speech, moderation and Groq's
+/// own agent systems, which don't take custom tools.
+const NON_CHAT_MODELS: &[&str] = &["whisper", "tts", "orpheus", "playai", "guard", "compound"];
+
+pub struct GroqChatProvider {
+    api: ChatCompletionsApi,
+    model: String,
+    context_window: usize,
+}
+
+impl GroqChatProvider {
+    pub fn new(api_key: &str, model: &str, context_window: Option<usize>) -> Self {
+        Self {
+            api: api(api_key),
+            model: model.to_string(),
+            context_window: context_window.unwrap_or(DEFAULT_CONTEXT_WINDOW),
+        }
+    }
+
+    /// Fetch the active chat models, sorted by ID. The listing needs a
+    /// valid key, so this also verifies it.
+    pub async fn fetch_models(api_key: &str) -> Result<Vec<RemoteModelInfo>> {
+        let response: ModelsResponse = api(api_key)
+            .get("/models")
+            .await
+            .context("Failed to list models")?;
+
+        let mut models: Vec<RemoteModelInfo> = response
+            .data
+            .into_iter()
+            .filter(|m| m.active && !NON_CHAT_MODELS.iter().any(|n| m.id.contains(n)))
+            .map(|m| RemoteModelInfo {
+                name: m.id.clone(),
+                description: m.owned_by.map(|owner| format!("by {}", owner)),
+                id: m.id,
+                context_window: m.context_window,
+            })
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(models)
+    }
+}
+
+fn api(api_key: &str) -> ChatCompletionsApi {
+    ChatCompletionsApi::new("Groq", GROQ_API_URL, api_key)
+}
+
+/// Request fields that turn `model`'s reasoning on or off, for the
+/// reasoning models Groq serves. Left to Groq's default, Qwen and
+/// DeepSeek put their reasoning inline in `<think>` tags.
+fn reasoning_fields(model: &str, thinking: bool) -> Option<(&'static str, serde_json::Value)> {
+    if model.contains("gpt-oss") {
+        Some(("include_reasoning", thinking.into()))
+    } else if model.contains("qwen3") || model.contains("deepseek-r1") {
+        let format = if thinking { "parsed" } else { "hidden" };
+        Some(("reasoning_format", format.into()))
+    } else {
+        None
+    }
+}
+
+impl Provider for GroqChatProvider {
+    fn provider_name(&self) -> &'static str {
+        "groq"
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+}
+
+#[async_trait]
+impl ChatProvider for GroqChatProvider {
+    fn context_window(&self) -> usize {
+        self.context_window
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        sampling: &SamplingParams,
+        event_tx: mpsc::Sender<ProviderEvent>,
+        cancel_token: CancellationToken,
+    ) -> Result<CompletionResult> {
+        let mut request = ChatRequest::new(&self.model, messages, tools, sampling);
+        if let Some((field, value)) = reasoning_fields(&self.model, sampling.thinking()) {
+            request.extra.insert(field.to_string(), value);
+        }
+
+        let mut attempt = 1;
+        loop {
+            let result = self
+                .api
+                .stream(&request, event_tx.clone(), cancel_token.clone())
+                .await;
+            // The rejection comes before anything streams, so resending
+            // can't repeat output.
+            let tool_use_failed = result.as_ref().err().is_some_and(|e| {
+                e.downcast_ref::<ApiStatusError>()
+                    .is_some_and(|f| f.code.as_deref() == Some("tool_use_failed"))
+            });
+            if !tool_use_failed || attempt == TOOL_USE_ATTEMPTS {
+                return result;
+            }
+            tracing::debug!(model = %self.model, "Groq rejected a malformed tool call, retrying");
+            attempt += 1;
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelInfo {
+    id: String,
+    #[serde(default)]
+    owned_by: Option<String>,
+    #[serde(default = "default_active")]
+    active: bool,
+    #[serde(default)]
+    context_window: Option<usize>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reasoning_fields_by_model() {
+        assert_eq!(
+            reasoning_fields("qwen/qwen3-32b", false),
+            Some(("reasoning_format", serde_json::json!("hidden")))
+        );
+        assert_eq!(
+            reasoning_fields("openai/gpt-oss-120b", true),
+            Some(("include_reasoning", serde_json::json!(true)))
+        );
+        assert_eq!(reasoning_fields("llama-3.3-70b-versatile", true), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Mistral chat provider over Mistral's Chat Completions API, hosted in
+//! the EU.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::chat_completions::{ChatCompletionsApi, ChatRequest};
+use crate::agent::Message;
+use crate::provider::{
+    ChatProvider, CompletionResult, Provider, ProviderEvent, RemoteModelInfo, SamplingParams,
+    ToolDefinition,
+};
+
+const MISTRAL_API_URL: &str = "https://api.mistral.ai/v1";
+/// Context window assumed when the model listing didn't give one.
+const DEFAULT_CONTEXT_WINDOW: usize = 128_000;
+
+pub struct MistralChatProvider {
+    api: ChatCompletionsApi,
+    model: String,
+    context_window: usize,
+}
+
+impl MistralChatProvider {
+    pub fn new(api_key: &str, model: &str, context_window: Option<usize>) -> Self {
+        Self {
+            api: ChatCompletionsApi::new("Mistral", MISTRAL_API_URL, api_key),
+            model: model.to_string(),
+            context_window: context_window.unwrap_or(DEFAULT_CONTEXT_WINDOW),
+        }
+    }
+
+    /// Fetch the chat models that can call tools, sorted by ID. The
+    /// listing needs a valid key, so this also verifies it.
+    pub async fn fetch_models(api_key: &str) -> Result<Vec<RemoteModelInfo>> {
+        let api = ChatCompletionsApi::new("Mistral", MISTRAL_API_URL, api_key);
+        let response: ModelsResponse = api.get("/models").await.context("Failed to list models")?;
+
+        let mut models: Vec<RemoteModelInfo> = response
+            .data
+            .into_iter()
+            .filter(|m| {
+                m.capabilities.completion_chat
+                    && m.capabilities.function_calling
+                    && m.deprecation.is_none()
+            })
+            .map(|m| RemoteModelInfo {
+                name: m.name.unwrap_or_else(|| m.id.clone()),
+                id: m.id,
+                description: m.description.filter(|d| !d.is_empty()),
+                context_window: m.max_context_length,
+            })
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        // The listing repeats a model under each of its aliases.
+        models.dedup_by(|a, b| a.id == b.id);
+
+        Ok(models)
+    }
+}
+
+impl Provider for MistralChatProvider {
+    fn provider_name(&self) -> &'static str {
+        "mistral"
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+}
+
+#[async_trait]
+impl ChatProvider for MistralChatProvider {
+    fn context_window(&self) -> usize {
+        self.context_window
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        sampling: &SamplingParams,
+        event_tx: mpsc::Sender<ProviderEvent>,
+        cancel_token: CancellationToken,
+    ) -> Result<CompletionResult> {
+        // Mistral reports usage on the last chunk unasked, and its
+        // reasoning models think without being told to.
+        let request =
+            ChatRequest::new(&self.model, messages, tools, sampling).without_stream_options();
+        self.api.stream(&request, event_tx, cancel_token).await
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelInfo {
+    id: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    max_context_length: Option<usize>,
+    #[serde(default)]
+    capabilities: Capabilities,
+    #[serde(default)]
+    deprecation: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Capabilities {
+    #[serde(default)]
+    completion_chat: bool,
+    #[serde(default)]
+    function_calling: bool,
+}
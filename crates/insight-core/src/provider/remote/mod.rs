@@ -5,7 +5,16 @@
 //! contribute to local eviction decisions.
 
 pub mod anthropic;
+mod chat_completions;
+pub mod groq;
+pub mod mistral;
 pub mod openai;
+pub mod openai_compatible;
+pub mod openrouter;
 
 pub use anthropic::AnthropicChatProvider;
+pub use groq::GroqChatProvider;
+pub use mistral::MistralChatProvider;
 pub use openai::OpenAIChatProvider;
+pub use openai_compatible::OpenAICompatibleChatProvider;
+pub use openrouter::OpenRouterChatProvider;
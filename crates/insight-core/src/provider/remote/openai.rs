@@ -5,8 +5,8 @@ use async_openai::{
     config::OpenAIConfig,
     types::responses::{
         CreateResponse, EasyInputContent, EasyInputMessage, FunctionCallOutput,
-        FunctionCallOutputItemParam, FunctionTool, FunctionToolCall, InputItem, InputParam, Item,
-        MessageType, OutputItem, Role, Tool,
+        FunctionCallOutputItemParam, FunctionTool, FunctionToolCall, ImageDetail, InputContent,
+        InputImageContent, InputItem, InputParam, Item, MessageType, OutputItem, Role, Tool,
     },
     Client,
 };
@@ -17,28 +17,27 @@ use tokio_util::sync::CancellationToken;
 use tracing::debug;
 
 use crate::agent::{render_context_message, ContentBlock, Message, MessageRole};
+use crate::provider::retry::ApiStatusError;
 use crate::provider::{
     finalize_tool_calls, ChatProvider, CompletionResult, Provider, ProviderEvent, RemoteModelInfo,
-    ToolDefinition,
+    SamplingParams, StopReason, TokenUsage, ToolDefinition,
 };
 
 pub struct OpenAIChatProvider {
-    client: Client<OpenAIConfig>,
+    config: OpenAIConfig,
     model: String,
 }
 
 impl OpenAIChatProvider {
     pub fn new(api_key: &str, model: &str) -> Self {
-        let config = OpenAIConfig::new().with_api_key(api_key);
         Self {
-            client: Client::with_config(config),
+            config: OpenAIConfig::new().with_api_key(api_key),
             model: model.to_string(),
         }
     }
 
     pub async fn fetch_models(api_key: &str) -> Result<Vec<RemoteModelInfo>> {
-        let config = OpenAIConfig::new().with_api_key(api_key);
-        let client = Client::with_config(config);
+        let client = client(OpenAIConfig::new().with_api_key(api_key))?;
 
         let models = client
             .models()
@@ -61,6 +60,7 @@ impl OpenAIChatProvider {
                 id: m.id.clone(),
                 name: format_model_name(&m.id),
                 description: None,
+                context_window: None,
             })
             .collect();
 
@@ -68,6 +68,11 @@ impl OpenAIChatProvider {
     }
 }
 
+/// A client for `config` going through [`crate::net::http_client`].
+fn client(config: OpenAIConfig) -> Result<Client<OpenAIConfig>> {
+    Ok(Client::with_config(config).with_http_client(crate::net::http_client()?))
+}
+
 fn format_model_name(id: &str) -> String {
     match id {
         "gpt-5" => "GPT-5".to_string(),
@@ -101,10 +106,19 @@ impl Provider for OpenAIChatProvider {
 
 #[async_trait]
 impl ChatProvider for OpenAIChatProvider {
+    fn context_window(&self) -> usize {
+        128_000
+    }
+
+    fn supports_images(&self) -> bool {
+        true
+    }
+
     async fn stream_completion(
         &self,
         messages: &[Message],
         tools: &[ToolDefinition],
+        sampling: &SamplingParams,
         event_tx: mpsc::Sender<ProviderEvent>,
         cancel_token: CancellationToken,
     ) -> Result<CompletionResult> {
@@ -141,18 +155,22 @@ impl ChatProvider for OpenAIChatProvider {
             input: InputParam::Items(input_items),
             instructions,
             tools: openai_tools,
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            max_output_tokens: sampling.max_tokens,
             stream: Some(true),
             ..Default::default()
         };
 
-        let mut stream = self
-            .client
+        let mut stream = client(self.config.clone())?
             .responses()
             .create_stream(request)
             .await
             .context("Failed to create response stream")?;
 
         let mut text_content = String::new();
+        let mut usage = TokenUsage::default();
+        let mut truncated = false;
         let mut tool_calls: std::collections::HashMap<u32, (String, String, String)> =
             std::collections::HashMap::new();
 
@@ -209,21 +227,50 @@ impl ChatProvider for OpenAIChatProvider {
                     }
                 }
 
-                ResponseStreamEvent::ResponseCompleted(_) => {
+                ResponseStreamEvent::ResponseCompleted(completed) => {
                     debug!("Response completed");
+                    if let Some(reported) = completed.response.usage {
+                        usage = TokenUsage {
+                            input_tokens: reported.input_tokens as u64,
+                            output_tokens: reported.output_tokens as u64,
+                        };
+                    }
+                }
+
+                // Incomplete responses were cut off by max_output_tokens
+                // (or, rarely, the content filter).
+                ResponseStreamEvent::ResponseIncomplete(incomplete) => {
+                    debug!("Response incomplete");
+                    truncated = true;
+                    if let Some(reported) = incomplete.response.usage {
+                        usage = TokenUsage {
+                            input_tokens: reported.input_tokens as u64,
+                            output_tokens: reported.output_tokens as u64,
+                        };
+                    }
                 }
 
                 ResponseStreamEvent::ResponseFailed(failed) => {
-                    let error_msg = format!("Response failed: {:?}", failed.response.error);
-                    let _ = event_tx.send(ProviderEvent::Error(error_msg.clone())).await;
-                    return Err(anyhow::anyhow!(error_msg));
+                    let error = match failed.response.error {
+                        Some(e) => ApiStatusError::from_code("OpenAI", Some(&e.code), e.message),
+                        None => {
+                            ApiStatusError::from_code("OpenAI", None, "Response failed".to_string())
+                        }
+                    };
+                    let _ = event_tx.send(ProviderEvent::Error(error.to_string())).await;
+                    return Err(error.into());
                 }
 
                 ResponseStreamEvent::ResponseError(err) => {
                     let _ = event_tx
                         .send(ProviderEvent::Error(err.message.clone()))
                         .await;
-                    return Err(anyhow::anyhow!("OpenAI error: {}", err.message));
+                    return Err(ApiStatusError::from_code(
+                        "OpenAI",
+                        err.code.as_deref(),
+                        err.message,
+                    )
+                    .into());
                 }
 
                 _ => {}
@@ -231,12 +278,25 @@ impl ChatProvider for OpenAIChatProvider {
         }
 
         let completed_tool_calls = finalize_tool_calls(tool_calls.into_values());
+        let stop_reason = if truncated {
+            StopReason::Length
+        } else if !completed_tool_calls.is_empty() {
+            StopReason::ToolUse
+        } else {
+            StopReason::Stop
+        };
 
+        let _ = event_tx
+            .send(ProviderEvent::Finished { usage, stop_reason })
+            .await;
         let _ = event_tx.send(ProviderEvent::Done).await;
 
         Ok(CompletionResult {
             text: text_content,
             tool_calls: completed_tool_calls,
+            usage,
+            stop_reason,
+            ..CompletionResult::default()
         })
     }
 }
@@ -325,6 +385,29 @@ fn convert_messages(messages: &[Message]) -> (Option<String>, Vec<InputItem>) {
                                 },
                             )));
                         }
+                        // The Responses API takes images in user messages
+                        // only, so a tool's page image follows its result.
+                        ContentBlock::PageImage {
+                            media_type, data, ..
+                        } => {
+                            items.push(InputItem::EasyMessage(EasyInputMessage {
+                                r#type: MessageType::Message,
+                                role: Role::User,
+                                content: EasyInputContent::ContentList(vec![
+                                    InputContent::InputImage(InputImageContent {
+                                        detail: ImageDetail::Auto,
+                                        file_id: None,
+                                        image_url: Some(format!(
+                                            "data:{};base64,{}",
+                                            media_type, data
+                                        )),
+                                    }),
+                                ]),
+                            }));
+                        }
+                        ContentBlock::Thinking { .. }
+                        | ContentBlock::Citation { .. }
+                        | ContentBlock::SourceFile { .. } => {}
                     }
                 }
             }
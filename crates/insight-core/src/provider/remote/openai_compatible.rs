@@ -0,0 +1,114 @@
+//! Chat provider for any server with an OpenAI-style Chat Completions API:
+//! llama.cpp's server, vLLM, LM Studio, Ollama or a self-hosted gateway.
+//!
+//! Such servers differ in what they report. Model listing and context
+//! windows are used when available; otherwise the user names the model
+//! and the context window falls back to the conservative default.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::chat_completions::{ChatCompletionsApi, ChatRequest};
+use crate::agent::Message;
+use crate::provider::chat::DEFAULT_CONTEXT_WINDOW;
+use crate::provider::{
+    ChatProvider, CompletionResult, Provider, ProviderEvent, RemoteModelInfo, SamplingParams,
+    ToolDefinition,
+};
+
+const SERVICE: &str = "OpenAI-compatible endpoint";
+
+pub struct OpenAICompatibleChatProvider {
+    api: ChatCompletionsApi,
+    model: String,
+    context_window: usize,
+}
+
+impl OpenAICompatibleChatProvider {
+    /// A provider for `model` at `base_url`, e.g. `http://localhost:8080/v1`.
+    /// `api_key` may be empty for servers that don't check one.
+    pub fn new(base_url: &str, api_key: &str, model: &str, context_window: Option<usize>) -> Self {
+        Self {
+            api: ChatCompletionsApi::new(SERVICE, base_url, api_key),
+            model: model.to_string(),
+            context_window: context_window.unwrap_or(DEFAULT_CONTEXT_WINDOW),
+        }
+    }
+
+    /// Fetch the endpoint's models, sorted by ID. Fails for endpoints
+    /// without a `/models` listing; the model then has to be named.
+    pub async fn fetch_models(base_url: &str, api_key: &str) -> Result<Vec<RemoteModelInfo>> {
+        let response: ModelsResponse = ChatCompletionsApi::new(SERVICE, base_url, api_key)
+            .get("/models")
+            .await
+            .context("Failed to list models")?;
+
+        let mut models: Vec<RemoteModelInfo> = response
+            .data
+            .into_iter()
+            .map(|m| RemoteModelInfo {
+                name: m.id.clone(),
+                id: m.id,
+                description: None,
+                context_window: m.max_model_len.or(m.meta.and_then(|meta| meta.n_ctx_train)),
+            })
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+
+        Ok(models)
+    }
+}
+
+impl Provider for OpenAICompatibleChatProvider {
+    fn provider_name(&self) -> &'static str {
+        "openai_compatible"
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+}
+
+#[async_trait]
+impl ChatProvider for OpenAICompatibleChatProvider {
+    fn context_window(&self) -> usize {
+        self.context_window
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        sampling: &SamplingParams,
+        event_tx: mpsc::Sender<ProviderEvent>,
+        cancel_token: CancellationToken,
+    ) -> Result<CompletionResult> {
+        let request = ChatRequest::new(&self.model, messages, tools, sampling);
+        self.api.stream(&request, event_tx, cancel_token).await
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelInfo {
+    id: String,
+    /// Reported by vLLM.
+    #[serde(default)]
+    max_model_len: Option<usize>,
+    /// Reported by llama.cpp's server.
+    #[serde(default)]
+    meta: Option<ModelMeta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelMeta {
+    #[serde(default)]
+    n_ctx_train: Option<usize>,
+}
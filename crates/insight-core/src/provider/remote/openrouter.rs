@@ -0,0 +1,122 @@
+//! OpenRouter chat provider over its OpenAI-compatible Chat Completions API.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::chat_completions::{ChatCompletionsApi, ChatRequest};
+use crate::agent::Message;
+use crate::provider::{
+    ChatProvider, CompletionResult, Provider, ProviderEvent, RemoteModelInfo, SamplingParams,
+    ToolDefinition,
+};
+
+const OPENROUTER_API_URL: &str = "https://openrouter.ai/api/v1";
+/// Context window assumed when the model listing didn't give one.
+const DEFAULT_CONTEXT_WINDOW: usize = 128_000;
+
+pub struct OpenRouterChatProvider {
+    api: ChatCompletionsApi,
+    model: String,
+    context_window: usize,
+}
+
+impl OpenRouterChatProvider {
+    pub fn new(api_key: &str, model: &str, context_window: Option<usize>) -> Self {
+        Self {
+            api: api(api_key),
+            model: model.to_string(),
+            context_window: context_window.unwrap_or(DEFAULT_CONTEXT_WINDOW),
+        }
+    }
+
+    /// Fetch the models that can call tools, sorted by name.
+    ///
+    /// The model listing is public, so the key is checked first; otherwise
+    /// a mistyped key would only fail on the first chat.
+    pub async fn fetch_models(api_key: &str) -> Result<Vec<RemoteModelInfo>> {
+        let api = api(api_key);
+        api.get::<serde_json::Value>("/key")
+            .await
+            .context("Failed to verify API key")?;
+        let response: ModelsResponse = api.get("/models").await.context("Failed to list models")?;
+
+        let mut models: Vec<RemoteModelInfo> = response
+            .data
+            .into_iter()
+            // Tools are how the agent reads documents.
+            .filter(|m| m.supported_parameters.iter().any(|p| p == "tools"))
+            .map(|m| RemoteModelInfo {
+                id: m.id,
+                name: m.name,
+                description: m.description.filter(|d| !d.is_empty()),
+                context_window: m.context_length,
+            })
+            .collect();
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(models)
+    }
+}
+
+fn api(api_key: &str) -> ChatCompletionsApi {
+    // OpenRouter attributes requests to the app named here.
+    ChatCompletionsApi::new("OpenRouter", OPENROUTER_API_URL, api_key)
+        .with_header("x-title", "Insight")
+}
+
+impl Provider for OpenRouterChatProvider {
+    fn provider_name(&self) -> &'static str {
+        "openrouter"
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+}
+
+#[async_trait]
+impl ChatProvider for OpenRouterChatProvider {
+    fn context_window(&self) -> usize {
+        self.context_window
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        sampling: &SamplingParams,
+        event_tx: mpsc::Sender<ProviderEvent>,
+        cancel_token: CancellationToken,
+    ) -> Result<CompletionResult> {
+        let mut request = ChatRequest::new(&self.model, messages, tools, sampling);
+        // OpenRouter maps the budget onto each model's own reasoning
+        // setting and ignores it for models that can't reason.
+        if let Some(budget) = sampling.thinking_budget.filter(|_| sampling.thinking()) {
+            request.extra.insert(
+                "reasoning".to_string(),
+                serde_json::json!({ "max_tokens": budget }),
+            );
+        }
+        self.api.stream(&request, event_tx, cancel_token).await
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelInfo {
+    id: String,
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    context_length: Option<usize>,
+    #[serde(default)]
+    supported_parameters: Vec<String>,
+}
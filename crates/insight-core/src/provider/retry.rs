@@ -0,0 +1,305 @@
+//! Retrying remote completions that failed for a passing reason: rate
+//! limits (429), overloaded or failing servers (5xx) and dropped
+//! connections.
+//!
+//! A completion is only retried when it failed before streaming anything,
+//! so the user never sees the same text twice. Waits grow exponentially
+//! with jitter, and a `Retry-After` from the service takes precedence.
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use super::{ChatProvider, CompletionResult, ProviderEvent, SamplingParams, ToolDefinition};
+use crate::agent::Message;
+
+/// Attempts at a completion, the first included.
+pub const MAX_ATTEMPTS: u32 = 4;
+
+/// Wait before the first retry; doubled for each one after.
+const BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest wait before a retry. A service asking for longer is reported
+/// to the user instead of leaving the turn hanging.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// An error status from a remote provider's API, with the service's own
+/// message.
+#[derive(Debug)]
+pub struct ApiStatusError {
+    /// Name used in the message, e.g. "Anthropic".
+    pub service: &'static str,
+    pub status: StatusCode,
+    /// The service's error code, e.g. `tool_use_failed`, if it sent one.
+    pub code: Option<String>,
+    pub message: String,
+    /// How long the service asked to wait before trying again.
+    pub retry_after: Option<Duration>,
+}
+
+impl fmt::Display for ApiStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} API error ({}): {}",
+            self.service, self.status, self.message
+        )
+    }
+}
+
+impl std::error::Error for ApiStatusError {}
+
+impl ApiStatusError {
+    /// An error a service reported mid-stream, where there is no HTTP
+    /// status, classified by its code so rate limits and server errors
+    /// are retried like their HTTP counterparts.
+    pub fn from_code(service: &'static str, code: Option<&str>, message: String) -> Self {
+        let status = match code {
+            Some("rate_limit_exceeded") => StatusCode::TOO_MANY_REQUESTS,
+            Some("server_error") => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        };
+        Self {
+            service,
+            status,
+            code: code.map(str::to_string),
+            message,
+            retry_after: None,
+        }
+    }
+}
+
+/// The wait a response's `Retry-After` header asks for, given either in
+/// seconds or as an HTTP date. A date already past asks for no wait.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).ok();
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = at.signed_duration_since(chrono::Utc::now());
+    Some(wait.to_std().unwrap_or_default())
+}
+
+/// Whether `err` is worth retrying, and the wait the service asked for
+/// if it did. `None` for errors another attempt won't fix.
+pub fn transient(err: &anyhow::Error) -> Option<Option<Duration>> {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<ApiStatusError>() {
+            return is_transient_status(e.status).then_some(e.retry_after);
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            let transient =
+                e.is_timeout() || e.is_connect() || e.status().is_some_and(is_transient_status);
+            return transient.then_some(None);
+        }
+        if let Some(async_openai::error::OpenAIError::ApiError(e)) =
+            cause.downcast_ref::<async_openai::error::OpenAIError>()
+        {
+            let transient = e.code.as_deref() == Some("rate_limit_exceeded")
+                || e.r#type.as_deref() == Some("server_error");
+            return transient.then_some(None);
+        }
+    }
+    None
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    // 529 is Anthropic's "overloaded".
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The wait before retry number `retry` (1-based): the service's own
+/// request if it made one, otherwise exponential backoff with up to 50%
+/// jitter so parallel requests don't retry in lockstep. `None` when the
+/// wait would exceed [`MAX_DELAY`].
+pub fn backoff_delay(retry: u32, requested: Option<Duration>) -> Option<Duration> {
+    let delay = requested.unwrap_or_else(|| {
+        let exponential = BASE_DELAY.saturating_mul(1 << (retry - 1).min(8));
+        exponential.mul_f64(1.0 + rand::random::<f64>() * 0.5)
+    });
+    (delay <= MAX_DELAY).then_some(delay)
+}
+
+/// [`ChatProvider::stream_completion`], retried on transient errors.
+///
+/// Each retry is announced with [`ProviderEvent::Retrying`]. Error events
+/// from a failed attempt are held back unless it is the last one, since
+/// the turn isn't over yet.
+pub async fn stream_with_retry(
+    provider: &dyn ChatProvider,
+    messages: &[Message],
+    tools: &[ToolDefinition],
+    sampling: &SamplingParams,
+    event_tx: mpsc::Sender<ProviderEvent>,
+    cancel_token: CancellationToken,
+) -> Result<CompletionResult> {
+    let mut attempt = 1;
+    loop {
+        let (attempt_tx, mut attempt_rx) = mpsc::channel::<ProviderEvent>(100);
+        let streamed = Arc::new(AtomicBool::new(false));
+        let forward = {
+            let event_tx = event_tx.clone();
+            let streamed = streamed.clone();
+            tokio::spawn(async move {
+                let mut held = Vec::new();
+                while let Some(event) = attempt_rx.recv().await {
+                    match event {
+                        ProviderEvent::Error(_) => held.push(event),
+                        event => {
+                            if !matches!(
+                                event,
+                                ProviderEvent::Finished { .. } | ProviderEvent::Done
+                            ) {
+                                streamed.store(true, Ordering::Relaxed);
+                            }
+                            let _ = event_tx.send(event).await;
+                        }
+                    }
+                }
+                held
+            })
+        };
+
+        let result = provider
+            .stream_completion(messages, tools, sampling, attempt_tx, cancel_token.clone())
+            .await;
+        let held = forward.await.unwrap_or_default();
+
+        let err = match result {
+            Ok(result) => return Ok(result),
+            Err(err) => err,
+        };
+        let delay = (attempt < MAX_ATTEMPTS
+            && !streamed.load(Ordering::Relaxed)
+            && !cancel_token.is_cancelled())
+        .then(|| transient(&err))
+        .flatten()
+        .and_then(|requested| backoff_delay(attempt, requested));
+        let Some(delay) = delay else {
+            for event in held {
+                let _ = event_tx.send(event).await;
+            }
+            return Err(err);
+        };
+
+        warn!(
+            provider = provider.provider_name(),
+            attempt,
+            ?delay,
+            error = %err,
+            "Transient provider error, retrying"
+        );
+        let _ = event_tx
+            .send(ProviderEvent::Retrying {
+                attempt: attempt + 1,
+                max_attempts: MAX_ATTEMPTS,
+                delay,
+                reason: err.to_string(),
+            })
+            .await;
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = cancel_token.cancelled() => {
+                let _ = event_tx.send(ProviderEvent::Done).await;
+                return Ok(CompletionResult::default());
+            }
+        }
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    fn status_error(status: u16, retry_after: Option<Duration>) -> anyhow::Error {
+        ApiStatusError {
+            service: "Test",
+            status: StatusCode::from_u16(status).unwrap(),
+            code: None,
+            message: "failed".to_string(),
+            retry_after,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_transient_classifies_statuses() {
+        assert_eq!(transient(&status_error(429, None)), Some(None));
+        assert_eq!(
+            transient(&status_error(529, Some(Duration::from_secs(2)))),
+            Some(Some(Duration::from_secs(2)))
+        );
+        assert_eq!(transient(&status_error(400, None)), None);
+        assert_eq!(transient(&status_error(401, None)), None);
+        assert_eq!(
+            transient(&status_error(503, None).context("Stream failed")),
+            Some(None)
+        );
+        assert_eq!(transient(&anyhow::anyhow!("Invalid tool arguments")), None);
+    }
+
+    #[test]
+    fn test_stream_error_codes_are_retried() {
+        let error = |code| -> anyhow::Error {
+            ApiStatusError::from_code("OpenAI", code, "failed".to_string()).into()
+        };
+        assert_eq!(transient(&error(Some("rate_limit_exceeded"))), Some(None));
+        assert_eq!(transient(&error(Some("server_error"))), Some(None));
+        assert_eq!(transient(&error(Some("invalid_prompt"))), None);
+        assert_eq!(transient(&error(None)), None);
+    }
+
+    #[test]
+    fn test_backoff_delay_grows_and_caps() {
+        for retry in 1..=3 {
+            let delay = backoff_delay(retry, None).unwrap();
+            let base = BASE_DELAY * (1 << (retry - 1));
+            assert!(delay >= base && delay <= base.mul_f64(1.5));
+        }
+        assert_eq!(
+            backoff_delay(1, Some(Duration::from_secs(7))),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(backoff_delay(1, Some(Duration::from_secs(3600))), None);
+        assert_eq!(backoff_delay(8, None), None);
+    }
+
+    #[test]
+    fn test_retry_after_parses_seconds() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, "2.5".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_millis(2500)));
+        headers.insert(RETRY_AFTER, "1e300".parse().unwrap());
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, "-1".parse().unwrap());
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_retry_after_parses_dates() {
+        let mut headers = HeaderMap::new();
+        let at = chrono::Utc::now() + chrono::Duration::seconds(30);
+        headers.insert(RETRY_AFTER, at.to_rfc2822().parse().unwrap());
+        let wait = retry_after(&headers).unwrap();
+        assert!(wait > Duration::from_secs(28) && wait <= Duration::from_secs(30));
+        headers.insert(
+            RETRY_AFTER,
+            "Sun, 06 Nov 1994 08:49:37 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(retry_after(&headers), None);
+    }
+}
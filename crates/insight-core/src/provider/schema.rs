@@ -0,0 +1,142 @@
+//! Checking model output against a JSON schema.
+//!
+//! Covers the subset of JSON Schema that structured completions use:
+//! `type` (one or a list), `properties`, `required`,
+//! `additionalProperties: false`, `items`, and `enum`. Keywords outside the
+//! subset are ignored rather than rejected.
+
+use serde_json::Value;
+
+/// Check `value` against `schema`. The error names the first offending
+/// path, e.g. `$.events[2].date: expected string`, so it can be fed back
+/// to the model.
+pub fn validate(value: &Value, schema: &Value) -> Result<(), String> {
+    check(value, schema, "$")
+}
+
+fn check(value: &Value, schema: &Value, path: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|t| has_type(value, t)) {
+            return Err(format!("{}: expected {}", path, allowed.join(" or ")));
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            return Err(format!("{}: not one of the allowed values", path));
+        }
+    }
+
+    if let Value::Object(object) = value {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(key) {
+                    return Err(format!("{}: missing required property \"{}\"", path, key));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        let closed = schema.get("additionalProperties") == Some(&Value::Bool(false));
+        for (key, item) in object {
+            match properties.and_then(|p| p.get(key)) {
+                Some(item_schema) => check(item, item_schema, &format!("{}.{}", path, key))?,
+                None if closed => {
+                    return Err(format!("{}: unexpected property \"{}\"", path, key));
+                }
+                None => {}
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            check(item, item_schema, &format!("{}[{}]", path, i))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "events": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "date": { "type": "string" },
+                            "page": { "type": ["integer", "null"] },
+                            "kind": { "enum": ["meeting", "payment"] }
+                        },
+                        "required": ["date"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["events"]
+        })
+    }
+
+    #[test]
+    fn test_validate_accepts_matching_value() {
+        let value = json!({
+            "events": [
+                { "date": "2021-03-01", "page": 4, "kind": "payment" },
+                { "date": "2021-04", "page": null }
+            ]
+        });
+        assert_eq!(validate(&value, &schema()), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_reports_first_problem() {
+        let cases = [
+            (json!({}), "$: missing required property \"events\""),
+            (json!({ "events": {} }), "$.events: expected array"),
+            (
+                json!({ "events": [{ "date": "x" }, { "date": 5 }] }),
+                "$.events[1].date: expected string",
+            ),
+            (
+                json!({ "events": [{ "date": "x", "page": 1.5 }] }),
+                "$.events[0].page: expected integer or null",
+            ),
+            (
+                json!({ "events": [{ "date": "x", "kind": "memo" }] }),
+                "$.events[0].kind: not one of the allowed values",
+            ),
+            (
+                json!({ "events": [{ "date": "x", "note": "" }] }),
+                "$.events[0]: unexpected property \"note\"",
+            ),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(validate(&value, &schema()), Err(expected.to_string()));
+        }
+    }
+}
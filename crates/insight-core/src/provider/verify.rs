@@ -0,0 +1,215 @@
+//! End-to-end check of a chat provider.
+//!
+//! Listing a service's models only shows the key is accepted. A long chat
+//! can still fail on its first turn: the model may be unavailable to the
+//! account, out of credit, or unable to call tools, which the agent
+//! depends on. The check sends one tiny request, then asks the model to
+//! call a tool, and reports what worked.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::{ChatProvider, CompletionResult, ProviderEvent, SamplingParams, ToolDefinition};
+use crate::agent::{ContentBlock, Message, MessageRole};
+
+/// Longest wait for each request before the check gives up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Enough for a short reply, or for reasoning models to get to one.
+const MAX_TOKENS: u32 = 256;
+
+/// Tool the model is asked to call.
+const PROBE_TOOL: &str = "report_status";
+
+/// What checking a chat provider found.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderCheck {
+    pub provider: String,
+    pub model: String,
+    /// The model answered a minimal request.
+    pub reachable: bool,
+    /// Time the minimal request took, in milliseconds.
+    pub latency_ms: Option<u64>,
+    /// The model called a tool when asked to.
+    pub tool_calls: bool,
+    /// The provider accepts page images.
+    pub images: bool,
+    pub context_window: usize,
+    /// What went wrong, if anything did.
+    pub error: Option<String>,
+}
+
+/// Check `provider` with a minimal request and a tool-call probe. The
+/// probe is skipped when the provider can't be reached.
+pub async fn verify(provider: &dyn ChatProvider) -> ProviderCheck {
+    let mut check = ProviderCheck {
+        provider: provider.provider_name().to_string(),
+        model: provider.model_id().to_string(),
+        reachable: false,
+        latency_ms: None,
+        tool_calls: false,
+        images: provider.supports_images(),
+        context_window: provider.context_window(),
+        error: None,
+    };
+
+    let start = Instant::now();
+    if let Err(e) = request(provider, "Reply with the word OK.", &[]).await {
+        check.error = Some(e.to_string());
+        return check;
+    }
+    check.reachable = true;
+    check.latency_ms = Some(start.elapsed().as_millis() as u64);
+
+    let probe = ToolDefinition {
+        name: PROBE_TOOL.to_string(),
+        description: "Report the status of this connection check.".to_string(),
+        parameters: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "status": {
+                    "type": "string",
+                    "description": "Always \"ok\""
+                }
+            },
+            "required": ["status"]
+        }),
+    };
+    let prompt = format!("Call the {} tool with status \"ok\".", PROBE_TOOL);
+    match request(provider, &prompt, &[probe]).await {
+        Ok(result) => {
+            check.tool_calls = result.tool_calls.iter().any(|c| c.name == PROBE_TOOL);
+            if !check.tool_calls {
+                check.error = Some(
+                    "The model answered but didn't call the tool it was asked to. \
+                     It may not support tool calling, which Insight needs."
+                        .to_string(),
+                );
+            }
+        }
+        Err(e) => check.error = Some(format!("Tool calling failed: {}", e)),
+    }
+    check
+}
+
+/// One completion of `prompt`, without retries so a misconfiguration
+/// shows at once.
+async fn request(
+    provider: &dyn ChatProvider,
+    prompt: &str,
+    tools: &[ToolDefinition],
+) -> Result<CompletionResult> {
+    let messages = vec![Message {
+        role: MessageRole::User,
+        content: vec![ContentBlock::Text {
+            text: prompt.to_string(),
+        }],
+    }];
+    let sampling = SamplingParams {
+        max_tokens: Some(MAX_TOKENS),
+        ..SamplingParams::default()
+    };
+
+    let (event_tx, mut event_rx) = mpsc::channel::<ProviderEvent>(100);
+    let drain = tokio::spawn(async move { while event_rx.recv().await.is_some() {} });
+    let cancel_token = CancellationToken::new();
+    let result = tokio::time::timeout(
+        REQUEST_TIMEOUT,
+        provider.stream_completion(&messages, tools, &sampling, event_tx, cancel_token.clone()),
+    )
+    .await;
+    cancel_token.cancel();
+    let _ = drain.await;
+
+    result.map_err(|_| anyhow!("No reply within {} seconds", REQUEST_TIMEOUT.as_secs()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::CompletedToolCall;
+
+    /// Replies with text, calling the probe tool only if `calls_tools`.
+    struct ProbeProvider {
+        calls_tools: bool,
+        fails: bool,
+    }
+
+    impl crate::provider::Provider for ProbeProvider {
+        fn provider_name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn model_id(&self) -> &str {
+            "mock-model"
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ChatProvider for ProbeProvider {
+        async fn stream_completion(
+            &self,
+            _messages: &[Message],
+            tools: &[ToolDefinition],
+            _sampling: &SamplingParams,
+            _event_tx: mpsc::Sender<ProviderEvent>,
+            _cancel_token: CancellationToken,
+        ) -> Result<CompletionResult> {
+            if self.fails {
+                anyhow::bail!("Invalid API key");
+            }
+            let tool_calls = if self.calls_tools && !tools.is_empty() {
+                vec![CompletedToolCall {
+                    id: "call_1".to_string(),
+                    name: PROBE_TOOL.to_string(),
+                    arguments: serde_json::json!({"status": "ok"}),
+                }]
+            } else {
+                vec![]
+            };
+            Ok(CompletionResult {
+                text: "OK".to_string(),
+                tool_calls,
+                ..CompletionResult::default()
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_reports_capabilities() {
+        let check = verify(&ProbeProvider {
+            calls_tools: true,
+            fails: false,
+        })
+        .await;
+        assert!(check.reachable);
+        assert!(check.latency_ms.is_some());
+        assert!(check.tool_calls);
+        assert!(check.error.is_none());
+
+        let check = verify(&ProbeProvider {
+            calls_tools: false,
+            fails: false,
+        })
+        .await;
+        assert!(check.reachable);
+        assert!(!check.tool_calls);
+        assert!(check.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_verify_stops_when_unreachable() {
+        let check = verify(&ProbeProvider {
+            calls_tools: true,
+            fails: true,
+        })
+        .await;
+        assert!(!check.reachable);
+        assert!(!check.tool_calls);
+        assert_eq!(check.error.as_deref(), Some("Invalid API key"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Client-side rate limiting of remote chat models.
+//!
+//! Chat, next-message predictions, summaries and batch questions all share
+//! one API key per provider, and a batch job can easily send more requests
+//! in a minute than the provider allows, making the user's own chat fail.
+//! Every remote completion therefore takes a token from its provider's
+//! bucket first, waiting for one to refill when the bucket is empty. The
+//! default limits sit at or below each provider's entry-tier limits, and
+//! the user can raise or lower them per provider in Settings; providers
+//! with no limit either way aren't limited.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::agent::Message;
+use crate::provider::{
+    ChatProvider, CompletionResult, MemoryKind, Provider, ProviderEvent, SamplingParams,
+    ToolDefinition,
+};
+
+/// Default requests per minute by provider name.
+pub const DEFAULT_LIMITS: &[(&str, u32)] = &[
+    ("anthropic", 50),
+    ("groq", 30),
+    ("mistral", 60),
+    ("openai", 500),
+    ("openrouter", 20),
+];
+
+/// Buckets by provider name, shared by every provider instance so a model
+/// switch doesn't reset the count.
+static BUCKETS: Mutex<BTreeMap<&'static str, TokenBucket>> = Mutex::new(BTreeMap::new());
+
+/// Limits the user set, by provider name. 0 turns a provider's limit off.
+static OVERRIDES: RwLock<BTreeMap<String, u32>> = RwLock::new(BTreeMap::new());
+
+/// Use `overrides` instead of the default limits for the providers it
+/// names. Buckets start over so a new limit applies from the next request.
+pub fn set_overrides(overrides: BTreeMap<String, u32>) {
+    *OVERRIDES.write().unwrap_or_else(|e| e.into_inner()) = overrides;
+    BUCKETS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// The default requests-per-minute limit for `provider`, if it has one.
+pub fn default_requests_per_minute(provider: &str) -> Option<u32> {
+    DEFAULT_LIMITS
+        .iter()
+        .find(|(name, _)| *name == provider)
+        .map(|(_, rpm)| *rpm)
+}
+
+/// The limit for `provider` given the user's `overrides`.
+fn limit_for(provider: &str, overrides: &BTreeMap<String, u32>) -> Option<u32> {
+    match overrides.get(provider) {
+        Some(0) => None,
+        Some(rpm) => Some(*rpm),
+        None => default_requests_per_minute(provider),
+    }
+}
+
+/// The requests-per-minute limit for `provider`, if it has one.
+pub fn requests_per_minute(provider: &str) -> Option<u32> {
+    limit_for(
+        provider,
+        &OVERRIDES.read().unwrap_or_else(|e| e.into_inner()),
+    )
+}
+
+/// A bucket that holds up to `capacity` requests and refills at a steady
+/// rate. Taking from an empty bucket reserves the next token, so waiting
+/// callers are served in order.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    /// Negative while callers are waiting for tokens they reserved.
+    tokens: f64,
+    per_second: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket for `rpm` requests a minute, allowing bursts of a
+    /// sixth of that.
+    fn new(rpm: u32, now: Instant) -> Self {
+        let capacity = (f64::from(rpm) / 6.0).max(1.0);
+        Self {
+            capacity,
+            tokens: capacity,
+            per_second: f64::from(rpm) / 60.0,
+            updated: now,
+        }
+    }
+
+    /// Take a token, returning how long to wait before using it.
+    fn take(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.updated = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.per_second)
+        }
+    }
+}
+
+/// Wait until `provider` may send another request. Returns at once for
+/// providers without a limit.
+pub async fn acquire(provider: &'static str, cancel_token: &CancellationToken) -> Result<()> {
+    let Some(rpm) = requests_per_minute(provider) else {
+        return Ok(());
+    };
+    let wait = {
+        let now = Instant::now();
+        let mut buckets = BUCKETS.lock().unwrap_or_else(|e| e.into_inner());
+        buckets
+            .entry(provider)
+            .or_insert_with(|| TokenBucket::new(rpm, now))
+            .take(now)
+    };
+    if wait.is_zero() {
+        return Ok(());
+    }
+    debug!(provider, ?wait, "Waiting for the provider's rate limit");
+    tokio::select! {
+        _ = tokio::time::sleep(wait) => Ok(()),
+        _ = cancel_token.cancelled() => bail!("Cancelled while waiting for the rate limit"),
+    }
+}
+
+/// Wraps a remote chat provider to wait for its rate limit before each
+/// completion.
+pub struct RateLimitedChatProvider {
+    inner: Arc<dyn ChatProvider>,
+}
+
+impl RateLimitedChatProvider {
+    pub fn new(inner: Arc<dyn ChatProvider>) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl Provider for RateLimitedChatProvider {
+    fn provider_name(&self) -> &'static str {
+        self.inner.provider_name()
+    }
+
+    fn model_id(&self) -> &str {
+        self.inner.model_id()
+    }
+
+    fn memory_kind(&self) -> MemoryKind {
+        self.inner.memory_kind()
+    }
+
+    fn coexist(&self) -> bool {
+        self.inner.coexist()
+    }
+
+    fn set_coexist(&self, coexist: bool) {
+        self.inner.set_coexist(coexist);
+    }
+
+    async fn is_loaded(&self) -> bool {
+        self.inner.is_loaded().await
+    }
+
+    async fn ensure_loaded(&self) -> Result<()> {
+        self.inner.ensure_loaded().await
+    }
+
+    async fn unload(&self) -> Result<bool> {
+        self.inner.unload().await
+    }
+}
+
+#[async_trait]
+impl ChatProvider for RateLimitedChatProvider {
+    fn context_window(&self) -> usize {
+        self.inner.context_window()
+    }
+
+    fn supports_images(&self) -> bool {
+        self.inner.supports_images()
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        sampling: &SamplingParams,
+        event_tx: mpsc::Sender<ProviderEvent>,
+        cancel_token: CancellationToken,
+    ) -> Result<CompletionResult> {
+        acquire(self.provider_name(), &cancel_token).await?;
+        self.inner
+            .stream_completion(messages, tools, sampling, event_tx, cancel_token)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_spaces_requests() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(60, start);
+        for _ in 0..10 {
+            assert_eq!(bucket.take(start), Duration::ZERO);
+        }
+        // Empty: one token a second, reserved in order.
+        assert_eq!(bucket.take(start), Duration::from_secs(1));
+        assert_eq!(bucket.take(start), Duration::from_secs(2));
+        // Refilled after waiting, but never past capacity.
+        let later = start + Duration::from_secs(60);
+        assert_eq!(bucket.take(later), Duration::ZERO);
+        assert!(bucket.tokens <= bucket.capacity);
+    }
+
+    #[test]
+    fn test_requests_per_minute_by_provider() {
+        let none = BTreeMap::new();
+        assert_eq!(limit_for("groq", &none), Some(30));
+        assert_eq!(limit_for("openai_compatible", &none), None);
+        assert_eq!(limit_for("local", &none), None);
+
+        let overrides = BTreeMap::from([
+            ("groq".to_string(), 0),
+            ("openai".to_string(), 5000),
+            ("openai_compatible".to_string(), 10),
+        ]);
+        assert_eq!(limit_for("groq", &overrides), None);
+        assert_eq!(limit_for("openai", &overrides), Some(5000));
+        assert_eq!(limit_for("openai_compatible", &overrides), Some(10));
+        assert_eq!(limit_for("anthropic", &overrides), Some(50));
+    }
+}
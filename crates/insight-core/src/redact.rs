@@ -0,0 +1,534 @@
+//! Personal data redaction for sharing documents outside the team.
+//!
+//! [`RedactionMap::detect`] finds names, email addresses, phone numbers
+//! and account numbers with rule-based scanners, the same way the agent's
+//! entity extraction works: fast, deterministic, and every entry points at
+//! text that is really there. Each distinct value gets a numbered label
+//! (`[NAME 1]`, `[EMAIL 2]`), so a redacted copy still reads coherently:
+//! the same person is the same label throughout. Detection errs towards
+//! redacting; the user reviews the map and can keep entries before
+//! exporting. Exported PDFs are new text-only files, so nothing from the
+//! original survives underneath a black box.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::agent::entities::{find_entities, EntityKind};
+use crate::agent::tools::page_spans;
+
+/// Lowercase words shortly before a number that say it's an account.
+const ACCOUNT_CUES: &[&str] = &[
+    "account",
+    "acct",
+    "a/c",
+    "iban",
+    "card",
+    "routing",
+    "sort code",
+    "ssn",
+    "passport",
+];
+
+/// Lowercase words shortly before a number that say it's a phone number.
+const PHONE_CUES: &[&str] = &["tel", "phone", "mobile", "cell", "fax", "call", "whatsapp"];
+
+/// Bytes before a number searched for a cue.
+const CUE_WINDOW: usize = 24;
+
+/// Shortest surname redacted on its own once the full name was found.
+const MIN_SURNAME_CHARS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Name,
+    Email,
+    Phone,
+    Account,
+}
+
+impl PiiKind {
+    fn label(self) -> &'static str {
+        match self {
+            PiiKind::Name => "NAME",
+            PiiKind::Email => "EMAIL",
+            PiiKind::Phone => "PHONE",
+            PiiKind::Account => "ACCOUNT",
+        }
+    }
+}
+
+/// One value to redact, with where it appears.
+#[derive(Debug, Clone, Serialize)]
+pub struct RedactionEntry {
+    /// Replacement text, e.g. `[NAME 1]`.
+    pub label: String,
+    pub kind: PiiKind,
+    /// The value as first found.
+    pub text: String,
+    pub count: usize,
+    /// Pages it appears on, from 1.
+    pub pages: Vec<usize>,
+}
+
+/// A found value on one page.
+#[derive(Debug, Clone)]
+struct Span {
+    /// Page index, from 0.
+    page: usize,
+    range: Range<usize>,
+    entry: usize,
+}
+
+/// Everything to redact in a document.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RedactionMap {
+    pub entries: Vec<RedactionEntry>,
+    #[serde(skip)]
+    spans: Vec<Span>,
+}
+
+impl RedactionMap {
+    /// Find personal data in `pages` (page 1 first).
+    pub fn detect(pages: &[&str]) -> Self {
+        let mut map = Self::default();
+        let mut by_key: HashMap<(PiiKind, String), usize> = HashMap::new();
+        let mut surnames: HashMap<String, usize> = HashMap::new();
+
+        let mut found: Vec<Vec<(PiiKind, Range<usize>)>> = Vec::with_capacity(pages.len());
+        for page in pages {
+            let mut mentions = find_emails(page);
+            mentions.extend(find_numbers(page));
+            mentions.extend(
+                find_entities(page)
+                    .into_iter()
+                    // Capitalized codes like "IBAN GB29" aren't names.
+                    .filter(|m| {
+                        m.kind == EntityKind::Person && !m.name.chars().any(|c| c.is_ascii_digit())
+                    })
+                    .map(|m| (PiiKind::Name, m.span)),
+            );
+            found.push(without_overlaps(mentions));
+        }
+
+        for (page, mentions) in found.iter().enumerate() {
+            for (kind, range) in mentions {
+                let text = &pages[page][range.clone()];
+                let entry = map.entry(&mut by_key, *kind, text);
+                map.add_span(page, range.clone(), entry);
+                if *kind == PiiKind::Name {
+                    if let Some(surname) = text.split_whitespace().last() {
+                        if surname.chars().count() >= MIN_SURNAME_CHARS && surname != text {
+                            surnames.entry(surname.to_string()).or_insert(entry);
+                        }
+                    }
+                }
+            }
+        }
+
+        // "Jane Roe ... Roe said" redacts the second mention too.
+        for (page, text) in pages.iter().enumerate() {
+            for (surname, &entry) in &surnames {
+                for start in word_positions(text, surname) {
+                    let range = start..start + surname.len();
+                    let covered = map.spans.iter().any(|s| {
+                        s.page == page && s.range.start < range.end && range.start < s.range.end
+                    });
+                    if !covered {
+                        map.add_span(page, range, entry);
+                    }
+                }
+            }
+        }
+
+        map.spans.sort_by_key(|s| (s.page, s.range.start));
+        map
+    }
+
+    /// The entry for `text`, created on first sight.
+    fn entry(
+        &mut self,
+        by_key: &mut HashMap<(PiiKind, String), usize>,
+        kind: PiiKind,
+        text: &str,
+    ) -> usize {
+        let key = match kind {
+            PiiKind::Phone | PiiKind::Account => {
+                text.chars().filter(char::is_ascii_alphanumeric).collect()
+            }
+            PiiKind::Name | PiiKind::Email => text
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase(),
+        };
+        *by_key.entry((kind, key)).or_insert_with(|| {
+            let number = self.entries.iter().filter(|e| e.kind == kind).count() + 1;
+            self.entries.push(RedactionEntry {
+                label: format!("[{} {}]", kind.label(), number),
+                kind,
+                text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+                count: 0,
+                pages: Vec::new(),
+            });
+            self.entries.len() - 1
+        })
+    }
+
+    fn add_span(&mut self, page: usize, range: Range<usize>, entry: usize) {
+        let e = &mut self.entries[entry];
+        e.count += 1;
+        if !e.pages.contains(&(page + 1)) {
+            e.pages.push(page + 1);
+            e.pages.sort_unstable();
+        }
+        self.spans.push(Span { page, range, entry });
+    }
+
+    /// `pages` with every entry replaced by its label, except the entries
+    /// labelled in `keep`.
+    pub fn apply(&self, pages: &[&str], keep: &[String]) -> Vec<String> {
+        pages
+            .iter()
+            .enumerate()
+            .map(|(page, text)| {
+                let mut out = String::with_capacity(text.len());
+                let mut last = 0;
+                for span in self.spans.iter().filter(|s| s.page == page) {
+                    let entry = &self.entries[span.entry];
+                    if keep.contains(&entry.label) {
+                        continue;
+                    }
+                    out.push_str(&text[last..span.range.start]);
+                    out.push_str(&entry.label);
+                    last = span.range.end;
+                }
+                out.push_str(&text[last..]);
+                out
+            })
+            .collect()
+    }
+}
+
+/// The pages of a document's extracted text, page 1 first.
+pub fn split_pages<'a>(text: &'a str, page_boundaries: &[usize]) -> Vec<&'a str> {
+    page_spans(text, page_boundaries)
+        .into_iter()
+        .map(|range| &text[range])
+        .collect()
+}
+
+/// How a redacted copy is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactedFormat {
+    Text,
+    Pdf,
+}
+
+/// Write redacted `pages` of the document `document_id` into `dir` and
+/// return the file's path. The copy is named after the ID rather than the
+/// document's name, which can hold the very data that was redacted.
+pub fn export(
+    dir: &Path,
+    document_id: &str,
+    pages: &[String],
+    format: RedactedFormat,
+) -> Result<PathBuf> {
+    let stem = format!("{} (redacted)", crate::net::file_stem(document_id));
+    std::fs::create_dir_all(dir).context("Failed to create export directory")?;
+
+    let (path, bytes) = match format {
+        RedactedFormat::Text => (
+            dir.join(format!("{}.txt", stem)),
+            pages.join("\n\n\u{c}\n\n").into_bytes(),
+        ),
+        RedactedFormat::Pdf => (
+            dir.join(format!("{}.pdf", stem)),
+            crate::pdf::text_pdf(&stem, pages)?,
+        ),
+    };
+    std::fs::write(&path, bytes).context("Failed to write redacted copy")?;
+    Ok(path)
+}
+
+/// Drop mentions overlapping an earlier one, longest first on ties.
+fn without_overlaps(mut mentions: Vec<(PiiKind, Range<usize>)>) -> Vec<(PiiKind, Range<usize>)> {
+    mentions.sort_by(|a, b| a.1.start.cmp(&b.1.start).then(b.1.end.cmp(&a.1.end)));
+    let mut kept: Vec<(PiiKind, Range<usize>)> = Vec::new();
+    for mention in mentions {
+        if kept.last().is_some_and(|last| mention.1.start < last.1.end) {
+            continue;
+        }
+        kept.push(mention);
+    }
+    kept
+}
+
+/// Byte offsets of `word` where it stands as a whole word.
+fn word_positions(text: &str, word: &str) -> Vec<usize> {
+    text.match_indices(word)
+        .map(|(start, _)| start)
+        .filter(|&start| {
+            let before = text[..start].chars().next_back();
+            let after = text[start + word.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+        .collect()
+}
+
+/// Email addresses: a local part, `@`, and a domain with a dot.
+fn find_emails(text: &str) -> Vec<(PiiKind, Range<usize>)> {
+    let bytes = text.as_bytes();
+    let is_local = |b: u8| b.is_ascii_alphanumeric() || b"._%+-".contains(&b);
+    let is_domain = |b: u8| b.is_ascii_alphanumeric() || b".-".contains(&b);
+
+    let mut emails = Vec::new();
+    for (at, _) in text.match_indices('@') {
+        let mut start = at;
+        while start > 0 && is_local(bytes[start - 1]) {
+            start -= 1;
+        }
+        let mut end = at + 1;
+        while end < bytes.len() && is_domain(bytes[end]) {
+            end += 1;
+        }
+        // A sentence may end right after the address.
+        while end > at + 1 && bytes[end - 1] == b'.' {
+            end -= 1;
+        }
+        let domain = &text[at + 1..end];
+        let valid_tld = domain.rsplit_once('.').is_some_and(|(host, tld)| {
+            !host.is_empty() && tld.len() >= 2 && tld.bytes().all(|b| b.is_ascii_alphabetic())
+        });
+        if start < at && valid_tld {
+            emails.push((PiiKind::Email, start..end));
+        }
+    }
+    emails
+}
+
+/// Phone and account numbers, including IBANs.
+fn find_numbers(text: &str) -> Vec<(PiiKind, Range<usize>)> {
+    let mut numbers = find_ibans(text);
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let starts_number = bytes[i].is_ascii_digit()
+            || (matches!(bytes[i], b'+' | b'(')
+                && bytes.get(i + 1).is_some_and(u8::is_ascii_digit));
+        let after_word = i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'.');
+        if !starts_number || after_word {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut end = i + 1;
+        let mut k = i + 1;
+        while k < bytes.len() {
+            if bytes[k].is_ascii_digit() {
+                k += 1;
+                end = k;
+            } else if b" -.()".contains(&bytes[k])
+                && bytes
+                    .get(k + 1)
+                    .is_some_and(|b| b.is_ascii_digit() || *b == b'(')
+            {
+                k += 1;
+            } else if bytes[k] == b')' {
+                k += 1;
+                end = k;
+            } else {
+                break;
+            }
+        }
+        i = end.max(start + 1);
+        if bytes.get(end).is_some_and(u8::is_ascii_alphabetic) {
+            continue;
+        }
+        if let Some(kind) = classify_number(text, start..end) {
+            numbers.push((kind, start..end));
+        }
+    }
+    numbers
+}
+
+fn classify_number(text: &str, range: Range<usize>) -> Option<PiiKind> {
+    let number = &text[range.clone()];
+    let digits = number.bytes().filter(u8::is_ascii_digit).count();
+    let groups: Vec<&str> = number
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|g| !g.is_empty())
+        .collect();
+
+    // Amounts and dates look like numbers too.
+    let before = text[..range.start].chars().next_back();
+    if before.is_some_and(|c| "$€£¥".contains(c)) {
+        return None;
+    }
+    if groups.len() > 1 && groups[1..].iter().all(|g| g.len() == 3) && groups[0].len() <= 3 {
+        return None;
+    }
+    if groups.len() == 3 {
+        let short = |g: &&str| g.len() <= 2;
+        if (groups[0].len() == 4 && groups[1..].iter().all(short))
+            || (groups[2].len() == 4 && groups[..2].iter().all(short))
+        {
+            return None;
+        }
+    }
+
+    let mut cue_start = range.start.saturating_sub(CUE_WINDOW);
+    while !text.is_char_boundary(cue_start) {
+        cue_start -= 1;
+    }
+    let cue = text[cue_start..range.start].to_lowercase();
+    if ACCOUNT_CUES.iter().any(|c| cue.contains(c)) && digits >= 6 {
+        return Some(PiiKind::Account);
+    }
+    if PHONE_CUES.iter().any(|c| cue.contains(c)) && (7..=15).contains(&digits) {
+        return Some(PiiKind::Phone);
+    }
+
+    let phone_like = number.starts_with('+') || number.contains('(') || groups.len() >= 2;
+    if phone_like && (7..=15).contains(&digits) && !groups.iter().all(|g| g.len() == 4) {
+        return Some(PiiKind::Phone);
+    }
+    if (13..=19).contains(&digits) && groups.iter().all(|g| g.len() <= 4) {
+        return Some(PiiKind::Account);
+    }
+    if groups.len() == 1 && (8..=18).contains(&digits) {
+        return Some(PiiKind::Account);
+    }
+    None
+}
+
+/// IBANs: country code, two check digits, and 11 to 30 letters and digits,
+/// optionally in groups of four.
+fn find_ibans(text: &str) -> Vec<(PiiKind, Range<usize>)> {
+    let bytes = text.as_bytes();
+    let mut ibans = Vec::new();
+    let mut i = 0;
+    while i + 4 <= bytes.len() {
+        let starts = bytes[i].is_ascii_uppercase()
+            && bytes[i + 1].is_ascii_uppercase()
+            && bytes[i + 2].is_ascii_digit()
+            && bytes[i + 3].is_ascii_digit()
+            && (i == 0 || !bytes[i - 1].is_ascii_alphanumeric());
+        if !starts {
+            i += 1;
+            continue;
+        }
+        let mut end = i + 4;
+        let mut k = end;
+        let mut length = 4;
+        while k < bytes.len() {
+            let b = bytes[k];
+            if b.is_ascii_uppercase() || b.is_ascii_digit() {
+                k += 1;
+                length += 1;
+                end = k;
+            } else if b == b' '
+                && bytes
+                    .get(k + 1)
+                    .is_some_and(|n| n.is_ascii_uppercase() || n.is_ascii_digit())
+            {
+                k += 1;
+            } else {
+                break;
+            }
+        }
+        if (15..=34).contains(&length) && !bytes.get(end).is_some_and(u8::is_ascii_alphanumeric) {
+            ibans.push((PiiKind::Account, i..end));
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    ibans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str) -> Vec<(PiiKind, &str)> {
+        let map = RedactionMap::detect(&[text]);
+        map.spans
+            .iter()
+            .map(|s| (map.entries[s.entry].kind, &text[s.range.clone()]))
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_contact_details() {
+        assert_eq!(
+            found("Write to jane.roe@example.org. Call +44 20 7946 0958 or (212) 555-0147."),
+            vec![
+                (PiiKind::Email, "jane.roe@example.org"),
+                (PiiKind::Phone, "+44 20 7946 0958"),
+                (PiiKind::Phone, "(212) 555-0147"),
+            ]
+        );
+        assert_eq!(
+            found("Paid from account 00123456 and IBAN GB29 NWBK 6016 1331 9268 19 by card 4111 1111 1111 1111."),
+            vec![
+                (PiiKind::Account, "00123456"),
+                (PiiKind::Account, "GB29 NWBK 6016 1331 9268 19"),
+                (PiiKind::Account, "4111 1111 1111 1111"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skips_dates_and_amounts() {
+        assert!(found("On 2021-03-14 it cost $1,500,000, or 1.250.000 EUR, in 2021.").is_empty());
+    }
+
+    #[test]
+    fn test_labels_are_consistent() {
+        let pages = [
+            "Dr. Jane Roe met John Smith. Roe's number is 555-0147-22.",
+            "Jane Roe called 5550147 22 again.",
+        ];
+        let map = RedactionMap::detect(&pages);
+        let jane = map.entries.iter().find(|e| e.text == "Jane Roe").unwrap();
+        assert_eq!(jane.label, "[NAME 1]");
+        assert_eq!(jane.count, 3);
+        assert_eq!(jane.pages, vec![1, 2]);
+
+        let redacted = map.apply(&pages, &[]);
+        assert_eq!(
+            redacted[0],
+            "Dr. [NAME 1] met [NAME 2]. [NAME 1]'s number is [PHONE 1]."
+        );
+        assert_eq!(redacted[1], "[NAME 1] called [PHONE 1] again.");
+
+        // Kept entries stay readable.
+        let redacted = map.apply(&pages, &["[NAME 2]".to_string()]);
+        assert!(redacted[0].contains("met John Smith."));
+    }
+
+    #[test]
+    fn test_export_leaves_the_name_out() {
+        // The document could be named "Jane Roe.pdf"; only its ID and the
+        // redacted text make it into the copy.
+        let pages = ["Jane Roe can be reached at jane.roe@example.org."];
+        let redacted = RedactionMap::detect(&pages).apply(&pages, &[]);
+        let dir = tempfile::tempdir().unwrap();
+
+        for format in [RedactedFormat::Text, RedactedFormat::Pdf] {
+            let path = export(dir.path(), "doc-1", &redacted, format).unwrap();
+            let file_name = path.file_name().unwrap().to_string_lossy();
+            assert!(file_name.starts_with("doc-1 (redacted)."));
+            let bytes = std::fs::read(&path).unwrap();
+            let text = String::from_utf8_lossy(&bytes);
+            assert!(!text.contains("Roe") && !text.contains("example.org"));
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! Importing from remote storage: S3-compatible buckets and WebDAV servers.
+//!
+//! A [`RemoteSource`] in settings names an endpoint; its credentials are
+//! kept with the other secrets (see [`crate::secrets`]), never in the
+//! settings file. [`Remote`] pairs the two to list and fetch objects.
+//!
+//! Objects picked for import are queued as `remote://{source}/{key}`
+//! paths, so they go through the import queue like local files and links:
+//! each is fetched when its turn comes, and one left waiting when the app
+//! closes is fetched on the next start.
+
+mod s3;
+mod webdav;
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::net::Download;
+use crate::secrets::{SecretRef, SecretStore};
+
+/// Prefix of queued remote paths.
+const QUEUED_SCHEME: &str = "remote://";
+
+/// Most objects one listing returns; the rest are left out with a warning.
+const MAX_LISTED: usize = 10_000;
+
+/// Object names that can be imported, by extension.
+const IMPORTABLE_EXTENSIONS: &[&str] = &[".pdf", ".md", ".markdown", ".txt"];
+
+/// Remote storage to import from, as kept in settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteSource {
+    pub id: String,
+    pub name: String,
+    pub endpoint: RemoteEndpoint,
+    /// Where the credentials are kept; `None` for anonymous access.
+    #[serde(default)]
+    pub credentials: Option<SecretRef>,
+}
+
+/// Where a remote source's objects live.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RemoteEndpoint {
+    /// An S3-compatible bucket. Objects are addressed path-style,
+    /// `{url}/{bucket}/{key}`, which AWS, MinIO, R2 and most others accept.
+    S3 {
+        url: String,
+        region: String,
+        bucket: String,
+    },
+    /// A WebDAV folder. Objects are keyed by their path below it.
+    WebDav { url: String },
+}
+
+impl RemoteEndpoint {
+    /// Check the endpoint is filled in enough to connect to.
+    pub fn validate(&self) -> Result<()> {
+        let url = match self {
+            Self::S3 { url, bucket, .. } => {
+                if bucket.trim().is_empty() {
+                    bail!("Enter a bucket name");
+                }
+                url
+            }
+            Self::WebDav { url } => url,
+        };
+        match reqwest::Url::parse(url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(()),
+            _ => bail!("Not an http(s) URL: {}", url),
+        }
+    }
+}
+
+/// Access key ID and secret key for S3; user name and password for WebDAV.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RemoteCredentials {
+    pub user: String,
+    pub secret: String,
+}
+
+impl std::fmt::Debug for RemoteCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteCredentials")
+            .field("user", &self.user)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RemoteSource {
+    /// The stored credentials, if any.
+    pub fn credentials(&self, secrets: &SecretStore) -> Option<RemoteCredentials> {
+        let secret = self.credentials.as_ref()?;
+        let value = secrets.get(secret).unwrap_or_else(|e| {
+            tracing::warn!(source = %self.id, error = %e, "Failed to read remote credentials");
+            None
+        })?;
+        serde_json::from_str(&value)
+            .inspect_err(|e| {
+                tracing::warn!(source = %self.id, error = %e, "Stored remote credentials are corrupt")
+            })
+            .ok()
+    }
+
+    /// Store `credentials`, replacing any earlier ones. `None` removes them.
+    pub fn set_credentials(
+        &mut self,
+        secrets: &SecretStore,
+        credentials: Option<&RemoteCredentials>,
+    ) -> Result<()> {
+        match credentials {
+            Some(credentials) => {
+                let value = serde_json::to_string(credentials)?;
+                self.credentials = Some(secrets.set(&format!("remote_{}", self.id), &value)?);
+            }
+            None => {
+                if let Some(secret) = self.credentials.take() {
+                    secrets.delete(&secret)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An object in remote storage.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RemoteObject {
+    /// Path within the source, without a leading slash.
+    pub key: String,
+    pub size: u64,
+    /// As the server reports it.
+    pub modified: Option<String>,
+}
+
+/// A remote source with its credentials, ready to list and fetch from.
+#[derive(Debug, Clone)]
+pub struct Remote {
+    source: RemoteSource,
+    credentials: Option<RemoteCredentials>,
+}
+
+impl Remote {
+    pub fn new(source: RemoteSource, credentials: Option<RemoteCredentials>) -> Self {
+        Self {
+            source,
+            credentials,
+        }
+    }
+
+    /// Connect every source in `sources`, reading their credentials.
+    pub fn connect_all(sources: &[RemoteSource], secrets: &SecretStore) -> Vec<Self> {
+        sources
+            .iter()
+            .map(|source| Self::new(source.clone(), source.credentials(secrets)))
+            .collect()
+    }
+
+    pub fn id(&self) -> &str {
+        &self.source.id
+    }
+
+    /// Importable objects whose key starts with `prefix`, in folders below
+    /// it too, sorted by key.
+    pub async fn list(&self, prefix: &str) -> Result<Vec<RemoteObject>> {
+        let client = crate::net::http_client()?;
+        let prefix = prefix.trim_start_matches('/');
+        let mut objects = match &self.source.endpoint {
+            RemoteEndpoint::S3 {
+                url,
+                region,
+                bucket,
+            } => {
+                s3::Bucket::new(url, region, bucket, self.credentials.as_ref())
+                    .list(&client, prefix)
+                    .await?
+            }
+            RemoteEndpoint::WebDav { url } => {
+                webdav::list(&client, url, self.credentials.as_ref(), prefix).await?
+            }
+        };
+        objects.retain(|o| is_importable(&o.key));
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    /// Fetch the object at `key`, checked as a URL download is.
+    pub async fn fetch(&self, key: &str) -> Result<Download> {
+        let client = crate::net::http_client()?;
+        let url = self.object_url(key);
+        let request = match &self.source.endpoint {
+            RemoteEndpoint::S3 {
+                url,
+                region,
+                bucket,
+            } => {
+                s3::Bucket::new(url, region, bucket, self.credentials.as_ref()).get(&client, key)?
+            }
+            RemoteEndpoint::WebDav { .. } => {
+                webdav::authorize(client.get(&url), self.credentials.as_ref())
+            }
+        };
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {}", url))?
+            .error_for_status()?;
+        crate::net::read_document(response, &url).await
+    }
+
+    /// Where `key` lives, recorded as the imported document's source.
+    pub fn object_url(&self, key: &str) -> String {
+        match &self.source.endpoint {
+            RemoteEndpoint::S3 { url, bucket, .. } => format!(
+                "{}/{}/{}",
+                url.trim_end_matches('/'),
+                uri_encode(bucket, false),
+                uri_encode(key, true)
+            ),
+            RemoteEndpoint::WebDav { url } => {
+                format!("{}/{}", url.trim_end_matches('/'), uri_encode(key, true))
+            }
+        }
+    }
+}
+
+/// The queued path standing for `key` in source `source_id`.
+pub fn queued_path(source_id: &str, key: &str) -> PathBuf {
+    PathBuf::from(format!("{}{}/{}", QUEUED_SCHEME, source_id, key))
+}
+
+/// The source ID and key of a queued remote path.
+pub fn parse_queued(path: &Path) -> Option<(&str, &str)> {
+    path.to_str()?.strip_prefix(QUEUED_SCHEME)?.split_once('/')
+}
+
+/// Whether an object named `key` is a type that can be imported.
+fn is_importable(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    IMPORTABLE_EXTENSIONS.iter().any(|ext| key.ends_with(ext))
+}
+
+/// Percent-encode everything but unreserved characters (and `/` with
+/// `keep_slash`), as S3 signing requires and any server accepts.
+fn uri_encode(s: &str, keep_slash: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            b'/' if keep_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+/// Element names along the way to the current XML node, without
+/// namespace prefixes, for the listing parsers.
+#[derive(Default)]
+struct XmlPath(Vec<String>);
+
+impl XmlPath {
+    fn push(&mut self, name: quick_xml::name::QName<'_>) {
+        self.0
+            .push(String::from_utf8_lossy(name.local_name().as_ref()).into_owned());
+    }
+
+    fn pop(&mut self) -> Option<String> {
+        self.0.pop()
+    }
+
+    /// Whether the current node is `names`, innermost last.
+    fn ends_with(&self, names: &[&str]) -> bool {
+        self.0.len() >= names.len()
+            && self.0[self.0.len() - names.len()..]
+                .iter()
+                .zip(names)
+                .all(|(a, b)| a.eq_ignore_ascii_case(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queued_path_round_trip() {
+        let path = queued_path("tips", "2024/leak dump/memo.pdf");
+        assert_eq!(
+            parse_queued(&path),
+            Some(("tips", "2024/leak dump/memo.pdf"))
+        );
+        assert_eq!(parse_queued(Path::new("/home/a/memo.pdf")), None);
+    }
+
+    #[test]
+    fn test_object_url() {
+        let remote = Remote::new(
+            RemoteSource {
+                id: "s".to_string(),
+                name: "Dump".to_string(),
+                endpoint: RemoteEndpoint::S3 {
+                    url: "https://s3.example.com/".to_string(),
+                    region: "us-east-1".to_string(),
+                    bucket: "dump".to_string(),
+                },
+                credentials: None,
+            },
+            None,
+        );
+        assert_eq!(
+            remote.object_url("a b/c+d.pdf"),
+            "https://s3.example.com/dump/a%20b/c%2Bd.pdf"
+        );
+    }
+}
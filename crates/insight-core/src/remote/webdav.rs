@@ -0,0 +1,195 @@
+//! WebDAV servers: listing with PROPFIND, one folder at a time, and
+//! fetching with GET, with basic authentication when there are
+//! credentials.
+
+use anyhow::{Context, Result};
+use quick_xml::events::Event;
+
+use super::{uri_encode, RemoteCredentials, RemoteObject, XmlPath, MAX_LISTED};
+use crate::net::percent_decode;
+
+/// Properties asked for in each listing.
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop><d:resourcetype/><d:getcontentlength/><d:getlastmodified/></d:prop>
+</d:propfind>"#;
+
+/// One `response` of a PROPFIND multistatus.
+#[derive(Debug, Default, PartialEq)]
+struct Entry {
+    href: String,
+    is_folder: bool,
+    size: u64,
+    modified: Option<String>,
+}
+
+pub(super) fn authorize(
+    request: reqwest::RequestBuilder,
+    credentials: Option<&RemoteCredentials>,
+) -> reqwest::RequestBuilder {
+    match credentials {
+        Some(credentials) => request.basic_auth(&credentials.user, Some(&credentials.secret)),
+        None => request,
+    }
+}
+
+/// Every file below the folder `prefix` of `url`, up to [`MAX_LISTED`].
+/// Servers often refuse `Depth: infinity`, so folders are walked one by
+/// one.
+pub(super) async fn list(
+    client: &reqwest::Client,
+    url: &str,
+    credentials: Option<&RemoteCredentials>,
+    prefix: &str,
+) -> Result<Vec<RemoteObject>> {
+    let root = reqwest::Url::parse(&format!("{}/", url.trim_end_matches('/')))
+        .context("Invalid WebDAV URL")?;
+    let root_path = percent_decode(root.path());
+    let method = reqwest::Method::from_bytes(b"PROPFIND").expect("valid method");
+
+    let mut objects = Vec::new();
+    let mut folders = vec![match prefix.trim_end_matches('/') {
+        "" => String::new(),
+        folder => format!("{}/", folder),
+    }];
+    while let Some(folder) = folders.pop() {
+        let folder_url = root
+            .join(&uri_encode(&folder, true))
+            .context("Invalid WebDAV path")?;
+        let response = authorize(
+            client.request(method.clone(), folder_url.clone()),
+            credentials,
+        )
+        .header("Depth", "1")
+        .header(reqwest::header::CONTENT_TYPE, "application/xml")
+        .body(PROPFIND_BODY)
+        .send()
+        .await
+        .with_context(|| format!("Failed to list {}", folder_url))?
+        .error_for_status()?;
+
+        for entry in parse_multistatus(&response.text().await?)? {
+            // Hrefs may be paths or full URLs.
+            let Ok(href) = folder_url.join(&entry.href) else {
+                continue;
+            };
+            let Some(key) = percent_decode(href.path())
+                .strip_prefix(&root_path)
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            if entry.is_folder {
+                // The folder itself is listed along with its contents.
+                let key = format!("{}/", key.trim_end_matches('/'));
+                if key != folder && key != "/" {
+                    folders.push(key);
+                }
+            } else {
+                objects.push(RemoteObject {
+                    key,
+                    size: entry.size,
+                    modified: entry.modified,
+                });
+                if objects.len() >= MAX_LISTED {
+                    tracing::warn!(url = %url, "WebDAV listing cut off at {} files", MAX_LISTED);
+                    return Ok(objects);
+                }
+            }
+        }
+    }
+    Ok(objects)
+}
+
+fn parse_multistatus(xml: &str) -> Result<Vec<Entry>> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut path = XmlPath::default();
+    let mut entries = Vec::new();
+    let mut entry: Option<Entry> = None;
+    loop {
+        match reader.read_event().context("Unreadable WebDAV listing")? {
+            Event::Start(e) => {
+                path.push(e.name());
+                if path.ends_with(&["response"]) {
+                    entry = Some(Entry::default());
+                }
+            }
+            Event::Empty(e) => {
+                path.push(e.name());
+                if path.ends_with(&["resourcetype", "collection"]) {
+                    if let Some(ref mut entry) = entry {
+                        entry.is_folder = true;
+                    }
+                }
+                path.pop();
+            }
+            Event::End(_) => {
+                let name = path.pop();
+                if name.as_deref() == Some("response") {
+                    entries.extend(entry.take().filter(|e| !e.href.is_empty()));
+                }
+            }
+            Event::Text(text) => {
+                let Some(ref mut entry) = entry else {
+                    continue;
+                };
+                let text = text.unescape()?.into_owned();
+                if path.ends_with(&["response", "href"]) {
+                    entry.href = text;
+                } else if path.ends_with(&["prop", "getcontentlength"]) {
+                    entry.size = text.parse().unwrap_or_default();
+                } else if path.ends_with(&["prop", "getlastmodified"]) {
+                    entry.modified = Some(text);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multistatus() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response>
+    <d:href>/dav/dump/</d:href>
+    <d:propstat><d:prop>
+      <d:resourcetype><d:collection/></d:resourcetype>
+    </d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/dump/memo%201.pdf</d:href>
+    <d:propstat><d:prop>
+      <d:resourcetype/>
+      <d:getcontentlength>2048</d:getcontentlength>
+      <d:getlastmodified>Fri, 01 Mar 2024 10:00:00 GMT</d:getlastmodified>
+    </d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>
+  </d:response>
+</d:multistatus>"#;
+        let entries = parse_multistatus(xml).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                Entry {
+                    href: "/dav/dump/".to_string(),
+                    is_folder: true,
+                    size: 0,
+                    modified: None,
+                },
+                Entry {
+                    href: "/dav/dump/memo%201.pdf".to_string(),
+                    is_folder: false,
+                    size: 2048,
+                    modified: Some("Fri, 01 Mar 2024 10:00:00 GMT".to_string()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,239 @@
+//! Searches kept for later and alerts on new matches.
+//!
+//! Each collection has its own JSON file of saved searches, written by the
+//! agent's `save_search` tool or from the UI. A search flagged `notify`
+//! remembers which documents it has already matched, so re-running it after
+//! an import reports only the documents that are new since the last check.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::agent::tools::SourcePassage;
+use crate::agent::{self, AgentContext};
+use crate::collection_files;
+use crate::{AppState, CollectionInfo};
+
+/// Passages each alert check looks at. Documents matching further down
+/// aren't reported.
+const ALERT_SEARCH_LIMIT: usize = 50;
+
+/// One saved search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    /// Report documents that start matching after the search was saved.
+    #[serde(default)]
+    pub notify: bool,
+    /// Documents already matched, so alerts only report new ones.
+    #[serde(default)]
+    pub seen_document_ids: Vec<String>,
+    pub created_at: String,
+    #[serde(default)]
+    pub last_checked_at: Option<String>,
+}
+
+/// Documents that newly match a saved search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchAlert {
+    pub search_id: String,
+    pub search_name: String,
+    pub collection_id: String,
+    pub matches: Vec<SourcePassage>,
+}
+
+/// Names saved-search files in error messages.
+const WHAT: &str = "saved searches";
+
+/// A collection's saved searches, oldest first. Empty when none were saved.
+pub fn load_saved_searches(dir: &Path, collection_id: &str) -> Result<Vec<SavedSearch>> {
+    collection_files::load_list(dir, collection_id, WHAT)
+}
+
+fn store_saved_searches(dir: &Path, collection_id: &str, searches: &[SavedSearch]) -> Result<()> {
+    collection_files::store_list(dir, collection_id, searches, WHAT)
+}
+
+/// Save `query` under `name`. Saving a query that is already saved (ignoring
+/// case and spacing) renames it and updates its `notify` flag instead of
+/// adding a duplicate. `seen_document_ids` are the documents the query
+/// matches now, which later alerts won't report.
+pub fn save_search(
+    dir: &Path,
+    collection_id: &str,
+    name: &str,
+    query: &str,
+    notify: bool,
+    seen_document_ids: Vec<String>,
+) -> Result<SavedSearch> {
+    let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    if query.is_empty() {
+        bail!("Nothing to search for");
+    }
+    let name = match name.trim() {
+        "" => query.clone(),
+        name => name.to_string(),
+    };
+
+    let mut searches = load_saved_searches(dir, collection_id)?;
+    let search = match searches
+        .iter_mut()
+        .find(|s| s.query.eq_ignore_ascii_case(&query))
+    {
+        Some(existing) => {
+            existing.name = name;
+            existing.notify = notify;
+            existing.clone()
+        }
+        None => {
+            let search = SavedSearch {
+                id: uuid::Uuid::new_v4().to_string(),
+                name,
+                query,
+                notify,
+                seen_document_ids,
+                created_at: chrono::Utc::now().to_rfc3339(),
+                last_checked_at: None,
+            };
+            searches.push(search.clone());
+            search
+        }
+    };
+    store_saved_searches(dir, collection_id, &searches)?;
+    Ok(search)
+}
+
+/// Turn alerts for a saved search on or off. Returns whether it existed.
+pub fn set_notify(dir: &Path, collection_id: &str, search_id: &str, notify: bool) -> Result<bool> {
+    let mut searches = load_saved_searches(dir, collection_id)?;
+    let Some(search) = searches.iter_mut().find(|s| s.id == search_id) else {
+        return Ok(false);
+    };
+    search.notify = notify;
+    store_saved_searches(dir, collection_id, &searches)?;
+    Ok(true)
+}
+
+/// Delete one saved search. Returns whether it existed.
+pub fn delete_saved_search(dir: &Path, collection_id: &str, search_id: &str) -> Result<bool> {
+    collection_files::remove_from_list(dir, collection_id, WHAT, |s: &SavedSearch| {
+        s.id == search_id
+    })
+}
+
+/// Delete every saved search of a collection, e.g. when it is deleted.
+/// Missing files are treated as success.
+pub fn delete_saved_searches(dir: &Path, collection_id: &str) -> Result<()> {
+    collection_files::delete_list(dir, collection_id, WHAT)
+}
+
+/// The passages of documents `search` hasn't matched before, one per
+/// document, and mark those documents seen.
+fn take_new_matches(search: &mut SavedSearch, passages: Vec<SourcePassage>) -> Vec<SourcePassage> {
+    let mut seen: HashSet<String> = search.seen_document_ids.iter().cloned().collect();
+    let mut new = Vec::new();
+    for passage in passages {
+        if seen.insert(passage.document_id.clone()) {
+            search.seen_document_ids.push(passage.document_id.clone());
+            new.push(passage);
+        }
+    }
+    new
+}
+
+/// Re-run a collection's `notify` searches and report documents that match
+/// for the first time. Searches with nothing new are left out.
+pub async fn check_alerts(
+    state: &AppState,
+    collection: CollectionInfo,
+) -> Result<Vec<SearchAlert>> {
+    let dir = state.config.saved_searches_dir();
+    let collection_id = collection.id.clone();
+    let mut searches = load_saved_searches(&dir, &collection_id)?;
+    if !searches.iter().any(|s| s.notify) {
+        return Ok(Vec::new());
+    }
+
+    let ctx = AgentContext::new(state.clone(), Some(vec![collection]));
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut alerts = Vec::new();
+    for search in searches.iter_mut().filter(|s| s.notify) {
+        let passages =
+            agent::tools::search_passages(&ctx, &search.query, ALERT_SEARCH_LIMIT).await?;
+        let matches = take_new_matches(search, passages);
+        search.last_checked_at = Some(now.clone());
+        if !matches.is_empty() {
+            alerts.push(SearchAlert {
+                search_id: search.id.clone(),
+                search_name: search.name.clone(),
+                collection_id: collection_id.clone(),
+                matches,
+            });
+        }
+    }
+    store_saved_searches(&dir, &collection_id, &searches)?;
+    Ok(alerts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passage(document_id: &str) -> SourcePassage {
+        SourcePassage {
+            collection_id: "abc123".to_string(),
+            document_id: document_id.to_string(),
+            document_name: format!("{}.pdf", document_id),
+            page: Some(1),
+            quote: "Acme".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_save_load_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().join("saved_searches");
+        assert!(load_saved_searches(&dir, "abc123").unwrap().is_empty());
+
+        let first = save_search(&dir, "abc123", "", " Acme  payments ", false, vec![]).unwrap();
+        assert_eq!(first.name, "Acme payments");
+        let again = save_search(&dir, "abc123", "Acme", "acme payments", true, vec![]).unwrap();
+        assert_eq!(again.id, first.id);
+        assert_eq!(again.name, "Acme");
+        assert!(again.notify);
+        save_search(&dir, "abc123", "CFO", "CFO resignation", false, vec![]).unwrap();
+        assert_eq!(load_saved_searches(&dir, "abc123").unwrap().len(), 2);
+
+        assert!(set_notify(&dir, "abc123", &first.id, false).unwrap());
+        assert!(!load_saved_searches(&dir, "abc123").unwrap()[0].notify);
+
+        assert!(delete_saved_search(&dir, "abc123", &first.id).unwrap());
+        assert!(!delete_saved_search(&dir, "abc123", &first.id).unwrap());
+        delete_saved_searches(&dir, "abc123").unwrap();
+        assert!(load_saved_searches(&dir, "abc123").unwrap().is_empty());
+        assert!(save_search(&dir, "../x", "", "q", false, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_take_new_matches() {
+        let mut search = SavedSearch {
+            id: "s".to_string(),
+            name: "Acme".to_string(),
+            query: "acme".to_string(),
+            notify: true,
+            seen_document_ids: vec!["old".to_string()],
+            created_at: String::new(),
+            last_checked_at: None,
+        };
+        let passages = vec![passage("old"), passage("new"), passage("new")];
+        let new = take_new_matches(&mut search, passages);
+
+        assert_eq!(new, vec![passage("new")]);
+        assert_eq!(search.seen_document_ids, vec!["old", "new"]);
+        assert!(take_new_matches(&mut search, vec![passage("new")]).is_empty());
+    }
+}
@@ -258,6 +258,7 @@ mod tests {
             start_page: 1,
             end_page: 1,
             vector: None,
+            embedder: None,
         }];
 
         let result = handle.index_chunks(chunks).await;
@@ -281,6 +282,7 @@ mod tests {
             start_page: 1,
             end_page: 1,
             vector: None,
+            embedder: None,
         }];
         handle.index_chunks(chunks).await.unwrap();
 
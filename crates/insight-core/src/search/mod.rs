@@ -376,6 +376,9 @@ pub struct ChunkToIndex {
     pub end_page: usize,
     /// Pre-computed embedding vector for this chunk
     pub vector: Option<Vec<f32>>,
+    /// Index embedder the vector belongs to (None = [`DEFAULT_EMBEDDER`]).
+    /// Projected collections use their own embedder per vector space.
+    pub embedder: Option<String>,
 }
 
 /// Maximum chunks per indexing batch
@@ -451,7 +454,8 @@ fn index_chunk_batch(
             m.insert("end_page".to_string(), Value::Number(chunk.end_page.into()));
             // Add pre-computed vector if present (single vector, not array)
             if let Some(ref vector) = chunk.vector {
-                m.insert("_vectors".to_string(), json!({ "default": [vector] }));
+                let embedder = chunk.embedder.as_deref().unwrap_or(DEFAULT_EMBEDDER);
+                m.insert("_vectors".to_string(), json!({ embedder: [vector] }));
             }
             m
         })
@@ -542,18 +546,25 @@ pub fn compute_hit_score(scores: &[milli::score_details::ScoreDetails]) -> f64 {
     milli::score_details::ScoreDetails::global_score(scores.iter())
 }
 
+/// Index embedder for the active model's native vectors.
+pub const DEFAULT_EMBEDDER: &str = "default";
+
 /// Parameters for searching the index
 pub struct SearchParams<'a> {
     pub query: &'a str,
     pub limit: usize,
     pub offset: usize,
     pub collection_ids: Option<&'a [String]>,
+    /// Only search chunks of these documents (None or empty = all)
+    pub document_ids: Option<&'a [String]>,
     /// Pre-computed query embedding for semantic search
     pub query_vector: Option<Vec<f32>>,
     /// Balance between keyword (0.0) and semantic (1.0) search
     pub semantic_ratio: f32,
     /// Filter out results below this score threshold
     pub min_score: Option<f32>,
+    /// Embedder `query_vector` belongs to (None = [`DEFAULT_EMBEDDER`])
+    pub embedder: Option<&'a str>,
 }
 
 impl Default for SearchParams<'_> {
@@ -563,9 +574,11 @@ impl Default for SearchParams<'_> {
             limit: 20,
             offset: 0,
             collection_ids: None,
+            document_ids: None,
             query_vector: None,
             semantic_ratio: 0.0,
             min_score: None,
+            embedder: None,
         }
     }
 }
@@ -577,10 +590,9 @@ fn execute_hybrid_search<'a>(
     search: &mut milli::Search<'a>,
     query_vector: Vec<f32>,
     semantic_ratio: f32,
+    embedder_name: &str,
 ) -> Result<milli::SearchResult> {
-    const EMBEDDER_NAME: &str = "default";
-
-    let Some((embedder, quantized)) = get_embedder_from_index(index, rtxn, EMBEDDER_NAME)? else {
+    let Some((embedder, quantized)) = get_embedder_from_index(index, rtxn, embedder_name)? else {
         tracing::warn!("Embedder not configured, falling back to keyword search");
         return Ok(search.execute()?);
     };
@@ -592,7 +604,7 @@ fn execute_hybrid_search<'a>(
     );
 
     search.semantic(
-        EMBEDDER_NAME.to_string(),
+        embedder_name.to_string(),
         embedder,
         quantized,
         Some(query_vector),
@@ -623,9 +635,11 @@ pub fn search_index(index: &Index, params: SearchParams<'_>) -> Result<SearchRes
         limit,
         offset,
         collection_ids,
+        document_ids,
         query_vector,
         semantic_ratio,
         min_score,
+        embedder,
     } = params;
 
     let rtxn = index.read_txn()?;
@@ -638,11 +652,23 @@ pub fn search_index(index: &Index, params: SearchParams<'_>) -> Result<SearchRes
     search.exhaustive_number_hits(true);
     search.terms_matching_strategy(TermsMatchingStrategy::Last);
 
-    // Apply collection filter
-    let filter_str = collection_ids.filter(|ids| !ids.is_empty()).map(|ids| {
+    // Apply collection and document filters
+    let in_filter = |field: &str, ids: &[String]| {
         let quoted: Vec<String> = ids.iter().map(|id| format!("\"{}\"", id)).collect();
-        format!("collection_id IN [{}]", quoted.join(", "))
-    });
+        format!("{} IN [{}]", field, quoted.join(", "))
+    };
+    let filters: Vec<String> = [
+        collection_ids
+            .filter(|ids| !ids.is_empty())
+            .map(|ids| in_filter("collection_id", ids)),
+        document_ids
+            .filter(|ids| !ids.is_empty())
+            .map(|ids| in_filter("parent_id", ids)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let filter_str = (!filters.is_empty()).then(|| filters.join(" AND "));
     if let Some(ref fs) = filter_str {
         if let Some(f) = parse_index_filter(fs)? {
             search.filter(f);
@@ -651,7 +677,14 @@ pub fn search_index(index: &Index, params: SearchParams<'_>) -> Result<SearchRes
 
     // Execute search (hybrid if semantic enabled, otherwise keyword-only)
     let result = match query_vector.filter(|_| semantic_ratio > 0.0) {
-        Some(vec) => execute_hybrid_search(index, &rtxn, &mut search, vec, semantic_ratio)?,
+        Some(vec) => execute_hybrid_search(
+            index,
+            &rtxn,
+            &mut search,
+            vec,
+            semantic_ratio,
+            embedder.unwrap_or(DEFAULT_EMBEDDER),
+        )?,
         None => search.execute()?,
     };
 
@@ -718,6 +751,25 @@ pub fn get_document_by_external_id(index: &Index, external_id: &str) -> Result<O
     }
 }
 
+/// Every document with chunks in the index, as parent ID → collection ID.
+pub fn indexed_documents(index: &Index) -> Result<HashMap<String, String>> {
+    let rtxn = index.read_txn()?;
+
+    // One chunk at a time: the whole index may not fit in memory.
+    let mut documents = HashMap::new();
+    for id in index.documents_ids(&rtxn)? {
+        let Some(chunk) = get_document(index, &rtxn, id)? else {
+            continue;
+        };
+        let field = |name: &str| chunk.get(name).and_then(|v| v.as_str()).map(String::from);
+        if let (Some(parent_id), Some(collection_id)) = (field("parent_id"), field("collection_id"))
+        {
+            documents.insert(parent_id, collection_id);
+        }
+    }
+    Ok(documents)
+}
+
 /// Delete all chunks belonging to a document by its parent ID
 pub fn delete_document_chunks(
     index: &Index,
@@ -1136,6 +1188,22 @@ mod tests {
         )
         .unwrap();
         assert_eq!(two.hits.len(), 2);
+
+        // Filter to documents, within the collection filter
+        let docs = search_index(
+            &index,
+            SearchParams {
+                query: "climate",
+                limit: 10,
+                collection_ids: Some(&["climate".to_string(), "news".to_string()]),
+                document_ids: Some(&["doc2".to_string(), "doc3".to_string()]),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(docs.hits.len(), 1);
+        let name = get_field(&index, docs.hits[0].doc_id, "parent_name");
+        assert_eq!(name, Some("b.pdf".to_string()));
     }
 
     #[test]
@@ -1234,6 +1302,7 @@ mod tests {
                 start_page: 1,
                 end_page: 1,
                 vector: None,
+                embedder: None,
             },
             ChunkToIndex {
                 id: "doc2_chunk_0".to_string(),
@@ -1246,6 +1315,7 @@ mod tests {
                 start_page: 1,
                 end_page: 1,
                 vector: None,
+                embedder: None,
             },
         ];
         index_chunks_batch(&index, &config, chunks).unwrap();
@@ -0,0 +1,301 @@
+//! API keys and other secrets, kept out of the settings file.
+//!
+//! Secrets go to the platform keychain (macOS Keychain, Windows Credential
+//! Manager, the Secret Service on Linux). Where there is no keychain, as on
+//! a headless Linux box, they fall back to a separate file readable only by
+//! the user. Settings hold a [`SecretRef`] naming the secret and where it
+//! went, never the value.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::Settings;
+
+/// Service name secrets are filed under in the keychain.
+const KEYCHAIN_SERVICE: &str = "insight";
+
+/// Settings fields that held API keys in plain text, by provider type.
+const LEGACY_KEY_FIELDS: &[(&str, &str)] = &[
+    ("openai_api_key", "openai"),
+    ("anthropic_api_key", "anthropic"),
+    ("openrouter_api_key", "openrouter"),
+    ("mistral_api_key", "mistral"),
+    ("groq_api_key", "groq"),
+    ("openai_compatible_api_key", "openai_compatible"),
+];
+
+/// Where a secret is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretBackend {
+    Keychain,
+    File,
+}
+
+/// A stored secret's name and where to find it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretRef {
+    pub name: String,
+    pub backend: SecretBackend,
+}
+
+/// Reads and writes secrets in the keychain, or the fallback file.
+#[derive(Debug, Clone)]
+pub struct SecretStore {
+    /// Fallback file, a JSON object of secrets by name.
+    file: PathBuf,
+    keychain: bool,
+}
+
+impl SecretStore {
+    pub fn new(file: PathBuf) -> Self {
+        Self {
+            file,
+            keychain: true,
+        }
+    }
+
+    /// A store that never touches the keychain.
+    pub fn file_only(file: PathBuf) -> Self {
+        Self {
+            file,
+            keychain: false,
+        }
+    }
+
+    /// Store `value` under `name`, in the keychain if there is one.
+    pub fn set(&self, name: &str, value: &str) -> Result<SecretRef> {
+        if self.keychain {
+            match keychain_entry(name).and_then(|entry| Ok(entry.set_password(value)?)) {
+                Ok(()) => {
+                    // Don't leave an older copy behind in the file.
+                    if let Err(e) = self.remove_from_file(name) {
+                        warn!(secret = name, error = %e, "Failed to remove secret from file");
+                    }
+                    return Ok(SecretRef {
+                        name: name.to_string(),
+                        backend: SecretBackend::Keychain,
+                    });
+                }
+                Err(e) => {
+                    warn!(secret = name, error = %e, "Keychain unavailable, using the secrets file")
+                }
+            }
+        }
+
+        let mut secrets = self.read_file()?;
+        secrets.insert(name.to_string(), value.to_string());
+        self.write_file(&secrets)?;
+        Ok(SecretRef {
+            name: name.to_string(),
+            backend: SecretBackend::File,
+        })
+    }
+
+    /// The secret `secret` refers to, or `None` if it's gone.
+    pub fn get(&self, secret: &SecretRef) -> Result<Option<String>> {
+        match secret.backend {
+            SecretBackend::Keychain => match keychain_entry(&secret.name)?.get_password() {
+                Ok(value) => Ok(Some(value)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => Err(e).context("Failed to read from the keychain"),
+            },
+            SecretBackend::File => Ok(self.read_file()?.remove(&secret.name)),
+        }
+    }
+
+    /// Delete the secret `secret` refers to. Deleting a missing secret
+    /// succeeds.
+    pub fn delete(&self, secret: &SecretRef) -> Result<()> {
+        match secret.backend {
+            SecretBackend::Keychain => match keychain_entry(&secret.name)?.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(e) => Err(e).context("Failed to delete from the keychain"),
+            },
+            SecretBackend::File => self.remove_from_file(&secret.name),
+        }
+    }
+
+    fn remove_from_file(&self, name: &str) -> Result<()> {
+        let mut secrets = self.read_file()?;
+        if secrets.remove(name).is_some() {
+            self.write_file(&secrets)?;
+        }
+        Ok(())
+    }
+
+    fn read_file(&self) -> Result<BTreeMap<String, String>> {
+        match std::fs::read_to_string(&self.file) {
+            Ok(contents) => serde_json::from_str(&contents).context("Secrets file is corrupt"),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e).context("Failed to read the secrets file"),
+        }
+    }
+
+    fn write_file(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        if let Some(dir) = self.file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string_pretty(secrets)?;
+        write_private(&self.file, contents.as_bytes()).context("Failed to write the secrets file")
+    }
+}
+
+fn keychain_entry(name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, name).context("Failed to open the keychain")
+}
+
+/// Write `contents` to `path`, readable and writable by the user only.
+#[cfg(unix)]
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // `mode` only applies to new files.
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, contents)
+}
+
+/// Move API keys an older version left in the settings file into `store`,
+/// leaving references in their place. Does nothing once done.
+pub fn migrate_settings(settings_file: &Path, store: &SecretStore) -> Result<()> {
+    let contents = match std::fs::read_to_string(settings_file) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).context("Failed to read settings"),
+    };
+    let mut value: serde_json::Value =
+        serde_json::from_str(&contents).context("Failed to parse settings")?;
+    let Some(object) = value.as_object_mut() else {
+        return Ok(());
+    };
+
+    let mut keys = Vec::new();
+    for (field, provider) in LEGACY_KEY_FIELDS {
+        if let Some(key) = object.remove(*field) {
+            keys.push((*provider, key));
+        }
+    }
+    if let Some(provider) = object.get_mut("provider").and_then(|p| p.as_object_mut()) {
+        let key = provider.remove("api_key");
+        let provider_type = provider.get("type").and_then(|t| t.as_str());
+        let provider_type = LEGACY_KEY_FIELDS
+            .iter()
+            .map(|(_, p)| *p)
+            .find(|p| Some(*p) == provider_type);
+        if let (Some(key), Some(provider_type)) = (key, provider_type) {
+            // The active provider's key wins over an older stored one.
+            keys.retain(|(p, _)| *p != provider_type);
+            keys.push((provider_type, key));
+        }
+    }
+    if keys.is_empty() {
+        return Ok(());
+    }
+
+    let mut settings: Settings =
+        serde_json::from_value(value).context("Failed to parse settings")?;
+    let mut moved = 0;
+    for (provider, key) in keys {
+        if let Some(key) = key.as_str().filter(|k| !k.is_empty()) {
+            settings.set_api_key(store, provider, key)?;
+            moved += 1;
+        }
+    }
+    settings
+        .save(&settings_file.to_path_buf())
+        .context("Failed to save settings")?;
+    info!(keys = moved, "Moved API keys out of the settings file");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_store_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SecretStore::file_only(dir.path().join("secrets.json"));
+
+        let secret = store.set("openai_api_key", "sk-1").unwrap();
+        assert_eq!(secret.backend, SecretBackend::File);
+        assert_eq!(store.get(&secret).unwrap().as_deref(), Some("sk-1"));
+
+        store.set("openai_api_key", "sk-2").unwrap();
+        assert_eq!(store.get(&secret).unwrap().as_deref(), Some("sk-2"));
+
+        store.delete(&secret).unwrap();
+        assert_eq!(store.get(&secret).unwrap(), None);
+        store.delete(&secret).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_secrets_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("secrets.json");
+        SecretStore::file_only(file.clone())
+            .set("groq_api_key", "gsk")
+            .unwrap();
+        let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_migrate_moves_plaintext_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings_file = dir.path().join("settings.json");
+        let store = SecretStore::file_only(dir.path().join("secrets.json"));
+        std::fs::write(
+            &settings_file,
+            r#"{
+                "provider": {"type": "anthropic", "api_key": "sk-ant", "model": "claude"},
+                "openai_api_key": "sk-openai",
+                "anthropic_api_key": "sk-old",
+                "openai_compatible_api_key": ""
+            }"#,
+        )
+        .unwrap();
+
+        migrate_settings(&settings_file, &store).unwrap();
+
+        let contents = std::fs::read_to_string(&settings_file).unwrap();
+        assert!(!contents.contains("sk-"));
+        let settings = Settings::load(&settings_file);
+        assert_eq!(
+            settings.api_key(&store, "anthropic").as_deref(),
+            Some("sk-ant")
+        );
+        assert_eq!(
+            settings.api_key(&store, "openai").as_deref(),
+            Some("sk-openai")
+        );
+        assert_eq!(settings.api_key(&store, "openai_compatible"), None);
+        assert_eq!(
+            settings.provider.as_ref().map(|p| p.model_id()),
+            Some("claude")
+        );
+
+        // A second run finds nothing to move.
+        migrate_settings(&settings_file, &store).unwrap();
+        assert_eq!(std::fs::read_to_string(&settings_file).unwrap(), contents);
+    }
+}
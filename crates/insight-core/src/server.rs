@@ -0,0 +1,387 @@
+//! HTTP API for running Insight headless, as a node shared by scripts and
+//! intranet tools.
+//!
+//! Every route is under `/api` and needs `Authorization: Bearer <token>`.
+//! The token is created on first start and kept in the secret store;
+//! `INSIGHT_API_TOKEN` overrides it. Imports take paths on the server, or
+//! http(s) URLs, and run in the background like imports from the app.
+//!
+//! ```text
+//! GET  /api/collections
+//! POST /api/collections                          {"name": "..."}
+//! GET  /api/collections/{id}/documents
+//! GET  /api/collections/{id}/documents/{doc_id}
+//! GET  /api/collections/{id}/documents/{doc_id}/text
+//! POST /api/collections/{id}/import              {"paths": ["..."]}
+//! GET  /api/search?q=...&collection=...&limit=...
+//! ```
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use iroh_docs::NamespaceId;
+use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::agent::tools::{search_passages, SourcePassage};
+use crate::agent::AgentContext;
+use crate::config::Settings;
+use crate::pipeline::PipelineProgress;
+use crate::storage::DocumentMetadata;
+use crate::{AppState, CollectionInfo};
+
+/// Environment variable overriding the stored API token.
+pub const TOKEN_ENV: &str = "INSIGHT_API_TOKEN";
+
+/// Name the API token is stored under in the secret store.
+const TOKEN_SECRET: &str = "api_token";
+
+/// Search results returned when a request doesn't say.
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// Most search results a request may ask for.
+const MAX_SEARCH_LIMIT: usize = 100;
+
+/// The token clients must send: from [`TOKEN_ENV`] if set, otherwise the
+/// stored one. Returns whether it was just created, so it can be shown
+/// once.
+pub fn api_token(state: &AppState) -> Result<(String, bool)> {
+    if let Ok(token) = std::env::var(TOKEN_ENV) {
+        if !token.trim().is_empty() {
+            return Ok((token.trim().to_string(), false));
+        }
+    }
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    if let Some(secret) = &settings.server.token {
+        if let Some(token) = state.secrets.get(secret)? {
+            return Ok((token, false));
+        }
+    }
+
+    let token: String = rand::random::<[u8; 32]>()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    settings.server.token = Some(state.secrets.set(TOKEN_SECRET, &token)?);
+    settings
+        .save(&state.config.settings_file)
+        .context("Failed to save settings")?;
+    Ok((token, true))
+}
+
+/// Serve the API on `address` until `cancel` fires.
+pub async fn serve(
+    state: AppState,
+    address: SocketAddr,
+    token: String,
+    cancel: CancellationToken,
+) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to listen on {}", address))?;
+    tracing::info!("Serving the API on http://{}", address);
+    axum::serve(listener, router(state, token))
+        .with_graceful_shutdown(async move { cancel.cancelled().await })
+        .await?;
+    Ok(())
+}
+
+fn router(state: AppState, token: String) -> Router {
+    let token = Arc::new(token);
+    Router::new()
+        .route(
+            "/api/collections",
+            get(list_collections).post(create_collection),
+        )
+        .route("/api/collections/{id}/documents", get(list_documents))
+        .route(
+            "/api/collections/{id}/documents/{doc_id}",
+            get(get_document),
+        )
+        .route(
+            "/api/collections/{id}/documents/{doc_id}/text",
+            get(get_document_text),
+        )
+        .route("/api/collections/{id}/import", axum::routing::post(import))
+        .route("/api/search", get(search))
+        .layer(middleware::from_fn_with_state(token, require_token))
+        .with_state(state)
+}
+
+/// Whether an `Authorization` header carries `token`. Compares in
+/// constant time so the token can't be guessed byte by byte.
+fn authorized(header: Option<&str>, token: &str) -> bool {
+    let Some(given) = header.and_then(|h| h.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn require_token(State(token): State<Arc<String>>, request: Request, next: Next) -> Response {
+    let header = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok());
+    if !authorized(header, &token) {
+        return ApiError::new(StatusCode::UNAUTHORIZED, "Missing or invalid token").into_response();
+    }
+    next.run(request).await
+}
+
+/// An error response, `{"error": "..."}`.
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn not_found(what: &str) -> Self {
+        Self::new(StatusCode::NOT_FOUND, format!("{} not found", what))
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        tracing::warn!(error = %e, "API request failed");
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({ "error": self.message }));
+        (self.status, body).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+fn namespace(id: &str) -> std::result::Result<NamespaceId, ApiError> {
+    id.parse()
+        .map_err(|_| ApiError::new(StatusCode::BAD_REQUEST, "Invalid collection ID"))
+}
+
+/// The collection `id` names; a 404 if there is none.
+async fn collection(state: &AppState, id: &str) -> std::result::Result<NamespaceId, ApiError> {
+    let namespace_id = namespace(id)?;
+    let storage = state.storage.read().await;
+    match storage.get_collection_metadata(namespace_id).await? {
+        Some(_) => Ok(namespace_id),
+        None => Err(ApiError::not_found("Collection")),
+    }
+}
+
+/// Every collection, with its document and page counts.
+async fn collections(state: &AppState) -> Result<Vec<CollectionInfo>> {
+    let storage = state.storage.read().await;
+    let mut result = Vec::new();
+    for (namespace_id, metadata) in storage.list_collections().await? {
+        let documents = storage
+            .list_documents(namespace_id)
+            .await
+            .unwrap_or_default();
+        result.push(CollectionInfo {
+            id: namespace_id.to_string(),
+            name: metadata.name,
+            document_count: documents.len(),
+            total_pages: documents.iter().map(|d| d.page_count).sum(),
+            created_at: Some(metadata.created_at),
+        });
+    }
+    Ok(result)
+}
+
+async fn list_collections(State(state): State<AppState>) -> ApiResult<Vec<CollectionInfo>> {
+    Ok(Json(collections(&state).await?))
+}
+
+#[derive(Deserialize)]
+struct NewCollection {
+    name: String,
+}
+
+async fn create_collection(
+    State(state): State<AppState>,
+    Json(body): Json<NewCollection>,
+) -> std::result::Result<(StatusCode, Json<CollectionInfo>), ApiError> {
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Name is empty"));
+    }
+    let (namespace_id, metadata) = state.storage.read().await.create_collection(name).await?;
+    state.watch_namespace(namespace_id).await;
+    state.register_author(&[namespace_id]).await;
+    tracing::info!("Created collection '{}' over the API", name);
+
+    let info = CollectionInfo {
+        id: namespace_id.to_string(),
+        name: metadata.name,
+        document_count: 0,
+        total_pages: 0,
+        created_at: Some(metadata.created_at),
+    };
+    Ok((StatusCode::CREATED, Json(info)))
+}
+
+async fn list_documents(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Vec<DocumentMetadata>> {
+    let namespace_id = collection(&state, &id).await?;
+    let storage = state.storage.read().await;
+    Ok(Json(storage.list_documents(namespace_id).await?))
+}
+
+async fn get_document(
+    State(state): State<AppState>,
+    Path((id, doc_id)): Path<(String, String)>,
+) -> ApiResult<DocumentMetadata> {
+    let namespace_id = collection(&state, &id).await?;
+    let storage = state.storage.read().await;
+    storage
+        .get_document(namespace_id, &doc_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Document"))
+}
+
+async fn get_document_text(
+    State(state): State<AppState>,
+    Path((id, doc_id)): Path<(String, String)>,
+) -> std::result::Result<Response, ApiError> {
+    let namespace_id = collection(&state, &id).await?;
+    let storage = state.storage.read().await;
+    let text = storage
+        .get_document_text(namespace_id, &doc_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Document text"))?;
+    let text = String::from_utf8_lossy(&text).into_owned();
+    Ok(([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], text).into_response())
+}
+
+#[derive(Deserialize)]
+struct ImportRequest {
+    paths: Vec<String>,
+}
+
+/// Start importing files into a collection. Returns at once with the
+/// collection's progress; the import goes on in the background.
+async fn import(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<ImportRequest>,
+) -> std::result::Result<(StatusCode, Json<PipelineProgress>), ApiError> {
+    let namespace_id = collection(&state, &id).await?;
+    if body.paths.is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "No paths to import"));
+    }
+
+    let collection_id = namespace_id.to_string();
+    tracing::info!(
+        "Importing {} files into collection {} over the API",
+        body.paths.len(),
+        collection_id
+    );
+    let paths: Vec<PathBuf> = body.paths.iter().map(PathBuf::from).collect();
+    let pipeline = state.pipeline.clone();
+    let events = state.events.clone();
+    let id = collection_id.clone();
+    tokio::spawn(async move {
+        let (success, errors) = pipeline.import_files(namespace_id, paths).await;
+        tracing::info!(
+            "Import complete for {}: {} successful, {} failed",
+            id,
+            success,
+            errors.len()
+        );
+        for (path, error) in &errors {
+            tracing::error!("Failed to import {:?}: {}", path, error);
+        }
+        if let Some(progress) = pipeline.get_progress(&id).await {
+            events.publish(progress);
+        }
+    });
+
+    let progress = state
+        .pipeline
+        .get_progress(&collection_id)
+        .await
+        .unwrap_or_else(|| PipelineProgress {
+            collection_id,
+            ..Default::default()
+        });
+    Ok((StatusCode::ACCEPTED, Json(progress)))
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    /// Collection to search (None = all).
+    collection: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Passages matching a query, best first, by keyword and, once an
+/// embedding model is set up, by meaning.
+async fn search(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+) -> ApiResult<Vec<SourcePassage>> {
+    if query.q.trim().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "Query is empty"));
+    }
+    let collections = match &query.collection {
+        Some(id) => {
+            namespace(id)?;
+            let collection = collections(&state)
+                .await?
+                .into_iter()
+                .find(|c| c.id == *id)
+                .ok_or_else(|| ApiError::not_found("Collection"))?;
+            Some(vec![collection])
+        }
+        None => None,
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+
+    let ctx = AgentContext::new(state, collections);
+    Ok(Json(search_passages(&ctx, query.q.trim(), limit).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorized_needs_the_exact_bearer_token() {
+        assert!(authorized(Some("Bearer secret"), "secret"));
+        assert!(!authorized(Some("Bearer secret2"), "secret"));
+        assert!(!authorized(Some("Bearer secreT"), "secret"));
+        assert!(!authorized(Some("secret"), "secret"));
+        assert!(!authorized(None, "secret"));
+    }
+}
@@ -0,0 +1,235 @@
+//! Spending on remote chat models, with an optional monthly budget.
+//!
+//! Every completion on a remote model adds its tokens and estimated cost
+//! to a ledger of monthly totals, whichever feature made the call: chat,
+//! batch questions, summaries. Once the month's spend reaches the budget
+//! set in Settings, remote calls fail with an error saying so until the
+//! budget is raised or the month ends. Local models are never blocked.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::agent::{ConversationUsage, Message};
+use crate::config::Settings;
+use crate::provider::{
+    ChatProvider, CompletionResult, MemoryKind, Provider, ProviderEvent, SamplingParams,
+    TokenUsage, ToolDefinition,
+};
+
+/// Monthly totals keyed by `YYYY-MM`.
+pub type Ledger = BTreeMap<String, ConversationUsage>;
+
+/// Serializes read-modify-write of the ledger file across concurrent
+/// completions.
+static LEDGER_LOCK: Mutex<()> = Mutex::new(());
+
+/// What has been spent, for the settings page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpendSummary {
+    /// The current month, `YYYY-MM`.
+    pub month: String,
+    pub this_month: ConversationUsage,
+    pub total: ConversationUsage,
+    pub monthly_budget_usd: Option<f64>,
+}
+
+/// Ledger key of the current month.
+pub fn current_month() -> String {
+    chrono::Utc::now().format("%Y-%m").to_string()
+}
+
+/// Monthly totals so far. Empty when nothing was spent yet.
+pub fn load_ledger(path: &Path) -> Result<Ledger> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Ledger::new()),
+        Err(e) => return Err(e).context("Failed to read spend ledger"),
+    };
+    serde_json::from_str(&content).context("Failed to parse spend ledger")
+}
+
+/// Add one completion's usage to `month`.
+pub fn record_spend(
+    path: &Path,
+    month: &str,
+    provider_name: &str,
+    model_id: &str,
+    usage: &TokenUsage,
+) -> Result<()> {
+    let _guard = LEDGER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut ledger = load_ledger(path)?;
+    ledger
+        .entry(month.to_string())
+        .or_default()
+        .record(provider_name, model_id, usage);
+    let content = serde_json::to_string_pretty(&ledger).context("Failed to serialize ledger")?;
+    std::fs::write(path, content).context("Failed to write spend ledger")?;
+    Ok(())
+}
+
+/// Spend in `month` and overall.
+pub fn spend_summary(path: &Path, month: &str, budget: Option<f64>) -> Result<SpendSummary> {
+    let ledger = load_ledger(path)?;
+    let mut total = ConversationUsage::default();
+    for usage in ledger.values() {
+        total.add(usage);
+    }
+    Ok(SpendSummary {
+        month: month.to_string(),
+        this_month: ledger.get(month).copied().unwrap_or_default(),
+        total,
+        monthly_budget_usd: budget,
+    })
+}
+
+/// Fail when `month`'s spend has reached `budget`.
+pub fn check_budget(path: &Path, month: &str, budget: Option<f64>) -> Result<()> {
+    let Some(budget) = budget else {
+        return Ok(());
+    };
+    let spent = load_ledger(path)?
+        .get(month)
+        .map_or(0.0, |usage| usage.estimated_cost_usd);
+    if spent >= budget {
+        bail!(
+            "The monthly budget of ${:.2} for remote models is used up (${:.2} spent in {}). \
+             Raise it in Settings or switch to a local model.",
+            budget,
+            spent,
+            month
+        );
+    }
+    Ok(())
+}
+
+/// Where a [`MeteredChatProvider`] keeps its ledger and reads the budget.
+#[derive(Debug, Clone)]
+pub struct Metering {
+    pub ledger_file: PathBuf,
+    pub settings_file: PathBuf,
+}
+
+/// Wraps a remote chat provider to check the budget before each completion
+/// and record what it cost after.
+pub struct MeteredChatProvider {
+    inner: Arc<dyn ChatProvider>,
+    metering: Metering,
+}
+
+impl MeteredChatProvider {
+    pub fn new(inner: Arc<dyn ChatProvider>, metering: Metering) -> Self {
+        Self { inner, metering }
+    }
+}
+
+#[async_trait]
+impl Provider for MeteredChatProvider {
+    fn provider_name(&self) -> &'static str {
+        self.inner.provider_name()
+    }
+
+    fn model_id(&self) -> &str {
+        self.inner.model_id()
+    }
+
+    fn memory_kind(&self) -> MemoryKind {
+        self.inner.memory_kind()
+    }
+
+    fn coexist(&self) -> bool {
+        self.inner.coexist()
+    }
+
+    fn set_coexist(&self, coexist: bool) {
+        self.inner.set_coexist(coexist);
+    }
+
+    async fn is_loaded(&self) -> bool {
+        self.inner.is_loaded().await
+    }
+
+    async fn ensure_loaded(&self) -> Result<()> {
+        self.inner.ensure_loaded().await
+    }
+
+    async fn unload(&self) -> Result<bool> {
+        self.inner.unload().await
+    }
+}
+
+#[async_trait]
+impl ChatProvider for MeteredChatProvider {
+    fn context_window(&self) -> usize {
+        self.inner.context_window()
+    }
+
+    fn supports_images(&self) -> bool {
+        self.inner.supports_images()
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        sampling: &SamplingParams,
+        event_tx: mpsc::Sender<ProviderEvent>,
+        cancel_token: CancellationToken,
+    ) -> Result<CompletionResult> {
+        let month = current_month();
+        let budget = Settings::load(&self.metering.settings_file).monthly_budget_usd;
+        check_budget(&self.metering.ledger_file, &month, budget)?;
+
+        let result = self
+            .inner
+            .stream_completion(messages, tools, sampling, event_tx, cancel_token)
+            .await?;
+        if let Err(e) = record_spend(
+            &self.metering.ledger_file,
+            &month,
+            self.provider_name(),
+            self.model_id(),
+            &result.usage,
+        ) {
+            tracing::warn!(error = %e, "Failed to record spend");
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spend_summary_and_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spend.json");
+        let usage = TokenUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+        };
+        // $3.50 each on gpt-4o; local models cost nothing.
+        record_spend(&path, "2026-09", "openai", "gpt-4o", &usage).unwrap();
+        record_spend(&path, "2026-10", "openai", "gpt-4o", &usage).unwrap();
+        record_spend(&path, "2026-10", "local", "qwen3-8b", &usage).unwrap();
+
+        let summary = spend_summary(&path, "2026-10", Some(5.0)).unwrap();
+        assert_eq!(summary.this_month.estimated_cost_usd, 3.5);
+        assert_eq!(summary.this_month.input_tokens, 2_000_000);
+        assert_eq!(summary.total.estimated_cost_usd, 7.0);
+
+        assert!(check_budget(&path, "2026-10", None).is_ok());
+        assert!(check_budget(&path, "2026-10", Some(5.0)).is_ok());
+        let err = check_budget(&path, "2026-10", Some(3.0)).unwrap_err();
+        assert!(err.to_string().contains("budget of $3.00"));
+        // A new month starts from zero.
+        assert!(check_budget(&path, "2026-11", Some(3.0)).is_ok());
+    }
+}
@@ -0,0 +1,149 @@
+//! Which nodes may connect to this one.
+//!
+//! Every protocol on the router is wrapped in an [`AccessLimit`] checking the
+//! connecting node against the same [`AccessList`], so a collection shared
+//! broadly can't be synced or fetched from by nodes refused here. The list
+//! is read on each new connection and can be replaced at runtime;
+//! connections already open are left alone.
+//!
+//! A bad entry in the saved list never opens this node up: invalid IDs are
+//! skipped, and a list that allowed only invalid IDs refuses everyone.
+
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{Context, Result};
+use iroh::protocol::{AccessLimit, ProtocolHandler};
+use iroh::EndpointId;
+
+use crate::config::PeerAccess;
+
+/// Parsed [`PeerAccess`].
+#[derive(Debug, Default)]
+pub(super) struct AccessList {
+    /// None when any node not blocked may connect.
+    allowed: Option<HashSet<EndpointId>>,
+    blocked: HashSet<EndpointId>,
+}
+
+impl AccessList {
+    /// Fails if any node ID is invalid.
+    pub(super) fn parse(access: &PeerAccess) -> Result<Self> {
+        for id in access.allowed.iter().chain(&access.blocked) {
+            parse_id(id)?;
+        }
+        Ok(Self::parse_valid(access))
+    }
+
+    /// Skips invalid node IDs. If any nodes were allowed, all others are
+    /// refused even when none of the allowed IDs are valid.
+    pub(super) fn parse_valid(access: &PeerAccess) -> Self {
+        let parse = |ids: &[String]| {
+            ids.iter()
+                .filter_map(|id| {
+                    parse_id(id)
+                        .inspect_err(|e| tracing::warn!(error = %e, "Skipping peer access entry"))
+                        .ok()
+                })
+                .collect::<HashSet<_>>()
+        };
+        Self {
+            allowed: (!access.allowed.is_empty()).then(|| parse(&access.allowed)),
+            blocked: parse(&access.blocked),
+        }
+    }
+
+    pub(super) fn allows(&self, node_id: &EndpointId) -> bool {
+        !self.blocked.contains(node_id)
+            && self
+                .allowed
+                .as_ref()
+                .is_none_or(|allowed| allowed.contains(node_id))
+    }
+}
+
+fn parse_id(id: &str) -> Result<EndpointId> {
+    id.trim()
+        .parse()
+        .with_context(|| format!("Invalid node ID: {}", id))
+}
+
+/// `handler`, refusing nodes `list` doesn't allow.
+pub(super) fn limit<P: ProtocolHandler + Clone>(
+    handler: P,
+    list: &Arc<RwLock<AccessList>>,
+) -> AccessLimit<P> {
+    let list = list.clone();
+    AccessLimit::new(handler, move |node_id| {
+        let allowed = list
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .allows(&node_id);
+        if !allowed {
+            tracing::debug!(node = %node_id.fmt_short(), "Refused connection");
+        }
+        allowed
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(seed: u8) -> EndpointId {
+        iroh::SecretKey::from_bytes(&[seed; 32]).public()
+    }
+
+    #[test]
+    fn test_access_list_allows_and_blocks() {
+        let (alice, bob, carol) = (node(1), node(2), node(3));
+
+        let open = AccessList::default();
+        assert!(open.allows(&alice));
+
+        let list = AccessList::parse(&PeerAccess {
+            allowed: vec![],
+            blocked: vec![bob.to_string()],
+        })
+        .unwrap();
+        assert!(list.allows(&alice));
+        assert!(!list.allows(&bob));
+
+        // Blocking wins over allowing; anyone not allowed is refused.
+        let list = AccessList::parse(&PeerAccess {
+            allowed: vec![alice.to_string(), format!(" {} ", bob)],
+            blocked: vec![bob.to_string()],
+        })
+        .unwrap();
+        assert!(list.allows(&alice));
+        assert!(!list.allows(&bob));
+        assert!(!list.allows(&carol));
+
+        assert!(AccessList::parse(&PeerAccess {
+            allowed: vec!["not a node".to_string()],
+            blocked: vec![],
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_invalid_saved_ids_never_allow_everyone() {
+        let (alice, bob) = (node(1), node(2));
+
+        // Only invalid IDs allowed: everyone is refused.
+        let list = AccessList::parse_valid(&PeerAccess {
+            allowed: vec!["not a node".to_string()],
+            blocked: vec![],
+        });
+        assert!(!list.allows(&alice));
+
+        // Invalid entries are skipped; the valid ones still apply.
+        let list = AccessList::parse_valid(&PeerAccess {
+            allowed: vec!["not a node".to_string(), alice.to_string()],
+            blocked: vec!["also not a node".to_string(), bob.to_string()],
+        });
+        assert!(list.allows(&alice));
+        assert!(!list.allows(&bob));
+        assert!(!list.allows(&node(3)));
+    }
+}
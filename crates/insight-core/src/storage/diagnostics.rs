@@ -0,0 +1,220 @@
+//! Why syncing with peers might not work.
+//!
+//! iroh probes the network in the background: which relay servers answer,
+//! whether UDP gets out and whether the NAT maps this node to the same
+//! public address for every destination. Behind a NAT that doesn't
+//! (symmetric, "hard" NAT) hole punching mostly fails and peers are only
+//! reached through a relay, which is slower and can stall large syncs.
+//! [`NetworkDiagnostics`] puts that report next to the path iroh currently
+//! uses to each peer the collections were synced with.
+
+use std::time::Duration;
+
+use iroh::endpoint::ConnectionType;
+use iroh::{Endpoint, EndpointId, Watcher};
+use serde::{Deserialize, Serialize};
+
+/// How long to wait for the first network report after startup.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How this node's NAT maps it to public addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NatType {
+    /// The same public address for every destination; hole punching
+    /// usually works.
+    EndpointIndependent,
+    /// A different public address per destination; peers are mostly
+    /// reached through a relay.
+    Symmetric,
+    /// UDP doesn't get out at all; only relays work.
+    UdpBlocked,
+    /// Not probed yet.
+    Unknown,
+}
+
+/// The path iroh uses to reach a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathKind {
+    Direct,
+    Relay,
+    /// Switching between a direct address and a relay.
+    Mixed,
+    /// No path; the peer hasn't been reached this run.
+    None,
+}
+
+/// How this node reaches one peer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerPath {
+    /// The peer's node ID.
+    pub node_id: String,
+    pub path: PathKind,
+    /// The peer's direct address, if one is in use.
+    pub addr: Option<String>,
+    /// The relay used to reach the peer, if any.
+    pub relay_url: Option<String>,
+}
+
+/// This node's connectivity, for working out why sync doesn't complete.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkDiagnostics {
+    /// This node's ID.
+    pub node_id: String,
+    /// Whether a relay server answered; without one, peers behind NATs
+    /// can't be reached at all.
+    pub relay_reachable: bool,
+    /// The closest relay that answered.
+    pub relay_url: Option<String>,
+    pub nat_type: NatType,
+    pub udp_v4: bool,
+    pub udp_v6: bool,
+    /// Public addresses as seen by the relays.
+    pub public_addrs: Vec<String>,
+    /// Share of the peers reached that are reached directly, from 0 to 1.
+    /// `None` if no peer has been reached.
+    pub direct_rate: Option<f64>,
+    /// Peers the collections were synced with, directly reached first.
+    pub peers: Vec<PeerPath>,
+}
+
+/// NAT type from what iroh probed: whether UDP got out and whether the
+/// public address varied by destination.
+fn nat_type(udp: bool, mapping_varies: Option<bool>) -> NatType {
+    if !udp {
+        return NatType::UdpBlocked;
+    }
+    match mapping_varies {
+        Some(true) => NatType::Symmetric,
+        Some(false) => NatType::EndpointIndependent,
+        None => NatType::Unknown,
+    }
+}
+
+/// Share of `peers` with a path that is direct, counting mixed paths.
+fn direct_rate(peers: &[PeerPath]) -> Option<f64> {
+    let reached = peers.iter().filter(|p| p.path != PathKind::None).count();
+    if reached == 0 {
+        return None;
+    }
+    let direct = peers
+        .iter()
+        .filter(|p| matches!(p.path, PathKind::Direct | PathKind::Mixed))
+        .count();
+    Some(direct as f64 / reached as f64)
+}
+
+fn peer_path(endpoint: &Endpoint, node_id: EndpointId) -> PeerPath {
+    let conn_type = endpoint
+        .conn_type(node_id)
+        .map(|mut watcher| watcher.get())
+        .unwrap_or(ConnectionType::None);
+    let (path, addr, relay_url) = match conn_type {
+        ConnectionType::Direct(addr) => (PathKind::Direct, Some(addr.to_string()), None),
+        ConnectionType::Relay(url) => (PathKind::Relay, None, Some(url.to_string())),
+        ConnectionType::Mixed(addr, url) => (
+            PathKind::Mixed,
+            Some(addr.to_string()),
+            Some(url.to_string()),
+        ),
+        ConnectionType::None => (PathKind::None, None, None),
+    };
+    PeerPath {
+        node_id: node_id.to_string(),
+        path,
+        addr,
+        relay_url,
+    }
+}
+
+/// Diagnose `endpoint`'s connectivity and its paths to `peers`.
+pub(super) async fn diagnose(endpoint: &Endpoint, peers: Vec<EndpointId>) -> NetworkDiagnostics {
+    let mut report = endpoint.net_report();
+    let report = match tokio::time::timeout(REPORT_TIMEOUT, report.initialized()).await {
+        Ok(report) => Some(report),
+        Err(_) => {
+            tracing::debug!("No network report yet");
+            None
+        }
+    };
+
+    let mut peers: Vec<PeerPath> = peers
+        .into_iter()
+        .map(|node_id| peer_path(endpoint, node_id))
+        .collect();
+    let rank = |path: PathKind| match path {
+        PathKind::Direct => 0,
+        PathKind::Mixed => 1,
+        PathKind::Relay => 2,
+        PathKind::None => 3,
+    };
+    peers.sort_by_key(|p| rank(p.path));
+
+    let (relay_url, nat, udp_v4, udp_v6, public_addrs) = match &report {
+        Some(report) => {
+            let public_addrs = report
+                .global_v4
+                .map(|addr| addr.to_string())
+                .into_iter()
+                .chain(report.global_v6.map(|addr| addr.to_string()))
+                .collect();
+            let udp = report.udp_v4 || report.udp_v6;
+            let varies = report
+                .mapping_varies_by_dest_ipv4
+                .or(report.mapping_varies_by_dest_ipv6);
+            (
+                report.preferred_relay.as_ref().map(|url| url.to_string()),
+                nat_type(udp, varies),
+                report.udp_v4,
+                report.udp_v6,
+                public_addrs,
+            )
+        }
+        None => (None, NatType::Unknown, false, false, Vec::new()),
+    };
+
+    NetworkDiagnostics {
+        node_id: endpoint.id().to_string(),
+        relay_reachable: relay_url.is_some(),
+        relay_url,
+        nat_type: nat,
+        udp_v4,
+        udp_v6,
+        public_addrs,
+        direct_rate: direct_rate(&peers),
+        peers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nat_type_from_probes() {
+        assert_eq!(nat_type(false, Some(false)), NatType::UdpBlocked);
+        assert_eq!(nat_type(true, Some(true)), NatType::Symmetric);
+        assert_eq!(nat_type(true, Some(false)), NatType::EndpointIndependent);
+        assert_eq!(nat_type(true, None), NatType::Unknown);
+    }
+
+    #[test]
+    fn test_direct_rate_ignores_unreached_peers() {
+        let peer = |path| PeerPath {
+            node_id: String::new(),
+            path,
+            addr: None,
+            relay_url: None,
+        };
+        assert_eq!(direct_rate(&[peer(PathKind::None)]), None);
+        let peers = [
+            peer(PathKind::Direct),
+            peer(PathKind::Mixed),
+            peer(PathKind::Relay),
+            peer(PathKind::Relay),
+            peer(PathKind::None),
+        ];
+        assert_eq!(direct_rate(&peers), Some(0.5));
+    }
+}
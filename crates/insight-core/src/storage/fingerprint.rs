@@ -0,0 +1,128 @@
+//! Text fingerprints for near-duplicate detection.
+//!
+//! A document's extracted text is reduced to a 64-bit simhash over
+//! overlapping word shingles. Texts that differ only in a few places
+//! (re-scans, re-exports, a changed header) land within a few bits of
+//! each other, so comparing fingerprints is a popcount rather than a
+//! text diff.
+
+use serde::{Deserialize, Serialize};
+
+/// Words per shingle.
+const SHINGLE_WORDS: usize = 4;
+
+/// Texts with fewer shingles than this aren't fingerprinted: short
+/// documents collide too easily to say anything useful.
+const MIN_SHINGLES: usize = 16;
+
+/// Maximum differing bits for two fingerprints to count as near-duplicates.
+pub const NEAR_DUPLICATE_BITS: u32 = 3;
+
+/// Another document in the same collection whose text is nearly identical.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NearDuplicate {
+    pub doc_id: String,
+    /// Fraction of matching fingerprint bits, 0.0-1.0.
+    pub similarity: f32,
+}
+
+/// Simhash of `text`, or `None` if it's too short to fingerprint.
+///
+/// Words are lowercased and stripped of punctuation first so layout and
+/// OCR noise around them doesn't count as a difference.
+pub fn simhash(text: &str) -> Option<u64> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|w| {
+            w.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.len() < SHINGLE_WORDS + MIN_SHINGLES - 1 {
+        return None;
+    }
+
+    let mut weights = [0i64; 64];
+    for shingle in words.windows(SHINGLE_WORDS) {
+        let hash = shingle_hash(shingle);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| **w > 0)
+            .fold(0u64, |acc, (bit, _)| acc | (1 << bit)),
+    )
+}
+
+/// Fraction of bits two fingerprints share.
+pub fn similarity(a: u64, b: u64) -> f32 {
+    1.0 - (a ^ b).count_ones() as f32 / 64.0
+}
+
+/// Whether two fingerprints are close enough to flag.
+pub fn is_near_duplicate(a: u64, b: u64) -> bool {
+    (a ^ b).count_ones() <= NEAR_DUPLICATE_BITS
+}
+
+fn shingle_hash(words: &[String]) -> u64 {
+    let mut hasher = blake3::Hasher::new();
+    for word in words {
+        hasher.update(word.as_bytes());
+        hasher.update(b" ");
+    }
+    let bytes = hasher.finalize();
+    u64::from_le_bytes(bytes.as_bytes()[..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(n: usize) -> String {
+        (0..n)
+            .map(|i| format!("word{} clause{}", i, i % 7))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn test_simhash_near_and_far() {
+        let text = sample(200);
+        let a = simhash(&text).unwrap();
+
+        // Same text with different layout and punctuation hashes identically
+        let reflowed = text.replace(' ', "\n").to_uppercase() + ".";
+        assert_eq!(simhash(&reflowed), Some(a));
+
+        // A small edit stays close
+        let edited = text.replacen("word10 ", "changed ", 1);
+        assert!(is_near_duplicate(a, simhash(&edited).unwrap()));
+
+        // Unrelated text doesn't
+        let other: String = (0..200)
+            .map(|i| format!("other{} thing{}", i * 3, i))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let b = simhash(&other).unwrap();
+        assert!(!is_near_duplicate(a, b));
+        assert!(similarity(a, b) < similarity(a, a));
+    }
+
+    #[test]
+    fn test_simhash_skips_short_text() {
+        assert_eq!(simhash("just a few words here"), None);
+        assert_eq!(simhash(""), None);
+    }
+}
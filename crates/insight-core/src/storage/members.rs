@@ -0,0 +1,171 @@
+//! Who writes to and replicates a collection.
+//!
+//! Each node records itself under `_authors/{author_id}` in the collections
+//! it can write to: its node ID, which ties the peers a collection syncs
+//! with to the entries they signed, and the display name from its settings.
+//! Members with a read-only share can't write there, so they are known by
+//! node ID alone.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+/// Prefix of author registry entries
+pub(super) const AUTHORS_PREFIX: &str = "_authors/";
+
+/// What a node records about itself in a collection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorProfile {
+    pub node_id: String,
+    pub name: Option<String>,
+}
+
+/// Someone who writes to or replicates a collection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionMember {
+    /// `None` for a peer that has neither written nor registered.
+    pub author_id: Option<String>,
+    /// `None` for an author whose registry entry hasn't synced.
+    pub node_id: Option<String>,
+    /// Display name from the author registry.
+    pub name: Option<String>,
+    /// This node.
+    pub is_self: bool,
+    /// Entries they signed, their registry entry not included.
+    pub entries: usize,
+    /// When they last wrote, RFC 3339.
+    pub last_write: Option<String>,
+    /// Whether this node has synced the collection with them.
+    pub replicates: bool,
+}
+
+/// Entries signed by one author.
+#[derive(Debug, Default)]
+pub(super) struct Writes {
+    pub count: usize,
+    /// Latest entry timestamp, in microseconds since the epoch.
+    pub last: u64,
+}
+
+/// Combine who signed entries, who registered and who we synced with,
+/// authors and peers by ID. This node comes first, then the most recent
+/// writers, then peers that only replicate.
+pub(super) fn members(
+    self_author: &str,
+    mut writes: HashMap<String, Writes>,
+    profiles: HashMap<String, AuthorProfile>,
+    mut peers: HashSet<String>,
+) -> Vec<CollectionMember> {
+    let mut authors: HashSet<String> = writes.keys().cloned().collect();
+    authors.extend(profiles.keys().cloned());
+
+    let mut members: Vec<CollectionMember> = authors
+        .into_iter()
+        .map(|author_id| {
+            let written = writes.remove(&author_id).unwrap_or_default();
+            let profile = profiles.get(&author_id);
+            let node_id = profile.map(|p| p.node_id.clone());
+            let replicates = node_id.as_ref().is_some_and(|id| peers.remove(id));
+            CollectionMember {
+                is_self: author_id == self_author,
+                name: profile.and_then(|p| p.name.clone()),
+                node_id,
+                entries: written.count,
+                last_write: (written.count > 0)
+                    .then(|| chrono::DateTime::from_timestamp_micros(written.last as i64))
+                    .flatten()
+                    .map(|t| t.to_rfc3339()),
+                replicates,
+                author_id: Some(author_id),
+            }
+        })
+        .collect();
+    members.sort_by(|a, b| {
+        b.is_self
+            .cmp(&a.is_self)
+            .then_with(|| b.last_write.cmp(&a.last_write))
+    });
+
+    let mut peers: Vec<String> = peers.into_iter().collect();
+    peers.sort();
+    members.extend(peers.into_iter().map(|node_id| CollectionMember {
+        author_id: None,
+        node_id: Some(node_id),
+        name: None,
+        is_self: false,
+        entries: 0,
+        last_write: None,
+        replicates: true,
+    }));
+    members
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_members_combine_writers_registry_and_peers() {
+        let writes = HashMap::from([
+            (
+                "me".to_string(),
+                Writes {
+                    count: 3,
+                    last: 1_700_000_000_000_000,
+                },
+            ),
+            (
+                "alice".to_string(),
+                Writes {
+                    count: 10,
+                    last: 1_800_000_000_000_000,
+                },
+            ),
+            (
+                "bob".to_string(),
+                Writes {
+                    count: 1,
+                    last: 1_600_000_000_000_000,
+                },
+            ),
+        ]);
+        let profile = |node_id: &str, name: Option<&str>| AuthorProfile {
+            node_id: node_id.to_string(),
+            name: name.map(str::to_string),
+        };
+        let profiles = HashMap::from([
+            ("me".to_string(), profile("node-me", Some("Me"))),
+            ("alice".to_string(), profile("node-alice", Some("Alice"))),
+        ]);
+        let peers = HashSet::from(["node-alice".to_string(), "node-reader".to_string()]);
+
+        let members = members("me", writes, profiles, peers);
+        let summary: Vec<_> = members
+            .iter()
+            .map(|m| {
+                (
+                    m.author_id.as_deref(),
+                    m.name.as_deref(),
+                    m.entries,
+                    m.replicates,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (Some("me"), Some("Me"), 3, false),
+                (Some("alice"), Some("Alice"), 10, true),
+                (Some("bob"), None, 1, false),
+                (None, None, 0, true),
+            ]
+        );
+        assert!(members[0].is_self);
+        assert_eq!(members[3].node_id.as_deref(), Some("node-reader"));
+        assert!(members[1]
+            .last_write
+            .as_deref()
+            .unwrap()
+            .starts_with("2027-"));
+    }
+}
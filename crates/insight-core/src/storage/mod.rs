@@ -1,22 +1,48 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::{Arc, RwLock};
 
 use anyhow::{Context, Result};
 use futures::{Stream, StreamExt};
 use iroh::protocol::Router;
 use iroh::{Endpoint, RelayMode};
+use iroh_blobs::api::downloader::Downloader;
+use iroh_blobs::store::fs::options::{GcConfig, Options as BlobOptions, ProtectCallbackHandler};
 use iroh_blobs::store::fs::FsStore;
-use iroh_blobs::Hash;
+use iroh_blobs::ticket::BlobTicket;
+use iroh_blobs::{BlobFormat, Hash, HashAndFormat};
 use iroh_blobs::{BlobsProtocol, ALPN as BLOBS_ALPN};
 use iroh_docs::api::protocol::{AddrInfoOptions, ShareMode};
 use iroh_docs::api::DocsApi;
 pub use iroh_docs::engine::LiveEvent;
 use iroh_docs::net::ALPN as DOCS_ALPN;
 use iroh_docs::protocol::Docs;
-use iroh_docs::store::Query;
+use iroh_docs::store::{DownloadPolicy, FilterKind, Query};
 use iroh_docs::{AuthorId, ContentStatus, DocTicket, NamespaceId};
 use iroh_gossip::net::{Gossip, GOSSIP_ALPN};
 use serde::{Deserialize, Serialize};
 
+use crate::chat::ChatMessage;
+use crate::config::{PeerAccess, SyncPolicy};
+use crate::projection::{Projection, ProjectionSpec};
+use crate::provider::ChunkingConfig;
+
+mod access;
+mod diagnostics;
+pub mod fingerprint;
+mod members;
+mod tags;
+mod transfer;
+mod uploads;
+mod vector_encoding;
+
+pub use diagnostics::{NatType, NetworkDiagnostics, PathKind, PeerPath};
+pub use fingerprint::NearDuplicate;
+pub use members::{AuthorProfile, CollectionMember};
+pub use transfer::{DocumentBundle, DocumentOffer};
+pub use uploads::Upload;
+pub use vector_encoding::VectorEncoding;
+
 // =============================================================================
 // Key Structure Constants
 // =============================================================================
@@ -30,12 +56,22 @@ use serde::{Deserialize, Serialize};
 /// Key for collection metadata
 pub const COLLECTION_KEY: &[u8] = b"_collection";
 
+/// Key for the collection's vector projection matrix (see `crate::projection`)
+pub const PROJECTION_KEY: &[u8] = b"_projection";
+
+/// File type of Markdown documents, such as agent-written reports. Their
+/// source is the text itself, so extraction just copies it.
+pub const MARKDOWN_FILE_TYPE: &str = "text/markdown";
+
 /// Prefix for all document entries
 const FILES_PREFIX: &str = "files/";
 
 /// Suffix for document metadata entries
 const META_SUFFIX: &str = "/meta";
 
+/// Prefix of chat message entries
+const CHAT_PREFIX: &str = "_chat/";
+
 /// Suffix for document text entries
 const TEXT_SUFFIX: &str = "/text";
 
@@ -45,12 +81,19 @@ const SOURCE_SUFFIX: &str = "/source";
 /// Suffix for OCR task entries (pages awaiting the OCR worker)
 const OCR_TASK_SUFFIX: &str = "/ocr_task";
 
+/// Suffix for the entry marking a document whose extraction failed for good
+const EXTRACT_FAILED_SUFFIX: &str = "/extract_failed";
+
 /// Prefix for hash index (duplicate detection)
 const HASH_INDEX_PREFIX: &str = "_hash_index/";
 
 /// Part name for embeddings (stored under files/{doc_id}/embeddings/{model_id})
 const EMBEDDINGS_PART: &str = "/embeddings/";
 
+/// How often the blob store deletes blobs that are neither tagged nor
+/// referenced. See [`Storage::untag_unreferenced_blobs`] for the tagging side.
+const BLOB_GC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
 /// Build the key for a document's metadata entry
 #[inline]
 pub fn doc_meta_key(doc_id: &str) -> String {
@@ -79,12 +122,39 @@ pub fn doc_ocr_task_key(doc_id: &str) -> String {
     format!("{}{}{}", FILES_PREFIX, doc_id, OCR_TASK_SUFFIX)
 }
 
+/// Build the key for a document's extraction failure marker.
+///
+/// Written by the extract worker when a document can't be extracted, so
+/// it isn't tried again on every start. Holds the error message.
+#[inline]
+pub fn doc_extract_failed_key(doc_id: &str) -> String {
+    format!("{}{}{}", FILES_PREFIX, doc_id, EXTRACT_FAILED_SUFFIX)
+}
+
 /// Build the key for a hash index entry
 #[inline]
 fn hash_index_key(hash: &Hash) -> String {
     format!("{}{}", HASH_INDEX_PREFIX, hash)
 }
 
+/// Build the key for an author registry entry
+#[inline]
+fn author_key(author_id: &AuthorId) -> String {
+    format!("{}{}", members::AUTHORS_PREFIX, author_id)
+}
+
+/// Key of a chat message: `_chat/{sent_at}/{id}`, the time in zero-padded
+/// microseconds so keys sort in the order messages were sent.
+fn chat_key(message: &ChatMessage) -> Result<String> {
+    let sent_at = chrono::DateTime::parse_from_rfc3339(&message.sent_at)?;
+    Ok(format!(
+        "{}{:020}/{}",
+        CHAT_PREFIX,
+        sent_at.timestamp_micros(),
+        message.id
+    ))
+}
+
 /// Build the key for a specific embedding
 /// Pattern: files/{doc_id}/embeddings/{model_id}
 #[inline]
@@ -114,6 +184,15 @@ pub fn extract_doc_id(key: &str) -> Option<&str> {
 pub struct CollectionMetadata {
     pub name: String,
     pub created_at: String,
+    /// Chunking override for this collection (None = use global settings).
+    /// Lives in `_collection` so peers chunk the same way and can reuse
+    /// each other's embeddings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunking: Option<ChunkingConfig>,
+    /// Vector projection applied before storage and indexing (None = store
+    /// the model's native vectors). The matrix lives under `_projection`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub projection: Option<ProjectionSpec>,
 }
 
 /// Document metadata stored in iroh-docs under `files/{id}/meta` key
@@ -134,6 +213,18 @@ pub struct DocumentMetadata {
     /// Character offset where each page ends (for chunk-to-page mapping)
     #[serde(default)]
     pub page_boundaries: Vec<usize>,
+    /// Pages whose text came from OCR, 1-indexed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scanned_pages: Vec<usize>,
+    /// URL the source was downloaded from, for documents imported from the web.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    /// Simhash of the extracted text, see [`fingerprint::simhash`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simhash: Option<u64>,
+    /// Other documents in the collection with nearly identical text.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub near_duplicates: Vec<NearDuplicate>,
 }
 
 fn default_file_type() -> String {
@@ -141,12 +232,27 @@ fn default_file_type() -> String {
 }
 
 /// Embedding data for a document, stored per model under `embeddings/{doc_id}/{model_id}` key
+///
+/// Serialized through `vector_encoding::StoredEmbeddingData`, which packs
+/// the vectors according to `vector_encoding`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    into = "vector_encoding::StoredEmbeddingData",
+    try_from = "vector_encoding::StoredEmbeddingData"
+)]
 pub struct EmbeddingData {
     pub model_id: String,
     pub dimensions: usize,
     pub chunks: Vec<EmbeddingChunk>,
     pub created_at: String,
+    /// Chunking settings the chunks were produced with. None for data
+    /// written before chunking became configurable.
+    pub chunking: Option<ChunkingConfig>,
+    /// Projection the vectors were reduced with; `dimensions` is then the
+    /// projected size. None for the model's native vectors.
+    pub projection: Option<ProjectionSpec>,
+    /// How vectors are written to storage. Always `f32` in memory.
+    pub vector_encoding: VectorEncoding,
 }
 
 /// A single chunk with its embedding vector
@@ -159,6 +265,25 @@ pub struct EmbeddingChunk {
     pub end_page: usize,
 }
 
+/// An entry whose content is still on the peers that wrote it.
+#[derive(Debug, Clone)]
+pub struct MissingContent {
+    pub key: String,
+    pub hash: Hash,
+    pub len: u64,
+}
+
+/// The latest entry under a key, whoever wrote it.
+#[derive(Debug, Clone)]
+pub struct LatestEntry {
+    pub key: String,
+    pub hash: Hash,
+    /// 0 if the key was deleted.
+    pub len: u64,
+    /// When it was written, in microseconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
 /// Storage layer using iroh for P2P content-addressed storage
 ///
 /// Uses iroh_docs::Engine via the Docs protocol wrapper for native event subscriptions.
@@ -168,12 +293,16 @@ pub struct Storage {
     pub blobs: FsStore,
     /// Docs protocol wrapper containing the Engine
     docs: Docs,
-    /// Gossip protocol for pub/sub (used for P2P sync)
-    #[allow(dead_code)]
+    /// Gossip protocol for pub/sub (used for P2P sync and presence)
     gossip: Gossip,
     /// Router for accepting incoming protocol connections
-    #[allow(dead_code)]
     router: Router,
+    /// Documents other nodes send this one
+    inbox: transfer::Inbox,
+    /// Nodes allowed to connect, checked by every protocol on the router
+    access: Arc<RwLock<access::AccessList>>,
+    /// Content peers download from this node
+    uploads: tokio::sync::broadcast::Sender<Upload>,
     /// Default author ID for this node
     author_id: AuthorId,
 }
@@ -182,7 +311,8 @@ impl Storage {
     /// Initialize storage at the given path
     ///
     /// Sets up the iroh networking stack (local-only mode) and spawns the docs Engine.
-    pub async fn open(path: &Path) -> Result<Self> {
+    /// Only nodes `peer_access` allows may connect, from the first connection on.
+    pub async fn open(path: &Path, peer_access: &PeerAccess) -> Result<Self> {
         std::fs::create_dir_all(path)?;
 
         let blobs_path = path.join("blobs");
@@ -191,8 +321,16 @@ impl Storage {
         // Ensure docs directory exists (required by Docs::persistent)
         std::fs::create_dir_all(&docs_path)?;
 
-        // Create blob store
-        let blobs = FsStore::load(&blobs_path)
+        // Create blob store. Its GC frees blobs that are neither tagged nor
+        // referenced by a doc entry; the docs engine reports the latter
+        // through the protect handler.
+        let (protect_handler, protect_cb) = ProtectCallbackHandler::new();
+        let mut blob_options = BlobOptions::new(&blobs_path);
+        blob_options.gc = Some(GcConfig {
+            interval: BLOB_GC_INTERVAL,
+            add_protected: Some(protect_cb),
+        });
+        let blobs = FsStore::load_with_opts(blobs_path.join("blobs.db"), blob_options)
             .await
             .context("Failed to open blob store")?;
 
@@ -209,20 +347,26 @@ impl Storage {
         // Create docs with Engine - uses the blobs api::Store (via Deref)
         let blobs_api = (*blobs).clone();
         let docs = Docs::persistent(docs_path)
+            .protect_handler(protect_handler)
             .spawn(endpoint.clone(), blobs_api.clone(), gossip.clone())
             .await
             .context("Failed to spawn docs engine")?;
 
-        // Create blobs protocol handler for serving blob requests
-        let blobs_protocol = BlobsProtocol::new(&blobs_api, None);
+        // Create blobs protocol handler for serving blob requests, reporting
+        // what it sends
+        let (upload_events, uploads) = uploads::events();
+        let blobs_protocol = BlobsProtocol::new(&blobs_api, Some(upload_events));
 
         // Create router to accept incoming connections for our protocols
         // This is critical for P2P sync - without it, peers can discover us
         // but can't establish protocol-level connections
+        let inbox = transfer::Inbox::new();
+        let access = Arc::new(RwLock::new(access::AccessList::parse_valid(peer_access)));
         let router = Router::builder(endpoint.clone())
-            .accept(BLOBS_ALPN, blobs_protocol)
-            .accept(DOCS_ALPN, docs.clone())
-            .accept(GOSSIP_ALPN, gossip.clone())
+            .accept(BLOBS_ALPN, access::limit(blobs_protocol, &access))
+            .accept(DOCS_ALPN, access::limit(docs.clone(), &access))
+            .accept(GOSSIP_ALPN, access::limit(gossip.clone(), &access))
+            .accept(transfer::INBOX_ALPN, access::limit(inbox.clone(), &access))
             .spawn();
 
         // Get or create default author
@@ -243,6 +387,9 @@ impl Storage {
             docs,
             gossip,
             router,
+            inbox,
+            access,
+            uploads,
             author_id,
         })
     }
@@ -252,6 +399,24 @@ impl Storage {
         self.author_id
     }
 
+    /// This node's ID, which peers connect to.
+    pub fn node_id(&self) -> iroh::EndpointId {
+        self.router.endpoint().id()
+    }
+
+    /// Replace which nodes may connect. Applies to new connections; fails,
+    /// changing nothing, if a node ID is invalid.
+    pub fn set_peer_access(&self, access: &PeerAccess) -> Result<()> {
+        let list = access::AccessList::parse(access)?;
+        *self.access.write().unwrap_or_else(|e| e.into_inner()) = list;
+        Ok(())
+    }
+
+    /// Gossip protocol shared with the docs engine.
+    pub fn gossip(&self) -> &Gossip {
+        &self.gossip
+    }
+
     /// Get the docs API for direct access
     pub fn docs(&self) -> &DocsApi {
         self.docs.api()
@@ -291,6 +456,8 @@ impl Storage {
         let metadata = CollectionMetadata {
             name: name.to_string(),
             created_at: chrono::Utc::now().to_rfc3339(),
+            chunking: None,
+            projection: None,
         };
 
         // Create new document (namespace)
@@ -386,6 +553,122 @@ impl Storage {
         Ok(metadata)
     }
 
+    /// Set or clear a collection's chunking override.
+    ///
+    /// Rewrites the `_collection` entry, so the change syncs to peers.
+    /// Existing embeddings are left alone — re-embed to apply it.
+    pub async fn set_collection_chunking(
+        &self,
+        namespace_id: NamespaceId,
+        chunking: Option<ChunkingConfig>,
+    ) -> Result<CollectionMetadata> {
+        let mut metadata = self
+            .get_collection_metadata(namespace_id)
+            .await?
+            .context("Collection not found")?;
+        metadata.chunking = chunking;
+        self.put_collection_metadata(namespace_id, &metadata)
+            .await?;
+        Ok(metadata)
+    }
+
+    /// Set or clear the collection's vector projection.
+    ///
+    /// Writes the matrix to `_projection` and its spec to `_collection`.
+    /// Existing embeddings keep their old vectors until re-embedded.
+    pub async fn set_collection_projection(
+        &self,
+        namespace_id: NamespaceId,
+        projection: Option<&Projection>,
+    ) -> Result<CollectionMetadata> {
+        let mut metadata = self
+            .get_collection_metadata(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+        match projection {
+            Some(projection) => {
+                let bytes = projection.to_bytes();
+                let hash = self.store_blob(&bytes).await?;
+                doc.set_hash(
+                    self.author_id,
+                    PROJECTION_KEY.to_vec(),
+                    hash,
+                    bytes.len() as u64,
+                )
+                .await?;
+            }
+            None => {
+                doc.del(self.author_id, PROJECTION_KEY.to_vec()).await?;
+            }
+        }
+        doc.close().await?;
+
+        metadata.projection = projection.map(|p| p.spec.clone());
+        self.put_collection_metadata(namespace_id, &metadata)
+            .await?;
+        Ok(metadata)
+    }
+
+    /// Load the collection's projection matrix, if it has one.
+    pub async fn get_collection_projection(
+        &self,
+        namespace_id: NamespaceId,
+    ) -> Result<Option<Projection>> {
+        let Some(spec) = self
+            .get_collection_metadata(namespace_id)
+            .await?
+            .and_then(|m| m.projection)
+        else {
+            return Ok(None);
+        };
+
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+        let entry = doc.get_one(Query::key_exact(PROJECTION_KEY)).await?;
+        doc.close().await?;
+
+        let entry = entry.context("Collection has a projection spec but no matrix")?;
+        let bytes = self
+            .get_blob(&entry.content_hash())
+            .await?
+            .context("Projection matrix blob not available yet")?;
+        Ok(Some(Projection::from_bytes(spec, &bytes)?))
+    }
+
+    /// Overwrite the `_collection` entry.
+    async fn put_collection_metadata(
+        &self,
+        namespace_id: NamespaceId,
+        metadata: &CollectionMetadata,
+    ) -> Result<()> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let metadata_bytes = serde_json::to_vec(metadata)?;
+        let hash = self.store_blob(&metadata_bytes).await?;
+        let len = metadata_bytes.len() as u64;
+        doc.set_hash(self.author_id, COLLECTION_KEY.to_vec(), hash, len)
+            .await?;
+
+        doc.close().await?;
+        Ok(())
+    }
+
     /// Count documents in a collection.
     ///
     /// Counts only `files/*/meta` entries (one per document).
@@ -513,10 +796,20 @@ impl Storage {
         tokio::pin!(stream);
 
         let mut documents = Vec::new();
+        let mut tag_edits: HashMap<String, tags::TagEdits> = HashMap::new();
         while let Some(result) = stream.next().await {
             let entry = result?;
             let key = String::from_utf8_lossy(entry.key());
 
+            if let Some((doc_id, tag)) = tags::parse_key(&key) {
+                tag_edits.entry(doc_id.to_string()).or_default().record(
+                    tag,
+                    entry.timestamp(),
+                    entry.content_hash(),
+                );
+                continue;
+            }
+
             // Only process /meta entries
             if !key.ends_with("/meta") {
                 continue;
@@ -533,6 +826,11 @@ impl Storage {
                 }
             }
         }
+        for metadata in &mut documents {
+            if let Some(edits) = tag_edits.get(&metadata.id) {
+                edits.apply(&mut metadata.tags);
+            }
+        }
 
         doc.close().await?;
         Ok(documents)
@@ -581,6 +879,148 @@ impl Storage {
         Ok(has_ocr_task.difference(&has_text).cloned().collect())
     }
 
+    /// Return doc IDs whose `source` entry this node wrote and that have
+    /// neither a `text`, an `ocr_task` nor an `extract_failed` entry:
+    /// documents imported here that the extract phase never finished
+    /// with. Documents synced from peers wait for the peer's `text`
+    /// instead, and deleted ones (empty `source`) are skipped.
+    pub async fn find_pending_extractions(&self, namespace_id: NamespaceId) -> Result<Vec<String>> {
+        use futures::StreamExt;
+        use std::collections::HashSet;
+
+        let doc = match self.docs.api().open(namespace_id).await? {
+            Some(d) => d,
+            None => return Ok(Vec::new()),
+        };
+
+        let stream = doc.get_many(Query::key_prefix(b"files/")).await?;
+        tokio::pin!(stream);
+
+        let mut has_source: HashSet<String> = HashSet::new();
+        let mut handled: HashSet<String> = HashSet::new();
+        while let Some(result) = stream.next().await {
+            let entry = result?;
+            let key = String::from_utf8_lossy(entry.key()).into_owned();
+            if let Some(rest) = key.strip_prefix(FILES_PREFIX) {
+                if let Some((doc_id, suffix)) = rest.split_once('/') {
+                    match suffix {
+                        "source" => {
+                            if entry.author() == self.author_id && entry.content_len() > 0 {
+                                has_source.insert(doc_id.to_string());
+                            }
+                        }
+                        "text" | "ocr_task" | "extract_failed" => {
+                            handled.insert(doc_id.to_string());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        doc.close().await?;
+        Ok(has_source.difference(&handled).cloned().collect())
+    }
+
+    /// Return doc IDs that have a `text` entry, i.e. documents whose
+    /// extract (or OCR) phase has finished and that can be embedded.
+    pub async fn list_documents_with_text(&self, namespace_id: NamespaceId) -> Result<Vec<String>> {
+        use futures::StreamExt;
+
+        let doc = match self.docs.api().open(namespace_id).await? {
+            Some(d) => d,
+            None => return Ok(Vec::new()),
+        };
+
+        let stream = doc.get_many(Query::key_prefix(b"files/")).await?;
+        tokio::pin!(stream);
+
+        let mut doc_ids = Vec::new();
+        while let Some(result) = stream.next().await {
+            let entry = result?;
+            let key = String::from_utf8_lossy(entry.key());
+            if let Some(doc_id) = key
+                .strip_prefix(FILES_PREFIX)
+                .and_then(|rest| rest.strip_suffix(TEXT_SUFFIX))
+            {
+                doc_ids.push(doc_id.to_string());
+            }
+        }
+        doc.close().await?;
+        Ok(doc_ids)
+    }
+
+    /// Return doc IDs that have a `text` entry but no
+    /// `embeddings/{model_id}` entry — documents the embed stage never
+    /// finished for the given model (app closed mid-import, or the model
+    /// was switched).
+    pub async fn find_pending_embeddings(
+        &self,
+        namespace_id: NamespaceId,
+        model_id: &str,
+    ) -> Result<Vec<String>> {
+        use futures::StreamExt;
+        use std::collections::HashSet;
+
+        let doc = match self.docs.api().open(namespace_id).await? {
+            Some(d) => d,
+            None => return Ok(Vec::new()),
+        };
+
+        let stream = doc.get_many(Query::key_prefix(b"files/")).await?;
+        tokio::pin!(stream);
+
+        let embedding_suffix = format!("embeddings/{}", model_id);
+        let mut has_text: HashSet<String> = HashSet::new();
+        let mut has_embeddings: HashSet<String> = HashSet::new();
+        while let Some(result) = stream.next().await {
+            let entry = result?;
+            let key = String::from_utf8_lossy(entry.key()).into_owned();
+            if let Some(rest) = key.strip_prefix(FILES_PREFIX) {
+                if let Some((doc_id, suffix)) = rest.split_once('/') {
+                    if suffix == "text" {
+                        has_text.insert(doc_id.to_string());
+                    } else if suffix == embedding_suffix {
+                        has_embeddings.insert(doc_id.to_string());
+                    }
+                }
+            }
+        }
+        doc.close().await?;
+        Ok(has_text.difference(&has_embeddings).cloned().collect())
+    }
+
+    /// Return doc IDs that have `embeddings/{model_id}` stored, whether or
+    /// not they made it into the search index.
+    pub async fn find_embedded_documents(
+        &self,
+        namespace_id: NamespaceId,
+        model_id: &str,
+    ) -> Result<Vec<String>> {
+        let doc = match self.docs.api().open(namespace_id).await? {
+            Some(d) => d,
+            None => return Ok(Vec::new()),
+        };
+
+        let stream = doc.get_many(Query::key_prefix(b"files/")).await?;
+        tokio::pin!(stream);
+
+        let embedding_suffix = format!("embeddings/{}", model_id);
+        let mut doc_ids = Vec::new();
+        while let Some(result) = stream.next().await {
+            let entry = result?;
+            let key = String::from_utf8_lossy(entry.key()).into_owned();
+            if let Some(rest) = key.strip_prefix(FILES_PREFIX) {
+                if let Some((doc_id, suffix)) = rest.split_once('/') {
+                    if suffix == embedding_suffix {
+                        doc_ids.push(doc_id.to_string());
+                    }
+                }
+            }
+        }
+        doc.close().await?;
+        Ok(doc_ids)
+    }
+
     /// Get a single document's metadata from a collection by ID
     pub async fn get_document(
         &self,
@@ -596,7 +1036,7 @@ impl Storage {
         let query = Query::key_exact(key.as_bytes());
         let entry = doc.get_one(query).await?;
 
-        let metadata = if let Some(entry) = entry {
+        let mut metadata = if let Some(entry) = entry {
             let hash = entry.content_hash();
 
             if let Some(data) = self.get_blob(&hash).await? {
@@ -614,6 +1054,20 @@ impl Storage {
             None
         };
 
+        if let Some(metadata) = &mut metadata {
+            let prefix = tags::prefix(document_id);
+            let stream = doc.get_many(Query::key_prefix(prefix.as_bytes())).await?;
+            tokio::pin!(stream);
+            let mut edits = tags::TagEdits::default();
+            while let Some(entry) = stream.next().await {
+                let entry = entry?;
+                if let Some((_, tag)) = tags::parse_key(&String::from_utf8_lossy(entry.key())) {
+                    edits.record(tag, entry.timestamp(), entry.content_hash());
+                }
+            }
+            edits.apply(&mut metadata.tags);
+        }
+
         doc.close().await?;
         Ok(metadata)
     }
@@ -720,6 +1174,8 @@ impl Storage {
             doc_id = %doc_id,
             model_id = %data.model_id,
             chunk_count = data.chunks.len(),
+            encoding = ?data.vector_encoding,
+            bytes = len,
             "Stored embeddings"
         );
 
@@ -759,6 +1215,25 @@ impl Storage {
         Ok(result)
     }
 
+    /// Whether the collection has an embeddings entry for `model_id`,
+    /// downloaded or not.
+    pub async fn has_embeddings(
+        &self,
+        namespace_id: NamespaceId,
+        doc_id: &str,
+        model_id: &str,
+    ) -> Result<bool> {
+        let doc = match self.docs.api().open(namespace_id).await? {
+            Some(doc) => doc,
+            None => return Ok(false),
+        };
+
+        let key = embedding_key(doc_id, model_id);
+        let entry = doc.get_one(Query::key_exact(key.as_bytes())).await?;
+        doc.close().await?;
+        Ok(entry.is_some_and(|entry| entry.content_len() > 0))
+    }
+
     /// Delete a document from a collection
     ///
     /// Sweeps every entry under `files/{id}/` (meta, text, source,
@@ -825,38 +1300,465 @@ impl Storage {
         Ok(())
     }
 
-    /// Subscribe to document events for a namespace
-    ///
-    /// Returns a stream of LiveEvent that includes:
-    /// - InsertLocal: When a document is added locally
-    /// - InsertRemote: When a document is added from a peer
-    /// - ContentReady: When content has been downloaded
-    /// - SyncFinished: When a sync operation completes
-    pub async fn subscribe(
-        &self,
-        namespace_id: NamespaceId,
-    ) -> Result<impl Stream<Item = Result<LiveEvent>> + Send + Unpin + 'static> {
+    /// Create the space shared with this user's other devices; see
+    /// [`crate::personal`]. It has no `_collection` entry, so it isn't
+    /// listed as a collection.
+    pub async fn create_personal_space(&self) -> Result<NamespaceId> {
+        let doc = self.docs.api().create().await?;
+        let namespace_id = doc.id();
+        doc.close().await?;
+        tracing::info!("Created personal space {}", namespace_id);
+        Ok(namespace_id)
+    }
+
+    /// Join the personal space a write ticket from another device of this
+    /// user's shares.
+    pub async fn join_personal_space(&self, ticket: &str) -> Result<NamespaceId> {
+        let ticket: DocTicket = ticket.parse().context("Invalid device ticket")?;
+        if !matches!(ticket.capability, iroh_docs::Capability::Write(_)) {
+            anyhow::bail!("Device ticket is read-only");
+        }
+        let doc = self.docs.api().import(ticket).await?;
+        let namespace_id = doc.id();
+        doc.close().await?;
+        tracing::info!("Joined personal space {}", namespace_id);
+        Ok(namespace_id)
+    }
+
+    /// The latest entry under each key of a namespace, including empty
+    /// ones left by deletions.
+    pub async fn latest_entries(&self, namespace_id: NamespaceId) -> Result<Vec<LatestEntry>> {
         let doc = self
             .docs
             .api()
             .open(namespace_id)
             .await?
-            .context("Collection not found")?;
-
-        let stream = doc.subscribe().await?;
+            .context("Namespace not found")?;
 
-        Ok(stream)
+        let mut entries = Vec::new();
+        let stream = doc
+            .get_many(Query::single_latest_per_key().include_empty(true))
+            .await?;
+        tokio::pin!(stream);
+        while let Some(entry) = stream.next().await {
+            let entry = entry?;
+            entries.push(LatestEntry {
+                key: String::from_utf8_lossy(entry.key()).into_owned(),
+                hash: entry.content_hash(),
+                len: entry.content_len(),
+                timestamp: entry.timestamp(),
+            });
+        }
+        doc.close().await?;
+        Ok(entries)
     }
 
-    /// Generate a share ticket for a collection
-    ///
-    /// The ticket string can be shared with others who can then import the collection.
-    /// If `writable` is true, the recipient can also add/edit documents.
-    pub async fn share_collection(
+    /// Write `bytes` under `key` as this node's author.
+    pub async fn put_entry(
         &self,
         namespace_id: NamespaceId,
-        writable: bool,
-    ) -> Result<String> {
+        key: &str,
+        bytes: &[u8],
+    ) -> Result<()> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Namespace not found")?;
+        let hash = self.store_blob(bytes).await?;
+        doc.set_hash(
+            self.author_id,
+            key.as_bytes().to_vec(),
+            hash,
+            bytes.len() as u64,
+        )
+        .await?;
+        doc.close().await?;
+        Ok(())
+    }
+
+    /// Delete the entries under `key` and any key it is a prefix of.
+    pub async fn delete_entry(&self, namespace_id: NamespaceId, key: &str) -> Result<()> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Namespace not found")?;
+        doc.del(self.author_id, key.as_bytes().to_vec()).await?;
+        doc.close().await?;
+        Ok(())
+    }
+
+    /// Subscribe to document events for a namespace
+    ///
+    /// Returns a stream of LiveEvent that includes:
+    /// - InsertLocal: When a document is added locally
+    /// - InsertRemote: When a document is added from a peer
+    /// - ContentReady: When content has been downloaded
+    /// - SyncFinished: When a sync operation completes
+    pub async fn subscribe(
+        &self,
+        namespace_id: NamespaceId,
+    ) -> Result<impl Stream<Item = Result<LiveEvent>> + Send + Unpin + 'static> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let stream = doc.subscribe().await?;
+
+        Ok(stream)
+    }
+
+    /// Have iroh download the content of everything peers write to the
+    /// collection or, for a narrower `policy`, nothing under `files/`:
+    /// the collection watcher downloads what the policy allows itself.
+    pub async fn set_download_policy(
+        &self,
+        namespace_id: NamespaceId,
+        policy: SyncPolicy,
+    ) -> Result<()> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let excluded = match policy {
+            SyncPolicy::Everything => Vec::new(),
+            SyncPolicy::Text | SyncPolicy::Metadata => {
+                vec![FilterKind::Prefix(FILES_PREFIX.into())]
+            }
+        };
+        doc.set_download_policy(DownloadPolicy::EverythingExcept(excluded))
+            .await?;
+        doc.close().await?;
+        Ok(())
+    }
+
+    /// Detach the collection from its peers or attach it again. While
+    /// detached nothing is sent or received and peers trying to sync are
+    /// turned away; its entries and content stay. Attaching syncs with the
+    /// peers it synced with before.
+    pub async fn set_sync_enabled(&self, namespace_id: NamespaceId, enabled: bool) -> Result<()> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        if enabled {
+            let peers = self
+                .sync_peers(namespace_id)
+                .await?
+                .into_iter()
+                .map(iroh::EndpointAddr::new)
+                .collect();
+            doc.start_sync(peers).await?;
+        } else {
+            doc.leave().await?;
+        }
+        doc.close().await?;
+        Ok(())
+    }
+
+    /// Entries under `prefix` whose content hasn't been downloaded.
+    pub async fn missing_content(
+        &self,
+        namespace_id: NamespaceId,
+        prefix: &str,
+    ) -> Result<Vec<MissingContent>> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let mut missing = Vec::new();
+        let stream = doc.get_many(Query::key_prefix(prefix.as_bytes())).await?;
+        tokio::pin!(stream);
+        while let Some(entry) = stream.next().await {
+            let entry = entry?;
+            if entry.content_len() > 0 && !self.blobs.has(entry.content_hash()).await? {
+                missing.push(MissingContent {
+                    key: String::from_utf8_lossy(entry.key()).into_owned(),
+                    hash: entry.content_hash(),
+                    len: entry.content_len(),
+                });
+            }
+        }
+        doc.close().await?;
+        Ok(missing)
+    }
+
+    /// Hashes of the content of a collection's entries, downloaded or not.
+    pub async fn content_hashes(&self, namespace_id: NamespaceId) -> Result<Vec<Hash>> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let mut hashes = Vec::new();
+        let stream = doc.get_many(Query::all()).await?;
+        tokio::pin!(stream);
+        while let Some(entry) = stream.next().await {
+            let entry = entry?;
+            if entry.content_len() > 0 {
+                hashes.push(entry.content_hash());
+            }
+        }
+        doc.close().await?;
+        Ok(hashes)
+    }
+
+    /// Documents with content that hasn't been downloaded, such as those
+    /// a narrower [`SyncPolicy`] leaves on peers.
+    pub async fn documents_missing_content(
+        &self,
+        namespace_id: NamespaceId,
+    ) -> Result<Vec<String>> {
+        let mut doc_ids: Vec<String> = self
+            .missing_content(namespace_id, FILES_PREFIX)
+            .await?
+            .into_iter()
+            .filter_map(|m| {
+                let rest = m.key.strip_prefix(FILES_PREFIX)?;
+                Some(rest.split('/').next()?.to_string())
+            })
+            .collect();
+        doc_ids.sort();
+        doc_ids.dedup();
+        Ok(doc_ids)
+    }
+
+    /// Peers this node has synced the collection with.
+    pub async fn sync_peers(&self, namespace_id: NamespaceId) -> Result<Vec<iroh::EndpointId>> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let peers = doc
+            .get_sync_peers()
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|bytes| iroh::EndpointId::from_bytes(&bytes).ok())
+            .collect();
+        doc.close().await?;
+        Ok(peers)
+    }
+
+    /// Relay reachability, NAT type and the path to each peer any
+    /// collection was synced with, for working out why sync doesn't
+    /// complete.
+    pub async fn network_diagnostics(&self) -> Result<NetworkDiagnostics> {
+        let mut peers = Vec::new();
+        for (namespace_id, _) in self.list_collections().await? {
+            peers.extend(self.sync_peers(namespace_id).await?);
+        }
+        peers.sort();
+        peers.dedup();
+        Ok(diagnostics::diagnose(self.router.endpoint(), peers).await)
+    }
+
+    /// Downloads blobs from peers into this node's store.
+    pub fn downloader(&self) -> Downloader {
+        self.blobs.downloader(self.router.endpoint())
+    }
+
+    /// Record this node in the collection's author registry, under `name`.
+    /// Nothing is written if the entry is unchanged.
+    /// Fails for collections shared with this node read-only.
+    pub async fn register_author(
+        &self,
+        namespace_id: NamespaceId,
+        name: Option<&str>,
+    ) -> Result<()> {
+        let profile = AuthorProfile {
+            node_id: self.node_id().to_string(),
+            name: name.map(str::to_string),
+        };
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let key = author_key(&self.author_id).into_bytes();
+        if let Some(entry) = doc.get_exact(self.author_id, key.clone(), false).await? {
+            let current = self.get_blob(&entry.content_hash()).await?;
+            let current = current.and_then(|bytes| serde_json::from_slice(&bytes).ok());
+            if current.as_ref() == Some(&profile) {
+                doc.close().await?;
+                return Ok(());
+            }
+        }
+
+        let bytes = serde_json::to_vec(&profile)?;
+        let hash = self.store_blob(&bytes).await?;
+        doc.set_hash(self.author_id, key, hash, bytes.len() as u64)
+            .await?;
+        doc.close().await?;
+        Ok(())
+    }
+
+    /// Everyone who has written to the collection or replicates it, with
+    /// names from the author registry. Replicating peers are those this
+    /// node has synced the collection with.
+    pub async fn collection_members(
+        &self,
+        namespace_id: NamespaceId,
+    ) -> Result<Vec<CollectionMember>> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let mut writes: HashMap<String, members::Writes> = HashMap::new();
+        let mut profiles = HashMap::new();
+        let stream = doc.get_many(Query::all()).await?;
+        tokio::pin!(stream);
+        while let Some(entry) = stream.next().await {
+            let entry = entry?;
+            let author = entry.author();
+            // A registry entry only counts under its signer's own key.
+            if entry.key().starts_with(members::AUTHORS_PREFIX.as_bytes()) {
+                if entry.key() == author_key(&author).as_bytes() {
+                    let profile = self.get_blob(&entry.content_hash()).await?;
+                    match profile.map(|bytes| serde_json::from_slice::<AuthorProfile>(&bytes)) {
+                        Some(Ok(profile)) => {
+                            profiles.insert(author.to_string(), profile);
+                        }
+                        Some(Err(e)) => {
+                            tracing::warn!(author = %author.fmt_short(), error = %e, "Unreadable author registry entry");
+                        }
+                        None => {}
+                    }
+                }
+                continue;
+            }
+            let written = writes.entry(author.to_string()).or_default();
+            written.count += 1;
+            written.last = written.last.max(entry.timestamp());
+        }
+
+        doc.close().await?;
+        let peers = self
+            .sync_peers(namespace_id)
+            .await?
+            .into_iter()
+            .map(|id| id.to_string())
+            .collect();
+
+        Ok(members::members(
+            &self.author_id.to_string(),
+            writes,
+            profiles,
+            peers,
+        ))
+    }
+
+    /// Store a chat message in the collection, where it syncs to the
+    /// other members. Fails for collections shared with this node read-only.
+    pub async fn store_chat_message(
+        &self,
+        namespace_id: NamespaceId,
+        message: &ChatMessage,
+    ) -> Result<()> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let bytes = serde_json::to_vec(message)?;
+        let hash = self.store_blob(&bytes).await?;
+        doc.set_hash(
+            self.author_id,
+            chat_key(message)?.into_bytes(),
+            hash,
+            bytes.len() as u64,
+        )
+        .await?;
+        doc.close().await?;
+        Ok(())
+    }
+
+    /// The last `limit` chat messages stored in the collection, oldest
+    /// first. Messages whose content hasn't synced yet are left out.
+    pub async fn chat_history(
+        &self,
+        namespace_id: NamespaceId,
+        limit: usize,
+    ) -> Result<Vec<ChatMessage>> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let mut entries = Vec::new();
+        let stream = doc
+            .get_many(Query::key_prefix(CHAT_PREFIX.as_bytes()))
+            .await?;
+        tokio::pin!(stream);
+        while let Some(entry) = stream.next().await {
+            let entry = entry?;
+            entries.push((entry.key().to_vec(), entry.content_hash()));
+        }
+        doc.close().await?;
+
+        // Entries come by author, then key.
+        entries.sort();
+        let skip = entries.len().saturating_sub(limit);
+        let mut messages = Vec::with_capacity(entries.len() - skip);
+        for (key, hash) in entries.into_iter().skip(skip) {
+            let Some(bytes) = self.get_blob(&hash).await? else {
+                continue;
+            };
+            match serde_json::from_slice::<ChatMessage>(&bytes) {
+                Ok(mut message) => {
+                    message.collection_id = namespace_id.to_string();
+                    messages.push(message);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        key = %String::from_utf8_lossy(&key),
+                        error = %e,
+                        "Unreadable chat message"
+                    );
+                }
+            }
+        }
+        Ok(messages)
+    }
+
+    /// Generate a share ticket for a collection
+    ///
+    /// The ticket string can be shared with others who can then import the collection.
+    /// If `writable` is true, the recipient can also add/edit documents.
+    pub async fn share_collection(
+        &self,
+        namespace_id: NamespaceId,
+        writable: bool,
+    ) -> Result<String> {
         let doc = self
             .docs
             .api()
@@ -889,8 +1791,13 @@ impl Storage {
     /// This registers the namespace locally and starts syncing with the peer
     /// who shared it. Waits for the collection metadata to sync before returning.
     /// The DocWatcher will pick up InsertRemote events and trigger embedding +
-    /// indexing automatically for document entries.
-    pub async fn import_collection(&self, ticket_str: &str) -> Result<NamespaceId> {
+    /// indexing automatically for document entries. Document content is
+    /// downloaded as `policy` allows from the start.
+    pub async fn import_collection(
+        &self,
+        ticket_str: &str,
+        policy: SyncPolicy,
+    ) -> Result<NamespaceId> {
         use std::time::Duration;
         use tokio::time::timeout;
 
@@ -905,6 +1812,7 @@ impl Storage {
         // Import and subscribe to events so we can wait for the _collection entry
         let (doc, mut events) = self.docs.api().import_and_subscribe(ticket).await?;
         let namespace_id = doc.id();
+        self.set_download_policy(namespace_id, policy).await?;
 
         tracing::info!(
             "Importing collection {}, waiting for metadata...",
@@ -1031,6 +1939,7 @@ impl Storage {
         &self,
         path: &std::path::Path,
         namespace_id: NamespaceId,
+        tags: &[String],
     ) -> Result<String> {
         let file_name = path
             .file_name()
@@ -1038,11 +1947,77 @@ impl Storage {
             .unwrap_or_else(|| "unknown.pdf".to_string());
 
         let pdf_bytes = std::fs::read(path).context("Failed to read PDF file")?;
+        self.store_source(
+            namespace_id,
+            file_name,
+            &pdf_bytes,
+            "application/pdf",
+            tags.to_vec(),
+            None,
+        )
+        .await
+    }
+
+    /// Store a document downloaded from `url`, recording the URL in its
+    /// metadata. `file_type` is the sniffed type of `bytes`.
+    ///
+    /// Returns the new `doc_id`.
+    pub async fn store_downloaded_source(
+        &self,
+        namespace_id: NamespaceId,
+        url: &str,
+        file_name: String,
+        bytes: &[u8],
+        file_type: &str,
+        tags: &[String],
+    ) -> Result<String> {
+        self.store_source(
+            namespace_id,
+            file_name,
+            bytes,
+            file_type,
+            tags.to_vec(),
+            Some(url.to_string()),
+        )
+        .await
+    }
+
+    /// Store a Markdown document, e.g. an agent-written report, as a new
+    /// source. Extraction then copies it into `files/{id}/text` as a single
+    /// page, so it is embedded, indexed and synced like an imported file.
+    ///
+    /// Returns the new `doc_id`.
+    pub async fn store_markdown_source(
+        &self,
+        namespace_id: NamespaceId,
+        file_name: &str,
+        markdown: &str,
+    ) -> Result<String> {
+        self.store_source(
+            namespace_id,
+            file_name.to_string(),
+            markdown.as_bytes(),
+            MARKDOWN_FILE_TYPE,
+            Vec::new(),
+            None,
+        )
+        .await
+    }
 
+    /// Store source bytes with minimal metadata and a hash index entry.
+    async fn store_source(
+        &self,
+        namespace_id: NamespaceId,
+        file_name: String,
+        source_bytes: &[u8],
+        file_type: &str,
+        tags: Vec<String>,
+        source_url: Option<String>,
+    ) -> Result<String> {
         // Hand bytes to iroh-blobs once. add_slice returns a TempTag holding
         // the BLAKE3 hash; iroh-blobs is content-addressed, so re-adding bytes
         // that are already stored is a no-op at the storage layer.
-        let source_temp_tag = self.blobs.add_slice(&pdf_bytes).await?;
+        let source_temp_tag = self.blobs.add_slice(source_bytes).await?;
         let source_blob_hash = source_temp_tag.hash;
 
         // Check for duplicate using the hash iroh just computed. Dropping
@@ -1071,11 +2046,15 @@ impl Storage {
         let metadata = DocumentMetadata {
             id: doc_id.clone(),
             name: file_name,
-            file_type: "application/pdf".to_string(),
+            file_type: file_type.to_string(),
             page_count: 0, // Unknown until extraction
-            tags: vec![],
+            tags,
             created_at: chrono::Utc::now().to_rfc3339(),
             page_boundaries: vec![], // Unknown until extraction
+            scanned_pages: Vec::new(),
+            source_url,
+            simhash: None,
+            near_duplicates: Vec::new(),
         };
 
         let doc = self
@@ -1103,7 +2082,7 @@ impl Storage {
             self.author_id,
             source_key.into_bytes(),
             source_blob_hash,
-            pdf_bytes.len() as u64,
+            source_bytes.len() as u64,
         )
         .await?;
 
@@ -1123,7 +2102,7 @@ impl Storage {
 
         tracing::info!(
             doc_id = %doc_id,
-            "Stored PDF source for {}",
+            "Stored source for {}",
             metadata.name
         );
 
@@ -1168,6 +2147,15 @@ impl Storage {
             }
         };
 
+        if let Some(metadata) = self.get_document(namespace_id, doc_id).await? {
+            if metadata.file_type == MARKDOWN_FILE_TYPE {
+                return self
+                    .store_markdown_text(namespace_id, metadata, &source_bytes)
+                    .await
+                    .map(Some);
+            }
+        }
+
         let extracted =
             tokio::task::spawn_blocking(move || crate::pdf::extract_text_from_bytes(source_bytes))
                 .await
@@ -1199,6 +2187,13 @@ impl Storage {
             // pages. Leave page_boundaries empty so consumers don't read
             // stale offsets.
             metadata.page_boundaries = Vec::new();
+            metadata.scanned_pages = extracted
+                .pages
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.decision == crate::pdf::PageDecision::NeedsOcr)
+                .map(|(idx, _)| idx + 1)
+                .collect();
             self.store_meta_inner(&doc, doc_id, &metadata).await?;
 
             let task = crate::pdf::OcrTask {
@@ -1219,15 +2214,10 @@ impl Storage {
 
             doc.close().await?;
 
-            let ocr_pages = extracted
-                .pages
-                .iter()
-                .filter(|p| p.decision == crate::pdf::PageDecision::NeedsOcr)
-                .count();
             tracing::info!(
                 doc_id = %doc_id,
                 page_count = extracted.page_count,
-                ocr_pages,
+                ocr_pages = metadata.scanned_pages.len(),
                 "Queued document for OCR ({})",
                 metadata.name
             );
@@ -1263,11 +2253,51 @@ impl Storage {
         Ok(Some(metadata))
     }
 
-    /// Read the OCR task payload for a document, if one was written.
-    pub async fn get_ocr_task(
+    /// Extraction for Markdown sources: the text is the source, as one page.
+    async fn store_markdown_text(
         &self,
         namespace_id: NamespaceId,
-        doc_id: &str,
+        mut metadata: DocumentMetadata,
+        source_bytes: &[u8],
+    ) -> Result<DocumentMetadata> {
+        let text = String::from_utf8_lossy(source_bytes);
+        metadata.page_count = 1;
+        metadata.page_boundaries = vec![text.len()];
+
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+        self.store_meta_inner(&doc, &metadata.id, &metadata).await?;
+
+        let text_hash = self.store_blob(text.as_bytes()).await?;
+        let text_key = doc_text_key(&metadata.id);
+        doc.set_hash(
+            self.author_id,
+            text_key.into_bytes(),
+            text_hash,
+            text.len() as u64,
+        )
+        .await?;
+
+        doc.close().await?;
+
+        tracing::info!(
+            doc_id = %metadata.id,
+            text_len = text.len(),
+            "Extracted Markdown text for {}",
+            metadata.name
+        );
+        Ok(metadata)
+    }
+
+    /// Read the OCR task payload for a document, if one was written.
+    pub async fn get_ocr_task(
+        &self,
+        namespace_id: NamespaceId,
+        doc_id: &str,
     ) -> Result<Option<crate::pdf::OcrTask>> {
         let doc = self
             .docs
@@ -1294,6 +2324,33 @@ impl Storage {
         Ok(Some(task))
     }
 
+    /// Mark a document's extraction as failed for good, with the error.
+    /// [`Self::find_pending_extractions`] skips it from then on; a later
+    /// successful retry writes `text`, which supersedes the marker.
+    pub async fn mark_extract_failed(
+        &self,
+        namespace_id: NamespaceId,
+        doc_id: &str,
+        error: &str,
+    ) -> Result<()> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+        let hash = self.store_blob(error.as_bytes()).await?;
+        doc.set_hash(
+            self.author_id,
+            doc_extract_failed_key(doc_id).into_bytes(),
+            hash,
+            error.len() as u64,
+        )
+        .await?;
+        doc.close().await?;
+        Ok(())
+    }
+
     /// Delete the OCR task entry. Called by the OCR worker once it has
     /// successfully written the merged text.
     pub async fn delete_ocr_task(&self, namespace_id: NamespaceId, doc_id: &str) -> Result<()> {
@@ -1360,6 +2417,525 @@ impl Storage {
         Ok(())
     }
 
+    /// Add and remove tags on a document. Tags are trimmed, empty ones are
+    /// ignored, and removal wins if a tag is in both lists.
+    ///
+    /// Each change is its own entry, so members editing a document's tags
+    /// at the same time keep each other's changes; see [`tags`].
+    ///
+    /// Returns the document's new tags, or `None` if it doesn't exist.
+    pub async fn update_document_tags(
+        &self,
+        namespace_id: NamespaceId,
+        doc_id: &str,
+        add: &[String],
+        remove: &[String],
+    ) -> Result<Option<Vec<String>>> {
+        if self.get_document(namespace_id, doc_id).await?.is_none() {
+            return Ok(None);
+        }
+
+        let remove: Vec<&str> = remove
+            .iter()
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+            .collect();
+        let add = add
+            .iter()
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty() && !remove.contains(t));
+        let edits: Vec<(&str, bool)> = add
+            .map(|t| (t, true))
+            .chain(remove.iter().map(|t| (*t, false)))
+            .collect();
+
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+        for (tag, added) in edits {
+            let content = tags::content(added);
+            let hash = self.store_blob(content).await?;
+            doc.set_hash(
+                self.author_id,
+                tags::key(doc_id, tag).into_bytes(),
+                hash,
+                content.len() as u64,
+            )
+            .await?;
+        }
+        doc.close().await?;
+
+        Ok(self
+            .get_document(namespace_id, doc_id)
+            .await?
+            .map(|metadata| metadata.tags))
+    }
+
+    /// Store a document's text fingerprint and link it with every other
+    /// document in the collection whose fingerprint is within
+    /// [`fingerprint::NEAR_DUPLICATE_BITS`]. Links are kept on both sides;
+    /// ones that no longer hold (the text was re-extracted) are dropped.
+    ///
+    /// Returns the document's near-duplicates, most similar first.
+    pub async fn record_fingerprint(
+        &self,
+        namespace_id: NamespaceId,
+        doc_id: &str,
+        simhash: u64,
+    ) -> Result<Vec<NearDuplicate>> {
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+
+        let mut matches = Vec::new();
+        for mut other in self.list_documents(namespace_id).await? {
+            if other.id == doc_id {
+                continue;
+            }
+            let near = other
+                .simhash
+                .filter(|&h| fingerprint::is_near_duplicate(simhash, h));
+            let linked = other.near_duplicates.iter().any(|d| d.doc_id == doc_id);
+            match near {
+                Some(h) => {
+                    let similarity = fingerprint::similarity(simhash, h);
+                    matches.push(NearDuplicate {
+                        doc_id: other.id.clone(),
+                        similarity,
+                    });
+                    if !linked {
+                        other.near_duplicates.push(NearDuplicate {
+                            doc_id: doc_id.to_string(),
+                            similarity,
+                        });
+                        self.store_meta_inner(&doc, &other.id, &other).await?;
+                    }
+                }
+                None if linked => {
+                    other.near_duplicates.retain(|d| d.doc_id != doc_id);
+                    self.store_meta_inner(&doc, &other.id, &other).await?;
+                }
+                None => {}
+            }
+        }
+        matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+
+        // Re-read right before writing so fields set since the scan started
+        // aren't overwritten.
+        if let Some(mut metadata) = self.get_document(namespace_id, doc_id).await? {
+            metadata.simhash = Some(simhash);
+            metadata.near_duplicates = matches.clone();
+            self.store_meta_inner(&doc, doc_id, &metadata).await?;
+        }
+        doc.close().await?;
+
+        Ok(matches)
+    }
+
+    /// Drop the permanent tag of every blob no entry in any namespace
+    /// refers to any more — parts of deleted documents and collections,
+    /// superseded metadata and embeddings. The blob store's GC then frees
+    /// them.
+    ///
+    /// [`Self::store_blob`] tags a blob before the entry pointing at it is
+    /// written, so the caller must hold the storage lock exclusively; a
+    /// blob caught in between would otherwise be untagged and lost.
+    ///
+    /// Returns the number of blobs untagged.
+    pub async fn untag_unreferenced_blobs(&self) -> Result<usize> {
+        let mut referenced = std::collections::HashSet::new();
+        let mut namespaces = self.docs.api().list().await?;
+        while let Some(result) = namespaces.next().await {
+            let (namespace_id, _) = result?;
+            let Some(doc) = self.docs.api().open(namespace_id).await? else {
+                continue;
+            };
+            let stream = doc.get_many(Query::all()).await?;
+            tokio::pin!(stream);
+            while let Some(entry) = stream.next().await {
+                referenced.insert(entry?.content_hash());
+            }
+            doc.close().await?;
+        }
+
+        let mut unreferenced = Vec::new();
+        let tags = self.blobs.tags().list().await?;
+        tokio::pin!(tags);
+        while let Some(tag) = tags.next().await {
+            let tag = tag?;
+            // Only tags named by `store_blob`; anything else isn't ours.
+            let named_for_hash = std::str::from_utf8(tag.name.as_ref())
+                .ok()
+                .and_then(|name| name.parse::<Hash>().ok())
+                == Some(tag.hash);
+            if named_for_hash && !referenced.contains(&tag.hash) {
+                unreferenced.push(tag.name);
+            }
+        }
+
+        for name in &unreferenced {
+            self.blobs.tags().delete(name).await?;
+        }
+        if !unreferenced.is_empty() {
+            tracing::info!(count = unreferenced.len(), "Untagged unreferenced blobs");
+        }
+        Ok(unreferenced.len())
+    }
+
+    /// Every source in every collection, by content hash, read from each
+    /// collection's `_hash_index/`. Serves as the global hash registry:
+    /// nothing is stored beyond the per-collection indexes, so it can't
+    /// drift from them.
+    pub async fn source_hash_registry(&self) -> Result<HashMap<Hash, Vec<(NamespaceId, String)>>> {
+        let mut registry: HashMap<Hash, Vec<(NamespaceId, String)>> = HashMap::new();
+        for (namespace_id, _) in self.list_collections().await? {
+            let Some(doc) = self.docs.api().open(namespace_id).await? else {
+                continue;
+            };
+            let stream = doc
+                .get_many(Query::key_prefix(HASH_INDEX_PREFIX.as_bytes()))
+                .await?;
+            tokio::pin!(stream);
+            let mut entries = Vec::new();
+            while let Some(result) = stream.next().await {
+                let entry = result?;
+                let hash = std::str::from_utf8(entry.key())
+                    .ok()
+                    .and_then(|key| key.strip_prefix(HASH_INDEX_PREFIX))
+                    .and_then(|hash| hash.parse::<Hash>().ok());
+                if let Some(hash) = hash {
+                    entries.push((hash, entry.content_hash()));
+                }
+            }
+            doc.close().await?;
+
+            for (hash, doc_id_hash) in entries {
+                if let Some(doc_id) = self.get_blob(&doc_id_hash).await? {
+                    registry
+                        .entry(hash)
+                        .or_default()
+                        .push((namespace_id, String::from_utf8_lossy(&doc_id).into_owned()));
+                }
+            }
+        }
+        Ok(registry)
+    }
+
+    /// ID of the document in `namespace_id` whose source has `hash`, read
+    /// from the collection's `_hash_index/`.
+    pub async fn find_source(
+        &self,
+        namespace_id: NamespaceId,
+        hash: &Hash,
+    ) -> Result<Option<String>> {
+        let Some(doc) = self.docs.api().open(namespace_id).await? else {
+            return Ok(None);
+        };
+        let key = hash_index_key(hash);
+        let entry = doc.get_one(Query::key_exact(key.as_bytes())).await?;
+        doc.close().await?;
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+        Ok(self
+            .get_blob(&entry.content_hash())
+            .await?
+            .map(|doc_id| String::from_utf8_lossy(&doc_id).into_owned()))
+    }
+
+    /// Documents in collections other than `namespace_id` whose source has
+    /// `hash`.
+    pub async fn find_source_elsewhere(
+        &self,
+        namespace_id: NamespaceId,
+        hash: &Hash,
+    ) -> Result<Vec<(NamespaceId, DocumentMetadata)>> {
+        let mut found = Vec::new();
+        for (other, _) in self.list_collections().await? {
+            if other == namespace_id {
+                continue;
+            }
+            let Some(doc_id) = self.find_source(other, hash).await? else {
+                continue;
+            };
+            if let Some(metadata) = self.get_document(other, &doc_id).await? {
+                found.push((other, metadata));
+            }
+        }
+        Ok(found)
+    }
+
+    /// Add document `doc_id` of collection `from` to collection `to` as
+    /// `new_doc_id`, pointing at the same blobs instead of storing and
+    /// processing it again. Source, text and embeddings are copied as
+    /// entries; a pending OCR task is not, so extraction redoes it.
+    ///
+    /// Entries are written metadata first and embeddings last, so by the
+    /// time the watcher queues indexing the document is complete.
+    pub async fn link_document(
+        &self,
+        from: NamespaceId,
+        doc_id: &str,
+        to: NamespaceId,
+        new_doc_id: &str,
+    ) -> Result<DocumentMetadata> {
+        let mut metadata = self
+            .get_document(from, doc_id)
+            .await?
+            .with_context(|| format!("Document not found: {}", doc_id))?;
+
+        let from_doc = self
+            .docs
+            .api()
+            .open(from)
+            .await?
+            .context("Collection not found")?;
+        let prefix = format!("{}{}/", FILES_PREFIX, doc_id);
+        let stream = from_doc
+            .get_many(Query::key_prefix(prefix.as_bytes()))
+            .await?;
+        tokio::pin!(stream);
+        // (part after `files/{id}`, content hash, length)
+        let mut parts: Vec<(String, Hash, u64)> = Vec::new();
+        while let Some(result) = stream.next().await {
+            let entry = result?;
+            let Some(part) = std::str::from_utf8(entry.key())
+                .ok()
+                .and_then(|key| key.strip_prefix(&prefix[..prefix.len() - 1]))
+            else {
+                continue;
+            };
+            if part == META_SUFFIX || part == OCR_TASK_SUFFIX {
+                continue;
+            }
+            parts.push((part.to_string(), entry.content_hash(), entry.content_len()));
+        }
+        from_doc.close().await?;
+
+        let source_hash = parts
+            .iter()
+            .find(|(part, _, _)| part == SOURCE_SUFFIX)
+            .map(|(_, hash, _)| *hash)
+            .with_context(|| format!("Document has no source: {}", doc_id))?;
+        if self.has_source_hash(to, &source_hash).await? {
+            anyhow::bail!("Duplicate document: {}", metadata.name);
+        }
+        parts.sort_by_key(|(part, _, _)| match part.as_str() {
+            SOURCE_SUFFIX => 0,
+            TEXT_SUFFIX => 1,
+            _ => 2,
+        });
+
+        let to_doc = self
+            .docs
+            .api()
+            .open(to)
+            .await?
+            .context("Collection not found")?;
+        metadata.id = new_doc_id.to_string();
+        metadata.created_at = chrono::Utc::now().to_rfc3339();
+        // Near-duplicate links name documents in the source collection;
+        // the caller re-checks against this one.
+        metadata.simhash = None;
+        metadata.near_duplicates.clear();
+        self.store_meta_inner(&to_doc, new_doc_id, &metadata)
+            .await?;
+        for (part, hash, len) in parts {
+            let key = format!("{}{}{}", FILES_PREFIX, new_doc_id, part);
+            to_doc
+                .set_hash(self.author_id, key.into_bytes(), hash, len)
+                .await?;
+        }
+        let doc_id_hash = self.store_blob(new_doc_id.as_bytes()).await?;
+        to_doc
+            .set_hash(
+                self.author_id,
+                hash_index_key(&source_hash).into_bytes(),
+                doc_id_hash,
+                new_doc_id.len() as u64,
+            )
+            .await?;
+        to_doc.close().await?;
+
+        tracing::info!(
+            from = %from,
+            to = %to,
+            doc_id = %new_doc_id,
+            "Linked document '{}'",
+            metadata.name
+        );
+        Ok(metadata)
+    }
+
+    /// Send document `doc_id` to the node `peer`, without the rest of the
+    /// collection, as `from_name`. Returns the blob ticket for it, which
+    /// can also be passed on by hand. Fails if the peer can't be reached.
+    pub async fn send_document(
+        &self,
+        namespace_id: NamespaceId,
+        doc_id: &str,
+        peer: &str,
+        from_name: Option<&str>,
+    ) -> Result<String> {
+        let peer: iroh::EndpointId = peer.parse().context("Invalid node ID")?;
+        let metadata = self
+            .get_document(namespace_id, doc_id)
+            .await?
+            .with_context(|| format!("Document not found: {}", doc_id))?;
+
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+        let source = doc
+            .get_one(Query::key_exact(doc_source_key(doc_id).as_bytes()))
+            .await?
+            .map(|e| (e.content_hash(), e.content_len()));
+        let text = doc
+            .get_one(Query::key_exact(doc_text_key(doc_id).as_bytes()))
+            .await?
+            .map(|e| (e.content_hash(), e.content_len()));
+        doc.close().await?;
+        let source = source.with_context(|| format!("Document has no source: {}", doc_id))?;
+
+        let name = metadata.name.clone();
+        let bundle = DocumentBundle {
+            metadata,
+            source,
+            text,
+        };
+        let manifest = self.store_blob(&serde_json::to_vec(&bundle)?).await?;
+        let ticket =
+            BlobTicket::new(self.router.endpoint().addr(), manifest, BlobFormat::Raw).to_string();
+
+        transfer::offer(
+            self.router.endpoint(),
+            peer,
+            ticket.clone(),
+            name.clone(),
+            from_name.map(str::to_string),
+        )
+        .await?;
+        tracing::info!(peer = %peer.fmt_short(), doc_id = %doc_id, "Sent document '{}'", name);
+        Ok(ticket)
+    }
+
+    /// Content peers download from this node, as each transfer ends.
+    pub fn subscribe_uploads(&self) -> tokio::sync::broadcast::Receiver<Upload> {
+        self.uploads.subscribe()
+    }
+
+    /// Documents other nodes send this one, as they arrive.
+    pub fn subscribe_offers(&self) -> tokio::sync::broadcast::Receiver<DocumentOffer> {
+        self.inbox.subscribe()
+    }
+
+    /// Download the bundle a document ticket points at, returning it and
+    /// the node to download its content from.
+    pub async fn fetch_bundle(&self, ticket: &str) -> Result<(DocumentBundle, iroh::EndpointId)> {
+        let ticket: BlobTicket = ticket.parse().context("Invalid document ticket")?;
+        let sender = ticket.addr().id;
+        self.download_blob(ticket.hash(), sender).await?;
+        let bytes = self
+            .get_blob(&ticket.hash())
+            .await?
+            .context("Document bundle missing")?;
+        let bundle = serde_json::from_slice(&bytes).context("Invalid document bundle")?;
+        Ok((bundle, sender))
+    }
+
+    /// Add a sent document to a collection as `doc_id`, downloading its
+    /// source and text from `sender`. The text is written after the
+    /// source, so extraction can be skipped once it lands.
+    pub async fn add_bundle(
+        &self,
+        namespace_id: NamespaceId,
+        bundle: DocumentBundle,
+        sender: iroh::EndpointId,
+        doc_id: &str,
+    ) -> Result<DocumentMetadata> {
+        let (source_hash, source_len) = bundle.source;
+        if self.has_source_hash(namespace_id, &source_hash).await? {
+            anyhow::bail!("Duplicate document: {}", bundle.metadata.name);
+        }
+        self.download_blob(source_hash, sender).await?;
+        if let Some((text_hash, _)) = bundle.text {
+            self.download_blob(text_hash, sender).await?;
+        }
+
+        let doc = self
+            .docs
+            .api()
+            .open(namespace_id)
+            .await?
+            .context("Collection not found")?;
+        let mut metadata = bundle.metadata;
+        metadata.id = doc_id.to_string();
+        metadata.created_at = chrono::Utc::now().to_rfc3339();
+        // Near-duplicate links name documents in the sender's collection;
+        // the caller re-checks against this one.
+        metadata.simhash = None;
+        metadata.near_duplicates.clear();
+        self.store_meta_inner(&doc, doc_id, &metadata).await?;
+        doc.set_hash(
+            self.author_id,
+            doc_source_key(doc_id).into_bytes(),
+            source_hash,
+            source_len,
+        )
+        .await?;
+        if let Some((text_hash, text_len)) = bundle.text {
+            doc.set_hash(
+                self.author_id,
+                doc_text_key(doc_id).into_bytes(),
+                text_hash,
+                text_len,
+            )
+            .await?;
+        }
+        let doc_id_hash = self.store_blob(doc_id.as_bytes()).await?;
+        doc.set_hash(
+            self.author_id,
+            hash_index_key(&source_hash).into_bytes(),
+            doc_id_hash,
+            doc_id.len() as u64,
+        )
+        .await?;
+        doc.close().await?;
+
+        tracing::info!(
+            from = %sender.fmt_short(),
+            doc_id = %doc_id,
+            "Received document '{}'",
+            metadata.name
+        );
+        Ok(metadata)
+    }
+
+    /// Download a blob from `peer` and tag it, so it is kept until
+    /// entries refer to it.
+    async fn download_blob(&self, hash: Hash, peer: iroh::EndpointId) -> Result<()> {
+        if !self.blobs.has(hash).await? {
+            self.downloader()
+                .download(hash, vec![peer])
+                .await
+                .with_context(|| format!("Failed to download {} from the sender", hash))?;
+        }
+        self.blobs
+            .tags()
+            .set(hash.to_string(), HashAndFormat::raw(hash))
+            .await?;
+        Ok(())
+    }
+
     /// Internal helper: serialize + store the metadata entry on an open
     /// doc handle.
     async fn store_meta_inner(
@@ -1389,7 +2965,9 @@ mod tests {
     #[tokio::test]
     async fn test_blob_roundtrip() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         let data = b"hello world";
         let hash = storage.store_blob(data).await.unwrap();
@@ -1401,7 +2979,9 @@ mod tests {
     #[tokio::test]
     async fn test_get_nonexistent_blob() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         let fake_hash = Hash::from_bytes([0u8; 32]);
         let result = storage.get_blob(&fake_hash).await.unwrap();
@@ -1411,7 +2991,9 @@ mod tests {
     #[tokio::test]
     async fn test_create_and_list_collections() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         // Initially empty
         let collections = storage.list_collections().await.unwrap();
@@ -1431,7 +3013,9 @@ mod tests {
     #[tokio::test]
     async fn test_get_collection_metadata() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         let (id, _) = storage.create_collection("My Docs").await.unwrap();
 
@@ -1440,10 +3024,72 @@ mod tests {
         assert_eq!(metadata.unwrap().name, "My Docs");
     }
 
+    #[tokio::test]
+    async fn test_collection_members_include_registered_author() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
+        let (id, _) = storage.create_collection("Shared").await.unwrap();
+
+        storage.register_author(id, Some("Ada")).await.unwrap();
+        // Unchanged, so nothing is written.
+        storage.register_author(id, Some("Ada")).await.unwrap();
+
+        let members = storage.collection_members(id).await.unwrap();
+        assert_eq!(members.len(), 1);
+        let me = &members[0];
+        assert!(me.is_self);
+        assert_eq!(me.name.as_deref(), Some("Ada"));
+        assert_eq!(me.author_id, Some(storage.author_id().to_string()));
+        // The `_collection` entry; the registry entry isn't counted.
+        assert_eq!(me.entries, 1);
+        assert!(!me.replicates);
+    }
+
+    #[tokio::test]
+    async fn test_chat_history_in_sent_order() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
+        let (id, _) = storage.create_collection("Shared").await.unwrap();
+
+        let message = |message_id: &str, sent_at: &str| ChatMessage {
+            id: message_id.to_string(),
+            collection_id: String::new(),
+            node_id: "node".to_string(),
+            name: Some("Ada".to_string()),
+            text: format!("message {}", message_id),
+            sent_at: sent_at.to_string(),
+        };
+        for (message_id, sent_at) in [
+            ("b", "2026-03-01T10:00:00.5+00:00"),
+            ("c", "2026-03-01T10:00:01+00:00"),
+            ("a", "2026-03-01T09:59:59.123456+00:00"),
+        ] {
+            storage
+                .store_chat_message(id, &message(message_id, sent_at))
+                .await
+                .unwrap();
+        }
+
+        let history = storage.chat_history(id, 10).await.unwrap();
+        let ids: Vec<&str> = history.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+        assert_eq!(history[0].collection_id, id.to_string());
+
+        let latest = storage.chat_history(id, 2).await.unwrap();
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[1].id, "c");
+    }
+
     #[tokio::test]
     async fn test_count_documents_empty() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         let (id, _) = storage.create_collection("Empty").await.unwrap();
         let count = storage.count_documents(id).await.unwrap();
@@ -1453,7 +3099,9 @@ mod tests {
     #[tokio::test]
     async fn test_add_and_list_documents() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         let (collection_id, _) = storage.create_collection("My Docs").await.unwrap();
 
@@ -1466,6 +3114,10 @@ mod tests {
             tags: vec!["test".to_string()],
             created_at: "2024-01-01T00:00:00Z".to_string(),
             page_boundaries: vec![],
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: None,
+            near_duplicates: Vec::new(),
         };
         let text_content = b"This is the extracted text";
         let source_content = b"PDF bytes here";
@@ -1499,10 +3151,154 @@ mod tests {
         assert_eq!(source, Some(source_content.to_vec()));
     }
 
+    #[tokio::test]
+    async fn test_add_bundle_writes_sent_document() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
+        let (from, _) = storage.create_collection("Sender").await.unwrap();
+        let (to, _) = storage.create_collection("Recipient").await.unwrap();
+
+        let text = b"Exhibit text";
+        let source = b"Exhibit PDF bytes";
+        let metadata = DocumentMetadata {
+            id: "doc-1".to_string(),
+            name: "exhibit.pdf".to_string(),
+            file_type: "application/pdf".to_string(),
+            page_count: 1,
+            tags: vec![],
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            page_boundaries: vec![],
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: Some(42),
+            near_duplicates: Vec::new(),
+        };
+        storage
+            .add_document(from, metadata.clone(), text, source)
+            .await
+            .unwrap();
+        let bundle = DocumentBundle {
+            metadata,
+            source: (Hash::new(source), source.len() as u64),
+            text: Some((Hash::new(text), text.len() as u64)),
+        };
+
+        // Content already here, so nothing is downloaded from the sender.
+        let received = storage
+            .add_bundle(to, bundle.clone(), storage.node_id(), "doc-2")
+            .await
+            .unwrap();
+        assert_eq!(received.id, "doc-2");
+        assert_eq!(received.name, "exhibit.pdf");
+        assert!(received.simhash.is_none());
+        assert_eq!(
+            storage.get_document_text(to, "doc-2").await.unwrap(),
+            Some(text.to_vec())
+        );
+        assert!(storage
+            .has_source_hash(to, &Hash::new(source))
+            .await
+            .unwrap());
+
+        // Sending it again is a duplicate.
+        assert!(storage
+            .add_bundle(to, bundle, storage.node_id(), "doc-3")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_document_tags() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
+
+        let (collection_id, _) = storage.create_collection("My Docs").await.unwrap();
+        let doc = DocumentMetadata {
+            id: "doc-1".to_string(),
+            name: "test.pdf".to_string(),
+            file_type: "application/pdf".to_string(),
+            page_count: 1,
+            tags: vec!["draft".to_string()],
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            page_boundaries: vec![],
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: None,
+            near_duplicates: Vec::new(),
+        };
+        storage
+            .add_document(collection_id, doc, b"text", b"source")
+            .await
+            .unwrap();
+
+        let tags = storage
+            .update_document_tags(
+                collection_id,
+                "doc-1",
+                &[" acme ".to_string(), "draft".to_string(), "".to_string()],
+                &["draft".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(tags, Some(vec!["acme".to_string()]));
+
+        let stored = storage
+            .get_document(collection_id, "doc-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.tags, vec!["acme".to_string()]);
+
+        // Another member's edits compose with this node's instead of
+        // replacing them.
+        let other = storage.docs.api().author_create().await.unwrap();
+        let doc = storage
+            .docs
+            .api()
+            .open(collection_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let added = tags::content(true);
+        let hash = storage.store_blob(added).await.unwrap();
+        doc.set_hash(
+            other,
+            tags::key("doc-1", "reviewed").into_bytes(),
+            hash,
+            added.len() as u64,
+        )
+        .await
+        .unwrap();
+        doc.close().await.unwrap();
+        let tags = storage
+            .update_document_tags(collection_id, "doc-1", &["urgent".to_string()], &[])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(tags.len(), 3);
+        assert!(["acme", "reviewed", "urgent"]
+            .iter()
+            .all(|t| tags.contains(&t.to_string())));
+        let listed = storage.list_documents(collection_id).await.unwrap();
+        assert_eq!(listed[0].tags, tags);
+
+        let missing = storage
+            .update_document_tags(collection_id, "nope", &["x".to_string()], &[])
+            .await
+            .unwrap();
+        assert!(missing.is_none());
+    }
+
     #[tokio::test]
     async fn test_delete_document() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         let (collection_id, _) = storage.create_collection("My Docs").await.unwrap();
 
@@ -1515,6 +3311,10 @@ mod tests {
             tags: vec![],
             created_at: "2024-01-01T00:00:00Z".to_string(),
             page_boundaries: vec![],
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: None,
+            near_duplicates: Vec::new(),
         };
         let source1 = b"source1";
 
@@ -1526,6 +3326,10 @@ mod tests {
             tags: vec![],
             created_at: "2024-01-01T00:00:00Z".to_string(),
             page_boundaries: vec![],
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: None,
+            near_duplicates: Vec::new(),
         };
         let source2 = b"source2";
 
@@ -1561,7 +3365,9 @@ mod tests {
     #[tokio::test]
     async fn test_delete_document_clears_ocr_task() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         let (collection_id, _) = storage.create_collection("OCR cleanup").await.unwrap();
 
@@ -1573,6 +3379,10 @@ mod tests {
             tags: vec![],
             created_at: "2024-01-01T00:00:00Z".to_string(),
             page_boundaries: vec![],
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: None,
+            near_duplicates: Vec::new(),
         };
         storage
             .add_document(collection_id, doc, b"text", b"source")
@@ -1627,7 +3437,9 @@ mod tests {
     #[tokio::test]
     async fn test_delete_collection() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         // Create two collections
         let (id1, _) = storage.create_collection("First").await.unwrap();
@@ -1649,7 +3461,9 @@ mod tests {
         use futures::StreamExt;
 
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         let (collection_id, _) = storage.create_collection("Events Test").await.unwrap();
 
@@ -1665,6 +3479,10 @@ mod tests {
             tags: vec![],
             created_at: "2024-01-01T00:00:00Z".to_string(),
             page_boundaries: vec![],
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: None,
+            near_duplicates: Vec::new(),
         };
         let source = b"source";
         storage
@@ -1691,7 +3509,9 @@ mod tests {
     #[tokio::test]
     async fn test_duplicate_detection() {
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
 
         let (collection_id, _) = storage.create_collection("Duplicates Test").await.unwrap();
 
@@ -1714,6 +3534,10 @@ mod tests {
             tags: vec![],
             created_at: "2024-01-01T00:00:00Z".to_string(),
             page_boundaries: vec![],
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: None,
+            near_duplicates: Vec::new(),
         };
         storage
             .add_document(collection_id, doc, b"text", source_content)
@@ -1789,7 +3613,9 @@ mod tests {
         }
 
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
         let (collection_id, _) = storage.create_collection("Two Phase Test").await.unwrap();
 
         // Create a test PDF file. Needs enough alphanumerics to clear
@@ -1802,7 +3628,7 @@ mod tests {
 
         // Phase 1: Store source
         let doc_id = storage
-            .store_pdf_source(&pdf_path, collection_id)
+            .store_pdf_source(&pdf_path, collection_id, &[])
             .await
             .unwrap();
         assert!(!doc_id.is_empty());
@@ -1858,12 +3684,97 @@ mod tests {
             .unwrap());
     }
 
+    #[tokio::test]
+    async fn test_markdown_source_extracts_as_one_page() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
+        let (collection_id, _) = storage.create_collection("Reports").await.unwrap();
+
+        let markdown = "# Findings\n\nAcme paid the invoice twice.\n";
+        let doc_id = storage
+            .store_markdown_source(collection_id, "Findings.md", markdown)
+            .await
+            .unwrap();
+
+        let metadata = storage
+            .extract_and_dispatch(collection_id, &doc_id)
+            .await
+            .unwrap()
+            .expect("document should exist");
+        assert_eq!(metadata.file_type, MARKDOWN_FILE_TYPE);
+        assert_eq!(metadata.page_count, 1);
+        assert_eq!(metadata.page_boundaries, vec![markdown.len()]);
+
+        let text = storage
+            .get_document_text(collection_id, &doc_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(text, markdown.as_bytes());
+
+        // Saving the same report again is a duplicate.
+        assert!(storage
+            .store_markdown_source(collection_id, "Copy.md", markdown)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_find_pending_extractions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
+        let (collection_id, _) = storage.create_collection("Extract Pending").await.unwrap();
+
+        let doc = storage
+            .docs
+            .api()
+            .open(collection_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let peer = storage.docs.api().author_create().await.unwrap();
+        let source = b"%PDF-1.7";
+        let hash = storage.store_blob(source).await.unwrap();
+        for (author, doc_id) in [
+            (storage.author_id, "interrupted"),
+            (storage.author_id, "broken"),
+            (peer, "from-peer"),
+        ] {
+            doc.set_hash(
+                author,
+                doc_source_key(doc_id).into_bytes(),
+                hash,
+                source.len() as u64,
+            )
+            .await
+            .unwrap();
+        }
+        doc.close().await.unwrap();
+        storage
+            .mark_extract_failed(collection_id, "broken", "Not a PDF")
+            .await
+            .unwrap();
+
+        // Failed documents and ones a peer imported are not retried.
+        let pending = storage
+            .find_pending_extractions(collection_id)
+            .await
+            .unwrap();
+        assert_eq!(pending, vec!["interrupted".to_string()]);
+    }
+
     #[tokio::test]
     async fn test_find_pending_ocr_tasks() {
         use crate::pdf::{OcrTask, PageDecision, PageExtraction};
 
         let temp_dir = tempfile::tempdir().unwrap();
-        let storage = Storage::open(temp_dir.path()).await.unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
         let (collection_id, _) = storage.create_collection("OCR Pending").await.unwrap();
 
         // Empty collection → no orphans.
@@ -1925,4 +3836,199 @@ mod tests {
         let pending = storage.find_pending_ocr_tasks(collection_id).await.unwrap();
         assert_eq!(pending, vec!["orphan-1".to_string()]);
     }
+
+    #[tokio::test]
+    async fn test_find_pending_embeddings() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
+        let (collection_id, _) = storage.create_collection("Embed Pending").await.unwrap();
+
+        for id in ["doc-1", "doc-2"] {
+            let doc = DocumentMetadata {
+                id: id.to_string(),
+                name: format!("{}.pdf", id),
+                file_type: "application/pdf".to_string(),
+                page_count: 1,
+                tags: vec![],
+                created_at: "2024-01-01T00:00:00Z".to_string(),
+                page_boundaries: vec![],
+                scanned_pages: Vec::new(),
+                source_url: None,
+                simhash: None,
+                near_duplicates: Vec::new(),
+            };
+            storage
+                .add_document(collection_id, doc, b"text", b"source")
+                .await
+                .unwrap();
+        }
+
+        let mut pending = storage
+            .find_pending_embeddings(collection_id, "model-a")
+            .await
+            .unwrap();
+        pending.sort();
+        assert_eq!(pending, vec!["doc-1".to_string(), "doc-2".to_string()]);
+
+        let data = EmbeddingData {
+            model_id: "model-a".to_string(),
+            dimensions: 2,
+            chunks: vec![],
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            chunking: None,
+            projection: None,
+            vector_encoding: VectorEncoding::F16,
+        };
+        storage
+            .store_embeddings(collection_id, "doc-1", data)
+            .await
+            .unwrap();
+
+        assert!(storage
+            .has_embeddings(collection_id, "doc-1", "model-a")
+            .await
+            .unwrap());
+        assert!(!storage
+            .has_embeddings(collection_id, "doc-1", "model-b")
+            .await
+            .unwrap());
+
+        // doc-1 is done for model-a but still pending for any other model.
+        let pending = storage
+            .find_pending_embeddings(collection_id, "model-a")
+            .await
+            .unwrap();
+        assert_eq!(pending, vec!["doc-2".to_string()]);
+
+        let pending = storage
+            .find_pending_embeddings(collection_id, "model-b")
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_list_documents_with_text() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
+        let (collection_id, _) = storage.create_collection("With Text").await.unwrap();
+
+        assert!(storage
+            .list_documents_with_text(collection_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let doc = DocumentMetadata {
+            id: "doc-1".to_string(),
+            name: "doc-1.pdf".to_string(),
+            file_type: "application/pdf".to_string(),
+            page_count: 1,
+            tags: vec![],
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            page_boundaries: vec![],
+            scanned_pages: Vec::new(),
+            source_url: None,
+            simhash: None,
+            near_duplicates: Vec::new(),
+        };
+        storage
+            .add_document(collection_id, doc, b"text", b"source")
+            .await
+            .unwrap();
+
+        let ids = storage
+            .list_documents_with_text(collection_id)
+            .await
+            .unwrap();
+        assert_eq!(ids, vec!["doc-1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_set_collection_chunking() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
+        let (collection_id, _) = storage.create_collection("Chunked").await.unwrap();
+
+        let metadata = storage
+            .get_collection_metadata(collection_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(metadata.chunking.is_none());
+
+        let chunking = ChunkingConfig {
+            strategy: crate::provider::ChunkStrategy::Semantic,
+            target_tokens: 256,
+            overlap_tokens: 32,
+            max_chunks_per_doc: Some(100),
+        };
+        storage
+            .set_collection_chunking(collection_id, Some(chunking.clone()))
+            .await
+            .unwrap();
+
+        let metadata = storage
+            .get_collection_metadata(collection_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.name, "Chunked");
+        assert_eq!(metadata.chunking, Some(chunking));
+
+        storage
+            .set_collection_chunking(collection_id, None)
+            .await
+            .unwrap();
+        let metadata = storage
+            .get_collection_metadata(collection_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(metadata.chunking.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_set_collection_projection() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(temp_dir.path(), &PeerAccess::default())
+            .await
+            .unwrap();
+        let (collection_id, _) = storage.create_collection("Projected").await.unwrap();
+
+        assert!(storage
+            .get_collection_projection(collection_id)
+            .await
+            .unwrap()
+            .is_none());
+
+        let projection = Projection::generate(ProjectionSpec::new("model-a", 32, 8).unwrap());
+        let metadata = storage
+            .set_collection_projection(collection_id, Some(&projection))
+            .await
+            .unwrap();
+        assert_eq!(metadata.projection, Some(projection.spec.clone()));
+
+        let loaded = storage
+            .get_collection_projection(collection_id)
+            .await
+            .unwrap();
+        assert_eq!(loaded, Some(projection));
+
+        storage
+            .set_collection_projection(collection_id, None)
+            .await
+            .unwrap();
+        assert!(storage
+            .get_collection_projection(collection_id)
+            .await
+            .unwrap()
+            .is_none());
+    }
 }
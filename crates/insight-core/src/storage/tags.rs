@@ -0,0 +1,136 @@
+//! Document tags that merge across peers.
+//!
+//! Each tag added to or removed from a document is its own entry,
+//! `files/{id}/tags/{tag}`, saying which. The latest entry under a key
+//! decides, whoever wrote it, so members tagging a document at the same
+//! time keep each other's tags rather than the last metadata write
+//! replacing the whole list. Tags in the document's metadata, given at
+//! import, stand until an entry removes them.
+//!
+//! Tags are hex-encoded in keys, so none can pass for another part of the
+//! document (`meta`, `text`) or contain a `/`. Whether a tag was added or
+//! removed is told by the entry's content hash, before any content is
+//! downloaded.
+
+use std::collections::HashMap;
+
+use iroh_blobs::Hash;
+
+use super::FILES_PREFIX;
+
+/// Part of a document's keys its tag entries go under.
+const TAGS_PART: &str = "/tags/";
+
+const ADDED: &[u8] = b"added";
+const REMOVED: &[u8] = b"removed";
+
+/// Content of an entry adding or removing a tag.
+pub(super) fn content(added: bool) -> &'static [u8] {
+    if added {
+        ADDED
+    } else {
+        REMOVED
+    }
+}
+
+/// Prefix of a document's tag entries.
+pub(super) fn prefix(doc_id: &str) -> String {
+    format!("{}{}{}", FILES_PREFIX, doc_id, TAGS_PART)
+}
+
+/// Key of the entry adding or removing `tag` on a document.
+pub(super) fn key(doc_id: &str, tag: &str) -> String {
+    let encoded: String = tag.bytes().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", prefix(doc_id), encoded)
+}
+
+/// Document ID and tag of a tag entry's key.
+pub(super) fn parse_key(key: &str) -> Option<(&str, String)> {
+    let (doc_id, encoded) = key.strip_prefix(FILES_PREFIX)?.split_once(TAGS_PART)?;
+    if encoded.len() % 2 != 0 {
+        return None;
+    }
+    let bytes = (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(encoded.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    Some((doc_id, String::from_utf8(bytes).ok()?))
+}
+
+/// A document's tag entries, the latest for each tag.
+#[derive(Debug, Default)]
+pub(super) struct TagEdits {
+    /// Whether each tag was added, with when the entry was written and
+    /// its content hash, which breaks ties.
+    latest: HashMap<String, (u64, Hash, bool)>,
+}
+
+impl TagEdits {
+    /// Record a tag entry. Entries with unknown content are ignored.
+    pub(super) fn record(&mut self, tag: String, timestamp: u64, hash: Hash) {
+        let added = if hash == Hash::new(ADDED) {
+            true
+        } else if hash == Hash::new(REMOVED) {
+            false
+        } else {
+            return;
+        };
+        let edit = (timestamp, hash, added);
+        match self.latest.get(&tag) {
+            Some(latest) if (latest.0, latest.1) >= (edit.0, edit.1) => {}
+            _ => {
+                self.latest.insert(tag, edit);
+            }
+        }
+    }
+
+    /// Apply the edits to a document's tags from metadata. Tags added
+    /// since come after them, oldest first.
+    pub(super) fn apply(&self, tags: &mut Vec<String>) {
+        tags.retain(|tag| self.latest.get(tag).is_none_or(|(_, _, added)| *added));
+        let mut added: Vec<(u64, &String)> = self
+            .latest
+            .iter()
+            .filter(|(tag, (_, _, added))| *added && !tags.contains(tag))
+            .map(|(tag, (timestamp, _, _))| (*timestamp, tag))
+            .collect();
+        added.sort();
+        tags.extend(added.into_iter().map(|(_, tag)| tag.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_keys_round_trip() {
+        let key = key("doc-1", "meta/ü");
+        assert!(key.starts_with("files/doc-1/tags/"));
+        assert!(!key.ends_with("/meta"));
+        assert_eq!(parse_key(&key), Some(("doc-1", "meta/ü".to_string())));
+        assert_eq!(parse_key("files/doc-1/meta"), None);
+        assert_eq!(parse_key("files/doc-1/tags/zz"), None);
+    }
+
+    #[test]
+    fn test_concurrent_tag_edits_merge() {
+        let added = Hash::new(content(true));
+        let removed = Hash::new(content(false));
+        let mut edits = TagEdits::default();
+
+        // Two members add different tags at once; one removes an
+        // imported tag the other had re-added earlier.
+        edits.record("acme".to_string(), 20, added);
+        edits.record("urgent".to_string(), 10, added);
+        edits.record("draft".to_string(), 5, added);
+        edits.record("draft".to_string(), 30, removed);
+        // An older add doesn't undo a later removal.
+        edits.record("draft".to_string(), 15, added);
+        edits.record("junk".to_string(), 40, Hash::new(b"other"));
+
+        let mut tags = vec!["draft".to_string(), "contract".to_string()];
+        edits.apply(&mut tags);
+        assert_eq!(tags, vec!["contract", "urgent", "acme"]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Sending a single document to another node.
+//!
+//! The sender stores a manifest blob naming the document's source and text
+//! blobs, and hands the recipient a blob ticket for it over [`INBOX_ALPN`].
+//! Nothing else of the collection is shared. The recipient sees an offer;
+//! accepting it downloads the manifest, source and text from the sender
+//! into a collection of their choice, so the sender has to stay online
+//! until then.
+
+use anyhow::Result;
+use iroh::endpoint::Connection;
+use iroh::protocol::{AcceptError, ProtocolHandler};
+use iroh::{Endpoint, EndpointId};
+use iroh_blobs::Hash;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use super::DocumentMetadata;
+
+/// Protocol offers are sent over.
+pub const INBOX_ALPN: &[u8] = b"insight/inbox/0";
+
+/// Largest offer accepted; a ticket and a few names.
+const MAX_OFFER_BYTES: usize = 16 * 1024;
+
+/// Offers held for subscribers that fall behind.
+const OFFER_CAPACITY: usize = 64;
+
+/// What a sent document consists of.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentBundle {
+    pub metadata: DocumentMetadata,
+    pub source: (Hash, u64),
+    /// `None` if the sender hadn't extracted the text yet.
+    pub text: Option<(Hash, u64)>,
+}
+
+/// A document another node sent this one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentOffer {
+    /// Blob ticket for the document's bundle.
+    pub ticket: String,
+    pub name: String,
+    /// The sender's node ID.
+    pub from: String,
+    /// The sender's display name.
+    pub from_name: Option<String>,
+    /// When it arrived, RFC 3339.
+    pub received_at: String,
+}
+
+/// What goes over the wire; the recipient fills in the rest of the offer.
+#[derive(Serialize, Deserialize)]
+struct Offer {
+    ticket: String,
+    name: String,
+    from_name: Option<String>,
+}
+
+/// Accepts offers from other nodes and passes them on.
+#[derive(Debug, Clone)]
+pub(super) struct Inbox {
+    offers: broadcast::Sender<DocumentOffer>,
+}
+
+impl Inbox {
+    pub(super) fn new() -> Self {
+        let (offers, _) = broadcast::channel(OFFER_CAPACITY);
+        Self { offers }
+    }
+
+    pub(super) fn subscribe(&self) -> broadcast::Receiver<DocumentOffer> {
+        self.offers.subscribe()
+    }
+}
+
+impl ProtocolHandler for Inbox {
+    async fn accept(&self, connection: Connection) -> Result<(), AcceptError> {
+        let from = connection.remote_id();
+        let mut recv = connection.accept_uni().await?;
+        let bytes = recv
+            .read_to_end(MAX_OFFER_BYTES)
+            .await
+            .map_err(AcceptError::from_err)?;
+        let offer: Offer = serde_json::from_slice(&bytes).map_err(AcceptError::from_err)?;
+        tracing::info!(from = %from.fmt_short(), name = %offer.name, "Document offered");
+
+        let _ = self.offers.send(DocumentOffer {
+            ticket: offer.ticket,
+            name: offer.name,
+            from: from.to_string(),
+            from_name: offer.from_name,
+            received_at: chrono::Utc::now().to_rfc3339(),
+        });
+        connection.close(0u32.into(), b"received");
+        Ok(())
+    }
+}
+
+/// Offer the document `ticket` points at to `peer`, as `from_name`.
+/// Returns once the peer has read the offer.
+pub(super) async fn offer(
+    endpoint: &Endpoint,
+    peer: EndpointId,
+    ticket: String,
+    name: String,
+    from_name: Option<String>,
+) -> Result<()> {
+    let bytes = serde_json::to_vec(&Offer {
+        ticket,
+        name,
+        from_name,
+    })?;
+    let connection = endpoint.connect(peer, INBOX_ALPN).await?;
+    let mut send = connection.open_uni().await?;
+    send.write_all(&bytes).await?;
+    send.finish()?;
+    // The peer closes the connection once it has the offer.
+    connection.closed().await;
+    Ok(())
+}
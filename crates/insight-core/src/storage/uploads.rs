@@ -0,0 +1,63 @@
+//! Content peers download from this node.
+//!
+//! The blobs protocol notifies each request it serves; once a transfer
+//! completes or is aborted, the bytes it sent are passed on as an
+//! [`Upload`].
+
+use iroh_blobs::provider::events::{
+    EventMask, EventSender, ProviderMessage, RequestMode, RequestUpdate,
+};
+use iroh_blobs::Hash;
+use tokio::sync::broadcast;
+
+/// Provider events waiting to be read.
+const EVENT_CAPACITY: usize = 64;
+
+/// Uploads held for subscribers that fall behind.
+const UPLOAD_CAPACITY: usize = 256;
+
+/// Content sent to a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Upload {
+    pub hash: Hash,
+    /// Content bytes sent, not counting protocol overhead.
+    pub bytes: u64,
+}
+
+/// Events for the blobs protocol to report requests to, and where the
+/// uploads they make are broadcast.
+pub(super) fn events() -> (EventSender, broadcast::Sender<Upload>) {
+    let mask = EventMask {
+        get: RequestMode::NotifyLog,
+        ..EventMask::DEFAULT
+    };
+    let (events, mut messages) = EventSender::channel(EVENT_CAPACITY, mask);
+    let (uploads, _) = broadcast::channel(UPLOAD_CAPACITY);
+
+    let sender = uploads.clone();
+    tokio::spawn(async move {
+        while let Some(message) = messages.recv().await {
+            let ProviderMessage::GetRequestReceivedNotify(request) = message else {
+                continue;
+            };
+            let hash = request.request.hash;
+            let mut updates = request.rx;
+            let uploads = sender.clone();
+            tokio::spawn(async move {
+                while let Ok(Some(update)) = updates.recv().await {
+                    let stats = match update {
+                        RequestUpdate::Completed(done) => done.stats,
+                        RequestUpdate::Aborted(aborted) => aborted.stats,
+                        _ => continue,
+                    };
+                    let _ = uploads.send(Upload {
+                        hash,
+                        bytes: stats.payload_bytes_sent,
+                    });
+                    break;
+                }
+            });
+        }
+    });
+    (events, uploads)
+}
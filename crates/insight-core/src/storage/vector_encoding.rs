@@ -0,0 +1,280 @@
+//! Compact on-disk encodings for embedding vectors.
+//!
+//! `EmbeddingData` is synced to every peer, and JSON float arrays cost
+//! 10–12 bytes per dimension. Vectors can instead be stored as base64 of
+//! little-endian `f16`s (2 bytes per dimension) or of `i8`s with a
+//! per-vector scale (1 byte per dimension plus 4). In memory they are
+//! always `Vec<f32>`; conversion happens only when (de)serializing.
+//!
+//! Data written before this existed has no `vector_encoding` field and
+//! plain float arrays, and still reads as `f32`.
+
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use half::f16;
+use serde::{Deserialize, Serialize};
+
+use crate::projection::ProjectionSpec;
+use crate::provider::ChunkingConfig;
+
+use super::{EmbeddingChunk, EmbeddingData};
+
+/// How an `EmbeddingData`'s vectors are written to storage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorEncoding {
+    /// JSON float arrays. Lossless; the format of legacy data.
+    #[default]
+    F32,
+    /// Half precision. Cosine similarity changes by well under 0.001.
+    F16,
+    /// Symmetric 8-bit quantization with one `f32` scale per vector.
+    Int8,
+}
+
+impl VectorEncoding {
+    fn encode(self, vector: &[f32]) -> StoredVector {
+        match self {
+            Self::F32 => StoredVector::Floats(vector.to_vec()),
+            Self::F16 => {
+                let bytes: Vec<u8> = vector
+                    .iter()
+                    .flat_map(|v| f16::from_f32(*v).to_le_bytes())
+                    .collect();
+                StoredVector::Packed(BASE64.encode(bytes))
+            }
+            Self::Int8 => {
+                let max = vector.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+                let scale = max / i8::MAX as f32;
+                let mut bytes = Vec::with_capacity(4 + vector.len());
+                bytes.extend_from_slice(&scale.to_le_bytes());
+                bytes.extend(vector.iter().map(|v| {
+                    let q = if scale > 0.0 {
+                        (v / scale).round()
+                    } else {
+                        0.0
+                    };
+                    q.clamp(-(i8::MAX as f32), i8::MAX as f32) as i8 as u8
+                }));
+                StoredVector::Packed(BASE64.encode(bytes))
+            }
+        }
+    }
+
+    fn decode(self, stored: StoredVector) -> Result<Vec<f32>> {
+        let packed = match stored {
+            StoredVector::Floats(vector) => return Ok(vector),
+            StoredVector::Packed(packed) => packed,
+        };
+        let bytes = BASE64.decode(packed.as_bytes())?;
+
+        match self {
+            Self::F32 => anyhow::bail!("Packed vector in f32-encoded embeddings"),
+            Self::F16 => {
+                if bytes.len() % 2 != 0 {
+                    anyhow::bail!("f16 vector has odd byte length {}", bytes.len());
+                }
+                Ok(bytes
+                    .chunks_exact(2)
+                    .map(|b| f16::from_le_bytes([b[0], b[1]]).to_f32())
+                    .collect())
+            }
+            Self::Int8 => {
+                if bytes.len() < 4 {
+                    anyhow::bail!("int8 vector is missing its scale");
+                }
+                let scale = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                Ok(bytes[4..].iter().map(|&q| q as i8 as f32 * scale).collect())
+            }
+        }
+    }
+}
+
+/// A vector as written: a float array, or base64 of the packed bytes.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredVector {
+    Floats(Vec<f32>),
+    Packed(String),
+}
+
+/// Serialized form of [`EmbeddingData`].
+#[derive(Serialize, Deserialize)]
+pub(super) struct StoredEmbeddingData {
+    model_id: String,
+    dimensions: usize,
+    chunks: Vec<StoredChunk>,
+    created_at: String,
+    #[serde(default)]
+    chunking: Option<ChunkingConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    projection: Option<ProjectionSpec>,
+    #[serde(default)]
+    vector_encoding: VectorEncoding,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredChunk {
+    index: usize,
+    content: String,
+    vector: StoredVector,
+    start_page: usize,
+    end_page: usize,
+}
+
+impl From<EmbeddingData> for StoredEmbeddingData {
+    fn from(data: EmbeddingData) -> Self {
+        let encoding = data.vector_encoding;
+        Self {
+            model_id: data.model_id,
+            dimensions: data.dimensions,
+            chunks: data
+                .chunks
+                .into_iter()
+                .map(|chunk| StoredChunk {
+                    index: chunk.index,
+                    content: chunk.content,
+                    vector: encoding.encode(&chunk.vector),
+                    start_page: chunk.start_page,
+                    end_page: chunk.end_page,
+                })
+                .collect(),
+            created_at: data.created_at,
+            chunking: data.chunking,
+            projection: data.projection,
+            vector_encoding: encoding,
+        }
+    }
+}
+
+impl TryFrom<StoredEmbeddingData> for EmbeddingData {
+    type Error = anyhow::Error;
+
+    fn try_from(stored: StoredEmbeddingData) -> Result<Self> {
+        let encoding = stored.vector_encoding;
+        let chunks = stored
+            .chunks
+            .into_iter()
+            .map(|chunk| {
+                Ok(EmbeddingChunk {
+                    index: chunk.index,
+                    content: chunk.content,
+                    vector: encoding.decode(chunk.vector)?,
+                    start_page: chunk.start_page,
+                    end_page: chunk.end_page,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            model_id: stored.model_id,
+            dimensions: stored.dimensions,
+            chunks,
+            created_at: stored.created_at,
+            chunking: stored.chunking,
+            projection: stored.projection,
+            vector_encoding: encoding,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_vector(dims: usize) -> Vec<f32> {
+        let raw: Vec<f32> = (0..dims)
+            .map(|i| ((i * 37 % 101) as f32 - 50.0) / 17.0)
+            .collect();
+        let norm = raw.iter().map(|x| x * x).sum::<f32>().sqrt();
+        raw.into_iter().map(|x| x / norm).collect()
+    }
+
+    fn sample_data(encoding: VectorEncoding) -> EmbeddingData {
+        EmbeddingData {
+            model_id: "model-a".to_string(),
+            dimensions: 1024,
+            chunks: (0..8)
+                .map(|i| EmbeddingChunk {
+                    index: i,
+                    content: format!("chunk {}", i),
+                    vector: sample_vector(1024),
+                    start_page: 1,
+                    end_page: 1,
+                })
+                .collect(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            chunking: None,
+            projection: None,
+            vector_encoding: encoding,
+        }
+    }
+
+    fn max_error(a: &[f32], b: &[f32]) -> f32 {
+        a.iter()
+            .zip(b)
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_roundtrip_each_encoding() {
+        let original = sample_vector(1024);
+        for (encoding, tolerance) in [
+            (VectorEncoding::F32, 0.0),
+            (VectorEncoding::F16, 1e-3),
+            (VectorEncoding::Int8, 1e-2),
+        ] {
+            let json = serde_json::to_vec(&sample_data(encoding)).unwrap();
+            let decoded: EmbeddingData = serde_json::from_slice(&json).unwrap();
+            assert_eq!(decoded.vector_encoding, encoding);
+            let vector = &decoded.chunks[0].vector;
+            assert_eq!(vector.len(), 1024);
+            assert!(max_error(&original, vector) <= tolerance, "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn test_compact_encodings_shrink_sync_size() {
+        let f32_len = serde_json::to_vec(&sample_data(VectorEncoding::F32))
+            .unwrap()
+            .len();
+        let f16_len = serde_json::to_vec(&sample_data(VectorEncoding::F16))
+            .unwrap()
+            .len();
+        let int8_len = serde_json::to_vec(&sample_data(VectorEncoding::Int8))
+            .unwrap()
+            .len();
+
+        // 1024 dims × 8 chunks: ~100 KB as JSON floats, ~22 KB as f16,
+        // ~11 KB as int8.
+        assert!(f16_len * 3 < f32_len, "f16 {} vs f32 {}", f16_len, f32_len);
+        assert!(
+            int8_len * 6 < f32_len,
+            "int8 {} vs f32 {}",
+            int8_len,
+            f32_len
+        );
+    }
+
+    #[test]
+    fn test_reads_legacy_float_arrays() {
+        let json = r#"{
+            "model_id": "model-a",
+            "dimensions": 2,
+            "chunks": [{"index": 0, "content": "a", "vector": [0.5, -0.25], "start_page": 1, "end_page": 1}],
+            "created_at": "2024-01-01T00:00:00Z"
+        }"#;
+        let data: EmbeddingData = serde_json::from_str(json).unwrap();
+        assert_eq!(data.vector_encoding, VectorEncoding::F32);
+        assert_eq!(data.chunks[0].vector, vec![0.5, -0.25]);
+    }
+
+    #[test]
+    fn test_int8_zero_vector() {
+        let stored = VectorEncoding::Int8.encode(&[0.0, 0.0, 0.0]);
+        let decoded = VectorEncoding::Int8.decode(stored).unwrap();
+        assert_eq!(decoded, vec![0.0, 0.0, 0.0]);
+    }
+}
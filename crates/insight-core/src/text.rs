@@ -0,0 +1,124 @@
+//! Splitting text into words, for the tools that compare passages without
+//! the search index: entity and date finding and claim verification.
+
+use std::ops::Range;
+
+/// Words too common to say what a question or claim is about.
+pub const STOP_WORDS: &[&str] = &[
+    "the",
+    "and",
+    "for",
+    "are",
+    "was",
+    "were",
+    "what",
+    "which",
+    "who",
+    "whom",
+    "when",
+    "where",
+    "why",
+    "how",
+    "does",
+    "did",
+    "this",
+    "that",
+    "with",
+    "from",
+    "into",
+    "each",
+    "every",
+    "any",
+    "also",
+    "not",
+    "but",
+    "been",
+    "has",
+    "have",
+    "had",
+    "its",
+    "their",
+    "there",
+    "page",
+    "document",
+    "documents",
+    "according",
+    "states",
+    "says",
+    "said",
+    "shows",
+];
+
+/// Byte ranges of alphanumeric runs.
+pub fn tokenize(text: &str) -> Vec<Range<usize>> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                tokens.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(s..text.len());
+    }
+    tokens
+}
+
+/// Lowercased words; digits separated only by `,` or `.` stay together,
+/// so "1,200" and "3.5" are one word each.
+pub fn words(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut split = Vec::new();
+    let mut current = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        let joins_number = matches!(c, ',' | '.')
+            && i > 0
+            && chars[i - 1].is_ascii_digit()
+            && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit());
+        if c.is_alphanumeric() || joins_number {
+            current.extend(c.to_lowercase());
+        } else if !current.is_empty() {
+            split.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        split.push(current);
+    }
+    split
+}
+
+/// Whether a word from [`words`] says something: longer than two letters
+/// and not a stop word.
+pub fn is_term(word: &str) -> bool {
+    word.len() > 2 && !STOP_WORDS.contains(&word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_keep_numbers_together() {
+        assert_eq!(
+            words("The fee, 1,200 USD, rose 3.5%."),
+            vec!["the", "fee", "1,200", "usd", "rose", "3.5"]
+        );
+        let terms: Vec<String> = words("What does the lease say?")
+            .into_iter()
+            .filter(|w| is_term(w))
+            .collect();
+        assert_eq!(terms, vec!["lease", "say"]);
+    }
+
+    #[test]
+    fn test_tokenize_byte_ranges() {
+        let text = "Zoë met Bo.";
+        let tokens: Vec<&str> = tokenize(text).into_iter().map(|r| &text[r]).collect();
+        assert_eq!(tokens, vec!["Zoë", "met", "Bo"]);
+    }
+}
@@ -1,7 +1,18 @@
+use std::path::PathBuf;
+
+use iroh_docs::NamespaceId;
 use tauri::State;
 
 use super::CollectionId;
-use crate::core::{AppState, CollectionInfo};
+use crate::core::chat;
+use crate::core::memory::{self, MemoryEntry};
+use crate::core::projection::ProjectionSpec;
+use crate::core::saved_searches::{self, SavedSearch, SearchAlert};
+use crate::core::{
+    AppState, ChatMessage, ChunkingConfig, CollectionInfo, CollectionMember, CollectionTransfer,
+    NetworkDiagnostics, PeerAccess, PeerPresence, PeerSyncHealth, PersonalSyncStatus, Settings,
+    SyncPolicy, WatchFolder,
+};
 use crate::error::{CommandError, CommandResult, ResultExt};
 
 /// Get all collections
@@ -48,6 +59,7 @@ pub async fn create_collection(
 
     // Start watching the new collection for sync events (documents from peers)
     state.watch_namespace(namespace_id).await;
+    state.register_author(&[namespace_id]).await;
 
     Ok(CollectionInfo {
         id: namespace_id.to_string(),
@@ -76,6 +88,40 @@ pub async fn delete_collection(
             .await
             .storage_err()?;
     }
+    state.leave_gossip(&namespace_id);
+
+    if let Err(e) = memory::delete_memories(&state.config.memory_dir(), &collection_id) {
+        tracing::warn!(
+            "Failed to delete memories for collection {}: {}",
+            collection_id,
+            e
+        );
+    }
+    if let Err(e) =
+        saved_searches::delete_saved_searches(&state.config.saved_searches_dir(), &collection_id)
+    {
+        tracing::warn!(
+            "Failed to delete saved searches for collection {}: {}",
+            collection_id,
+            e
+        );
+    }
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    let watched = settings.watch_folders.len();
+    settings
+        .watch_folders
+        .retain(|f| f.collection_id != collection_id);
+    if settings.watch_folders.len() != watched {
+        if let Err(e) = settings.save(&state.config.settings_file) {
+            tracing::warn!(
+                "Failed to remove watch folders for collection {}: {}",
+                collection_id,
+                e
+            );
+        }
+        state.watch_folders(settings.watch_folders);
+    }
 
     // Delete all chunks from search index in background
     let index_worker = state.index_worker.clone();
@@ -123,30 +169,48 @@ pub async fn share_collection(
 
     let storage = state.storage.read().await;
 
-    storage
+    let ticket = storage
         .share_collection(namespace_id, writable)
         .await
-        .storage_err()
+        .storage_err()?;
+
+    // Sharing starts syncing; a paused collection stays paused.
+    let settings = Settings::load(&state.config.settings_file);
+    if settings.paused_sync.contains(&namespace_id.to_string()) {
+        storage
+            .set_sync_enabled(namespace_id, false)
+            .await
+            .storage_err()?;
+    }
+    Ok(ticket)
 }
 
 /// Import a collection from a share ticket
 ///
 /// The ticket string is obtained from someone who called `share_collection`.
-/// After import, the collection will sync with the original peer.
+/// After import, the collection will sync with the original peer,
+/// downloading what `sync_policy` allows (everything if not given).
 #[tauri::command]
 pub async fn import_collection(
     ticket: String,
+    sync_policy: Option<SyncPolicy>,
     state: State<'_, AppState>,
 ) -> CommandResult<CollectionInfo> {
     tracing::info!("Importing collection from ticket");
 
+    let sync_policy = sync_policy.unwrap_or_default();
     let namespace_id = {
         let storage = state.storage.read().await;
-        storage.import_collection(&ticket).await.storage_err()?
+        storage
+            .import_collection(&ticket, sync_policy)
+            .await
+            .storage_err()?
     };
+    save_sync_policy(&state, namespace_id, sync_policy).await?;
 
     // Start watching the imported collection for sync events
     state.watch_namespace(namespace_id).await;
+    state.register_author(&[namespace_id]).await;
 
     // Fetch collection info
     let storage = state.storage.read().await;
@@ -172,3 +236,534 @@ pub async fn import_collection(
         created_at: Some(metadata.created_at),
     })
 }
+
+/// Everyone who writes to or replicates a collection, this node first.
+#[tauri::command]
+pub async fn get_collection_members(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<CollectionMember>> {
+    let storage = state.storage.read().await;
+    storage
+        .collection_members(collection_id.namespace())
+        .await
+        .storage_err()
+}
+
+/// How syncing a collection with each peer is going: when they were last
+/// seen, when a sync last succeeded or failed, and what is still
+/// downloading from them. Covers this run only.
+#[tauri::command]
+pub async fn get_sync_health(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<PeerSyncHealth>> {
+    Ok(state.pipeline.sync_health(&collection_id.namespace()))
+}
+
+/// Data a collection exchanged with peers since the app started.
+#[tauri::command]
+pub async fn get_transfer_stats(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<CollectionTransfer> {
+    Ok(state.pipeline.transfer_stats(&collection_id.namespace()))
+}
+
+/// Relay reachability, NAT type and the path to each peer, for working out
+/// why sync doesn't complete.
+#[tauri::command]
+pub async fn get_network_diagnostics(
+    state: State<'_, AppState>,
+) -> CommandResult<NetworkDiagnostics> {
+    let storage = state.storage.read().await;
+    storage.network_diagnostics().await.storage_err()
+}
+
+/// Who is online in a collection, as announced over gossip, online first.
+#[tauri::command]
+pub async fn get_presence(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<PeerPresence>> {
+    Ok(state.presence.collection(&collection_id.namespace()))
+}
+
+/// The latest chat messages stored in a collection, oldest first.
+#[tauri::command]
+pub async fn get_chat_history(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<ChatMessage>> {
+    let storage = state.storage.read().await;
+    storage
+        .chat_history(collection_id.namespace(), chat::HISTORY_LIMIT)
+        .await
+        .storage_err()
+}
+
+/// Send a chat message to the members of a collection.
+#[tauri::command]
+pub async fn send_chat_message(
+    collection_id: CollectionId,
+    text: String,
+    state: State<'_, AppState>,
+) -> CommandResult<ChatMessage> {
+    let length = text.trim().chars().count();
+    if length == 0 {
+        return Err(CommandError::invalid_input("Message is empty"));
+    }
+    if length > chat::MAX_MESSAGE_CHARS {
+        return Err(CommandError::invalid_input(format!(
+            "Message is longer than {} characters",
+            chat::MAX_MESSAGE_CHARS
+        )));
+    }
+    state
+        .send_chat_message(collection_id.namespace(), &text)
+        .await
+        .external_err()
+}
+
+/// What is downloaded of a collection as it syncs.
+#[tauri::command]
+pub async fn get_sync_policy(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<SyncPolicy> {
+    Ok(state.pipeline.sync_policy(&collection_id.namespace()).await)
+}
+
+/// Change what is downloaded of a collection. Content the new policy
+/// allows starts downloading; content it no longer allows is kept.
+#[tauri::command]
+pub async fn set_sync_policy(
+    collection_id: CollectionId,
+    policy: SyncPolicy,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    save_sync_policy(&state, collection_id.namespace(), policy).await
+}
+
+async fn save_sync_policy(
+    state: &AppState,
+    namespace_id: NamespaceId,
+    policy: SyncPolicy,
+) -> CommandResult<()> {
+    let mut settings = Settings::load(&state.config.settings_file);
+    if policy == SyncPolicy::default() {
+        settings.sync_policies.remove(&namespace_id.to_string());
+    } else {
+        settings
+            .sync_policies
+            .insert(namespace_id.to_string(), policy);
+    }
+    settings.save(&state.config.settings_file).storage_err()?;
+
+    state.pipeline.set_sync_policy(namespace_id, policy).await;
+    Ok(())
+}
+
+/// Whether a collection is syncing with peers.
+#[tauri::command]
+pub async fn get_sync_enabled(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<bool> {
+    let settings = Settings::load(&state.config.settings_file);
+    Ok(!settings
+        .paused_sync
+        .contains(&collection_id.namespace().to_string()))
+}
+
+/// Pause or resume syncing a collection with peers. Paused, it keeps its
+/// documents and peers, and can still be searched and added to; changes
+/// are exchanged once it is resumed.
+#[tauri::command]
+pub async fn set_sync_enabled(
+    collection_id: CollectionId,
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let namespace_id = collection_id.namespace();
+    {
+        let storage = state.storage.read().await;
+        storage
+            .set_sync_enabled(namespace_id, enabled)
+            .await
+            .storage_err()?;
+    }
+
+    let id = namespace_id.to_string();
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.paused_sync.retain(|paused| *paused != id);
+    if !enabled {
+        settings.paused_sync.push(id);
+    }
+    settings.save(&state.config.settings_file).storage_err()?;
+    if enabled {
+        state.join_gossip(&[namespace_id]).await;
+    } else {
+        state.leave_gossip(&namespace_id);
+    }
+    tracing::info!(
+        "{} sync for collection {}",
+        if enabled { "Resumed" } else { "Paused" },
+        namespace_id
+    );
+    Ok(())
+}
+
+/// The name shown to the people collections are shared with.
+#[tauri::command]
+pub async fn get_display_name(state: State<'_, AppState>) -> CommandResult<Option<String>> {
+    Ok(Settings::load(&state.config.settings_file).display_name)
+}
+
+/// Set or clear the display name and update it in every collection.
+#[tauri::command]
+pub async fn set_display_name(
+    name: Option<String>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let name = name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.display_name = name.clone();
+    settings.save(&state.config.settings_file).storage_err()?;
+    state.presence.set_name(name);
+
+    state.register_author(&state.collection_ids().await).await;
+    Ok(())
+}
+
+/// Nodes allowed or refused to connect to this one.
+#[tauri::command]
+pub async fn get_peer_access(state: State<'_, AppState>) -> CommandResult<PeerAccess> {
+    Ok(Settings::load(&state.config.settings_file).peer_access)
+}
+
+/// Set which nodes may connect to this one. Takes effect for new
+/// connections right away.
+#[tauri::command]
+pub async fn set_peer_access(access: PeerAccess, state: State<'_, AppState>) -> CommandResult<()> {
+    let normalize = |ids: Vec<String>| {
+        let mut ids: Vec<String> = ids
+            .iter()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    };
+    let access = PeerAccess {
+        allowed: normalize(access.allowed),
+        blocked: normalize(access.blocked),
+    };
+    state
+        .storage
+        .read()
+        .await
+        .set_peer_access(&access)
+        .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.peer_access = access;
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(())
+}
+
+/// Whether this device syncs settings, saved searches and conversations
+/// with the user's other devices.
+#[tauri::command]
+pub async fn get_personal_sync(state: State<'_, AppState>) -> CommandResult<PersonalSyncStatus> {
+    Ok(state.personal.status())
+}
+
+/// Start syncing with the user's other devices. Returns the ticket to join
+/// from them; it grants write access, so it shouldn't go to anyone else.
+#[tauri::command]
+pub async fn enable_personal_sync(state: State<'_, AppState>) -> CommandResult<String> {
+    state.personal.enable().await.storage_err()
+}
+
+/// Sync with another of the user's devices, from the ticket it showed.
+#[tauri::command]
+pub async fn join_personal_sync(ticket: String, state: State<'_, AppState>) -> CommandResult<()> {
+    state
+        .personal
+        .join(ticket.trim())
+        .await
+        .map_err(|e| CommandError::invalid_input(e.to_string()))
+}
+
+/// Stop syncing with the user's other devices. Local files are kept.
+#[tauri::command]
+pub async fn disable_personal_sync(state: State<'_, AppState>) -> CommandResult<()> {
+    state.personal.disable().await.storage_err()
+}
+
+/// Set or clear a collection's chunking override.
+///
+/// `None` reverts the collection to the global defaults. The override
+/// syncs to peers with the collection. Existing embeddings are kept until
+/// the collection is re-embedded.
+#[tauri::command]
+pub async fn set_collection_chunking(
+    collection_id: CollectionId,
+    chunking: Option<ChunkingConfig>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    if let Some(ref config) = chunking {
+        config
+            .validate()
+            .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+    }
+
+    let storage = state.storage.read().await;
+    storage
+        .set_collection_chunking(collection_id.namespace(), chunking)
+        .await
+        .storage_err()?;
+
+    Ok(())
+}
+
+/// Set or clear a collection's vector projection.
+///
+/// `target_dimensions` reduces the active model's vectors to that size
+/// before storage and indexing; `None` goes back to native vectors. The
+/// collection is re-embedded either way. Returns the number of documents
+/// queued.
+#[tauri::command]
+pub async fn set_collection_projection(
+    collection_id: CollectionId,
+    target_dimensions: Option<usize>,
+    state: State<'_, AppState>,
+) -> CommandResult<usize> {
+    state
+        .pipeline
+        .set_collection_projection(collection_id.namespace(), target_dimensions)
+        .await
+        .map_err(|e| CommandError::invalid_input(e.to_string()))
+}
+
+/// Get a collection's projection, if any.
+#[tauri::command]
+pub async fn get_collection_projection(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Option<ProjectionSpec>> {
+    let storage = state.storage.read().await;
+    let metadata = storage
+        .get_collection_metadata(collection_id.namespace())
+        .await
+        .storage_err()?
+        .ok_or(CommandError::collection_not_found())?;
+    Ok(metadata.projection)
+}
+
+/// Get a collection's chunking override, if any.
+#[tauri::command]
+pub async fn get_collection_chunking(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Option<ChunkingConfig>> {
+    let storage = state.storage.read().await;
+    let metadata = storage
+        .get_collection_metadata(collection_id.namespace())
+        .await
+        .storage_err()?
+        .ok_or(CommandError::collection_not_found())?;
+    Ok(metadata.chunking)
+}
+
+/// Facts the agent remembers about a collection, oldest first.
+#[tauri::command]
+pub async fn get_collection_memories(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<MemoryEntry>> {
+    memory::load_memories(
+        &state.config.memory_dir(),
+        &collection_id.namespace().to_string(),
+    )
+    .storage_err()
+}
+
+/// Add a fact for the agent to remember about a collection.
+#[tauri::command]
+pub async fn add_collection_memory(
+    collection_id: CollectionId,
+    text: String,
+    state: State<'_, AppState>,
+) -> CommandResult<MemoryEntry> {
+    if text.trim().is_empty() {
+        return Err(CommandError::invalid_input("Nothing to remember"));
+    }
+    memory::add_memory(
+        &state.config.memory_dir(),
+        &collection_id.namespace().to_string(),
+        &text,
+    )
+    .storage_err()
+}
+
+/// Forget one remembered fact.
+#[tauri::command]
+pub async fn delete_collection_memory(
+    collection_id: CollectionId,
+    memory_id: String,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    memory::delete_memory(
+        &state.config.memory_dir(),
+        &collection_id.namespace().to_string(),
+        &memory_id,
+    )
+    .storage_err()?;
+    Ok(())
+}
+
+/// Searches saved to a collection, oldest first.
+#[tauri::command]
+pub async fn get_saved_searches(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<SavedSearch>> {
+    saved_searches::load_saved_searches(
+        &state.config.saved_searches_dir(),
+        &collection_id.namespace().to_string(),
+    )
+    .storage_err()
+}
+
+/// Turn new-match alerts for a saved search on or off.
+#[tauri::command]
+pub async fn set_saved_search_notify(
+    collection_id: CollectionId,
+    search_id: String,
+    notify: bool,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let found = saved_searches::set_notify(
+        &state.config.saved_searches_dir(),
+        &collection_id.namespace().to_string(),
+        &search_id,
+        notify,
+    )
+    .storage_err()?;
+    if !found {
+        return Err(CommandError::invalid_input("Saved search not found"));
+    }
+    Ok(())
+}
+
+/// Delete a saved search.
+#[tauri::command]
+pub async fn delete_saved_search(
+    collection_id: CollectionId,
+    search_id: String,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    saved_searches::delete_saved_search(
+        &state.config.saved_searches_dir(),
+        &collection_id.namespace().to_string(),
+        &search_id,
+    )
+    .storage_err()?;
+    Ok(())
+}
+
+/// Re-run the collection's alerting searches and return documents that
+/// match for the first time. Each document is reported once.
+#[tauri::command]
+pub async fn check_saved_search_alerts(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<SearchAlert>> {
+    let namespace_id = collection_id.namespace();
+    let metadata = {
+        let storage = state.storage.read().await;
+        storage
+            .get_collection_metadata(namespace_id)
+            .await
+            .storage_err()?
+            .ok_or(CommandError::collection_not_found())?
+    };
+    let collection = CollectionInfo {
+        id: namespace_id.to_string(),
+        name: metadata.name,
+        document_count: 0,
+        total_pages: 0,
+        created_at: None,
+    };
+    saved_searches::check_alerts(&state, collection)
+        .await
+        .storage_err()
+}
+
+/// Folders watched for new PDFs to import into the collection.
+#[tauri::command]
+pub async fn get_watch_folders(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<PathBuf>> {
+    let collection_id = collection_id.namespace().to_string();
+    Ok(Settings::load(&state.config.settings_file)
+        .watch_folders
+        .into_iter()
+        .filter(|f| f.collection_id == collection_id)
+        .map(|f| f.path)
+        .collect())
+}
+
+/// Watch a folder, importing PDFs already in it and any added later. Each
+/// file is tagged with the name of the folder it is in.
+#[tauri::command]
+pub async fn add_watch_folder(
+    collection_id: CollectionId,
+    path: PathBuf,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    if !path.is_dir() {
+        return Err(CommandError::invalid_input(format!(
+            "Not a folder: {}",
+            path.display()
+        )));
+    }
+    let folder = WatchFolder {
+        collection_id: collection_id.namespace().to_string(),
+        path,
+    };
+    let mut settings = Settings::load(&state.config.settings_file);
+    if settings.watch_folders.contains(&folder) {
+        return Ok(());
+    }
+    tracing::info!(
+        "Watching {} for collection {}",
+        folder.path.display(),
+        folder.collection_id
+    );
+    settings.watch_folders.push(folder);
+    settings.save(&state.config.settings_file).storage_err()?;
+    state.watch_folders(settings.watch_folders);
+    Ok(())
+}
+
+/// Stop watching a folder. Documents already imported from it are kept.
+#[tauri::command]
+pub async fn remove_watch_folder(
+    collection_id: CollectionId,
+    path: PathBuf,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let collection_id = collection_id.namespace().to_string();
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings
+        .watch_folders
+        .retain(|f| !(f.collection_id == collection_id && f.path == path));
+    settings.save(&state.config.settings_file).storage_err()?;
+    state.watch_folders(settings.watch_folders);
+    Ok(())
+}
@@ -1,8 +1,12 @@
 use iroh_docs::NamespaceId;
-use tauri::{AppHandle, Emitter, State};
+use tauri::{AppHandle, State};
 use tokio_util::sync::CancellationToken;
 
-use crate::core::{agent, conversations, AppState, ProviderEvent};
+use crate::core::prompts::{self, PromptLibrary};
+use crate::core::{
+    agent, conversations, AgentLimits, AppState, CoreEvent, PromptPreset, ProviderEvent,
+    SamplingParams, Settings, ToolApproval,
+};
 use crate::error::{CommandError, CommandResult, ResultExt};
 
 /// List all saved conversations
@@ -32,6 +36,23 @@ pub async fn load_conversation(
     Ok(conversation)
 }
 
+/// Token usage and estimated cost of one conversation, or of every stored
+/// conversation when `conversation_id` is omitted.
+#[tauri::command]
+pub async fn get_token_usage(
+    conversation_id: Option<String>,
+    state: State<'_, AppState>,
+) -> CommandResult<agent::ConversationUsage> {
+    let Some(conversation_id) = conversation_id else {
+        return conversations::total_usage(&state.config.conversations_dir).storage_err();
+    };
+    if let Some(conversation) = state.conversations.read().await.get(&conversation_id) {
+        return Ok(conversation.usage);
+    }
+    let path = conversations::conversation_path(&state.config.conversations_dir, &conversation_id);
+    Ok(conversations::load_conversation(&path).storage_err()?.usage)
+}
+
 /// Enrich bare [`CollectionInfo`] values (which the frontend only knows a
 /// subset of) with storage-derived `total_pages` counts. Collections whose IDs
 /// don't parse as valid namespaces are passed through untouched so the call
@@ -67,9 +88,14 @@ async fn enrich_collections(
 /// Requires a chat provider to be configured first. If `collections` is
 /// provided and non-empty, the conversation's initial scope is recorded via
 /// `Conversation::set_collections` so a breadcrumb is added to the transcript.
+/// `preset_id` picks a prompt preset from settings to layer on top of the
+/// base system prompt. `pinned_document_ids` are documents to discuss; see
+/// [`agent::AgentContext::pinned_document_ids`].
 #[tauri::command]
 pub async fn start_chat(
     collections: Option<Vec<agent::CollectionInfo>>,
+    preset_id: Option<String>,
+    pinned_document_ids: Option<Vec<String>>,
     state: State<'_, AppState>,
 ) -> CommandResult<agent::Conversation> {
     if !state.models.chat_ready().await {
@@ -77,7 +103,16 @@ pub async fn start_chat(
     }
 
     let conversation_id = uuid::Uuid::new_v4().to_string();
-    let mut conversation = agent::Conversation::new(conversation_id.clone());
+    let mut conversation = match preset_id {
+        Some(preset_id) => {
+            let settings = Settings::load(&state.config.settings_file);
+            let preset = settings
+                .prompt_preset(&preset_id)
+                .ok_or_else(|| CommandError::invalid_input("Unknown prompt preset"))?;
+            agent::Conversation::with_preset(conversation_id.clone(), preset)
+        }
+        None => agent::Conversation::new(conversation_id.clone()),
+    };
 
     if let Some(cols) = collections {
         if !cols.is_empty() {
@@ -85,6 +120,7 @@ pub async fn start_chat(
             conversation.set_collections(enriched);
         }
     }
+    conversation.pinned_document_ids = pinned_document_ids.unwrap_or_default();
 
     conversations::save_conversation(&state.config.conversations_dir, &conversation)
         .storage_err()?;
@@ -150,6 +186,27 @@ pub async fn set_conversation_collections(
     Ok(conversation)
 }
 
+/// Set a conversation's own sampling, e.g. temperature 0 for extracting
+/// figures. Unset fields follow the provider default.
+#[tauri::command]
+pub async fn set_conversation_sampling(
+    conversation_id: String,
+    sampling: SamplingParams,
+    state: State<'_, AppState>,
+) -> CommandResult<agent::Conversation> {
+    sampling
+        .validate()
+        .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+    let mut conversations_map = state.conversations.write().await;
+    let conversation = conversations_map
+        .get_mut(&conversation_id)
+        .ok_or(CommandError::conversation_not_found())?;
+    conversation.sampling = sampling;
+    conversations::save_conversation(&state.config.conversations_dir, conversation)
+        .storage_err()?;
+    Ok(conversation.clone())
+}
+
 /// Delete a conversation. Cancels any in-flight generation or prediction,
 /// drops the in-memory entry, and removes the JSON file from disk.
 #[tauri::command]
@@ -188,12 +245,13 @@ pub async fn delete_conversation(
 ///
 /// The search tools are scoped to the conversation's stored collection set;
 /// callers change that scope via [`set_conversation_collections`] ahead of
-/// time, not here.
+/// time, not here. `pinned_document_ids`, when given, replaces the
+/// conversation's pinned documents from this message on.
 #[tauri::command]
 pub async fn send_message(
     conversation_id: String,
     message: String,
-    app: AppHandle,
+    pinned_document_ids: Option<Vec<String>>,
     state: State<'_, AppState>,
 ) -> CommandResult<()> {
     tracing::info!(
@@ -202,7 +260,61 @@ pub async fn send_message(
         message
     );
 
-    let conversation = state
+    let mut conversation = state
+        .conversations
+        .read()
+        .await
+        .get(&conversation_id)
+        .ok_or(CommandError::conversation_not_found())?
+        .clone();
+    if let Some(ids) = pinned_document_ids {
+        conversation.pinned_document_ids = ids;
+    }
+
+    spawn_agent_run(&state, conversation, message).await
+}
+
+/// Discard the last response and generate it again from the same user
+/// message. Streams events like [`send_message`].
+#[tauri::command]
+pub async fn regenerate_response(
+    conversation_id: String,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let mut conversation = state
+        .conversations
+        .read()
+        .await
+        .get(&conversation_id)
+        .ok_or(CommandError::conversation_not_found())?
+        .clone();
+
+    let message = conversation
+        .pop_last_turn()
+        .ok_or_else(|| CommandError::invalid_input("There is no response to regenerate"))?;
+    tracing::info!(
+        "Regenerating last response in conversation {}",
+        conversation_id
+    );
+
+    spawn_agent_run(&state, conversation, message).await
+}
+
+/// Replace the text of user turn `turn` (0-based), drop everything after
+/// it, and run the agent again from there. Streams events like
+/// [`send_message`].
+#[tauri::command]
+pub async fn edit_message(
+    conversation_id: String,
+    turn: usize,
+    message: String,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    if message.trim().is_empty() {
+        return Err(CommandError::invalid_input("Message cannot be empty"));
+    }
+
+    let mut conversation = state
         .conversations
         .read()
         .await
@@ -210,6 +322,64 @@ pub async fn send_message(
         .ok_or(CommandError::conversation_not_found())?
         .clone();
 
+    conversation
+        .truncate_at_turn(turn)
+        .ok_or_else(|| CommandError::invalid_input("The conversation has no such turn"))?;
+    tracing::info!(
+        "Editing turn {} of conversation {} and resending",
+        turn,
+        conversation_id
+    );
+
+    spawn_agent_run(&state, conversation, message).await
+}
+
+/// Start a new conversation that shares `conversation_id`'s transcript
+/// through user turn `turn` (0-based), so the user can take the research in
+/// another direction without losing the original.
+#[tauri::command]
+pub async fn branch_conversation(
+    conversation_id: String,
+    turn: usize,
+    state: State<'_, AppState>,
+) -> CommandResult<agent::Conversation> {
+    let source = state
+        .conversations
+        .read()
+        .await
+        .get(&conversation_id)
+        .cloned()
+        .ok_or(CommandError::conversation_not_found())?;
+
+    let branch_id = uuid::Uuid::new_v4().to_string();
+    let branch = source
+        .branch(branch_id.clone(), turn)
+        .ok_or_else(|| CommandError::invalid_input("The conversation has no such turn"))?;
+
+    conversations::save_conversation(&state.config.conversations_dir, &branch).storage_err()?;
+    state
+        .conversations
+        .write()
+        .await
+        .insert(branch_id.clone(), branch.clone());
+
+    tracing::info!(
+        "Branched conversation {} from {} at turn {}",
+        branch_id,
+        conversation_id,
+        turn
+    );
+    Ok(branch)
+}
+
+/// Run the agent loop for `message` on `conversation` in the background,
+/// publishing its events to the event bus and saving the conversation when
+/// it finishes.
+async fn spawn_agent_run(
+    state: &AppState,
+    conversation: agent::Conversation,
+    message: String,
+) -> CommandResult<()> {
     if !state.models.chat_ready().await {
         return Err(CommandError::provider_not_configured());
     }
@@ -218,6 +388,7 @@ pub async fn send_message(
         return Err(CommandError::no_collection_scope());
     }
 
+    let conversation_id = conversation.id.clone();
     let cancel_token = CancellationToken::new();
     state
         .active_generations
@@ -225,32 +396,34 @@ pub async fn send_message(
         .await
         .insert(conversation_id.clone(), cancel_token.clone());
 
-    let (tx, mut rx) = tokio::sync::mpsc::channel::<agent::AgentEvent>(100);
+    let (tx, rx) = tokio::sync::mpsc::channel::<agent::AgentEvent>(100);
 
-    let app_handle = app.clone();
     let conv_id = conversation_id.clone();
-    tokio::spawn(async move {
-        while let Some(event) = rx.recv().await {
-            let event_name = format!("agent-event-{}", conv_id);
-            if let Err(e) = app_handle.emit(&event_name, &event) {
-                tracing::error!("Failed to emit agent event: {}", e);
-            }
-        }
-    });
+    state
+        .events
+        .forward_mpsc(rx, move |event| CoreEvent::Agent {
+            conversation_id: conv_id.clone(),
+            event,
+        });
 
     let conversations_dir = state.config.conversations_dir.clone();
-    let state_clone = state.inner().clone();
+    let state_clone = state.clone();
 
-    let conv_id = conversation_id.clone();
+    let conv_id = conversation_id;
     let mut conversation = conversation;
     let scope_collections = Some(conversation.collections.clone());
+    let pinned_document_ids = conversation.pinned_document_ids.clone();
 
     tokio::spawn(async move {
         let ctx = agent::AgentContext {
             state: state_clone.clone(),
             collections: scope_collections,
+            pinned_document_ids,
+            cancel: cancel_token.clone(),
         };
 
+        // Background embedding yields until the answer is done.
+        let interactive = state_clone.models.interactive();
         let lease = match state_clone.models.acquire_chat().await {
             Ok(Some(l)) => l,
             Ok(None) => {
@@ -281,13 +454,9 @@ pub async fn send_message(
             );
         }
         drop(lease);
+        drop(interactive);
 
-        let user_count = conversation
-            .messages
-            .iter()
-            .filter(|m| m.role == agent::MessageRole::User)
-            .count();
-        if user_count == 1 {
+        if conversation.user_turns() == 1 {
             conversation.generate_title();
         }
 
@@ -333,14 +502,156 @@ pub async fn cancel_generation(
     Ok(())
 }
 
-const PREDICTION_PROMPT: &str = r#"Based on the conversation above, predict what the user is most likely to ask or say next.
+/// Answer a tool call the agent is waiting on (see
+/// `AgentEvent::ToolApprovalRequired`).
+#[tauri::command]
+pub async fn respond_to_tool_approval(
+    tool_use_id: String,
+    approval: ToolApproval,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let answer_tx = state
+        .pending_confirmations
+        .write()
+        .await
+        .remove(&tool_use_id)
+        .ok_or_else(|| CommandError::invalid_input("No tool call is waiting for approval"))?;
+    // The agent loop may have been cancelled in the meantime.
+    let _ = answer_tx.send(approval);
+    Ok(())
+}
+
+/// Tools the user chose to always allow.
+#[tauri::command]
+pub async fn get_always_allowed_tools(state: State<'_, AppState>) -> CommandResult<Vec<String>> {
+    Ok(Settings::load(&state.config.settings_file).always_allowed_tools)
+}
+
+/// Ask for approval again before running `tool_name`.
+#[tauri::command]
+pub async fn revoke_always_allowed_tool(
+    tool_name: String,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.always_allowed_tools.retain(|t| *t != tool_name);
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(())
+}
 
-Rules:
-- Output ONLY the predicted message, nothing else
-- Keep it concise (1-2 sentences max)
-- Make it a natural follow-up question or statement
-- If the assistant just answered a question, predict a likely follow-up
-- If unsure, output nothing"#;
+/// Iteration and time limits for agent turns.
+#[tauri::command]
+pub async fn get_agent_limits(state: State<'_, AppState>) -> CommandResult<AgentLimits> {
+    Ok(Settings::load(&state.config.settings_file).agent_limits)
+}
+
+/// Persist agent limits. They apply from the next turn on.
+#[tauri::command]
+pub async fn set_agent_limits(
+    limits: AgentLimits,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    if limits.max_iterations == 0 || limits.tool_timeout_secs == 0 || limits.turn_timeout_secs == 0
+    {
+        return Err(CommandError::invalid_input(
+            "Agent limits must be at least 1",
+        ));
+    }
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.agent_limits = limits;
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(())
+}
+
+/// Whether final answers are checked against the passages they cite.
+#[tauri::command]
+pub async fn get_verify_answers(state: State<'_, AppState>) -> CommandResult<bool> {
+    Ok(Settings::load(&state.config.settings_file).verify_answers)
+}
+
+/// Turn answer verification on or off. Applies from the next turn on.
+#[tauri::command]
+pub async fn set_verify_answers(enabled: bool, state: State<'_, AppState>) -> CommandResult<()> {
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.verify_answers = enabled;
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(())
+}
+
+/// Prompt presets the user can start a chat with.
+#[tauri::command]
+pub async fn get_prompt_presets(state: State<'_, AppState>) -> CommandResult<Vec<PromptPreset>> {
+    Ok(Settings::load(&state.config.settings_file).prompt_presets)
+}
+
+/// Create a prompt preset, or replace the one with the same ID. Existing
+/// conversations keep the prompt they were started with.
+#[tauri::command]
+pub async fn save_prompt_preset(
+    preset: PromptPreset,
+    state: State<'_, AppState>,
+) -> CommandResult<PromptPreset> {
+    if preset.name.trim().is_empty() {
+        return Err(CommandError::invalid_input("A prompt preset needs a name"));
+    }
+    let preset = PromptPreset {
+        id: if preset.id.is_empty() {
+            uuid::Uuid::new_v4().to_string()
+        } else {
+            preset.id
+        },
+        name: preset.name.trim().to_string(),
+        instructions: preset.instructions,
+    };
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    match settings
+        .prompt_presets
+        .iter_mut()
+        .find(|p| p.id == preset.id)
+    {
+        Some(existing) => *existing = preset.clone(),
+        None => settings.prompt_presets.push(preset.clone()),
+    }
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(preset)
+}
+
+/// Remove a prompt preset.
+#[tauri::command]
+pub async fn delete_prompt_preset(
+    preset_id: String,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.prompt_presets.retain(|p| p.id != preset_id);
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(())
+}
+
+/// Prompt templates used for summaries, comparisons and batch questions,
+/// with the user's overrides applied.
+#[tauri::command]
+pub async fn get_prompt_templates(
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<prompts::PromptTemplate>> {
+    let library = PromptLibrary::load(&state.config.prompts_dir());
+    Ok(library.templates().cloned().collect())
+}
+
+/// Open the folder where prompt overrides go, creating it if needed.
+#[tauri::command]
+pub async fn open_prompts_folder(app: AppHandle, state: State<'_, AppState>) -> CommandResult<()> {
+    use tauri_plugin_opener::OpenerExt;
+
+    let dir = state.config.prompts_dir();
+    std::fs::create_dir_all(&dir).storage_err()?;
+    app.opener()
+        .open_path(dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| CommandError::external(e.to_string()))?;
+    Ok(())
+}
 
 /// Predict what the user might say next in a conversation
 #[tauri::command]
@@ -378,23 +689,46 @@ pub async fn predict_next_message(
         .await
         .insert(conversation_id.clone(), cancel_token.clone());
 
-    let lease = match state.models.acquire_chat().await {
+    let _interactive = state.models.interactive();
+    let lease = match state.models.acquire_prediction().await {
         Ok(Some(l)) => l,
         Ok(None) => return Ok(None),
         Err(e) => {
-            tracing::debug!(error = %e, "Failed to load chat provider for prediction");
+            tracing::debug!(error = %e, "Failed to load model for prediction");
             return Ok(None);
         }
     };
 
+    let prompt = match PromptLibrary::load(&state.config.prompts_dir())
+        .render("predict-next-message", &[])
+    {
+        Ok(prompt) => prompt,
+        Err(e) => {
+            tracing::debug!(error = %e, "Failed to render prediction prompt");
+            return Ok(None);
+        }
+    };
     let mut prediction_messages = conversation.messages.clone();
     prediction_messages.push(agent::Message {
         role: agent::MessageRole::User,
-        content: vec![agent::ContentBlock::Text {
-            text: PREDICTION_PROMPT.to_string(),
-        }],
+        content: vec![agent::ContentBlock::Text { text: prompt }],
     });
 
+    // The same conversation asks the same question, e.g. when reopened.
+    let sampling = SamplingParams::default();
+    if let Some(prediction) =
+        state
+            .completion_cache
+            .get(lease.provider(), &prediction_messages, &sampling)
+    {
+        state
+            .active_predictions
+            .write()
+            .await
+            .remove(&conversation_id);
+        return Ok(Some(shorten_prediction(prediction)));
+    }
+
     let (tx, mut rx) = tokio::sync::mpsc::channel::<ProviderEvent>(50);
 
     let collect_task = tokio::spawn(async move {
@@ -409,7 +743,13 @@ pub async fn predict_next_message(
 
     let completion_result = tokio::time::timeout(
         std::time::Duration::from_secs(5),
-        lease.stream_completion(&prediction_messages, &[], tx, cancel_token.clone()),
+        lease.stream_completion(
+            &prediction_messages,
+            &[],
+            &sampling,
+            tx,
+            cancel_token.clone(),
+        ),
     )
     .await;
 
@@ -424,16 +764,16 @@ pub async fn predict_next_message(
     match completion_result {
         Ok(Ok(_)) => {
             let prediction = collected_text.trim().to_string();
-            let prediction = if prediction.len() > 150 {
-                format!("{}...", &prediction[..147])
-            } else {
-                prediction
-            };
             if prediction.is_empty() {
-                Ok(None)
-            } else {
-                Ok(Some(prediction))
+                return Ok(None);
             }
+            state.completion_cache.insert(
+                lease.provider(),
+                &prediction_messages,
+                &sampling,
+                prediction.clone(),
+            );
+            Ok(Some(shorten_prediction(prediction)))
         }
         Ok(Err(e)) => {
             tracing::debug!("Prediction failed: {}", e);
@@ -446,6 +786,15 @@ pub async fn predict_next_message(
     }
 }
 
+/// Cut a prediction to fit the input box.
+fn shorten_prediction(prediction: String) -> String {
+    if prediction.len() > 150 {
+        format!("{}...", &prediction[..147])
+    } else {
+        prediction
+    }
+}
+
 /// Cancel any pending prediction for a conversation
 #[tauri::command]
 pub async fn cancel_prediction(
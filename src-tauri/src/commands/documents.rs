@@ -1,8 +1,26 @@
+use std::collections::{HashMap, HashSet};
+
+use iroh_blobs::Hash;
+use iroh_docs::NamespaceId;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, State};
+use tokio_util::sync::CancellationToken;
 
 use super::CollectionId;
-use crate::core::{AppState, PipelineProgress};
+use crate::core::agent::tools::page_spans;
+use crate::core::feeds::Feed;
+use crate::core::hooks::{Hook, HookAction, HookEvent, Hooks};
+use crate::core::jobs::{self, BatchAnswer, MaintenanceTask, ReembedSummary};
+use crate::core::mail::MailboxConfig;
+use crate::core::redact::{self, RedactedFormat, RedactionMap};
+use crate::core::remote::{
+    self, Remote, RemoteCredentials, RemoteEndpoint, RemoteObject, RemoteSource,
+};
+use crate::core::storage::DocumentMetadata;
+use crate::core::{
+    models, AppState, DirectoryFilter, FilePreview, ImportPriority, ImportReport, ImportSummary,
+    MaintenanceConfig, NearDuplicate, PipelineProgress, QueuedImport, Settings, ThroughputStats,
+};
 use crate::error::{CommandError, CommandResult, ResultExt};
 
 /// Document metadata returned to frontend
@@ -14,6 +32,10 @@ pub struct DocumentInfo {
     pub page_count: usize,
     pub tags: Vec<String>,
     pub created_at: String,
+    /// URL the document was downloaded from, if imported from the web
+    pub source_url: Option<String>,
+    /// Other documents in the collection with nearly identical text
+    pub near_duplicates: Vec<NearDuplicate>,
 }
 
 /// Event payload for document added
@@ -33,11 +55,15 @@ pub struct DocumentAddedEvent {
 ///
 /// Progress is tracked per-stage via the pipeline.
 /// Returns immediately with initial progress.
+///
+/// `paths` may also hold http(s) URLs of PDF or Markdown documents, which
+/// are downloaded when their turn comes. `priorities` raises or lowers
+/// files by path; the rest go smallest first.
 #[tauri::command]
-pub async fn start_import<R: tauri::Runtime>(
+pub async fn start_import(
     paths: Vec<String>,
     collection_id: CollectionId,
-    app: AppHandle<R>,
+    priorities: Option<HashMap<String, ImportPriority>>,
     state: State<'_, AppState>,
 ) -> CommandResult<PipelineProgress> {
     let namespace_id = collection_id.namespace();
@@ -56,12 +82,18 @@ pub async fn start_import<R: tauri::Runtime>(
         );
     }
 
+    for (path, priority) in priorities.unwrap_or_default() {
+        state
+            .pipeline
+            .set_file_priority(&namespace_id, std::path::Path::new(&path), priority);
+    }
+
     // Convert paths to PathBuf
     let paths: Vec<std::path::PathBuf> = paths.iter().map(std::path::PathBuf::from).collect();
 
     // Clone pipeline for async task
     let pipeline = state.pipeline.clone();
-    let app_clone = app.clone();
+    let events = state.events.clone();
     let collection_id_clone = collection_id.clone();
 
     // Spawn async task to import files
@@ -81,7 +113,7 @@ pub async fn start_import<R: tauri::Runtime>(
 
         // Emit progress update
         if let Some(progress) = pipeline.get_progress(&collection_id_clone).await {
-            let _ = app_clone.emit("pipeline-progress", &progress);
+            events.publish(progress);
         }
     });
 
@@ -98,6 +130,767 @@ pub async fn start_import<R: tauri::Runtime>(
     Ok(progress)
 }
 
+/// Preview what importing `paths` into a collection would do, without
+/// storing anything: page counts, language, duplicates and estimated
+/// embedding time, one entry per path in order.
+#[tauri::command]
+pub async fn preview_import(
+    paths: Vec<String>,
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<FilePreview>> {
+    let paths: Vec<std::path::PathBuf> = paths.iter().map(std::path::PathBuf::from).collect();
+    state
+        .pipeline
+        .preview_import(collection_id.namespace(), &paths)
+        .await
+        .storage_err()
+}
+
+/// Import every PDF under a directory, recursively.
+///
+/// `include` and `exclude` are globs matched against each file's path
+/// relative to the directory and against its name; with no `include`
+/// every PDF is imported. Files already in the collection are skipped, and
+/// each document is tagged with its folder relative to the directory.
+/// Returns immediately; progress arrives via `pipeline-progress` events.
+#[tauri::command]
+pub async fn import_directory(
+    path: String,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let namespace_id = collection_id.namespace();
+    let collection_id = namespace_id.to_string();
+    let root = std::path::PathBuf::from(path);
+    if !root.is_dir() {
+        return Err(CommandError::invalid_input(format!(
+            "Not a folder: {}",
+            root.display()
+        )));
+    }
+    let filter = DirectoryFilter::new(&include, &exclude)
+        .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+
+    tracing::info!(
+        "Importing directory {} into collection {}",
+        root.display(),
+        collection_id
+    );
+
+    let pipeline = state.pipeline.clone();
+    let events = state.events.clone();
+    tokio::spawn(async move {
+        match pipeline
+            .import_directory(namespace_id, &root, &filter)
+            .await
+        {
+            Ok((success, errors)) => {
+                tracing::info!(
+                    "Directory import complete for {}: {} successful, {} failed",
+                    collection_id,
+                    success,
+                    errors.len()
+                );
+                for (path, error) in &errors {
+                    tracing::error!("Failed to import {:?}: {}", path, error);
+                }
+            }
+            Err(e) => tracing::error!("Failed to import directory {:?}: {}", root, e),
+        }
+
+        if let Some(progress) = pipeline.get_progress(&collection_id).await {
+            events.publish(progress);
+        }
+    });
+
+    Ok(())
+}
+
+/// A remote source as the settings form sends it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoteSourceInput {
+    /// `None` for a new source.
+    pub id: Option<String>,
+    pub name: String,
+    pub endpoint: RemoteEndpoint,
+    /// New credentials; `None` keeps the stored ones, an empty secret
+    /// removes them.
+    pub credentials: Option<RemoteCredentials>,
+}
+
+/// S3 and WebDAV storage configured to import from.
+#[tauri::command]
+pub async fn list_remote_sources(state: State<'_, AppState>) -> CommandResult<Vec<RemoteSource>> {
+    Ok(Settings::load(&state.config.settings_file).remote_sources)
+}
+
+/// Add or update a remote source. Credentials go to the keychain.
+#[tauri::command]
+pub async fn save_remote_source(
+    source: RemoteSourceInput,
+    state: State<'_, AppState>,
+) -> CommandResult<RemoteSource> {
+    let name = source.name.trim();
+    if name.is_empty() {
+        return Err(CommandError::invalid_input("Name the source"));
+    }
+    source
+        .endpoint
+        .validate()
+        .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    let id = source
+        .id
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let index = match settings.remote_sources.iter().position(|s| s.id == id) {
+        Some(index) => index,
+        None => {
+            settings.remote_sources.push(RemoteSource {
+                id: id.clone(),
+                name: String::new(),
+                endpoint: source.endpoint.clone(),
+                credentials: None,
+            });
+            settings.remote_sources.len() - 1
+        }
+    };
+    let saved = &mut settings.remote_sources[index];
+    saved.name = name.to_string();
+    saved.endpoint = source.endpoint;
+    if let Some(credentials) = source.credentials {
+        let credentials = (!credentials.secret.is_empty()).then_some(credentials);
+        saved
+            .set_credentials(&state.secrets, credentials.as_ref())
+            .storage_err()?;
+    }
+    let saved = saved.clone();
+
+    settings.save(&state.config.settings_file).storage_err()?;
+    state.connect_remote_sources(&settings.remote_sources).await;
+    Ok(saved)
+}
+
+/// Remove a remote source and its credentials. Objects still queued from
+/// it fail when their turn comes.
+#[tauri::command]
+pub async fn delete_remote_source(id: String, state: State<'_, AppState>) -> CommandResult<()> {
+    let mut settings = Settings::load(&state.config.settings_file);
+    let Some(index) = settings.remote_sources.iter().position(|s| s.id == id) else {
+        return Ok(());
+    };
+    let mut source = settings.remote_sources.remove(index);
+    source.set_credentials(&state.secrets, None).storage_err()?;
+    settings.save(&state.config.settings_file).storage_err()?;
+    state.connect_remote_sources(&settings.remote_sources).await;
+    Ok(())
+}
+
+/// PDFs and text files in a remote source whose key starts with `prefix`.
+#[tauri::command]
+pub async fn list_remote_objects(
+    source_id: String,
+    prefix: String,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<RemoteObject>> {
+    let settings = Settings::load(&state.config.settings_file);
+    let source = settings
+        .remote_sources
+        .into_iter()
+        .find(|s| s.id == source_id)
+        .ok_or_else(|| CommandError::invalid_input("Unknown remote source"))?;
+    let credentials = source.credentials(&state.secrets);
+    Remote::new(source, credentials)
+        .list(&prefix)
+        .await
+        .external_err()
+}
+
+/// Import objects from a remote source. Each is fetched when its turn in
+/// the queue comes; progress arrives via `pipeline-progress` events.
+#[tauri::command]
+pub async fn import_remote(
+    collection_id: CollectionId,
+    source_id: String,
+    keys: Vec<String>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let namespace_id = collection_id.namespace();
+    let collection_id = namespace_id.to_string();
+    let paths: Vec<std::path::PathBuf> = keys
+        .iter()
+        .map(|key| remote::queued_path(&source_id, key))
+        .collect();
+
+    tracing::info!(
+        "Starting remote import: {} objects from {} into collection {}",
+        paths.len(),
+        source_id,
+        collection_id
+    );
+
+    let pipeline = state.pipeline.clone();
+    let events = state.events.clone();
+    tokio::spawn(async move {
+        let (success, errors) = pipeline.import_files(namespace_id, paths).await;
+        tracing::info!(
+            "Remote import complete for {}: {} successful, {} failed",
+            collection_id,
+            success,
+            errors.len()
+        );
+        for (path, error) in &errors {
+            tracing::error!("Failed to import {:?}: {}", path, error);
+        }
+        if let Some(progress) = pipeline.get_progress(&collection_id).await {
+            events.publish(progress);
+        }
+    });
+
+    Ok(())
+}
+
+/// The tips mailbox, if one is set up.
+#[tauri::command]
+pub async fn get_mailbox_config(
+    state: State<'_, AppState>,
+) -> CommandResult<Option<MailboxConfig>> {
+    Ok(Settings::load(&state.config.settings_file).mailbox)
+}
+
+/// Set up or change the tips mailbox; `None` removes it along with its
+/// password. A `password` of `None` keeps the stored one, an empty one
+/// removes it. New messages are checked for on the maintenance interval.
+#[tauri::command]
+pub async fn set_mailbox_config(
+    config: Option<MailboxConfig>,
+    password: Option<String>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let mut settings = Settings::load(&state.config.settings_file);
+    let stored = settings.mailbox.take();
+    let Some(mut config) = config else {
+        if let Some(mut stored) = stored {
+            stored.set_password(&state.secrets, "").storage_err()?;
+        }
+        return settings.save(&state.config.settings_file).storage_err();
+    };
+
+    if config.host.trim().is_empty() || config.user.trim().is_empty() {
+        return Err(CommandError::invalid_input(
+            "Enter the server and user name",
+        ));
+    }
+    if config.folder.trim().is_empty() {
+        return Err(CommandError::invalid_input("Enter a folder"));
+    }
+    config
+        .collection_id
+        .parse::<NamespaceId>()
+        .map_err(|_| CommandError::invalid_input("Choose a collection"))?;
+
+    config.password = stored.and_then(|s| s.password);
+    if let Some(password) = password {
+        config
+            .set_password(&state.secrets, &password)
+            .storage_err()?;
+    }
+    settings.mailbox = Some(config);
+    settings.save(&state.config.settings_file).storage_err()
+}
+
+/// A feed as the settings form sends it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeedInput {
+    /// `None` for a new feed.
+    pub id: Option<String>,
+    pub name: String,
+    pub url: String,
+    pub collection_id: String,
+}
+
+/// RSS and Atom feeds followed into collections.
+#[tauri::command]
+pub async fn list_feeds(state: State<'_, AppState>) -> CommandResult<Vec<Feed>> {
+    Ok(Settings::load(&state.config.settings_file).feeds)
+}
+
+/// Follow a feed, or change one. New articles are imported on the
+/// maintenance interval.
+#[tauri::command]
+pub async fn save_feed(feed: FeedInput, state: State<'_, AppState>) -> CommandResult<Feed> {
+    let url = feed.url.trim().to_string();
+    let feed = Feed {
+        id: feed.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        name: match feed.name.trim() {
+            "" => url.clone(),
+            name => name.to_string(),
+        },
+        url,
+        collection_id: feed.collection_id,
+    };
+    feed.validate()
+        .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    match settings.feeds.iter_mut().find(|f| f.id == feed.id) {
+        Some(saved) => *saved = feed.clone(),
+        None => settings.feeds.push(feed.clone()),
+    }
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(feed)
+}
+
+/// Stop following a feed. Articles already imported stay.
+#[tauri::command]
+pub async fn delete_feed(id: String, state: State<'_, AppState>) -> CommandResult<()> {
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.feeds.retain(|f| f.id != id);
+    settings.save(&state.config.settings_file).storage_err()
+}
+
+/// A hook as the settings form sends it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HookInput {
+    /// `None` for a new hook.
+    pub id: Option<String>,
+    pub name: String,
+    pub enabled: bool,
+    pub events: Vec<HookEvent>,
+    pub action: HookAction,
+}
+
+impl HookInput {
+    fn into_hook(self) -> CommandResult<Hook> {
+        let hook = Hook {
+            id: self.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            name: self.name.trim().to_string(),
+            enabled: self.enabled,
+            events: self.events,
+            action: self.action,
+        };
+        if hook.name.is_empty() {
+            return Err(CommandError::invalid_input("Name the hook"));
+        }
+        hook.validate()
+            .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+        Ok(hook)
+    }
+}
+
+/// Hooks run as documents are imported, deleted and synced.
+#[tauri::command]
+pub async fn list_hooks(state: State<'_, AppState>) -> CommandResult<Vec<Hook>> {
+    Ok(Settings::load(&state.config.settings_file).hooks)
+}
+
+/// Add or change a hook. Takes effect for the next event.
+#[tauri::command]
+pub async fn save_hook(hook: HookInput, state: State<'_, AppState>) -> CommandResult<Hook> {
+    let hook = hook.into_hook()?;
+    let mut settings = Settings::load(&state.config.settings_file);
+    match settings.hooks.iter_mut().find(|h| h.id == hook.id) {
+        Some(saved) => *saved = hook.clone(),
+        None => settings.hooks.push(hook.clone()),
+    }
+    settings.save(&state.config.settings_file).storage_err()?;
+    state.pipeline.set_hooks(settings.hooks);
+    Ok(hook)
+}
+
+/// Remove a hook.
+#[tauri::command]
+pub async fn delete_hook(id: String, state: State<'_, AppState>) -> CommandResult<()> {
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.hooks.retain(|h| h.id != id);
+    settings.save(&state.config.settings_file).storage_err()?;
+    state.pipeline.set_hooks(settings.hooks);
+    Ok(())
+}
+
+/// Send a hook a test payload, marked `"test": true`, and report whether
+/// it was delivered.
+#[tauri::command]
+pub async fn test_hook(hook: HookInput) -> CommandResult<()> {
+    Hooks::test(&hook.into_hook()?).await.external_err()
+}
+
+/// Retry imports that failed for good, for one collection or all of them.
+/// Transient failures are retried automatically first; this is for after
+/// the cause has been fixed, e.g. an embedder configured. Returns the
+/// number of documents and files retried.
+#[tauri::command]
+pub async fn retry_failed_imports(
+    collection_id: Option<CollectionId>,
+    state: State<'_, AppState>,
+) -> CommandResult<usize> {
+    let namespace_id = collection_id.map(|id| id.namespace());
+    Ok(state.pipeline.retry_failed(namespace_id).await)
+}
+
+/// A document in another collection with the same content as a file about
+/// to be imported.
+#[derive(Debug, Clone, Serialize)]
+pub struct CrossCollectionMatch {
+    pub path: String,
+    pub collection_id: String,
+    pub collection_name: String,
+    pub document_id: String,
+    pub document_name: String,
+}
+
+/// A document to link from another collection.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkRequest {
+    pub collection_id: CollectionId,
+    pub document_id: String,
+}
+
+/// Documents with the same source in more than one collection.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub documents: Vec<DuplicateDocument>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateDocument {
+    pub collection_id: String,
+    pub collection_name: String,
+    pub document_id: String,
+    pub document_name: String,
+}
+
+/// Files among `paths` already in another collection, so the user can link
+/// those instead of importing copies. Empty unless cross-collection
+/// duplicate checks are turned on in settings.
+#[tauri::command]
+pub async fn find_cross_collection_duplicates(
+    paths: Vec<String>,
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<CrossCollectionMatch>> {
+    if !Settings::load(&state.config.settings_file).cross_collection_duplicates {
+        return Ok(Vec::new());
+    }
+    let namespace_id = collection_id.namespace();
+    let storage = state.storage.read().await;
+    let names: HashMap<_, _> = storage
+        .list_collections()
+        .await
+        .storage_err()?
+        .into_iter()
+        .map(|(id, metadata)| (id, metadata.name))
+        .collect();
+
+    let mut matches = Vec::new();
+    for path in paths {
+        // URLs aren't known until downloaded.
+        let Ok(bytes) = tokio::fs::read(&path).await else {
+            continue;
+        };
+        let found = storage
+            .find_source_elsewhere(namespace_id, &Hash::new(&bytes))
+            .await
+            .storage_err()?;
+        for (other, metadata) in found {
+            matches.push(CrossCollectionMatch {
+                path: path.clone(),
+                collection_id: other.to_string(),
+                collection_name: names.get(&other).cloned().unwrap_or_default(),
+                document_id: metadata.id,
+                document_name: metadata.name,
+            });
+        }
+    }
+    Ok(matches)
+}
+
+/// Add documents from other collections to this one by linking: content,
+/// text and embeddings are shared, not imported again. Returns the linked
+/// documents.
+#[tauri::command]
+pub async fn link_documents(
+    documents: Vec<LinkRequest>,
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<DocumentInfo>> {
+    let namespace_id = collection_id.namespace();
+    let mut linked = Vec::with_capacity(documents.len());
+    for request in documents {
+        let metadata = state
+            .pipeline
+            .link_document(
+                request.collection_id.namespace(),
+                &request.document_id,
+                namespace_id,
+            )
+            .await
+            .storage_err()?;
+        linked.push(DocumentInfo {
+            id: metadata.id,
+            name: metadata.name,
+            file_type: metadata.file_type,
+            page_count: metadata.page_count,
+            tags: metadata.tags,
+            created_at: metadata.created_at,
+            source_url: metadata.source_url,
+            near_duplicates: metadata.near_duplicates,
+        });
+    }
+    Ok(linked)
+}
+
+/// Documents whose content is in more than one collection.
+#[tauri::command]
+pub async fn list_cross_collection_duplicates(
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<DuplicateGroup>> {
+    let storage = state.storage.read().await;
+    let names: HashMap<_, _> = storage
+        .list_collections()
+        .await
+        .storage_err()?
+        .into_iter()
+        .map(|(id, metadata)| (id, metadata.name))
+        .collect();
+    let registry = storage.source_hash_registry().await.storage_err()?;
+
+    let mut groups = Vec::new();
+    for (hash, entries) in registry {
+        let collections: HashSet<_> = entries.iter().map(|(id, _)| id).collect();
+        if collections.len() < 2 {
+            continue;
+        }
+        let mut documents = Vec::with_capacity(entries.len());
+        for (namespace_id, document_id) in entries {
+            let document_name = storage
+                .get_document(namespace_id, &document_id)
+                .await
+                .storage_err()?
+                .map(|m| m.name)
+                .unwrap_or_default();
+            documents.push(DuplicateDocument {
+                collection_id: namespace_id.to_string(),
+                collection_name: names.get(&namespace_id).cloned().unwrap_or_default(),
+                document_id,
+                document_name,
+            });
+        }
+        groups.push(DuplicateGroup {
+            hash: hash.to_string(),
+            documents,
+        });
+    }
+    groups.sort_by(|a, b| {
+        a.documents[0]
+            .document_name
+            .cmp(&b.documents[0].document_name)
+    });
+    Ok(groups)
+}
+
+/// Whether imports are checked against other collections.
+#[tauri::command]
+pub async fn get_cross_collection_duplicates(state: State<'_, AppState>) -> CommandResult<bool> {
+    Ok(Settings::load(&state.config.settings_file).cross_collection_duplicates)
+}
+
+/// Turn cross-collection duplicate checks on or off.
+#[tauri::command]
+pub async fn set_cross_collection_duplicates(
+    enabled: bool,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.cross_collection_duplicates = enabled;
+    settings.save(&state.config.settings_file).storage_err()
+}
+
+/// Intervals of the background maintenance jobs.
+#[tauri::command]
+pub async fn get_maintenance_config(
+    state: State<'_, AppState>,
+) -> CommandResult<MaintenanceConfig> {
+    Ok(state.maintenance.config())
+}
+
+/// Persist maintenance intervals and apply them to the running scheduler.
+#[tauri::command]
+pub async fn set_maintenance_config(
+    config: MaintenanceConfig,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.maintenance = config.clone();
+    settings.save(&state.config.settings_file).storage_err()?;
+    state.maintenance.set_config(config);
+    Ok(())
+}
+
+/// Run a maintenance job now. The result arrives as a `maintenance-run`
+/// event.
+#[tauri::command]
+pub async fn run_maintenance_task(
+    task: MaintenanceTask,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    state.maintenance.run_now(task);
+    Ok(())
+}
+
+/// Event payload for re-embed progress
+#[derive(Debug, Clone, Serialize)]
+pub struct ReembedProgressEvent {
+    pub collection_id: String,
+    pub model_id: String,
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Regenerate embeddings for every document in a collection with
+/// `model_id`, or the active embedding model when omitted.
+///
+/// Used after changing the chunking, or to embed with a downloaded model
+/// before switching to it so search works right after the switch.
+/// Progress arrives via `reembed-progress` events; `cancel_reembed`
+/// stops it.
+#[tauri::command]
+pub async fn reembed_collection(
+    collection_id: CollectionId,
+    model_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> CommandResult<ReembedSummary> {
+    let model_id = match model_id {
+        Some(id) => id,
+        None => state
+            .models
+            .embedding_model_id()
+            .await
+            .ok_or(CommandError::embedder_not_configured())?,
+    };
+    if models::get_embedding_model(&model_id).is_none() {
+        return Err(CommandError::model_not_found(model_id.as_str()));
+    }
+
+    let namespace_id = collection_id.namespace();
+    let collection_id = namespace_id.to_string();
+    let cancel_token = CancellationToken::new();
+    state
+        .active_reembeds
+        .write()
+        .await
+        .insert(collection_id.clone(), cancel_token.clone());
+
+    let result = jobs::reembed_collection(
+        &state,
+        namespace_id,
+        &model_id,
+        |progress| {
+            let _ = app.emit(
+                "reembed-progress",
+                &ReembedProgressEvent {
+                    collection_id: collection_id.clone(),
+                    model_id: model_id.clone(),
+                    completed: progress.completed,
+                    total: progress.total,
+                },
+            );
+        },
+        &cancel_token,
+    )
+    .await;
+    state.active_reembeds.write().await.remove(&collection_id);
+    result.internal_err()
+}
+
+/// Cancel a collection's re-embed in progress
+#[tauri::command]
+pub async fn cancel_reembed(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let collection_id = collection_id.namespace().to_string();
+    if let Some(token) = state.active_reembeds.read().await.get(&collection_id) {
+        token.cancel();
+        tracing::info!("Cancelled re-embed for collection {}", collection_id);
+    }
+    Ok(())
+}
+
+/// Event payload for batch question progress
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchAskProgressEvent {
+    pub collection_id: String,
+    pub completed: usize,
+    pub total: usize,
+}
+
+/// Ask one question of every document in a collection and return a row per
+/// document with the answer and the pages it cites. Progress arrives via
+/// `batch-ask-progress` events; `cancel_batch_ask` stops it.
+#[tauri::command]
+pub async fn batch_ask(
+    collection_id: CollectionId,
+    question: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<BatchAnswer>> {
+    if question.trim().is_empty() {
+        return Err(CommandError::invalid_input("Question cannot be empty"));
+    }
+    if !state.models.chat_ready().await {
+        return Err(CommandError::provider_not_configured());
+    }
+
+    let namespace_id = collection_id.namespace();
+    let collection_id = namespace_id.to_string();
+    let cancel_token = CancellationToken::new();
+    state
+        .active_batches
+        .write()
+        .await
+        .insert(collection_id.clone(), cancel_token.clone());
+
+    let result = jobs::batch_ask(
+        &state,
+        namespace_id,
+        &question,
+        |progress| {
+            let _ = app.emit(
+                "batch-ask-progress",
+                &BatchAskProgressEvent {
+                    collection_id: collection_id.clone(),
+                    completed: progress.completed,
+                    total: progress.total,
+                },
+            );
+        },
+        &cancel_token,
+    )
+    .await;
+    state.active_batches.write().await.remove(&collection_id);
+    result.internal_err()
+}
+
+/// Cancel a collection's batch question in progress
+#[tauri::command]
+pub async fn cancel_batch_ask(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let collection_id = collection_id.namespace().to_string();
+    if let Some(token) = state.active_batches.read().await.get(&collection_id) {
+        token.cancel();
+        tracing::info!("Cancelled batch question for collection {}", collection_id);
+    }
+    Ok(())
+}
+
 /// Get pipeline progress for all active collections
 ///
 /// Returns progress for each stage: Store, Extract, Embed, Index.
@@ -109,6 +902,138 @@ pub async fn get_pipeline_progress(
     Ok(state.pipeline.get_all_progress().await)
 }
 
+/// Files queued for import and not yet stored, across all collections.
+///
+/// The queue is kept on disk, so files left when the app closed show up
+/// here until they are imported again on startup.
+#[tauri::command]
+pub async fn get_import_queue(state: State<'_, AppState>) -> CommandResult<Vec<QueuedImport>> {
+    Ok(state.pipeline.queued_imports())
+}
+
+/// Reports of a collection's finished imports, newest first.
+#[tauri::command]
+pub async fn list_import_reports(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<ImportSummary>> {
+    state
+        .pipeline
+        .import_reports()
+        .list(Some(&collection_id.namespace().to_string()))
+        .storage_err()
+}
+
+/// An import report with each file's status, hashes and page count.
+#[tauri::command]
+pub async fn get_import_report(
+    id: String,
+    state: State<'_, AppState>,
+) -> CommandResult<ImportReport> {
+    import_report(&state, &id)
+}
+
+/// Save a copy of an import report to `path`, chosen by the user.
+#[tauri::command]
+pub async fn save_import_report(
+    id: String,
+    path: String,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let report = import_report(&state, &id)?;
+    let json = serde_json::to_vec_pretty(&report).internal_err()?;
+    std::fs::write(&path, json).storage_err()
+}
+
+fn import_report(state: &AppState, id: &str) -> CommandResult<ImportReport> {
+    state
+        .pipeline
+        .import_reports()
+        .get(id)
+        .storage_err()?
+        .ok_or_else(|| CommandError::invalid_input("No such import report"))
+}
+
+/// Pause an import. With `path`, only that file is paused; otherwise the
+/// whole collection. Jobs already running finish; the rest wait.
+#[tauri::command]
+pub async fn pause_import(
+    collection_id: CollectionId,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let namespace_id = collection_id.namespace();
+    match path {
+        Some(path) => state
+            .pipeline
+            .pause_file(&namespace_id, std::path::Path::new(&path)),
+        None => state.pipeline.pause_collection(&namespace_id).await,
+    }
+    Ok(())
+}
+
+/// Raise or lower a file waiting to be imported. Files are stored by
+/// priority, then smallest first.
+#[tauri::command]
+pub async fn set_import_priority(
+    collection_id: CollectionId,
+    path: String,
+    priority: ImportPriority,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    state.pipeline.set_file_priority(
+        &collection_id.namespace(),
+        std::path::Path::new(&path),
+        priority,
+    );
+    Ok(())
+}
+
+/// Resume a paused import, the whole collection or one file.
+#[tauri::command]
+pub async fn resume_import(
+    collection_id: CollectionId,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let namespace_id = collection_id.namespace();
+    match path {
+        Some(path) => state
+            .pipeline
+            .resume_file(&namespace_id, std::path::Path::new(&path)),
+        None => state.pipeline.resume_collection(&namespace_id).await,
+    }
+    Ok(())
+}
+
+/// Cancel an import, the whole collection or one file.
+///
+/// Files not yet stored are dropped and documents stored by the import
+/// but not yet indexed are removed. Returns the number removed.
+#[tauri::command]
+pub async fn cancel_import(
+    collection_id: CollectionId,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> CommandResult<usize> {
+    let namespace_id = collection_id.namespace();
+    let removed = match path {
+        Some(path) => {
+            let removed = state
+                .pipeline
+                .cancel_file(namespace_id, std::path::Path::new(&path))
+                .await;
+            usize::from(removed)
+        }
+        None => state.pipeline.cancel_collection(namespace_id).await,
+    };
+
+    if let Some(progress) = state.pipeline.get_progress(&namespace_id.to_string()).await {
+        state.events.publish(progress);
+    }
+    Ok(removed)
+}
+
 /// Get pipeline progress for a specific collection
 #[tauri::command]
 pub async fn get_collection_pipeline_progress(
@@ -119,6 +1044,15 @@ pub async fn get_collection_pipeline_progress(
     Ok(state.pipeline.get_progress(&key).await)
 }
 
+/// Get embedding and indexing throughput with an estimated time to finish
+///
+/// The same snapshot is pushed as `pipeline-throughput` every few seconds
+/// while documents are being processed.
+#[tauri::command]
+pub async fn get_pipeline_throughput(state: State<'_, AppState>) -> CommandResult<ThroughputStats> {
+    Ok(state.pipeline.throughput().await)
+}
+
 /// Get all documents in a collection
 #[tauri::command]
 pub async fn get_documents(
@@ -141,10 +1075,88 @@ pub async fn get_documents(
             page_count: m.page_count,
             tags: m.tags,
             created_at: m.created_at,
+            source_url: m.source_url,
+            near_duplicates: m.near_duplicates,
         })
         .collect())
 }
 
+/// Documents in a collection with content still on peers, such as the
+/// sources a narrower sync policy doesn't download.
+#[tauri::command]
+pub async fn get_undownloaded_documents(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<String>> {
+    let storage = state.storage.read().await;
+    storage
+        .documents_missing_content(collection_id.namespace())
+        .await
+        .storage_err()
+}
+
+/// Download whatever of a document hasn't been downloaded yet. It is
+/// embedded and indexed like any synced document once it arrives.
+#[tauri::command]
+pub async fn fetch_document(
+    collection_id: CollectionId,
+    document_id: String,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    state
+        .pipeline
+        .fetch_document(&collection_id.namespace(), &document_id)
+        .await;
+    Ok(())
+}
+
+/// Send a document, without the rest of its collection, to the node
+/// `peer`. Returns the ticket the peer downloads it with, which can also
+/// be passed on by hand.
+#[tauri::command]
+pub async fn send_document(
+    collection_id: CollectionId,
+    document_id: String,
+    peer: String,
+    state: State<'_, AppState>,
+) -> CommandResult<String> {
+    let name = Settings::load(&state.config.settings_file).display_name;
+    let storage = state.storage.read().await;
+    storage
+        .send_document(
+            collection_id.namespace(),
+            &document_id,
+            peer.trim(),
+            name.as_deref(),
+        )
+        .await
+        .external_err()
+}
+
+/// Add a document a peer sent to a collection, downloading it from them.
+#[tauri::command]
+pub async fn receive_document(
+    collection_id: CollectionId,
+    ticket: String,
+    state: State<'_, AppState>,
+) -> CommandResult<DocumentInfo> {
+    let metadata = state
+        .pipeline
+        .receive_document(collection_id.namespace(), ticket.trim())
+        .await
+        .external_err()?;
+    Ok(DocumentInfo {
+        id: metadata.id,
+        name: metadata.name,
+        file_type: metadata.file_type,
+        page_count: metadata.page_count,
+        tags: metadata.tags,
+        created_at: metadata.created_at,
+        source_url: metadata.source_url,
+        near_duplicates: metadata.near_duplicates,
+    })
+}
+
 /// Get a single document from a collection by ID
 #[tauri::command]
 pub async fn get_document(
@@ -167,6 +1179,8 @@ pub async fn get_document(
         page_count: document.page_count,
         tags: document.tags,
         created_at: document.created_at,
+        source_url: document.source_url,
+        near_duplicates: document.near_duplicates,
     })
 }
 
@@ -191,6 +1205,21 @@ pub async fn get_document_text(
     })
 }
 
+/// Get the extracted text of a document page by page, so views can open
+/// at a cited page
+#[tauri::command]
+pub async fn get_document_pages(
+    collection_id: CollectionId,
+    document_id: String,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<String>> {
+    let (metadata, text) = document_with_text(&state, &collection_id, &document_id).await?;
+    Ok(page_spans(&text, &metadata.page_boundaries)
+        .into_iter()
+        .map(|span| text[span].to_string())
+        .collect())
+}
+
 /// Get the text chunks for a document (read from stored embeddings)
 #[tauri::command]
 pub async fn get_document_chunks(
@@ -267,3 +1296,83 @@ pub async fn delete_document(
 
     Ok(())
 }
+
+/// Open a file exported by the agent's `get_source_file` tool, or a
+/// redacted copy, in the system's default viewer. Only paths inside the
+/// exports directory are opened, since the path comes from the transcript.
+#[tauri::command]
+pub async fn open_source_file(
+    path: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use tauri_plugin_opener::OpenerExt;
+
+    let exports_dir = state.config.exports_dir().canonicalize().internal_err()?;
+    let path = std::path::Path::new(&path)
+        .canonicalize()
+        .map_err(|_| CommandError::invalid_input("The exported file no longer exists"))?;
+    if !path.starts_with(&exports_dir) {
+        return Err(CommandError::invalid_input(
+            "Only exported source files can be opened",
+        ));
+    }
+
+    app.opener()
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|e| CommandError::external(e.to_string()))?;
+    Ok(())
+}
+
+/// A document's metadata and extracted text.
+async fn document_with_text(
+    state: &AppState,
+    collection_id: &CollectionId,
+    document_id: &str,
+) -> CommandResult<(DocumentMetadata, String)> {
+    let storage = state.storage.read().await;
+    let metadata = storage
+        .get_document(collection_id.namespace(), document_id)
+        .await
+        .storage_err()?
+        .ok_or(CommandError::document_not_found())?;
+    let text = storage
+        .get_document_text(collection_id.namespace(), document_id)
+        .await
+        .storage_err()?
+        .ok_or(CommandError::text_not_found())?;
+    Ok((metadata, String::from_utf8_lossy(&text).into_owned()))
+}
+
+/// Find the names, email addresses, phone numbers and account numbers in
+/// a document, for the user to review before exporting a redacted copy.
+#[tauri::command]
+pub async fn get_redaction_map(
+    collection_id: CollectionId,
+    document_id: String,
+    state: State<'_, AppState>,
+) -> CommandResult<RedactionMap> {
+    let (metadata, text) = document_with_text(&state, &collection_id, &document_id).await?;
+    let pages = redact::split_pages(&text, &metadata.page_boundaries);
+    Ok(RedactionMap::detect(&pages))
+}
+
+/// Export a copy of a document with its personal data replaced by labels,
+/// leaving the entries labelled in `keep`. Returns the file's path, which
+/// `open_source_file` opens.
+#[tauri::command]
+pub async fn export_redacted_document(
+    collection_id: CollectionId,
+    document_id: String,
+    keep: Vec<String>,
+    format: RedactedFormat,
+    state: State<'_, AppState>,
+) -> CommandResult<String> {
+    let (metadata, text) = document_with_text(&state, &collection_id, &document_id).await?;
+    let pages = redact::split_pages(&text, &metadata.page_boundaries);
+    let redacted = RedactionMap::detect(&pages).apply(&pages, &keep);
+
+    let dir = state.config.exports_dir().join(&metadata.id);
+    let path = redact::export(&dir, &metadata.id, &redacted, format).internal_err()?;
+    Ok(path.to_string_lossy().into_owned())
+}
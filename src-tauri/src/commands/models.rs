@@ -1,9 +1,14 @@
 use std::sync::Arc;
 
 use serde::Serialize;
-use tauri::{AppHandle, Emitter, State};
-
-use crate::core::{models, search, AppState, ModelType};
+use tauri::State;
+
+use super::CollectionId;
+use crate::core::{
+    models, search, AppState, ChunkingConfig, CompletionCacheStats, CoreEvent, DeviceSettings,
+    EmbeddingCacheStats, EmbeddingReadiness, EventBus, LocalRuntimeConfig, ModelType,
+    PipelineConfig,
+};
 use crate::error::{CommandError, CommandResult, ResultExt};
 
 /// Model info for frontend (unified across types)
@@ -15,6 +20,12 @@ pub struct ModelInfo {
     pub size_gb: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dimensions: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub languages: Option<models::LanguageCoverage>,
+    /// Why this build can't run the model; the UI shows it and disables
+    /// the model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unavailable: Option<String>,
 }
 
 impl From<models::LanguageModelInfo> for ModelInfo {
@@ -25,6 +36,8 @@ impl From<models::LanguageModelInfo> for ModelInfo {
             description: m.description,
             size_gb: m.size_gb,
             dimensions: None,
+            languages: None,
+            unavailable: None,
         }
     }
 }
@@ -32,11 +45,13 @@ impl From<models::LanguageModelInfo> for ModelInfo {
 impl From<models::EmbeddingModelInfo> for ModelInfo {
     fn from(m: models::EmbeddingModelInfo) -> Self {
         Self {
+            unavailable: m.unavailable_reason().map(String::from),
             id: m.id,
             name: m.name,
             description: m.description,
             size_gb: m.size_gb,
             dimensions: Some(m.dimensions),
+            languages: Some(m.languages),
         }
     }
 }
@@ -49,22 +64,37 @@ impl From<models::OcrModelInfo> for ModelInfo {
             description: m.description,
             size_gb: m.size_gb,
             dimensions: None,
+            languages: None,
+            unavailable: None,
         }
     }
 }
 
-/// Get list of available models for a type
+/// Get list of available models for a type. A configured custom embedding
+/// model is listed after the curated ones.
 #[tauri::command]
-pub async fn get_available_models(model_type: ModelType) -> CommandResult<Vec<ModelInfo>> {
+pub async fn get_available_models(
+    model_type: ModelType,
+    state: State<'_, AppState>,
+) -> CommandResult<Vec<ModelInfo>> {
+    use crate::core::Settings;
+
     Ok(match model_type {
         ModelType::Language => models::available_language_models()
             .into_iter()
             .map(ModelInfo::from)
             .collect(),
-        ModelType::Embedding => models::available_embedding_models()
-            .into_iter()
-            .map(ModelInfo::from)
-            .collect(),
+        ModelType::Embedding => {
+            let mut available = models::available_embedding_models();
+            let configured = Settings::load(&state.config.settings_file).embedding_model_id;
+            if let Some(custom) = configured
+                .filter(|id| id.starts_with(models::CUSTOM_EMBEDDING_PREFIX))
+                .and_then(|id| models::get_embedding_model(&id))
+            {
+                available.push(custom);
+            }
+            available.into_iter().map(ModelInfo::from).collect()
+        }
         ModelType::Ocr => models::available_ocr_models()
             .into_iter()
             .map(ModelInfo::from)
@@ -72,6 +102,71 @@ pub async fn get_available_models(model_type: ModelType) -> CommandResult<Vec<Mo
     })
 }
 
+/// Documents sampled per collection when recommending an embedding model.
+const LANGUAGE_SAMPLE_DOCS: usize = 20;
+
+/// Suggest a multilingual embedding model if most of a collection's
+/// documents aren't English and the configured model isn't multilingual.
+/// Returns `None` when the current model is a fine fit.
+#[tauri::command]
+pub async fn recommend_embedding_model(
+    collection_id: CollectionId,
+    state: State<'_, AppState>,
+) -> CommandResult<Option<ModelInfo>> {
+    let namespace_id = collection_id.namespace();
+    let mut samples = Vec::new();
+    {
+        let storage = state.storage.read().await;
+        let doc_ids = storage
+            .list_documents_with_text(namespace_id)
+            .await
+            .storage_err()?;
+        for doc_id in doc_ids.iter().take(LANGUAGE_SAMPLE_DOCS) {
+            if let Some(bytes) = storage
+                .get_document_text(namespace_id, doc_id)
+                .await
+                .storage_err()?
+            {
+                let text = String::from_utf8_lossy(&bytes);
+                samples.push(text.chars().take(4000).collect::<String>());
+            }
+        }
+    }
+
+    let current = state
+        .models
+        .embedding_model_id()
+        .await
+        .and_then(|id| models::get_embedding_model(&id));
+    let samples: Vec<&str> = samples.iter().map(|s| s.as_str()).collect();
+    Ok(models::recommend_embedding_model(current.as_ref(), &samples).map(ModelInfo::from))
+}
+
+/// Register a custom embedding model by HuggingFace repo id.
+///
+/// Checks the repo exists and has the files we load, then returns the
+/// model's ID. Pass that ID to `download_model` and `configure_model` as
+/// with any curated model. Dimensions are confirmed against the model's
+/// real output on first load.
+#[tauri::command]
+pub async fn add_custom_embedding_model(
+    repo_id: String,
+    dimensions: usize,
+    state: State<'_, AppState>,
+) -> CommandResult<ModelInfo> {
+    let model = models::custom_embedding_model(&repo_id, dimensions)
+        .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+
+    state
+        .model_downloader
+        .verify_remote_files(&model)
+        .await
+        .external_err()?;
+
+    tracing::info!(model_id = %model.id, repo = %model.hf_repo_id, "Added custom embedding model");
+    Ok(model.into())
+}
+
 /// Model download status
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "status")]
@@ -117,27 +212,10 @@ pub async fn get_model_status(
 pub async fn download_model(
     model_type: ModelType,
     model_id: String,
-    app: AppHandle,
     state: State<'_, AppState>,
 ) -> CommandResult<()> {
-    use crate::core::{ModelDownloadProgress, ModelStatus};
-
-    let (status_tx, mut status_rx) = tokio::sync::mpsc::channel::<ModelStatus>(10);
-    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::channel::<ModelDownloadProgress>(100);
-
-    let app_status = app.clone();
-    tokio::spawn(async move {
-        while let Some(status) = status_rx.recv().await {
-            let _ = app_status.emit("model-status-changed", &status);
-        }
-    });
-
-    let app_progress = app.clone();
-    tokio::spawn(async move {
-        while let Some(progress) = progress_rx.recv().await {
-            let _ = app_progress.emit("model-download-progress", &progress);
-        }
-    });
+    let status_tx = state.events.channel(CoreEvent::ModelStatus);
+    let progress_tx = state.events.channel(CoreEvent::ModelDownload);
 
     match model_type {
         ModelType::Language => {
@@ -156,6 +234,9 @@ pub async fn download_model(
         ModelType::Embedding => {
             let model = models::get_embedding_model(&model_id)
                 .ok_or(CommandError::model_not_found(&model_id))?;
+            if let Some(reason) = model.unavailable_reason() {
+                return Err(CommandError::invalid_input(reason));
+            }
             if state.model_downloader.is_downloaded(&model) {
                 tracing::info!("Model {} is already downloaded", model_id);
                 return Ok(());
@@ -187,12 +268,18 @@ pub async fn download_model(
 /// Snapshot of a provider's persistent state (unconfigured or ready).
 ///
 /// Transient states (downloading, loading, failed) are carried by
-/// `model-status-changed` events, not by this query.
+/// `model-status-changed` events. The embedding slot also reports its
+/// readiness and last load/health-check error here, so the UI can tell a
+/// broken embedder from one that simply hasn't loaded yet.
 #[derive(Debug, Clone, Serialize)]
 pub struct ProviderStatus {
     pub provider_type: Option<String>,
     pub model_id: Option<String>,
     pub ready: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readiness: Option<EmbeddingReadiness>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
 }
 
 #[tauri::command]
@@ -204,10 +291,13 @@ pub async fn get_provider_status(
         ModelType::Embedding => {
             let model_id = state.models.embedding_model_id().await;
             let ready = state.models.embedding_ready().await;
+            let health = state.models.embedding_health().await;
             ProviderStatus {
                 provider_type: model_id.as_ref().map(|_| "local".to_string()),
                 model_id,
                 ready,
+                readiness: Some(health.readiness),
+                last_error: health.last_error,
             }
         }
         ModelType::Language => {
@@ -217,6 +307,8 @@ pub async fn get_provider_status(
                 provider_type: config.as_ref().map(|c| c.provider_type().to_string()),
                 model_id: config.as_ref().map(|c| c.model_id().to_string()),
                 ready,
+                readiness: None,
+                last_error: None,
             }
         }
         ModelType::Ocr => {
@@ -226,6 +318,8 @@ pub async fn get_provider_status(
                 provider_type: model_id.as_ref().map(|_| "local".to_string()),
                 model_id,
                 ready,
+                readiness: None,
+                last_error: None,
             }
         }
     })
@@ -248,6 +342,277 @@ pub async fn get_current_model(
     })
 }
 
+/// Get the global chunking defaults used when embedding documents.
+#[tauri::command]
+pub async fn get_chunking_config(state: State<'_, AppState>) -> CommandResult<ChunkingConfig> {
+    Ok(state.pipeline.chunking_config().await)
+}
+
+/// Update the global chunking defaults. Only affects documents embedded
+/// afterwards; call `reembed_collection` to rechunk existing documents.
+#[tauri::command]
+pub async fn set_chunking_config(
+    config: ChunkingConfig,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use crate::core::Settings;
+
+    config
+        .validate()
+        .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+
+    state.pipeline.set_chunking_config(config.clone()).await;
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.chunking = config;
+    settings.save(&state.config.settings_file).storage_err()?;
+
+    Ok(())
+}
+
+/// Pipeline worker settings as persisted. Changes apply on next launch.
+#[tauri::command]
+pub async fn get_pipeline_config(state: State<'_, AppState>) -> CommandResult<PipelineConfig> {
+    use crate::core::Settings;
+
+    Ok(Settings::load(&state.config.settings_file).pipeline)
+}
+
+/// Persist pipeline worker settings. Worker pools are sized at startup,
+/// so the new values take effect after a restart.
+#[tauri::command]
+pub async fn set_pipeline_config(
+    config: PipelineConfig,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use crate::core::Settings;
+
+    if config.store_workers == 0
+        || config.extract_workers == 0
+        || config.embed_workers == 0
+        || config.embed_concurrency == 0
+    {
+        return Err(CommandError::invalid_input(
+            "store_workers, extract_workers, embed_workers and embed_concurrency must be at least 1",
+        ));
+    }
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.pipeline = config;
+    settings.save(&state.config.settings_file).storage_err()?;
+
+    Ok(())
+}
+
+/// Device placement for local models as persisted.
+#[tauri::command]
+pub async fn get_device_settings(state: State<'_, AppState>) -> CommandResult<DeviceSettings> {
+    use crate::core::Settings;
+
+    Ok(Settings::load(&state.config.settings_file).devices)
+}
+
+/// Persist device placement for local models. Takes effect the next time
+/// a model is configured or the app starts.
+#[tauri::command]
+pub async fn set_device_settings(
+    devices: DeviceSettings,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use crate::core::Settings;
+
+    for device in [&devices.chat, &devices.embedding] {
+        device
+            .validate()
+            .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+    }
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings
+        .local_runtime
+        .validate(&devices.chat)
+        .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+    settings.devices = devices;
+    settings.save(&state.config.settings_file).storage_err()?;
+
+    Ok(())
+}
+
+/// Context and attention settings for local chat models as persisted.
+#[tauri::command]
+pub async fn get_local_runtime(state: State<'_, AppState>) -> CommandResult<LocalRuntimeConfig> {
+    use crate::core::Settings;
+
+    Ok(Settings::load(&state.config.settings_file).local_runtime)
+}
+
+/// Persist context and attention settings for local chat models. Takes
+/// effect the next time a model is configured or the app starts.
+#[tauri::command]
+pub async fn set_local_runtime(
+    runtime: LocalRuntimeConfig,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use crate::core::Settings;
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    runtime
+        .validate(&settings.devices.chat)
+        .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+    settings.local_runtime = runtime;
+    settings.save(&state.config.settings_file).storage_err()?;
+
+    Ok(())
+}
+
+/// The draft model paired with local chat model `model_id`, if any.
+#[tauri::command]
+pub async fn get_draft_model(
+    model_id: String,
+    state: State<'_, AppState>,
+) -> CommandResult<Option<String>> {
+    use crate::core::Settings;
+
+    Ok(Settings::load(&state.config.settings_file)
+        .draft_models
+        .get(&model_id)
+        .cloned())
+}
+
+/// Pair local chat model `model_id` with a smaller draft model for
+/// speculative decoding, or unpair it with `None`. Reinstalls the chat
+/// model if it's the one configured, so the next answer uses the pairing.
+#[tauri::command]
+pub async fn set_draft_model(
+    model_id: String,
+    draft_model_id: Option<String>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use crate::core::{ProviderConfig, Settings};
+
+    let model =
+        models::get_language_model(&model_id).ok_or(CommandError::model_not_found(&model_id))?;
+    let mut settings = Settings::load(&state.config.settings_file);
+    match draft_model_id {
+        Some(draft_id) => {
+            let draft = models::get_language_model(&draft_id)
+                .ok_or(CommandError::model_not_found(&draft_id))?;
+            if !draft.can_draft_for(&model) {
+                return Err(CommandError::invalid_input(format!(
+                    "{} can't draft for {}: it needs the same vocabulary and a smaller size",
+                    draft.name, model.name
+                )));
+            }
+            if !state.model_downloader.is_downloaded(&draft) {
+                return Err(CommandError::model_not_downloaded(&draft_id));
+            }
+            settings.draft_models.insert(model_id.clone(), draft_id);
+        }
+        None => {
+            settings.draft_models.remove(&model_id);
+        }
+    }
+    settings.save(&state.config.settings_file).storage_err()?;
+
+    let Some(
+        config @ ProviderConfig::Local {
+            model_id: active, ..
+        },
+    ) = settings.provider.clone()
+    else {
+        return Ok(());
+    };
+    if active != model_id {
+        return Ok(());
+    }
+    let Some(path) = state.model_downloader.get_path(&model) else {
+        return Ok(());
+    };
+    let provider = state.local_chat_provider(&model, &path, &settings);
+    state
+        .models
+        .set_chat(Arc::new(provider), config)
+        .await
+        .internal_err()?;
+    Ok(())
+}
+
+/// The local model used for next-message predictions, if one is set.
+#[tauri::command]
+pub async fn get_prediction_model(state: State<'_, AppState>) -> CommandResult<Option<String>> {
+    use crate::core::Settings;
+
+    Ok(Settings::load(&state.config.settings_file).prediction_model_id)
+}
+
+/// Use a small downloaded local model for next-message predictions, kept
+/// loaded alongside the chat model. `None` goes back to predicting with
+/// the chat model.
+#[tauri::command]
+pub async fn configure_prediction_model(
+    model_id: Option<String>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use crate::core::Settings;
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    match model_id {
+        Some(ref id) => {
+            let model = models::get_language_model(id).ok_or(CommandError::model_not_found(id))?;
+            if !state.model_downloader.is_downloaded(&model) {
+                return Err(CommandError::model_not_downloaded(id));
+            }
+            state
+                .install_prediction_provider(id, &settings.devices.chat, &settings.local_runtime)
+                .await;
+        }
+        None => state.models.clear_prediction().await,
+    }
+    settings.prediction_model_id = model_id;
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(())
+}
+
+/// Free a model's memory now, keeping it configured. Returns false when
+/// it wasn't loaded or is busy answering.
+#[tauri::command]
+pub async fn unload_model(
+    model_type: ModelType,
+    state: State<'_, AppState>,
+) -> CommandResult<bool> {
+    Ok(state.models.unload(model_type).await)
+}
+
+/// Unload a model and load it again.
+#[tauri::command]
+pub async fn reload_model(model_type: ModelType, state: State<'_, AppState>) -> CommandResult<()> {
+    state.models.reload(model_type).await.internal_err()
+}
+
+/// Hit/miss counters for the chunk embedding cache.
+#[tauri::command]
+pub async fn get_embedding_cache_stats(
+    state: State<'_, AppState>,
+) -> CommandResult<EmbeddingCacheStats> {
+    Ok(state.pipeline.embedding_cache_stats())
+}
+
+/// Hit/miss counters for the cache of repeated summaries and suggestions.
+#[tauri::command]
+pub async fn get_completion_cache_stats(
+    state: State<'_, AppState>,
+) -> CommandResult<CompletionCacheStats> {
+    Ok(state.completion_cache.stats())
+}
+
+/// Forget cached summaries and suggestions, so the next ones are asked
+/// of the model afresh.
+#[tauri::command]
+pub async fn clear_completion_cache(state: State<'_, AppState>) -> CommandResult<()> {
+    state.completion_cache.clear();
+    Ok(())
+}
+
 /// Configure and load a model.
 ///
 /// Emits `model-status-changed` events around the slow load so the frontend
@@ -256,45 +621,37 @@ pub async fn get_current_model(
 pub async fn configure_model(
     model_type: ModelType,
     model_id: Option<String>,
-    app: AppHandle,
     state: State<'_, AppState>,
 ) -> CommandResult<()> {
     match model_type {
-        ModelType::Language => configure_language_model_impl(model_id, app, state).await,
-        ModelType::Embedding => configure_embedding_model_impl(model_id, app, state).await,
-        ModelType::Ocr => configure_ocr_model_impl(model_id, app, state).await,
+        ModelType::Language => configure_language_model_impl(model_id, state).await,
+        ModelType::Embedding => configure_embedding_model_impl(model_id, state).await,
+        ModelType::Ocr => configure_ocr_model_impl(model_id, state).await,
     }
 }
 
-fn emit_ready(app: &AppHandle, model_type: ModelType, id: &str) {
+fn emit_ready(events: &EventBus, model_type: ModelType, id: &str) {
     use crate::core::ModelStatus;
-    let _ = app.emit(
-        "model-status-changed",
-        &ModelStatus::Ready {
-            model_type,
-            model_id: id.to_string(),
-        },
-    );
+    events.publish(ModelStatus::Ready {
+        model_type,
+        model_id: id.to_string(),
+    });
 }
 
-fn emit_failed(app: &AppHandle, model_type: ModelType, id: &str, error: &str) {
+fn emit_failed(events: &EventBus, model_type: ModelType, id: &str, error: &str) {
     use crate::core::ModelStatus;
-    let _ = app.emit(
-        "model-status-changed",
-        &ModelStatus::Failed {
-            model_type,
-            model_id: id.to_string(),
-            error: error.to_string(),
-        },
-    );
+    events.publish(ModelStatus::Failed {
+        model_type,
+        model_id: id.to_string(),
+        error: error.to_string(),
+    });
 }
 
 async fn configure_language_model_impl(
     model_id: Option<String>,
-    app: AppHandle,
     state: State<'_, AppState>,
 ) -> CommandResult<()> {
-    use crate::core::{LocalChatProvider, ProviderConfig, Settings};
+    use crate::core::{ProviderConfig, Settings};
 
     if let Some(ref id) = model_id {
         let model = models::get_language_model(id).ok_or(CommandError::model_not_found(id))?;
@@ -310,10 +667,12 @@ async fn configure_language_model_impl(
             .get_path(&model)
             .ok_or(CommandError::model_not_downloaded(id))?;
 
-        let provider = LocalChatProvider::new(&model_path, &model);
+        let settings = Settings::load(&state.config.settings_file);
+        let provider = state.local_chat_provider(&model, &model_path, &settings);
 
         let provider_config = ProviderConfig::Local {
             model_id: id.clone(),
+            sampling: crate::commands::providers::current_sampling(&state).await,
         };
         if let Err(e) = state
             .models
@@ -321,7 +680,7 @@ async fn configure_language_model_impl(
             .await
         {
             let msg = format!("Failed to install provider: {}", e);
-            emit_failed(&app, ModelType::Language, id, &msg);
+            emit_failed(&state.events, ModelType::Language, id, &msg);
             return Err(CommandError::internal(msg));
         }
 
@@ -329,7 +688,7 @@ async fn configure_language_model_impl(
         settings.provider = Some(provider_config);
         settings.save(&state.config.settings_file).storage_err()?;
 
-        emit_ready(&app, ModelType::Language, id);
+        emit_ready(&state.events, ModelType::Language, id);
     } else {
         tracing::info!("Unloading chat provider");
         state.models.clear_chat().await;
@@ -344,13 +703,19 @@ async fn configure_language_model_impl(
 
 async fn configure_embedding_model_impl(
     model_id: Option<String>,
-    app: AppHandle,
     state: State<'_, AppState>,
 ) -> CommandResult<()> {
-    use crate::core::{LocalEmbeddingProvider, Settings};
+    use crate::core::{provider, Settings};
+
+    if model_id != state.models.embedding_model_id().await {
+        state.pipeline.clear_embedding_cache();
+    }
 
     if let Some(ref id) = model_id {
         let model = models::get_embedding_model(id).ok_or(CommandError::model_not_found(id))?;
+        if let Some(reason) = model.unavailable_reason() {
+            return Err(CommandError::invalid_input(reason));
+        }
 
         tracing::info!(
             "Configuring embedding model: {} ({})",
@@ -358,7 +723,12 @@ async fn configure_embedding_model_impl(
             model.hf_repo_id
         );
 
-        let provider = LocalEmbeddingProvider::new(id, &model.hf_repo_id, model.dimensions);
+        let device = Settings::load(&state.config.settings_file)
+            .devices
+            .embedding;
+        let provider =
+            provider::local::embedding_provider(id, &model, &state.model_downloader, &device)
+                .internal_err()?;
 
         {
             let index = &*state.search;
@@ -369,13 +739,9 @@ async fn configure_embedding_model_impl(
                 })?;
         }
 
-        if let Err(e) = state
-            .models
-            .set_embedding(Arc::new(provider), id.clone())
-            .await
-        {
+        if let Err(e) = state.models.set_embedding(provider, id.clone()).await {
             let msg = format!("Failed to install embedder: {}", e);
-            emit_failed(&app, ModelType::Embedding, id, &msg);
+            emit_failed(&state.events, ModelType::Embedding, id, &msg);
             return Err(CommandError::internal(msg));
         }
 
@@ -383,7 +749,12 @@ async fn configure_embedding_model_impl(
         settings.embedding_model_id = Some(id.clone());
         settings.save(&state.config.settings_file).storage_err()?;
 
-        emit_ready(&app, ModelType::Embedding, id);
+        emit_ready(&state.events, ModelType::Embedding, id);
+
+        // Index documents already embedded with the newly active model and
+        // embed the ones that lack vectors for it.
+        state.pipeline.index_stored_embeddings().await;
+        state.pipeline.requeue_pending_embeddings().await;
     } else {
         tracing::info!("Disabling embedding model");
 
@@ -407,7 +778,6 @@ async fn configure_embedding_model_impl(
 
 async fn configure_ocr_model_impl(
     model_id: Option<String>,
-    app: AppHandle,
     state: State<'_, AppState>,
 ) -> CommandResult<()> {
     use crate::core::{LocalOcrProvider, Settings};
@@ -425,7 +795,7 @@ async fn configure_ocr_model_impl(
 
         if let Err(e) = state.models.set_ocr(Arc::new(provider), id.clone()).await {
             let msg = format!("Failed to install OCR provider: {}", e);
-            emit_failed(&app, ModelType::Ocr, id, &msg);
+            emit_failed(&state.events, ModelType::Ocr, id, &msg);
             return Err(CommandError::internal(msg));
         }
 
@@ -433,7 +803,7 @@ async fn configure_ocr_model_impl(
         settings.ocr_model_id = Some(id.clone());
         settings.save(&state.config.settings_file).storage_err()?;
 
-        emit_ready(&app, ModelType::Ocr, id);
+        emit_ready(&state.events, ModelType::Ocr, id);
 
         // Drain any documents whose extract phase parked an `ocr_task`
         // entry while OCR was unconfigured. Phase 4 wires this method.
@@ -1,11 +1,18 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
+use crate::core::net;
+use crate::core::provider::verify;
+use crate::core::rate_limit;
+use crate::core::spend::{self, SpendSummary};
 use crate::core::{
     get_provider_families as core_get_provider_families, AnthropicChatProvider, AppState,
-    LifecycleConfig, OpenAIChatProvider, ProviderConfig, ProviderFamily, RemoteModelInfo,
+    ChatProvider, GroqChatProvider, LifecycleConfig, MistralChatProvider, OpenAIChatProvider,
+    OpenAICompatibleChatProvider, OpenRouterChatProvider, ProviderCheck, ProviderConfig,
+    ProviderFamily, ProxyConfig, RemoteModelInfo, SamplingParams,
 };
 use crate::error::{CommandError, CommandResult, ResultExt};
 
@@ -23,6 +30,28 @@ pub async fn get_current_provider(
     Ok(state.models.chat_config().await)
 }
 
+/// Check the configured chat provider end to end: a minimal request and a
+/// tool call, with the latency and what the model can do
+#[tauri::command]
+pub async fn verify_provider(state: State<'_, AppState>) -> CommandResult<ProviderCheck> {
+    let lease = state
+        .models
+        .acquire_chat()
+        .await
+        .internal_err()?
+        .ok_or_else(|| CommandError::invalid_input("No chat model is configured"))?;
+    let check = verify::verify(lease.provider()).await;
+    tracing::info!(
+        provider = %check.provider,
+        model = %check.model,
+        reachable = check.reachable,
+        latency_ms = ?check.latency_ms,
+        tool_calls = check.tool_calls,
+        "Checked chat provider"
+    );
+    Ok(check)
+}
+
 /// Fetch available models from OpenAI API
 #[tauri::command]
 pub async fn fetch_openai_models(api_key: String) -> CommandResult<Vec<RemoteModelInfo>> {
@@ -39,39 +68,92 @@ pub async fn fetch_anthropic_models(api_key: String) -> CommandResult<Vec<Remote
         .external_err()
 }
 
-/// Configure OpenAI as the chat provider
+/// Fetch the OpenRouter models that can call tools (verifies API key)
 #[tauri::command]
-pub async fn configure_openai_provider(
-    api_key: String,
-    model: String,
-    state: State<'_, AppState>,
-) -> CommandResult<()> {
-    use crate::core::Settings;
+pub async fn fetch_openrouter_models(api_key: String) -> CommandResult<Vec<RemoteModelInfo>> {
+    OpenRouterChatProvider::fetch_models(&api_key)
+        .await
+        .external_err()
+}
 
-    tracing::info!("Configuring OpenAI provider with model: {}", model);
+/// Fetch the Mistral models that can call tools (verifies API key)
+#[tauri::command]
+pub async fn fetch_mistral_models(api_key: String) -> CommandResult<Vec<RemoteModelInfo>> {
+    MistralChatProvider::fetch_models(&api_key)
+        .await
+        .external_err()
+}
 
-    let provider = OpenAIChatProvider::new(&api_key, &model);
-    let config = ProviderConfig::OpenAI {
-        api_key: api_key.clone(),
-        model: model.clone(),
-    };
+/// Fetch the active Groq chat models (verifies API key)
+#[tauri::command]
+pub async fn fetch_groq_models(api_key: String) -> CommandResult<Vec<RemoteModelInfo>> {
+    GroqChatProvider::fetch_models(&api_key)
+        .await
+        .external_err()
+}
 
+/// Fetch the models an OpenAI-compatible endpoint serves, for endpoints
+/// that list them
+#[tauri::command]
+pub async fn fetch_openai_compatible_models(
+    base_url: String,
+    api_key: Option<String>,
+) -> CommandResult<Vec<RemoteModelInfo>> {
+    OpenAICompatibleChatProvider::fetch_models(&base_url, api_key.as_deref().unwrap_or_default())
+        .await
+        .external_err()
+}
+
+/// Install `provider` as the chat provider and persist its config, with
+/// the API key stored under the provider family's name so switching back
+/// later fills it in.
+async fn install_provider(
+    state: &AppState,
+    provider: Arc<dyn ChatProvider>,
+    config: ProviderConfig,
+    api_key: &str,
+) -> CommandResult<()> {
+    use crate::core::Settings;
+
+    let key_name = config.provider_type();
     state
         .models
-        .set_chat(Arc::new(provider), config.clone())
+        .set_chat(provider, config.clone())
         .await
         .map_err(|e| CommandError::internal(format!("Failed to install provider: {}", e)))?;
 
-    // Persist setting and store API key separately for easy switching
     let mut settings = Settings::load(&state.config.settings_file);
+    if let ProviderConfig::OpenAICompatible { ref base_url, .. } = config {
+        settings.openai_compatible_base_url = Some(base_url.clone());
+    }
     settings.provider = Some(config);
-    settings.openai_api_key = Some(api_key);
+    settings
+        .set_api_key(&state.secrets, key_name, api_key)
+        .storage_err()?;
     settings.save(&state.config.settings_file).storage_err()?;
 
-    tracing::info!("OpenAI provider configured successfully");
+    tracing::info!("{} provider configured successfully", key_name);
     Ok(())
 }
 
+/// Configure OpenAI as the chat provider
+#[tauri::command]
+pub async fn configure_openai_provider(
+    api_key: String,
+    model: String,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    tracing::info!("Configuring OpenAI provider with model: {}", model);
+
+    let provider = OpenAIChatProvider::new(&api_key, &model);
+    let config = ProviderConfig::OpenAI {
+        api_key: api_key.clone(),
+        model,
+        sampling: current_sampling(&state).await,
+    };
+    install_provider(&state, Arc::new(provider), config, &api_key).await
+}
+
 /// Configure Anthropic as the chat provider
 #[tauri::command]
 pub async fn configure_anthropic_provider(
@@ -79,29 +161,112 @@ pub async fn configure_anthropic_provider(
     model: String,
     state: State<'_, AppState>,
 ) -> CommandResult<()> {
-    use crate::core::Settings;
-
     tracing::info!("Configuring Anthropic provider with model: {}", model);
 
     let provider = AnthropicChatProvider::new(&api_key, &model);
     let config = ProviderConfig::Anthropic {
         api_key: api_key.clone(),
-        model: model.clone(),
+        model,
+        sampling: current_sampling(&state).await,
     };
+    install_provider(&state, Arc::new(provider), config, &api_key).await
+}
 
-    state
-        .models
-        .set_chat(Arc::new(provider), config.clone())
-        .await
-        .map_err(|e| CommandError::internal(format!("Failed to install provider: {}", e)))?;
+/// Configure OpenRouter as the chat provider. `context_window` is the one
+/// the model listing reported, if any.
+#[tauri::command]
+pub async fn configure_openrouter_provider(
+    api_key: String,
+    model: String,
+    context_window: Option<usize>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    tracing::info!("Configuring OpenRouter provider with model: {}", model);
 
-    let mut settings = Settings::load(&state.config.settings_file);
-    settings.provider = Some(config);
-    settings.anthropic_api_key = Some(api_key);
-    settings.save(&state.config.settings_file).storage_err()?;
+    let provider = OpenRouterChatProvider::new(&api_key, &model, context_window);
+    let config = ProviderConfig::OpenRouter {
+        api_key: api_key.clone(),
+        model,
+        context_window,
+        sampling: current_sampling(&state).await,
+    };
+    install_provider(&state, Arc::new(provider), config, &api_key).await
+}
 
-    tracing::info!("Anthropic provider configured successfully");
-    Ok(())
+/// Configure Mistral as the chat provider. `context_window` is the one
+/// the model listing reported, if any.
+#[tauri::command]
+pub async fn configure_mistral_provider(
+    api_key: String,
+    model: String,
+    context_window: Option<usize>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    tracing::info!("Configuring Mistral provider with model: {}", model);
+
+    let provider = MistralChatProvider::new(&api_key, &model, context_window);
+    let config = ProviderConfig::Mistral {
+        api_key: api_key.clone(),
+        model,
+        context_window,
+        sampling: current_sampling(&state).await,
+    };
+    install_provider(&state, Arc::new(provider), config, &api_key).await
+}
+
+/// Configure Groq as the chat provider. `context_window` is the one
+/// the model listing reported, if any.
+#[tauri::command]
+pub async fn configure_groq_provider(
+    api_key: String,
+    model: String,
+    context_window: Option<usize>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    tracing::info!("Configuring Groq provider with model: {}", model);
+
+    let provider = GroqChatProvider::new(&api_key, &model, context_window);
+    let config = ProviderConfig::Groq {
+        api_key: api_key.clone(),
+        model,
+        context_window,
+        sampling: current_sampling(&state).await,
+    };
+    install_provider(&state, Arc::new(provider), config, &api_key).await
+}
+
+/// Configure an OpenAI-compatible endpoint as the chat provider
+#[tauri::command]
+pub async fn configure_openai_compatible_provider(
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    context_window: Option<usize>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    let base_url = base_url.trim().trim_end_matches('/').to_string();
+    if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+        return Err(CommandError::invalid_input(
+            "Endpoint URL must start with http:// or https://",
+        ));
+    }
+    let api_key = api_key.unwrap_or_default();
+
+    tracing::info!(
+        "Configuring OpenAI-compatible provider with model {} at {}",
+        model,
+        base_url
+    );
+
+    let provider = OpenAICompatibleChatProvider::new(&base_url, &api_key, &model, context_window);
+    let config = ProviderConfig::OpenAICompatible {
+        base_url,
+        api_key: api_key.clone(),
+        model,
+        context_window,
+        sampling: current_sampling(&state).await,
+    };
+    install_provider(&state, Arc::new(provider), config, &api_key).await
 }
 
 /// Get stored API keys (for auto-populating when switching providers)
@@ -110,9 +275,15 @@ pub async fn get_stored_api_keys(state: State<'_, AppState>) -> CommandResult<St
     use crate::core::Settings;
 
     let settings = Settings::load(&state.config.settings_file);
+    let key = |provider| settings.api_key(&state.secrets, provider);
     Ok(StoredApiKeys {
-        openai: settings.openai_api_key,
-        anthropic: settings.anthropic_api_key,
+        openai: key("openai"),
+        anthropic: key("anthropic"),
+        openrouter: key("openrouter"),
+        mistral: key("mistral"),
+        groq: key("groq"),
+        openai_compatible: key("openai_compatible"),
+        openai_compatible_base_url: settings.openai_compatible_base_url.clone(),
     })
 }
 
@@ -120,6 +291,145 @@ pub async fn get_stored_api_keys(state: State<'_, AppState>) -> CommandResult<St
 pub struct StoredApiKeys {
     pub openai: Option<String>,
     pub anthropic: Option<String>,
+    pub openrouter: Option<String>,
+    pub mistral: Option<String>,
+    pub groq: Option<String>,
+    pub openai_compatible: Option<String>,
+    /// Endpoint the `openai_compatible` key belongs to.
+    pub openai_compatible_base_url: Option<String>,
+}
+
+/// Default sampling of the current provider, carried over when the user
+/// switches to another one.
+pub(crate) async fn current_sampling(state: &AppState) -> SamplingParams {
+    state
+        .models
+        .chat_config()
+        .await
+        .map(|config| config.sampling())
+        .unwrap_or_default()
+}
+
+/// Set the default temperature, top_p and reply length for chat turns.
+/// Conversations with their own settings keep them.
+#[tauri::command]
+pub async fn set_default_sampling(
+    sampling: SamplingParams,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use crate::core::Settings;
+
+    sampling
+        .validate()
+        .map_err(|e| CommandError::invalid_input(e.to_string()))?;
+    let config = state
+        .models
+        .set_chat_sampling(sampling)
+        .await
+        .ok_or_else(|| CommandError::invalid_input("No chat model is configured"))?;
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.provider = Some(config);
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(())
+}
+
+/// Estimated spend on remote models this month and overall, with the
+/// monthly budget.
+#[tauri::command]
+pub async fn get_spend_summary(state: State<'_, AppState>) -> CommandResult<SpendSummary> {
+    use crate::core::Settings;
+
+    let budget = Settings::load(&state.config.settings_file).monthly_budget_usd;
+    spend::spend_summary(&state.config.spend_file(), &spend::current_month(), budget).storage_err()
+}
+
+/// Set the monthly budget for remote models, or remove it with `None`.
+/// Takes effect from the next model call.
+#[tauri::command]
+pub async fn set_monthly_budget(
+    budget_usd: Option<f64>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use crate::core::Settings;
+
+    if budget_usd.is_some_and(|b| !b.is_finite() || b < 0.0) {
+        return Err(CommandError::invalid_input(
+            "Budget must be a positive amount",
+        ));
+    }
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.monthly_budget_usd = budget_usd;
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(())
+}
+
+/// Requests-per-minute limits on remote chat providers: the defaults and
+/// the ones the user set in their place.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimits {
+    pub defaults: BTreeMap<String, u32>,
+    /// 0 means the provider isn't limited.
+    pub overrides: BTreeMap<String, u32>,
+}
+
+/// The rate limits remote chat requests wait for.
+#[tauri::command]
+pub async fn get_rate_limits(state: State<'_, AppState>) -> CommandResult<RateLimits> {
+    use crate::core::Settings;
+
+    Ok(RateLimits {
+        defaults: rate_limit::DEFAULT_LIMITS
+            .iter()
+            .map(|(name, rpm)| (name.to_string(), *rpm))
+            .collect(),
+        overrides: Settings::load(&state.config.settings_file).rate_limits,
+    })
+}
+
+/// Limit `provider` to `rpm` requests a minute (0 = no limit), or go back
+/// to its default with `None`. Takes effect from the next request.
+#[tauri::command]
+pub async fn set_rate_limit(
+    provider: String,
+    rpm: Option<u32>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use crate::core::Settings;
+
+    let mut settings = Settings::load(&state.config.settings_file);
+    match rpm {
+        Some(rpm) => settings.rate_limits.insert(provider, rpm),
+        None => settings.rate_limits.remove(&provider),
+    };
+    settings.save(&state.config.settings_file).storage_err()?;
+    rate_limit::set_overrides(settings.rate_limits);
+    Ok(())
+}
+
+/// The proxy remote providers and downloads go through, if one is set.
+#[tauri::command]
+pub async fn get_proxy(state: State<'_, AppState>) -> CommandResult<Option<ProxyConfig>> {
+    use crate::core::Settings;
+
+    Ok(Settings::load(&state.config.settings_file).proxy)
+}
+
+/// Route remote providers and model downloads through `proxy`, or connect
+/// directly with `None`. Remote providers pick it up when next configured;
+/// downloads on the next start.
+#[tauri::command]
+pub async fn set_proxy(
+    proxy: Option<ProxyConfig>,
+    state: State<'_, AppState>,
+) -> CommandResult<()> {
+    use crate::core::Settings;
+
+    net::set_proxy(proxy.clone()).map_err(|e| CommandError::invalid_input(e.to_string()))?;
+    let mut settings = Settings::load(&state.config.settings_file);
+    settings.proxy = proxy;
+    settings.save(&state.config.settings_file).storage_err()?;
+    Ok(())
 }
 
 /// Get the current lifecycle config (coexist flags).
@@ -12,6 +12,7 @@ use serde::Serialize;
 pub enum CommandError {
     // Validation errors
     InvalidUtf8 { message: String },
+    InvalidInput { message: String },
 
     // Not found errors
     DocumentNotFound { message: String },
@@ -33,6 +34,12 @@ pub enum CommandError {
 }
 
 impl CommandError {
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::InvalidInput {
+            message: message.into(),
+        }
+    }
+
     pub fn document_not_found() -> Self {
         Self::DocumentNotFound {
             message: "Document not found".to_string(),
@@ -115,6 +122,7 @@ impl std::fmt::Display for CommandError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::InvalidUtf8 { message } => write!(f, "{}", message),
+            Self::InvalidInput { message } => write!(f, "{}", message),
             Self::DocumentNotFound { message } => write!(f, "{}", message),
             Self::TextNotFound { message } => write!(f, "{}", message),
             Self::CollectionNotFound { message } => write!(f, "{}", message),
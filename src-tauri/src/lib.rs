@@ -2,9 +2,11 @@ pub mod commands;
 pub mod core;
 pub mod error;
 
-use tauri::{Manager, RunEvent};
+use anyhow::Context;
+use tauri::{AppHandle, Emitter, Manager, RunEvent};
+use tokio_util::sync::CancellationToken;
 
-use crate::core::{AppState, Config, ModelDownloadProgress, ModelStatus};
+use crate::core::{server, AppState, Config, CoreEvent, Settings};
 
 /// Initialize tracing/logging with the given directives
 pub fn init_logging(directives: &[&str]) {
@@ -15,81 +17,125 @@ pub fn init_logging(directives: &[&str]) {
     tracing_subscriber::fmt().with_env_filter(filter).init();
 }
 
+/// Export the proxy from settings to the environment, for the model
+/// downloader; see [`crate::core::net::export_proxy_env`]. Called first
+/// thing, while this is the only thread. A proxy that can't be used is
+/// left out, and downloads then fail on `net::check_proxy`.
+fn export_proxy_env(config: &Config) {
+    let Some(proxy) = Settings::load(&config.settings_file).proxy else {
+        return;
+    };
+    if let Err(e) = crate::core::net::export_proxy_env(&proxy) {
+        tracing::error!(error = %e, "Proxy settings can't be used");
+    }
+}
+
+/// Emit a core event to the frontend under the name it listens for.
+fn emit(app: &AppHandle, event: CoreEvent) {
+    let result = match event {
+        CoreEvent::PipelineProgress(progress) => app.emit("pipeline-progress", progress),
+        CoreEvent::EmbeddingProgress(progress) => app.emit("embedding-progress", progress),
+        CoreEvent::FileProgress(progress) => app.emit("file-progress", progress),
+        CoreEvent::Throughput(stats) => app.emit("pipeline-throughput", stats),
+        CoreEvent::SyncedDocument(document) => app.emit("document-synced", document),
+        CoreEvent::TransferStats(stats) => app.emit("transfer-stats", stats),
+        CoreEvent::Maintenance(run) => app.emit("maintenance-run", run),
+        CoreEvent::ChatMessage(message) => app.emit("chat-message", message),
+        CoreEvent::DocumentOffered(offer) => app.emit("document-offered", offer),
+        CoreEvent::ModelStatus(status) => app.emit("model-status-changed", status),
+        CoreEvent::ModelDownload(progress) => app.emit("model-download-progress", progress),
+        CoreEvent::Agent {
+            conversation_id,
+            event,
+        } => app.emit(&format!("agent-event-{}", conversation_id), event),
+    };
+    if let Err(e) = result {
+        tracing::warn!("Failed to emit event: {}", e);
+    }
+}
+
+/// Run without a window, serving the HTTP API (see
+/// [`crate::core::server`]) until interrupted. `address` overrides the one
+/// in settings.
+pub fn run_headless(address: Option<String>) -> anyhow::Result<()> {
+    init_logging(&["insight=info"]);
+    tracing::info!("Starting Insight headless");
+
+    let config = Config::load_or_default();
+    export_proxy_env(&config);
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        config.ensure_dirs()?;
+        let state = AppState::new(config).await?;
+
+        let address =
+            address.unwrap_or_else(|| Settings::load(&state.config.settings_file).server.address);
+        let address = address
+            .parse::<std::net::SocketAddr>()
+            .with_context(|| format!("Invalid address: {}", address))?;
+        let (token, created) = server::api_token(&state)?;
+        if created {
+            println!(
+                "API token (shown once, kept in the secret store): {}",
+                token
+            );
+        }
+
+        state.models.spawn_idle_reaper();
+        let restoring = state.clone();
+        tokio::spawn(async move { restoring.restore_configs_from_settings().await });
+
+        let cancel = CancellationToken::new();
+        let shutdown = cancel.clone();
+        tokio::spawn(async move {
+            let _ = tokio::signal::ctrl_c().await;
+            tracing::info!("Shutting down");
+            shutdown.cancel();
+        });
+        server::serve(state.clone(), address, token, cancel).await?;
+
+        let _ = tokio::time::timeout(std::time::Duration::from_secs(1), state.presence.shutdown())
+            .await;
+        let _ =
+            tokio::time::timeout(std::time::Duration::from_secs(3), state.models.shutdown()).await;
+        Ok(())
+    })
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     init_logging(&["insight=debug", "milli=debug"]);
     tracing::info!("Starting Insight");
 
+    let config = Config::load_or_default();
+    export_proxy_env(&config);
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
-        .setup(|app| {
-            // Load config
-            let config = Config::load_or_default();
+        .setup(move |app| {
             config.ensure_dirs()?;
 
             // Initialize state using Tauri's async runtime (fast, ~100ms)
-            let (state, mut pipeline_progress_rx) =
-                tauri::async_runtime::block_on(AppState::new(config))?;
+            let state = tauri::async_runtime::block_on(AppState::new(config))?;
+            let mut events = state.events.subscribe();
             app.manage(state);
 
-            // Forward pipeline progress events to frontend
-            let pipeline_handle = app.handle().clone();
+            // Forward everything the core reports to the frontend.
+            let events_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
-                use tauri::Emitter;
-                while let Some(progress) = pipeline_progress_rx.recv().await {
-                    let _ = pipeline_handle.emit("pipeline-progress", &progress);
-                }
-            });
-
-            // Subscribe the frontend to the manager's status broadcast so
-            // lazy-load transitions (loading → ready/failed on first use)
-            // surface as `model-status-changed` events.
-            let state = app.state::<AppState>();
-            let mut status_rx = state.models.subscribe_status();
-            let status_handle = app.handle().clone();
-            tauri::async_runtime::spawn(async move {
-                use tauri::Emitter;
-                while let Ok(status) = status_rx.recv().await {
-                    let _ = status_handle.emit("model-status-changed", &status);
+                while let Some(event) = events.recv().await {
+                    emit(&events_handle, event);
                 }
             });
 
             // Restore provider configs (no weights loaded yet) and start
             // the idle reaper. Both need a Tokio runtime, so we do them
             // from inside async_runtime::spawn.
-            let state_clone = state.inner().clone();
-            let app_handle = app.handle().clone();
-
+            let state = app.state::<AppState>().inner().clone();
             tauri::async_runtime::spawn(async move {
-                use tauri::Emitter;
-
-                state_clone.models.spawn_idle_reaper();
-
-                let (status_tx, mut status_rx) = tokio::sync::mpsc::channel::<ModelStatus>(10);
-                let (progress_tx, mut progress_rx) =
-                    tokio::sync::mpsc::channel::<ModelDownloadProgress>(100);
-
-                // Forward download-sourced status events (distinct from the
-                // manager's broadcast channel — this covers the one-shot
-                // "downloading" transition during initial setup).
-                let status_handle = app_handle.clone();
-                tokio::spawn(async move {
-                    while let Some(status) = status_rx.recv().await {
-                        let _ = status_handle.emit("model-status-changed", &status);
-                    }
-                });
-
-                let progress_handle = app_handle.clone();
-                tokio::spawn(async move {
-                    while let Some(progress) = progress_rx.recv().await {
-                        let _ = progress_handle.emit("model-download-progress", &progress);
-                    }
-                });
-
-                state_clone
-                    .restore_configs_from_settings(status_tx, progress_tx)
-                    .await;
+                state.models.spawn_idle_reaper();
+                state.restore_configs_from_settings().await;
             });
 
             Ok(())
@@ -100,37 +146,168 @@ pub fn run() {
             commands::collections::delete_collection,
             commands::collections::share_collection,
             commands::collections::import_collection,
+            commands::collections::get_collection_members,
+            commands::collections::get_sync_health,
+            commands::collections::get_transfer_stats,
+            commands::collections::get_network_diagnostics,
+            commands::collections::get_presence,
+            commands::collections::get_chat_history,
+            commands::collections::send_chat_message,
+            commands::collections::get_sync_policy,
+            commands::collections::set_sync_policy,
+            commands::collections::get_sync_enabled,
+            commands::collections::set_sync_enabled,
+            commands::collections::get_display_name,
+            commands::collections::set_display_name,
+            commands::collections::get_peer_access,
+            commands::collections::set_peer_access,
+            commands::collections::get_personal_sync,
+            commands::collections::enable_personal_sync,
+            commands::collections::join_personal_sync,
+            commands::collections::disable_personal_sync,
+            commands::collections::get_collection_chunking,
+            commands::collections::set_collection_chunking,
+            commands::collections::get_collection_projection,
+            commands::collections::set_collection_projection,
+            commands::collections::get_collection_memories,
+            commands::collections::add_collection_memory,
+            commands::collections::delete_collection_memory,
+            commands::collections::get_saved_searches,
+            commands::collections::set_saved_search_notify,
+            commands::collections::delete_saved_search,
+            commands::collections::check_saved_search_alerts,
+            commands::collections::get_watch_folders,
+            commands::collections::add_watch_folder,
+            commands::collections::remove_watch_folder,
             commands::documents::get_documents,
+            commands::documents::get_undownloaded_documents,
+            commands::documents::fetch_document,
+            commands::documents::send_document,
+            commands::documents::receive_document,
             commands::documents::get_document,
             commands::documents::get_document_text,
+            commands::documents::get_document_pages,
             commands::documents::get_document_chunks,
             commands::documents::start_import,
+            commands::documents::preview_import,
+            commands::documents::list_remote_sources,
+            commands::documents::save_remote_source,
+            commands::documents::delete_remote_source,
+            commands::documents::list_remote_objects,
+            commands::documents::import_remote,
+            commands::documents::get_mailbox_config,
+            commands::documents::set_mailbox_config,
+            commands::documents::list_feeds,
+            commands::documents::save_feed,
+            commands::documents::delete_feed,
+            commands::documents::list_hooks,
+            commands::documents::save_hook,
+            commands::documents::delete_hook,
+            commands::documents::test_hook,
+            commands::documents::import_directory,
             commands::documents::get_pipeline_progress,
+            commands::documents::get_import_queue,
+            commands::documents::list_import_reports,
+            commands::documents::get_import_report,
+            commands::documents::save_import_report,
+            commands::documents::pause_import,
+            commands::documents::resume_import,
+            commands::documents::set_import_priority,
+            commands::documents::cancel_import,
+            commands::documents::retry_failed_imports,
+            commands::documents::find_cross_collection_duplicates,
+            commands::documents::link_documents,
+            commands::documents::list_cross_collection_duplicates,
+            commands::documents::get_cross_collection_duplicates,
+            commands::documents::set_cross_collection_duplicates,
+            commands::documents::get_maintenance_config,
+            commands::documents::set_maintenance_config,
+            commands::documents::run_maintenance_task,
             commands::documents::get_collection_pipeline_progress,
+            commands::documents::get_pipeline_throughput,
+            commands::documents::reembed_collection,
+            commands::documents::cancel_reembed,
             commands::documents::delete_document,
+            commands::documents::batch_ask,
+            commands::documents::cancel_batch_ask,
+            commands::documents::open_source_file,
+            commands::documents::get_redaction_map,
+            commands::documents::export_redacted_document,
             // Conversation commands
             commands::conversations::list_conversations,
             commands::conversations::load_conversation,
+            commands::conversations::get_token_usage,
             commands::conversations::start_chat,
             commands::conversations::send_message,
+            commands::conversations::regenerate_response,
+            commands::conversations::edit_message,
+            commands::conversations::branch_conversation,
             commands::conversations::cancel_generation,
+            commands::conversations::respond_to_tool_approval,
+            commands::conversations::get_always_allowed_tools,
+            commands::conversations::revoke_always_allowed_tool,
+            commands::conversations::get_agent_limits,
+            commands::conversations::set_agent_limits,
+            commands::conversations::get_verify_answers,
+            commands::conversations::set_verify_answers,
+            commands::conversations::get_prompt_presets,
+            commands::conversations::save_prompt_preset,
+            commands::conversations::delete_prompt_preset,
+            commands::conversations::get_prompt_templates,
+            commands::conversations::open_prompts_folder,
             commands::conversations::set_conversation_collections,
+            commands::conversations::set_conversation_sampling,
             commands::conversations::delete_conversation,
             // Model commands (unified)
             commands::models::get_available_models,
+            commands::models::add_custom_embedding_model,
             commands::models::get_model_status,
             commands::models::get_provider_status,
             commands::models::download_model,
             commands::models::get_current_model,
             commands::models::configure_model,
+            commands::models::get_prediction_model,
+            commands::models::configure_prediction_model,
+            commands::models::unload_model,
+            commands::models::reload_model,
+            commands::models::get_chunking_config,
+            commands::models::set_chunking_config,
+            commands::models::get_embedding_cache_stats,
+            commands::models::get_completion_cache_stats,
+            commands::models::clear_completion_cache,
+            commands::models::get_pipeline_config,
+            commands::models::set_pipeline_config,
+            commands::models::get_device_settings,
+            commands::models::recommend_embedding_model,
+            commands::models::set_device_settings,
+            commands::models::get_local_runtime,
+            commands::models::set_local_runtime,
+            commands::models::get_draft_model,
+            commands::models::set_draft_model,
             // Provider management
             commands::providers::get_provider_families,
             commands::providers::get_current_provider,
             commands::providers::fetch_openai_models,
             commands::providers::fetch_anthropic_models,
+            commands::providers::fetch_openrouter_models,
+            commands::providers::fetch_mistral_models,
+            commands::providers::fetch_groq_models,
+            commands::providers::fetch_openai_compatible_models,
             commands::providers::configure_openai_provider,
             commands::providers::configure_anthropic_provider,
+            commands::providers::configure_openrouter_provider,
+            commands::providers::configure_mistral_provider,
+            commands::providers::configure_groq_provider,
+            commands::providers::configure_openai_compatible_provider,
             commands::providers::get_stored_api_keys,
+            commands::providers::verify_provider,
+            commands::providers::set_default_sampling,
+            commands::providers::get_spend_summary,
+            commands::providers::set_monthly_budget,
+            commands::providers::get_rate_limits,
+            commands::providers::set_rate_limit,
+            commands::providers::get_proxy,
+            commands::providers::set_proxy,
             commands::providers::get_lifecycle_config,
             commands::providers::set_lifecycle_config,
             commands::providers::research_focus_enter,
@@ -148,6 +325,12 @@ pub fn run() {
                 // the driver is deinitialized.
                 tauri::async_runtime::block_on(async {
                     let state = app_handle.state::<AppState>();
+                    // Tell peers this node is going offline.
+                    let _ = tokio::time::timeout(
+                        std::time::Duration::from_secs(1),
+                        state.presence.shutdown(),
+                    )
+                    .await;
                     let models = state.models.clone();
                     // Run in a separate task with a deadline so we don't
                     // block exit indefinitely.
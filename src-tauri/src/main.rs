@@ -2,5 +2,22 @@
 #![cfg_attr(all(not(debug_assertions)), windows_subsystem = "windows")]
 
 fn main() {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("serve") {
+        let args: Vec<String> = args.collect();
+        let address = match args.as_slice() {
+            [] => None,
+            [flag, address] if flag == "--address" => Some(address.clone()),
+            _ => {
+                eprintln!("Usage: insight serve [--address <host:port>]");
+                std::process::exit(2);
+            }
+        };
+        if let Err(e) = insight_lib::run_headless(address) {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     insight_lib::run();
 }
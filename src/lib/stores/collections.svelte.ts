@@ -21,6 +21,15 @@ export interface Document {
 	page_count: number;
 	tags: string[];
 	created_at: string;
+	source_url: string | null;
+	near_duplicates: NearDuplicate[];
+}
+
+/** Another document in the same collection with nearly identical text */
+export interface NearDuplicate {
+	doc_id: string;
+	/** Fraction of matching fingerprint bits, 0-1 */
+	similarity: number;
 }
 
 /** Per-stage progress counts */
@@ -29,6 +38,7 @@ export interface StageProgress {
 	active: number;
 	completed: number;
 	failed: number;
+	cancelled: number;
 }
 
 /** Per-document progress within a stage */
@@ -46,6 +56,7 @@ export interface PipelineProgress {
 	ocr: StageProgress;
 	embed: StageProgress;
 	index: StageProgress;
+	paused: boolean;
 	store_doc: DocProgress | null;
 	extract_doc: DocProgress | null;
 	ocr_doc: DocProgress | null;
@@ -53,6 +64,32 @@ export interface PipelineProgress {
 	index_doc: DocProgress | null;
 }
 
+/** Where one file is within its current stage */
+export interface FileProgress {
+	collection_id: string;
+	doc_id: string | null;
+	path: string | null;
+	stage: 'store' | 'extract' | 'ocr' | 'embed' | 'index';
+	current: number;
+	total: number;
+	percent: number;
+	error: string | null;
+}
+
+/** How far a document written by a peer has got here */
+export type SyncStatus =
+	| 'received'
+	| 'downloading'
+	| 'embedding_reused'
+	| 'embedding_regenerated'
+	| 'indexed';
+
+export interface SyncedDocument {
+	collection_id: string;
+	doc_id: string;
+	status: SyncStatus;
+}
+
 interface DocumentAddedEvent {
 	collection_id: string;
 	document: Document;
@@ -63,10 +100,19 @@ let collections = $state<Collection[]>([]);
 let loading = $state(false);
 let error = $state<string | null>(null);
 let pipelineProgress = $state<Record<string, PipelineProgress>>({});
+// Files in flight per collection, keyed by doc ID (or path until stored)
+let fileProgress = $state<Record<string, Record<string, FileProgress>>>({});
+// Documents from peers not yet indexed, per collection, keyed by doc ID
+let syncStatus = $state<Record<string, Record<string, SyncStatus>>>({});
+// Documents other nodes sent, not yet accepted or dismissed
+let documentOffers = $state<DocumentOffer[]>([]);
 
 // Track unlisten functions for cleanup
 let unlistenDocAdded: UnlistenFn | null = null;
 let unlistenPipelineProgress: UnlistenFn | null = null;
+let unlistenFileProgress: UnlistenFn | null = null;
+let unlistenDocSynced: UnlistenFn | null = null;
+let unlistenDocOffered: UnlistenFn | null = null;
 
 function updateCollectionDocCount(collectionId: string, delta: number) {
 	collections = collections.map((c) =>
@@ -98,6 +144,9 @@ function updatePipelineProgress(progress: PipelineProgress) {
 		const updated = { ...pipelineProgress };
 		delete updated[progress.collection_id];
 		pipelineProgress = updated;
+		const files = { ...fileProgress };
+		delete files[progress.collection_id];
+		fileProgress = files;
 
 		// Refresh collections to get updated document counts
 		loadCollections();
@@ -109,6 +158,33 @@ function updatePipelineProgress(progress: PipelineProgress) {
 	}
 }
 
+function updateFileProgress(progress: FileProgress) {
+	const files = { ...(fileProgress[progress.collection_id] ?? {}) };
+	// Once stored, a file is tracked by its document ID.
+	if (progress.path) delete files[progress.path];
+	const key = progress.doc_id ?? progress.path;
+	if (!key) return;
+	const finished =
+		progress.error !== null ||
+		(progress.stage === 'index' && progress.percent === 100);
+	if (finished) {
+		delete files[key];
+	} else {
+		files[key] = progress;
+	}
+	fileProgress = { ...fileProgress, [progress.collection_id]: files };
+}
+
+function updateSyncStatus({ collection_id, doc_id, status }: SyncedDocument) {
+	const docs = { ...(syncStatus[collection_id] ?? {}) };
+	if (status === 'indexed') {
+		delete docs[doc_id];
+	} else {
+		docs[doc_id] = status;
+	}
+	syncStatus = { ...syncStatus, [collection_id]: docs };
+}
+
 async function loadCollections() {
 	if (loading) return;
 	loading = true;
@@ -152,6 +228,27 @@ async function setupEventListeners() {
 			updatePipelineProgress(event.payload);
 		},
 	);
+
+	unlistenFileProgress = await listen<FileProgress>(
+		'file-progress',
+		(event) => {
+			updateFileProgress(event.payload);
+		},
+	);
+
+	unlistenDocSynced = await listen<SyncedDocument>(
+		'document-synced',
+		(event) => {
+			updateSyncStatus(event.payload);
+		},
+	);
+
+	unlistenDocOffered = await listen<DocumentOffer>(
+		'document-offered',
+		(event) => {
+			documentOffers = [...documentOffers, event.payload];
+		},
+	);
 }
 
 // Initialize on module load
@@ -169,6 +266,12 @@ export function cleanup() {
 	unlistenDocAdded = null;
 	unlistenPipelineProgress?.();
 	unlistenPipelineProgress = null;
+	unlistenFileProgress?.();
+	unlistenFileProgress = null;
+	unlistenDocSynced?.();
+	unlistenDocSynced = null;
+	unlistenDocOffered?.();
+	unlistenDocOffered = null;
 }
 
 // =============================================================================
@@ -271,15 +374,29 @@ export async function shareCollection(
 }
 
 /**
- * Import a collection from a share ticket.
- * Returns the imported collection or null on failure.
+ * What of a collection's documents is downloaded from peers: everything,
+ * metadata and text, or metadata only. The rest can be fetched per document.
+ */
+export type SyncPolicy = 'everything' | 'text' | 'metadata';
+
+export const syncPolicyLabels: Record<SyncPolicy, string> = {
+	everything: 'Everything',
+	text: 'Text only',
+	metadata: 'Titles only',
+};
+
+/**
+ * Import a collection from a share ticket, downloading what `syncPolicy`
+ * allows. Returns the imported collection or null on failure.
  */
 export async function importCollection(
 	ticket: string,
+	syncPolicy: SyncPolicy = 'everything',
 ): Promise<Collection | null> {
 	try {
 		const collection = await invoke<Collection>('import_collection', {
 			ticket: ticket.trim(),
+			syncPolicy,
 		});
 		collections = [...collections, collection];
 		return collection;
@@ -289,10 +406,297 @@ export async function importCollection(
 	}
 }
 
+/** Someone who writes to or replicates a collection */
+export interface CollectionMember {
+	author_id: string | null;
+	node_id: string | null;
+	name: string | null;
+	is_self: boolean;
+	entries: number;
+	last_write: string | null;
+	replicates: boolean;
+}
+
+/**
+ * Who has a collection: authors of its entries and the peers it has synced
+ * with, this node first.
+ */
+export async function getCollectionMembers(
+	collectionId: string,
+): Promise<CollectionMember[]> {
+	try {
+		return await invoke<CollectionMember[]>('get_collection_members', {
+			collectionId,
+		});
+	} catch (e) {
+		console.error('Failed to load collection members:', e);
+		return [];
+	}
+}
+
+/** What is downloaded of a collection */
+export async function getSyncPolicy(collectionId: string): Promise<SyncPolicy> {
+	try {
+		return await invoke<SyncPolicy>('get_sync_policy', { collectionId });
+	} catch (e) {
+		console.error('Failed to load sync policy:', e);
+		return 'everything';
+	}
+}
+
+/**
+ * Change what is downloaded of a collection. Content the new policy allows
+ * starts downloading.
+ */
+export async function setSyncPolicy(
+	collectionId: string,
+	policy: SyncPolicy,
+): Promise<boolean> {
+	try {
+		await invoke('set_sync_policy', { collectionId, policy });
+		return true;
+	} catch (e) {
+		console.error('Failed to set sync policy:', e);
+		return false;
+	}
+}
+
+/** Whether a collection is syncing with peers */
+export async function getSyncEnabled(collectionId: string): Promise<boolean> {
+	try {
+		return await invoke<boolean>('get_sync_enabled', { collectionId });
+	} catch (e) {
+		console.error('Failed to load sync state:', e);
+		return true;
+	}
+}
+
+/**
+ * Pause or resume syncing a collection with peers. Its documents and peers
+ * are kept while paused.
+ */
+export async function setSyncEnabled(
+	collectionId: string,
+	enabled: boolean,
+): Promise<boolean> {
+	try {
+		await invoke('set_sync_enabled', { collectionId, enabled });
+		return true;
+	} catch (e) {
+		console.error('Failed to change sync state:', e);
+		return false;
+	}
+}
+
+/** IDs of documents with content that hasn't been downloaded from peers */
+export async function getUndownloadedDocuments(
+	collectionId: string,
+): Promise<string[]> {
+	try {
+		return await invoke<string[]>('get_undownloaded_documents', {
+			collectionId,
+		});
+	} catch (e) {
+		console.error('Failed to load undownloaded documents:', e);
+		return [];
+	}
+}
+
+/**
+ * Download the rest of a document. Its progress is reported like any
+ * synced document's.
+ */
+export async function fetchDocument(
+	collectionId: string,
+	documentId: string,
+): Promise<void> {
+	try {
+		await invoke('fetch_document', { collectionId, documentId });
+	} catch (e) {
+		console.error('Failed to fetch document:', e);
+	}
+}
+
+/** A document another node sent this one */
+export interface DocumentOffer {
+	ticket: string;
+	name: string;
+	from: string;
+	from_name: string | null;
+	received_at: string;
+}
+
+/**
+ * Send a document, without the rest of its collection, to the node with
+ * ID `peer`. Returns the ticket it is downloaded with, or null if the
+ * peer couldn't be reached.
+ */
+export async function sendDocument(
+	collectionId: string,
+	documentId: string,
+	peer: string,
+): Promise<string | null> {
+	try {
+		return await invoke<string>('send_document', {
+			collectionId,
+			documentId,
+			peer,
+		});
+	} catch (e) {
+		console.error('Failed to send document:', e);
+		return null;
+	}
+}
+
+/** Documents sent to this node this run, waiting to be accepted */
+export function getDocumentOffers(): DocumentOffer[] {
+	return documentOffers;
+}
+
+/** Forget an offered document without downloading it */
+export function dismissDocumentOffer(ticket: string) {
+	documentOffers = documentOffers.filter((o) => o.ticket !== ticket);
+}
+
+/**
+ * Download an offered document into a collection. Returns false if it
+ * couldn't be, such as when the sender went offline.
+ */
+export async function receiveDocument(
+	collectionId: string,
+	ticket: string,
+): Promise<boolean> {
+	try {
+		await invoke<Document>('receive_document', { collectionId, ticket });
+		dismissDocumentOffer(ticket);
+		updateCollectionDocCount(collectionId, 1);
+		return true;
+	} catch (e) {
+		console.error('Failed to receive document:', e);
+		return false;
+	}
+}
+
+/** How syncing a collection with one peer is going */
+export interface PeerSyncHealth {
+	collection_id: string;
+	peer: string;
+	connected: boolean;
+	last_seen: string;
+	last_sync: string | null;
+	entries_received: number;
+	entries_sent: number;
+	pending: number;
+	last_error: string | null;
+	last_error_at: string | null;
+}
+
+/**
+ * Sync state of a collection with each peer seen this run, most recently
+ * seen first.
+ */
+export async function getSyncHealth(
+	collectionId: string,
+): Promise<PeerSyncHealth[]> {
+	try {
+		return await invoke<PeerSyncHealth[]>('get_sync_health', {
+			collectionId,
+		});
+	} catch (e) {
+		console.error('Failed to load sync health:', e);
+		return [];
+	}
+}
+
+/** Data a collection exchanged with peers since the app started */
+export interface CollectionTransfer {
+	collection_id: string;
+	bytes_downloaded: number;
+	bytes_uploaded: number;
+	entries_received: number;
+	entries_sent: number;
+	last_transfer: string | null;
+}
+
+/** What syncing a collection has cost in data this run */
+export async function getTransferStats(
+	collectionId: string,
+): Promise<CollectionTransfer | null> {
+	try {
+		return await invoke<CollectionTransfer>('get_transfer_stats', {
+			collectionId,
+		});
+	} catch (e) {
+		console.error('Failed to load transfer stats:', e);
+		return null;
+	}
+}
+
+/** A peer announcing itself in a collection */
+export interface PeerPresence {
+	node_id: string;
+	name: string | null;
+	online: boolean;
+	last_seen: string;
+}
+
+/** Peers seen in a collection this run, online first */
+export async function getPresence(
+	collectionId: string,
+): Promise<PeerPresence[]> {
+	try {
+		return await invoke<PeerPresence[]>('get_presence', { collectionId });
+	} catch (e) {
+		console.error('Failed to load presence:', e);
+		return [];
+	}
+}
+
+/** A message in a collection's chat */
+export interface ChatMessage {
+	id: string;
+	collection_id: string;
+	node_id: string;
+	name: string | null;
+	text: string;
+	sent_at: string;
+}
+
+/** The latest chat messages stored in a collection, oldest first */
+export async function getChatHistory(
+	collectionId: string,
+): Promise<ChatMessage[]> {
+	try {
+		return await invoke<ChatMessage[]>('get_chat_history', { collectionId });
+	} catch (e) {
+		console.error('Failed to load chat history:', e);
+		return [];
+	}
+}
+
+/** Send a chat message to a collection's members; null if it failed */
+export async function sendChatMessage(
+	collectionId: string,
+	text: string,
+): Promise<ChatMessage | null> {
+	try {
+		return await invoke<ChatMessage>('send_chat_message', {
+			collectionId,
+			text,
+		});
+	} catch (e) {
+		console.error('Failed to send chat message:', e);
+		return null;
+	}
+}
+
 // =============================================================================
 // Document imports and pipeline progress
 // =============================================================================
 
+/** Where a file goes in the import order; normal files go smallest first */
+export type ImportPriority = 'low' | 'normal' | 'high';
+
 /**
  * Start importing files into a collection.
  * Progress updates are tracked automatically via events.
@@ -300,11 +704,13 @@ export async function importCollection(
 export async function startImport(
 	collectionId: string,
 	paths: string[],
+	priorities?: Record<string, ImportPriority>,
 ): Promise<boolean> {
 	try {
 		const progress = await invoke<PipelineProgress>('start_import', {
 			paths,
 			collectionId,
+			priorities,
 		});
 		updatePipelineProgress(progress);
 		return true;
@@ -314,6 +720,262 @@ export async function startImport(
 	}
 }
 
+/** How a previewed file relates to documents already stored */
+export type DuplicateStatus =
+	| { kind: 'new' }
+	| { kind: 'exact'; document_id: string; document_name: string }
+	| { kind: 'other_collections'; collection_ids: string[] }
+	| {
+			kind: 'near';
+			document_id: string;
+			document_name: string;
+			similarity: number;
+	  };
+
+/** What importing one file would produce */
+export interface FilePreview {
+	path: string;
+	name: string;
+	size_bytes: number;
+	page_count: number;
+	ocr_pages: number;
+	characters: number;
+	language: string;
+	duplicate: DuplicateStatus;
+	estimated_chunks: number;
+	/** Null until something has been embedded to measure the rate */
+	estimated_embed_secs: number | null;
+	error: string | null;
+}
+
+/**
+ * Extract and check files without importing them, so a batch can be
+ * vetted first. Nothing is stored.
+ */
+export async function previewImport(
+	collectionId: string,
+	paths: string[],
+): Promise<FilePreview[]> {
+	try {
+		return await invoke<FilePreview[]>('preview_import', {
+			paths,
+			collectionId,
+		});
+	} catch (e) {
+		console.error('Failed to preview import:', e);
+		return [];
+	}
+}
+
+/**
+ * Import every PDF under a directory, recursively. `include` and `exclude`
+ * are globs such as `*.pdf` or `drafts/**`.
+ */
+export async function importDirectory(
+	collectionId: string,
+	path: string,
+	include: string[],
+	exclude: string[],
+): Promise<boolean> {
+	try {
+		await invoke('import_directory', { collectionId, path, include, exclude });
+		return true;
+	} catch (e) {
+		console.error('Failed to import directory:', e);
+		return false;
+	}
+}
+
+/** Where a remote source's objects live */
+export type RemoteEndpoint =
+	| { kind: 's3'; url: string; region: string; bucket: string }
+	| { kind: 'web_dav'; url: string };
+
+/** S3 or WebDAV storage configured in settings */
+export interface RemoteSource {
+	id: string;
+	name: string;
+	endpoint: RemoteEndpoint;
+	/** Set when credentials are stored; null for anonymous access */
+	credentials: { name: string; backend: string } | null;
+}
+
+/** An importable file in remote storage */
+export interface RemoteObject {
+	key: string;
+	size: number;
+	modified: string | null;
+}
+
+export async function listRemoteSources(): Promise<RemoteSource[]> {
+	try {
+		return await invoke<RemoteSource[]>('list_remote_sources');
+	} catch (e) {
+		console.error('Failed to load remote sources:', e);
+		return [];
+	}
+}
+
+/**
+ * PDFs and text files in a remote source under `prefix`. Throws so the
+ * caller can show why listing failed (bad credentials, unreachable host).
+ */
+export async function listRemoteObjects(
+	sourceId: string,
+	prefix: string,
+): Promise<RemoteObject[]> {
+	return await invoke<RemoteObject[]>('list_remote_objects', {
+		sourceId,
+		prefix,
+	});
+}
+
+/**
+ * Import objects from a remote source. Each is fetched when its turn in
+ * the import queue comes.
+ */
+export async function importRemote(
+	collectionId: string,
+	sourceId: string,
+	keys: string[],
+): Promise<boolean> {
+	try {
+		await invoke('import_remote', { collectionId, sourceId, keys });
+		return true;
+	} catch (e) {
+		console.error('Failed to import from remote storage:', e);
+		return false;
+	}
+}
+
+/** A finished import; the full report also lists each file */
+export interface ImportSummary {
+	id: string;
+	collection_id: string;
+	started_at: string;
+	finished_at: string;
+	duration_ms: number;
+	stored: number;
+	failed: number;
+	cancelled: number;
+}
+
+export async function listImportReports(
+	collectionId: string,
+): Promise<ImportSummary[]> {
+	try {
+		return await invoke<ImportSummary[]>('list_import_reports', {
+			collectionId,
+		});
+	} catch (e) {
+		console.error('Failed to load import reports:', e);
+		return [];
+	}
+}
+
+/** Save a copy of an import report as JSON at `path` */
+export async function saveImportReport(
+	id: string,
+	path: string,
+): Promise<boolean> {
+	try {
+		await invoke('save_import_report', { id, path });
+		return true;
+	} catch (e) {
+		console.error('Failed to save import report:', e);
+		return false;
+	}
+}
+
+/** A file about to be imported that's already in another collection */
+export interface CrossCollectionMatch {
+	path: string;
+	collection_id: string;
+	collection_name: string;
+	document_id: string;
+	document_name: string;
+}
+
+/**
+ * Files among `paths` already in other collections. Empty unless the check
+ * is turned on in settings.
+ */
+export async function findCrossCollectionDuplicates(
+	collectionId: string,
+	paths: string[],
+): Promise<CrossCollectionMatch[]> {
+	try {
+		return await invoke<CrossCollectionMatch[]>(
+			'find_cross_collection_duplicates',
+			{ collectionId, paths },
+		);
+	} catch (e) {
+		console.error('Failed to check for duplicates:', e);
+		return [];
+	}
+}
+
+/**
+ * Link documents from other collections into this one instead of
+ * importing copies.
+ */
+export async function linkDocuments(
+	collectionId: string,
+	matches: CrossCollectionMatch[],
+): Promise<Document[]> {
+	try {
+		return await invoke<Document[]>('link_documents', {
+			collectionId,
+			documents: matches.map((m) => ({
+				collection_id: m.collection_id,
+				document_id: m.document_id,
+			})),
+		});
+	} catch (e) {
+		console.error('Failed to link documents:', e);
+		return [];
+	}
+}
+
+/**
+ * Pause or resume imports into a collection.
+ */
+export async function setImportPaused(
+	collectionId: string,
+	paused: boolean,
+): Promise<void> {
+	try {
+		await invoke(paused ? 'pause_import' : 'resume_import', { collectionId });
+	} catch (e) {
+		console.error('Failed to change import state:', e);
+	}
+}
+
+/**
+ * Cancel imports into a collection. Documents not yet indexed are removed.
+ */
+export async function cancelImport(collectionId: string): Promise<number> {
+	try {
+		return await invoke<number>('cancel_import', { collectionId });
+	} catch (e) {
+		console.error('Failed to cancel import:', e);
+		return 0;
+	}
+}
+
+/**
+ * Retry imports into a collection that failed for good, e.g. once an
+ * embedder is configured. Returns how many documents and files were retried.
+ */
+export async function retryFailedImports(collectionId: string): Promise<number> {
+	try {
+		return await invoke<number>('retry_failed_imports', { collectionId });
+	} catch (e) {
+		console.error('Failed to retry imports:', e);
+		return 0;
+	}
+}
+
 /**
  * Get pipeline progress for a specific collection.
  */
@@ -370,3 +1032,21 @@ export function getActiveDocSummary(collectionId: string): string | null {
 	}
 	return null;
 }
+
+/**
+ * Files currently moving through the pipeline for a collection, with the
+ * stage each is in and how far along it is.
+ */
+export function getFileProgress(collectionId: string): FileProgress[] {
+	return Object.values(fileProgress[collectionId] ?? {});
+}
+
+/**
+ * Documents from peers that aren't searchable here yet, with how far each
+ * has got, keyed by document ID.
+ */
+export function getSyncStatus(
+	collectionId: string,
+): Record<string, SyncStatus> {
+	return syncStatus[collectionId] ?? {};
+}
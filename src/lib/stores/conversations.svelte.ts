@@ -13,12 +13,43 @@ import type { Collection } from './collections.svelte';
 
 export type ContentBlock =
 	| { type: 'text'; text: string }
+	| {
+			type: 'thinking';
+			thinking: string;
+			signature?: string;
+			redacted?: string[];
+		}
 	| { type: 'tool_use'; id: string; name: string; arguments: object }
 	| {
 			type: 'tool_result';
 			tool_use_id: string;
 			content: string;
 			is_error: boolean;
+	  }
+	| {
+			type: 'citation';
+			collection_id: string;
+			document_id: string;
+			document_name: string;
+			page: number | null;
+			quote: string;
+	  }
+	| {
+			type: 'source_file';
+			collection_id: string;
+			document_id: string;
+			document_name: string;
+			file_type: string;
+			path: string;
+	  }
+	| {
+			type: 'page_image';
+			collection_id: string;
+			document_id: string;
+			document_name: string;
+			page: number;
+			media_type: string;
+			data: string;
 	  };
 
 export type ChatMessageRole = 'user' | 'assistant' | 'context';
@@ -29,10 +60,19 @@ export interface ChatMessage {
 	block: ContentBlock;
 }
 
+/** Tokens a conversation used, with an estimated cost for remote models. */
+export interface ConversationUsage {
+	input_tokens: number;
+	output_tokens: number;
+	estimated_cost_usd: number;
+}
+
 export interface ConversationSummary {
 	id: string;
 	title: string;
 	updated_at: string;
+	parent_id?: string;
+	usage?: ConversationUsage;
 }
 
 interface BackendMessage {
@@ -47,14 +87,90 @@ interface Conversation {
 	created_at: string;
 	updated_at: string;
 	collections: Collection[];
+	preset_id?: string;
+	pinned_document_ids?: string[];
+	sampling?: SamplingParams;
+}
+
+/** Sampling settings; a missing field falls back to the next default. */
+export interface SamplingParams {
+	temperature?: number;
+	top_p?: number;
+	max_tokens?: number;
+	thinking_budget?: number;
+}
+
+/** A document pinned to the active conversation for discussion. */
+export interface PinnedDocument {
+	id: string;
+	name: string;
+}
+
+/** Reusable instructions layered on top of the base system prompt. */
+export interface PromptPreset {
+	id: string;
+	name: string;
+	instructions: string;
+}
+
+type ContentDelta =
+	| { type: 'text'; text: string }
+	| { type: 'thinking'; thinking: string };
+
+/** The user's answer to a tool call waiting for approval. */
+export type ToolApproval = 'deny' | 'allow' | 'always_allow';
+
+/** A tool call that changes data and is waiting for the user's approval. */
+export interface PendingConfirmation {
+	tool_use_id: string;
+	tool_name: string;
+	prompt: string;
+}
+
+export type PlanStatus = 'pending' | 'in_progress' | 'done' | 'skipped';
+
+/** One step of the plan the agent laid out for the current turn. */
+export interface PlanStep {
+	description: string;
+	status: PlanStatus;
+}
+
+/** A tool call the model is still writing. `arguments` is partial JSON. */
+export interface StreamingToolCall {
+	id: string;
+	name: string;
+	arguments: string;
+}
+
+/** How one cited claim of the last answer held up against its source. */
+export interface ClaimCheck {
+	claim: string;
+	collection_id: string;
+	document_id: string;
+	document_name: string;
+	page: number | null;
+	supported: boolean;
+	overlap: number;
 }
 
-type ContentDelta = { type: 'text'; text: string };
+/** A model request that failed for a passing reason and is sent again. */
+export interface RetryNotice {
+	attempt: number;
+	max_attempts: number;
+	delay_secs: number;
+	message: string;
+}
 
 type AgentEvent =
 	| { type: 'content_block_start'; data: { block: ContentBlock } }
 	| { type: 'content_block_delta'; data: { delta: ContentDelta } }
 	| { type: 'content_block_stop' }
+	| { type: 'tool_call_start'; data: { id: string; name: string } }
+	| { type: 'tool_call_delta'; data: { id: string; arguments_delta: string } }
+	| { type: 'tool_approval_required'; data: PendingConfirmation }
+	| { type: 'plan'; data: { steps: PlanStep[] } }
+	| { type: 'verification'; data: { claims: ClaimCheck[] } }
+	| { type: 'retrying'; data: RetryNotice }
 	| { type: 'done' }
 	| { type: 'error'; data: { message: string } };
 
@@ -69,7 +185,14 @@ let activeId = $state<string | null>(null);
 let activeMessages = $state<ChatMessage[]>([]);
 let activeCollections = $state<Collection[]>([]);
 let streamingBlocks = $state<ContentBlock[]>([]);
+let streamingToolCalls = $state<StreamingToolCall[]>([]);
 let isGenerating = $state(false);
+let pendingConfirmation = $state<PendingConfirmation | null>(null);
+let currentPlan = $state<PlanStep[]>([]);
+let verification = $state<ClaimCheck[]>([]);
+let retryNotice = $state<RetryNotice | null>(null);
+let pinnedDocuments = $state<PinnedDocument[]>([]);
+let activeSampling = $state<SamplingParams>({});
 let isLoading = $state(false);
 let listLoaded = $state(false);
 let initialized = $state(false);
@@ -108,6 +231,37 @@ function adoptConversation(conv: Conversation) {
 	activeMessages = flattenMessages(conv.messages);
 	activeCollections = conv.collections ?? [];
 	streamingBlocks = [];
+	streamingToolCalls = [];
+	currentPlan = [];
+	verification = [];
+	retryNotice = null;
+	pinnedDocuments = (conv.pinned_document_ids ?? []).map((id) => ({
+		id,
+		name: id,
+	}));
+	activeSampling = conv.sampling ?? {};
+	resolvePinnedNames(conv.id);
+}
+
+/** Replace the placeholder names of pinned documents with real ones. */
+async function resolvePinnedNames(convId: string) {
+	for (const pinned of pinnedDocuments) {
+		for (const collection of activeCollections) {
+			try {
+				const doc = await invoke<{ name: string }>('get_document', {
+					collectionId: collection.id,
+					documentId: pinned.id,
+				});
+				if (activeId !== convId) return;
+				pinnedDocuments = pinnedDocuments.map((d) =>
+					d.id === pinned.id ? { ...d, name: doc.name } : d,
+				);
+				break;
+			} catch {
+				// Not in this collection.
+			}
+		}
+	}
 }
 
 /** Drop the active conversation: detach listener and reset per-chat state. */
@@ -118,7 +272,14 @@ function clearActive() {
 	activeMessages = [];
 	activeCollections = [];
 	streamingBlocks = [];
+	streamingToolCalls = [];
 	isGenerating = false;
+	pendingConfirmation = null;
+	currentPlan = [];
+	verification = [];
+	retryNotice = null;
+	pinnedDocuments = [];
+	activeSampling = {};
 	persistActiveId();
 }
 
@@ -133,10 +294,19 @@ async function attachListener(convId: string) {
 function handleAgentEvent(event: { payload: AgentEvent }) {
 	const payload = event.payload;
 
+	// Any progress means the retried request went through.
+	if (payload.type !== 'retrying') retryNotice = null;
+
 	switch (payload.type) {
-		case 'content_block_start':
-			streamingBlocks = [...streamingBlocks, payload.data.block];
+		case 'content_block_start': {
+			const block = payload.data.block;
+			streamingBlocks = [...streamingBlocks, block];
+			// The finished call replaces its streamed preview.
+			if (block.type === 'tool_use') {
+				streamingToolCalls = streamingToolCalls.filter((c) => c.id !== block.id);
+			}
 			break;
+		}
 
 		case 'content_block_delta': {
 			const lastIdx = streamingBlocks.length - 1;
@@ -148,6 +318,11 @@ function handleAgentEvent(event: { payload: AgentEvent }) {
 						...streamingBlocks.slice(0, lastIdx),
 						{ type: 'text', text: block.text + delta.text },
 					];
+				} else if (delta.type === 'thinking' && block.type === 'thinking') {
+					streamingBlocks = [
+						...streamingBlocks.slice(0, lastIdx),
+						{ type: 'thinking', thinking: block.thinking + delta.thinking },
+					];
 				}
 			}
 			break;
@@ -156,6 +331,37 @@ function handleAgentEvent(event: { payload: AgentEvent }) {
 		case 'content_block_stop':
 			break;
 
+		case 'tool_call_start':
+			streamingToolCalls = [
+				...streamingToolCalls,
+				{ id: payload.data.id, name: payload.data.name, arguments: '' },
+			];
+			break;
+
+		case 'tool_call_delta': {
+			const { id, arguments_delta } = payload.data;
+			streamingToolCalls = streamingToolCalls.map((c) =>
+				c.id === id ? { ...c, arguments: c.arguments + arguments_delta } : c,
+			);
+			break;
+		}
+
+		case 'tool_approval_required':
+			pendingConfirmation = payload.data;
+			break;
+
+		case 'plan':
+			currentPlan = payload.data.steps;
+			break;
+
+		case 'verification':
+			verification = payload.data.claims;
+			break;
+
+		case 'retrying':
+			retryNotice = payload.data;
+			break;
+
 		case 'done': {
 			const newMessages: ChatMessage[] = streamingBlocks.map((block) => ({
 				role: 'assistant',
@@ -163,7 +369,10 @@ function handleAgentEvent(event: { payload: AgentEvent }) {
 			}));
 			activeMessages = [...activeMessages, ...newMessages];
 			streamingBlocks = [];
+			streamingToolCalls = [];
 			isGenerating = false;
+			pendingConfirmation = null;
+			currentPlan = [];
 			// Refresh list so titles/timestamps update in the sidebar.
 			refreshList();
 			break;
@@ -173,6 +382,9 @@ function handleAgentEvent(event: { payload: AgentEvent }) {
 			error = payload.data?.message || 'Unknown error';
 			console.error('Agent error:', payload.data?.message);
 			isGenerating = false;
+			pendingConfirmation = null;
+			streamingToolCalls = [];
+			currentPlan = [];
 			break;
 	}
 }
@@ -215,14 +427,16 @@ async function loadById(
 	}
 }
 
-async function createNew(): Promise<boolean> {
+async function createNew(presetId?: string): Promise<boolean> {
 	try {
 		isLoading = true;
 		error = null;
 
 		// Fresh conversations always start unscoped — the user picks a collection
 		// via the filter bar before they can chat.
-		const conv = await invoke<Conversation>('start_chat', {});
+		const conv = await invoke<Conversation>('start_chat', {
+			presetId: presetId ?? null,
+		});
 
 		adoptConversation(conv);
 
@@ -291,8 +505,8 @@ export async function selectConversation(id: string): Promise<void> {
 }
 
 /** Create a brand-new conversation (called by the "New Chat" button). */
-export async function newConversation(): Promise<void> {
-	await createNew();
+export async function newConversation(presetId?: string): Promise<void> {
+	await createNew(presetId);
 }
 
 /**
@@ -362,6 +576,21 @@ export async function removeActiveCollection(id: string): Promise<void> {
 	await setActiveCollections(activeCollections.filter((c) => c.id !== id));
 }
 
+/** Override the provider's sampling for the active conversation. */
+export async function setActiveSampling(sampling: SamplingParams): Promise<void> {
+	if (!activeId) return;
+	try {
+		const conv = await invoke<Conversation>('set_conversation_sampling', {
+			conversationId: activeId,
+			sampling,
+		});
+		activeSampling = conv.sampling ?? {};
+	} catch (e) {
+		error = `Failed to update sampling: ${e}`;
+		console.error('Failed to update sampling:', e);
+	}
+}
+
 /** Send a user message to the active conversation. */
 export async function sendMessage(text: string): Promise<void> {
 	const trimmed = text.trim();
@@ -374,11 +603,13 @@ export async function sendMessage(text: string): Promise<void> {
 	];
 	isGenerating = true;
 	streamingBlocks = [];
+	verification = [];
 
 	try {
 		await invoke('send_message', {
 			conversationId: activeId,
 			message: trimmed,
+			pinnedDocumentIds: pinnedDocuments.map((d) => d.id),
 		});
 	} catch (e) {
 		error = `Failed to send message: ${e}`;
@@ -387,6 +618,85 @@ export async function sendMessage(text: string): Promise<void> {
 	}
 }
 
+/** Discard the last response and generate it again. */
+export async function regenerateResponse(): Promise<void> {
+	if (!activeId || isGenerating) return;
+	const lastUser = activeMessages.findLastIndex((m) => m.role === 'user');
+	if (lastUser < 0) return;
+
+	error = null;
+	activeMessages = activeMessages.slice(0, lastUser + 1);
+	isGenerating = true;
+	streamingBlocks = [];
+	verification = [];
+
+	try {
+		await invoke('regenerate_response', { conversationId: activeId });
+	} catch (e) {
+		error = `Failed to regenerate response: ${e}`;
+		console.error('Failed to regenerate response:', e);
+		isGenerating = false;
+	}
+}
+
+/**
+ * Replace the text of user turn `turn` (0-based), drop everything after it
+ * and run the agent again.
+ */
+export async function editMessage(turn: number, text: string): Promise<void> {
+	const trimmed = text.trim();
+	if (!activeId || !trimmed || isGenerating) return;
+
+	let userCount = -1;
+	const cut = activeMessages.findIndex(
+		(m) => m.role === 'user' && ++userCount === turn,
+	);
+	if (cut < 0) return;
+
+	error = null;
+	activeMessages = [
+		...activeMessages.slice(0, cut),
+		{ role: 'user', block: { type: 'text', text: trimmed } },
+	];
+	isGenerating = true;
+	streamingBlocks = [];
+	verification = [];
+
+	try {
+		await invoke('edit_message', {
+			conversationId: activeId,
+			turn,
+			message: trimmed,
+		});
+	} catch (e) {
+		error = `Failed to resend message: ${e}`;
+		console.error('Failed to resend message:', e);
+		isGenerating = false;
+	}
+}
+
+/**
+ * Copy the active conversation through user turn `turn` (0-based) into a new
+ * conversation and switch to it.
+ */
+export async function branchConversation(turn: number): Promise<void> {
+	if (!activeId || isGenerating) return;
+	try {
+		error = null;
+		const conv = await invoke<Conversation>('branch_conversation', {
+			conversationId: activeId,
+			turn,
+		});
+		adoptConversation(conv);
+		await attachListener(conv.id);
+		persistActiveId();
+		await refreshList();
+	} catch (e) {
+		error = `Failed to branch conversation: ${e}`;
+		console.error('Failed to branch conversation:', e);
+	}
+}
+
 /** Cancel an in-flight generation. */
 export async function cancelGeneration(): Promise<void> {
 	if (!activeId) return;
@@ -394,6 +704,26 @@ export async function cancelGeneration(): Promise<void> {
 		await invoke('cancel_generation', { conversationId: activeId });
 	} finally {
 		isGenerating = false;
+		pendingConfirmation = null;
+	}
+}
+
+/**
+ * Answer the tool call the agent is waiting on. `always_allow` also stops
+ * future calls to the same tool from asking.
+ */
+export async function respondToConfirmation(approval: ToolApproval): Promise<void> {
+	const pending = pendingConfirmation;
+	if (!pending) return;
+	pendingConfirmation = null;
+	try {
+		await invoke('respond_to_tool_approval', {
+			toolUseId: pending.tool_use_id,
+			approval,
+		});
+	} catch (e) {
+		error = `Failed to answer confirmation: ${e}`;
+		console.error('Failed to answer confirmation:', e);
 	}
 }
 
@@ -449,10 +779,63 @@ export function getStreamingBlocks(): ContentBlock[] {
 	return streamingBlocks;
 }
 
+export function getStreamingToolCalls(): StreamingToolCall[] {
+	return streamingToolCalls;
+}
+
 export function getIsGenerating(): boolean {
 	return isGenerating;
 }
 
+export function getPendingConfirmation(): PendingConfirmation | null {
+	return pendingConfirmation;
+}
+
+export function getCurrentPlan(): PlanStep[] {
+	return currentPlan;
+}
+
+/** Open an original file the agent exported, in the system viewer. */
+export async function openSourceFile(path: string): Promise<void> {
+	try {
+		await invoke('open_source_file', { path });
+	} catch (e) {
+		error = `Failed to open file: ${e}`;
+		console.error('Failed to open source file:', e);
+	}
+}
+
+export function getActiveSampling(): SamplingParams {
+	return activeSampling;
+}
+
+export function getPinnedDocuments(): PinnedDocument[] {
+	return pinnedDocuments;
+}
+
+/**
+ * Pin a document to the active conversation. Takes effect with the next
+ * message.
+ */
+export function pinDocument(id: string, name: string) {
+	if (pinnedDocuments.some((d) => d.id === id)) return;
+	pinnedDocuments = [...pinnedDocuments, { id, name }];
+}
+
+export function unpinDocument(id: string) {
+	pinnedDocuments = pinnedDocuments.filter((d) => d.id !== id);
+}
+
+/** The model request being retried after a rate limit or outage, if any. */
+export function getRetryNotice(): RetryNotice | null {
+	return retryNotice;
+}
+
+/** Claim checks for the last answer; empty until verification reports. */
+export function getVerification(): ClaimCheck[] {
+	return verification;
+}
+
 export function getIsLoading(): boolean {
 	return isLoading;
 }
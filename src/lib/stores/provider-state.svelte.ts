@@ -41,6 +41,19 @@ interface ProviderStatusResponse {
 	provider_type: string | null;
 	model_id: string | null;
 	ready: boolean;
+	/** Embedding only. */
+	readiness?: 'unconfigured' | 'idle' | 'loading' | 'ready' | 'broken';
+	last_error?: string;
+}
+
+function statusFromResponse(res: ProviderStatusResponse): ProviderStatus {
+	if (res.readiness === 'broken') {
+		return { kind: 'error', message: res.last_error ?? 'Model failed to load' };
+	}
+	if (res.readiness === 'loading') {
+		return { kind: 'loading' };
+	}
+	return res.ready ? { kind: 'ready' } : { kind: 'unconfigured' };
 }
 
 interface ModelStatusEvent {
@@ -89,7 +102,7 @@ async function queryInitialStatus(type: ProviderType) {
 		const state = stateFor(type);
 		state.providerType = res.provider_type;
 		state.modelId = res.model_id;
-		state.status = res.ready ? { kind: 'ready' } : { kind: 'unconfigured' };
+		state.status = statusFromResponse(res);
 	} catch (e) {
 		console.error(`Failed to get ${type} provider status:`, e);
 	}
@@ -203,6 +216,14 @@ export function getProviderDisplayName(providerType: string | null): string {
 			return 'OpenAI';
 		case 'anthropic':
 			return 'Anthropic';
+		case 'openrouter':
+			return 'OpenRouter';
+		case 'mistral':
+			return 'Mistral';
+		case 'groq':
+			return 'Groq';
+		case 'openai_compatible':
+			return 'Custom Endpoint';
 		default:
 			return 'Not configured';
 	}